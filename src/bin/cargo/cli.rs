@@ -1,7 +1,10 @@
+use std::process;
+
 use clap;
 
 use clap::{AppSettings, Arg, ArgMatches};
 
+use cargo::util::profile;
 use cargo::{self, CliResult, Config};
 
 use super::commands;
@@ -31,7 +34,6 @@ Available unstable (nightly-only) flags:
     -Z avoid-dev-deps   -- Avoid installing dev-dependencies if possible
     -Z minimal-versions -- Install minimal dependency versions instead of maximum
     -Z no-index-update  -- Do not update the registry, avoids a network request for benchmarking
-    -Z offline          -- Offline mode that does not perform network requests
     -Z unstable-options -- Allow the usage of unstable options such as --registry
     -Z config-profile   -- Read profiles from .cargo/config files
 
@@ -74,9 +76,25 @@ Run with 'cargo -Z [FLAG] [SUBCOMMAND]'"
         return Ok(());
     }
 
-    let args = expand_aliases(config, args)?;
-
-    execute_subcommand(config, &args)
+    let self_profile = args.is_present("self-profile");
+    let result = {
+        let _p = profile::start(format!(
+            "cargo {}",
+            args.subcommand_name().unwrap_or_default()
+        ));
+        expand_aliases(config, args).and_then(|args| execute_subcommand(config, &args))
+    };
+    if self_profile {
+        let path = config
+            .cwd()
+            .join(format!("cargo-self-profile-{}.json", process::id()));
+        profile::finish_self_profile(&path)?;
+        config.shell().status(
+            "Profiled",
+            format!("cargo, the trace is in `{}`", path.display()),
+        )?;
+    }
+    result
 }
 
 pub fn get_version_string(is_verbose: bool) -> String {
@@ -153,6 +171,9 @@ fn execute_subcommand(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         &args.value_of("color").map(|s| s.to_string()),
         args.is_present("frozen"),
         args.is_present("locked"),
+        args.is_present("offline"),
+        args.is_present("unlocked"),
+        args.is_present("ephemeral"),
         arg_target_dir,
         &args
             .values_of_lossy("unstable-features")
@@ -160,7 +181,13 @@ fn execute_subcommand(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     )?;
 
     if let Some(exec) = commands::builtin_exec(cmd) {
-        return exec(config, subcommand_args);
+        let result = exec(config, subcommand_args);
+        if let Some(path) = config.ephemeral_lockfile() {
+            config
+                .shell()
+                .status("Ephemeral", format!("lock file written to {}", path.display()))?;
+        }
+        return result;
     }
 
     let mut ext_args: Vec<&str> = vec![cmd];
@@ -229,6 +256,22 @@ See 'cargo help <command>' for more information on a specific command.\n",
         )
         .arg(opt("frozen", "Require Cargo.lock and cache are up to date").global(true))
         .arg(opt("locked", "Require Cargo.lock is up to date").global(true))
+        .arg(opt("offline", "Run without accessing the network").global(true))
+        .arg(opt("unlocked", "Resolve dependencies afresh, ignoring Cargo.lock").global(true))
+        .arg(
+            opt(
+                "ephemeral",
+                "Write the lock file to a temporary location instead of Cargo.lock",
+            )
+            .global(true),
+        )
+        .arg(
+            opt(
+                "self-profile",
+                "Record where cargo spends its time in a trace for Chrome's about:tracing",
+            )
+            .global(true),
+        )
         .arg(
             Arg::with_name("unstable-features")
                 .help("Unstable (nightly-only) flags to Cargo, see 'cargo -Z help' for details")
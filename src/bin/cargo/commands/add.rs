@@ -0,0 +1,64 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, AddOptions};
+
+pub fn cli() -> App {
+    subcommand("add")
+        .about("Add dependencies on registry crates to the manifest")
+        .arg(
+            Arg::with_name("crate")
+                .value_name("CRATE[@VERSION]")
+                .multiple(true)
+                .required_unless("auto"),
+        )
+        .arg(opt(
+            "auto",
+            "Check the package and propose to add the crates it uses \
+             without depending on them",
+        ))
+        .arg(opt(
+            "yes",
+            "Add the crates found with `--auto` without asking for confirmation",
+        ))
+        .arg(opt("registry", "Registry to look the crates up in").value_name("REGISTRY"))
+        .arg_manifest_path()
+        .after_help(
+            "\
+Each crate is added to the `[dependencies]` of the manifest with its latest
+stable version in the registry, unless a version requirement is given, like
+`cargo add serde@1.0`. The manifest is edited in place, like with
+`cargo manifest set`.
+
+With `--auto` the package is checked, and the crates named by the paths of
+`use` declarations and `extern crate` items rustc couldn't resolve, which
+aren't dependencies or modules of the package already, are looked up in the
+registry, trying `foo-bar` for `foo_bar`. Each crate found is proposed with
+its latest version, and added once confirmed.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let registry = args.registry(config)?;
+    let mut check_flags = Vec::new();
+    for flag in &["frozen", "locked", "offline"] {
+        if args.is_present(flag) {
+            check_flags.push(format!("--{}", flag));
+        }
+    }
+    for flag in args.values_of("unstable-features").unwrap_or_default() {
+        check_flags.push("-Z".to_string());
+        check_flags.push(flag.to_string());
+    }
+    let opts = AddOptions {
+        config,
+        manifest_path: args.root_manifest(config)?,
+        crates: values(args, "crate"),
+        auto: args.is_present("auto"),
+        registry,
+        yes: args.is_present("yes"),
+        check_flags,
+    };
+    ops::add(&opts)?;
+    Ok(())
+}
@@ -29,6 +29,13 @@ pub fn cli() -> App {
             "Benchmark all targets",
         )
         .arg(opt("no-run", "Compile, but don't run benchmarks"))
+        .arg(
+            opt(
+                "run-manifest",
+                "With --no-run, print how to run each benchmark executable",
+            )
+            .requires("no-run"),
+        )
         .arg_package_spec(
             "Package to run benchmarks for",
             "Benchmark all packages in the workspace",
@@ -37,9 +44,11 @@ pub fn cli() -> App {
         .arg_jobs()
         .arg_features()
         .arg_target_triple("Build for the target triple")
+        .arg_target_cpu()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_deny_warnings()
         .arg(opt(
             "no-fail-fast",
             "Run all benchmarks regardless of failure",
@@ -73,11 +82,20 @@ Compilation can be customized with the `bench` profile in the manifest.
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let ws = args.workspace(config)?;
     let mut compile_opts = args.compile_options(config, CompileMode::Bench)?;
-    compile_opts.build_config.release = true;
+    compile_opts.build_config.profile_kind = ProfileKind::Release;
+
+    let run_manifest = args.is_present("run-manifest");
+    if run_manifest && !config.cli_unstable().unstable_options {
+        Err(format_err!(
+            "`--run-manifest` flag is unstable, pass `-Z unstable-options` to enable it"
+        ))?;
+    }
 
     let ops = TestOptions {
         no_run: args.is_present("no-run"),
         no_fail_fast: args.is_present("no-fail-fast"),
+        run_manifest,
+        shuffle: None,
         compile_opts,
     };
 
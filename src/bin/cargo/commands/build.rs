@@ -1,5 +1,6 @@
 use crate::command_prelude::*;
 
+use cargo::core::compiler::show_failed_commands;
 use cargo::ops;
 
 pub fn cli() -> App {
@@ -26,12 +27,53 @@ pub fn cli() -> App {
             "Build all targets",
         )
         .arg_release("Build artifacts in release mode, with optimizations")
+        .arg_profile("Build artifacts with the specified custom profile")
         .arg_features()
-        .arg_target_triple("Build for the target triple")
+        .arg_target_triples("Build for the target triple")
+        .arg_target_cpu()
         .arg_target_dir()
         .arg(opt("out-dir", "Copy final artifacts to this directory").value_name("PATH"))
+        .arg(
+            opt(
+                "dist",
+                "Copy the final artifacts, README and licenses of each package \
+                 to a directory of its own in this directory",
+            )
+            .value_name("PATH"),
+        )
         .arg_manifest_path()
         .arg_message_format()
+        .arg_deny_warnings()
+        .arg_dependency()
+        .arg_emit()
+        .arg_lipo()
+        .arg(multi_opt(
+            "crate-type",
+            "TYPE",
+            "Only build this crate type of the library, like `rlib`",
+        ))
+        .arg(
+            opt(
+                "from-archive",
+                "Build with the dependencies of this archive instead of their sources",
+            )
+            .value_name("PATH"),
+        )
+        .arg(
+            opt(
+                "repeat-failed",
+                "Only build the units which failed in the previous build",
+            )
+            .conflicts_with("dependency"),
+        )
+        .arg(opt(
+            "show-failed-command",
+            "Print the commands of the units which failed in the previous build",
+        ))
+        .arg(opt(
+            "assert-reproducible",
+            "Build twice and fail if the artifacts of the two builds differ",
+        ))
         .arg_build_plan()
         .after_help(
             "\
@@ -42,19 +84,73 @@ Note that `--exclude` has to be specified in conjunction with the `--all` flag.
 Compilation can be configured via the use of profiles which are configured in
 the manifest. The default profile for this command is `dev`, but passing
 the --release flag will use the `release` profile instead.
+
+The `--emit` flag takes a comma separated list of `asm`, `llvm-ir`, `llvm-bc`
+and `mir`. These files are written for the selected packages only, which are
+then compiled with a single codegen unit, and collected in the `emit`
+directory of the profile, like `target/debug/emit/lib/foo.s`.
+
+With `--lipo`, the binaries and dynamic or static libraries built for each of
+several Apple targets are combined into universal ones, along with their
+debug info, which are written to `target/universal/<profile>`.
+
+With `--dist`, the binaries and dynamic or static libraries of each package
+selected, their debug info, README and license files are copied to the `bin`,
+`lib`, `debug` and `doc` directories of a directory of the package in the
+given directory, like `dist/foo-1.0.0-x86_64-unknown-linux-gnu`, for each of
+the targets. `dist.json` lists the files of each of them.
+
+With `--crate-type`, the libraries of the packages selected are only built as
+the given crate types, out of the ones they declare, like only the `rlib` of a
+library which is also a `cdylib`, to skip linking the `cdylib`.
+
+With `--dependency`, only the library of that dependency of the selected
+packages is built, with the same features, profile and target as when it's
+built for them, instead of the packages themselves.
+
+With `--from-archive`, the dependencies are built from an archive written by
+`cargo fetch --to-archive` instead of the registries and git repositories
+they come from, which are never contacted.
+
+The units which fail to build are remembered in the directory of the profile.
+`--repeat-failed` builds only these units again, along with those of their
+dependencies which are out of date, and `--show-failed-command` prints the
+command each of them last ran, to run it by hand.
+
+With `--assert-reproducible`, everything is rebuilt, and then built a second
+time in a temporary target directory, with the paths of both target
+directories remapped to `/cargo-target`. The libraries, binaries and build
+scripts of the two builds are compared, and the ones which differ are
+reported with their first difference, down to the member of the archive and
+the ELF section it's in. The second build is kept if they differ.
 ",
         )
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
-    let ws = args.workspace(config)?;
+    if let Some(path) = args.value_of_path("from-archive", config) {
+        let dir = ops::unpack_archive(&args.workspace(config)?, &path)?;
+        ops::use_archive(config, &dir)?;
+    }
+    let mut ws = args.workspace(config)?;
     let mut compile_opts = args.compile_options(config, CompileMode::Build)?;
+    if args.is_present("show-failed-command") {
+        show_failed_commands(&ws, compile_opts.build_config.profile_kind)?;
+        return Ok(());
+    }
+    compile_opts.build_config.repeat_failed = args.is_present("repeat-failed");
+    compile_opts.build_config.crate_types = values(args, "crate-type");
+    compile_opts.build_config.dist_dir = args.value_of_path("dist", config);
     compile_opts.export_dir = args.value_of_path("out-dir", config);
     if compile_opts.export_dir.is_some() && !config.cli_unstable().unstable_options {
         Err(format_err!(
             "`--out-dir` flag is unstable, pass `-Z unstable-options` to enable it"
         ))?;
     };
-    ops::compile(&ws, &compile_opts)?;
+    if args.is_present("assert-reproducible") {
+        ops::assert_reproducible(&mut ws, &mut compile_opts)?;
+    } else {
+        ops::compile(&ws, &compile_opts)?;
+    }
     Ok(())
 }
@@ -28,10 +28,17 @@ pub fn cli() -> App {
         .arg_release("Check artifacts in release mode, with optimizations")
         .arg(opt("profile", "Profile to build the selected target for").value_name("PROFILE"))
         .arg_features()
-        .arg_target_triple("Check for the target triple")
+        .arg_target_triples("Check for the target triple")
+        .arg_target_cpu()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_deny_warnings()
+        .arg_dependency()
+        .arg(opt(
+            "remote",
+            "Check a version published to crates.io, given with `--package foo@1.2.3`",
+        ))
         .after_help(
             "\
 If the --package argument is given, then SPEC is a package id specification
@@ -49,12 +56,23 @@ the --release flag will use the `release` profile instead.
 
 The `--profile test` flag can be used to check unit tests with the
 `#[cfg(test)]` attribute.
+
+With `--dependency SPEC`, only the library of that dependency of the selected
+packages is checked, with the same features, profile and target as when it's
+built for them, instead of the packages themselves, like to check a patched
+dependency the way the workspace uses it.
+
+With `--remote`, the SPEC of the --package argument is the name of a crate on
+crates.io followed by `@` and one of its versions, like `foo@1.2.3`, or just
+the name for the latest version. That version is downloaded and checked on
+its own, with the given features and target, in a temporary target directory
+unless --target-dir is passed, without the need for a project depending on
+it.
 ",
         )
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
-    let ws = args.workspace(config)?;
     let test = match args.value_of("profile") {
         Some("test") => true,
         None => false,
@@ -68,7 +86,19 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         }
     };
     let mode = CompileMode::Check { test };
-    let compile_opts = args.compile_options(config, mode)?;
+    let mut compile_opts = args.compile_options(config, mode)?;
+    if args.is_present("remote") {
+        let specs = args.values_of_lossy("package").unwrap_or_default();
+        if specs.len() != 1 {
+            Err(format_err!(
+                "`--remote` requires exactly one `--package`, like `--package foo@1.2.3`"
+            ))?;
+        }
+        compile_opts.spec = ops::Packages::Default;
+        ops::check_remote(&specs[0], &compile_opts)?;
+        return Ok(());
+    }
+    let ws = args.workspace(config)?;
     ops::compile(&ws, &compile_opts)?;
     Ok(())
 }
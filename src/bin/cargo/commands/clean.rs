@@ -10,6 +10,7 @@ pub fn cli() -> App {
         .arg_target_triple("Target triple to clean output for (default all)")
         .arg_target_dir()
         .arg_release("Whether or not to clean release artifacts")
+        .arg_profile("Clean artifacts of the specified profile")
         .arg_doc("Whether or not to clean just the documentation directory")
         .after_help(
             "\
@@ -27,7 +28,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         config,
         spec: values(args, "package"),
         target: args.target(),
-        release: args.is_present("release"),
+        profile_kind: args.profile_kind(config)?,
         doc: args.is_present("doc"),
     };
     ops::clean(&ws, &opts)?;
@@ -0,0 +1,28 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, Completion};
+
+pub fn cli() -> App {
+    subcommand("complete-crate-name")
+        .about("Print the names of the crates in the registry starting with a prefix")
+        .arg(Arg::with_name("prefix").value_name("PREFIX"))
+        .arg_index()
+        .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .after_help(
+            "\
+The crates are listed one per line, sorted, from the local copy of the index of
+the registry, which isn't updated, so that it works offline and is fast enough
+for completions. Commands like `cargo fetch` download the index.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let registry = args.registry(config)?;
+    let index = args.index(config)?;
+    let completion = Completion::CrateNames(args.value_of("prefix").unwrap_or(""));
+    for item in ops::complete(config, &completion, index, registry)? {
+        println!("{}", item);
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, Completion};
+
+pub fn cli() -> App {
+    subcommand("complete-features")
+        .about("Print the features of a crate in the registry")
+        .arg(Arg::with_name("crate").value_name("CRATE").required(true))
+        .arg_index()
+        .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .after_help(
+            "\
+The features of the crate's latest version, or of the latest version matching
+the requirement given with it, like `serde@1.0`, are listed one per line,
+sorted, with its optional dependencies, from the local copy of the index of the
+registry, which isn't updated, so that it works offline and is fast enough for
+completions.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let registry = args.registry(config)?;
+    let index = args.index(config)?;
+    let completion = Completion::Features(args.value_of("crate").unwrap());
+    for item in ops::complete(config, &completion, index, registry)? {
+        println!("{}", item);
+    }
+    Ok(())
+}
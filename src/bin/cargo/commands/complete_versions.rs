@@ -0,0 +1,28 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, Completion};
+
+pub fn cli() -> App {
+    subcommand("complete-versions")
+        .about("Print the versions of a crate in the registry")
+        .arg(Arg::with_name("crate").value_name("CRATE").required(true))
+        .arg_index()
+        .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .after_help(
+            "\
+The versions are listed one per line, newest first, from the local copy of the
+index of the registry, which isn't updated, so that it works offline and is fast
+enough for completions. Yanked versions aren't listed.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let registry = args.registry(config)?;
+    let index = args.index(config)?;
+    let completion = Completion::Versions(args.value_of("crate").unwrap());
+    for item in ops::complete(config, &completion, index, registry)? {
+        println!("{}", item);
+    }
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, DiffOptions};
+use cargo::util::paths;
+
+pub fn cli() -> App {
+    subcommand("diff")
+        .about("Show the changes between two published versions of a crate")
+        .arg(Arg::with_name("crate").required(true))
+        .arg(Arg::with_name("from").required(true).help("The old version"))
+        .arg(Arg::with_name("to").required(true).help("The new version"))
+        .arg(opt("registry", "Registry to download the crate from").value_name("REGISTRY"))
+        .arg(
+            opt("context", "Number of unchanged lines shown around each change")
+                .value_name("LINES")
+                .default_value("3"),
+        )
+        .arg(opt("output", "Write the diff to this file").value_name("PATH"))
+        .after_help(
+            "\
+Both versions of the crate are downloaded from the registry, and their
+checksums verified, like the dependencies of a package are. The unified diff
+of their sources is printed to stdout, or written to the `--output` file, to
+review what changed in an update before accepting it into `Cargo.lock`, for
+example `cargo diff serde 1.0.79 1.0.80`.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let opts = DiffOptions {
+        config,
+        krate: args.value_of("crate").unwrap().to_string(),
+        from: args.value_of("from").unwrap().to_string(),
+        to: args.value_of("to").unwrap().to_string(),
+        registry: args.registry(config)?,
+        context: args.value_of_u32("context")?.unwrap() as usize,
+    };
+    let diff = ops::diff(&opts)?;
+    match args.value_of_path("output", config) {
+        Some(path) => paths::write(&path, diff.as_bytes())?,
+        None => print!("{}", diff),
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::command_prelude::*;
+
+use cargo::core::Verbosity;
+use cargo::ops::{self, ExecOptions, Packages};
+
+pub fn cli() -> App {
+    subcommand("exec")
+        .setting(AppSettings::TrailingVarArg)
+        .about("Run a tool with the environment of the workspace")
+        .arg(
+            Arg::with_name("command")
+                .value_name("TOOL [ARGS]...")
+                .multiple(true)
+                .required(true),
+        )
+        .arg(opt("each", "Run the tool once for each selected package"))
+        .arg_package_spec(
+            "Package to run the tool for",
+            "Run the tool for all packages in the workspace",
+            "Exclude packages from running the tool",
+        )
+        .arg(opt(
+            "no-deps",
+            "Describe the workspace members only in `CARGO_METADATA`, \
+             without resolving their dependencies",
+        ))
+        .arg_release("Load the libraries built in release mode")
+        .arg_profile("Load the libraries built with the specified custom profile")
+        .arg_target_dir()
+        .arg_manifest_path()
+        .after_help(
+            "\
+Runs an external tool the way cargo runs the ones it builds, so that tools
+don't each have to find the layout of the workspace themselves. The tool gets:
+
+    CARGO                 The path of cargo.
+    CARGO_WORKSPACE_ROOT  The directory of the root manifest of the workspace.
+    CARGO_TARGET_DIR      The target directory of the workspace.
+    CARGO_MANIFEST_PATH   The root manifest, or the manifest of the package.
+    CARGO_METADATA        A file with the output of
+                          `cargo metadata --format-version 1`.
+
+along with the libraries of the workspace and of the toolchain in its dynamic
+library search path. The tool runs once, at the root of the workspace, unless
+`--each` is passed, in which case it runs in the directory of each package
+selected by `-p`, `--all` and `--exclude`, like `cargo build` selects them,
+with the `CARGO_MANIFEST_DIR` and `CARGO_PKG_*` variables of the package. It
+stops at the first package the tool fails for.
+
+All the arguments following the tool are passed to it, for example
+`cargo exec --each rustfmt --check src/lib.rs`.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let each = args.is_present("each");
+    let selects_packages = ["package", "all", "exclude"]
+        .iter()
+        .any(|&flag| args.is_present(flag));
+    if selects_packages && !each {
+        return Err(format_err!("`-p`, `--all` and `--exclude` require `--each`").into());
+    }
+    let mut command = values(args, "command");
+    let opts = ExecOptions {
+        tool: command.remove(0),
+        args: command,
+        each: if each {
+            Some(Packages::from_flags(
+                args.is_present("all"),
+                values(args, "exclude"),
+                values(args, "package"),
+            )?)
+        } else {
+            None
+        },
+        profile_kind: args.profile_kind(config)?,
+        no_deps: args.is_present("no-deps"),
+    };
+    match ops::exec(&ws, &opts)? {
+        None => Ok(()),
+        Some(err) => {
+            // The tool couldn't be spawned at all.
+            let exit = match err.exit {
+                Some(exit) => exit,
+                None => return Err(CliError::new(err.into(), 101)),
+            };
+            let exit_code = exit.code().unwrap_or(101);
+            let is_quiet = config.shell().verbosity() == Verbosity::Quiet;
+            Err(if is_quiet {
+                CliError::code(exit_code)
+            } else {
+                CliError::new(err.into(), exit_code)
+            })
+        }
+    }
+}
@@ -8,6 +8,13 @@ pub fn cli() -> App {
         .about("Fetch dependencies of a package from the network")
         .arg_manifest_path()
         .arg_target_triple("Fetch dependencies for the target triple")
+        .arg(
+            opt(
+                "to-archive",
+                "Write the sources of all dependencies to this archive",
+            )
+            .value_name("PATH"),
+        )
         .after_help(
             "\
 If a lockfile is available, this command will ensure that all of the git
@@ -18,6 +25,10 @@ the lockfile changes.
 If the lockfile is not available, then this is the equivalent of
 `cargo generate-lockfile`. A lockfile is generated and dependencies are also
 all updated.
+
+With `--to-archive`, the sources of every dependency in the lockfile that
+doesn't come from a path are also written to an archive, which
+`cargo build --from-archive` builds from without touching the network.
 ",
         )
 }
@@ -29,6 +40,9 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         config,
         target: args.target(),
     };
-    ops::fetch(&ws, &opts)?;
+    let (resolve, packages) = ops::fetch(&ws, &opts)?;
+    if let Some(path) = args.value_of_path("to-archive", config) {
+        ops::write_archive(&ws, &resolve, &packages, &path)?;
+    }
     Ok(())
 }
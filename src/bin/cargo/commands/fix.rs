@@ -51,6 +51,12 @@ pub fn cli() -> App {
                 .conflicts_with("edition")
                 .hidden(true),
         )
+        .arg(
+            Arg::with_name("migrate")
+                .long("migrate")
+                .requires("edition")
+                .help("Also fix all features, bump the manifest edition and re-check (unstable)"),
+        )
         .arg(
             Arg::with_name("idioms")
                 .long("edition-idioms")
@@ -133,8 +139,15 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             tests: FilterRule::All,
         }
     }
+    let migrate = args.is_present("migrate");
+    if migrate && !config.cli_unstable().unstable_options {
+        Err(format_err!(
+            "`--migrate` flag is unstable, pass `-Z unstable-options` to enable it"
+        ))?;
+    }
     ops::fix(&ws, &mut ops::FixOptions {
         edition: args.is_present("edition"),
+        migrate,
         prepare_for: args.value_of("prepare-for"),
         idioms: args.is_present("idioms"),
         compile_opts: opts,
@@ -0,0 +1,80 @@
+use crate::command_prelude::*;
+
+use cargo::core::dependency::Kind;
+use cargo::ops::{self, GraphOptions, Packages};
+
+pub fn cli() -> App {
+    subcommand("graph")
+        .about("Output the resolved dependency graph in a renderable format")
+        .arg_package_spec(
+            "Package to start the graph from",
+            "Start the graph from all packages in the workspace",
+            "Exclude packages from the graph",
+        )
+        .arg_features()
+        .arg(
+            opt("format", "Format of the graph description")
+                .value_name("FORMAT")
+                .possible_values(&["dot", "json", "mermaid"])
+                .default_value("dot"),
+        )
+        .arg(
+            multi_opt("edges", "KIND", "Dependency kinds to follow (default: all)")
+                .possible_values(&["normal", "build", "dev"]),
+        )
+        .arg(
+            opt("depth", "Maximum depth of dependencies to include")
+                .value_name("DEPTH"),
+        )
+        .arg(opt(
+            "show-features",
+            "Annotate packages with their activated features and \
+             dependencies with the features they enable",
+        ))
+        .arg_manifest_path()
+        .after_help(
+            "\
+The graph is printed to stdout. The `dot` format can be rendered with Graphviz,
+for example `cargo graph | dot -Tsvg > deps.svg`, and the `mermaid` format can
+be embedded in Markdown documents which support Mermaid diagrams.
+
+If no packages are selected, the graph starts from the default members of the
+workspace.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+
+    let spec = Packages::from_flags(
+        args.is_present("all"),
+        values(args, "exclude"),
+        values(args, "package"),
+    )?;
+    let kinds = match args.values_of("edges") {
+        Some(edges) => edges
+            .map(|edge| match edge {
+                "build" => Kind::Build,
+                "dev" => Kind::Development,
+                _ => Kind::Normal,
+            })
+            .collect(),
+        None => vec![Kind::Normal, Kind::Build, Kind::Development],
+    };
+
+    let opts = GraphOptions {
+        features: values(args, "features"),
+        all_features: args.is_present("all-features"),
+        no_default_features: args.is_present("no-default-features"),
+        spec,
+        format: args.value_of("format").unwrap().parse()?,
+        kinds,
+        depth: args.value_of_u32("depth")?.map(|d| d as usize),
+        show_features: args.is_present("show-features"),
+    };
+
+    let graph = ops::graph(&ws, &opts)?;
+    println!("{}", graph);
+    Ok(())
+}
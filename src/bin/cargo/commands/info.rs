@@ -0,0 +1,29 @@
+use crate::command_prelude::*;
+
+use cargo::ops;
+
+pub fn cli() -> App {
+    subcommand("info")
+        .about("Show the metadata of a crate in the registry")
+        .arg(Arg::with_name("crate").value_name("CRATE").required(true))
+        .arg_index()
+        .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .after_help(
+            "\
+The crate is shown at its latest version, or at the latest version matching
+the requirement given with it, like `serde@1.0`. Its versions, features and
+dependencies come from the index of the registry, and its description,
+license, links and the minimum Rust version it declares from the registry's
+API, when it has one. If run in a workspace, the versions of the crate its
+lockfile uses are shown as well.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let registry = args.registry(config)?;
+    let index = args.index(config)?;
+    let krate = args.value_of("crate").unwrap();
+    ops::info(krate, config, index, registry)?;
+    Ok(())
+}
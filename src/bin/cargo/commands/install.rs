@@ -35,6 +35,10 @@ pub fn cli() -> App {
         .arg_target_triple("Build for the target triple")
         .arg(opt("root", "Directory to install packages into").value_name("DIR"))
         .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .arg(opt(
+            "ignore-local-config",
+            "Only use the configuration in the home directory of cargo",
+        ))
         .after_help(
             "\
 This command manages Cargo's local set of installed binary crates. Only packages
@@ -70,17 +74,46 @@ If the source is crates.io or `--git` then by default the crate will be built
 in a temporary target directory.  To avoid this, the target directory can be
 specified by setting the `CARGO_TARGET_DIR` environment variable to a relative
 path.  In particular, this can be useful for caching build artifacts on
-continuous integration systems.",
+continuous integration systems.
+
+The configuration in the home directory of cargo (`$CARGO_HOME/config`) always
+applies. A crate installed with `--path`, or from the current directory, is
+built with the configuration found from its directory, like `cargo build` in
+it. The configuration file a crate from crates.io or `--git` bundles, in its
+`.cargo/config`, applies to its build too, over the one in the home directory,
+and cargo warns about the keys it sets. It doesn't apply to the keys read
+before the crate is fetched, like `build.jobs` and `install.root`. With
+`--ignore-local-config` only the configuration in the home directory of cargo
+applies.",
         )
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let registry = args.registry(config)?;
 
-    config.reload_rooted_at_cargo_home()?;
+    // Packages on the filesystem are built with the configuration found from
+    // their directory, like `cargo build` in it.
+    let ignore_local_config = args.is_present("ignore-local-config");
+    let local_path = if args.is_present("git") {
+        None
+    } else if let Some(path) = args.value_of_path("path", config) {
+        Some(path)
+    } else if args.values_of("crate").is_none() {
+        Some(config.cwd().to_path_buf())
+    } else {
+        None
+    };
+    match local_path {
+        Some(ref path) if !ignore_local_config => config.reload_rooted_at(path)?,
+        _ => config.reload_rooted_at_cargo_home()?,
+    }
     let mut compile_opts = args.compile_options(config, CompileMode::Build)?;
 
-    compile_opts.build_config.release = !args.is_present("debug");
+    compile_opts.build_config.profile_kind = if args.is_present("debug") {
+        ProfileKind::Dev
+    } else {
+        ProfileKind::Release
+    };
 
     let krates = args
         .values_of("crate")
@@ -126,6 +159,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             version,
             &compile_opts,
             args.is_present("force"),
+            ignore_local_config,
         )?;
     }
     Ok(())
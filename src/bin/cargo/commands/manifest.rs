@@ -0,0 +1,99 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, ManifestEdit};
+
+pub fn cli() -> App {
+    subcommand("manifest")
+        .about("Edit the manifest of a package, keeping its formatting")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("set")
+                .about("Set a key of the manifest")
+                .arg(Arg::with_name("key").value_name("KEY").required(true))
+                .arg(Arg::with_name("value").value_name("VALUE").required(true))
+                .arg(opt(
+                    "toml",
+                    "Interpret VALUE as TOML, like `true` or `[\"a\", \"b\"]`, \
+                     instead of as a string",
+                ))
+                .arg_manifest_path()
+                .after_help(
+                    "\
+KEY is the path of the key in the manifest, like `package.version` or
+`dependencies.serde.features`, with parts containing dots quoted, like
+`target.\"cfg(unix)\".dependencies.libc`. Inline tables are edited in place,
+and keys of tables which aren't in the manifest yet are added in a new table.
+
+For example `cargo manifest set package.version 1.2.0` or
+`cargo manifest set package.publish false --toml`.
+",
+                ),
+        )
+        .subcommand(
+            subcommand("unset")
+                .about("Remove a key, or a table, from the manifest")
+                .arg(Arg::with_name("key").value_name("KEY").required(true))
+                .arg_manifest_path(),
+        )
+        .subcommand(
+            subcommand("add-feature")
+                .about("Add a feature to the manifest")
+                .arg(Arg::with_name("name").value_name("NAME").required(true))
+                .arg(multi_opt(
+                    "enables",
+                    "FEATURE",
+                    "Feature, optional dependency or feature of a dependency, \
+                     like `bar/baz`, the feature enables",
+                ))
+                .arg_manifest_path()
+                .after_help(
+                    "\
+If the feature exists already, the features passed with `--enables` it
+doesn't enable yet are added to it.
+",
+                ),
+        )
+        .after_help(
+            "\
+The manifest is edited in place, so the rest of the file keeps its formatting
+and comments. The edited manifest is checked the way cargo reads manifests
+before it's written: an edit which would make it invalid, or which sets a key
+cargo doesn't know about, leaves it unchanged.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let (edit, args) = match args.subcommand() {
+        ("set", Some(args)) => {
+            let value = args.value_of("value").unwrap();
+            let value = if args.is_present("toml") {
+                value.to_string()
+            } else {
+                toml::Value::String(value.to_string()).to_string()
+            };
+            let edit = ManifestEdit::Set {
+                key: args.value_of("key").unwrap().to_string(),
+                value,
+            };
+            (edit, args)
+        }
+        ("unset", Some(args)) => {
+            let edit = ManifestEdit::Unset {
+                key: args.value_of("key").unwrap().to_string(),
+            };
+            (edit, args)
+        }
+        ("add-feature", Some(args)) => {
+            let edit = ManifestEdit::AddFeature {
+                name: args.value_of("name").unwrap().to_string(),
+                enables: values(args, "enables"),
+            };
+            (edit, args)
+        }
+        _ => unreachable!(),
+    };
+    let path = args.root_manifest(config)?;
+    ops::edit_manifest(config, &path, &edit)?;
+    Ok(())
+}
@@ -16,6 +16,20 @@ pub fn cli() -> App {
             "Output information only about the root package \
              and don't fetch dependencies",
         ))
+        .arg(
+            opt(
+                "no-deps-resolve",
+                "Like --no-deps, but read the workspace manifests only, \
+                 never accessing the network or the resolver",
+            )
+            .conflicts_with_all(&["features", "all-features", "no-default-features"]),
+        )
+        .arg(opt(
+            "target-info",
+            "Also output the platforms of the host and the target, with \
+             their cfg values and tools",
+        ))
+        .arg_target_triples("Target triples to output the platforms of with --target-info")
         .arg_manifest_path()
         .arg(
             opt("format-version", "Format version")
@@ -25,6 +39,10 @@ pub fn cli() -> App {
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let no_deps_resolve = args.is_present("no-deps-resolve");
+    if no_deps_resolve {
+        config.set_offline(true);
+    }
     let ws = args.workspace(config)?;
 
     let version = match args.value_of("format-version") {
@@ -43,8 +61,10 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         features: values(args, "features"),
         all_features: args.is_present("all-features"),
         no_default_features: args.is_present("no-default-features"),
-        no_deps: args.is_present("no-deps"),
+        no_deps: args.is_present("no-deps") || no_deps_resolve,
         version,
+        target_info: args.is_present("target-info"),
+        targets: args.targets(),
     };
 
     let result = ops::output_metadata(&ws, &options)?;
@@ -2,19 +2,28 @@ use crate::command_prelude::*;
 
 pub fn builtin() -> Vec<App> {
     vec![
+        add::cli(),
         bench::cli(),
         build::cli(),
         check::cli(),
         clean::cli(),
+        complete_crate_name::cli(),
+        complete_features::cli(),
+        complete_versions::cli(),
+        diff::cli(),
         doc::cli(),
+        exec::cli(),
         fetch::cli(),
         fix::cli(),
         generate_lockfile::cli(),
+        graph::cli(),
         git_checkout::cli(),
+        info::cli(),
         init::cli(),
         install::cli(),
         locate_project::cli(),
         login::cli(),
+        manifest::cli(),
         metadata::cli(),
         new::cli(),
         owner::cli(),
@@ -22,6 +31,7 @@ pub fn builtin() -> Vec<App> {
         pkgid::cli(),
         publish::cli(),
         read_manifest::cli(),
+        report::cli(),
         run::cli(),
         rustc::cli(),
         rustdoc::cli(),
@@ -29,6 +39,8 @@ pub fn builtin() -> Vec<App> {
         test::cli(),
         uninstall::cli(),
         update::cli(),
+        vendor::cli(),
+        verify_crate::cli(),
         verify_project::cli(),
         version::cli(),
         yank::cli(),
@@ -37,19 +49,28 @@ pub fn builtin() -> Vec<App> {
 
  pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> CliResult> {
      let f = match cmd {
+        "add" => add::exec,
         "bench" => bench::exec,
         "build" => build::exec,
         "check" => check::exec,
         "clean" => clean::exec,
+        "complete-crate-name" => complete_crate_name::exec,
+        "complete-features" => complete_features::exec,
+        "complete-versions" => complete_versions::exec,
+        "diff" => diff::exec,
         "doc" => doc::exec,
+        "exec" => exec::exec,
         "fetch" => fetch::exec,
         "fix" => fix::exec,
         "generate-lockfile" => generate_lockfile::exec,
+        "graph" => graph::exec,
         "git-checkout" => git_checkout::exec,
+        "info" => info::exec,
         "init" => init::exec,
         "install" => install::exec,
         "locate-project" => locate_project::exec,
         "login" => login::exec,
+        "manifest" => manifest::exec,
         "metadata" => metadata::exec,
         "new" => new::exec,
         "owner" => owner::exec,
@@ -57,6 +78,7 @@ pub fn builtin() -> Vec<App> {
         "pkgid" => pkgid::exec,
         "publish" => publish::exec,
         "read-manifest" => read_manifest::exec,
+        "report" => report::exec,
         "run" => run::exec,
         "rustc" => rustc::exec,
         "rustdoc" => rustdoc::exec,
@@ -64,6 +86,8 @@ pub fn builtin() -> Vec<App> {
         "test" => test::exec,
         "uninstall" => uninstall::exec,
         "update" => update::exec,
+        "vendor" => vendor::exec,
+        "verify-crate" => verify_crate::exec,
         "verify-project" => verify_project::exec,
         "version" => version::exec,
         "yank" => yank::exec,
@@ -72,19 +96,28 @@ pub fn builtin() -> Vec<App> {
     Some(f)
 }
 
+pub mod add;
 pub mod bench;
 pub mod build;
 pub mod check;
 pub mod clean;
+pub mod complete_crate_name;
+pub mod complete_features;
+pub mod complete_versions;
+pub mod diff;
 pub mod doc;
+pub mod exec;
 pub mod fetch;
 pub mod fix;
 pub mod generate_lockfile;
+pub mod graph;
 pub mod git_checkout;
+pub mod info;
 pub mod init;
 pub mod install;
 pub mod locate_project;
 pub mod login;
+pub mod manifest;
 pub mod metadata;
 pub mod new;
 pub mod owner;
@@ -92,6 +125,7 @@ pub mod package;
 pub mod pkgid;
 pub mod publish;
 pub mod read_manifest;
+pub mod report;
 pub mod run;
 pub mod rustc;
 pub mod rustdoc;
@@ -99,6 +133,8 @@ pub mod search;
 pub mod test;
 pub mod uninstall;
 pub mod update;
+pub mod vendor;
+pub mod verify_crate;
 pub mod verify_project;
 pub mod version;
 pub mod yank;
@@ -20,6 +20,10 @@ pub fn cli() -> App {
         .arg_manifest_path()
         .arg_jobs()
         .arg(opt("dry-run", "Perform all checks without uploading"))
+        .arg(opt(
+            "show-payload",
+            "Print the metadata and the files of the tarball sent to the registry, without uploading",
+        ))
         .arg(opt("registry", "Registry to publish to").value_name("REGISTRY"))
 }
 
@@ -39,6 +43,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             target: args.target(),
             jobs: args.jobs()?,
             dry_run: args.is_present("dry-run"),
+            show_payload: args.is_present("show-payload"),
             registry,
         },
     )?;
@@ -0,0 +1,181 @@
+use crate::command_prelude::*;
+
+use cargo::ops;
+use cargo::print_json;
+
+pub fn cli() -> App {
+    subcommand("report")
+        .about("Generate and display various kinds of reports")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("inputs")
+                .about(
+                    "Output the files and environment variables the last build \
+                     of a package depended on, in machine-readable format",
+                )
+                .arg_package_spec(
+                    "Package(s) to report on",
+                    "Report on all packages in the workspace",
+                    "Exclude packages from the report",
+                )
+                .arg_targets_all(
+                    "Report only on this package's library",
+                    "Report only on the specified binary",
+                    "Report on all binaries",
+                    "Report only on the specified example",
+                    "Report on all examples",
+                    "Report only on the specified test target",
+                    "Report on all tests",
+                    "Report only on the specified bench target",
+                    "Report on all benches",
+                    "Report on all targets",
+                )
+                .arg(opt(
+                    "external",
+                    "Report on the path dependencies outside of the workspace instead",
+                ))
+                .arg_release("Report on artifacts built in release mode")
+                .arg_features()
+                .arg_target_triple("Report on artifacts built for the target triple")
+                .arg_target_dir()
+                .arg_manifest_path()
+                .after_help(
+                    "\
+The report lists every unit of compilation of the selected packages, including
+the compilation and execution of their build scripts. For each unit the source
+files recorded in its dep-info file, or the `rerun-if-changed` paths printed by
+its build script, are listed along with the environment variables the unit was
+fingerprinted against. Units which have not been built yet have no files.
+
+With `--external` the report lists the units of every path dependency of the
+selected packages which lives outside of the workspace root instead, such as
+`foo = { path = \"../foo\" }`. These are the external directories Cargo watches
+for changes when building the selected packages.
+
+Nothing is compiled by this command, the report describes the last build made
+with the same set of options.
+",
+                ),
+        )
+        .subcommand(
+            subcommand("bug")
+                .about(
+                    "Output what's needed to reproduce a problem with building a \
+                     package, in machine-readable format",
+                )
+                .arg_package_spec(
+                    "Package(s) to report on",
+                    "Report on all packages in the workspace",
+                    "Exclude packages from the report",
+                )
+                .arg_targets_all(
+                    "Report only on this package's library",
+                    "Report only on the specified binary",
+                    "Report on all binaries",
+                    "Report only on the specified example",
+                    "Report on all examples",
+                    "Report only on the specified test target",
+                    "Report on all tests",
+                    "Report only on the specified bench target",
+                    "Report on all benches",
+                    "Report on all targets",
+                )
+                .arg_release("Report on artifacts built in release mode")
+                .arg_features()
+                .arg_target_triple("Report on artifacts built for the target triple")
+                .arg_target_dir()
+                .arg_manifest_path()
+                .after_help(
+                    "\
+The report is meant to be attached to a bug report for Cargo. It contains the
+versions of Cargo and rustc, the merged configuration and the environment
+variables starting with `CARGO` or `RUST`, a summary of the resolved dependency
+graph, and the state the last build left for every unit of the selected
+packages and their dependencies: the fingerprint of its last successful build,
+and the end of the output of build scripts. A unit without a fingerprint has
+never been built successfully.
+
+Keys and variables which look like they hold a secret, such as registry tokens,
+are redacted, as are the credentials in URLs. The paths to the workspace,
+Cargo's home and the user's home directory are replaced with `$WORKSPACE`,
+`$CARGO_HOME` and `$HOME`. Please still look over the report before sharing it.
+
+Nothing is compiled by this command, run it with the options of the build which
+failed.
+",
+                ),
+        )
+        .subcommand(
+            subcommand("bloat")
+                .about(
+                    "Output how much each crate contributes to the size of the \
+                     binaries of a package, in machine-readable format",
+                )
+                .arg_package_spec(
+                    "Package(s) to report on",
+                    "Report on all packages in the workspace",
+                    "Exclude packages from the report",
+                )
+                .arg_targets_bins_examples(
+                    "Report only on the specified binary",
+                    "Report on all binaries",
+                    "Report only on the specified example",
+                    "Report on all examples",
+                )
+                .arg_release("Report on artifacts built in release mode")
+                .arg_features()
+                .arg_target_triple("Report on artifacts built for the target triple")
+                .arg_target_dir()
+                .arg_manifest_path()
+                .after_help(
+                    "\
+The report lists the binaries of the selected packages along with their size on
+disk. The symbols in the symbol table of each binary, as listed by `nm`, are
+demangled and attributed to the crate their path starts with. For every crate
+the total size of its symbols is reported along with the packages in the
+dependency graph of the binary which provide a library of that name. Functions
+which were instantiated more than once, typically because they're generic, are
+listed separately with the number of instantiations and their total size.
+
+Binaries without a symbol table, for example because they have been stripped,
+only have their file size reported. Binaries which have not been built yet have
+no file size.
+
+Nothing is compiled by this command, the report describes the last build made
+with the same set of options.
+",
+                ),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().unstable_options {
+        Err(format_err!(
+            "`cargo report` is unstable, pass `-Z unstable-options` to enable it"
+        ))?;
+    }
+    match args.subcommand() {
+        ("inputs", Some(args)) => {
+            let ws = args.workspace(config)?;
+            let compile_opts = args.compile_options(config, CompileMode::Build)?;
+            let report = ops::unit_inputs(&ws, &compile_opts, args.is_present("external"))?;
+            print_json(&report);
+            Ok(())
+        }
+        ("bug", Some(args)) => {
+            let ws = args.workspace(config)?;
+            let compile_opts = args.compile_options(config, CompileMode::Build)?;
+            let report = ops::bug_report(&ws, &compile_opts)?;
+            print_json(&report);
+            Ok(())
+        }
+        ("bloat", Some(args)) => {
+            let ws = args.workspace(config)?;
+            let compile_opts = args.compile_options(config, CompileMode::Build)?;
+            let report = ops::bloat_report(&ws, &compile_opts)?;
+            print_json(&report);
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
@@ -17,17 +17,20 @@ pub fn cli() -> App {
         .arg_package("Package with the target to run")
         .arg_jobs()
         .arg_release("Build artifacts in release mode, with optimizations")
+        .arg_profile("Build artifacts with the specified custom profile")
         .arg_features()
         .arg_target_triple("Build for the target triple")
+        .arg_target_cpu()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_deny_warnings()
         .after_help(
             "\
 If neither `--bin` nor `--example` are given, then if the package only has one
 bin target it will be run. Otherwise `--bin` specifies the bin target to run,
 and `--example` specifies the example target to run. At most one of `--bin` or
-`--example` can be provided.
+`--example` can be provided. Without a name, they list the available targets.
 
 All the arguments following the two dashes (`--`) are passed to the binary to
 run. If you're passing arguments to both Cargo and the binary, the ones after
@@ -40,6 +43,15 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let ws = args.workspace(config)?;
 
     let mut compile_opts = args.compile_options(config, CompileMode::Build)?;
+
+    // `--bin` or `--example` without a name lists the targets to pick from.
+    for &flag in &["bin", "example"] {
+        if args.is_present(flag) && values(args, flag).is_empty() {
+            let packages = compile_opts.spec.get_packages(&ws)?;
+            return Err(ops::missing_target_error(&packages, flag, None).into());
+        }
+    }
+
     if !args.is_present("example") && !args.is_present("bin") {
         let default_runs: Vec<_> = compile_opts
             .spec
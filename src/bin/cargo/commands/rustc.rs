@@ -25,9 +25,11 @@ pub fn cli() -> App {
         .arg(opt("profile", "Profile to build the selected target for").value_name("PROFILE"))
         .arg_features()
         .arg_target_triple("Target triple which compiles will be for")
+        .arg_target_cpu()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_deny_warnings()
         .after_help(
             "\
 The specified target for the current package (or package specified by SPEC if
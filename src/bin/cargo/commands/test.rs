@@ -32,7 +32,20 @@ pub fn cli() -> App {
         )
         .arg(opt("doc", "Test only this library's documentation"))
         .arg(opt("no-run", "Compile, but don't run tests"))
+        .arg(
+            opt(
+                "run-manifest",
+                "With --no-run, print how to run each test executable",
+            )
+            .requires("no-run"),
+        )
         .arg(opt("no-fail-fast", "Run all tests regardless of failure"))
+        .arg(
+            opt("shuffle", "Run the test binaries in a random order")
+                .value_name("SEED")
+                .min_values(0)
+                .require_equals(true),
+        )
         .arg_package_spec(
             "Package to run tests for",
             "Test all packages in the workspace",
@@ -40,11 +53,14 @@ pub fn cli() -> App {
         )
         .arg_jobs()
         .arg_release("Build artifacts in release mode, with optimizations")
+        .arg_profile("Build artifacts with the specified custom profile")
         .arg_features()
         .arg_target_triple("Build for the target triple")
+        .arg_target_cpu()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_deny_warnings()
         .after_help(
             "\
 The test filtering argument `TESTNAME` and all the arguments following the
@@ -57,6 +73,12 @@ tests with `foo` in their name on 3 threads in parallel:
 
     cargo test foo -- --test-threads 3
 
+Test binaries are run one after the other, ordered by package, then by kind
+(libraries, binaries, integration tests, benchmarks and examples) and then by
+name. Passing `--shuffle` runs them in a random order instead, which helps to
+find tests which depend on each other. The seed used is printed and can be
+passed back with `--shuffle=SEED` to reproduce the order.
+
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be tested. If it is not given, then the
 current package is tested. For more information on SPEC and its format, see the
@@ -114,9 +136,35 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         );
     }
 
+    let run_manifest = args.is_present("run-manifest");
+    if run_manifest && !config.cli_unstable().unstable_options {
+        Err(format_err!(
+            "`--run-manifest` flag is unstable, pass `-Z unstable-options` to enable it"
+        ))?;
+    }
+
+    let shuffle = if args.is_present("shuffle") {
+        if !config.cli_unstable().unstable_options {
+            Err(format_err!(
+                "`--shuffle` flag is unstable, pass `-Z unstable-options` to enable it"
+            ))?;
+        }
+        match args.value_of("shuffle") {
+            Some(seed) => Some(
+                seed.parse::<u64>()
+                    .map_err(|_| format_err!("could not parse `{}` as a shuffle seed", seed))?,
+            ),
+            None => Some(ops::random_shuffle_seed()),
+        }
+    } else {
+        None
+    };
+
     let ops = ops::TestOptions {
         no_run: args.is_present("no-run"),
         no_fail_fast: args.is_present("no-fail-fast"),
+        run_manifest,
+        shuffle,
         compile_opts,
     };
 
@@ -11,6 +11,12 @@ pub fn cli() -> App {
             "Force updating all dependencies of <name> as well",
         ))
         .arg(opt("precise", "Update a single dependency to exactly PRECISE").value_name("PRECISE"))
+        .arg(multi_opt(
+            "pin",
+            "SPEC",
+            "Hold a dependency back to exactly this version, like `foo@1.4.2`",
+        ))
+        .arg(multi_opt("unpin", "NAME", "Stop holding a dependency back"))
         .arg_manifest_path()
         .after_help(
             "\
@@ -32,6 +38,12 @@ be updated to.
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
 
+`--pin foo@1.4.2` updates the dependency `foo` from a registry to exactly
+version 1.4.2, and records the pin in the `[pin]` table of `Cargo.lock`. Later
+resolutions keep every dependency on `foo` which allows for 1.4.2 at that
+version, until the pin is removed with `--unpin foo`, which leaves `foo` at
+its current version for the next update.
+
 For more information about package id specifications, see `cargo help pkgid`.
 ",
         )
@@ -44,6 +56,8 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         aggressive: args.is_present("aggressive"),
         precise: args.value_of("precise"),
         to_update: values(args, "package"),
+        pin: values(args, "pin"),
+        unpin: values(args, "unpin"),
         config,
     };
     ops::update_lockfile(&ws, &update_opts)?;
@@ -0,0 +1,120 @@
+use crate::command_prelude::*;
+
+use std::env;
+use std::io::{self, Write};
+
+use cargo::ops;
+
+pub fn cli() -> App {
+    subcommand("vendor")
+        .setting(AppSettings::TrailingVarArg)
+        .setting(AppSettings::AllowLeadingHyphen)
+        // `--help` is passed on to the plugin, whose options it lists.
+        .setting(AppSettings::DisableHelpFlags)
+        .about("Vendor all dependencies of a package locally")
+        .arg(opt(
+            "verify",
+            "Check that the vendored sources match the lock file",
+        ))
+        .arg(Arg::with_name("args").value_name("ARGS").multiple(true))
+        .arg_manifest_path()
+        .after_help(
+            "\
+Vendoring itself is done by the `cargo-vendor` plugin, which all the arguments
+are passed to, along with cargo's own flags like `-v` or `--locked`, unless
+`--verify` is passed.
+
+With `--verify [DIR]`, the sources vendored in DIR, `vendor` by default, are
+checked against the lock file instead: every package from a registry in it
+must be vendored, with the checksum the lock file lists, and none of the files
+of a vendored package may have been modified. All the problems found are
+reported.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    // Everything after the first of the arguments is in `args`, including
+    // `--verify` if it comes after a directory.
+    let mut rest = args
+        .values_of("args")
+        .unwrap_or_default()
+        .collect::<Vec<_>>();
+    let verify = args.is_present("verify") || rest.contains(&"--verify");
+    rest.retain(|arg| *arg != "--verify");
+    if !verify {
+        return vendor(config, args, rest);
+    }
+
+    if rest.len() > 1 {
+        return Err(format_err!(
+            "`--verify` takes a single vendor directory, but got: {}",
+            rest.join(" ")
+        )
+        .into());
+    }
+    let ws = args.workspace(config)?;
+    let dir = config.cwd().join(rest.get(0).cloned().unwrap_or("vendor"));
+    ops::verify_vendored_sources(&ws, &dir)?;
+    config.shell().status(
+        "Verified",
+        format!("vendored sources in `{}`", dir.display()),
+    )?;
+    Ok(())
+}
+
+/// Runs the `cargo-vendor` plugin with `rest`, and the flags of cargo
+/// itself, which were parsed before it.
+fn vendor(config: &mut Config, args: &ArgMatches<'_>, rest: Vec<&str>) -> CliResult {
+    let plugin = format!("cargo-vendor{}", env::consts::EXE_SUFFIX);
+    let installed = crate::search_directories(config)
+        .iter()
+        .any(|dir| crate::is_executable(dir.join(&plugin)));
+    let help = rest.iter().any(|arg| *arg == "--help" || *arg == "-h");
+    if help {
+        // What `--verify` does, before the help of the plugin.
+        cli().print_help()?;
+        println!();
+        if !installed {
+            return Ok(());
+        }
+        println!("\nThe help of `cargo-vendor` follows:\n");
+        io::stdout().flush().map_err(failure::Error::from)?;
+    }
+    if !installed {
+        return Err(format_err!(
+            "vendoring is done by the `cargo-vendor` plugin, which isn't installed\n\
+             Install it with `cargo install cargo-vendor`, or pass `--verify` \
+             to check vendored sources"
+        )
+        .into());
+    }
+
+    let mut ext_args = vec!["vendor".to_string()];
+    for _ in 0..args.occurrences_of("verbose") {
+        ext_args.push("-v".to_string());
+    }
+    if args.is_present("quiet") {
+        ext_args.push("-q".to_string());
+    }
+    if let Some(color) = args.value_of("color") {
+        ext_args.push("--color".to_string());
+        ext_args.push(color.to_string());
+    }
+    for flag in &["frozen", "locked", "offline"] {
+        if args.is_present(flag) {
+            ext_args.push(format!("--{}", flag));
+        }
+    }
+    for flag in args.values_of("unstable-features").unwrap_or_default() {
+        ext_args.push("-Z".to_string());
+        ext_args.push(flag.to_string());
+    }
+    if let Some(path) = args.value_of("manifest-path") {
+        ext_args.push("--manifest-path".to_string());
+        ext_args.push(path.to_string());
+    }
+    ext_args.extend(rest.iter().map(|s| s.to_string()));
+    let ext_args = ext_args.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    crate::execute_external_subcommand(config, "vendor", &ext_args)
+}
@@ -0,0 +1,39 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, VerifyCrateOptions};
+
+pub fn cli() -> App {
+    subcommand("verify-crate")
+        .about("Verify a published crate against its checksum and its repository")
+        .arg(Arg::with_name("crate").required(true).help("The crate to verify, like `foo@1.2.3`"))
+        .arg(opt("registry", "Registry to download the crate from").value_name("REGISTRY"))
+        .arg(opt(
+            "against-repo",
+            "Compare the crate with the source in its repository",
+        ))
+        .after_help(
+            "\
+The crate is downloaded from the registry, and its checksum verified, like the
+dependencies of a package are.
+
+With `--against-repo`, the `repository` declared by the crate is cloned, and
+the package of the same name is read from the tag of the version, the first
+of `v1.2.3`, `1.2.3`, `foo-v1.2.3` and `foo-1.2.3` which exists, or from the
+commit the crate records it was packaged from. Every file of the crate must
+be one `cargo package` includes from there, with the same contents, except
+for the files `cargo package` generates, and `Cargo.toml.orig` is compared
+with `Cargo.toml`.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let opts = VerifyCrateOptions {
+        config,
+        spec: args.value_of("crate").unwrap().to_string(),
+        registry: args.registry(config)?,
+        against_repo: args.is_present("against-repo"),
+    };
+    ops::verify_crate(&opts)?;
+    Ok(())
+}
@@ -37,6 +37,15 @@ fn main() {
     env_logger::init();
     cargo::core::maybe_allow_nightly_features();
 
+    // Started before the configuration is loaded, so that loading it is in
+    // the trace of `cargo --self-profile` too, which `cli::main` writes.
+    if env::args_os()
+        .take_while(|arg| arg != "--")
+        .any(|arg| arg == "--self-profile")
+    {
+        util::profile::start_self_profile();
+    }
+
     let mut config = match Config::default() {
         Ok(cfg) => cfg,
         Err(e) => {
@@ -0,0 +1,97 @@
+//! The manifest of the artifacts of a build, with `build.artifact-manifest`.
+//!
+//! Build systems packaging the libraries built by Cargo, like Gradle for
+//! Android or Xcode for iOS, need to know which files were built for which
+//! architecture. The manifest lists them for each of the targets, along with
+//! the packaging metadata configured for the target, and is written to
+//! `artifacts.json` in the directory of the profile, like
+//! `target/debug/artifacts.json`.
+
+use std::path::PathBuf;
+
+use serde_json;
+
+use super::{CompileMode, Context, FileFlavor, Kind, Unit};
+use crate::core::{PackageId, Target};
+use crate::util::{paths, CargoResult, Cfg};
+
+/// The version of the format of the manifest, bumped on incompatible changes.
+const VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct ArtifactManifest<'a> {
+    version: u32,
+    profile: &'a str,
+    targets: Vec<TargetArtifacts<'a>>,
+}
+
+#[derive(Serialize)]
+struct TargetArtifacts<'a> {
+    triple: &'a str,
+    arch: Option<&'a str>,
+    os: Option<&'a str>,
+    abi: Option<&'a str>,
+    min_sdk: Option<&'a str>,
+    artifacts: Vec<ManifestArtifact<'a>>,
+}
+
+#[derive(Serialize)]
+struct ManifestArtifact<'a> {
+    package_id: PackageId,
+    target: &'a Target,
+    filenames: Vec<PathBuf>,
+}
+
+/// Writes the manifest of the artifacts of `units`, the units requested on
+/// the command line, if `build.artifact-manifest` is set.
+pub fn write<'a>(cx: &mut Context<'a, '_>, units: &[Unit<'a>]) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    if !bcx.build_config.artifact_manifest || bcx.build_config.build_plan {
+        return Ok(());
+    }
+    let mut targets = Vec::new();
+    for kind in bcx.build_config.target_kinds() {
+        let cfg = bcx.cfg(kind);
+        let (min_sdk, abi) = bcx.packaging(kind);
+        let mut artifacts = Vec::new();
+        for unit in units.iter().filter(|u| u.kind == kind && u.mode == CompileMode::Build) {
+            let filenames = cx
+                .outputs(unit)?
+                .iter()
+                .filter(|output| match output.flavor {
+                    FileFlavor::Normal | FileFlavor::Linkable { rmeta: false } => true,
+                    _ => false,
+                })
+                .map(|output| output.hardlink.as_ref().unwrap_or(&output.path).clone())
+                .collect();
+            artifacts.push(ManifestArtifact {
+                package_id: unit.pkg.package_id(),
+                target: unit.target,
+                filenames,
+            });
+        }
+        targets.push(TargetArtifacts {
+            triple: bcx.triple(kind),
+            arch: cfg_value(cfg, "target_arch"),
+            os: cfg_value(cfg, "target_os"),
+            abi,
+            min_sdk,
+            artifacts,
+        });
+    }
+    let manifest = ArtifactManifest {
+        version: VERSION,
+        profile: bcx.build_config.profile_kind.dest(),
+        targets,
+    };
+    let path = cx.files().layout(Kind::Host).dest().join("artifacts.json");
+    let json = serde_json::to_string_pretty(&manifest)?;
+    paths::write(&path, json.as_bytes())
+}
+
+fn cfg_value<'a>(cfg: &'a [Cfg], key: &str) -> Option<&'a str> {
+    cfg.iter().find_map(|cfg| match cfg {
+        Cfg::KeyPair(k, v) if k == key => Some(v.as_str()),
+        _ => None,
+    })
+}
@@ -0,0 +1,386 @@
+//! Attribution of the size of binaries to the crates they're built from, for
+//! `cargo report bloat`.
+//!
+//! Like the other reports this doesn't compile anything, it reads the symbol
+//! table of the binaries the last build left in the target directory with
+//! `nm`. The symbols are demangled and attributed to the crate their path
+//! starts with, which is mapped back to the packages in the unit graph of the
+//! binary. Functions which were instantiated more than once, generics, are
+//! reported separately as they tend to be where the size comes from.
+//!
+//! Binaries without a symbol table, such as stripped ones, or platforms where
+//! `nm` isn't available only get their file size reported.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+
+use crate::core::PackageId;
+use crate::util::{process, CargoResult};
+
+use super::{Context, FileFlavor, Unit};
+
+/// The name symbols which can't be attributed to a crate are reported under.
+const UNKNOWN_CRATE: &str = "[unknown]";
+
+/// The report produced by `cargo report bloat`.
+#[derive(Debug, Serialize)]
+pub struct BloatReport {
+    pub binaries: Vec<BinaryBloat>,
+}
+
+/// The size of a single binary, broken down by crate.
+#[derive(Debug, Serialize)]
+pub struct BinaryBloat {
+    pub package_id: PackageId,
+    pub target: String,
+    /// The size of the binary on disk, or `None` if it hasn't been built yet.
+    pub file_size: Option<u64>,
+    /// The sizes of the crates the symbols of the binary belong to, largest
+    /// first, or `None` if the binary has no symbol table which can be read.
+    pub crates: Option<Vec<CrateBloat>>,
+    /// The functions which were instantiated more than once, largest first.
+    pub generics: Option<Vec<GenericBloat>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrateBloat {
+    pub name: String,
+    /// The packages in the unit graph of the binary with a library of this
+    /// name. Empty for crates which don't come from a package, like `std`.
+    pub package_ids: Vec<PackageId>,
+    pub size: u64,
+    pub symbols: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GenericBloat {
+    pub name: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub instantiations: usize,
+    pub size: u64,
+}
+
+struct Symbol {
+    name: String,
+    krate: String,
+    size: u64,
+}
+
+pub fn bloat_report<'a, 'cfg>(
+    cx: &mut Context<'a, 'cfg>,
+    units: &[Unit<'a>],
+) -> CargoResult<BloatReport> {
+    let mut binaries = Vec::new();
+    for unit in units {
+        let is_binary = unit.target.is_bin() || unit.target.is_bin_example();
+        if !is_binary || unit.mode.is_any_test() || unit.mode.is_check() {
+            continue;
+        }
+        let outputs = cx.outputs(unit)?;
+        let output = match outputs.iter().find(|o| o.flavor == FileFlavor::Normal) {
+            Some(output) => output,
+            None => continue,
+        };
+        let file_size = fs::metadata(&output.path).ok().map(|m| m.len());
+
+        let mut crate_packages = HashMap::new();
+        let mut visited = HashSet::new();
+        collect_crates(cx, unit, &mut visited, &mut crate_packages);
+
+        let symbols = match file_size {
+            Some(_) => read_symbols(&output.path.display().to_string(), &crate_packages),
+            None => None,
+        };
+        let (crates, generics) = match symbols {
+            Some(symbols) => (
+                Some(crate_sizes(&symbols, &crate_packages)),
+                Some(generic_sizes(&symbols)),
+            ),
+            None => (None, None),
+        };
+        binaries.push(BinaryBloat {
+            package_id: unit.pkg.package_id(),
+            target: unit.target.name().to_string(),
+            file_size,
+            crates,
+            generics,
+        });
+    }
+    Ok(BloatReport { binaries })
+}
+
+/// Maps the crate names in the unit graph of `unit` to the packages they
+/// come from.
+fn collect_crates<'a, 'cfg>(
+    cx: &Context<'a, 'cfg>,
+    unit: &Unit<'a>,
+    visited: &mut HashSet<Unit<'a>>,
+    ret: &mut HashMap<String, BTreeSet<PackageId>>,
+) {
+    if !visited.insert(*unit) {
+        return;
+    }
+    if !unit.mode.is_run_custom_build() && !unit.target.is_custom_build() {
+        ret.entry(unit.target.crate_name())
+            .or_default()
+            .insert(unit.pkg.package_id());
+    }
+    for dep in cx.dep_targets(unit) {
+        // Build scripts and their dependencies don't end up in the binary.
+        if dep.target.is_custom_build() {
+            continue;
+        }
+        collect_crates(cx, &dep, visited, ret);
+    }
+}
+
+/// Reads the defined symbols of the binary at `path`, returning `None` if
+/// there's no symbol table to read.
+fn read_symbols(
+    path: &str,
+    crate_packages: &HashMap<String, BTreeSet<PackageId>>,
+) -> Option<Vec<Symbol>> {
+    let output = process("nm")
+        .arg("--defined-only")
+        .arg("--print-size")
+        .arg(path)
+        .exec_with_output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut symbols = Vec::new();
+    for line in stdout.lines() {
+        // Symbols with a size look like `<address> <size> <type> <name>`.
+        let fields = line.splitn(4, ' ').collect::<Vec<_>>();
+        if fields.len() != 4 {
+            continue;
+        }
+        let size = match u64::from_str_radix(fields[1], 16) {
+            Ok(size) => size,
+            Err(..) => continue,
+        };
+        // Uninitialized data takes no room in the file.
+        if fields[2] == "b" || fields[2] == "B" {
+            continue;
+        }
+        let (name, krate) = match demangle(fields[3]) {
+            Some(name) => {
+                let krate = symbol_crate(&name, crate_packages);
+                (name, krate)
+            }
+            None => (
+                fields[3].to_string(),
+                v0_symbol_crate(fields[3], crate_packages),
+            ),
+        };
+        symbols.push(Symbol { name, krate, size });
+    }
+    if symbols.is_empty() {
+        None
+    } else {
+        Some(symbols)
+    }
+}
+
+fn crate_sizes(
+    symbols: &[Symbol],
+    crate_packages: &HashMap<String, BTreeSet<PackageId>>,
+) -> Vec<CrateBloat> {
+    let mut sizes = HashMap::new();
+    for symbol in symbols {
+        let entry = sizes.entry(&symbol.krate[..]).or_insert((0, 0));
+        entry.0 += symbol.size;
+        entry.1 += 1;
+    }
+    let mut crates = sizes
+        .into_iter()
+        .map(|(name, (size, symbols))| CrateBloat {
+            name: name.to_string(),
+            package_ids: crate_packages
+                .get(name)
+                .map(|ids| ids.iter().cloned().collect())
+                .unwrap_or_default(),
+            size,
+            symbols,
+        })
+        .collect::<Vec<_>>();
+    crates.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    crates
+}
+
+fn generic_sizes(symbols: &[Symbol]) -> Vec<GenericBloat> {
+    let mut sizes = HashMap::new();
+    for symbol in symbols {
+        let entry = sizes
+            .entry(&symbol.name[..])
+            .or_insert((&symbol.krate[..], 0, 0));
+        entry.1 += 1;
+        entry.2 += symbol.size;
+    }
+    let mut generics = sizes
+        .into_iter()
+        .filter(|&(_, (_, instantiations, _))| instantiations > 1)
+        .map(|(name, (krate, instantiations, size))| GenericBloat {
+            name: name.to_string(),
+            krate: krate.to_string(),
+            instantiations,
+            size,
+        })
+        .collect::<Vec<_>>();
+    generics.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    generics
+}
+
+/// Picks the crate a demangled symbol belongs to.
+///
+/// For trait implementations like `<foo::Bar as core::fmt::Debug>::fmt`
+/// this is the first crate of the unit graph which is mentioned, the crate
+/// of the implementing type here, otherwise the first crate mentioned.
+fn symbol_crate(name: &str, crate_packages: &HashMap<String, BTreeSet<PackageId>>) -> String {
+    let mut first = None;
+    let mut start = None;
+    for (i, c) in name.char_indices() {
+        if c.is_alphanumeric() || c == '_' {
+            if start.is_none() {
+                start = Some(i);
+            }
+            continue;
+        }
+        if let Some(s) = start.take() {
+            if name[i..].starts_with("::") && !name[..s].ends_with("::") {
+                let krate = &name[s..i];
+                if crate_packages.contains_key(krate) {
+                    return krate.to_string();
+                }
+                first = first.or(Some(krate));
+            }
+        }
+    }
+    first.unwrap_or(UNKNOWN_CRATE).to_string()
+}
+
+/// Picks the crate a symbol mangled with the `v0` mangling scheme belongs
+/// to, the same way as `symbol_crate` does, without demangling it.
+///
+/// Crate roots are encoded as `C`, an optional disambiguator `s<base-62>_`
+/// and the length prefixed crate name. This may be fooled by identifiers
+/// which happen to look like crate roots, good enough to break sizes down.
+fn v0_symbol_crate(symbol: &str, crate_packages: &HashMap<String, BTreeSet<PackageId>>) -> String {
+    let rest = symbol.trim_start_matches('_');
+    if !rest.starts_with('R') {
+        return UNKNOWN_CRATE.to_string();
+    }
+    let mut first = None;
+    let mut rest = &rest[1..];
+    while let Some(i) = rest.find('C') {
+        rest = &rest[i + 1..];
+        let mut root = rest;
+        if root.starts_with('s') {
+            root = match root.find('_') {
+                Some(end) if root[1..end].chars().all(|c| c.is_ascii_alphanumeric()) => {
+                    &root[end + 1..]
+                }
+                _ => continue,
+            };
+        }
+        let digits = root
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(root.len());
+        let len = match root[..digits].parse::<usize>() {
+            Ok(len) => len,
+            Err(..) => continue,
+        };
+        // Names starting with an underscore or a digit are separated from
+        // their length by an underscore.
+        let root = &root[digits..];
+        let root = root.strip_prefix('_').unwrap_or(root);
+        if root.len() < len {
+            continue;
+        }
+        let krate = &root[..len];
+        if krate.is_empty() || !krate.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        if crate_packages.contains_key(krate) {
+            return krate.to_string();
+        }
+        first = first.or(Some(krate));
+    }
+    first.unwrap_or(UNKNOWN_CRATE).to_string()
+}
+
+/// Demangles a symbol mangled with the legacy Rust mangling scheme into its
+/// path, without the trailing hash. Returns `None` for symbols which aren't
+/// mangled that way, like C symbols.
+fn demangle(symbol: &str) -> Option<String> {
+    // macOS prefixes every symbol with one more underscore.
+    let mut rest = symbol.trim_start_matches('_');
+    let underscores = symbol.len() - rest.len();
+    if !rest.starts_with("ZN") || underscores == 0 || underscores > 2 {
+        return None;
+    }
+    rest = &rest[2..];
+    let mut segments = Vec::new();
+    while !rest.starts_with('E') {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let len = rest[..digits].parse::<usize>().ok()?;
+        rest = &rest[digits..];
+        if rest.len() < len {
+            return None;
+        }
+        segments.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    if let Some(last) = segments.last() {
+        let is_hash = last.len() == 17
+            && last.starts_with('h')
+            && last[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if is_hash {
+            segments.pop();
+        }
+    }
+    let segments = segments
+        .iter()
+        .map(|s| unescape(s))
+        .collect::<Option<Vec<_>>>()?;
+    Some(segments.join("::"))
+}
+
+fn unescape(segment: &str) -> Option<String> {
+    let mut s = segment;
+    if s.starts_with("_$") {
+        s = &s[1..];
+    }
+    let mut ret = String::new();
+    while !s.is_empty() {
+        if s.starts_with("..") {
+            ret.push_str("::");
+            s = &s[2..];
+        } else if s.starts_with('$') {
+            let end = s[1..].find('$')? + 1;
+            let escape = &s[1..end];
+            let c = match escape {
+                "SP" => '@',
+                "BP" => '*',
+                "RF" => '&',
+                "LT" => '<',
+                "GT" => '>',
+                "LP" => '(',
+                "RP" => ')',
+                "C" => ',',
+                _ if escape.starts_with('u') => {
+                    let code = u32::from_str_radix(&escape[1..], 16).ok()?;
+                    ::std::char::from_u32(code)?
+                }
+                _ => return None,
+            };
+            ret.push(c);
+            s = &s[end + 1..];
+        } else {
+            let c = s.chars().next().unwrap();
+            ret.push(c);
+            s = &s[c.len_utf8()..];
+        }
+    }
+    Some(ret)
+}
@@ -0,0 +1,239 @@
+//! Auditing what build scripts do, with `build.audit-build-scripts`.
+//!
+//! Build scripts are run under `strace` where it's available (only on Linux
+//! for now), which records the files they and their child processes open,
+//! change and execute, and the network connections they attempt. An `audit`
+//! file listing those is written next to the `output` of each script run,
+//! like `target/debug/build/foo-<hash>/audit`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::PackageId;
+use crate::util::{self, paths, CargoResult, ProcessBuilder};
+
+/// The system calls which change the files named in their arguments.
+const WRITE_CALLS: &[&str] = &[
+    "creat",
+    "rename",
+    "renameat",
+    "renameat2",
+    "unlink",
+    "unlinkat",
+    "mkdir",
+    "mkdirat",
+    "rmdir",
+    "link",
+    "linkat",
+    "symlink",
+    "symlinkat",
+    "chmod",
+    "fchmodat",
+    "truncate",
+];
+
+/// Finds a tracer which works in this environment, `None` if build scripts
+/// can't be audited.
+pub fn find_tracer() -> Option<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    // Tracing may be forbidden, like in some containers, which only shows
+    // when trying it.
+    let strace = PathBuf::from("strace");
+    let works = util::process(&strace)
+        .args(&["-f", "-qq", "-o", "/dev/null", "true"])
+        .exec_with_output()
+        .is_ok();
+    if works {
+        Some(strace)
+    } else {
+        None
+    }
+}
+
+/// Wraps the build script command `cmd` to run it under `tracer`, writing
+/// the trace of each process to a file in `trace_dir`.
+pub fn traced(cmd: &ProcessBuilder, tracer: &Path, trace_dir: &Path) -> ProcessBuilder {
+    let mut traced = cmd.clone();
+    let mut args = vec![
+        "-ff".into(),
+        "-qq".into(),
+        "-s".into(),
+        "4096".into(),
+        "-e".into(),
+        "trace=file,connect".into(),
+        "-o".into(),
+        trace_dir.join("trace").into_os_string(),
+        "--".into(),
+        cmd.get_program().clone(),
+    ];
+    args.extend(cmd.get_args().iter().cloned());
+    traced.program(tracer).args_replace(&args);
+    traced
+}
+
+/// Turns the traces in `trace_dir` into the audit log at `audit_file`, and
+/// removes them.
+pub fn write_audit_log(id: PackageId, trace_dir: &Path, audit_file: &Path) -> CargoResult<()> {
+    let mut accesses = BTreeSet::new();
+    for entry in fs::read_dir(trace_dir)? {
+        let path = entry?.path();
+        for line in paths::read(&path)?.lines() {
+            accesses.extend(parse_trace_line(line));
+        }
+    }
+    let mut log = format!("# build script of `{}`\n", id);
+    for (access, target) in accesses {
+        log.push_str(&format!("{} {}\n", access, target));
+    }
+    paths::write(audit_file, log.as_bytes())?;
+    paths::remove_dir_all(trace_dir)
+}
+
+/// Parses a system call recorded by `strace`, like
+/// `openat(AT_FDCWD, "src/foo.rs", O_RDONLY|O_CLOEXEC) = 3`, into the
+/// accesses it made.
+///
+/// Failed file accesses are left out, as there are lots of them when
+/// programs search for files, but failed connections are kept, as they're
+/// still attempts to use the network.
+fn parse_trace_line(line: &str) -> Vec<(&'static str, String)> {
+    let open = match line.find('(') {
+        Some(open) => open,
+        None => return Vec::new(),
+    };
+    let call = &line[..open];
+    let (args, result) = match line.rfind(") = ") {
+        Some(close) => (&line[open + 1..close], &line[close + 4..]),
+        None => return Vec::new(),
+    };
+    let failed = result.starts_with('-');
+
+    if call == "connect" {
+        return parse_sockaddr(args)
+            .map(|addr| vec![("connect", addr)])
+            .unwrap_or_default();
+    }
+    if failed {
+        return Vec::new();
+    }
+    let strings = quoted_strings(args);
+    match call {
+        "open" | "openat" => {
+            let writes = ["O_WRONLY", "O_RDWR", "O_CREAT", "O_TRUNC"]
+                .iter()
+                .any(|flag| args.contains(flag));
+            let access = if writes { "write" } else { "read" };
+            strings.into_iter().take(1).map(|s| (access, s)).collect()
+        }
+        "execve" => strings.into_iter().take(1).map(|s| ("exec", s)).collect(),
+        _ if WRITE_CALLS.contains(&call) => strings.into_iter().map(|s| ("write", s)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Formats the address `connect` was called with.
+fn parse_sockaddr(args: &str) -> Option<String> {
+    let field = |name: &str| -> Option<&str> {
+        let start = args.find(name)? + name.len();
+        let end = args[start..].find(|c| c == ',' || c == '}')? + start;
+        Some(&args[start..end])
+    };
+    let family = field("sa_family=")?;
+    match family {
+        "AF_INET" => {
+            let port = field("sin_port=htons(")?.trim_end_matches(')');
+            let addr = quoted_strings(field("sin_addr=")?).pop()?;
+            Some(format!("{}:{}", addr, port))
+        }
+        "AF_INET6" => {
+            let port = field("sin6_port=htons(")?.trim_end_matches(')');
+            let start = args.find("inet_pton(AF_INET6, ")?;
+            let addr = quoted_strings(&args[start..]).into_iter().next()?;
+            Some(format!("[{}]:{}", addr, port))
+        }
+        "AF_UNIX" => {
+            let start = args.find("sun_path=")?;
+            let path = quoted_strings(&args[start..]).into_iter().next()?;
+            Some(format!("unix:{}", path))
+        }
+        _ => Some(family.to_string()),
+    }
+}
+
+/// The strings quoted in the arguments of a system call, with the escapes
+/// of quotes and backslashes undone.
+fn quoted_strings(args: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut current = None;
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        match (c, current.as_mut()) {
+            ('"', None) => current = Some(String::new()),
+            ('"', Some(_)) => strings.extend(current.take()),
+            ('\\', Some(s)) => s.extend(chars.next()),
+            (c, Some(s)) => s.push(c),
+            (_, None) => {}
+        }
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_trace_line;
+
+    #[test]
+    fn files() {
+        assert_eq!(
+            parse_trace_line(r#"openat(AT_FDCWD, "src/foo.rs", O_RDONLY|O_CLOEXEC) = 3"#),
+            vec![("read", "src/foo.rs".to_string())]
+        );
+        assert_eq!(
+            parse_trace_line(
+                r#"openat(AT_FDCWD, "/out/a \"b\".rs", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 4"#
+            ),
+            vec![("write", "/out/a \"b\".rs".to_string())]
+        );
+        assert_eq!(
+            parse_trace_line(r#"rename("/out/tmp", "/out/lib.a") = 0"#),
+            vec![
+                ("write", "/out/tmp".to_string()),
+                ("write", "/out/lib.a".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_trace_line(r#"execve("/usr/bin/cc", ["cc", "-c"], 0x7ffd /* 42 vars */) = 0"#),
+            vec![("exec", "/usr/bin/cc".to_string())]
+        );
+        assert!(parse_trace_line(
+            r#"openat(AT_FDCWD, "/nope", O_RDONLY) = -1 ENOENT (No such file or directory)"#
+        )
+        .is_empty());
+        assert!(parse_trace_line(r#"stat("/etc", {st_mode=S_IFDIR|0755, ...}) = 0"#).is_empty());
+    }
+
+    #[test]
+    fn connections() {
+        assert_eq!(
+            parse_trace_line(
+                r#"connect(3, {sa_family=AF_INET, sin_port=htons(443), sin_addr=inet_addr("93.184.216.34")}, 16) = -1 EINPROGRESS (Operation now in progress)"#
+            ),
+            vec![("connect", "93.184.216.34:443".to_string())]
+        );
+        assert_eq!(
+            parse_trace_line(
+                r#"connect(3, {sa_family=AF_INET6, sin6_port=htons(80), sin6_flowinfo=htonl(0), inet_pton(AF_INET6, "::1", &sin6_addr), sin6_scope_id=0}, 28) = 0"#
+            ),
+            vec![("connect", "[::1]:80".to_string())]
+        );
+        assert_eq!(
+            parse_trace_line(
+                r#"connect(4, {sa_family=AF_UNIX, sun_path="/var/run/nscd/socket"}, 110) = -1 ENOENT (No such file or directory)"#
+            ),
+            vec![("connect", "unix:/var/run/nscd/socket".to_string())]
+        );
+    }
+}
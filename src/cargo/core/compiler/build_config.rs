@@ -1,19 +1,27 @@
 use std::cell::RefCell;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 use serde::ser;
 
+use crate::core::interning::InternedString;
+use crate::core::PackageIdSpec;
 use crate::util::{CargoResult, CargoResultExt, Config, RustfixDiagnosticServer};
 
+use super::{BuildEnv, KeepArtifacts, Kind, ProcMacroSandbox};
+
 /// Configuration information for a rustc build.
 #[derive(Debug)]
 pub struct BuildConfig {
-    /// The target arch triple, defaults to host arch
-    pub requested_target: Option<String>,
+    /// The target arch triples, defaults to host arch
+    pub requested_targets: Vec<String>,
     /// How many rustc jobs to run in parallel
     pub jobs: u32,
-    /// Whether we are building for release
-    pub release: bool,
+    /// The profile to build with, `dev` unless `--release` or `--profile`
+    /// was passed
+    pub profile_kind: ProfileKind,
     /// In what mode we are compiling
     pub mode: CompileMode,
     /// Whether to print std output in json format (for machine reading)
@@ -29,6 +37,51 @@ pub struct BuildConfig {
     /// Extra args to inject into rustc commands
     pub extra_rustc_args: Vec<String>,
     pub rustfix_diagnostic_server: RefCell<Option<RustfixDiagnosticServer>>,
+    /// Units which take longer than this to build are reported at the end of
+    /// the build
+    pub slow_unit_budget: Option<Duration>,
+    /// Start compiling dependent libraries as soon as the metadata of their
+    /// dependencies is available, rather than waiting for the full rlib
+    pub pipelining: bool,
+    /// What to do with the warnings rustc emits for workspace members
+    pub warnings: WarningHandling,
+    /// Run build scripts under a tracer, recording the files and network
+    /// connections they use
+    pub audit_build_scripts: bool,
+    /// Write a manifest of the artifacts built for each target, for other
+    /// build systems to consume
+    pub artifact_manifest: bool,
+    /// Extra files rustc writes for the packages selected on the command
+    /// line, collected in the `emit` directory of the profile
+    pub emit: Vec<EmitKind>,
+    /// Only build these crate types of the libraries of the packages
+    /// selected on the command line, instead of all the ones they declare
+    pub crate_types: Vec<String>,
+    /// The CPU to generate code for, `native` for the CPU of this machine
+    pub target_cpu: Option<String>,
+    /// Combine the binaries and dynamic or static libraries built for each
+    /// of the Apple targets into universal ones with `lipo`
+    pub lipo: bool,
+    /// Copy the final artifacts of the packages selected, with their README
+    /// and licenses, to a directory of each package in this directory
+    pub dist_dir: Option<PathBuf>,
+    /// Also write the output of each unit to a log file of its own in this
+    /// directory, relative to the target directory
+    pub log_dir: Option<PathBuf>,
+    /// Only build the units which failed in the previous build, and their
+    /// dirty dependencies
+    pub repeat_failed: bool,
+    /// Only build this dependency of the packages selected, the way it's
+    /// built for them
+    pub dependency: Option<PackageIdSpec>,
+    /// Compile proc-macros, and the crates using them, in a restricted
+    /// environment
+    pub proc_macro_sandbox: Option<ProcMacroSandbox>,
+    /// The environment variables removed from, or the only ones passed on
+    /// to, rustc, rustdoc and build scripts
+    pub build_env: BuildEnv,
+    /// The artifacts of unused units to remove at the end of the build
+    pub keep_artifacts: Option<KeepArtifacts>,
 }
 
 impl BuildConfig {
@@ -36,36 +89,52 @@ impl BuildConfig {
     /// configured options are:
     ///
     /// * build.jobs
+    /// * build.artifact-manifest
+    /// * build.audit-build-scripts
+    /// * build.env-allowlist
+    /// * build.env-scrub
+    /// * build.keep-artifacts
+    /// * build.log-dir
+    /// * build.pipelining
+    /// * build.proc-macro-sandbox
     /// * build.target
+    /// * build.warn-slow-units
+    /// * build.warnings
+    /// * target-groups.$name
     /// * target.$target.ar
     /// * target.$target.linker
     /// * target.$target.libfoo.metadata
     pub fn new(
         config: &Config,
         jobs: Option<u32>,
-        requested_target: &Option<String>,
+        requested_targets: &[String],
         mode: CompileMode,
     ) -> CargoResult<BuildConfig> {
-        let requested_target = match requested_target {
-            &Some(ref target) if target.ends_with(".json") => {
+        let requested_targets = expand_target_groups(config, requested_targets)?;
+        let mut targets = Vec::new();
+        for target in requested_targets.iter() {
+            let target = if target.ends_with(".json") {
                 let path = Path::new(target)
                     .canonicalize()
                     .chain_err(|| format_err!("Target path {:?} is not a valid file", target))?;
-                Some(
-                    path.into_os_string()
-                        .into_string()
-                        .map_err(|_| format_err!("Target path is not valid unicode"))?,
-                )
-            }
-            other => other.clone(),
-        };
-        if let Some(ref s) = requested_target {
-            if s.trim().is_empty() {
+                path.into_os_string()
+                    .into_string()
+                    .map_err(|_| format_err!("Target path is not valid unicode"))?
+            } else {
+                target.clone()
+            };
+            if target.trim().is_empty() {
                 bail!("target was empty")
             }
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        if targets.is_empty() {
+            if let Some(cfg_target) = config.get_string("build.target")? {
+                targets.extend(expand_target_groups(config, &[cfg_target.val])?);
+            }
         }
-        let cfg_target = config.get_string("build.target")?.map(|s| s.val);
-        let target = requested_target.clone().or(cfg_target);
 
         if jobs == Some(0) {
             bail!("jobs must be at least 1")
@@ -79,10 +148,43 @@ impl BuildConfig {
         }
         let cfg_jobs: Option<u32> = config.get("build.jobs")?;
         let jobs = jobs.or(cfg_jobs).unwrap_or(::num_cpus::get() as u32);
+        let slow_unit_budget = match config.get_string("build.warn-slow-units")? {
+            Some(value) => match parse_duration(&value.val) {
+                Some(budget) => Some(budget),
+                None => bail!(
+                    "invalid configuration for key `build.warn-slow-units`, \
+                     expected a duration like `60s` or `2m`, found `{}` \
+                     (found in {})",
+                    value.val,
+                    value.definition
+                ),
+            },
+            None => None,
+        };
+        let pipelining: Option<bool> = config.get("build.pipelining")?;
+        let audit_build_scripts: Option<bool> = config.get("build.audit-build-scripts")?;
+        let artifact_manifest: Option<bool> = config.get("build.artifact-manifest")?;
+        let log_dir = config
+            .get_string("build.log-dir")?
+            .map(|dir| PathBuf::from(dir.val));
+        let warnings = match config.get_string("build.warnings")? {
+            Some(value) => match &value.val[..] {
+                "warn" => WarningHandling::Warn,
+                "allow" => WarningHandling::Allow,
+                "deny" => WarningHandling::Deny,
+                _ => bail!(
+                    "invalid configuration for key `build.warnings`, expected \
+                     `warn`, `allow` or `deny`, found `{}` (found in {})",
+                    value.val,
+                    value.definition
+                ),
+            },
+            None => WarningHandling::Warn,
+        };
         Ok(BuildConfig {
-            requested_target: target,
+            requested_targets: targets,
             jobs,
-            release: false,
+            profile_kind: ProfileKind::Dev,
             mode,
             message_format: MessageFormat::Human,
             force_rebuild: false,
@@ -91,9 +193,37 @@ impl BuildConfig {
             extra_rustc_env: Vec::new(),
             extra_rustc_args: Vec::new(),
             rustfix_diagnostic_server: RefCell::new(None),
+            slow_unit_budget,
+            pipelining: pipelining.unwrap_or(false),
+            warnings,
+            audit_build_scripts: audit_build_scripts.unwrap_or(false),
+            artifact_manifest: artifact_manifest.unwrap_or(false),
+            emit: Vec::new(),
+            crate_types: Vec::new(),
+            target_cpu: None,
+            lipo: false,
+            dist_dir: None,
+            log_dir,
+            repeat_failed: false,
+            dependency: None,
+            proc_macro_sandbox: ProcMacroSandbox::from_config(config)?,
+            build_env: BuildEnv::from_config(config)?,
+            keep_artifacts: KeepArtifacts::from_config(config)?,
         })
     }
 
+    /// The kinds the units requested on the command line are compiled for,
+    /// one for each requested target.
+    pub fn target_kinds(&self) -> Vec<Kind> {
+        if self.requested_targets.is_empty() {
+            return vec![Kind::Target(None)];
+        }
+        self.requested_targets
+            .iter()
+            .map(|t| Kind::Target(Some(InternedString::new(t))))
+            .collect()
+    }
+
     pub fn json_messages(&self) -> bool {
         self.message_format == MessageFormat::Json
     }
@@ -103,6 +233,141 @@ impl BuildConfig {
     }
 }
 
+/// Replaces the groups of targets defined in `[target-groups]` among
+/// `targets`, given as `@name`, with the targets they list, which may be
+/// other groups.
+fn expand_target_groups(config: &Config, targets: &[String]) -> CargoResult<Vec<String>> {
+    if !targets.iter().any(|t| t.starts_with('@')) {
+        return Ok(targets.to_vec());
+    }
+    let groups = config
+        .get::<Option<BTreeMap<String, Vec<String>>>>("target-groups")?
+        .unwrap_or_default();
+    let mut expanded = Vec::new();
+    for target in targets {
+        expand_target_group(&groups, target, &mut Vec::new(), &mut expanded)?;
+        if target.starts_with('@') && expanded.len() > 1 && !config.cli_unstable().multitarget {
+            bail!(
+                "`{}` builds for several targets, which is unstable, pass \
+                 `-Z multitarget` to enable it",
+                target
+            );
+        }
+    }
+    Ok(expanded)
+}
+
+fn expand_target_group(
+    groups: &BTreeMap<String, Vec<String>>,
+    target: &str,
+    stack: &mut Vec<String>,
+    expanded: &mut Vec<String>,
+) -> CargoResult<()> {
+    if !target.starts_with('@') {
+        if !expanded.iter().any(|t| t == target) {
+            expanded.push(target.to_string());
+        }
+        return Ok(());
+    }
+    let name = &target[1..];
+    if stack.iter().any(|n| n == name) {
+        bail!(
+            "target group `@{}` includes itself: {} -> @{}",
+            name,
+            stack
+                .iter()
+                .map(|n| format!("@{}", n))
+                .collect::<Vec<_>>()
+                .join(" -> "),
+            name
+        );
+    }
+    let group = match groups.get(name) {
+        Some(group) if group.is_empty() => bail!("target group `@{}` is empty", name),
+        Some(group) => group,
+        None if groups.is_empty() => bail!(
+            "no target group named `@{}`, there are none in `[target-groups]`",
+            name
+        ),
+        None => bail!(
+            "no target group named `@{}`, the groups are: {}",
+            name,
+            groups
+                .keys()
+                .map(|n| format!("`@{}`", n))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    stack.push(name.to_string());
+    for target in group {
+        expand_target_group(groups, target, stack, expanded)?;
+    }
+    stack.pop();
+    Ok(())
+}
+
+/// Parses durations like `500ms`, `60s`, `2m`, `1h` or `30d`, a plain number
+/// is a number of seconds.
+pub(super) fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => (&s[..i], &s[i..]),
+        None => (s, "s"),
+    };
+    let number = number.parse::<u64>().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(number)),
+        "s" => Some(Duration::from_secs(number)),
+        "m" => Some(Duration::from_secs(number * 60)),
+        "h" => Some(Duration::from_secs(number * 60 * 60)),
+        "d" => Some(Duration::from_secs(number * 60 * 60 * 24)),
+        _ => None,
+    }
+}
+
+/// The profile selected on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProfileKind {
+    Dev,
+    Release,
+    /// A custom profile from `[profile.<name>]`.
+    Custom(InternedString),
+}
+
+impl ProfileKind {
+    /// The profile selected with `--profile name`.
+    pub fn from_name(name: &str) -> CargoResult<ProfileKind> {
+        Ok(match name {
+            "dev" => ProfileKind::Dev,
+            "release" => ProfileKind::Release,
+            "test" | "bench" | "doc" => bail!(
+                "profile `{}` can't be selected with `--profile`, only `dev`, \
+                 `release` and custom profiles can",
+                name
+            ),
+            _ => ProfileKind::Custom(InternedString::new(name)),
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ProfileKind::Dev => "dev",
+            ProfileKind::Release => "release",
+            ProfileKind::Custom(name) => name.as_str(),
+        }
+    }
+
+    /// The directory in the target directory the artifacts built with the
+    /// profile are placed in.
+    pub fn dest(&self) -> &'static str {
+        match *self {
+            ProfileKind::Dev => "debug",
+            _ => self.name(),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MessageFormat {
     Human,
@@ -110,6 +375,67 @@ pub enum MessageFormat {
     Short,
 }
 
+/// What is done with the warnings rustc emits while compiling the members of
+/// the workspace, the warnings of other packages are left alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningHandling {
+    /// Warnings are printed, the default.
+    Warn,
+    /// Warnings are hidden.
+    Allow,
+    /// Warnings are printed and fail the build once everything is compiled.
+    Deny,
+}
+
+/// An extra kind of file rustc can write for a crate, with `--emit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EmitKind {
+    Asm,
+    LlvmIr,
+    LlvmBc,
+    Mir,
+}
+
+impl EmitKind {
+    /// The name of the kind for rustc's `--emit`.
+    pub fn name(self) -> &'static str {
+        match self {
+            EmitKind::Asm => "asm",
+            EmitKind::LlvmIr => "llvm-ir",
+            EmitKind::LlvmBc => "llvm-bc",
+            EmitKind::Mir => "mir",
+        }
+    }
+
+    /// The extension of the file rustc writes.
+    pub fn extension(self) -> &'static str {
+        match self {
+            EmitKind::Asm => "s",
+            EmitKind::LlvmIr => "ll",
+            EmitKind::LlvmBc => "bc",
+            EmitKind::Mir => "mir",
+        }
+    }
+}
+
+impl FromStr for EmitKind {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> CargoResult<EmitKind> {
+        match s {
+            "asm" => Ok(EmitKind::Asm),
+            "llvm-ir" => Ok(EmitKind::LlvmIr),
+            "llvm-bc" => Ok(EmitKind::LlvmBc),
+            "mir" => Ok(EmitKind::Mir),
+            _ => bail!(
+                "invalid emit kind `{}`, expected one of `asm`, `llvm-ir`, \
+                 `llvm-bc` or `mir`",
+                s
+            ),
+        }
+    }
+}
+
 /// The general "mode" of what to do.
 /// This is used for two purposes.  The commands themselves pass this in to
 /// `compile_ws` to tell it the general execution strategy.  This influences
@@ -7,9 +7,10 @@ use crate::core::profiles::Profiles;
 use crate::core::{Dependency, Workspace};
 use crate::core::{PackageId, PackageSet, Resolve};
 use crate::util::errors::CargoResult;
-use crate::util::{profile, Cfg, CfgExpr, Config, Rustc};
+use crate::util::{machine_message, profile, Cfg, CfgExpr, Config, Rustc};
 
-use super::{BuildConfig, BuildOutput, Kind, Unit};
+use super::standard_lib::StandardLib;
+use super::{BuildConfig, BuildOutput, CompileMode, Kind, Unit};
 
 mod target_info;
 pub use self::target_info::{FileFlavor, TargetInfo};
@@ -32,11 +33,13 @@ pub struct BuildContext<'a, 'cfg: 'a> {
     pub rustc: Rustc,
     /// Build information for the host arch
     pub host_config: TargetConfig,
-    /// Build information for the target
-    pub target_config: TargetConfig,
-    pub target_info: TargetInfo,
+    /// Build information for each of the targets
+    pub target_configs: HashMap<Kind, TargetConfig>,
+    pub target_infos: HashMap<Kind, TargetInfo>,
     pub host_info: TargetInfo,
     pub incremental_env: Option<bool>,
+    /// The standard library crates built from source with `-Z build-std`.
+    pub std: Option<StandardLib<'a>>,
 }
 
 impl<'a, 'cfg> BuildContext<'a, 'cfg> {
@@ -55,19 +58,34 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
         };
 
         let rustc = config.rustc(Some(ws))?;
-        let host_config = TargetConfig::new(config, &rustc.host)?;
-        let target_config = match build_config.requested_target.as_ref() {
-            Some(triple) => TargetConfig::new(config, triple)?,
-            None => host_config.clone(),
+        let target_applies_to_host = config.target_applies_to_host()?;
+        let host_config = if target_applies_to_host {
+            TargetConfig::new(config, &rustc.host)?
+        } else {
+            TargetConfig::load(config, "host")?
         };
-        let (host_info, target_info) = {
+        let requested_targets = &build_config.requested_targets;
+        let mut target_configs = HashMap::new();
+        for kind in build_config.target_kinds() {
+            let target_config = match kind {
+                Kind::Target(Some(triple)) => TargetConfig::new(config, &triple)?,
+                _ if !target_applies_to_host => TargetConfig::new(config, &rustc.host)?,
+                _ => host_config.clone(),
+            };
+            target_configs.insert(kind, target_config);
+        }
+        let (host_info, target_infos) = {
             let _p = profile::start("BuildContext::probe_target_info");
             debug!("probe_target_info");
-            let host_info =
-                TargetInfo::new(config, &build_config.requested_target, &rustc, Kind::Host)?;
-            let target_info =
-                TargetInfo::new(config, &build_config.requested_target, &rustc, Kind::Target)?;
-            (host_info, target_info)
+            let host_info = TargetInfo::new(config, requested_targets, &rustc, Kind::Host)?;
+            let mut target_infos = HashMap::new();
+            let target_cpu = build_config.target_cpu.as_ref().map(|s| &s[..]);
+            for kind in build_config.target_kinds() {
+                let info =
+                    TargetInfo::with_target_cpu(config, requested_targets, &rustc, kind, target_cpu)?;
+                target_infos.insert(kind, info);
+            }
+            (host_info, target_infos)
         };
 
         Ok(BuildContext {
@@ -76,20 +94,41 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
             packages,
             config,
             rustc,
-            target_config,
-            target_info,
+            target_configs,
+            target_infos,
             host_config,
             host_info,
             build_config,
             profiles,
             incremental_env,
             extra_compiler_args,
+            std: None,
         })
     }
 
+    /// Whether `pkg` is one of the standard library crates built with
+    /// `-Z build-std`.
+    pub fn is_std(&self, pkg: PackageId) -> bool {
+        self.std
+            .as_ref()
+            .map_or(false, |std| std.packages.contains_key(&pkg))
+    }
+
+    /// The dependency graph `pkg` is part of, either the one of the build or
+    /// the one of the standard library.
+    pub fn resolve_of(&self, pkg: PackageId) -> &'a Resolve {
+        match self.std {
+            Some(ref std) if std.packages.contains_key(&pkg) => std.resolve,
+            _ => self.resolve,
+        }
+    }
+
     pub fn extern_crate_name(&self, unit: &Unit<'a>, dep: &Unit<'a>) -> CargoResult<String> {
-        self.resolve
-            .extern_crate_name(unit.pkg.package_id(), dep.pkg.package_id(), dep.target)
+        self.resolve_of(unit.pkg.package_id()).extern_crate_name(
+            unit.pkg.package_id(),
+            dep.pkg.package_id(),
+            dep.target,
+        )
     }
 
     /// Whether a dependency should be compiled for the host or target platform,
@@ -101,11 +140,7 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
             Some(p) => p,
             None => return true,
         };
-        let (name, info) = match kind {
-            Kind::Host => (self.host_triple(), &self.host_info),
-            Kind::Target => (self.target_triple(), &self.target_info),
-        };
-        platform.matches(name, info.cfg())
+        platform.matches(self.triple(kind), self.info(kind).cfg())
     }
 
     /// Get the user-specified linker for a particular host or target
@@ -118,13 +153,84 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
         self.target_config(kind).ar.as_ref().map(|s| s.as_ref())
     }
 
+    /// The linker for a particular host or target, the user-specified one
+    /// or else the default of the target
+    pub fn target_linker(&self, kind: Kind) -> PathBuf {
+        match self.linker(kind) {
+            Some(linker) => linker.to_path_buf(),
+            None => PathBuf::from(&self.info(kind).default_linker),
+        }
+    }
+
+    /// The `ar` program for a particular host or target, the user-specified
+    /// one or else the one of the target's toolchain
+    pub fn target_ar(&self, kind: Kind) -> PathBuf {
+        match self.ar(kind) {
+            Some(ar) => ar.to_path_buf(),
+            None => PathBuf::from(&self.info(kind).default_ar),
+        }
+    }
+
+    /// Get the user-specified resource compiler for a particular host or
+    /// target
+    pub fn resource_compiler(&self, kind: Kind) -> Option<&Path> {
+        self.target_config(kind)
+            .resource_compiler
+            .as_ref()
+            .map(|s| s.as_ref())
+    }
+
+    /// Get the user-specified program to run on the `.wasm` files linked for
+    /// a particular host or target, along with its arguments
+    pub fn post_link(&self, kind: Kind) -> Option<&(PathBuf, Vec<String>)> {
+        self.target_config(kind).post_link.as_ref()
+    }
+
+    /// Get the user-specified packaging metadata for a particular host or
+    /// target, its minimum SDK and ABI name
+    pub fn packaging(&self, kind: Kind) -> (Option<&str>, Option<&str>) {
+        let config = self.target_config(kind);
+        (
+            config.min_sdk.as_ref().map(|s| s.as_str()),
+            config.abi.as_ref().map(|s| s.as_str()),
+        )
+    }
+
     /// Get the list of cfg printed out from the compiler for the specified kind
     pub fn cfg(&self, kind: Kind) -> &[Cfg] {
-        let info = match kind {
-            Kind::Host => &self.host_info,
-            Kind::Target => &self.target_info,
-        };
-        info.cfg().unwrap_or(&[])
+        self.info(kind).cfg().unwrap_or(&[])
+    }
+
+    /// The platforms of the host and of each target, with their cfg values
+    /// and tools, for the `target-info` messages.
+    pub fn target_infos(&self) -> Vec<machine_message::TargetInfo> {
+        let mut kinds = vec![Kind::Host];
+        kinds.extend(self.build_config.target_kinds());
+        kinds
+            .into_iter()
+            .map(|kind| {
+                machine_message::TargetInfo::new(
+                    kind,
+                    self.triple(kind),
+                    self.cfg(kind),
+                    &self.target_linker(kind),
+                    &self.target_ar(kind),
+                    self.info(kind).llvm_tools_dir.as_ref().map(|p| p.as_ref()),
+                )
+            })
+            .collect()
+    }
+
+    /// Get the sysroot of the compiler for the specified kind, as reported by
+    /// the compiler
+    pub fn sysroot(&self, kind: Kind) -> Option<&Path> {
+        self.info(kind).sysroot.as_ref().map(|s| s.as_ref())
+    }
+
+    /// Get the directory holding the standard library for the specified kind,
+    /// as reported by the compiler
+    pub fn sysroot_libdir(&self, kind: Kind) -> Option<&Path> {
+        self.info(kind).sysroot_libdir.as_ref().map(|s| s.as_ref())
     }
 
     /// The host arch triple
@@ -137,19 +243,29 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
         &self.rustc.host
     }
 
+    /// The first requested target triple, or the host triple if no target
+    /// was requested
     pub fn target_triple(&self) -> &str {
         self.build_config
-            .requested_target
-            .as_ref()
+            .requested_targets
+            .first()
             .map(|s| s.as_str())
             .unwrap_or_else(|| self.host_triple())
     }
 
+    /// The triple units of the specified kind are compiled for
+    pub fn triple(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Target(Some(triple)) => triple.as_str(),
+            _ => self.host_triple(),
+        }
+    }
+
     /// Get the target configuration for a particular host or target
     fn target_config(&self, kind: Kind) -> &TargetConfig {
         match kind {
             Kind::Host => &self.host_config,
-            Kind::Target => &self.target_config,
+            Kind::Target(_) => &self.target_configs[&kind],
         }
     }
 
@@ -159,20 +275,50 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
     }
 
     pub fn rustflags_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
-        env_args(
+        let mut args = env_args(
             self.config,
-            &self.build_config.requested_target,
+            &self.build_config.requested_targets,
             self.host_triple(),
             self.info(unit.kind).cfg(),
             unit.kind,
             "RUSTFLAGS",
-        )
+        )?;
+        if let Some(target_cpu) = self.target_cpu(unit.kind) {
+            args.push("-C".to_string());
+            args.push(format!("target-cpu={}", target_cpu));
+        }
+        Ok(args)
+    }
+
+    /// The CPU code is generated for with `--target-cpu`, only for the units
+    /// built for the target, build scripts and procedural macros are left
+    /// alone.
+    pub fn target_cpu(&self, kind: Kind) -> Option<&str> {
+        match kind {
+            Kind::Host => None,
+            Kind::Target(_) => self.build_config.target_cpu.as_ref().map(|s| &s[..]),
+        }
+    }
+
+    /// The target features rustc enables for code of `kind`, which depend on
+    /// the CPU with `--target-cpu`.
+    pub fn target_features(&self, kind: Kind) -> Vec<&str> {
+        let mut features = self
+            .cfg(kind)
+            .iter()
+            .filter_map(|cfg| match *cfg {
+                Cfg::KeyPair(ref key, ref value) if key == "target_feature" => Some(&value[..]),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        features.sort();
+        features
     }
 
     pub fn rustdocflags_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
         env_args(
             self.config,
-            &self.build_config.requested_target,
+            &self.build_config.requested_targets,
             self.host_triple(),
             self.info(unit.kind).cfg(),
             unit.kind,
@@ -181,16 +327,26 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
     }
 
     pub fn show_warnings(&self, pkg: PackageId) -> bool {
-        pkg.source_id().is_path() || self.config.extra_verbose()
+        (pkg.source_id().is_path() && !self.is_std(pkg)) || self.config.extra_verbose()
     }
 
-    fn info(&self, kind: Kind) -> &TargetInfo {
+    /// Get the information the compiler reported for the specified kind
+    pub fn info(&self, kind: Kind) -> &TargetInfo {
         match kind {
             Kind::Host => &self.host_info,
-            Kind::Target => &self.target_info,
+            Kind::Target(_) => &self.target_infos[&kind],
         }
     }
 
+    /// Whether `unit` emits its metadata in an `.rmeta` file next to the
+    /// `.rlib`, which lets dependent libraries start compiling before the
+    /// `.rlib` is finished, with `build.pipelining`.
+    pub fn emits_rmeta(&self, unit: &Unit) -> bool {
+        self.build_config.pipelining
+            && unit.mode == CompileMode::Build
+            && !unit.requires_upstream_objects()
+    }
+
     pub fn extra_args_for(&self, unit: &Unit<'a>) -> Option<&Vec<String>> {
         self.extra_compiler_args.get(unit)
     }
@@ -203,25 +359,52 @@ pub struct TargetConfig {
     pub ar: Option<PathBuf>,
     /// The path of the linker for this target.
     pub linker: Option<PathBuf>,
+    /// The resource compiler for this target, `rc` or `windres`.
+    pub resource_compiler: Option<PathBuf>,
+    /// The program, and its arguments, run on each `.wasm` file linked for
+    /// this target, like `wasm-bindgen` or `wasm-opt`.
+    pub post_link: Option<(PathBuf, Vec<String>)>,
+    /// The minimum platform version the artifacts for this target support,
+    /// like the API level on Android, for the artifact manifest.
+    pub min_sdk: Option<String>,
+    /// The name of this target's ABI in the platform's packaging, like
+    /// `arm64-v8a` on Android, for the artifact manifest.
+    pub abi: Option<String>,
     /// Special build options for any necessary input files (filename -> options)
     pub overrides: HashMap<String, BuildOutput>,
 }
 
 impl TargetConfig {
     pub fn new(config: &Config, triple: &str) -> CargoResult<TargetConfig> {
-        let key = format!("target.{}", triple);
+        TargetConfig::load(config, &format!("target.{}", triple))
+    }
+
+    /// Loads the configuration from the table at `key`, which is either
+    /// `target.<triple>` or `host`.
+    pub fn load(config: &Config, key: &str) -> CargoResult<TargetConfig> {
         let mut ret = TargetConfig {
             ar: config.get_path(&format!("{}.ar", key))?.map(|v| v.val),
             linker: config.get_path(&format!("{}.linker", key))?.map(|v| v.val),
+            resource_compiler: config
+                .get_path(&format!("{}.resource-compiler", key))?
+                .map(|v| v.val),
+            post_link: config
+                .get_path_and_args(&format!("{}.post-link", key))?
+                .map(|v| v.val),
+            min_sdk: config
+                .get_string(&format!("{}.min-sdk", key))?
+                .map(|v| v.val),
+            abi: config.get_string(&format!("{}.abi", key))?.map(|v| v.val),
             overrides: HashMap::new(),
         };
-        let table = match config.get_table(&key)? {
+        let table = match config.get_table(key)? {
             Some(table) => table.val,
             None => return Ok(ret),
         };
         for (lib_name, value) in table {
             match lib_name.as_str() {
-                "ar" | "linker" | "runner" | "rustflags" => continue,
+                "abi" | "ar" | "linker" | "min-sdk" | "post-link" | "resource-compiler" | "runner"
+                | "rustflags" => continue,
                 _ => {}
             }
 
@@ -229,6 +412,7 @@ impl TargetConfig {
                 library_paths: Vec::new(),
                 library_links: Vec::new(),
                 cfgs: Vec::new(),
+                dependent_cfgs: Vec::new(),
                 env: Vec::new(),
                 metadata: Vec::new(),
                 rerun_if_changed: Vec::new(),
@@ -267,6 +451,14 @@ impl TargetConfig {
                         let list = value.list(k)?;
                         output.cfgs.extend(list.iter().map(|v| v.0.clone()));
                     }
+                    "dependent-cfg" => {
+                        let list = value.list(k)?;
+                        for v in list.iter() {
+                            let whence = format!("in `{}` (in {})", key, v.1.display());
+                            BuildOutput::check_dependent_cfg(&v.0, &whence)?;
+                            output.dependent_cfgs.push(v.0.clone());
+                        }
+                    }
                     "rustc-env" => {
                         for (name, val) in value.table(k)?.0 {
                             let val = val.string(name)?.0;
@@ -305,10 +497,12 @@ impl TargetConfig {
 ///  - `build.rustflags` from the manifest
 ///
 /// Note that if a `target` is specified, no args will be passed to host code (plugins, build
-/// scripts, ...), even if it is the same as the target.
+/// scripts, ...), even if it is the same as the target. The same is true if
+/// `target-applies-to-host` is disabled, in which case host code gets
+/// `host.rustflags` instead.
 fn env_args(
     config: &Config,
-    requested_target: &Option<String>,
+    requested_targets: &[String],
     host_triple: &str,
     target_cfg: Option<&[Cfg]>,
     kind: Kind,
@@ -333,14 +527,25 @@ fn env_args(
     // This means that, e.g. even if the specified --target is the
     // same as the host, build scripts in plugins won't get
     // RUSTFLAGS.
-    let compiling_with_target = requested_target.is_some();
-    let is_target_kind = kind == Kind::Target;
+    //
+    // 3) If `target-applies-to-host` is disabled we always behave as if
+    // --target was specified, and host units get `host.rustflags`.
+    let target_applies_to_host = config.target_applies_to_host()?;
+    let compiling_with_target = !requested_targets.is_empty() || !target_applies_to_host;
+    let is_target_kind = kind != Kind::Host;
 
     if compiling_with_target && !is_target_kind {
         // This is probably a build script or plugin and we're
         // compiling with --target. In this scenario there are
-        // no rustflags we can apply.
-        return Ok(Vec::new());
+        // no rustflags we can apply, unless they were given for the
+        // host explicitly.
+        if target_applies_to_host || name != "RUSTFLAGS" {
+            return Ok(Vec::new());
+        }
+        return Ok(config
+            .get_list_or_split_string("host.rustflags")?
+            .map(|args| args.val)
+            .unwrap_or_default());
     }
 
     // First try RUSTFLAGS from the environment
@@ -360,10 +565,10 @@ fn env_args(
         .flat_map(|c| c.to_lowercase())
         .collect::<String>();
     // Then the target.*.rustflags value...
-    let target = requested_target
-        .as_ref()
-        .map(|s| s.as_str())
-        .unwrap_or(host_triple);
+    let target = match kind {
+        Kind::Target(Some(triple)) => triple.as_str(),
+        _ => host_triple,
+    };
     let key = format!("target.{}.{}", target, name);
     if let Some(args) = config.get_list_or_split_string(&key)? {
         let args = args.val.into_iter();
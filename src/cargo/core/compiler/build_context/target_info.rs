@@ -1,11 +1,16 @@
 use std::cell::RefCell;
 use std::collections::hash_map::{Entry, HashMap};
+use std::env;
 use std::path::PathBuf;
 use std::str::{self, FromStr};
 
+use failure::Error;
+use serde_json;
+
 use super::env_args;
 use super::Kind;
 use crate::core::TargetKind;
+use crate::util::errors::ProcessError;
 use crate::util::{CargoResult, CargoResultExt, Cfg, Config, ProcessBuilder, Rustc};
 
 #[derive(Clone)]
@@ -13,7 +18,16 @@ pub struct TargetInfo {
     crate_type_process: Option<ProcessBuilder>,
     crate_types: RefCell<HashMap<String, Option<(String, String)>>>,
     cfg: Option<Vec<Cfg>>,
+    pub sysroot: Option<PathBuf>,
     pub sysroot_libdir: Option<PathBuf>,
+    /// The linker rustc uses for this target unless told otherwise, like
+    /// `cc` or `link.exe`.
+    pub default_linker: String,
+    /// The archiver of this target's toolchain, like `ar` or `lib.exe`.
+    pub default_ar: String,
+    /// The directory of the LLVM tools in the sysroot, like `llvm-objcopy`
+    /// and `llvm-strip`, if the `llvm-tools` component is installed.
+    pub llvm_tools_dir: Option<PathBuf>,
 }
 
 /// Type of each file generated by a Unit.
@@ -21,10 +35,18 @@ pub struct TargetInfo {
 pub enum FileFlavor {
     /// Not a special file type.
     Normal,
-    /// It is something you can link against (e.g. a library)
-    Linkable,
+    /// It is something you can link against (e.g. a library). `rmeta` is
+    /// set for the metadata emitted next to an rlib for pipelining, which is
+    /// all a dependent rlib needs to start compiling.
+    Linkable { rmeta: bool },
     /// It is a piece of external debug information (e.g. *.dSYM and *.pdb)
     DebugInfo,
+    /// An extra file requested with `--emit` (e.g. *.s and *.ll), collected
+    /// in the `emit` directory.
+    Emitted,
+    /// The directory the `post-link` program configured for the target
+    /// writes to when processing a `.wasm` file.
+    PostLink,
 }
 
 pub struct FileType {
@@ -53,38 +75,54 @@ impl FileType {
 impl TargetInfo {
     pub fn new(
         config: &Config,
-        requested_target: &Option<String>,
+        requested_targets: &[String],
         rustc: &Rustc,
         kind: Kind,
     ) -> CargoResult<TargetInfo> {
-        let rustflags = env_args(
+        TargetInfo::with_target_cpu(config, requested_targets, rustc, kind, None)
+    }
+
+    /// Like `new`, but learns about code generated for `target_cpu`, which
+    /// changes the target features rustc reports.
+    pub fn with_target_cpu(
+        config: &Config,
+        requested_targets: &[String],
+        rustc: &Rustc,
+        kind: Kind,
+        target_cpu: Option<&str>,
+    ) -> CargoResult<TargetInfo> {
+        let probe_rustflags = config
+            .get_bool("build.probe-rustflags")?
+            .map(|v| v.val)
+            .unwrap_or(true);
+        let mut rustflags = env_args(
             config,
-            requested_target,
+            requested_targets,
             &rustc.host,
             None,
             kind,
             "RUSTFLAGS",
         )?;
-        let mut process = rustc.process();
-        process
-            .arg("-")
-            .arg("--crate-name")
-            .arg("___")
-            .arg("--print=file-names")
-            .args(&rustflags)
-            .env_remove("RUST_LOG");
-
-        let target_triple = requested_target
-            .as_ref()
-            .map(|s| s.as_str())
-            .unwrap_or(&rustc.host);
-        if kind == Kind::Target {
-            process.arg("--target").arg(target_triple);
+        // The rustflags conflict with compiling as much as with probing.
+        check_probe_rustflags(&rustflags)?;
+        if !probe_rustflags {
+            rustflags.clear();
+        }
+        if let Some(target_cpu) = target_cpu {
+            rustflags.push(format!("-Ctarget-cpu={}", target_cpu));
         }
 
+        let target_triple = match kind {
+            Kind::Target(Some(triple)) => triple.as_str(),
+            _ => &rustc.host,
+        };
+        let target = match kind {
+            Kind::Target(_) => Some(target_triple),
+            Kind::Host => None,
+        };
+        let mut process = probe_process(rustc, target, &rustflags);
+
         let crate_type_process = process.clone();
-        const KNOWN_CRATE_TYPES: &[&str] =
-            &["bin", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
         for crate_type in KNOWN_CRATE_TYPES.iter() {
             process.arg("--crate-type").arg(crate_type);
         }
@@ -100,32 +138,30 @@ impl TargetInfo {
                 has_cfg_and_sysroot = false;
                 rustc.cached_output(&process)
             })
+            .map_err(|e| diagnose_rustflags(rustc, target, &rustflags, e))
             .chain_err(|| "failed to run `rustc` to learn about target-specific information")?;
 
-        let mut lines = output.lines();
-        let mut map = HashMap::new();
-        for crate_type in KNOWN_CRATE_TYPES {
-            let out = parse_crate_type(crate_type, &error, &mut lines)?;
-            map.insert(crate_type.to_string(), out);
-        }
+        // A newer, older or patched compiler may print more, or less, than
+        // expected when asked for everything at once, in which case each
+        // piece is asked for on its own.
+        let printed = match PrintedInfo::parse(&output, &error, has_cfg_and_sysroot) {
+            Ok(printed) => printed,
+            Err(e) => {
+                debug!(
+                    "unexpected output of `{}` ({}), probing each crate type",
+                    with_cfg, e
+                );
+                PrintedInfo::probe(rustc, &crate_type_process, has_cfg_and_sysroot)?
+            }
+        };
+        let map = printed.crate_types;
 
+        let mut sysroot = None;
         let mut sysroot_libdir = None;
-        if has_cfg_and_sysroot {
-            let line = match lines.next() {
-                Some(line) => line,
-                None => bail!(
-                    "output of --print=sysroot missing when learning about \
-                     target-specific information from rustc"
-                ),
-            };
+        if let Some(ref line) = printed.sysroot {
             let mut rustlib = PathBuf::from(line);
             if kind == Kind::Host {
-                if cfg!(windows) {
-                    rustlib.push("bin");
-                } else {
-                    rustlib.push("lib");
-                }
-                sysroot_libdir = Some(rustlib);
+                sysroot_libdir = Some(config.host_platform()?.sysroot_libdir(&rustlib));
             } else {
                 rustlib.push("lib");
                 rustlib.push("rustlib");
@@ -133,19 +169,26 @@ impl TargetInfo {
                 rustlib.push("lib");
                 sysroot_libdir = Some(rustlib);
             }
+            sysroot = Some(PathBuf::from(line));
         }
 
-        let cfg = if has_cfg_and_sysroot {
-            Some(lines.map(Cfg::from_str).collect::<CargoResult<_>>()?)
-        } else {
-            None
-        };
+        // The tools run on the host, whatever the target.
+        let llvm_tools_dir = sysroot
+            .as_ref()
+            .map(|sysroot| sysroot.join("lib/rustlib").join(&rustc.host).join("bin"))
+            .filter(|dir| dir.join(exe("llvm-objcopy")).is_file());
+        let spec = target_spec(rustc, target);
+        let (default_linker, default_ar) = default_tools(spec.as_ref(), target_triple);
 
         Ok(TargetInfo {
             crate_type_process: Some(crate_type_process),
             crate_types: RefCell::new(map),
-            cfg,
+            cfg: printed.cfg,
+            sysroot,
             sysroot_libdir,
+            default_linker,
+            default_ar,
+            llvm_tools_dir,
         })
     }
 
@@ -159,13 +202,14 @@ impl TargetInfo {
         flavor: FileFlavor,
         kind: &TargetKind,
         target_triple: &str,
+        rustc: &Rustc,
     ) -> CargoResult<Option<Vec<FileType>>> {
         let mut crate_types = self.crate_types.borrow_mut();
         let entry = crate_types.entry(crate_type.to_string());
         let crate_type_info = match entry {
             Entry::Occupied(o) => &*o.into_mut(),
             Entry::Vacant(v) => {
-                let value = self.discover_crate_type(v.key())?;
+                let value = self.discover_crate_type(v.key(), rustc)?;
                 &*v.insert(value)
             }
         };
@@ -229,25 +273,392 @@ impl TargetInfo {
         Ok(Some(ret))
     }
 
-    fn discover_crate_type(&self, crate_type: &str) -> CargoResult<Option<(String, String)>> {
-        let mut process = self.crate_type_process.clone().unwrap();
+    fn discover_crate_type(
+        &self,
+        crate_type: &str,
+        rustc: &Rustc,
+    ) -> CargoResult<Option<(String, String)>> {
+        probe_crate_type(rustc, self.crate_type_process.as_ref().unwrap(), crate_type)
+    }
+}
 
-        process.arg("--crate-type").arg(crate_type);
+const KNOWN_CRATE_TYPES: &[&str] = &["bin", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+
+/// The options of rustc whose value may be the next argument.
+const OPTIONS_WITH_VALUES: &[&str] = &[
+    "-C",
+    "--codegen",
+    "-Z",
+    "--cfg",
+    "-L",
+    "-l",
+    "--target",
+    "--crate-type",
+    "--crate-name",
+    "--print",
+    "--emit",
+    "--edition",
+    "--extern",
+    "-o",
+    "--out-dir",
+    "--sysroot",
+    "--error-format",
+    "--cap-lints",
+    "--remap-path-prefix",
+    "-A",
+    "-W",
+    "-D",
+    "-F",
+    "--allow",
+    "--warn",
+    "--deny",
+    "--forbid",
+];
+
+/// The rustc process printing the file names of the crate types passed to
+/// it, for `target`, or the host if `None`, with `rustflags`.
+fn probe_process(rustc: &Rustc, target: Option<&str>, rustflags: &[String]) -> ProcessBuilder {
+    let mut process = rustc.process();
+    process
+        .arg("-")
+        .arg("--crate-name")
+        .arg("___")
+        .arg("--print=file-names")
+        .args(rustflags)
+        .env_remove("RUST_LOG");
+    if let Some(target) = target {
+        process.arg("--target").arg(target);
+    }
+    process
+}
+
+/// The specification of `target`, or the host if `None`, which only a
+/// nightly rustc prints.
+fn target_spec(rustc: &Rustc, target: Option<&str>) -> Option<serde_json::Value> {
+    let mut process = rustc.process();
+    process
+        .arg("-Zunstable-options")
+        .arg("--print=target-spec-json")
+        .env_remove("RUST_LOG");
+    if let Some(target) = target {
+        process.arg("--target").arg(target);
+    }
+    if !rustc.cached_success(&process).unwrap_or(false) {
+        return None;
+    }
+    let (output, _) = rustc.cached_output(&process).ok()?;
+    serde_json::from_str(&output).ok()
+}
 
-        let output = process.exec_with_output().chain_err(|| {
-            format!(
-                "failed to run `rustc` to learn about \
+/// The default linker and archiver of the target `triple`, from its
+/// specification `spec` if known, or else guessed from its triple.
+fn default_tools(spec: Option<&serde_json::Value>, triple: &str) -> (String, String) {
+    let field = |name: &str| {
+        spec.and_then(|spec| spec.get(name))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+    let flavor = field("linker-flavor").unwrap_or_else(|| {
+        if triple.ends_with("-msvc") {
+            "msvc".to_string()
+        } else if triple.starts_with("wasm32-") && !triple.ends_with("-emscripten") {
+            "wasm-ld".to_string()
+        } else if is_bare_metal_lld(triple) {
+            "ld.lld".to_string()
+        } else {
+            "gcc".to_string()
+        }
+    });
+    let linker = field("linker").unwrap_or_else(|| {
+        match flavor.as_str() {
+            "msvc" => "link.exe",
+            "wasm-ld" | "ld.lld" | "ld64.lld" | "lld-link" => "rust-lld",
+            _ => "cc",
+        }
+        .to_string()
+    });
+    let ar = field("ar").unwrap_or_else(|| {
+        if flavor == "msvc" {
+            "lib.exe".to_string()
+        } else {
+            "ar".to_string()
+        }
+    });
+    (linker, ar)
+}
+
+/// Whether `triple` is one of the ARM or RISC-V targets without an operating
+/// system, like `thumbv7em-none-eabi` or `riscv32imac-unknown-none-elf`, which
+/// are linked with `rust-lld`.
+fn is_bare_metal_lld(triple: &str) -> bool {
+    let arch = triple.split('-').next().unwrap_or("");
+    let lld_arch = ["thumb", "arm", "aarch64", "riscv"]
+        .iter()
+        .any(|prefix| arch.starts_with(prefix));
+    lld_arch && triple.split('-').skip(1).any(|part| part == "none")
+}
+
+/// The file name of the executable `name` on the host.
+fn exe(name: &str) -> String {
+    format!("{}{}", name, env::consts::EXE_SUFFIX)
+}
+
+/// Splits `rustflags` into flags, each option along with its value when
+/// that's the next argument, like `-C opt-level=3`.
+fn rustflag_groups(rustflags: &[String]) -> Vec<&[String]> {
+    let mut groups = Vec::new();
+    let mut i = 0;
+    while i < rustflags.len() {
+        let len = if OPTIONS_WITH_VALUES.contains(&rustflags[i].as_str()) {
+            2.min(rustflags.len() - i)
+        } else {
+            1
+        };
+        groups.push(&rustflags[i..i + len]);
+        i += len;
+    }
+    groups
+}
+
+/// The codegen option `group` sets, like `opt-level=3` for `-C opt-level=3`,
+/// `-Copt-level=3` or `--codegen=opt-level=3`, if it's one.
+fn codegen_option(group: &[String]) -> Option<String> {
+    let flag = group.join("");
+    ["--codegen=", "--codegen", "-C"]
+        .iter()
+        .find(|prefix| flag.starts_with(*prefix))
+        .map(|prefix| flag[prefix.len()..].to_string())
+}
+
+/// Fails if `rustflags` contain a flag conflicting with the ones cargo
+/// passes to rustc, to learn about the target as well as to compile.
+fn check_probe_rustflags(rustflags: &[String]) -> CargoResult<()> {
+    for group in rustflag_groups(rustflags) {
+        let option = group[0].split('=').next().unwrap();
+        let reason = match option {
+            "--crate-type" => {
+                "cargo passes the crate types of each target itself, \
+                 set them with `crate-type` in the manifest"
+            }
+            "--crate-name" => "cargo passes the crate name of each target itself",
+            "--target" => "set the target with `--target` or `build.target` instead",
+            "--print" => {
+                "rustc would only print and not compile, run `cargo rustc -- --print` instead"
+            }
+            _ if codegen_option(group).map_or(false, |o| o.starts_with("extra-filename")) => {
+                "cargo sets it itself to tell the artifacts of each crate apart"
+            }
+            _ => continue,
+        };
+        bail!(
+            "the rustflags can't contain `{}`: {}",
+            group.join(" "),
+            reason
+        );
+    }
+    Ok(())
+}
+
+/// Explains `err`, the failure of rustc with `rustflags` asked about
+/// `target`, by finding the flag it fails with, if any.
+fn diagnose_rustflags(
+    rustc: &Rustc,
+    target: Option<&str>,
+    rustflags: &[String],
+    err: Error,
+) -> Error {
+    let succeeds = |flags: &[String]| {
+        let mut process = probe_process(rustc, target, flags);
+        for crate_type in KNOWN_CRATE_TYPES.iter() {
+            process.arg("--crate-type").arg(crate_type);
+        }
+        rustc.cached_success(&process).unwrap_or(false)
+    };
+    if rustflags.is_empty() || !succeeds(&[]) {
+        return err;
+    }
+    let msg = match rustflag_groups(rustflags)
+        .into_iter()
+        .find(|group| !succeeds(group))
+    {
+        Some(group) => format!(
+            "{} fails with `{}` of the rustflags, which cargo also passes to \
+             it to learn about the target unless `build.probe-rustflags` is false",
+            rustc_version(rustc),
+            group.join(" ")
+        ),
+        None => format!(
+            "{} fails with the rustflags `{}`, but not with any of them alone",
+            rustc_version(rustc),
+            rustflags.join(" ")
+        ),
+    };
+    err.context(msg).into()
+}
+
+/// What rustc printed about a target with `--print=file-names`, and
+/// `--print=sysroot` and `--print=cfg` if it supports them.
+struct PrintedInfo {
+    crate_types: HashMap<String, Option<(String, String)>>,
+    sysroot: Option<String>,
+    cfg: Option<Vec<Cfg>>,
+}
+
+impl PrintedInfo {
+    /// Parses the output of rustc asked for the file names of all the known
+    /// crate types, followed by the sysroot and the cfg values if
+    /// `has_cfg_and_sysroot`, failing if it doesn't look as expected.
+    fn parse(output: &str, error: &str, has_cfg_and_sysroot: bool) -> CargoResult<PrintedInfo> {
+        let mut lines = output.lines();
+        let mut crate_types = HashMap::new();
+        for crate_type in KNOWN_CRATE_TYPES {
+            let out = parse_crate_type(crate_type, error, &mut lines)?;
+            crate_types.insert(crate_type.to_string(), out);
+        }
+        if !has_cfg_and_sysroot {
+            return Ok(PrintedInfo {
+                crate_types,
+                sysroot: None,
+                cfg: None,
+            });
+        }
+        let sysroot = match lines.next() {
+            Some(line) => line.to_string(),
+            None => bail!(
+                "output of --print=sysroot missing when learning about \
+                 target-specific information from rustc"
+            ),
+        };
+        Ok(PrintedInfo {
+            crate_types,
+            sysroot: Some(sysroot),
+            cfg: Some(parse_cfg(lines)),
+        })
+    }
+
+    /// Asks rustc for each piece of information on its own, with `process`
+    /// printing the file names, ignoring the lines it doesn't expect.
+    fn probe(
+        rustc: &Rustc,
+        process: &ProcessBuilder,
+        has_cfg_and_sysroot: bool,
+    ) -> CargoResult<PrintedInfo> {
+        let mut crate_types = HashMap::new();
+        for crate_type in KNOWN_CRATE_TYPES {
+            let out = probe_crate_type(rustc, process, crate_type)?;
+            crate_types.insert(crate_type.to_string(), out);
+        }
+        if !has_cfg_and_sysroot {
+            return Ok(PrintedInfo {
+                crate_types,
+                sysroot: None,
+                cfg: None,
+            });
+        }
+
+        // The file names of the default crate type are printed as well.
+        let mut with_sysroot = process.clone();
+        with_sysroot.arg("--print=sysroot");
+        let (output, _) = rustc
+            .cached_output(&with_sysroot)
+            .chain_err(|| "failed to run `rustc` to learn about its sysroot")?;
+        let sysroot = match output.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => line.to_string(),
+            None => bail!(
+                "output of --print=sysroot missing when learning about \
+                 target-specific information from {}",
+                rustc_version(rustc)
+            ),
+        };
+        let mut with_cfg = process.clone();
+        with_cfg.arg("--print=cfg");
+        let (output, _) = rustc
+            .cached_output(&with_cfg)
+            .chain_err(|| "failed to run `rustc` to learn about the target's cfg values")?;
+        let cfg = parse_cfg(output.lines().filter(|line| !line.contains("___")));
+        Ok(PrintedInfo {
+            crate_types,
+            sysroot: Some(sysroot),
+            cfg: Some(cfg),
+        })
+    }
+}
+
+/// Parses the cfg values printed by `--print=cfg`, skipping the lines which
+/// aren't ones.
+fn parse_cfg<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Cfg> {
+    lines
+        .filter_map(|line| match Cfg::from_str(line) {
+            Ok(cfg) => Some(cfg),
+            Err(e) => {
+                debug!("ignoring `{}` printed by --print=cfg: {}", line, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The first line of `rustc -vV`, like `rustc 1.33.0 (2aa4c46cf 2019-02-28)`.
+fn rustc_version(rustc: &Rustc) -> &str {
+    rustc.verbose_version.lines().next().unwrap_or("rustc")
+}
+
+/// Asks rustc, with `process` printing the file names, for the ones of
+/// `crate_type` only, ignoring the lines which aren't file names.
+fn probe_crate_type(
+    rustc: &Rustc,
+    process: &ProcessBuilder,
+    crate_type: &str,
+) -> CargoResult<Option<(String, String)>> {
+    let mut process = process.clone();
+    process.arg("--crate-type").arg(crate_type);
+
+    let (output, error) = match rustc.cached_output(&process) {
+        Ok(output) => output,
+        Err(e) => {
+            if let Some(reason) = rejection(&e, crate_type) {
+                bail!(
+                    "{} doesn't support crate-type {}: {}",
+                    rustc_version(rustc),
+                    crate_type,
+                    reason
+                );
+            }
+            return Err(e
+                .context(format!(
+                    "failed to run `rustc` to learn about \
                  crate-type {} information",
-                crate_type
-            )
-        })?;
+                    crate_type
+                ))
+                .into());
+        }
+    };
 
-        let error = str::from_utf8(&output.stderr).unwrap();
-        let output = str::from_utf8(&output.stdout).unwrap();
-        Ok(parse_crate_type(crate_type, error, &mut output.lines())?)
+    let mut file_names = output.lines().filter(|line| line.contains("___"));
+    match parse_crate_type(crate_type, &error, &mut file_names) {
+        Ok(out) => Ok(out),
+        Err(_) => bail!(
+            "{} didn't print the file names of crate-type {}, cannot learn \
+             about it",
+            rustc_version(rustc),
+            crate_type
+        ),
     }
 }
 
+/// The line of rustc's error output rejecting `crate_type`, if `err` is the
+/// failure of a probe for a crate type rustc doesn't know, like a newer one
+/// than it supports.
+fn rejection(err: &Error, crate_type: &str) -> Option<String> {
+    let output = err.downcast_ref::<ProcessError>()?.output.as_ref()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr.lines().find(|line| {
+        (line.contains("unknown crate type") || line.contains("unsupported crate type"))
+            && line.contains(crate_type)
+    })?;
+    Some(line.trim_start_matches("error: ").to_string())
+}
+
 /// Takes rustc output (using specialized command line args), and calculates the file prefix and
 /// suffix for the given crate type, or returns None if the type is not supported. (e.g. for a
 /// rust library like libcargo.rlib, prefix = "lib", suffix = "rlib").
@@ -256,10 +667,10 @@ impl TargetInfo {
 /// type: this is not checked.
 // This function can not handle more than 1 file per type (with wasm32-unknown-emscripten, there
 // are 2 files for bin (.wasm and .js))
-fn parse_crate_type(
+fn parse_crate_type<'a>(
     crate_type: &str,
     error: &str,
-    lines: &mut str::Lines,
+    lines: &mut impl Iterator<Item = &'a str>,
 ) -> CargoResult<Option<(String, String)>> {
     let not_supported = error.lines().any(|line| {
         (line.contains("unsupported crate type") || line.contains("unknown crate type"))
@@ -288,3 +699,120 @@ fn parse_crate_type(
 
     Ok(Some((prefix.to_string(), suffix.to_string())))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_probe_rustflags, default_tools, parse_cfg, rejection, rustflag_groups, PrintedInfo,
+    };
+
+    const OUTPUT: &str = "___\nlib___.rlib\nlib___.so\nlib___.so\nlib___.a\nlib___.so\n\
+                          /usr/local\ndebug_assertions\ntarget_os=\"linux\"\nunix\n";
+
+    #[test]
+    fn parse_printed_info() {
+        let printed = PrintedInfo::parse(OUTPUT, "", true).unwrap();
+        assert_eq!(
+            printed.crate_types["rlib"],
+            Some(("lib".to_string(), ".rlib".to_string()))
+        );
+        assert_eq!(printed.sysroot, Some("/usr/local".to_string()));
+        assert_eq!(printed.cfg.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn parse_printed_info_with_extra_lines() {
+        let output = format!("note: using a patched compiler\n{}", OUTPUT);
+        assert!(PrintedInfo::parse(&output, "", true).is_err());
+    }
+
+    #[test]
+    fn rustflags_are_grouped_with_their_values() {
+        let flags = ["-C", "opt-level=3", "--cfg=foo", "-Zbar", "-L"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let groups = rustflag_groups(&flags);
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[0], &flags[..2]);
+        assert_eq!(groups[3], &flags[4..]);
+    }
+
+    #[test]
+    fn rustflags_conflicting_with_the_probe() {
+        let check = |flags: &[&str]| {
+            let flags = flags.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            check_probe_rustflags(&flags)
+        };
+        assert!(check(&["--cfg", "foo", "-C", "linker=cc"]).is_ok());
+        assert!(check(&["--crate-type=lib"]).is_err());
+        assert!(check(&["--print", "cfg"]).is_err());
+        assert!(check(&["-C", "extra-filename=-x"]).is_err());
+        assert!(check(&["--codegen", "extra-filename=-x"]).is_err());
+        assert!(check(&["--codegen=extra-filename=-x"]).is_err());
+    }
+
+    #[test]
+    fn default_tools_of_targets() {
+        let tools = |linker: &str, ar: &str| (linker.to_string(), ar.to_string());
+        assert_eq!(
+            default_tools(None, "x86_64-unknown-linux-gnu"),
+            tools("cc", "ar")
+        );
+        assert_eq!(
+            default_tools(None, "x86_64-pc-windows-msvc"),
+            tools("link.exe", "lib.exe")
+        );
+        assert_eq!(
+            default_tools(None, "wasm32-unknown-unknown"),
+            tools("rust-lld", "ar")
+        );
+        assert_eq!(
+            default_tools(None, "thumbv7em-none-eabihf"),
+            tools("rust-lld", "ar")
+        );
+        assert_eq!(
+            default_tools(None, "riscv32imac-unknown-none-elf"),
+            tools("rust-lld", "ar")
+        );
+        assert_eq!(
+            default_tools(None, "arm-unknown-linux-gnueabihf"),
+            tools("cc", "ar")
+        );
+        let spec = serde_json::json!({
+            "linker": "arm-none-eabi-gcc",
+            "linker-flavor": "gcc",
+        });
+        assert_eq!(
+            default_tools(Some(&spec), "thumbv7em-none-eabi"),
+            tools("arm-none-eabi-gcc", "ar")
+        );
+    }
+
+    #[test]
+    fn parse_cfg_skips_unknown_lines() {
+        let lines = "unix\ntarget_os=\"linux\"\nnot a cfg (at all)\n";
+        assert_eq!(parse_cfg(lines.lines()).len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejection_of_unknown_crate_type() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::{ExitStatus, Output};
+
+        use crate::util::errors::process_error;
+
+        let output = Output {
+            status: ExitStatus::from_raw(1 << 8),
+            stdout: Vec::new(),
+            stderr: b"error: unknown crate type: `sdylib`, expected one of: `lib`\n".to_vec(),
+        };
+        let err = process_error("rustc failed", Some(output.status), Some(&output)).into();
+        assert_eq!(
+            rejection(&err, "sdylib"),
+            Some("unknown crate type: `sdylib`, expected one of: `lib`".to_string())
+        );
+        assert_eq!(rejection(&err, "rlib"), None);
+    }
+}
@@ -0,0 +1,103 @@
+//! The environment rustc, rustdoc and build scripts run with, configured with
+//! `build.env-scrub` and `build.env-allowlist`.
+//!
+//! By default they inherit the whole environment of cargo, so a variable
+//! set on one machine and not on another, read by a build script or by
+//! `env!`, may silently change what's built. The variables listed in
+//! `build.env-scrub` are removed from their environment, and with
+//! `build.env-allowlist` they only get the variables listed there, along
+//! with the ones cargo sets itself. The values of the allowed variables are
+//! recorded in fingerprints, so changing them rebuilds what they may affect.
+//!
+//! The test executables and doctests run by `cargo test` are restricted the
+//! same way with `test.env-scrub` and `test.env-allowlist`.
+
+use std::env;
+
+use crate::util::errors::CargoResult;
+use crate::util::{Config, ProcessBuilder};
+
+#[derive(Clone, Debug, Default)]
+pub struct BuildEnv {
+    /// The variables removed from the environment.
+    scrub: Vec<String>,
+    /// The only variables passed on, if any, in order.
+    allowlist: Option<Vec<String>>,
+}
+
+impl BuildEnv {
+    pub fn from_config(config: &Config) -> CargoResult<BuildEnv> {
+        BuildEnv::load(config, "build")
+    }
+
+    /// The environment of the tests run by `cargo test` and `cargo bench`.
+    pub fn for_tests(config: &Config) -> CargoResult<BuildEnv> {
+        BuildEnv::load(config, "test")
+    }
+
+    fn load(config: &Config, table: &str) -> CargoResult<BuildEnv> {
+        let scrub = config
+            .get_list_or_split_string(&format!("{}.env-scrub", table))?
+            .map(|v| v.val)
+            .unwrap_or_default();
+        let allowlist = config
+            .get_list_or_split_string(&format!("{}.env-allowlist", table))?
+            .map(|v| {
+                let mut names = v.val;
+                names.sort();
+                names.dedup();
+                names
+            });
+        Ok(BuildEnv { scrub, allowlist })
+    }
+
+    /// Whether the variable `name` of the environment of cargo may be passed
+    /// on.
+    pub fn allows(&self, name: &str) -> bool {
+        if self.scrub.iter().any(|s| s == name) {
+            return false;
+        }
+        match self.allowlist {
+            Some(ref allowlist) => allowlist.iter().any(|a| a == name),
+            None => true,
+        }
+    }
+
+    /// Restricts the environment of `cmd`, once it has every variable cargo
+    /// sets for it.
+    pub fn apply(&self, cmd: &mut ProcessBuilder) {
+        for name in self.scrub.iter() {
+            if !cmd.get_envs().contains_key(name) {
+                cmd.env_remove(name);
+            }
+        }
+        if let Some(ref allowlist) = self.allowlist {
+            for name in allowlist.iter().filter(|name| self.allows(name)) {
+                if cmd.get_envs().contains_key(name) {
+                    continue;
+                }
+                if let Some(value) = env::var_os(name) {
+                    cmd.env(name, value);
+                }
+            }
+            cmd.env_clear();
+        }
+    }
+
+    /// The variables recorded in fingerprints: the allowed ones along with
+    /// their values, or, without an allowlist, the scrubbed ones.
+    pub fn fingerprint(&self) -> Vec<(String, Option<String>)> {
+        match self.allowlist {
+            Some(ref allowlist) => allowlist
+                .iter()
+                .filter(|name| self.allows(name))
+                .map(|name| (name.clone(), env::var(name).ok()))
+                .collect(),
+            None => {
+                let mut scrub = self.scrub.clone();
+                scrub.sort();
+                scrub.into_iter().map(|name| (name, None)).collect()
+            }
+        }
+    }
+}
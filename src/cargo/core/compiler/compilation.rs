@@ -5,9 +5,9 @@ use std::path::PathBuf;
 
 use semver::Version;
 
-use super::BuildContext;
+use super::{BuildContext, Kind};
 use crate::core::{Edition, Package, PackageId, Target, TargetKind};
-use crate::util::{self, join_paths, process, CargoResult, CfgExpr, Config, ProcessBuilder};
+use crate::util::{join_paths, process, CargoResult, CfgExpr, Config, ProcessBuilder};
 
 pub struct Doctest {
     /// The package being doctested.
@@ -17,12 +17,16 @@ pub struct Doctest {
     /// Extern dependencies needed by `rustdoc`. The path is the location of
     /// the compiled lib.
     pub deps: Vec<(String, PathBuf)>,
+    /// The directory the doctests run in if they're sandboxed, as their
+    /// compilation may run proc-macros.
+    pub sandbox_dir: Option<PathBuf>,
 }
 
 /// A structure returning the result of a compilation.
 pub struct Compilation<'cfg> {
-    /// An array of all tests created during this compilation.
-    pub tests: Vec<(Package, TargetKind, String, PathBuf)>,
+    /// An array of all tests created during this compilation, with the kind
+    /// of the platform they were built for.
+    pub tests: Vec<(Package, TargetKind, String, PathBuf, Kind)>,
 
     /// An array of all binaries created.
     pub binaries: Vec<PathBuf>,
@@ -48,13 +52,17 @@ pub struct Compilation<'cfg> {
     /// The path to rustc's own libstd
     pub host_dylib_path: Option<PathBuf>,
 
-    /// The path to libstd for the target
-    pub target_dylib_path: Option<PathBuf>,
+    /// The path to libstd for each of the targets
+    pub target_dylib_paths: HashMap<Kind, Option<PathBuf>>,
 
     /// Extra environment variables that were passed to compilations and should
     /// be passed to future invocations of programs.
     pub extra_env: HashMap<PackageId, Vec<(String, String)>>,
 
+    /// The `OUT_DIR` of the build scripts of the packages which were
+    /// requested to be built.
+    pub out_dirs: HashMap<PackageId, PathBuf>,
+
     /// Libraries to test with rustdoc.
     pub to_doc_test: Vec<Doctest>,
 
@@ -66,15 +74,21 @@ pub struct Compilation<'cfg> {
 
     pub host: String,
     pub target: String,
+    /// The kind of the first of the requested targets, whose triple is
+    /// `target`.
+    pub target_kind: Kind,
 
     config: &'cfg Config,
     rustc_process: ProcessBuilder,
 
-    target_runner: Option<(PathBuf, Vec<String>)>,
+    /// The runner of each of the targets, if any.
+    target_runners: HashMap<Kind, Option<(PathBuf, Vec<String>)>>,
 }
 
 impl<'cfg> Compilation<'cfg> {
     pub fn new<'a>(bcx: &BuildContext<'a, 'cfg>) -> CargoResult<Compilation<'cfg>> {
+        // Doctests are only run for the first of the requested targets.
+        let kind = bcx.build_config.target_kinds()[0];
         // If we're using cargo as a rustc wrapper then we're in a situation
         // like `cargo fix`. For now just disregard the `RUSTC_WRAPPER` env var
         // (which is typically set to `sccache` for now). Eventually we'll
@@ -99,30 +113,62 @@ impl<'cfg> Compilation<'cfg> {
         if let Some(server) = &*srv {
             server.configure(&mut rustc);
         }
+        let mut target_dylib_paths = HashMap::new();
+        let mut target_runners = HashMap::new();
+        for kind in bcx.build_config.target_kinds() {
+            target_dylib_paths.insert(kind, bcx.info(kind).sysroot_libdir.clone());
+            target_runners.insert(kind, target_runner(&bcx, kind)?);
+        }
         Ok(Compilation {
             native_dirs: BTreeSet::new(), // TODO: deprecated, remove
             root_output: PathBuf::from("/"),
             deps_output: PathBuf::from("/"),
             host_deps_output: PathBuf::from("/"),
             host_dylib_path: bcx.host_info.sysroot_libdir.clone(),
-            target_dylib_path: bcx.target_info.sysroot_libdir.clone(),
+            target_dylib_paths,
             tests: Vec::new(),
             binaries: Vec::new(),
             extra_env: HashMap::new(),
+            out_dirs: HashMap::new(),
             to_doc_test: Vec::new(),
             cfgs: HashMap::new(),
             rustdocflags: HashMap::new(),
             config: bcx.config,
             rustc_process: rustc,
             host: bcx.host_triple().to_string(),
-            target: bcx.target_triple().to_string(),
-            target_runner: target_runner(&bcx)?,
+            target: bcx.triple(kind).to_string(),
+            target_kind: kind,
+            target_runners,
         })
     }
 
+    /// Merges the results of another compilation into this one.
+    ///
+    /// This is used when the selected packages are built one after the other
+    /// with separate feature resolutions. Per-package information already
+    /// recorded here is kept as is.
+    pub fn extend(&mut self, other: Compilation<'cfg>) {
+        self.tests.extend(other.tests);
+        self.binaries.extend(other.binaries);
+        self.native_dirs.extend(other.native_dirs);
+        self.to_doc_test.extend(other.to_doc_test);
+        for (id, env) in other.extra_env {
+            self.extra_env.entry(id).or_insert(env);
+        }
+        for (id, out_dir) in other.out_dirs {
+            self.out_dirs.entry(id).or_insert(out_dir);
+        }
+        for (id, cfgs) in other.cfgs {
+            self.cfgs.entry(id).or_insert(cfgs);
+        }
+        for (id, flags) in other.rustdocflags {
+            self.rustdocflags.entry(id).or_insert(flags);
+        }
+    }
+
     /// See `process`.
     pub fn rustc_process(&self, pkg: &Package, target: &Target) -> CargoResult<ProcessBuilder> {
-        let mut p = self.fill_env(self.rustc_process.clone(), pkg, true)?;
+        let mut p = self.fill_env(self.rustc_process.clone(), pkg, None)?;
         if target.edition() != Edition::Edition2015 {
             p.arg(format!("--edition={}", target.edition()));
         }
@@ -131,7 +177,8 @@ impl<'cfg> Compilation<'cfg> {
 
     /// See `process`.
     pub fn rustdoc_process(&self, pkg: &Package, target: &Target) -> CargoResult<ProcessBuilder> {
-        let mut p = self.fill_env(process(&*self.config.rustdoc()?), pkg, false)?;
+        let kind = Some(self.target_kind);
+        let mut p = self.fill_env(process(&*self.config.rustdoc()?), pkg, kind)?;
         if target.edition() != Edition::Edition2015 {
             p.arg(format!("--edition={}", target.edition()));
         }
@@ -144,20 +191,23 @@ impl<'cfg> Compilation<'cfg> {
         cmd: T,
         pkg: &Package,
     ) -> CargoResult<ProcessBuilder> {
-        self.fill_env(process(cmd), pkg, true)
+        self.fill_env(process(cmd), pkg, None)
     }
 
-    fn target_runner(&self) -> &Option<(PathBuf, Vec<String>)> {
-        &self.target_runner
+    /// The runner configured for executing binaries built for the target of
+    /// `kind`, if any, along with the arguments to pass it before the binary.
+    pub fn target_runner(&self, kind: Kind) -> Option<&(PathBuf, Vec<String>)> {
+        self.target_runners.get(&kind).and_then(|r| r.as_ref())
     }
 
     /// See `process`.
     pub fn target_process<T: AsRef<OsStr>>(
         &self,
         cmd: T,
+        kind: Kind,
         pkg: &Package,
     ) -> CargoResult<ProcessBuilder> {
-        let builder = if let Some((ref runner, ref args)) = *self.target_runner() {
+        let builder = if let Some((runner, args)) = self.target_runner(kind) {
             let mut builder = process(runner);
             builder.args(args);
             builder.arg(cmd);
@@ -165,37 +215,44 @@ impl<'cfg> Compilation<'cfg> {
         } else {
             process(cmd)
         };
-        self.fill_env(builder, pkg, false)
+        self.fill_env(builder, pkg, Some(kind))
     }
 
     /// Prepares a new process with an appropriate environment to run against
     /// the artifacts produced by the build process.
     ///
     /// The package argument is also used to configure environment variables as
-    /// well as the working directory of the child process.
+    /// well as the working directory of the child process. The kind is the one
+    /// of the target the program was built for, or `None` for the host.
     fn fill_env(
         &self,
         mut cmd: ProcessBuilder,
         pkg: &Package,
-        is_host: bool,
+        kind: Option<Kind>,
     ) -> CargoResult<ProcessBuilder> {
-        let mut search_path = if is_host {
-            let mut search_path = vec![self.host_deps_output.clone()];
-            search_path.extend(self.host_dylib_path.clone());
-            search_path
-        } else {
-            let mut search_path =
-                super::filter_dynamic_search_path(self.native_dirs.iter(), &self.root_output);
-            search_path.push(self.deps_output.clone());
-            search_path.push(self.root_output.clone());
-            search_path.extend(self.target_dylib_path.clone());
-            search_path
+        let mut search_path = match kind {
+            None => {
+                let mut search_path = vec![self.host_deps_output.clone()];
+                search_path.extend(self.host_dylib_path.clone());
+                search_path
+            }
+            Some(kind) => {
+                let mut search_path =
+                    super::filter_dynamic_search_path(self.native_dirs.iter(), &self.root_output);
+                search_path.push(self.deps_output.clone());
+                search_path.push(self.root_output.clone());
+                if let Some(Some(path)) = self.target_dylib_paths.get(&kind) {
+                    search_path.push(path.clone());
+                }
+                search_path
+            }
         };
 
-        search_path.extend(util::dylib_path().into_iter());
-        let search_path = join_paths(&search_path, util::dylib_path_envvar())?;
+        let host_platform = self.config.host_platform()?;
+        search_path.extend(host_platform.dylib_path());
+        let search_path = join_paths(&search_path, host_platform.dylib_path_envvar())?;
 
-        cmd.env(util::dylib_path_envvar(), &search_path);
+        cmd.env(host_platform.dylib_path_envvar(), &search_path);
         if let Some(env) = self.extra_env.get(&pkg.package_id()) {
             for &(ref k, ref v) in env {
                 cmd.env(k, v);
@@ -256,8 +313,8 @@ fn pre_version_component(v: &Version) -> String {
     ret
 }
 
-fn target_runner(bcx: &BuildContext) -> CargoResult<Option<(PathBuf, Vec<String>)>> {
-    let target = bcx.target_triple();
+fn target_runner(bcx: &BuildContext, kind: Kind) -> CargoResult<Option<(PathBuf, Vec<String>)>> {
+    let target = bcx.triple(kind);
 
     // try target.{}.runner
     let key = format!("target.{}.runner", target);
@@ -266,7 +323,7 @@ fn target_runner(bcx: &BuildContext) -> CargoResult<Option<(PathBuf, Vec<String>
     }
 
     // try target.'cfg(...)'.runner
-    if let Some(target_cfg) = bcx.target_info.cfg() {
+    if let Some(target_cfg) = bcx.info(kind).cfg() {
         if let Some(table) = bcx.config.get_table("target")? {
             let mut matching_runner = None;
 
@@ -7,9 +7,11 @@ use std::sync::Arc;
 
 use lazycell::LazyCell;
 
-use super::{BuildContext, Context, FileFlavor, Kind, Layout, Unit};
+use super::{BuildContext, CompileMode, Context, FileFlavor, Kind, Layout, Unit};
+use crate::core::compiler::EmitKind;
+use crate::core::interning::InternedString;
 use crate::core::{TargetKind, Workspace};
-use crate::util::{self, CargoResult};
+use crate::util::{self, CargoResult, CargoResultExt};
 
 #[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Metadata(u64);
@@ -23,8 +25,9 @@ impl fmt::Display for Metadata {
 pub struct CompilationFiles<'a, 'cfg: 'a> {
     /// The target directory layout for the host (and target if it is the same as host)
     pub(super) host: Layout,
-    /// The target directory layout for the target (if different from then host)
-    pub(super) target: Option<Layout>,
+    /// The target directory layouts for each of the targets passed with
+    /// `--target`
+    pub(super) target: HashMap<InternedString, Layout>,
     /// Additional directory to include a copy of the outputs.
     export_dir: Option<PathBuf>,
     /// The root targets requested by the user on the command line (does not
@@ -63,7 +66,7 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
     pub(super) fn new(
         roots: &[Unit<'a>],
         host: Layout,
-        target: Option<Layout>,
+        target: HashMap<InternedString, Layout>,
         export_dir: Option<PathBuf>,
         ws: &'a Workspace<'cfg>,
         cx: &Context<'a, 'cfg>,
@@ -91,8 +94,8 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
     /// Returns the appropriate directory layout for either a plugin or not.
     pub fn layout(&self, kind: Kind) -> &Layout {
         match kind {
-            Kind::Host => &self.host,
-            Kind::Target => self.target.as_ref().unwrap_or(&self.host),
+            Kind::Host | Kind::Target(None) => &self.host,
+            Kind::Target(Some(triple)) => &self.target[&triple],
         }
     }
 
@@ -173,6 +176,33 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
         self.layout(unit.kind).build().join(dir).join("out")
     }
 
+    /// Returns the persistent cache directory of the build script run by
+    /// `unit`, in `build-cache` at the root of the target directory, where
+    /// `cargo clean -p` and `cargo clean --release` leave it.
+    ///
+    /// It's keyed by the package id, the features enabled for the package,
+    /// the target triple the script builds for and the version of rustc, so
+    /// a new directory is used when any of them changes. The profile isn't
+    /// part of the key, so debug and release builds share it, and the script
+    /// locks it with a `.lock` file next to it while it runs.
+    pub fn build_script_cache_dir(&self, unit: &Unit<'a>, bcx: &BuildContext<'a, '_>) -> PathBuf {
+        assert!(unit.target.is_custom_build());
+        assert!(unit.mode.is_run_custom_build());
+        let pkg_id = unit.pkg.package_id();
+        let features = bcx.resolve_of(pkg_id).features_sorted(pkg_id);
+        let key = util::short_hash(&(
+            pkg_id.stable_hash(self.ws.root()),
+            features,
+            bcx.triple(unit.kind),
+            &bcx.rustc.verbose_version,
+        ));
+        self.ws
+            .target_dir()
+            .into_path_unlocked()
+            .join("build-cache")
+            .join(format!("{}-{}", pkg_id.name(), key))
+    }
+
     /// Returns the file stem for a given target/profile combo (with metadata)
     pub fn file_stem(&self, unit: &Unit<'a>) -> String {
         match self.metas[unit] {
@@ -239,6 +269,36 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
         }
     }
 
+    /// The extra files requested with `--emit` that rustc writes for `unit`,
+    /// only the units of the packages selected on the command line which
+    /// run codegen write them.
+    pub fn extra_emit<'b>(&self, unit: &Unit<'a>, bcx: &'b BuildContext) -> &'b [EmitKind] {
+        let codegen = match unit.mode {
+            CompileMode::Build | CompileMode::Test | CompileMode::Bench => true,
+            _ => false,
+        };
+        if codegen && !unit.target.is_custom_build() && self.roots.contains(unit) {
+            &bcx.build_config.emit
+        } else {
+            &[]
+        }
+    }
+
+    /// The directory the files requested with `--emit` for `unit` are
+    /// collected in, like `target/debug/emit/bin`.
+    fn emit_dir(&self, unit: &Unit<'a>) -> PathBuf {
+        let kind = if unit.mode.is_any_test() {
+            "test"
+        } else {
+            match *unit.target.kind() {
+                TargetKind::Lib(..) => "lib",
+                TargetKind::ExampleBin | TargetKind::ExampleLib(..) => "example",
+                _ => "bin",
+            }
+        };
+        self.layout(unit.kind).dest().join("emit").join(kind)
+    }
+
     fn calc_outputs(
         &self,
         unit: &Unit<'a>,
@@ -250,7 +310,7 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
         let info = if unit.target.for_host() {
             &bcx.host_info
         } else {
-            &bcx.target_info
+            bcx.info(unit.kind)
         };
 
         let mut ret = Vec::new();
@@ -264,7 +324,7 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
                     path,
                     hardlink: None,
                     export_path: None,
-                    flavor: FileFlavor::Linkable,
+                    flavor: FileFlavor::Linkable { rmeta: false },
                 });
             } else {
                 let mut add = |crate_type: &str, flavor: FileFlavor| -> CargoResult<()> {
@@ -273,12 +333,20 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
                     } else {
                         crate_type
                     };
-                    let file_types = info.file_types(
-                        crate_type,
-                        flavor,
-                        unit.target.kind(),
-                        bcx.target_triple(),
-                    )?;
+                    let file_types = info
+                        .file_types(
+                            crate_type,
+                            flavor,
+                            unit.target.kind(),
+                            bcx.triple(unit.kind),
+                            &bcx.rustc,
+                        )
+                        .chain_err(|| {
+                            format!(
+                                "failed to learn about the files of crate-type {} of `{}`",
+                                crate_type, unit.pkg
+                            )
+                        })?;
 
                     match file_types {
                         Some(types) => {
@@ -328,16 +396,52 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
                             add(
                                 kind.crate_type(),
                                 if kind.linkable() {
-                                    FileFlavor::Linkable
+                                    FileFlavor::Linkable { rmeta: false }
                                 } else {
                                     FileFlavor::Normal
                                 },
                             )?;
                         }
+                        if bcx.emits_rmeta(unit) {
+                            let path = out_dir.join(format!("lib{}.rmeta", file_stem));
+                            ret.push(OutputFile {
+                                path,
+                                hardlink: None,
+                                export_path: None,
+                                flavor: FileFlavor::Linkable { rmeta: true },
+                            });
+                        }
                     }
                 }
             }
         }
+        if !ret.is_empty() {
+            for kind in self.extra_emit(unit, bcx) {
+                let name = format!("{}.{}", unit.target.crate_name(), kind.extension());
+                ret.push(OutputFile {
+                    path: out_dir.join(format!("{}.{}", file_stem, kind.extension())),
+                    hardlink: Some(self.emit_dir(unit).join(name)),
+                    export_path: None,
+                    flavor: FileFlavor::Emitted,
+                });
+            }
+        }
+        if bcx.post_link(unit.kind).is_some() && unit.mode == CompileMode::Build {
+            let post_link = ret
+                .iter()
+                .filter(|output| {
+                    output.flavor == FileFlavor::Normal
+                        && output.path.extension().map_or(false, |ext| ext == "wasm")
+                })
+                .map(|output| OutputFile {
+                    path: output.path.with_extension("post-link"),
+                    hardlink: output.hardlink.as_ref().map(|p| p.with_extension("post-link")),
+                    export_path: output.export_path.as_ref().map(|p| p.with_extension("post-link")),
+                    flavor: FileFlavor::PostLink,
+                })
+                .collect::<Vec<_>>();
+            ret.extend(post_link);
+        }
         if ret.is_empty() {
             if !unsupported.is_empty() {
                 bail!(
@@ -345,14 +449,14 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
                      does not support these crate types",
                     unsupported.join(", "),
                     unit.pkg,
-                    bcx.target_triple()
+                    bcx.triple(unit.kind)
                 )
             }
             bail!(
                 "cannot compile `{}` as the target `{}` does not \
                  support any of the output crate types",
                 unit.pkg,
-                bcx.target_triple()
+                bcx.triple(unit.kind)
             );
         }
         info!("Target filenames: {:?}", ret);
@@ -406,7 +510,7 @@ fn compute_metadata<'a, 'cfg>(
     if !(unit.mode.is_any_test() || unit.mode.is_check())
         && (unit.target.is_dylib()
             || unit.target.is_cdylib()
-            || (unit.target.is_bin() && bcx.target_triple().starts_with("wasm32-")))
+            || (unit.target.is_bin() && bcx.triple(unit.kind).starts_with("wasm32-")))
         && unit.pkg.package_id().source_id().is_path()
         && __cargo_default_lib_metadata.is_err()
     {
@@ -440,7 +544,7 @@ fn compute_metadata<'a, 'cfg>(
 
     // Also mix in enabled features to our metadata. This'll ensure that
     // when changing feature sets each lib is separately cached.
-    bcx.resolve
+    bcx.resolve_of(unit.pkg.package_id())
         .features_sorted(unit.pkg.package_id())
         .hash(&mut hasher);
 
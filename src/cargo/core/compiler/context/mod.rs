@@ -1,4 +1,5 @@
 #![allow(deprecated)]
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fmt::Write;
@@ -8,16 +9,28 @@ use std::sync::Arc;
 use jobserver::Client;
 
 use crate::core::compiler::compilation;
+use crate::core::interning::InternedString;
 use crate::core::profiles::Profile;
-use crate::core::{Package, PackageId, Resolve, Target};
+use crate::core::{Package, PackageId, PackageIdSpec, Resolve, Target};
 use crate::util::errors::{CargoResult, CargoResultExt};
-use crate::util::{internal, profile, short_hash, Config};
+use crate::util::{profile, short_hash, Config};
 
+use super::artifact_manifest;
+use super::bloat::{self, BloatReport};
+use super::bug_report::{self, BugReport};
+use super::build_audit;
 use super::build_plan::BuildPlan;
 use super::custom_build::{self, BuildDeps, BuildScripts, BuildState};
+use super::dist;
+use super::failed_units;
 use super::fingerprint::Fingerprint;
+use super::inputs::{self, InputsReport};
 use super::job_queue::JobQueue;
 use super::layout::Layout;
+use super::lipo;
+use super::proc_macro_sandbox;
+use super::retention;
+use super::system_deps;
 use super::{BuildContext, Compilation, CompileMode, Executor, FileFlavor, Kind};
 
 mod unit_dependencies;
@@ -69,6 +82,12 @@ impl<'a> Unit<'a> {
     pub fn buildkey(&self) -> String {
         format!("{}-{}", self.pkg.name(), short_hash(self))
     }
+
+    /// Whether compiling this unit needs the object code of its dependencies,
+    /// rather than just their metadata.
+    pub fn requires_upstream_objects(&self) -> bool {
+        self.mode.is_any_test() || self.target.kind().requires_upstream_objects()
+    }
 }
 
 pub struct Context<'a, 'cfg: 'a> {
@@ -82,8 +101,14 @@ pub struct Context<'a, 'cfg: 'a> {
     pub build_scripts: HashMap<Unit<'a>, Arc<BuildScripts>>,
     pub links: Links,
     pub jobserver: Client,
+    /// The tracer build scripts are run under with
+    /// `build.audit-build-scripts`, if one is available.
+    pub build_script_tracer: Option<PathBuf>,
     primary_packages: HashSet<PackageId>,
     unit_dependencies: HashMap<Unit<'a>, Vec<Unit<'a>>>,
+    /// Units some dependent of which only needs their `.rmeta`, so they have
+    /// to report when it's ready.
+    rmeta_required: HashSet<Unit<'a>>,
     files: Option<CompilationFiles<'a, 'cfg>>,
     package_cache: HashMap<PackageId, &'a Package>,
 }
@@ -104,20 +129,35 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 .chain_err(|| "failed to create jobserver")?,
         };
 
+        let build_script_tracer = if bcx.build_config.audit_build_scripts {
+            let tracer = build_audit::find_tracer();
+            if tracer.is_none() {
+                config.shell().warn(
+                    "build scripts can't be audited, as `strace` isn't available \
+                     or tracing isn't permitted",
+                )?;
+            }
+            tracer
+        } else {
+            None
+        };
+
         Ok(Self {
             bcx,
             compilation: Compilation::new(bcx)?,
-            build_state: Arc::new(BuildState::new(&bcx.host_config, &bcx.target_config)),
+            build_state: Arc::new(BuildState::new(&bcx.host_config, &bcx.target_configs)),
             fingerprints: HashMap::new(),
             compiled: HashSet::new(),
             build_scripts: HashMap::new(),
             build_explicit_deps: HashMap::new(),
             links: Links::new(),
             jobserver,
+            build_script_tracer,
             build_script_overridden: HashSet::new(),
 
             primary_packages: HashSet::new(),
             unit_dependencies: HashMap::new(),
+            rmeta_required: HashSet::new(),
             files: None,
             package_cache: HashMap::new(),
         })
@@ -135,9 +175,20 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         let mut plan = BuildPlan::new();
         let build_plan = self.bcx.build_config.build_plan;
         self.prepare_units(export_dir, units)?;
+        let roots;
+        let units = if self.bcx.build_config.repeat_failed {
+            roots = failed_units::roots(self.bcx, self.unit_dependencies.keys())?;
+            &roots[..]
+        } else if let Some(ref spec) = self.bcx.build_config.dependency {
+            roots = dependency_roots(spec, self.unit_dependencies.keys())?;
+            &roots[..]
+        } else {
+            units
+        };
         self.prepare()?;
         custom_build::build_map(&mut self, units)?;
         self.check_collistions()?;
+        system_deps::check(self.bcx, self.unit_dependencies.keys())?;
 
         for unit in units.iter() {
             // Build up a list of pending jobs, each of which represent
@@ -151,6 +202,10 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
 
         // Now that we've figured out everything that we're going to do, do it!
         queue.execute(&mut self, &mut plan)?;
+        lipo::combine(&mut self, units)?;
+        artifact_manifest::write(&mut self, units)?;
+        dist::arrange(&mut self, units)?;
+        retention::enforce(&self, self.unit_dependencies.keys())?;
 
         if build_plan {
             plan.set_inputs(self.build_plan_inputs()?);
@@ -159,7 +214,10 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
 
         for unit in units.iter() {
             for output in self.outputs(unit)?.iter() {
-                if output.flavor == FileFlavor::DebugInfo {
+                if output.flavor == FileFlavor::DebugInfo
+                    || output.flavor == FileFlavor::Emitted
+                    || output.flavor == FileFlavor::PostLink
+                {
                     continue;
                 }
 
@@ -171,6 +229,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                         unit.target.kind().clone(),
                         unit.target.name().to_string(),
                         output.path.clone(),
+                        unit.kind,
                     ));
                 } else if unit.target.is_bin() || unit.target.is_bin_example() {
                     self.compilation.binaries.push(bindst.clone());
@@ -178,6 +237,13 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             }
 
             for dep in self.dep_targets(unit).iter() {
+                if dep.mode.is_run_custom_build() {
+                    let out_dir = self.files().build_script_out_dir(dep);
+                    self.compilation
+                        .out_dirs
+                        .insert(dep.pkg.package_id(), out_dir);
+                }
+
                 if !unit.target.is_lib() {
                     continue;
                 }
@@ -225,10 +291,11 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                     package: unit.pkg.clone(),
                     target: unit.target.clone(),
                     deps: doctest_deps,
+                    sandbox_dir: proc_macro_sandbox::working_dir(&self, unit),
                 });
             }
 
-            let feats = self.bcx.resolve.features(unit.pkg.package_id());
+            let feats = self.bcx.resolve_of(unit.pkg.package_id()).features(unit.pkg.package_id());
             if !feats.is_empty() {
                 self.compilation
                     .cfgs
@@ -240,6 +307,27 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                             .collect()
                     });
             }
+            let dependent_cfgs = {
+                let build_state = self.build_state.outputs.lock().unwrap();
+                super::dependent_cfg_deps(&self, unit)
+                    .into_iter()
+                    .filter_map(|(key, prefix)| build_state.get(&key).map(|o| (o, prefix)))
+                    .flat_map(|(output, prefix)| {
+                        output
+                            .dependent_cfgs
+                            .iter()
+                            .map(|cfg| format!("{}_{}", prefix, cfg))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+            };
+            if !dependent_cfgs.is_empty() {
+                self.compilation
+                    .cfgs
+                    .entry(unit.pkg.package_id())
+                    .or_insert_with(HashSet::new)
+                    .extend(dependent_cfgs);
+            }
             let rustdocflags = self.bcx.rustdocflags_args(unit)?;
             if !rustdocflags.is_empty() {
                 self.compilation
@@ -271,10 +359,34 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         Ok(self.compilation)
     }
 
+    /// Returns the inputs recorded by the last build of `units`, without
+    /// building anything.
+    pub fn inputs(mut self, units: &[Unit<'a>], external: bool) -> CargoResult<InputsReport> {
+        self.prepare_units(None, units)?;
+        inputs::unit_inputs(&mut self, units, external)
+    }
+
+    /// Returns what's needed to reproduce a problem with building `units`,
+    /// without building anything.
+    pub fn bug_report(mut self, units: &[Unit<'a>]) -> CargoResult<BugReport> {
+        self.prepare_units(None, units)?;
+        bug_report::bug_report(&mut self, units)
+    }
+
+    /// Returns how much the crates in the unit graph of the binaries among
+    /// `units` contribute to their size, without building anything.
+    pub fn bloat(mut self, units: &[Unit<'a>]) -> CargoResult<BloatReport> {
+        self.prepare_units(None, units)?;
+        bloat::bloat_report(&mut self, units)
+    }
+
     /// Returns the executable for the specified unit (if any).
     pub fn get_executable(&mut self, unit: &Unit<'a>) -> CargoResult<Option<PathBuf>> {
         for output in self.outputs(unit)?.iter() {
-            if output.flavor == FileFlavor::DebugInfo {
+            if output.flavor == FileFlavor::DebugInfo
+                || output.flavor == FileFlavor::Emitted
+                || output.flavor == FileFlavor::PostLink
+            {
                 continue;
             }
 
@@ -293,16 +405,13 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         export_dir: Option<PathBuf>,
         units: &[Unit<'a>],
     ) -> CargoResult<()> {
-        let dest = if self.bcx.build_config.release {
-            "release"
-        } else {
-            "debug"
-        };
+        let dest = self.bcx.build_config.profile_kind.dest();
         let host_layout = Layout::new(self.bcx.ws, None, dest)?;
-        let target_layout = match self.bcx.build_config.requested_target.as_ref() {
-            Some(target) => Some(Layout::new(self.bcx.ws, Some(target), dest)?),
-            None => None,
-        };
+        let mut target_layouts = HashMap::new();
+        for target in self.bcx.build_config.requested_targets.iter() {
+            let layout = Layout::new(self.bcx.ws, Some(target), dest)?;
+            target_layouts.insert(InternedString::new(target), layout);
+        }
         self.primary_packages
             .extend(units.iter().map(|u| u.pkg.package_id()));
 
@@ -312,10 +421,18 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             &mut self.unit_dependencies,
             &mut self.package_cache,
         )?;
+        let rmeta_required = self
+            .unit_dependencies
+            .iter()
+            .flat_map(|(parent, deps)| deps.iter().map(move |dep| (parent, dep)))
+            .filter(|&(parent, dep)| self.only_requires_rmeta(parent, dep))
+            .map(|(_, dep)| *dep)
+            .collect();
+        self.rmeta_required = rmeta_required;
         let files = CompilationFiles::new(
             units,
             host_layout,
-            target_layout,
+            target_layouts,
             export_dir,
             self.bcx.ws,
             self,
@@ -332,17 +449,17 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         self.files_mut()
             .host
             .prepare()
-            .chain_err(|| internal("couldn't prepare build directories"))?;
-        if let Some(ref mut target) = self.files.as_mut().unwrap().target {
+            .chain_err(|| "couldn't prepare build directories")?;
+        for target in self.files_mut().target.values_mut() {
             target
                 .prepare()
-                .chain_err(|| internal("couldn't prepare build directories"))?;
+                .chain_err(|| "couldn't prepare build directories")?;
         }
 
         self.compilation.host_deps_output = self.files_mut().host.deps().to_path_buf();
 
         let files = self.files.as_ref().unwrap();
-        let layout = files.target.as_ref().unwrap_or(&files.host);
+        let layout = files.layout(self.bcx.build_config.target_kinds()[0]);
         self.compilation.root_output = layout.dest().to_path_buf();
         self.compilation.deps_output = layout.deps().to_path_buf();
         Ok(())
@@ -386,6 +503,18 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         deps
     }
 
+    /// Whether `parent` can start compiling as soon as the metadata of `dep`
+    /// is available, without waiting for its rlib, with `build.pipelining`.
+    pub fn only_requires_rmeta(&self, parent: &Unit<'a>, dep: &Unit<'a>) -> bool {
+        self.bcx.emits_rmeta(parent) && self.bcx.emits_rmeta(dep)
+    }
+
+    /// Whether compiling `unit` has to report when its `.rmeta` is ready, as
+    /// some dependent only waits for that.
+    pub fn rmeta_required(&self, unit: &Unit<'a>) -> bool {
+        self.rmeta_required.contains(unit)
+    }
+
     pub fn incremental_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
         // There's a number of ways to configure incremental compilation right
         // now. In order of descending priority (first is highest priority) we
@@ -518,6 +647,48 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                     unit, other_unit))
             }
         };
+        // Paths which only differ in case are the same file on the
+        // case-insensitive filesystems of macOS and Windows, where one unit
+        // would silently overwrite the other's output. Other filesystems keep
+        // both, but the project wouldn't build on those platforms.
+        let mut case_collisions: HashMap<String, (Unit<'a>, PathBuf)> = HashMap::new();
+        let mut check_case_collision = |unit: &Unit<'a>, path: &PathBuf| -> CargoResult<()> {
+            let folded = path.to_string_lossy().to_lowercase();
+            let (other_unit, other_path) = match case_collisions.entry(folded) {
+                Entry::Occupied(e) => e.get().clone(),
+                Entry::Vacant(e) => {
+                    e.insert((*unit, path.clone()));
+                    return Ok(());
+                }
+            };
+            if other_path == *path {
+                return Ok(());
+            }
+            let msg = format!(
+                "The {} target `{}` in package `{}` has the same output \
+                 filename as the {} target `{}` in package `{}`, ignoring case.\n\
+                 Colliding filenames are: {} and {}\n\
+                 Consider changing their names to differ in more than case.",
+                unit.target.kind().description(),
+                unit.target.name(),
+                unit.pkg.package_id(),
+                other_unit.target.kind().description(),
+                other_unit.target.name(),
+                other_unit.pkg.package_id(),
+                other_path.display(),
+                path.display()
+            );
+            if self.files().layout(unit.kind).is_case_insensitive() {
+                bail!(
+                    "output filename collision on a case-insensitive filesystem.\n{}",
+                    msg
+                )
+            }
+            self.bcx.config.shell().warn(format!(
+                "output filenames would collide on a case-insensitive filesystem.\n{}",
+                msg
+            ))
+        };
         let mut keys = self
             .unit_dependencies
             .keys()
@@ -530,10 +701,12 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 if let Some(other_unit) = output_collisions.insert(output.path.clone(), unit) {
                     report_collision(unit, &other_unit, &output.path)?;
                 }
+                check_case_collision(unit, &output.path)?;
                 if let Some(hardlink) = output.hardlink.as_ref() {
                     if let Some(other_unit) = output_collisions.insert(hardlink.clone(), unit) {
                         report_collision(unit, &other_unit, hardlink)?;
                     }
+                    check_case_collision(unit, hardlink)?;
                 }
                 if let Some(ref export_path) = output.export_path {
                     if let Some(other_unit) = output_collisions.insert(export_path.clone(), unit) {
@@ -546,6 +719,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                             suggestion
                         ))?;
                     }
+                    check_case_collision(unit, export_path)?;
                 }
             }
         }
@@ -619,3 +793,35 @@ impl Links {
         Ok(())
     }
 }
+
+/// The library units of the dependency `spec` among `units`, the units of
+/// the build, to build instead of the units requested, with `--dependency`.
+/// They're built with the features, profile and target they have as a
+/// dependency of the packages requested.
+fn dependency_roots<'a, 'b>(
+    spec: &PackageIdSpec,
+    units: impl Iterator<Item = &'b Unit<'a>>,
+) -> CargoResult<Vec<Unit<'a>>>
+where
+    'a: 'b,
+{
+    let mut roots = units
+        .filter(|unit| unit.target.is_lib() && spec.matches(unit.pkg.package_id()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if roots.is_empty() {
+        bail!(
+            "`{}` isn't a dependency of the packages being built, \
+             see `cargo graph` for their dependencies",
+            spec
+        );
+    }
+    // Only one of several versions of a package may be picked.
+    let ids = roots
+        .iter()
+        .map(|unit| unit.pkg.package_id())
+        .collect::<HashSet<_>>();
+    spec.query(ids)?;
+    roots.sort();
+    Ok(roots)
+}
@@ -23,6 +23,7 @@ use crate::core::dependency::Kind as DepKind;
 use crate::core::package::Downloads;
 use crate::core::profiles::UnitFor;
 use crate::core::{Package, PackageId, Target};
+use crate::util::profile;
 use crate::CargoResult;
 
 struct State<'a: 'tmp, 'cfg: 'a, 'tmp> {
@@ -40,6 +41,7 @@ pub fn build_unit_dependencies<'a, 'cfg>(
     pkgs: &mut HashMap<PackageId, &'a Package>,
 ) -> CargoResult<()> {
     assert!(deps.is_empty(), "can only build unit deps once");
+    let _p = profile::start("building the unit graph");
 
     let mut state = State {
         bcx,
@@ -48,6 +50,9 @@ pub fn build_unit_dependencies<'a, 'cfg>(
         waiting_on_download: HashSet::new(),
         downloads: bcx.packages.enable_download()?,
     };
+    if let Some(ref std) = bcx.std {
+        state.pkgs.borrow_mut().extend(std.packages.iter());
+    }
 
     loop {
         for unit in roots.iter() {
@@ -128,7 +133,8 @@ fn compute_deps<'a, 'cfg, 'tmp>(
 
     let bcx = state.bcx;
     let id = unit.pkg.package_id();
-    let deps = bcx.resolve.deps(id).filter(|&(_id, deps)| {
+    let resolve = bcx.resolve_of(id);
+    let deps = resolve.deps(id).filter(|&(_id, deps)| {
         assert!(!deps.is_empty());
         deps.iter().any(|dep| {
             // If this target is a build command, then we only want build
@@ -156,7 +162,7 @@ fn compute_deps<'a, 'cfg, 'tmp>(
 
             // If the dependency is optional, then we're only activating it
             // if the corresponding feature was activated
-            if dep.is_optional() && !bcx.resolve.features(id).contains(&*dep.name_in_toml()) {
+            if dep.is_optional() && !resolve.features(id).contains(&*dep.name_in_toml()) {
                 return false;
             }
 
@@ -178,9 +184,10 @@ fn compute_deps<'a, 'cfg, 'tmp>(
         };
         let mode = check_or_build_mode(unit.mode, lib);
         let dep_unit_for = unit_for.with_for_host(lib.for_host());
-        let unit = new_unit(bcx, pkg, lib, dep_unit_for, unit.kind.for_target(lib), mode);
+        let unit = new_unit(bcx, pkg, lib, dep_unit_for, unit.kind.for_target(lib), mode)?;
         ret.push((unit, dep_unit_for));
     }
+    ret.extend(std_deps(unit, bcx));
 
     // If this target is a build script, then what we've collected so far is
     // all we need. If this isn't a build script, then it depends on the
@@ -197,7 +204,7 @@ fn compute_deps<'a, 'cfg, 'tmp>(
     if unit.target.is_lib() && unit.mode != CompileMode::Doctest {
         return Ok(ret);
     }
-    ret.extend(maybe_lib(unit, bcx, unit_for));
+    ret.extend(maybe_lib(unit, bcx, unit_for)?);
 
     // If any integration tests/benches are being run, make sure that
     // binaries are built as well.
@@ -205,33 +212,26 @@ fn compute_deps<'a, 'cfg, 'tmp>(
         && unit.mode.is_any_test()
         && (unit.target.is_test() || unit.target.is_bench())
     {
-        ret.extend(
-            unit.pkg
-                .targets()
-                .iter()
-                .filter(|t| {
-                    let no_required_features = Vec::new();
-
-                    t.is_bin() &&
-                        // Skip binaries with required features that have not been selected.
-                        t.required_features().unwrap_or(&no_required_features).iter().all(|f| {
-                            bcx.resolve.features(id).contains(f)
-                        })
+        let bins = unit.pkg.targets().iter().filter(|t| {
+            let no_required_features = Vec::new();
+
+            t.is_bin() &&
+                // Skip binaries with required features that have not been selected.
+                t.required_features().unwrap_or(&no_required_features).iter().all(|f| {
+                    resolve.features(id).contains(f)
                 })
-                .map(|t| {
-                    (
-                        new_unit(
-                            bcx,
-                            unit.pkg,
-                            t,
-                            UnitFor::new_normal(),
-                            unit.kind.for_target(t),
-                            CompileMode::Build,
-                        ),
-                        UnitFor::new_normal(),
-                    )
-                }),
-        );
+        });
+        for t in bins {
+            let bin = new_unit(
+                bcx,
+                unit.pkg,
+                t,
+                UnitFor::new_normal(),
+                unit.kind.for_target(t),
+                CompileMode::Build,
+            )?;
+            ret.push((bin, UnitFor::new_normal()));
+        }
     }
 
     Ok(ret)
@@ -261,7 +261,7 @@ fn compute_deps_custom_build<'a, 'cfg>(
         UnitFor::new_build(),
         Kind::Host, // build scripts always compiled for the host
         CompileMode::Build,
-    );
+    )?;
     // All dependencies of this unit should use profiles for custom
     // builds.
     Ok(vec![(unit, UnitFor::new_build())])
@@ -274,7 +274,7 @@ fn compute_deps_doc<'a, 'cfg, 'tmp>(
 ) -> CargoResult<Vec<(Unit<'a>, UnitFor)>> {
     let bcx = state.bcx;
     let deps = bcx
-        .resolve
+        .resolve_of(unit.pkg.package_id())
         .deps(unit.pkg.package_id())
         .filter(|&(_id, deps)| {
             deps.iter().any(|dep| match dep.kind() {
@@ -300,7 +300,7 @@ fn compute_deps_doc<'a, 'cfg, 'tmp>(
         // However, for plugins/proc-macros, deps should be built like normal.
         let mode = check_or_build_mode(unit.mode, lib);
         let dep_unit_for = UnitFor::new_normal().with_for_host(lib.for_host());
-        let lib_unit = new_unit(bcx, dep, lib, dep_unit_for, unit.kind.for_target(lib), mode);
+        let lib_unit = new_unit(bcx, dep, lib, dep_unit_for, unit.kind.for_target(lib), mode)?;
         ret.push((lib_unit, dep_unit_for));
         if let CompileMode::Doc { deps: true } = unit.mode {
             // Document this lib as well.
@@ -311,17 +311,18 @@ fn compute_deps_doc<'a, 'cfg, 'tmp>(
                 dep_unit_for,
                 unit.kind.for_target(lib),
                 unit.mode,
-            );
+            )?;
             ret.push((doc_unit, dep_unit_for));
         }
     }
+    ret.extend(std_deps(unit, bcx));
 
     // Be sure to build/run the build script for documented libraries as
     ret.extend(dep_build_script(unit, bcx));
 
     // If we document a binary, we need the library available
     if unit.target.is_bin() {
-        ret.extend(maybe_lib(unit, bcx, UnitFor::new_normal()));
+        ret.extend(maybe_lib(unit, bcx, UnitFor::new_normal())?);
     }
     Ok(ret)
 }
@@ -330,12 +331,15 @@ fn maybe_lib<'a>(
     unit: &Unit<'a>,
     bcx: &BuildContext,
     unit_for: UnitFor,
-) -> Option<(Unit<'a>, UnitFor)> {
-    unit.pkg.targets().iter().find(|t| t.linkable()).map(|t| {
-        let mode = check_or_build_mode(unit.mode, t);
-        let unit = new_unit(bcx, unit.pkg, t, unit_for, unit.kind.for_target(t), mode);
-        (unit, unit_for)
-    })
+) -> CargoResult<Option<(Unit<'a>, UnitFor)>> {
+    match unit.pkg.targets().iter().find(|t| t.linkable()) {
+        Some(t) => {
+            let mode = check_or_build_mode(unit.mode, t);
+            let unit = new_unit(bcx, unit.pkg, t, unit_for, unit.kind.for_target(t), mode)?;
+            Ok(Some((unit, unit_for)))
+        }
+        None => Ok(None),
+    }
 }
 
 /// If a build script is scheduled to be run for the package specified by
@@ -384,6 +388,20 @@ fn check_or_build_mode(mode: CompileMode, target: &Target) -> CompileMode {
     }
 }
 
+/// Returns the standard library crates built with `-Z build-std` which a unit
+/// compiled for a target links against, in place of those in the sysroot.
+fn std_deps<'a>(unit: &Unit<'a>, bcx: &BuildContext<'a, '_>) -> Vec<(Unit<'a>, UnitFor)> {
+    match bcx.std {
+        Some(ref std) if unit.kind != Kind::Host && !bcx.is_std(unit.pkg.package_id()) => std
+            .roots
+            .iter()
+            .filter(|root| root.kind == unit.kind)
+            .map(|&root| (root, UnitFor::new_normal()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
 fn new_unit<'a>(
     bcx: &BuildContext,
     pkg: &'a Package,
@@ -391,21 +409,21 @@ fn new_unit<'a>(
     unit_for: UnitFor,
     kind: Kind,
     mode: CompileMode,
-) -> Unit<'a> {
+) -> CargoResult<Unit<'a>> {
     let profile = bcx.profiles.get_profile(
         pkg.package_id(),
         bcx.ws.is_member(pkg),
         unit_for,
         mode,
-        bcx.build_config.release,
-    );
-    Unit {
+        bcx.build_config.profile_kind,
+    )?;
+    Ok(Unit {
         pkg,
         target,
         profile,
         kind,
         mode,
-    }
+    })
 }
 
 /// Fill in missing dependencies for units of the `RunCustomBuild`
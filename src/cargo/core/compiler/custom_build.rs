@@ -1,16 +1,19 @@
 use std::collections::hash_map::{Entry, HashMap};
 use std::collections::{BTreeSet, HashSet};
-use std::fs;
+use std::fs::{self, File, OpenOptions};
 use std::path::{Path, PathBuf};
 use std::str;
 use std::sync::{Arc, Mutex};
 
+use fs2::FileExt;
+
 use crate::core::PackageId;
-use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::errors::{CargoResult, CargoResultExt, ProcessError};
 use crate::util::machine_message;
 use crate::util::{self, internal, paths, profile};
 use crate::util::{Cfg, Freshness};
 
+use super::build_audit;
 use super::job::Work;
 use super::{fingerprint, Context, Kind, TargetConfig, Unit};
 
@@ -23,6 +26,9 @@ pub struct BuildOutput {
     pub library_links: Vec<String>,
     /// Various `--cfg` flags to pass to the compiler
     pub cfgs: Vec<String>,
+    /// Names of `--cfg` flags to pass to the compilation of packages which
+    /// directly depend on this one, namespaced by its `links` key.
+    pub dependent_cfgs: Vec<String>,
     /// Additional environment variables to run the compiler with.
     pub env: Vec<(String, String)>,
     /// Metadata to pass to the immediate dependencies
@@ -132,6 +138,7 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
         .expect("running a script not depending on an actual script");
     let script_dir = cx.files().build_script_dir(build_script_unit);
     let script_out_dir = cx.files().build_script_out_dir(unit);
+    let script_cache_dir = cx.files().build_script_cache_dir(unit, bcx);
     let build_plan = bcx.build_config.build_plan;
     let invocation_name = unit.buildkey();
 
@@ -153,20 +160,15 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
     let mut cmd = cx.compilation.host_process(to_exec, unit.pkg)?;
     let debug = unit.profile.debuginfo.unwrap_or(0) != 0;
     cmd.env("OUT_DIR", &script_out_dir)
+        .env("CARGO_CACHE_DIR", &script_cache_dir)
         .env("CARGO_MANIFEST_DIR", unit.pkg.root())
         .env("NUM_JOBS", &bcx.jobs().to_string())
-        .env(
-            "TARGET",
-            &match unit.kind {
-                Kind::Host => &bcx.host_triple(),
-                Kind::Target => bcx.target_triple(),
-            },
-        )
+        .env("TARGET", bcx.triple(unit.kind))
         .env("DEBUG", debug.to_string())
         .env("OPT_LEVEL", &unit.profile.opt_level.to_string())
         .env(
             "PROFILE",
-            if bcx.build_config.release {
+            if bcx.profiles.is_release(bcx.build_config.profile_kind) {
                 "release"
             } else {
                 "debug"
@@ -177,9 +179,43 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
         .env("RUSTDOC", &*bcx.config.rustdoc()?)
         .inherit_jobserver(&cx.jobserver);
 
-    if let Some(ref linker) = bcx.target_config.linker {
+    // The linker is the one of the target, even for scripts run on the host.
+    let linker_kind = match unit.kind {
+        Kind::Host => bcx.build_config.target_kinds()[0],
+        kind => kind,
+    };
+    if let Some(linker) = bcx.linker(linker_kind) {
         cmd.env("RUSTC_LINKER", linker);
     }
+    cmd.env("TARGET_LINKER", bcx.target_linker(linker_kind))
+        .env("TARGET_AR", bcx.target_ar(linker_kind));
+    if let Some(ref dir) = bcx.info(linker_kind).llvm_tools_dir {
+        cmd.env("LLVM_TOOLS_DIR", dir);
+    }
+
+    // Information about the platform the script is building for, so that
+    // probing doesn't accidentally look at the host when cross compiling.
+    if let Some(libdir) = bcx.sysroot_libdir(unit.kind) {
+        cmd.env("TARGET_SYSROOT_LIBDIR", libdir);
+    }
+    // The runner is the one of the target the script builds for, which for
+    // scripts built for the host is the requested target on the host, if any.
+    let runner_kind = match unit.kind {
+        Kind::Host => bcx
+            .build_config
+            .target_kinds()
+            .into_iter()
+            .find(|kind| bcx.triple(*kind) == bcx.host_triple()),
+        kind => Some(kind),
+    };
+    if let Some((runner, args)) = runner_kind.and_then(|k| cx.compilation.target_runner(k)) {
+        let mut value = runner.as_os_str().to_os_string();
+        for arg in args {
+            value.push(" ");
+            value.push(arg);
+        }
+        cmd.env("TARGET_RUNNER", value);
+    }
 
     if let Some(links) = unit.pkg.manifest().links() {
         cmd.env("CARGO_MANIFEST_LINKS", links);
@@ -187,7 +223,7 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
 
     // Be sure to pass along all enabled features for this package, this is the
     // last piece of statically known information that we have.
-    for feat in bcx.resolve.features(unit.pkg.package_id()).iter() {
+    for feat in bcx.resolve_of(unit.pkg.package_id()).features(unit.pkg.package_id()).iter() {
         cmd.env(&format!("CARGO_FEATURE_{}", super::envify(feat)), "1");
     }
 
@@ -218,6 +254,11 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
         }
     }
 
+    for (name, success) in run_probes(cx, unit)? {
+        let k = format!("CARGO_PROBE_{}", super::envify(&name));
+        cmd.env(&k, if success { "1" } else { "0" });
+    }
+
     // Gather the set of native dependencies that this package has along with
     // some other variables to close over.
     //
@@ -241,14 +282,18 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
     let pkg_name = unit.pkg.to_string();
     let build_state = Arc::clone(&cx.build_state);
     let id = unit.pkg.package_id();
-    let (output_file, err_file, root_output_file) = {
+    let (output_file, err_file, root_output_file, audit_file, trace_dir) = {
         let build_output_parent = script_out_dir.parent().unwrap();
         let output_file = build_output_parent.join("output");
         let err_file = build_output_parent.join("stderr");
         let root_output_file = build_output_parent.join("root-output");
-        (output_file, err_file, root_output_file)
+        let audit_file = build_output_parent.join("audit");
+        let trace_dir = build_output_parent.join("trace");
+        (output_file, err_file, root_output_file, audit_file, trace_dir)
     };
+    let tracer = cx.build_script_tracer.clone();
     let host_target_root = cx.files().target_root().to_path_buf();
+    let dylib_path_envvar = bcx.config.host_platform()?.dylib_path_envvar().to_string();
     let all = (
         id,
         pkg_name.clone(),
@@ -258,8 +303,12 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
     );
     let build_scripts = super::load_build_deps(cx, unit);
     let kind = unit.kind;
+    let has_links = unit.pkg.manifest().links().is_some();
+    let dependent_cfg = bcx.config.cli_unstable().dependent_cfg;
     let json_messages = bcx.build_config.json_messages();
     let extra_verbose = bcx.config.extra_verbose();
+    let log_output = bcx.build_config.log_dir.is_some() && !extra_verbose;
+    let build_env = bcx.build_config.build_env.clone();
 
     // Check to see if the build script has already run, and if it has keep
     // track of whether it has told us about some explicit dependencies
@@ -277,8 +326,8 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
     let deps = BuildDeps::new(&output_file, prev_output.as_ref());
     cx.build_explicit_deps.insert(*unit, deps);
 
-    fs::create_dir_all(&script_dir)?;
-    fs::create_dir_all(&script_out_dir)?;
+    paths::create_dir_all(&script_dir)?;
+    paths::create_dir_all(&script_out_dir)?;
 
     // Prepare the unit of "dirty work" which will actually run the custom build
     // command.
@@ -298,6 +347,14 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
                 )
             })?;
         }
+        paths::create_dir_all(&script_cache_dir)?;
+        // The runs of the script for different profiles share the cache, and
+        // may happen at the same time, in one build or in several.
+        let _cache_lock = if build_plan {
+            None
+        } else {
+            Some(lock_cache_dir(&script_cache_dir)?)
+        };
 
         // For all our native lib dependencies, pick up their metadata to pass
         // along to this custom build command. We're also careful to augment our
@@ -323,14 +380,29 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
                 }
             }
             if let Some(build_scripts) = build_scripts {
-                super::add_plugin_deps(&mut cmd, &build_state, &build_scripts, &host_target_root)?;
+                super::add_plugin_deps(
+                    &mut cmd,
+                    &build_state,
+                    &build_scripts,
+                    &host_target_root,
+                    &dylib_path_envvar,
+                )?;
             }
         }
 
+        build_env.apply(&mut cmd);
+
         // And now finally, run the build command itself!
         if build_plan {
             state.build_plan(invocation_name, cmd.clone(), Arc::new(Vec::new()));
         } else {
+            if let Some(ref tracer) = tracer {
+                if trace_dir.exists() {
+                    paths::remove_dir_all(&trace_dir)?;
+                }
+                paths::create_dir_all(&trace_dir)?;
+                cmd = build_audit::traced(&cmd, tracer, &trace_dir);
+            }
             state.running(&cmd);
             let output = if extra_verbose {
                 let prefix = format!("[{} {}] ", id.name(), id.version());
@@ -338,7 +410,33 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
             } else {
                 cmd.exec_with_output()
             };
+            // The output is only streamed when extra verbose, but the log of
+            // the unit gets it regardless.
+            if log_output {
+                let logged = match output {
+                    Ok(ref output) => Some(output),
+                    Err(ref e) => e
+                        .downcast_ref::<ProcessError>()
+                        .and_then(|e| e.output.as_ref()),
+                };
+                if let Some(logged) = logged {
+                    state.log_output(logged);
+                }
+            }
+            // What a failing script did is audited as well.
+            if tracer.is_some() {
+                build_audit::write_audit_log(id, &trace_dir, &audit_file)?;
+            }
             let output = output.map_err(|e| {
+                // Keep what a failing script printed to stderr, for `cargo
+                // report bug`. Its stdout isn't kept, as the next build reads
+                // the dependencies of the script from there.
+                let failed = e
+                    .downcast_ref::<ProcessError>()
+                    .and_then(|e| e.output.as_ref());
+                if let Some(failed) = failed {
+                    drop(paths::write(&err_file, &failed.stderr));
+                }
                 format_err!(
                     "failed to run custom build command for `{}`\n{}",
                     pkg_name,
@@ -356,8 +454,23 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
             paths::write(&output_file, &output.stdout)?;
             paths::write(&err_file, &output.stderr)?;
             paths::write(&root_output_file, util::path2bytes(&script_out_dir)?)?;
-            let parsed_output =
+            let mut parsed_output =
                 BuildOutput::parse(&output.stdout, &pkg_name, &script_out_dir, &script_out_dir)?;
+            if !parsed_output.dependent_cfgs.is_empty() {
+                if !dependent_cfg {
+                    parsed_output.warnings.push(
+                        "`cargo:dependent-cfg` is ignored because it requires \
+                         `-Z dependent-cfg`"
+                            .to_string(),
+                    );
+                } else if !has_links {
+                    parsed_output.warnings.push(
+                        "`cargo:dependent-cfg` is ignored because the package \
+                         has no `links` key"
+                            .to_string(),
+                    );
+                }
+            }
 
             if json_messages {
                 emit_build_output(&parsed_output, id);
@@ -393,11 +506,81 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
     Ok((dirty, fresh))
 }
 
+/// Compiles the probes declared in `[package.probes]` for the package of
+/// `unit`, returning whether each one succeeded.
+///
+/// Probes are small library crates compiled for the same target, edition and
+/// `RUSTFLAGS` as the package itself. Their results are stored in the rustc
+/// info cache, which is invalidated whenever the compiler changes, so a probe
+/// is only compiled once per compiler and target no matter how many build
+/// scripts ask for it.
+/// Waits until the cache directory `dir` of a build script is free, and
+/// locks it until the returned file is closed.
+fn lock_cache_dir(dir: &Path) -> CargoResult<File> {
+    let path = dir.with_extension("lock");
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(&path)
+        .chain_err(|| format!("failed to open `{}`", path.display()))?;
+    file.lock_exclusive()
+        .chain_err(|| format!("failed to lock `{}`", path.display()))?;
+    Ok(file)
+}
+
+fn run_probes<'a, 'cfg>(
+    cx: &Context<'a, 'cfg>,
+    unit: &Unit<'a>,
+) -> CargoResult<Vec<(String, bool)>> {
+    let probes = unit.pkg.manifest().probes();
+    if probes.is_empty() || cx.bcx.build_config.build_plan {
+        return Ok(Vec::new());
+    }
+    let bcx = &cx.bcx;
+    let dir = cx.files().layout(unit.kind).build().join(".probes");
+    fs::create_dir_all(&dir)?;
+    let rustflags = bcx.rustflags_args(unit)?;
+    let mut results = Vec::new();
+    for (name, probe) in probes {
+        let source = probe.source();
+        let crate_name = format!("probe_{}", util::short_hash(&source));
+        let file = dir.join(format!("{}.rs", crate_name));
+        paths::write(&file, source.as_bytes())?;
+
+        let mut cmd = bcx.rustc.process();
+        cmd.arg("--crate-name")
+            .arg(&crate_name)
+            .arg("--crate-type=lib")
+            .arg("--emit=metadata")
+            .arg(format!("--edition={}", unit.target.edition()))
+            .arg("--out-dir")
+            .arg(&dir)
+            .arg(&file);
+        if let Kind::Target(Some(target)) = unit.kind {
+            cmd.arg("--target").arg(&*target);
+        }
+        cmd.args(&rustflags);
+        let success = bcx
+            .rustc
+            .cached_success(&cmd)
+            .chain_err(|| format!("failed to run probe `{}` for `{}`", name, unit.pkg))?;
+        debug!("probe `{}` for {}: {}", name, unit.pkg, success);
+        results.push((name.clone(), success));
+    }
+    Ok(results)
+}
+
 impl BuildState {
-    pub fn new(host_config: &TargetConfig, target_config: &TargetConfig) -> BuildState {
+    pub fn new(
+        host_config: &TargetConfig,
+        target_configs: &HashMap<Kind, TargetConfig>,
+    ) -> BuildState {
         let mut overrides = HashMap::new();
         let i1 = host_config.overrides.iter().map(|p| (p, Kind::Host));
-        let i2 = target_config.overrides.iter().map(|p| (p, Kind::Target));
+        let i2 = target_configs
+            .iter()
+            .flat_map(|(kind, config)| config.overrides.iter().map(move |p| (p, *kind)));
         for ((name, output), kind) in i1.chain(i2) {
             overrides.insert((name.clone(), kind), output.clone());
         }
@@ -439,6 +622,7 @@ impl BuildOutput {
         let mut library_paths = Vec::new();
         let mut library_links = Vec::new();
         let mut cfgs = Vec::new();
+        let mut dependent_cfgs = Vec::new();
         let mut env = Vec::new();
         let mut metadata = Vec::new();
         let mut rerun_if_changed = Vec::new();
@@ -486,6 +670,10 @@ impl BuildOutput {
                 "rustc-link-lib" => library_links.push(value.to_string()),
                 "rustc-link-search" => library_paths.push(PathBuf::from(value)),
                 "rustc-cfg" => cfgs.push(value.to_string()),
+                "dependent-cfg" => {
+                    BuildOutput::check_dependent_cfg(&value, &whence)?;
+                    dependent_cfgs.push(value.to_string())
+                }
                 "rustc-env" => env.push(BuildOutput::parse_rustc_env(&value, &whence)?),
                 "warning" => warnings.push(value.to_string()),
                 "rerun-if-changed" => rerun_if_changed.push(PathBuf::from(value)),
@@ -498,6 +686,7 @@ impl BuildOutput {
             library_paths,
             library_links,
             cfgs,
+            dependent_cfgs,
             env,
             metadata,
             rerun_if_changed,
@@ -542,6 +731,24 @@ impl BuildOutput {
         Ok((library_paths, library_links))
     }
 
+    pub fn check_dependent_cfg(value: &str, whence: &str) -> CargoResult<()> {
+        let mut chars = value.chars();
+        let valid = match chars.next() {
+            Some(c) => {
+                (c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+            }
+            None => false,
+        };
+        if !valid {
+            bail!(
+                "Variable dependent-cfg must be a plain identifier in {}: `{}`",
+                whence,
+                value
+            )
+        }
+        Ok(())
+    }
+
     pub fn parse_rustc_env(value: &str, whence: &str) -> CargoResult<(String, String)> {
         let mut iter = value.splitn(2, '=');
         let name = iter.next();
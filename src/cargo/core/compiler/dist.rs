@@ -0,0 +1,230 @@
+//! The distribution layout of the artifacts of a build, for
+//! `cargo build --dist DIR`.
+//!
+//! Packaging a crate for a distribution, as a deb, an rpm or a zip, starts
+//! from its final artifacts, which are scattered in the target directory
+//! under names that depend on the profile, the target and its crate types.
+//! Each package selected on the command line gets a directory of its own in
+//! `DIR` instead, for each of the targets it was built for, like
+//! `DIR/foo-1.0.0-x86_64-unknown-linux-gnu`, with:
+//!
+//! * `bin`: its binaries;
+//! * `lib`: the dynamic and static libraries its library is built as, not
+//!   the rlibs, which are only of use to rustc;
+//! * `debug`: the debug info split out of them, like `.dSYM` or `.pdb`;
+//! * `doc`: its README and license files.
+//!
+//! Everything is copied, so that the layout outlives the target directory,
+//! and `DIR/dist.json` lists the files of each of the directories.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json;
+
+use super::{CompileMode, Context, FileFlavor, Kind, Unit};
+use crate::core::{Package, PackageId};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::paths;
+
+/// The version of the format of `dist.json`, bumped on incompatible changes.
+const VERSION: u32 = 1;
+
+/// The files of the root of a package copied to `doc`, besides the ones of
+/// the `readme` and `license-file` keys of its manifest, by prefix of their
+/// name in upper case.
+const DOC_FILES: &[&str] = &[
+    "README",
+    "LICENSE",
+    "LICENCE",
+    "COPYING",
+    "COPYRIGHT",
+    "NOTICE",
+];
+
+#[derive(Serialize)]
+struct DistManifest<'a> {
+    version: u32,
+    profile: &'a str,
+    packages: Vec<DistPackage<'a>>,
+}
+
+#[derive(Serialize)]
+struct DistPackage<'a> {
+    package_id: PackageId,
+    triple: &'a str,
+    /// The directory of the package, relative to the one of the manifest.
+    dir: String,
+    files: Vec<DistFile>,
+}
+
+#[derive(Serialize)]
+struct DistFile {
+    /// The path of the file, relative to the directory of the package.
+    path: PathBuf,
+    kind: DistFileKind,
+    /// The name of the target the file was built for, if any.
+    target: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+enum DistFileKind {
+    Bin,
+    Lib,
+    DebugInfo,
+    Doc,
+}
+
+impl DistFileKind {
+    fn dir(self) -> &'static str {
+        match self {
+            DistFileKind::Bin => "bin",
+            DistFileKind::Lib => "lib",
+            DistFileKind::DebugInfo => "debug",
+            DistFileKind::Doc => "doc",
+        }
+    }
+}
+
+/// Arranges the artifacts of `units`, the units requested on the command
+/// line, in the distribution layout, if `--dist` was passed.
+pub fn arrange<'a>(cx: &mut Context<'a, '_>, units: &[Unit<'a>]) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    let dist_dir = match bcx.build_config.dist_dir {
+        Some(ref dir) => dir,
+        None => return Ok(()),
+    };
+    if bcx.build_config.build_plan {
+        return Ok(());
+    }
+
+    // The artifacts of each package, by package and target kind.
+    let mut artifacts = BTreeMap::<(PackageId, Kind), (&Package, Vec<_>)>::new();
+    for unit in units {
+        if unit.mode != CompileMode::Build
+            || !(unit.target.is_bin() || unit.target.is_lib())
+            || unit.target.is_proc_macro()
+        {
+            continue;
+        }
+        let entry = artifacts
+            .entry((unit.pkg.package_id(), unit.kind))
+            .or_insert_with(|| (unit.pkg, Vec::new()));
+        for output in cx.outputs(unit)?.iter() {
+            let path = output.hardlink.as_ref().unwrap_or(&output.path);
+            let kind = match output.flavor {
+                FileFlavor::Normal if unit.target.is_bin() => DistFileKind::Bin,
+                FileFlavor::Normal => DistFileKind::Lib,
+                FileFlavor::Linkable { rmeta: false }
+                    if path.extension().map_or(true, |e| e != "rlib") =>
+                {
+                    DistFileKind::Lib
+                }
+                FileFlavor::DebugInfo => DistFileKind::DebugInfo,
+                _ => continue,
+            };
+            entry
+                .1
+                .push((kind, Some(unit.target.name().to_string()), path.clone()));
+        }
+    }
+
+    let mut packages = Vec::new();
+    for ((package_id, kind), (pkg, mut files)) in artifacts {
+        // Packages whose library is only built as an rlib have nothing to
+        // distribute.
+        if files.is_empty() {
+            continue;
+        }
+        for path in doc_files(pkg)? {
+            files.push((DistFileKind::Doc, None, path));
+        }
+        let triple = bcx.triple(kind);
+        let dir = format!("{}-{}-{}", pkg.name(), pkg.version(), triple);
+        let pkg_dir = dist_dir.join(&dir);
+        if pkg_dir.exists() {
+            paths::remove_dir_all(&pkg_dir)?;
+        }
+        files.sort();
+        let mut dist_files = Vec::new();
+        for (file_kind, target, src) in files {
+            let path = Path::new(file_kind.dir()).join(src.file_name().unwrap());
+            copy(&src, &pkg_dir.join(&path))?;
+            dist_files.push(DistFile {
+                path,
+                kind: file_kind,
+                target,
+            });
+        }
+        packages.push(DistPackage {
+            package_id,
+            triple,
+            dir,
+            files: dist_files,
+        });
+    }
+
+    let manifest = DistManifest {
+        version: VERSION,
+        profile: bcx.build_config.profile_kind.dest(),
+        packages,
+    };
+    paths::create_dir_all(dist_dir)?;
+    let json = serde_json::to_string_pretty(&manifest)?;
+    paths::write(&dist_dir.join("dist.json"), json.as_bytes())
+}
+
+/// The README and license files of `pkg`.
+fn doc_files(pkg: &Package) -> CargoResult<Vec<PathBuf>> {
+    let root = pkg.root();
+    let metadata = pkg.manifest().metadata();
+    let mut files = Vec::new();
+    for file in metadata.readme.iter().chain(metadata.license_file.iter()) {
+        let path = root.join(file);
+        if !path.is_file() {
+            bail!(
+                "`{}` of package `{}` doesn't exist, to copy it to the distribution",
+                file,
+                pkg.name()
+            );
+        }
+        files.push(path);
+    }
+    let entries =
+        fs::read_dir(root).chain_err(|| format!("failed to read `{}`", root.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_uppercase(),
+            None => continue,
+        };
+        if path.is_file()
+            && DOC_FILES.iter().any(|prefix| name.starts_with(prefix))
+            && !files.contains(&path)
+        {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Copies the file or the directory, like a `.dSYM` bundle, at `src` to
+/// `dst`.
+fn copy(src: &Path, dst: &Path) -> CargoResult<()> {
+    paths::create_dir_all(dst.parent().unwrap())?;
+    if src.is_dir() {
+        paths::create_dir_all(dst)?;
+        let entries =
+            fs::read_dir(src).chain_err(|| format!("failed to read `{}`", src.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            copy(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    fs::copy(src, dst)
+        .chain_err(|| format!("failed to copy `{}` to `{}`", src.display(), dst.display()))?;
+    Ok(())
+}
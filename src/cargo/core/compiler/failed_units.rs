@@ -0,0 +1,145 @@
+//! The units which failed in the previous build, for
+//! `cargo build --repeat-failed` and `cargo build --show-failed-command`.
+//!
+//! The failed units are written to `.failed-units.json` in the directory of
+//! the profile, like `target/debug/.failed-units.json`, along with the last
+//! command each of them ran, ready to be pasted into a shell. Building with
+//! `--repeat-failed` only builds these units, and whichever of their
+//! dependencies are dirty.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use serde_json;
+use shell_escape::escape;
+
+use super::job_queue::Key;
+use super::{BuildContext, Context, ProfileKind, Unit};
+use crate::core::Workspace;
+use crate::util::errors::CargoResult;
+use crate::util::{paths, short_hash, ProcessBuilder};
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct FailedUnit {
+    /// Identifies the unit in the next build.
+    id: String,
+    package_id: String,
+    unit: String,
+    command: Option<String>,
+}
+
+impl FailedUnit {
+    pub(super) fn new(key: &Key, cmd: Option<&ProcessBuilder>) -> FailedUnit {
+        FailedUnit {
+            id: short_hash(key),
+            package_id: key.pkg.to_string(),
+            unit: key.name_for_progress(),
+            command: cmd.map(shell_command),
+        }
+    }
+}
+
+fn path(ws: &Workspace, profile_kind: ProfileKind) -> PathBuf {
+    ws.target_dir()
+        .into_path_unlocked()
+        .join(profile_kind.dest())
+        .join(".failed-units.json")
+}
+
+fn read(ws: &Workspace, profile_kind: ProfileKind) -> CargoResult<Vec<FailedUnit>> {
+    let path = path(ws, profile_kind);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&paths::read(&path)?)?)
+}
+
+/// Remembers the units which failed in this build, forgetting the ones of
+/// the previous build.
+pub(super) fn write(cx: &Context, failed: Vec<FailedUnit>) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    let path = path(bcx.ws, bcx.build_config.profile_kind);
+    if failed.is_empty() {
+        if path.exists() {
+            paths::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+    paths::create_dir_all(path.parent().unwrap())?;
+    let json = serde_json::to_string_pretty(&failed)?;
+    paths::write(&path, json.as_bytes())
+}
+
+/// The units of the build, `units`, which failed in the previous build, to
+/// build instead of the units requested, with `--repeat-failed`.
+pub(super) fn roots<'a, 'b>(
+    bcx: &BuildContext,
+    units: impl Iterator<Item = &'b Unit<'a>>,
+) -> CargoResult<Vec<Unit<'a>>>
+where
+    'a: 'b,
+{
+    let failed = read(bcx.ws, bcx.build_config.profile_kind)?;
+    if failed.is_empty() {
+        bail!("no units failed in the previous build, there's nothing to repeat");
+    }
+    let ids = failed.iter().map(|f| &f.id[..]).collect::<HashSet<_>>();
+    let mut roots = units
+        .filter(|unit| ids.contains(&short_hash(&Key::new(unit))[..]))
+        .cloned()
+        .collect::<Vec<_>>();
+    if roots.is_empty() {
+        bail!(
+            "none of the units which failed in the previous build are part of \
+             this one, they were:\n  {}",
+            failed
+                .iter()
+                .map(|f| format!("{} ({})", f.unit, f.package_id))
+                .collect::<Vec<_>>()
+                .join("\n  ")
+        );
+    }
+    roots.sort();
+    Ok(roots)
+}
+
+/// Prints the last command each of the units which failed in the previous
+/// build ran, for `--show-failed-command`.
+pub fn show_failed_commands(ws: &Workspace, profile_kind: ProfileKind) -> CargoResult<()> {
+    let failed = read(ws, profile_kind)?;
+    if failed.is_empty() {
+        bail!("no units failed in the previous build");
+    }
+    for unit in failed {
+        ws.config()
+            .shell()
+            .status("Failed", format!("{} ({})", unit.unit, unit.package_id))?;
+        match unit.command {
+            Some(command) => println!("{}", command),
+            None => ws.config().shell().warn("the unit failed before running a command")?,
+        }
+    }
+    Ok(())
+}
+
+/// The command line running `cmd` from a shell, with its working directory
+/// and environment.
+pub(super) fn shell_command(cmd: &ProcessBuilder) -> String {
+    let mut line = String::new();
+    if let Some(cwd) = cmd.get_cwd() {
+        line.push_str(&format!("cd {} && ", escape(cwd.to_string_lossy())));
+    }
+    let mut envs = cmd.get_envs().iter().collect::<Vec<_>>();
+    envs.sort();
+    for (key, value) in envs {
+        if let Some(value) = value {
+            line.push_str(&format!("{}={} ", key, escape(value.to_string_lossy())));
+        }
+    }
+    line.push_str(&escape(cmd.get_program().to_string_lossy()));
+    for arg in cmd.get_args() {
+        line.push(' ');
+        line.push_str(&escape(arg.to_string_lossy()));
+    }
+    line
+}
@@ -13,7 +13,7 @@ use crate::core::{Edition, Package};
 use crate::util;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::paths;
-use crate::util::{internal, profile, Dirty, Fresh, Freshness};
+use crate::util::{internal, process, profile, Dirty, Fresh, Freshness};
 
 use super::custom_build::BuildDeps;
 use super::job::Work;
@@ -110,8 +110,9 @@ pub fn prepare_target<'a, 'cfg>(
 
     let allow_failure = bcx.extra_args_for(unit).is_some();
     let target_root = cx.files().target_root().to_path_buf();
+    let pkg_root = unit.pkg.root().to_path_buf();
     let write_fingerprint = Work::new(move |_| {
-        match fingerprint.update_local(&target_root) {
+        match fingerprint.update_local(&target_root, &pkg_root) {
             Ok(()) => {}
             Err(..) if allow_failure => return Ok(()),
             Err(e) => return Err(e),
@@ -171,6 +172,14 @@ pub struct Fingerprint {
     memoized_hash: Mutex<Option<u64>>,
     rustflags: Vec<String>,
     edition: Edition,
+    /// The environment variables `build.env-allowlist` passes on, with their
+    /// values, or the ones `build.env-scrub` removes.
+    #[serde(default)]
+    env: Vec<(String, Option<String>)>,
+    /// Whether `build.audit-build-scripts` is set, for the runs of build
+    /// scripts, which are repeated to write their audit log.
+    #[serde(default)]
+    audit: bool,
 }
 
 fn serialize_deps<S>(deps: &[DepFingerprint], ser: S) -> Result<S::Ok, S::Error>
@@ -202,6 +211,8 @@ where
                     memoized_hash: Mutex::new(Some(hash)),
                     edition: Edition::Edition2015,
                     rustflags: Vec::new(),
+                    env: Vec::new(),
+                    audit: false,
                 }),
             )
         })
@@ -212,6 +223,7 @@ where
 enum LocalFingerprint {
     Precalculated(String),
     MtimeBased(MtimeSlot, PathBuf),
+    ContentBased(ContentSlot, PathBuf),
     EnvBased(String, Option<String>),
 }
 
@@ -222,12 +234,23 @@ impl LocalFingerprint {
         let path = path.strip_prefix(root).unwrap_or(path);
         LocalFingerprint::MtimeBased(mtime, path.to_path_buf())
     }
+
+    fn content(root: &Path, hash: Option<u64>, path: &Path) -> LocalFingerprint {
+        let hash = ContentSlot(Mutex::new(hash));
+        assert!(path.is_absolute());
+        let path = path.strip_prefix(root).unwrap_or(path);
+        LocalFingerprint::ContentBased(hash, path.to_path_buf())
+    }
 }
 
 struct MtimeSlot(Mutex<Option<FileTime>>);
 
+/// The hash of the contents of all files listed in a dep-info file, `None` if
+/// the dep-info file or any of the files it lists doesn't exist.
+struct ContentSlot(Mutex<Option<u64>>);
+
 impl Fingerprint {
-    fn update_local(&self, root: &Path) -> CargoResult<()> {
+    fn update_local(&self, root: &Path, pkg_root: &Path) -> CargoResult<()> {
         let mut hash_busted = false;
         for local in self.local.iter() {
             match *local {
@@ -236,6 +259,11 @@ impl Fingerprint {
                     let mtime = paths::mtime(&path)?;
                     *slot.0.lock().unwrap() = Some(mtime);
                 }
+                LocalFingerprint::ContentBased(ref slot, ref path) => {
+                    let path = root.join(path);
+                    let hash = dep_info_content_hash(pkg_root, &path)?;
+                    *slot.0.lock().unwrap() = hash;
+                }
                 LocalFingerprint::EnvBased(..) | LocalFingerprint::Precalculated(..) => continue,
             }
             hash_busted = true;
@@ -279,6 +307,18 @@ impl Fingerprint {
         if self.rustflags != old.rustflags {
             bail!("RUSTFLAGS has changed")
         }
+        if self.env != old.env {
+            let (name, _) = self
+                .env
+                .iter()
+                .chain(old.env.iter())
+                .find(|var| !self.env.contains(var) || !old.env.contains(var))
+                .unwrap();
+            bail!("env var `{}` passed to the build has changed", name)
+        }
+        if self.audit != old.audit {
+            bail!("build.audit-build-scripts has changed")
+        }
         if self.local.len() != old.local.len() {
             bail!("local lens changed");
         }
@@ -319,6 +359,24 @@ impl Fingerprint {
                         )
                     }
                 }
+                (
+                    &LocalFingerprint::ContentBased(ref on_disk_hash, ref ap),
+                    &LocalFingerprint::ContentBased(ref previously_built_hash, ref bp),
+                ) => {
+                    let on_disk_hash = on_disk_hash.0.lock().unwrap();
+                    let previously_built_hash = previously_built_hash.0.lock().unwrap();
+
+                    if on_disk_hash.is_none() || *on_disk_hash != *previously_built_hash {
+                        bail!(
+                            "content based components have changed: previously {:?} now {:?}, \
+                             paths are {:?} and {:?}",
+                            *previously_built_hash,
+                            *on_disk_hash,
+                            ap,
+                            bp
+                        )
+                    }
+                }
                 (
                     &LocalFingerprint::EnvBased(ref akey, ref avalue),
                     &LocalFingerprint::EnvBased(ref bkey, ref bvalue),
@@ -363,10 +421,12 @@ impl hash::Hash for Fingerprint {
             ref local,
             edition,
             ref rustflags,
+            ref env,
+            audit,
             ..
         } = *self;
         (
-            rustc, features, target, path, profile, local, edition, rustflags,
+            rustc, features, target, path, profile, local, edition, rustflags, env, audit,
         )
             .hash(h);
 
@@ -411,13 +471,38 @@ impl<'de> de::Deserialize<'de> for MtimeSlot {
     }
 }
 
+impl hash::Hash for ContentSlot {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        self.0.lock().unwrap().hash(h)
+    }
+}
+
+impl ser::Serialize for ContentSlot {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        self.0.lock().unwrap().serialize(s)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for ContentSlot {
+    fn deserialize<D>(d: D) -> Result<ContentSlot, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let hash: Option<u64> = de::Deserialize::deserialize(d)?;
+        Ok(ContentSlot(Mutex::new(hash)))
+    }
+}
+
 /// Calculates the fingerprint for a package/target pair.
 ///
 /// This fingerprint is used by Cargo to learn about when information such as:
 ///
 /// * A non-path package changes (changes version, changes revision, etc).
 /// * Any dependency changes
-/// * The compiler changes
+/// * The compiler changes (or `rustdoc` for documentation)
 /// * The set of features a package is built with changes
 /// * The profile a target is compiled with changes (e.g. opt-level changes)
 ///
@@ -454,43 +539,70 @@ fn calculate<'a, 'cfg>(
     // And finally, calculate what our own local fingerprint is
     let local = if use_dep_info(unit) {
         let dep_info = dep_info_loc(cx, unit);
-        let mtime = dep_info_mtime_if_fresh(unit.pkg, &dep_info)?;
-        LocalFingerprint::mtime(cx.files().target_root(), mtime, &dep_info)
+        if bcx.config.cli_unstable().content_hash {
+            let hash = dep_info_content_hash(unit.pkg.root(), &dep_info)?;
+            LocalFingerprint::content(cx.files().target_root(), hash, &dep_info)
+        } else {
+            let mtime = dep_info_mtime_if_fresh(unit.pkg, &dep_info)?;
+            LocalFingerprint::mtime(cx.files().target_root(), mtime, &dep_info)
+        }
     } else {
         let fingerprint = pkg_fingerprint(&cx.bcx, unit.pkg)?;
         LocalFingerprint::Precalculated(fingerprint)
     };
     let mut deps = deps;
     deps.sort_by(|&(ref a, _, _), &(ref b, _, _)| a.cmp(b));
-    let extra_flags = if unit.mode.is_doc() {
-        bcx.rustdocflags_args(unit)?
+    let (compiler, extra_flags) = if unit.mode.is_doc() {
+        (
+            util::hash_u64(&(&bcx.rustc.verbose_version, rustdoc_version(bcx)?)),
+            bcx.rustdocflags_args(unit)?,
+        )
     } else {
-        bcx.rustflags_args(unit)?
+        (
+            util::hash_u64(&bcx.rustc.verbose_version),
+            bcx.rustflags_args(unit)?,
+        )
     };
+    // `--target-cpu native` means something else on another machine, so the
+    // features of the CPU are what matters.
+    let target_features = bcx.target_cpu(unit.kind).map(|_| bcx.target_features(unit.kind));
     let profile_hash = util::hash_u64(&(
         &unit.profile,
         unit.mode,
         bcx.extra_args_for(unit),
         cx.incremental_args(unit)?,
+        target_features,
+        bcx.post_link(unit.kind),
+        unit.pkg.manifest().windows_resources(),
     ));
     let fingerprint = Arc::new(Fingerprint {
-        rustc: util::hash_u64(&bcx.rustc.verbose_version),
+        rustc: compiler,
         target: util::hash_u64(&unit.target),
         profile: profile_hash,
         // Note that .0 is hashed here, not .1 which is the cwd. That doesn't
         // actually affect the output artifact so there's no need to hash it.
-        path: util::hash_u64(&super::path_args(&cx.bcx, unit).0),
-        features: format!("{:?}", bcx.resolve.features_sorted(unit.pkg.package_id())),
+        path: util::hash_u64(&super::path_args(cx, unit).0),
+        features: format!("{:?}", bcx.resolve_of(unit.pkg.package_id()).features_sorted(unit.pkg.package_id())),
         deps,
         local: vec![local],
         memoized_hash: Mutex::new(None),
         edition: unit.target.edition(),
         rustflags: extra_flags,
+        env: bcx.build_config.build_env.fingerprint(),
+        audit: false,
     });
     cx.fingerprints.insert(*unit, Arc::clone(&fingerprint));
     Ok(fingerprint)
 }
 
+/// The verbose version of `rustdoc`, which may not be the one which came with
+/// `rustc` if `RUSTDOC` is set.
+fn rustdoc_version(bcx: &BuildContext) -> CargoResult<String> {
+    let mut cmd = process(&*bcx.config.rustdoc()?);
+    cmd.arg("-vV");
+    Ok(bcx.rustc.cached_output(&cmd)?.0)
+}
+
 // We want to use the mtime for files if we're a path source, but if we're a
 // git/registry source, then the mtime of files may fluctuate, but they won't
 // change so long as the source itself remains constant (which is the
@@ -539,6 +651,8 @@ pub fn prepare_build_cmd<'a, 'cfg>(
         memoized_hash: Mutex::new(None),
         edition: Edition::Edition2015,
         rustflags: Vec::new(),
+        env: cx.bcx.build_config.build_env.fingerprint(),
+        audit: cx.bcx.build_config.audit_build_scripts,
     };
     let compare = compare_old_fingerprint(&loc, &fingerprint);
     log_compare(unit, &compare);
@@ -557,14 +671,16 @@ pub fn prepare_build_cmd<'a, 'cfg>(
     let key = (unit.pkg.package_id(), unit.kind);
     let pkg_root = unit.pkg.root().to_path_buf();
     let target_root = cx.files().target_root().to_path_buf();
+    let content_hash = cx.bcx.config.cli_unstable().content_hash;
     let write_fingerprint = Work::new(move |_| {
         if let Some(output_path) = output_path {
             let outputs = state.outputs.lock().unwrap();
             let outputs = &outputs[&key];
             if !outputs.rerun_if_changed.is_empty() || !outputs.rerun_if_env_changed.is_empty() {
                 let deps = BuildDeps::new(&output_path, Some(outputs));
-                fingerprint.local = local_fingerprints_deps(&deps, &target_root, &pkg_root);
-                fingerprint.update_local(&target_root)?;
+                fingerprint.local =
+                    local_fingerprints_deps(&deps, &target_root, &pkg_root, content_hash);
+                fingerprint.update_local(&target_root, &pkg_root)?;
             }
         }
         write_fingerprint(&loc, &fingerprint)
@@ -614,7 +730,12 @@ fn build_script_local_fingerprints<'a, 'cfg>(
     // dependencies as well as env vars listed as dependencies. Process them all
     // here.
     Ok((
-        local_fingerprints_deps(deps, cx.files().target_root(), unit.pkg.root()),
+        local_fingerprints_deps(
+            deps,
+            cx.files().target_root(),
+            unit.pkg.root(),
+            cx.bcx.config.cli_unstable().content_hash,
+        ),
         Some(output),
     ))
 }
@@ -623,14 +744,30 @@ fn local_fingerprints_deps(
     deps: &BuildDeps,
     target_root: &Path,
     pkg_root: &Path,
+    content_hash: bool,
 ) -> Vec<LocalFingerprint> {
     debug!("new local fingerprints deps");
     let mut local = Vec::new();
     if !deps.rerun_if_changed.is_empty() {
         let output = &deps.build_script_output;
         let deps = deps.rerun_if_changed.iter().map(|p| pkg_root.join(p));
-        let mtime = mtime_if_fresh(output, deps);
-        local.push(LocalFingerprint::mtime(target_root, mtime, output));
+        if content_hash {
+            // Like with mtimes the build script needs to be rerun if it hasn't
+            // been run yet, which is the case if its output doesn't exist.
+            let hash = if output.exists() {
+                files_content_hash(deps)
+            } else {
+                None
+            };
+            let s = format!(
+                "rerun-if-changed files with content hash: {}",
+                hash.map_or_else(|| "(missing)".to_string(), util::to_hex)
+            );
+            local.push(LocalFingerprint::Precalculated(s));
+        } else {
+            let mtime = mtime_if_fresh(output, deps);
+            local.push(LocalFingerprint::mtime(target_root, mtime, output));
+        }
     }
 
     for var in deps.rerun_if_env_changed.iter() {
@@ -663,6 +800,17 @@ pub fn prepare_init<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> Ca
     Ok(())
 }
 
+/// Returns the location of the fingerprint hash written by the last
+/// successful build of `unit`.
+pub fn fingerprint_loc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> PathBuf {
+    let dir = cx.files().fingerprint_dir(unit);
+    if unit.mode.is_run_custom_build() {
+        dir.join("build")
+    } else {
+        dir.join(&filename(cx, unit))
+    }
+}
+
 pub fn dep_info_loc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> PathBuf {
     cx.files()
         .fingerprint_dir(unit)
@@ -697,6 +845,10 @@ fn log_compare(unit: &Unit, compare: &CargoResult<()>) {
 
 // Parse the dep-info into a list of paths
 pub fn parse_dep_info(pkg: &Package, dep_info: &Path) -> CargoResult<Option<Vec<PathBuf>>> {
+    parse_dep_info_in(pkg.root(), dep_info)
+}
+
+fn parse_dep_info_in(pkg_root: &Path, dep_info: &Path) -> CargoResult<Option<Vec<PathBuf>>> {
     let data = match paths::read_bytes(dep_info) {
         Ok(data) => data,
         Err(_) => return Ok(None),
@@ -704,7 +856,7 @@ pub fn parse_dep_info(pkg: &Package, dep_info: &Path) -> CargoResult<Option<Vec<
     let paths = data
         .split(|&x| x == 0)
         .filter(|x| !x.is_empty())
-        .map(|p| util::bytes2path(p).map(|p| pkg.root().join(p)))
+        .map(|p| util::bytes2path(p).map(|p| pkg_root.join(p)))
         .collect::<Result<Vec<_>, _>>()?;
     if paths.is_empty() {
         Ok(None)
@@ -713,6 +865,29 @@ pub fn parse_dep_info(pkg: &Package, dep_info: &Path) -> CargoResult<Option<Vec<
     }
 }
 
+/// Returns the environment variables, along with their values at the time,
+/// that the last run of the build script `unit` was fingerprinted against.
+pub fn build_script_env_deps<'a, 'cfg>(
+    cx: &mut Context<'a, 'cfg>,
+    unit: &Unit<'a>,
+) -> CargoResult<Vec<(String, Option<String>)>> {
+    let loc = cx.files().fingerprint_dir(unit).join("build.json");
+    let contents = match paths::read(&loc) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let fingerprint: Fingerprint = serde_json::from_str(&contents)
+        .chain_err(|| internal(format!("failed to parse `{}`", loc.display())))?;
+    Ok(fingerprint
+        .local
+        .into_iter()
+        .filter_map(|local| match local {
+            LocalFingerprint::EnvBased(name, value) => Some((name, value)),
+            _ => None,
+        })
+        .collect())
+}
+
 fn dep_info_mtime_if_fresh(pkg: &Package, dep_info: &Path) -> CargoResult<Option<FileTime>> {
     if let Some(paths) = parse_dep_info(pkg, dep_info)? {
         Ok(mtime_if_fresh(dep_info, paths.iter()))
@@ -721,6 +896,37 @@ fn dep_info_mtime_if_fresh(pkg: &Package, dep_info: &Path) -> CargoResult<Option
     }
 }
 
+fn dep_info_content_hash(pkg_root: &Path, dep_info: &Path) -> CargoResult<Option<u64>> {
+    if let Some(paths) = parse_dep_info_in(pkg_root, dep_info)? {
+        Ok(files_content_hash(paths.iter()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Hashes the contents of `paths`, the hash doesn't depend on where the files
+/// are or when they were last modified so it can be compared across machines.
+///
+/// Returns `None` if any of the files can't be read.
+fn files_content_hash<I>(paths: I) -> Option<u64>
+where
+    I: IntoIterator,
+    I::Item: AsRef<Path>,
+{
+    let mut hashes = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        match paths::read_bytes(path) {
+            Ok(contents) => hashes.push(util::hash_u64(&contents)),
+            Err(..) => {
+                info!("stale: {} -- missing", path.display());
+                return None;
+            }
+        }
+    }
+    Some(util::hash_u64(&hashes))
+}
+
 fn pkg_fingerprint(bcx: &BuildContext, pkg: &Package) -> CargoResult<String> {
     let source_id = pkg.package_id().source_id();
     let sources = bcx.packages.sources();
@@ -812,6 +1018,10 @@ fn filename<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> String {
 /// The `rustc_cwd` argument is the absolute path to the cwd of the compiler
 /// when it was invoked.
 ///
+/// The `extra_deps` argument lists absolute paths of files the crate depends
+/// on which rustc doesn't know about, like the Windows resources linked into
+/// it.
+///
 /// The serialized Cargo format will contain a list of files, all of which are
 /// relative if they're under `root`. or absolute if they're elsewhere.
 pub fn translate_dep_info(
@@ -819,6 +1029,7 @@ pub fn translate_dep_info(
     cargo_dep_info: &Path,
     pkg_root: &Path,
     rustc_cwd: &Path,
+    extra_deps: &[PathBuf],
 ) -> CargoResult<()> {
     let target = parse_rustc_dep_info(rustc_dep_info)?;
     let deps = &target
@@ -827,7 +1038,8 @@ pub fn translate_dep_info(
         .1;
 
     let mut new_contents = Vec::new();
-    for file in deps {
+    let extra_deps = extra_deps.iter().map(|p| p.as_path());
+    for file in deps.iter().map(Path::new).chain(extra_deps) {
         let absolute = rustc_cwd.join(file);
         let path = absolute.strip_prefix(pkg_root).unwrap_or(&absolute);
         new_contents.extend(util::path2bytes(path)?);
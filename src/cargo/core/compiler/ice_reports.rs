@@ -0,0 +1,197 @@
+//! Reports of the crashes of rustc, its internal compiler errors (ICEs).
+//!
+//! When rustc panics while compiling a unit, the panic and its backtrace are
+//! easily lost among the output of the other jobs. Cargo then bundles what's
+//! needed to report the crash in
+//! `target/ice-reports/<package>-<target>-<timestamp>/`:
+//!
+//! * `command.sh`, the command which crashed, ready to be pasted into a shell,
+//! * `stderr.txt`, what rustc printed when it crashed. If that has no
+//!   backtrace, the command is run again with `RUST_BACKTRACE=1`, writing its
+//!   outputs to the `out` directory of the report rather than the target
+//!   directory, and what it printed then follows,
+//! * `report.txt`, the unit, the version of rustc, whether the command
+//!   crashed again if it was run again and whether the sources it compiled
+//!   can be had again to reproduce the crash.
+//!
+//! The build then fails with a short pointer to the report.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use failure::Error;
+
+use super::failed_units::shell_command;
+use super::job_queue::Key;
+use super::{Context, Unit};
+use crate::core::PackageId;
+use crate::util::errors::{CargoResult, ProcessError};
+use crate::util::{paths, ProcessBuilder};
+
+/// The exit code of rustc when it panics, compilation errors exit with 1.
+const ICE_EXIT_CODE: i32 = 101;
+
+/// What's needed to report a crash of rustc compiling a unit, prepared before
+/// it runs.
+pub(super) struct IceReporter {
+    /// The directory the reports are written to, `target/ice-reports`.
+    dir: PathBuf,
+    /// The name of the report, without its timestamp.
+    name: String,
+    /// The unit, like `foo(bin)`.
+    unit: String,
+    package_id: PackageId,
+    /// The crate root of the unit, `None` for a metabuild script.
+    src_path: Option<PathBuf>,
+    pkg_root: PathBuf,
+    rustc_version: String,
+}
+
+impl IceReporter {
+    pub(super) fn new(cx: &Context, unit: &Unit) -> IceReporter {
+        let bcx = cx.bcx;
+        let src_path = unit.target.src_path();
+        IceReporter {
+            dir: bcx.ws.target_dir().into_path_unlocked().join("ice-reports"),
+            name: format!("{}-{}", unit.pkg.name(), unit.target.name()),
+            unit: Key::new(unit).name_for_progress(),
+            package_id: unit.pkg.package_id(),
+            src_path: if src_path.is_path() {
+                Some(src_path.path().to_path_buf())
+            } else {
+                None
+            },
+            pkg_root: unit.pkg.root().to_path_buf(),
+            rustc_version: bcx.rustc.verbose_version.clone(),
+        }
+    }
+
+    /// Writes the report of the crash of `cmd` if `err`, the error of running
+    /// it, is one, returning `err` with a pointer to the report. Other errors
+    /// are returned as is. `stderr` is what `cmd` printed on stderr.
+    pub(super) fn check(&self, cmd: &ProcessBuilder, stderr: &[String], err: Error) -> Error {
+        let code = err
+            .downcast_ref::<ProcessError>()
+            .and_then(|perr| perr.exit)
+            .and_then(|exit| exit.code());
+        if code != Some(ICE_EXIT_CODE) {
+            return err;
+        }
+        let msg = match self.write(cmd, stderr) {
+            Ok(dir) => format!(
+                "rustc crashed compiling `{}`, which is a bug in the compiler\n\
+                 the command, its output and its backtrace are in `{}`, \
+                 please attach them to an issue at \
+                 https://github.com/rust-lang/rust/issues",
+                self.unit,
+                dir.display()
+            ),
+            Err(e) => format!(
+                "rustc crashed compiling `{}`, which is a bug in the compiler\n\
+                 its report couldn't be written: {}",
+                self.unit, e
+            ),
+        };
+        err.context(msg).into()
+    }
+
+    /// Writes the report, running `cmd` again for its backtrace if `stderr`
+    /// has none, and returns its directory.
+    fn write(&self, cmd: &ProcessBuilder, stderr: &[String]) -> CargoResult<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let dir = self.dir.join(format!("{}-{}", self.name, timestamp));
+        paths::create_dir_all(&dir)?;
+
+        let mut output = stderr.join("\n").into_bytes();
+        output.push(b'\n');
+        let has_backtrace = stderr
+            .iter()
+            .any(|line| line.trim_start().starts_with("stack backtrace:"));
+        let rerun = if has_backtrace {
+            "not needed, the output of the crash has its backtrace"
+        } else {
+            let (stderr, crashed_again) = run_again(cmd, &dir.join("out"))?;
+            output.extend_from_slice(b"\n--- rerun with RUST_BACKTRACE=1 ---\n");
+            output.extend_from_slice(&stderr);
+            if crashed_again {
+                "crashed again"
+            } else {
+                "didn't crash again, the crash may depend on the state of the target directory"
+            }
+        };
+
+        let input = if self.package_id.source_id().is_path() {
+            format!(
+                "not available: the sources in `{}` are local and may change after the \
+                 crash, archive them along with this report to reproduce it",
+                self.pkg_root.display()
+            )
+        } else {
+            format!(
+                "available: the sources of `{}` are the same wherever it's downloaded",
+                self.package_id
+            )
+        };
+        let crate_root = match self.src_path {
+            Some(ref path) => path.display().to_string(),
+            None => "a generated metabuild script".to_string(),
+        };
+        let report = format!(
+            "unit: {}\n\
+             package: {}\n\
+             crate root: {}\n\
+             input snapshot: {}\n\
+             rerun with RUST_BACKTRACE=1: {}\n\
+             rustc version:\n{}\n",
+            self.unit, self.package_id, crate_root, input, rerun, self.rustc_version
+        );
+        paths::write(&dir.join("command.sh"), shell_command(cmd).as_bytes())?;
+        paths::write(&dir.join("stderr.txt"), &output)?;
+        paths::write(&dir.join("report.txt"), report.as_bytes())?;
+        Ok(dir)
+    }
+}
+
+/// Runs `cmd` again with `RUST_BACKTRACE=1`, writing its outputs to `out_dir`
+/// so that those of the build aren't touched, and returns what it printed on
+/// stderr and whether it crashed again.
+fn run_again(cmd: &ProcessBuilder, out_dir: &Path) -> CargoResult<(Vec<u8>, bool)> {
+    paths::create_dir_all(out_dir)?;
+    let mut args = Vec::new();
+    let mut iter = cmd.get_args().iter();
+    while let Some(arg) = iter.next() {
+        args.push(arg.clone());
+        if arg == "--out-dir" {
+            if iter.next().is_some() {
+                args.push(out_dir.as_os_str().to_os_string());
+            }
+        } else if arg == "-C" {
+            match iter.next() {
+                Some(flag) if flag.to_string_lossy().starts_with("incremental=") => {
+                    let mut flag = OsString::from("incremental=");
+                    flag.push(out_dir.join("incremental"));
+                    args.push(flag);
+                }
+                Some(flag) => args.push(flag.clone()),
+                None => {}
+            }
+        }
+    }
+    let mut rerun = cmd.clone();
+    rerun.args_replace(&args).env("RUST_BACKTRACE", "1");
+    Ok(match rerun.exec_with_output() {
+        Ok(output) => (output.stderr, false),
+        Err(e) => match e.downcast::<ProcessError>() {
+            Ok(perr) => {
+                let crashed = perr.exit.and_then(|exit| exit.code()) == Some(ICE_EXIT_CODE);
+                let stderr = perr.output.map(|o| o.stderr).unwrap_or_default();
+                (stderr, crashed)
+            }
+            Err(e) => (e.to_string().into_bytes(), false),
+        },
+    })
+}
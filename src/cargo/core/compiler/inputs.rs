@@ -0,0 +1,146 @@
+//! Reporting of the inputs that the last build of a set of units depended on.
+//!
+//! This doesn't compile anything, it only reads back the dep-info files and
+//! fingerprints left behind in the target directory by a previous build. The
+//! result is intended for tools like file watchers or remote caches which
+//! want to know what a unit has to be rebuilt for without reimplementing
+//! Cargo's freshness checks.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::core::PackageId;
+use crate::util::{self, paths, CargoResult};
+
+use super::custom_build::BuildOutput;
+use super::{fingerprint, CompileMode, Context, Kind, Unit};
+
+/// The report produced by `cargo report inputs`.
+#[derive(Debug, Serialize)]
+pub struct InputsReport {
+    pub units: Vec<UnitInputs>,
+}
+
+/// The inputs recorded for a single unit.
+#[derive(Debug, Serialize)]
+pub struct UnitInputs {
+    pub package_id: PackageId,
+    pub target: String,
+    pub kind: Kind,
+    pub compile_mode: CompileMode,
+    /// Files the unit depended on, or `None` if it hasn't been built yet.
+    ///
+    /// For a build script which didn't print any `rerun-if-changed` lines
+    /// this is the root of the package, as any file in it may cause the
+    /// script to rerun.
+    pub files: Option<Vec<PathBuf>>,
+    /// Environment variables the unit depended on, along with the value they
+    /// had when it was last built.
+    pub env: Vec<EnvInput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnvInput {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+/// Collects the inputs of `units` and of all units they depend on which
+/// belong to the same package, such as the package's build script.
+///
+/// If `external` is set the inputs of all path dependencies of `units` which
+/// live outside of the workspace root are collected instead. Their changes
+/// are tracked just like those of workspace members, but it's easy to lose
+/// sight of them.
+pub fn unit_inputs<'a, 'cfg>(
+    cx: &mut Context<'a, 'cfg>,
+    units: &[Unit<'a>],
+    external: bool,
+) -> CargoResult<InputsReport> {
+    let mut visited = HashSet::new();
+    let mut ret = Vec::new();
+    for unit in units {
+        add_unit(cx, unit, external, &mut visited, &mut ret)?;
+    }
+    Ok(InputsReport { units: ret })
+}
+
+fn add_unit<'a, 'cfg>(
+    cx: &mut Context<'a, 'cfg>,
+    unit: &Unit<'a>,
+    external: bool,
+    visited: &mut HashSet<Unit<'a>>,
+    ret: &mut Vec<UnitInputs>,
+) -> CargoResult<()> {
+    if !visited.insert(*unit) {
+        return Ok(());
+    }
+    for dep in cx.dep_targets(unit) {
+        if external || dep.pkg.package_id() == unit.pkg.package_id() {
+            add_unit(cx, &dep, external, visited, ret)?;
+        }
+    }
+    if external && !is_external_path_dep(cx, unit) {
+        return Ok(());
+    }
+
+    let (files, env) = if unit.mode.is_run_custom_build() {
+        build_script_inputs(cx, unit)?
+    } else {
+        let dep_info = fingerprint::dep_info_loc(cx, unit);
+        (
+            fingerprint::parse_dep_info(unit.pkg, &dep_info)?,
+            Vec::new(),
+        )
+    };
+    ret.push(UnitInputs {
+        package_id: unit.pkg.package_id(),
+        target: unit.target.name().to_string(),
+        kind: unit.kind,
+        compile_mode: unit.mode,
+        files,
+        env,
+    });
+    Ok(())
+}
+
+fn is_external_path_dep<'a, 'cfg>(cx: &Context<'a, 'cfg>, unit: &Unit<'a>) -> bool {
+    unit.pkg.package_id().source_id().is_path() && !unit.pkg.root().starts_with(cx.bcx.ws.root())
+}
+
+fn build_script_inputs<'a, 'cfg>(
+    cx: &mut Context<'a, 'cfg>,
+    unit: &Unit<'a>,
+) -> CargoResult<(Option<Vec<PathBuf>>, Vec<EnvInput>)> {
+    let script_out_dir = cx.files().build_script_out_dir(unit);
+    let build_output_parent = script_out_dir.parent().unwrap();
+    let output_file = build_output_parent.join("output");
+    let root_output_file = build_output_parent.join("root-output");
+    if !output_file.exists() {
+        return Ok((None, Vec::new()));
+    }
+
+    let prev_script_out_dir = paths::read_bytes(&root_output_file)
+        .and_then(|bytes| util::bytes2path(&bytes))
+        .unwrap_or_else(|_| script_out_dir.clone());
+    let output = BuildOutput::parse_file(
+        &output_file,
+        &unit.pkg.to_string(),
+        &prev_script_out_dir,
+        &script_out_dir,
+    )?;
+    let files = if output.rerun_if_changed.is_empty() && output.rerun_if_env_changed.is_empty() {
+        vec![unit.pkg.root().to_path_buf()]
+    } else {
+        output
+            .rerun_if_changed
+            .iter()
+            .map(|p| unit.pkg.root().join(p))
+            .collect()
+    };
+    let env = fingerprint::build_script_env_deps(cx, unit)?
+        .into_iter()
+        .map(|(name, value)| EnvInput { name, value })
+        .collect();
+    Ok((Some(files), env))
+}
@@ -1,18 +1,22 @@
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 use std::collections::hash_map::HashMap;
 use std::collections::HashSet;
 use std::fmt;
 use std::io;
 use std::mem;
+use std::path::Path;
 use std::process::Output;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crossbeam_utils;
 use crossbeam_utils::thread::Scope;
 use jobserver::{Acquired, HelperThread};
 
 use crate::core::profiles::Profile;
-use crate::core::{PackageId, Target, TargetKind};
+use crate::core::{PackageId, Shell, Target, TargetKind, Verbosity};
 use crate::handle_error;
 use crate::util;
 use crate::util::diagnostic_server::{self, DiagnosticPrinter};
@@ -21,8 +25,11 @@ use crate::util::{Config, DependencyQueue, Dirty, Fresh, Freshness};
 use crate::util::{Progress, ProgressStyle};
 
 use super::context::OutputFile;
+use super::failed_units::{self, FailedUnit};
 use super::job::Job;
-use super::{BuildContext, BuildPlan, CompileMode, Context, Kind, Unit};
+use super::unit_logs::UnitLogs;
+use super::{BuildContext, BuildPlan, CompileMode, Context, Kind, ProfileKind, Unit};
+use super::WarningHandling;
 
 /// A management structure of the entire dependency graph to compile.
 ///
@@ -30,7 +37,7 @@ use super::{BuildContext, BuildPlan, CompileMode, Context, Kind, Unit};
 /// actual compilation step of each package. Packages enqueue units of work and
 /// then later on the entire graph is processed and compiled.
 pub struct JobQueue<'a> {
-    queue: DependencyQueue<Key<'a>, Vec<(Job, Freshness)>>,
+    queue: DependencyQueue<Key<'a>, Artifact, Vec<(Job, Freshness)>>,
     tx: Sender<Message<'a>>,
     rx: Receiver<Message<'a>>,
     active: Vec<Key<'a>>,
@@ -38,7 +45,32 @@ pub struct JobQueue<'a> {
     compiled: HashSet<PackageId>,
     documented: HashSet<PackageId>,
     counts: HashMap<PackageId, usize>,
-    is_release: bool,
+    profile_kind: ProfileKind,
+    /// Units whose rebuild was forced rather than caused by a change.
+    forced: HashSet<Key<'a>>,
+    /// When the first dirty job of each unit started running.
+    started: HashMap<Key<'a>, Instant>,
+    /// Units which took longer than `build.warn-slow-units` to build.
+    slow: Vec<(Key<'a>, Duration)>,
+    /// Units which report when their `.rmeta` is ready, as some dependent
+    /// only waits for that.
+    rmeta_required: HashSet<Key<'a>>,
+    /// The number of warnings rustc emitted for each unit, only counted for
+    /// the workspace members when `build.warnings` isn't `warn`.
+    warnings: HashMap<Key<'a>, usize>,
+    /// The last command each unit ran.
+    commands: HashMap<Key<'a>, ProcessBuilder>,
+    /// The units which failed, remembered for the next build.
+    failed: Vec<FailedUnit>,
+}
+
+/// What a unit waits for of one of its dependencies.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+enum Artifact {
+    /// The whole unit has to be finished.
+    All,
+    /// Only its `.rmeta` metadata is needed, with `build.pipelining`.
+    Metadata,
 }
 
 /// A helper structure for metadata about the state of a building package.
@@ -51,16 +83,57 @@ struct PendingBuild {
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash)]
-struct Key<'a> {
-    pkg: PackageId,
-    target: &'a Target,
+pub(super) struct Key<'a> {
+    pub(super) pkg: PackageId,
+    pub(super) target: &'a Target,
     profile: Profile,
-    kind: Kind,
-    mode: CompileMode,
+    pub(super) kind: Kind,
+    pub(super) mode: CompileMode,
+}
+
+/// Prints a status for a package, linking to its directory if it's a path
+/// package.
+fn package_status(config: &Config, status: &str, pkg: PackageId) -> CargoResult<()> {
+    let mut shell = config.shell();
+    if pkg.source_id().is_path() {
+        if let Ok(root) = pkg.source_id().url().to_file_path() {
+            let message = shell.hyperlink(&root, pkg);
+            return shell.status(status, message);
+        }
+    }
+    shell.status(status, pkg)
+}
+
+/// Links the location of a compiler diagnostic, like `--> src/lib.rs:1:5`,
+/// to its file if hyperlinks are enabled. Relative paths are relative to
+/// `root`, the directory rustc runs in.
+fn link_diagnostic_location<'s>(shell: &Shell, line: &'s str, root: &Path) -> Cow<'s, str> {
+    if !shell.hyperlinks() {
+        return Cow::Borrowed(line);
+    }
+    let mut start = match line.find("--> ").or_else(|| line.find("::: ")) {
+        Some(i) => i + 4,
+        None => return Cow::Borrowed(line),
+    };
+    // Skip the color escape sequences in front of the location.
+    while line[start..].starts_with("\x1B[") {
+        match line[start..].find('m') {
+            Some(i) => start += i + 1,
+            None => return Cow::Borrowed(line),
+        }
+    }
+    let end = line[start..].find('\x1B').map_or(line.len(), |i| start + i);
+    let location = &line[start..end];
+    let file = match location.rsplitn(3, ':').nth(2) {
+        Some(file) => file,
+        None => return Cow::Borrowed(line),
+    };
+    let link = shell.hyperlink(&root.join(file), location);
+    Cow::Owned(format!("{}{}{}", &line[..start], link, &line[end..]))
 }
 
 impl<'a> Key<'a> {
-    fn name_for_progress(&self) -> String {
+    pub(super) fn name_for_progress(&self) -> String {
         let pkg_name = self.pkg.name();
         match self.mode {
             CompileMode::Doc { .. } => format!("{}(doc)", pkg_name),
@@ -82,21 +155,32 @@ impl<'a> Key<'a> {
 
 pub struct JobState<'a> {
     tx: Sender<Message<'a>>,
+    key: Key<'a>,
+    /// Whether the job still has to report that its `.rmeta` is ready.
+    rmeta_required: Cell<bool>,
+    /// The number of warnings rustc emitted while running the job.
+    warnings: Cell<usize>,
+    /// The lines printed on stderr while running the job, for the report of
+    /// a crash of rustc.
+    stderr_lines: RefCell<Vec<String>>,
 }
 
 enum Message<'a> {
-    Run(String),
+    Run(Key<'a>, ProcessBuilder),
     BuildPlanMsg(String, ProcessBuilder, Arc<Vec<OutputFile>>),
-    Stdout(String),
-    Stderr(String),
+    Stdout(Key<'a>, String),
+    Stderr(Key<'a>, String),
+    /// Output which only goes to the log of the unit, with `build.log-dir`.
+    Log(Key<'a>, String),
     FixDiagnostic(diagnostic_server::Message),
     Token(io::Result<Acquired>),
-    Finish(Key<'a>, CargoResult<()>),
+    Finish(Key<'a>, Artifact, CargoResult<()>),
+    Warning(Key<'a>),
 }
 
 impl<'a> JobState<'a> {
     pub fn running(&self, cmd: &ProcessBuilder) {
-        let _ = self.tx.send(Message::Run(cmd.to_string()));
+        let _ = self.tx.send(Message::Run(self.key, cmd.clone()));
     }
 
     pub fn build_plan(
@@ -110,6 +194,56 @@ impl<'a> JobState<'a> {
             .send(Message::BuildPlanMsg(module_name, cmd, filenames));
     }
 
+    pub fn stdout(&self, out: String) {
+        let _ = self.tx.send(Message::Stdout(self.key, out));
+    }
+
+    pub fn stderr(&self, err: String) {
+        self.record_stderr(&err);
+        let _ = self.tx.send(Message::Stderr(self.key, err));
+    }
+
+    /// Records a line printed on stderr without going through `stderr`.
+    pub fn record_stderr(&self, line: &str) {
+        self.stderr_lines.borrow_mut().push(line.to_string());
+    }
+
+    /// The lines printed on stderr while running the job so far.
+    pub fn stderr_lines(&self) -> Vec<String> {
+        self.stderr_lines.borrow().clone()
+    }
+
+    /// Writes the output of a command which wasn't streamed to the log of
+    /// the unit, if there's one.
+    pub fn log_output(&self, output: &Output) {
+        for stream in &[&output.stdout, &output.stderr] {
+            for line in String::from_utf8_lossy(stream).lines() {
+                let _ = self.tx.send(Message::Log(self.key, line.to_string()));
+            }
+        }
+    }
+
+    /// Reports that the `.rmeta` of this unit is ready, which lets the
+    /// dependents only waiting for it start compiling.
+    pub fn rmeta_produced(&self) {
+        if self.rmeta_required.replace(false) {
+            let _ = self
+                .tx
+                .send(Message::Finish(self.key, Artifact::Metadata, Ok(())));
+        }
+    }
+
+    /// Records that rustc emitted a warning for this unit.
+    pub fn warning(&self) {
+        self.warnings.set(self.warnings.get() + 1);
+        let _ = self.tx.send(Message::Warning(self.key));
+    }
+
+    /// The number of warnings recorded with `warning` so far.
+    pub fn warnings(&self) -> usize {
+        self.warnings.get()
+    }
+
     pub fn capture_output(
         &self,
         cmd: &ProcessBuilder,
@@ -119,11 +253,11 @@ impl<'a> JobState<'a> {
         let prefix = prefix.unwrap_or_else(String::new);
         cmd.exec_with_streaming(
             &mut |out| {
-                let _ = self.tx.send(Message::Stdout(format!("{}{}", prefix, out)));
+                let _ = self.tx.send(Message::Stdout(self.key, format!("{}{}", prefix, out)));
                 Ok(())
             },
             &mut |err| {
-                let _ = self.tx.send(Message::Stderr(format!("{}{}", prefix, err)));
+                self.stderr(format!("{}{}", prefix, err));
                 Ok(())
             },
             capture_output,
@@ -143,10 +277,23 @@ impl<'a> JobQueue<'a> {
             compiled: HashSet::new(),
             documented: HashSet::new(),
             counts: HashMap::new(),
-            is_release: bcx.build_config.release,
+            profile_kind: bcx.build_config.profile_kind,
+            forced: HashSet::new(),
+            started: HashMap::new(),
+            slow: Vec::new(),
+            rmeta_required: HashSet::new(),
+            warnings: HashMap::new(),
+            commands: HashMap::new(),
+            failed: Vec::new(),
         }
     }
 
+    /// Records that `unit` is rebuilt because a rebuild was forced, for the
+    /// report about slow units.
+    pub fn force_rebuild(&mut self, unit: &Unit<'a>) {
+        self.forced.insert(Key::new(unit));
+    }
+
     pub fn enqueue<'cfg>(
         &mut self,
         cx: &Context<'a, 'cfg>,
@@ -160,6 +307,9 @@ impl<'a> JobQueue<'a> {
             .queue(Fresh, &key, Vec::new(), &deps)
             .push((job, fresh));
         *self.counts.entry(key.pkg).or_insert(0) += 1;
+        if cx.rmeta_required(unit) {
+            self.rmeta_required.insert(key);
+        }
         Ok(())
     }
 
@@ -233,6 +383,13 @@ impl<'a> JobQueue<'a> {
         let mut error = None;
         let mut progress = Progress::with_style("Building", ProgressStyle::Ratio, cx.bcx.config);
         let total = self.queue.len();
+        let mut logs = match cx.bcx.build_config.log_dir {
+            Some(ref dir) if !build_plan => {
+                let target_dir = cx.bcx.ws.target_dir().into_path_unlocked();
+                Some(UnitLogs::new(target_dir.join(dir))?)
+            }
+            _ => None,
+        };
         loop {
             // Dequeue as much work as we can, learning about everything
             // possible that can run. Note that this is also the point where we
@@ -287,28 +444,54 @@ impl<'a> JobQueue<'a> {
             progress.clear();
 
             match event {
-                Message::Run(cmd) => {
+                Message::Run(key, cmd) => {
+                    if let Some(ref mut logs) = logs {
+                        logs.command(&key, &cmd.to_string())?;
+                    }
                     cx.bcx
                         .config
                         .shell()
                         .verbose(|c| c.status("Running", &cmd))?;
+                    self.commands.insert(key, cmd);
                 }
                 Message::BuildPlanMsg(module_name, cmd, filenames) => {
                     plan.update(&module_name, &cmd, &filenames)?;
                 }
-                Message::Stdout(out) => {
+                Message::Stdout(key, out) => {
+                    if let Some(ref mut logs) = logs {
+                        logs.line(&key, &out)?;
+                    }
                     println!("{}", out);
                 }
-                Message::Stderr(err) => {
+                Message::Stderr(key, err) => {
+                    if let Some(ref mut logs) = logs {
+                        logs.line(&key, &err)?;
+                    }
                     let mut shell = cx.bcx.config.shell();
+                    let err = link_diagnostic_location(&shell, &err, cx.bcx.ws.root());
                     shell.print_ansi(err.as_bytes())?;
                     shell.err().write_all(b"\n")?;
                 }
+                Message::Log(key, line) => {
+                    if let Some(ref mut logs) = logs {
+                        logs.line(&key, &line)?;
+                    }
+                }
                 Message::FixDiagnostic(msg) => {
                     print.print(&msg)?;
                 }
-                Message::Finish(key, result) => {
+                Message::Finish(key, Artifact::Metadata, result) => {
+                    // The job is still running, only the dependents waiting
+                    // for its metadata can be started.
+                    info!("end (meta): {:?}", key);
+                    result?;
+                    self.finish_metadata(key);
+                }
+                Message::Finish(key, Artifact::All, result) => {
                     info!("end: {:?}", key);
+                    if let Some(ref mut logs) = logs {
+                        logs.finish(&key, result.is_ok());
+                    }
 
                     // self.active.remove_item(&key); // <- switch to this when stabilized.
                     let pos = self
@@ -324,6 +507,8 @@ impl<'a> JobQueue<'a> {
                     match result {
                         Ok(()) => self.finish(key, cx)?,
                         Err(e) => {
+                            self.failed
+                                .push(FailedUnit::new(&key, self.commands.get(&key)));
                             let msg = "The following warnings were emitted during compilation:";
                             self.emit_warnings(Some(msg), &key, cx)?;
 
@@ -340,14 +525,23 @@ impl<'a> JobQueue<'a> {
                         }
                     }
                 }
+                Message::Warning(key) => {
+                    *self.warnings.entry(key).or_insert(0) += 1;
+                }
                 Message::Token(acquired_token) => {
                     tokens.push(acquired_token.chain_err(|| "failed to acquire jobserver token")?);
                 }
             }
         }
         drop(progress);
+        if let Some(ref logs) = logs {
+            logs.write_index()?;
+        }
+        if !build_plan {
+            failed_units::write(cx, mem::replace(&mut self.failed, Vec::new()))?;
+        }
 
-        let build_type = if self.is_release { "release" } else { "dev" };
+        let build_type = self.profile_kind.name();
         // NOTE: This may be a bit inaccurate, since this may not display the
         // profile for what was actually built.  Profile overrides can change
         // these settings, and in some cases different targets are built with
@@ -355,7 +549,7 @@ impl<'a> JobQueue<'a> {
         // list of Units built, and maybe display a list of the different
         // profiles used.  However, to keep it simple and compatible with old
         // behavior, we just display what the base profile is.
-        let profile = cx.bcx.profiles.base_profile(self.is_release);
+        let profile = cx.bcx.profiles.base_profile(self.profile_kind)?;
         let mut opt_type = String::from(if profile.opt_level.as_str() == "0" {
             "unoptimized"
         } else {
@@ -368,12 +562,16 @@ impl<'a> JobQueue<'a> {
         let time_elapsed = util::elapsed(cx.bcx.config.creation_time().elapsed());
 
         if self.queue.is_empty() {
+            if cx.bcx.build_config.warnings == WarningHandling::Deny && !self.warnings.is_empty() {
+                return Err(self.denied_warnings());
+            }
             let message = format!(
                 "{} [{}] target(s) in {}",
                 build_type, opt_type, time_elapsed
             );
             if !build_plan {
                 cx.bcx.config.shell().status("Finished", message)?;
+                self.report_slow_units(cx)?;
             }
             Ok(())
         } else if let Some(e) = error {
@@ -401,9 +599,23 @@ impl<'a> JobQueue<'a> {
         *self.counts.get_mut(&key.pkg).unwrap() -= 1;
 
         let my_tx = self.tx.clone();
+        let rmeta_required = self.rmeta_required.contains(&key);
         let doit = move || {
-            let res = job.run(fresh, &JobState { tx: my_tx.clone() });
-            my_tx.send(Message::Finish(key, res)).unwrap();
+            let state = JobState {
+                tx: my_tx.clone(),
+                key,
+                rmeta_required: Cell::new(rmeta_required),
+                warnings: Cell::new(0),
+                stderr_lines: RefCell::new(Vec::new()),
+            };
+            let res = job.run(fresh, &state);
+            // A fresh job doesn't run rustc, and rustc may not have told us
+            // about the metadata, so make sure the dependents waiting for it
+            // are started before the job is finished.
+            if res.is_ok() {
+                state.rmeta_produced();
+            }
+            my_tx.send(Message::Finish(key, Artifact::All, res)).unwrap();
         };
 
         if !build_plan {
@@ -414,6 +626,7 @@ impl<'a> JobQueue<'a> {
         match fresh {
             Freshness::Fresh => doit(),
             Freshness::Dirty => {
+                self.started.entry(key).or_insert_with(Instant::now);
                 scope.spawn(move |_| doit());
             }
         }
@@ -453,10 +666,75 @@ impl<'a> JobQueue<'a> {
         state.amt -= 1;
         if state.amt == 0 {
             self.queue.finish(&key, state.fresh);
+            if let (Some(budget), Some(started)) =
+                (cx.bcx.build_config.slow_unit_budget, self.started.get(&key))
+            {
+                let elapsed = started.elapsed();
+                if elapsed > budget {
+                    self.slow.push((key, elapsed));
+                }
+            }
         }
         Ok(())
     }
 
+    /// Starts the dependents which only wait for the metadata of `key`.
+    fn finish_metadata(&mut self, key: Key<'a>) {
+        let fresh = self.pending[&key].fresh;
+        self.queue.finish_edge(&key, &Artifact::Metadata, fresh);
+    }
+
+    /// Warns about the units which exceeded the `build.warn-slow-units`
+    /// budget, slowest first.
+    fn report_slow_units(&mut self, cx: &Context) -> CargoResult<()> {
+        let budget = match cx.bcx.build_config.slow_unit_budget {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+        self.slow.sort_by(|a, b| b.1.cmp(&a.1));
+        let mut shell = cx.bcx.config.shell();
+        for &(ref key, elapsed) in self.slow.iter() {
+            let features = cx.bcx.resolve_of(key.pkg).features_sorted(key.pkg);
+            let features = if features.is_empty() {
+                "no features".to_string()
+            } else {
+                format!("features: {}", features.join(", "))
+            };
+            let forced = if self.forced.contains(key) {
+                ", forced dirty"
+            } else {
+                ""
+            };
+            shell.warn(format!(
+                "`{}` took {}, exceeding the build time budget of {} ({}{})",
+                key.name_for_progress(),
+                util::elapsed(elapsed),
+                util::elapsed(budget),
+                features,
+                forced
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// The error a build fails with when workspace members emitted warnings
+    /// and `build.warnings` is `deny`.
+    fn denied_warnings(&self) -> failure::Error {
+        let mut units = self
+            .warnings
+            .iter()
+            .map(|(key, &count)| {
+                let plural = if count == 1 { "" } else { "s" };
+                format!("`{}` emitted {} warning{}", key.name_for_progress(), count, plural)
+            })
+            .collect::<Vec<_>>();
+        units.sort();
+        format_err!(
+            "build failed because warnings are denied:\n  {}",
+            units.join("\n  ")
+        )
+    }
+
     // This isn't super trivial because we don't want to print loads and
     // loads of information to the console, but we also want to produce a
     // faithful representation of what's happening. This is somewhat nuanced
@@ -486,14 +764,14 @@ impl<'a> JobQueue<'a> {
                     // Skip Doctest
                     if !key.mode.is_any_test() {
                         self.documented.insert(key.pkg);
-                        config.shell().status("Documenting", key.pkg)?;
+                        package_status(config, "Documenting", key.pkg)?;
                     }
                 } else {
                     self.compiled.insert(key.pkg);
                     if key.mode.is_check() {
-                        config.shell().status("Checking", key.pkg)?;
+                        package_status(config, "Checking", key.pkg)?;
                     } else {
-                        config.shell().status("Compiling", key.pkg)?;
+                        package_status(config, "Compiling", key.pkg)?;
                     }
                 }
             }
@@ -503,7 +781,9 @@ impl<'a> JobQueue<'a> {
                     && !(key.mode == CompileMode::Doctest && self.compiled.contains(&key.pkg))
                 {
                     self.compiled.insert(key.pkg);
-                    config.shell().verbose(|c| c.status("Fresh", key.pkg))?;
+                    if config.shell().verbosity() == Verbosity::Verbose {
+                        package_status(config, "Fresh", key.pkg)?;
+                    }
                 }
             }
         }
@@ -512,7 +792,7 @@ impl<'a> JobQueue<'a> {
 }
 
 impl<'a> Key<'a> {
-    fn new(unit: &Unit<'a>) -> Key<'a> {
+    pub(super) fn new(unit: &Unit<'a>) -> Key<'a> {
         Key {
             pkg: unit.pkg.package_id(),
             target: unit.target,
@@ -522,27 +802,58 @@ impl<'a> Key<'a> {
         }
     }
 
-    fn dependencies<'cfg>(&self, cx: &Context<'a, 'cfg>) -> CargoResult<Vec<Key<'a>>> {
-        let unit = Unit {
+    fn dependencies<'cfg>(
+        &self,
+        cx: &Context<'a, 'cfg>,
+    ) -> CargoResult<Vec<(Key<'a>, Artifact)>> {
+        let parent = Unit {
             pkg: cx.get_package(self.pkg)?,
             target: self.target,
             profile: self.profile,
             kind: self.kind,
             mode: self.mode,
         };
-        let targets = cx.dep_targets(&unit);
-        Ok(targets
+        let targets = cx.dep_targets(&parent);
+        let mut deps = targets
             .iter()
             .filter_map(|unit| {
                 // Binaries aren't actually needed to *compile* tests, just to run
                 // them, so we don't include this dependency edge in the job graph.
                 if self.target.is_test() && unit.target.is_bin() {
                     None
+                } else if cx.only_requires_rmeta(&parent, unit) {
+                    Some((Key::new(unit), Artifact::Metadata))
                 } else {
-                    Some(Key::new(unit))
+                    Some((Key::new(unit), Artifact::All))
                 }
             })
-            .collect())
+            .collect::<HashMap<_, _>>();
+
+        // Linking needs the objects of every library down the graph, not only
+        // of the direct dependencies. With pipelining, a library only waits
+        // for the `.rmeta` of its own dependencies, so finishing it doesn't
+        // mean they are finished, and the dependent which links has to wait
+        // for all of them itself.
+        if parent.requires_upstream_objects() {
+            fn is_linked(unit: &Unit) -> bool {
+                unit.mode == CompileMode::Build && unit.target.is_lib() && !unit.target.for_host()
+            }
+            fn add_linked_libs<'a>(
+                cx: &Context<'a, '_>,
+                deps: &mut HashMap<Key<'a>, Artifact>,
+                unit: &Unit<'a>,
+            ) {
+                for dep in cx.dep_targets(unit).iter().filter(|dep| is_linked(dep)) {
+                    if deps.insert(Key::new(dep), Artifact::All) != Some(Artifact::All) {
+                        add_linked_libs(cx, deps, dep);
+                    }
+                }
+            }
+            for unit in targets.iter().filter(|unit| is_linked(unit)) {
+                add_linked_libs(cx, &mut deps, unit);
+            }
+        }
+        Ok(deps.into_iter().collect())
     }
 }
 
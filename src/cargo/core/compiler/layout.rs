@@ -50,11 +50,10 @@
 //! ```
 
 use std::fs;
-use std::io;
 use std::path::{Path, PathBuf};
 
 use crate::core::Workspace;
-use crate::util::{CargoResult, Config, FileLock, Filesystem};
+use crate::util::{paths, CargoResult, Config, FileLock, Filesystem};
 
 /// Contains the paths of all target output locations.
 ///
@@ -157,9 +156,9 @@ impl Layout {
     }
 
     /// Make sure all directories stored in the Layout exist on the filesystem.
-    pub fn prepare(&mut self) -> io::Result<()> {
+    pub fn prepare(&mut self) -> CargoResult<()> {
         if fs::metadata(&self.root).is_err() {
-            fs::create_dir_all(&self.root)?;
+            paths::create_dir_all(&self.root)?;
         }
 
         self.exclude_from_backups(&self.root);
@@ -173,14 +172,22 @@ impl Layout {
 
         return Ok(());
 
-        fn mkdir(dir: &Path) -> io::Result<()> {
+        fn mkdir(dir: &Path) -> CargoResult<()> {
             if fs::metadata(&dir).is_err() {
-                fs::create_dir(dir)?;
+                paths::create_dir_all(dir)?;
             }
             Ok(())
         }
     }
 
+    /// Whether the layout lives on a case-insensitive filesystem.
+    ///
+    /// This must be called after `prepare`.
+    pub fn is_case_insensitive(&self) -> bool {
+        let upper = self.root.join("DEPS");
+        same_file::is_same_file(&upper, &self.deps).unwrap_or(false)
+    }
+
     /// Fetch the root path.
     pub fn dest(&self) -> &Path {
         &self.root
@@ -0,0 +1,192 @@
+//! Combining what's built for several Apple targets into universal binaries
+//! with `lipo`, for `cargo build --lipo`.
+//!
+//! The binaries and dynamic or static libraries uplifted for each target,
+//! like `target/x86_64-apple-darwin/debug/foo`, are combined into
+//! `target/universal/debug/foo`. Their `.dSYM` bundles are combined as well,
+//! by combining the DWARF files inside of them, which are Mach-O files too.
+//! With `--message-format json`, a `compiler-artifact` message is emitted
+//! for each universal file.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::layout::Layout;
+use super::{CompileMode, Context, FileFlavor, Kind, Unit};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{machine_message, paths, process};
+
+/// The slices of an artifact, one for each of the targets.
+struct Slices<'a> {
+    /// The unit of the first target, which the universal file is reported as
+    /// an artifact of.
+    unit: Unit<'a>,
+    debug_info: bool,
+    paths: Vec<PathBuf>,
+}
+
+/// Combines the artifacts uplifted for each of the targets of the build into
+/// universal ones, if `--lipo` was passed.
+pub fn combine<'a>(cx: &mut Context<'a, '_>, units: &[Unit<'a>]) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    if !bcx.build_config.lipo || bcx.build_config.build_plan {
+        return Ok(());
+    }
+    let layout = Layout::new(bcx.ws, Some("universal"), bcx.build_config.profile_kind.dest())?;
+
+    // Keyed by where the artifact is uplifted to, relative to the layout of
+    // its target, like `foo` or `examples/libbar.dylib`.
+    let mut artifacts = BTreeMap::new();
+    for unit in units {
+        if unit.mode != CompileMode::Build || unit.kind == Kind::Host {
+            continue;
+        }
+        let dest = cx.files().layout(unit.kind).dest().to_path_buf();
+        for output in cx.outputs(unit)?.iter() {
+            let hardlink = match output.hardlink {
+                Some(ref hardlink) => hardlink,
+                None => continue,
+            };
+            let debug_info = match output.flavor {
+                FileFlavor::Normal | FileFlavor::Linkable { rmeta: false } => {
+                    match hardlink.extension() {
+                        None => false,
+                        Some(ext) if ext == "dylib" || ext == "a" => false,
+                        Some(_) => continue,
+                    }
+                }
+                FileFlavor::DebugInfo if hardlink.extension().map_or(false, |e| e == "dSYM") => {
+                    true
+                }
+                _ => continue,
+            };
+            let relative = hardlink.strip_prefix(&dest)?.to_path_buf();
+            artifacts
+                .entry(relative)
+                .or_insert_with(|| Slices {
+                    unit: *unit,
+                    debug_info,
+                    paths: Vec::new(),
+                })
+                .paths
+                .push(hardlink.clone());
+        }
+    }
+
+    for (relative, slices) in artifacts {
+        // Something only built for some of the targets can't be combined.
+        if slices.paths.len() < 2 {
+            continue;
+        }
+        let dst = layout.dest().join(relative);
+        let fresh = if slices.debug_info {
+            combine_dsyms(cx, &slices.paths, &dst)?
+        } else {
+            lipo(cx, &slices.paths, &dst)?
+        };
+        if bcx.build_config.json_messages() {
+            let executable = if slices.debug_info {
+                None
+            } else {
+                cx.get_executable(&slices.unit)?.map(|_| dst.clone())
+            };
+            emit_artifact(cx, &slices.unit, dst, executable, fresh);
+        }
+    }
+    Ok(())
+}
+
+/// Emits the `compiler-artifact` message of the universal file `dst` of
+/// `unit`.
+fn emit_artifact(
+    cx: &Context,
+    unit: &Unit,
+    dst: PathBuf,
+    executable: Option<PathBuf>,
+    fresh: bool,
+) {
+    let package_id = unit.pkg.package_id();
+    let profile = unit.profile;
+    let features = cx
+        .bcx
+        .resolve_of(package_id)
+        .features_sorted(package_id)
+        .into_iter()
+        .map(|s| s.to_owned())
+        .collect();
+    machine_message::emit(&machine_message::Artifact {
+        package_id,
+        target: unit.target,
+        profile: machine_message::ArtifactProfile {
+            opt_level: profile.opt_level.as_str(),
+            debuginfo: profile.debuginfo,
+            debug_assertions: profile.debug_assertions,
+            overflow_checks: profile.overflow_checks,
+            test: unit.mode.is_any_test(),
+        },
+        features,
+        filenames: vec![dst],
+        executable,
+        fresh,
+        // The slices are built for different CPUs.
+        target_cpu: None,
+    });
+}
+
+/// Combines the `.dSYM` bundles in `dsyms` into the bundle `dst`, with the
+/// `Info.plist` of the first one. Returns whether it was up to date already.
+fn combine_dsyms(cx: &Context, dsyms: &[PathBuf], dst: &Path) -> CargoResult<bool> {
+    let dwarf_files = dsyms
+        .iter()
+        .map(|dsym| dwarf_file(dsym))
+        .collect::<CargoResult<Vec<_>>>()?;
+    let name = dst.file_stem().unwrap();
+    let fresh = lipo(cx, &dwarf_files, &dst.join("Contents/Resources/DWARF").join(name))?;
+    let info = dsyms[0].join("Contents/Info.plist");
+    if info.exists() {
+        fs::copy(&info, dst.join("Contents/Info.plist"))
+            .chain_err(|| format!("failed to copy `{}`", info.display()))?;
+    }
+    Ok(fresh)
+}
+
+/// The DWARF file in a `.dSYM` bundle, which is named after the binary the
+/// bundle was made for.
+fn dwarf_file(dsym: &Path) -> CargoResult<PathBuf> {
+    let dir = dsym.join("Contents/Resources/DWARF");
+    let entry = fs::read_dir(&dir)
+        .chain_err(|| format!("failed to read `{}`", dir.display()))?
+        .next();
+    match entry {
+        Some(entry) => Ok(entry?.path()),
+        None => bail!("`{}` has no DWARF file", dsym.display()),
+    }
+}
+
+/// Combines the Mach-O files in `slices` into the universal file `dst`,
+/// unless it's newer than all of them already, which is returned.
+fn lipo(cx: &Context, slices: &[PathBuf], dst: &Path) -> CargoResult<bool> {
+    if dst.exists() {
+        let combined = paths::mtime(dst)?;
+        let mut fresh = true;
+        for slice in slices {
+            fresh &= paths::mtime(slice)? <= combined;
+        }
+        if fresh {
+            return Ok(true);
+        }
+    }
+    paths::create_dir_all(dst.parent().unwrap())?;
+    let mut cmd = process(env::var_os("LIPO").unwrap_or_else(|| OsString::from("lipo")));
+    cmd.arg("-create").arg("-output").arg(dst).args(slices);
+    cx.bcx
+        .config
+        .shell()
+        .verbose(|shell| shell.status("Running", &cmd))?;
+    cmd.exec_with_output()
+        .chain_err(|| format!("failed to create the universal `{}`", dst.display()))?;
+    Ok(false)
+}
@@ -7,48 +7,92 @@ use std::sync::Arc;
 
 use failure::Error;
 use same_file::is_same_file;
+use semver::Version;
+use serde::ser;
 use serde_json;
 
+use crate::core::interning::InternedString;
 use crate::core::manifest::TargetSourcePath;
 use crate::core::profiles::{Lto, Profile};
 use crate::core::{PackageId, Target};
 use crate::util::errors::{CargoResult, CargoResultExt, Internal, ProcessError};
 use crate::util::paths;
-use crate::util::{self, machine_message, process, Freshness, ProcessBuilder};
-use crate::util::{internal, join_paths, profile};
+use crate::util::{machine_message, process, Freshness, ProcessBuilder};
+use crate::util::{internal, join_paths, profile, Cfg};
 
 use self::build_plan::BuildPlan;
+use self::ice_reports::IceReporter;
 use self::job::{Job, Work};
-use self::job_queue::JobQueue;
+use self::job_queue::{JobQueue, JobState};
 
 use self::output_depinfo::output_depinfo;
 
-pub use self::build_config::{BuildConfig, CompileMode, MessageFormat};
+pub use self::bloat::{BinaryBloat, BloatReport, CrateBloat, GenericBloat};
+pub use self::bug_report::{BugReport, PackageSummary, UnitState};
+pub use self::build_config::{
+    BuildConfig, CompileMode, EmitKind, MessageFormat, ProfileKind, WarningHandling,
+};
 pub use self::build_context::{BuildContext, FileFlavor, TargetConfig, TargetInfo};
+pub use self::build_env::BuildEnv;
 pub use self::compilation::{Compilation, Doctest};
 pub use self::context::{Context, Unit};
 pub use self::custom_build::{BuildMap, BuildOutput, BuildScripts};
+pub use self::failed_units::show_failed_commands;
+pub use self::proc_macro_sandbox::ProcMacroSandbox;
+pub use self::retention::KeepArtifacts;
+pub use self::inputs::{EnvInput, InputsReport, UnitInputs};
 pub use self::layout::is_bad_artifact_name;
 
+mod artifact_manifest;
+mod bloat;
+mod bug_report;
+mod build_audit;
 mod build_config;
 mod build_context;
+mod build_env;
 mod build_plan;
 mod compilation;
 mod context;
 mod custom_build;
+mod dist;
+mod failed_units;
 mod fingerprint;
+mod ice_reports;
+mod inputs;
 mod job;
 mod job_queue;
 mod layout;
+mod lipo;
 mod output_depinfo;
+mod post_link;
+mod proc_macro_sandbox;
+mod retention;
+pub mod standard_lib;
+mod system_deps;
+mod unit_logs;
+mod windows_resources;
 
 /// Whether an object is for the host arch, or the target arch.
 ///
-/// These will be the same unless cross-compiling.
-#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord, Serialize)]
+/// These will be the same unless cross-compiling. A `Target` carries the
+/// triple passed with `--target`, if any, so that units for each of several
+/// requested targets are kept apart.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord)]
 pub enum Kind {
     Host,
-    Target,
+    Target(Option<InternedString>),
+}
+
+impl ser::Serialize for Kind {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match *self {
+            Kind::Host => "Host".serialize(s),
+            Kind::Target(_) => "Target".serialize(s),
+        }
+    }
 }
 
 /// A glorified callback for executing calls to rustc. Rather than calling rustc
@@ -141,7 +185,7 @@ fn compile<'a, 'cfg: 'a>(
     // we've got everything constructed.
     let p = profile::start(format!("preparing: {}/{}", unit.pkg, unit.target.name()));
     fingerprint::prepare_init(cx, unit)?;
-    cx.links.validate(bcx.resolve, unit)?;
+    cx.links.validate(bcx.resolve_of(unit.pkg.package_id()), unit)?;
 
     let (dirty, fresh, freshness) = if unit.mode.is_run_custom_build() {
         custom_build::prepare(cx, unit)?
@@ -161,12 +205,28 @@ fn compile<'a, 'cfg: 'a>(
         } else {
             rustc(cx, unit, exec)?
         };
+        // A unit whose warnings are denied isn't recorded as fresh when it
+        // emitted any, so that it's built and fails again the next time.
+        let dirty = if warning_handling(bcx, unit) == WarningHandling::Deny {
+            Work::new(move |state| {
+                if state.warnings() == 0 {
+                    dirty.call(state)?;
+                }
+                Ok(())
+            })
+        } else {
+            dirty
+        };
         // Need to link targets on both the dirty and fresh
-        let dirty = work.then(link_targets(cx, unit, false)?).then(dirty);
+        let dirty = work
+            .then(post_link::prepare(cx, unit)?)
+            .then(link_targets(cx, unit, false)?)
+            .then(dirty);
         let fresh = link_targets(cx, unit, true)?.then(fresh);
 
         if exec.force_rebuild(unit) || force_rebuild {
             freshness = Freshness::Dirty;
+            jobs.force_rebuild(unit);
         }
 
         (dirty, fresh, freshness)
@@ -209,6 +269,7 @@ fn rustc<'a, 'cfg>(
     let build_state = cx.build_state.clone();
     let current_id = unit.pkg.package_id();
     let build_deps = load_build_deps(cx, unit);
+    let cfg_deps = dependent_cfg_deps(cx, unit);
 
     // If we are a binary and the package also contains a library, then we
     // don't pass the `-l` flags.
@@ -225,13 +286,32 @@ fn rustc<'a, 'cfg>(
     }
     .with_extension("d");
     let dep_info_loc = fingerprint::dep_info_loc(cx, unit);
+    let missing_std = missing_std_hint(cx.bcx, kind);
+    let dylib_path_envvar = cx.bcx.config.host_platform()?.dylib_path_envvar().to_string();
 
+    if uses_versioned_names(cx.bcx, unit) {
+        rustc.arg("-C").arg(&format!(
+            "link-arg=-Wl,-soname,{}",
+            soname(&unit.target.crate_name(), unit.pkg.version())
+        ));
+    }
+    let resources = windows_resources::prepare(cx, unit)?;
+    if let Some(ref resources) = resources {
+        rustc
+            .arg("-C")
+            .arg(&format!("link-arg={}", resources.output.display()));
+    }
     rustc.args(&cx.bcx.rustflags_args(unit)?);
+    cx.bcx.build_config.build_env.apply(&mut rustc);
+    proc_macro_sandbox::restrict(cx, unit, &mut rustc)?;
     let json_messages = cx.bcx.build_config.json_messages();
+    let warnings = warning_handling(cx.bcx, unit);
+    let rendered_json = cx.rmeta_required(unit) || warnings != WarningHandling::Warn;
     let package_id = unit.pkg.package_id();
     let target = unit.target.clone();
     let mode = unit.mode;
 
+    let ice = IceReporter::new(cx, unit);
     exec.init(cx, unit);
     let exec = exec.clone();
 
@@ -260,16 +340,29 @@ fn rustc<'a, 'cfg>(
                     pass_l_flag,
                     current_id,
                 )?;
-                add_plugin_deps(&mut rustc, &build_state, &build_deps, &root_output)?;
+                add_plugin_deps(
+                    &mut rustc,
+                    &build_state,
+                    &build_deps,
+                    &root_output,
+                    &dylib_path_envvar,
+                )?;
             }
             add_custom_env(&mut rustc, &build_state, current_id, kind)?;
         }
+        if !build_plan {
+            let build_state = build_state.outputs.lock().unwrap();
+            add_dependent_cfgs(&mut rustc, &build_state, &cfg_deps)?;
+        }
 
         for output in outputs.iter() {
             // If there is both an rmeta and rlib, rustc will prefer to use the
             // rlib, even if it is older. Therefore, we must delete the rlib to
-            // force using the new rmeta.
-            if output.path.extension() == Some(OsStr::new("rmeta")) {
+            // force using the new rmeta. The rmeta emitted next to the rlib
+            // for pipelining is produced along with the rlib though.
+            if output.path.extension() == Some(OsStr::new("rmeta"))
+                && output.flavor != (FileFlavor::Linkable { rmeta: true })
+            {
                 let dst = root.join(&output.path).with_extension("rlib");
                 if dst.exists() {
                     paths::remove_file(&dst)?;
@@ -290,7 +383,21 @@ fn rustc<'a, 'cfg>(
             }
         }
 
+        fn with_missing_std_hint(err: Error, hint: &Option<String>) -> Error {
+            match *hint {
+                Some(ref hint) => err.context(hint.clone()).into(),
+                None => err,
+            }
+        }
+
+        if let Some(ref resources) = resources {
+            if !build_plan {
+                resources.compile(state, &name)?;
+            }
+        }
+
         state.running(&rustc);
+        let cmd = rustc.clone();
         if json_messages {
             exec.exec_json(
                 rustc,
@@ -298,15 +405,35 @@ fn rustc<'a, 'cfg>(
                 &target,
                 mode,
                 &mut assert_is_empty,
-                &mut |line| json_stderr(line, package_id, &target),
+                &mut |line| json_stderr(line, package_id, &target, state, warnings),
             )
+            .map_err(|err| ice.check(&cmd, &state.stderr_lines(), err))
             .map_err(internal_if_simple_exit_code)
+            .map_err(|err| with_missing_std_hint(err, &missing_std))
             .chain_err(|| format!("Could not compile `{}`.", name))?;
         } else if build_plan {
             state.build_plan(buildkey, rustc.clone(), outputs.clone());
+        } else if rendered_json {
+            exec.exec_json(
+                rustc,
+                package_id,
+                &target,
+                mode,
+                &mut |line| {
+                    state.stdout(line.to_string());
+                    Ok(())
+                },
+                &mut |line| rendered_stderr(line, state, warnings),
+            )
+            .map_err(|err| ice.check(&cmd, &state.stderr_lines(), err))
+            .map_err(internal_if_simple_exit_code)
+            .map_err(|err| with_missing_std_hint(err, &missing_std))
+            .chain_err(|| format!("Could not compile `{}`.", name))?;
         } else {
             exec.exec_and_capture_output(rustc, package_id, &target, mode, state)
+                .map_err(|err| ice.check(&cmd, &state.stderr_lines(), err))
                 .map_err(internal_if_simple_exit_code)
+                .map_err(|err| with_missing_std_hint(err, &missing_std))
                 .chain_err(|| format!("Could not compile `{}`.", name))?;
         }
 
@@ -326,13 +453,20 @@ fn rustc<'a, 'cfg>(
         }
 
         if rustc_dep_info_loc.exists() {
-            fingerprint::translate_dep_info(&rustc_dep_info_loc, &dep_info_loc, &pkg_root, &cwd)
-                .chain_err(|| {
-                    internal(format!(
-                        "could not parse/generate dep info at: {}",
-                        rustc_dep_info_loc.display()
-                    ))
-                })?;
+            let extra_deps = resources.as_ref().map_or(&[][..], |r| &r.inputs[..]);
+            fingerprint::translate_dep_info(
+                &rustc_dep_info_loc,
+                &dep_info_loc,
+                &pkg_root,
+                &cwd,
+                extra_deps,
+            )
+            .chain_err(|| {
+                internal(format!(
+                    "could not parse/generate dep info at: {}",
+                    rustc_dep_info_loc.display()
+                ))
+            })?;
         }
 
         Ok(())
@@ -403,13 +537,27 @@ fn link_targets<'a, 'cfg>(
     let profile = unit.profile;
     let unit_mode = unit.mode;
     let features = bcx
-        .resolve
+        .resolve_of(package_id)
         .features_sorted(package_id)
         .into_iter()
         .map(|s| s.to_owned())
         .collect();
     let json_messages = bcx.build_config.json_messages();
+    let target_cpu = bcx.target_cpu(unit.kind).map(|name| machine_message::ArtifactTargetCpu {
+        name: name.to_string(),
+        features: bcx
+            .target_features(unit.kind)
+            .into_iter()
+            .map(|f| f.to_string())
+            .collect(),
+    });
     let executable = cx.get_executable(unit)?;
+    let version = if uses_versioned_names(bcx, unit) {
+        Some(unit.pkg.version().clone())
+    } else {
+        None
+    };
+    let crate_name = unit.target.crate_name();
     let mut target = unit.target.clone();
     if let TargetSourcePath::Metabuild = target.src_path() {
         // Give it something to serialize.
@@ -430,6 +578,13 @@ fn link_targets<'a, 'cfg>(
             if !src.exists() {
                 continue;
             }
+            // Programs linked against the library in `deps` look it up there
+            // under its soname.
+            if let Some(ref version) = version {
+                if src.extension() == Some(OsStr::new("so")) {
+                    symlink_or_copy(src, &src.with_file_name(soname(&crate_name, version)))?;
+                }
+            }
             let dst = match output.hardlink.as_ref() {
                 Some(dst) => dst,
                 None => {
@@ -438,14 +593,29 @@ fn link_targets<'a, 'cfg>(
                 }
             };
             destinations.push(dst.clone());
-            hardlink_or_copy(src, dst)?;
+            if output.flavor == FileFlavor::Emitted {
+                paths::create_dir_all(dst.parent().unwrap())?;
+            }
+            let versioned = match version {
+                Some(ref version) if dst.extension() == Some(OsStr::new("so")) => Some(version),
+                _ => None,
+            };
+            match versioned {
+                Some(version) => destinations.extend(link_versioned(src, dst, version)?),
+                None => hardlink_or_copy(src, dst)?,
+            }
             if let Some(ref path) = output.export_path {
                 let export_dir = export_dir.as_ref().unwrap();
                 if !export_dir.exists() {
                     fs::create_dir_all(export_dir)?;
                 }
 
-                hardlink_or_copy(src, path)?;
+                match versioned {
+                    Some(version) => {
+                        link_versioned(src, path, version)?;
+                    }
+                    None => hardlink_or_copy(src, path)?,
+                }
             }
         }
 
@@ -466,6 +636,7 @@ fn link_targets<'a, 'cfg>(
                 filenames: destinations,
                 executable,
                 fresh,
+                target_cpu,
             });
         }
         Ok(())
@@ -474,6 +645,8 @@ fn link_targets<'a, 'cfg>(
 
 fn hardlink_or_copy(src: &Path, dst: &Path) -> CargoResult<()> {
     debug!("linking {} to {}", src.display(), dst.display());
+    let (src_display, dst_display) = (src, dst);
+    let (src, dst) = (&*paths::long_path(src), &*paths::long_path(dst));
     if is_same_file(src, dst).unwrap_or(false) {
         return Ok(());
     }
@@ -499,18 +672,70 @@ fn hardlink_or_copy(src: &Path, dst: &Path) -> CargoResult<()> {
     } else {
         fs::hard_link(src, dst)
     };
-    link_result
-        .or_else(|err| {
-            debug!("link failed {}. falling back to fs::copy", err);
-            fs::copy(src, dst).map(|_| ())
-        })
-        .chain_err(|| {
-            format!(
-                "failed to link or copy `{}` to `{}`",
-                src.display(),
-                dst.display()
-            )
-        })?;
+    let result = link_result.or_else(|err| {
+        debug!("link failed {}. falling back to fs::copy", err);
+        fs::copy(src, dst).map(|_| ())
+    });
+    paths::explain_long_path(result, dst_display).chain_err(|| {
+        format!(
+            "failed to link or copy `{}` to `{}`",
+            src_display.display(),
+            dst_display.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Whether the shared library of `unit` is uplifted under versioned names,
+/// which are only a convention of the Unix platforms using ELF.
+fn uses_versioned_names(bcx: &BuildContext, unit: &Unit) -> bool {
+    let cfg = bcx.cfg(unit.kind);
+    unit.target.versioned()
+        && (unit.target.is_cdylib() || unit.target.is_dylib())
+        && unit.mode == CompileMode::Build
+        && cfg.contains(&Cfg::Name("unix".to_string()))
+        && !cfg.contains(&Cfg::KeyPair("target_vendor".to_string(), "apple".to_string()))
+}
+
+/// The soname of the versioned shared library of the crate `crate_name`,
+/// like `libfoo.so.1`.
+fn soname(crate_name: &str, version: &Version) -> String {
+    format!("lib{}.so.{}", crate_name, version.major)
+}
+
+/// Links the shared library `src` to `dst` like `libfoo.so`, through the
+/// chain of versioned names `libfoo.so -> libfoo.so.1 -> libfoo.so.1.2.3`.
+/// Returns the versioned names.
+fn link_versioned(src: &Path, dst: &Path, version: &Version) -> CargoResult<Vec<PathBuf>> {
+    let name = dst.file_name().unwrap().to_str().unwrap();
+    let major = dst.with_file_name(format!("{}.{}", name, version.major));
+    let full = dst.with_file_name(format!(
+        "{}.{}.{}.{}",
+        name, version.major, version.minor, version.patch
+    ));
+    hardlink_or_copy(src, &full)?;
+    symlink_or_copy(&full, &major)?;
+    symlink_or_copy(&major, dst)?;
+    Ok(vec![major, full])
+}
+
+/// Replaces `dst` with a symlink to `src`, a file in the same directory, or
+/// a copy of it where symlinks aren't available.
+fn symlink_or_copy(src: &Path, dst: &Path) -> CargoResult<()> {
+    if fs::symlink_metadata(dst).is_ok() {
+        paths::remove_file(dst)?;
+    }
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(src.file_name().unwrap(), dst);
+    #[cfg(not(unix))]
+    let result = fs::copy(src, dst).map(|_| ());
+    result.chain_err(|| {
+        format!(
+            "failed to link or copy `{}` to `{}`",
+            src.display(),
+            dst.display()
+        )
+    })?;
     Ok(())
 }
 
@@ -526,8 +751,8 @@ fn add_plugin_deps(
     build_state: &BuildMap,
     build_scripts: &BuildScripts,
     root_output: &PathBuf,
+    var: &str,
 ) -> CargoResult<()> {
-    let var = util::dylib_path_envvar();
     let search_path = rustc.get_env(var).unwrap_or_default();
     let mut search_path = env::split_paths(&search_path).collect::<Vec<_>>();
     for &id in build_scripts.plugins.iter() {
@@ -544,6 +769,53 @@ fn add_plugin_deps(
     Ok(())
 }
 
+/// Returns the `(package, kind)` keys of the build scripts of the direct
+/// dependencies of `unit` which have a `links` key, along with the prefix
+/// used for the `--cfg` flags they pass on to `unit`, if `-Z dependent-cfg`
+/// is enabled.
+fn dependent_cfg_deps<'a, 'cfg>(
+    cx: &Context<'a, 'cfg>,
+    unit: &Unit<'a>,
+) -> Vec<((PackageId, Kind), String)> {
+    if !cx.bcx.config.cli_unstable().dependent_cfg {
+        return Vec::new();
+    }
+    let mut ret = cx
+        .dep_targets(unit)
+        .iter()
+        .filter(|dep| dep.target.linkable() && !dep.mode.is_run_custom_build())
+        .filter_map(|dep| {
+            let links = dep.pkg.manifest().links()?;
+            let prefix = format!("dep_{}", links.replace('-', "_"));
+            Some(((dep.pkg.package_id(), dep.kind), prefix))
+        })
+        .collect::<Vec<_>>();
+    ret.sort();
+    ret.dedup();
+    ret
+}
+
+// Add the `--cfg` flags that the build scripts of direct dependencies asked
+// to be passed to their dependents via `cargo:dependent-cfg`.
+fn add_dependent_cfgs(
+    cmd: &mut ProcessBuilder,
+    build_state: &BuildMap,
+    cfg_deps: &[((PackageId, Kind), String)],
+) -> CargoResult<()> {
+    for &(ref key, ref prefix) in cfg_deps {
+        let output = build_state.get(key).ok_or_else(|| {
+            internal(format!(
+                "couldn't find build state for {}/{:?}",
+                key.0, key.1
+            ))
+        })?;
+        for cfg in output.dependent_cfgs.iter() {
+            cmd.arg("--cfg").arg(&format!("{}_{}", prefix, cfg));
+        }
+    }
+    Ok(())
+}
+
 // Determine paths to add to the dynamic search path from -L entries
 //
 // Strip off prefixes like "native=" or "framework=" and filter out directories
@@ -600,7 +872,7 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult
     let mut rustdoc = cx.compilation.rustdoc_process(unit.pkg, unit.target)?;
     rustdoc.inherit_jobserver(&cx.jobserver);
     rustdoc.arg("--crate-name").arg(&unit.target.crate_name());
-    add_path_args(bcx, unit, &mut rustdoc);
+    add_path_args(cx, unit, &mut rustdoc);
     add_cap_lints(bcx, unit, &mut rustdoc);
 
     let mut can_add_color_process = process(&*bcx.config.rustdoc()?);
@@ -609,10 +881,8 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult
         add_color(bcx, &mut rustdoc);
     }
 
-    if unit.kind != Kind::Host {
-        if let Some(ref target) = bcx.build_config.requested_target {
-            rustdoc.arg("--target").arg(target);
-        }
+    if let Kind::Target(Some(target)) = unit.kind {
+        rustdoc.arg("--target").arg(&*target);
     }
 
     let doc_dir = cx.files().out_dir(unit);
@@ -622,13 +892,21 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult
     // it doesn't already exist.
     fs::create_dir_all(&doc_dir)?;
 
+    // rustdoc leaves the pages of items which have been removed since the
+    // last run behind, so the crate's pages are cleared before documenting.
+    let crate_dirs = vec![
+        doc_dir.join(unit.target.crate_name()),
+        doc_dir.join("src").join(unit.target.crate_name()),
+    ];
+
     rustdoc.arg("-o").arg(doc_dir);
 
-    for feat in bcx.resolve.features_sorted(unit.pkg.package_id()) {
+    let package_id = unit.pkg.package_id();
+    for feat in bcx.resolve_of(package_id).features_sorted(package_id) {
         rustdoc.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
     }
 
-    add_error_format(bcx, &mut rustdoc);
+    add_error_format(bcx, &mut rustdoc, false);
 
     if let Some(ref args) = bcx.extra_args_for(unit) {
         rustdoc.args(args);
@@ -637,22 +915,31 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult
     build_deps_args(&mut rustdoc, cx, unit)?;
 
     rustdoc.args(&bcx.rustdocflags_args(unit)?);
+    bcx.build_config.build_env.apply(&mut rustdoc);
+    proc_macro_sandbox::restrict(cx, unit, &mut rustdoc)?;
 
     let name = unit.pkg.name().to_string();
     let build_state = cx.build_state.clone();
     let key = (unit.pkg.package_id(), unit.kind);
+    let cfg_deps = dependent_cfg_deps(cx, unit);
     let json_messages = bcx.build_config.json_messages();
-    let package_id = unit.pkg.package_id();
     let target = unit.target.clone();
 
     Ok(Work::new(move |state| {
-        if let Some(output) = build_state.outputs.lock().unwrap().get(&key) {
-            for cfg in output.cfgs.iter() {
-                rustdoc.arg("--cfg").arg(cfg);
-            }
-            for &(ref name, ref value) in output.env.iter() {
-                rustdoc.env(name, value);
+        {
+            let build_state = build_state.outputs.lock().unwrap();
+            if let Some(output) = build_state.get(&key) {
+                for cfg in output.cfgs.iter() {
+                    rustdoc.arg("--cfg").arg(cfg);
+                }
+                for &(ref name, ref value) in output.env.iter() {
+                    rustdoc.env(name, value);
+                }
             }
+            add_dependent_cfgs(&mut rustdoc, &build_state, &cfg_deps)?;
+        }
+        for dir in crate_dirs.iter().filter(|d| d.exists()) {
+            paths::remove_dir_all(dir)?;
         }
         state.running(&rustdoc);
 
@@ -660,7 +947,9 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult
             rustdoc
                 .exec_with_streaming(
                     &mut assert_is_empty,
-                    &mut |line| json_stderr(line, package_id, &target),
+                    &mut |line| {
+                        json_stderr(line, package_id, &target, state, WarningHandling::Warn)
+                    },
                     false,
                 )
                 .map(drop)
@@ -686,7 +975,11 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult
 //
 // The first returned value here is the argument to pass to rustc, and the
 // second is the cwd that rustc should operate in.
-fn path_args(bcx: &BuildContext, unit: &Unit) -> (PathBuf, PathBuf) {
+//
+// Sandboxed compilations get the absolute path instead, since they run in a
+// directory of their own.
+fn path_args<'a>(cx: &Context<'a, '_>, unit: &Unit<'a>) -> (PathBuf, PathBuf) {
+    let bcx = cx.bcx;
     let ws_root = bcx.ws.root();
     let src = if unit.target.is_custom_build() && unit.pkg.manifest().metabuild().is_some() {
         unit.pkg.manifest().metabuild_path(bcx.ws.target_dir())
@@ -694,6 +987,9 @@ fn path_args(bcx: &BuildContext, unit: &Unit) -> (PathBuf, PathBuf) {
         unit.target.src_path().path().to_path_buf()
     };
     assert!(src.is_absolute());
+    if let Some(dir) = proc_macro_sandbox::working_dir(cx, unit) {
+        return (src, dir);
+    }
     if unit.pkg.package_id().source_id().is_path() {
         if let Ok(path) = src.strip_prefix(ws_root) {
             return (path.to_path_buf(), ws_root.to_path_buf());
@@ -702,12 +998,58 @@ fn path_args(bcx: &BuildContext, unit: &Unit) -> (PathBuf, PathBuf) {
     (src, unit.pkg.root().to_path_buf())
 }
 
-fn add_path_args(bcx: &BuildContext, unit: &Unit, cmd: &mut ProcessBuilder) {
-    let (arg, cwd) = path_args(bcx, unit);
+fn add_path_args<'a>(cx: &Context<'a, '_>, unit: &Unit<'a>, cmd: &mut ProcessBuilder) {
+    let (arg, cwd) = path_args(cx, unit);
     cmd.arg(arg);
     cmd.cwd(cwd);
 }
 
+/// Explains how to get hold of the standard library when the compiler doesn't
+/// have one for the target the unit is compiled for, so that a failure to
+/// compile it comes with more than a "can't find crate for `std`" error.
+fn missing_std_hint(bcx: &BuildContext, kind: Kind) -> Option<String> {
+    if kind == Kind::Host || bcx.std.is_some() {
+        return None;
+    }
+    let sysroot = bcx.sysroot(kind)?;
+    let libdir = bcx.sysroot_libdir(kind)?;
+    if libdir.exists() {
+        return None;
+    }
+    let triple = bcx.triple(kind);
+    if triple.ends_with(".json") {
+        let name = Path::new(triple)
+            .file_stem()
+            .map_or(triple.into(), |s| s.to_string_lossy());
+        return Some(format!(
+            "there is no standard library for the custom target `{}`, it has to \
+             be built for the target (e.g. with `xargo`) and the sysroot it is \
+             installed to has to be passed to rustc with `--sysroot` in the \
+             `build.rustflags` configuration",
+            name
+        ));
+    }
+    let rustup = sysroot
+        .join("lib/rustlib/multirust-channel-manifest.toml")
+        .exists();
+    if rustup {
+        Some(format!(
+            "the `{}` target isn't installed, install it with \
+             `rustup target add {0}`",
+            triple
+        ))
+    } else {
+        Some(format!(
+            "the standard library for the `{}` target isn't installed in the \
+             sysroot `{}`, install it to `{}` or select a toolchain which \
+             ships it with the `build.rustc` configuration",
+            triple,
+            sysroot.display(),
+            libdir.display()
+        ))
+    }
+}
+
 fn add_cap_lints(bcx: &BuildContext, unit: &Unit, cmd: &mut ProcessBuilder) {
     // If this is an upstream dep we don't want warnings from, turn off all
     // lints.
@@ -731,7 +1073,26 @@ fn add_color(bcx: &BuildContext, cmd: &mut ProcessBuilder) {
     cmd.args(&["--color", color]);
 }
 
-fn add_error_format(bcx: &BuildContext, cmd: &mut ProcessBuilder) {
+fn add_error_format(bcx: &BuildContext, cmd: &mut ProcessBuilder, rendered_json: bool) {
+    // rustc only reports when the `.rmeta` some dependent waits for is
+    // written in JSON mode, and the warnings of a unit can only be told
+    // apart from its other diagnostics in JSON mode, so with the human
+    // readable formats the diagnostics are rendered into the JSON messages
+    // for us to print.
+    if rendered_json {
+        cmd.arg("--error-format").arg("json");
+        let mut json = vec!["artifacts"];
+        if bcx.build_config.message_format != MessageFormat::Json {
+            if bcx.config.shell().supports_color() {
+                json.push("diagnostic-rendered-ansi");
+            }
+            if bcx.build_config.message_format == MessageFormat::Short {
+                json.push("diagnostic-short");
+            }
+        }
+        cmd.arg(format!("--json={}", json.join(",")));
+        return;
+    }
     match bcx.build_config.message_format {
         MessageFormat::Human => (),
         MessageFormat::Json => {
@@ -767,21 +1128,38 @@ fn build_base_args<'a, 'cfg>(
 
     cmd.arg("--crate-name").arg(&unit.target.crate_name());
 
-    add_path_args(bcx, unit, cmd);
-    add_color(bcx, cmd);
-    add_error_format(bcx, cmd);
+    add_path_args(cx, unit, cmd);
+    let rendered_json =
+        cx.rmeta_required(unit) || warning_handling(bcx, unit) != WarningHandling::Warn;
+    // rustc doesn't accept `--color` along with `--json`, the diagnostics
+    // rendered into the JSON messages are colored as requested there.
+    if !rendered_json {
+        add_color(bcx, cmd);
+    }
+    add_error_format(bcx, cmd, rendered_json);
 
+    // All the crate types of a library are produced by a single invocation,
+    // which shares the codegen of the crate between them, rather than
+    // compiling it again for each of them, like for a staticlib and a cdylib.
     if !test {
         for crate_type in crate_types.iter() {
             cmd.arg("--crate-type").arg(crate_type);
         }
     }
 
-    if unit.mode.is_check() {
-        cmd.arg("--emit=dep-info,metadata");
+    let extra_emit = cx.files().extra_emit(unit, bcx);
+    let mut emit = if unit.mode.is_check() {
+        "--emit=dep-info,metadata".to_string()
+    } else if bcx.emits_rmeta(unit) {
+        "--emit=dep-info,metadata,link".to_string()
     } else {
-        cmd.arg("--emit=dep-info,link");
+        "--emit=dep-info,link".to_string()
+    };
+    for kind in extra_emit {
+        emit.push(',');
+        emit.push_str(kind.name());
     }
+    cmd.arg(emit);
 
     let prefer_dynamic = (unit.target.for_host() && !unit.target.is_custom_build())
         || (crate_types.contains(&"dylib") && bcx.ws.members().any(|p| p != unit.pkg));
@@ -811,7 +1189,11 @@ fn build_base_args<'a, 'cfg>(
         }
     }
 
-    if let Some(n) = codegen_units {
+    if !extra_emit.is_empty() {
+        // rustc only writes a single file of each emitted kind when there's
+        // a single codegen unit, otherwise there's one per codegen unit.
+        cmd.arg("-C").arg("codegen-units=1");
+    } else if let Some(n) = codegen_units {
         // There are some restrictions with LTO and codegen-units, so we
         // only add codegen units when LTO is not used.
         cmd.arg("-C").arg(&format!("codegen-units={}", n));
@@ -855,7 +1237,8 @@ fn build_base_args<'a, 'cfg>(
     // We ideally want deterministic invocations of rustc to ensure that
     // rustc-caching strategies like sccache are able to cache more, so sort the
     // feature list here.
-    for feat in bcx.resolve.features_sorted(unit.pkg.package_id()) {
+    let package_id = unit.pkg.package_id();
+    for feat in bcx.resolve_of(package_id).features_sorted(package_id) {
         cmd.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
     }
 
@@ -874,6 +1257,12 @@ fn build_base_args<'a, 'cfg>(
         cmd.arg("-C").arg("rpath");
     }
 
+    // Everything in the standard library which isn't marked stable is
+    // unstable, as it is when rustc's own build system compiles it.
+    if bcx.is_std(package_id) {
+        cmd.arg("-Z").arg("force-unstable-if-unmarked");
+    }
+
     cmd.arg("--out-dir").arg(&cx.files().out_dir(unit));
 
     fn opt(cmd: &mut ProcessBuilder, key: &str, prefix: &str, val: Option<&OsStr>) {
@@ -884,16 +1273,8 @@ fn build_base_args<'a, 'cfg>(
         }
     }
 
-    if unit.kind == Kind::Target {
-        opt(
-            cmd,
-            "--target",
-            "",
-            bcx.build_config
-                .requested_target
-                .as_ref()
-                .map(|s| s.as_ref()),
-        );
+    if let Kind::Target(Some(target)) = unit.kind {
+        cmd.arg("--target").arg(&*target);
     }
 
     opt(cmd, "-C", "ar=", bcx.ar(unit.kind).map(|s| s.as_ref()));
@@ -922,7 +1303,7 @@ fn build_deps_args<'a, 'cfg>(
 
     // Be sure that the host path is also listed. This'll ensure that proc-macro
     // dependencies are correctly found (for reexported macros).
-    if let Kind::Target = unit.kind {
+    if let Kind::Target(_) = unit.kind {
         cmd.arg("-L").arg(&{
             let mut deps = OsString::from("dependency=");
             deps.push(cx.files().host_deps());
@@ -955,6 +1336,14 @@ fn build_deps_args<'a, 'cfg>(
         }
     }
 
+    // The standard library crates built with `-Z build-std` are passed with
+    // `noprelude`, like the sysroot ones they replace they mustn't show up in
+    // the extern prelude.
+    let package_id = unit.pkg.package_id();
+    if !bcx.is_std(package_id) && dep_targets.iter().any(|dep| bcx.is_std(dep.pkg.package_id())) {
+        cmd.arg("-Z").arg("unstable-options");
+    }
+
     for dep in dep_targets {
         if dep.mode.is_run_custom_build() {
             cmd.env("OUT_DIR", &cx.files().build_script_out_dir(&dep));
@@ -973,13 +1362,21 @@ fn build_deps_args<'a, 'cfg>(
         dep: &Unit<'a>,
     ) -> CargoResult<()> {
         let bcx = cx.bcx;
+        // A library only waiting for the metadata of `dep` is compiled
+        // against its `.rmeta`, everything else needs the whole library.
+        let only_rmeta = cx.only_requires_rmeta(current, dep);
         for output in cx.outputs(dep)?.iter() {
-            if output.flavor != FileFlavor::Linkable {
-                continue;
+            match output.flavor {
+                FileFlavor::Linkable { rmeta } if rmeta == only_rmeta => {}
+                _ => continue,
             }
             let mut v = OsString::new();
-            let name = bcx.extern_crate_name(current, dep)?;
-            v.push(name);
+            if bcx.is_std(dep.pkg.package_id()) && !bcx.is_std(current.pkg.package_id()) {
+                v.push("noprelude:");
+                v.push(dep.target.crate_name());
+            } else {
+                v.push(bcx.extern_crate_name(current, dep)?);
+            }
             v.push("=");
             v.push(cx.files().out_dir(dep));
             v.push(&path::MAIN_SEPARATOR.to_string());
@@ -1004,8 +1401,8 @@ impl Kind {
         // that needs to be on the host we lift ourselves up to `Host`
         match self {
             Kind::Host => Kind::Host,
-            Kind::Target if target.for_host() => Kind::Host,
-            Kind::Target => Kind::Target,
+            Kind::Target(_) if target.for_host() => Kind::Host,
+            Kind::Target(triple) => Kind::Target(triple),
         }
     }
 }
@@ -1021,9 +1418,107 @@ fn assert_is_empty(line: &str) -> CargoResult<()> {
     }
 }
 
-fn json_stderr(line: &str, package_id: PackageId, target: &Target) -> CargoResult<()> {
+/// A notification from rustc that it has written one of its outputs, as
+/// requested with `--json=artifacts`.
+#[derive(Deserialize)]
+struct ArtifactNotification {
+    artifact: String,
+}
+
+/// Handles the notification if `line` is one, reporting an `.rmeta` which a
+/// dependent may be waiting for.
+fn artifact_notification(line: &str, state: &JobState) -> bool {
+    match serde_json::from_str::<ArtifactNotification>(line) {
+        Ok(notification) => {
+            debug!("rustc wrote `{}`", notification.artifact);
+            if notification.artifact.ends_with(".rmeta") {
+                state.rmeta_produced();
+            }
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// What is done with the warnings of `unit`, `build.warnings` only applies
+/// to the members of the workspace.
+fn warning_handling(bcx: &BuildContext, unit: &Unit) -> WarningHandling {
+    if bcx.ws.is_member(unit.pkg) && !bcx.is_std(unit.pkg.package_id()) {
+        bcx.build_config.warnings
+    } else {
+        WarningHandling::Warn
+    }
+}
+
+/// Counts the diagnostic in `line` if it's a warning about the code of the
+/// unit and warnings aren't just printed, returns whether it's to be hidden.
+///
+/// The summary rustc emits at the end, like "1 warning emitted", has no
+/// spans and isn't counted, but it's hidden along with the warnings.
+fn handle_warning(line: &str, state: &JobState, warnings: WarningHandling) -> bool {
+    #[derive(Deserialize)]
+    struct Diagnostic {
+        level: String,
+        spans: Vec<serde::de::IgnoredAny>,
+    }
+
+    if warnings == WarningHandling::Warn {
+        return false;
+    }
+    match serde_json::from_str::<Diagnostic>(line) {
+        Ok(ref diagnostic) if diagnostic.level == "warning" => {
+            if !diagnostic.spans.is_empty() {
+                state.warning();
+            }
+            warnings == WarningHandling::Allow
+        }
+        _ => false,
+    }
+}
+
+/// Prints the diagnostics rustc rendered into its JSON messages when
+/// pipelining or handling warnings with the human readable message formats.
+fn rendered_stderr(line: &str, state: &JobState, warnings: WarningHandling) -> CargoResult<()> {
+    #[derive(Deserialize)]
+    struct CompilerMessage {
+        rendered: String,
+    }
+
+    if line.starts_with('{') {
+        if artifact_notification(line, state) {
+            return Ok(());
+        }
+        if handle_warning(line, state, warnings) {
+            return Ok(());
+        }
+        if let Ok(message) = serde_json::from_str::<CompilerMessage>(line) {
+            for line in message.rendered.lines() {
+                state.stderr(line.to_string());
+            }
+            return Ok(());
+        }
+    }
+    state.stderr(line.to_string());
+    Ok(())
+}
+
+fn json_stderr(
+    line: &str,
+    package_id: PackageId,
+    target: &Target,
+    state: &JobState,
+    warnings: WarningHandling,
+) -> CargoResult<()> {
+    state.record_stderr(line);
     // stderr from rustc/rustdoc can have a mix of JSON and non-JSON output
     if line.starts_with('{') {
+        if artifact_notification(line, state) {
+            return Ok(());
+        }
+        if handle_warning(line, state, warnings) {
+            return Ok(());
+        }
+
         // Handle JSON lines
         let compiler_message = serde_json::from_str(line)
             .map_err(|_| internal(&format!("compiler produced invalid json: `{}`", line)))?;
@@ -3,7 +3,7 @@ use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 
-use super::{fingerprint, Context, Unit};
+use super::{fingerprint, Context, FileFlavor, Unit};
 use crate::util::paths;
 use crate::util::{internal, CargoResult};
 
@@ -93,6 +93,9 @@ pub fn output_depinfo<'a, 'b>(cx: &mut Context<'a, 'b>, unit: &Unit<'a>) -> Carg
         .collect::<CargoResult<Vec<_>>>()?;
 
     for output in cx.outputs(unit)?.iter() {
+        if output.flavor == FileFlavor::Emitted || output.flavor == FileFlavor::PostLink {
+            continue;
+        }
         if let Some(ref link_dst) = output.hardlink {
             let output_path = link_dst.with_extension("d");
             if success {
@@ -0,0 +1,104 @@
+//! Running the `post-link` program configured for a target, like
+//! `wasm-bindgen` or `wasm-opt`, on the `.wasm` files linked for it.
+//!
+//! Each `.wasm` file gets a directory of its own next to it for the program
+//! to write to, like `deps/foo-<hash>.post-link`. These directories are
+//! outputs of the unit, so they're uplifted along with the `.wasm` file, and
+//! the unit is rebuilt when one goes missing.
+
+use std::path::Path;
+
+use super::job::Work;
+use super::{Context, FileFlavor, Unit};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, process};
+
+/// Prepares the work running the `post-link` program on the `.wasm` files
+/// of `unit`, which does nothing if there's no program for its target.
+pub fn prepare<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult<Work> {
+    let (program, args) = match cx.bcx.post_link(unit.kind) {
+        Some(post_link) => post_link.clone(),
+        None => return Ok(Work::noop()),
+    };
+    let out_dirs = cx
+        .outputs(unit)?
+        .iter()
+        .filter(|output| output.flavor == FileFlavor::PostLink)
+        .map(|output| output.path.clone())
+        .collect::<Vec<_>>();
+    if out_dirs.is_empty() {
+        return Ok(Work::noop());
+    }
+    let name = unit.target.crate_name();
+    let pkg = unit.pkg.to_string();
+
+    Ok(Work::new(move |state| {
+        for out_dir in out_dirs {
+            let artifact = out_dir.with_extension("wasm");
+            // Whatever a previous run left behind would otherwise end up
+            // mixed up with the new files.
+            if out_dir.exists() {
+                paths::remove_dir_all(&out_dir)?;
+            }
+            paths::create_dir_all(&out_dir)?;
+
+            let mut cmd = process(&program);
+            cmd.args(&expand_args(&args, &artifact, &out_dir, &name));
+            state.running(&cmd);
+            state
+                .capture_output(&cmd, None, false)
+                .chain_err(|| format!("failed to run the `post-link` program for `{}`", pkg))?;
+        }
+        Ok(())
+    }))
+}
+
+/// Replaces `{artifact}`, `{out-dir}` and `{name}` in the arguments of the
+/// `post-link` program. The `.wasm` file is passed as the last argument
+/// when none of them mention `{artifact}`.
+fn expand_args(args: &[String], artifact: &Path, out_dir: &Path, name: &str) -> Vec<String> {
+    let artifact = artifact.display().to_string();
+    let out_dir = out_dir.display().to_string();
+    let mut expanded = args
+        .iter()
+        .map(|arg| {
+            arg.replace("{artifact}", &artifact)
+                .replace("{out-dir}", &out_dir)
+                .replace("{name}", name)
+        })
+        .collect::<Vec<_>>();
+    if !args.iter().any(|arg| arg.contains("{artifact}")) {
+        expanded.push(artifact);
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::expand_args;
+
+    #[test]
+    fn expands_placeholders() {
+        let args = vec![
+            "-O".to_string(),
+            "-o".to_string(),
+            "{out-dir}/{name}.wasm".to_string(),
+            "{artifact}".to_string(),
+        ];
+        assert_eq!(
+            expand_args(&args, Path::new("deps/foo.wasm"), Path::new("deps/foo.post-link"), "foo"),
+            vec!["-O", "-o", "deps/foo.post-link/foo.wasm", "deps/foo.wasm"]
+        );
+    }
+
+    #[test]
+    fn appends_artifact() {
+        let args = vec!["--out-dir".to_string(), "{out-dir}".to_string()];
+        assert_eq!(
+            expand_args(&args, Path::new("deps/foo.wasm"), Path::new("deps/foo.post-link"), "foo"),
+            vec!["--out-dir", "deps/foo.post-link", "deps/foo.wasm"]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! An opt-in sandbox for proc-macros, configured with
+//! `[build.proc-macro-sandbox]`.
+//!
+//! Proc-macros run inside the compiler, when it compiles the crates using
+//! them. The compilations of proc-macro crates, and of the crates depending
+//! on them, even indirectly as crates re-export the macros of their
+//! dependencies, as well as their documentation and doctests, are run in a
+//! working directory of their own in the target directory instead of the
+//! package's, with only the environment variables cargo sets for rustc and a
+//! few the toolchain needs, and within the configured limits on CPU time and
+//! memory. This doesn't stop a malicious
+//! macro from reading or writing files, but keeps secrets in the environment
+//! of cargo, like registry tokens, out of its reach, and keeps runaway macros
+//! from taking the machine down.
+
+use std::collections::HashSet;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use super::{BuildEnv, Context, Unit};
+use crate::util::errors::CargoResult;
+use crate::util::{paths, Config, ProcessBuilder, ResourceLimits};
+
+/// The environment variables passed through to sandboxed compilations
+/// besides the ones cargo sets, which the toolchain needs to run.
+const DEFAULT_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "TMPDIR",
+    "TMP",
+    "TEMP",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "LD_LIBRARY_PATH",
+    "DYLD_LIBRARY_PATH",
+    "DYLD_FALLBACK_LIBRARY_PATH",
+    "SYSTEMROOT",
+    "LIB",
+];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SandboxConfig {
+    working_dir: Option<String>,
+    env: Option<Vec<String>>,
+    cpu_time: Option<u64>,
+    memory: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct ProcMacroSandbox {
+    /// The directory the compilations run in, relative to the target
+    /// directory.
+    working_dir: PathBuf,
+    /// The environment variables passed through besides `DEFAULT_ENV`.
+    env: Vec<String>,
+    limits: ResourceLimits,
+}
+
+impl ProcMacroSandbox {
+    /// The sandbox configured with `[build.proc-macro-sandbox]`, if any.
+    pub fn from_config(config: &Config) -> CargoResult<Option<ProcMacroSandbox>> {
+        let sandbox: Option<SandboxConfig> = config.get("build.proc-macro-sandbox")?;
+        let sandbox = match sandbox {
+            Some(sandbox) => sandbox,
+            None => return Ok(None),
+        };
+        let limits = ResourceLimits {
+            cpu_time: sandbox.cpu_time,
+            memory: sandbox.memory.map(|mib| mib * 1024 * 1024),
+        };
+        if !cfg!(unix) && limits != ResourceLimits::default() {
+            bail!(
+                "`build.proc-macro-sandbox.cpu-time` and `build.proc-macro-sandbox.memory` \
+                 are only supported on Unix"
+            );
+        }
+        Ok(Some(ProcMacroSandbox {
+            working_dir: PathBuf::from(
+                sandbox
+                    .working_dir
+                    .unwrap_or_else(|| "proc-macro-sandbox".to_string()),
+            ),
+            env: sandbox.env.unwrap_or_default(),
+            limits,
+        }))
+    }
+
+    /// Restricts the doctests `cmd` of `cargo test`, whose compilation may run
+    /// proc-macros, to run in `dir`, once it's got every environment variable
+    /// cargo sets for it.
+    pub fn restrict_doctests(
+        &self,
+        config: &Config,
+        build_env: &BuildEnv,
+        dir: &Path,
+        cmd: &mut ProcessBuilder,
+    ) -> CargoResult<()> {
+        paths::create_dir_all(dir)?;
+        cmd.cwd(dir);
+        self.restrict_env(config, build_env, cmd)
+    }
+
+    /// Clears the environment of `cmd` but for the variables cargo set and
+    /// the ones passed through, and applies the limits.
+    fn restrict_env(
+        &self,
+        config: &Config,
+        build_env: &BuildEnv,
+        cmd: &mut ProcessBuilder,
+    ) -> CargoResult<()> {
+        // The dynamic loader of the host may search another variable than the
+        // usual ones, configured with `build.dylib-path-env`.
+        let dylib_path_envvar = config.host_platform()?.dylib_path_envvar();
+        let names = DEFAULT_ENV
+            .iter()
+            .cloned()
+            .chain(Some(dylib_path_envvar))
+            .chain(self.env.iter().map(|s| s.as_str()));
+        for name in names {
+            if cmd.get_envs().contains_key(name) || !build_env.allows(name) {
+                continue;
+            }
+            if let Some(value) = env::var_os(name) {
+                cmd.env(name, value);
+            }
+        }
+        cmd.env_clear();
+        cmd.resource_limits(self.limits.clone());
+        Ok(())
+    }
+}
+
+/// The directory the compilation of `unit` runs in, if it's sandboxed: the
+/// compilation of a proc-macro, or of a crate which may run one.
+pub fn working_dir<'a>(cx: &Context<'a, '_>, unit: &Unit<'a>) -> Option<PathBuf> {
+    let sandbox = cx.bcx.build_config.proc_macro_sandbox.as_ref()?;
+    if !runs_proc_macros(cx, unit) {
+        return None;
+    }
+    let target_dir = cx.bcx.ws.target_dir().into_path_unlocked();
+    Some(target_dir.join(&sandbox.working_dir))
+}
+
+/// Whether `unit` is a proc-macro, or has one among the dependencies of
+/// its crate, direct or not, whose macros it may use as they can be
+/// re-exported.
+fn runs_proc_macros<'a>(cx: &Context<'a, '_>, unit: &Unit<'a>) -> bool {
+    if unit.target.is_proc_macro() {
+        return true;
+    }
+    let mut seen = HashSet::new();
+    let mut stack = vec![*unit];
+    while let Some(unit) = stack.pop() {
+        for dep in cx.dep_targets(&unit) {
+            // Only the build script itself uses the dependencies of a build
+            // script, not the crate it's run for.
+            if dep.mode.is_run_custom_build() {
+                continue;
+            }
+            if dep.target.is_proc_macro() {
+                return true;
+            }
+            if seen.insert(dep) {
+                stack.push(dep);
+            }
+        }
+    }
+    false
+}
+
+/// Restricts the compilation `cmd` of `unit`, if it's sandboxed, once it's
+/// got every environment variable cargo sets for it.
+pub fn restrict<'a>(
+    cx: &Context<'a, '_>,
+    unit: &Unit<'a>,
+    cmd: &mut ProcessBuilder,
+) -> CargoResult<()> {
+    let dir = match working_dir(cx, unit) {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+    let build_config = &cx.bcx.build_config;
+    let sandbox = build_config.proc_macro_sandbox.as_ref().unwrap();
+    paths::create_dir_all(&dir)?;
+    sandbox.restrict_env(cx.bcx.config, &build_config.build_env, cmd)
+}
@@ -0,0 +1,365 @@
+//! The retention policy of the artifacts in the target directory, configured
+//! with `build.keep-artifacts`.
+//!
+//! Each unit cargo builds leaves files named after its metadata hash in the
+//! directory of the profile, like `target/release`: its fingerprint in
+//! `.fingerprint/<name>-<hash>`, its artifacts in `deps` or `examples`, and
+//! for build scripts a directory in `build`. Once a unit isn't built anymore,
+//! because a dependency was updated or a flag changed, nothing refers to them
+//! but they stay around, and on CI runners shared by many builds the target
+//! directory grows until the disk is full.
+//!
+//! As a unit's fingerprint is only checked by the builds which need it, the
+//! last time a unit was used can't be told from its files: each build with a
+//! policy records it with a `last-use` file in the fingerprint directory of
+//! each of its units, which it writes at the end, whether the unit was fresh
+//! or not. Then, for the profiles the policy applies to, the units unused for
+//! longer than `max-age` are removed, then the least recently used ones until
+//! the directory takes less than `max-size`. The units without a `last-use`
+//! file, left by builds without a policy, were last used when their newest
+//! file was modified. The artifacts of the units of the build are
+//! never removed, nor the incremental compilation caches.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use filetime::FileTime;
+
+use super::build_config::parse_duration;
+use super::{Context, Kind, Unit};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, Config};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct KeepArtifactsConfig {
+    profiles: Option<Vec<String>>,
+    max_age: Option<String>,
+    max_size: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct KeepArtifacts {
+    /// The profiles whose directories the policy applies to, all of them if
+    /// `None`.
+    profiles: Option<Vec<String>>,
+    /// How long the artifacts of a unit are kept after it was last used.
+    max_age: Option<Duration>,
+    /// How many bytes the directory of a profile may take before the
+    /// artifacts of units are removed, the least recently used first.
+    max_size: Option<u64>,
+}
+
+impl KeepArtifacts {
+    /// The policy configured with `build.keep-artifacts`, if any.
+    pub fn from_config(config: &Config) -> CargoResult<Option<KeepArtifacts>> {
+        let keep: Option<KeepArtifactsConfig> = config.get("build.keep-artifacts")?;
+        let keep = match keep {
+            Some(keep) => keep,
+            None => return Ok(None),
+        };
+        let max_age = match keep.max_age {
+            Some(ref age) => match parse_duration(age) {
+                Some(age) => Some(age),
+                None => bail!(
+                    "invalid configuration for key `build.keep-artifacts.max-age`, \
+                     expected a duration like `12h` or `30d`, found `{}`",
+                    age
+                ),
+            },
+            None => None,
+        };
+        let max_size = match keep.max_size {
+            Some(ref size) => match parse_size(size) {
+                Some(size) => Some(size),
+                None => bail!(
+                    "invalid configuration for key `build.keep-artifacts.max-size`, \
+                     expected a size like `500MB` or `20GB`, found `{}`",
+                    size
+                ),
+            },
+            None => None,
+        };
+        if max_age.is_none() && max_size.is_none() {
+            bail!("`build.keep-artifacts` needs a `max-age` or a `max-size`");
+        }
+        Ok(Some(KeepArtifacts {
+            profiles: keep.profiles,
+            max_age,
+            max_size,
+        }))
+    }
+}
+
+/// The name of the file recording when a unit was last used, in its
+/// fingerprint directory.
+const LAST_USE: &str = "last-use";
+
+/// The files of the units with the same hash in the directory of a profile.
+#[derive(Default)]
+struct Unused {
+    paths: Vec<PathBuf>,
+    size: u64,
+    /// When a build last recorded using the unit.
+    last_use: Option<SystemTime>,
+    /// When the newest of the files was modified.
+    modified: Option<SystemTime>,
+}
+
+impl Unused {
+    fn last_used(&self) -> Option<SystemTime> {
+        self.last_use.or(self.modified)
+    }
+}
+
+/// Records the use of `units`, all the units of the build which just
+/// finished, then removes the artifacts of the units unused for too long
+/// from the directories of its profile, following `build.keep-artifacts`.
+pub fn enforce<'a: 'b, 'b>(
+    cx: &Context<'a, '_>,
+    units: impl Iterator<Item = &'b Unit<'a>>,
+) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    let keep = match bcx.build_config.keep_artifacts {
+        Some(ref keep) => keep,
+        None => return Ok(()),
+    };
+    if bcx.build_config.build_plan {
+        return Ok(());
+    }
+
+    // Recorded for every profile, as profiles like `test` and `dev` share a
+    // directory.
+    let files = cx.files();
+    let units = units.collect::<Vec<_>>();
+    let used = FileTime::from_system_time(SystemTime::now());
+    for unit in units.iter() {
+        let dir = files.fingerprint_dir(unit);
+        if dir.is_dir() {
+            // Writing an empty file which already is empty may not change
+            // its mtime, so it's set explicitly.
+            let last_use = dir.join(LAST_USE);
+            paths::write(&last_use, b"")?;
+            filetime::set_file_mtime(&last_use, used)
+                .chain_err(|| format!("failed to set the mtime of `{}`", last_use.display()))?;
+        }
+    }
+
+    let profile = bcx.build_config.profile_kind.name();
+    if let Some(ref profiles) = keep.profiles {
+        if !profiles.iter().any(|p| p == profile) {
+            return Ok(());
+        }
+    }
+
+    let live = units
+        .iter()
+        .map(|unit| match files.metadata(unit) {
+            Some(meta) => meta.to_string(),
+            None => files.target_short_hash(unit),
+        })
+        .collect::<HashSet<_>>();
+    let mut dests = vec![files.layout(Kind::Host).dest().to_path_buf()];
+    for kind in bcx.build_config.target_kinds() {
+        let dest = files.layout(kind).dest().to_path_buf();
+        if !dests.contains(&dest) {
+            dests.push(dest);
+        }
+    }
+
+    let now = SystemTime::now();
+    for dest in dests {
+        let mut unused = find_unused(&dest, &live)?.into_iter().collect::<Vec<_>>();
+        // The least recently used first.
+        unused.sort_by_key(|&(_, ref unit)| unit.last_used());
+        let mut size = dir_size(&dest);
+        let mut removed = 0;
+        let mut freed = 0;
+        for (_, unit) in unused {
+            let age = unit
+                .last_used()
+                .and_then(|last_used| now.duration_since(last_used).ok())
+                .unwrap_or_default();
+            let too_old = keep.max_age.map_or(false, |max_age| age > max_age);
+            let too_big = keep.max_size.map_or(false, |max_size| size > max_size);
+            if !too_old && !too_big {
+                continue;
+            }
+            for path in unit.paths.iter() {
+                remove(path)?;
+            }
+            size = size.saturating_sub(unit.size);
+            removed += 1;
+            freed += unit.size;
+        }
+        if removed > 0 {
+            bcx.config.shell().status(
+                "Pruned",
+                format!(
+                    "the artifacts of {} units not used recently from `{}`, freeing {}",
+                    removed,
+                    dest.display(),
+                    human_size(freed)
+                ),
+            )?;
+        }
+        if let Some(max_size) = keep.max_size {
+            if size > max_size {
+                bcx.config.shell().warn(format!(
+                    "`{}` takes {}, more than the {} of `build.keep-artifacts.max-size`, \
+                     but all its artifacts are used by this build",
+                    dest.display(),
+                    human_size(size),
+                    human_size(max_size)
+                ))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The files in the directory of a profile `dest` named after hashes which
+/// aren't in `live`, by hash.
+fn find_unused(dest: &Path, live: &HashSet<String>) -> CargoResult<BTreeMap<String, Unused>> {
+    let mut unused = BTreeMap::<String, Unused>::new();
+    for dir in &[".fingerprint", "build", "deps", "examples"] {
+        let dir = dest.join(dir);
+        if !dir.is_dir() {
+            continue;
+        }
+        let entries =
+            fs::read_dir(&dir).chain_err(|| format!("failed to read `{}`", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let hash = match path.file_name().and_then(|n| n.to_str()).and_then(hash_of) {
+                Some(hash) => hash,
+                None => continue,
+            };
+            if live.contains(hash) {
+                continue;
+            }
+            let unit = unused.entry(hash.to_string()).or_default();
+            let last_use = fs::metadata(path.join(LAST_USE)).and_then(|m| m.modified()).ok();
+            unit.last_use = unit.last_use.max(last_use);
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            unit.modified = unit.modified.max(modified);
+            unit.size += dir_size(&path);
+            unit.paths.push(path);
+        }
+    }
+    Ok(unused)
+}
+
+/// The hash in the name of a file of a unit, like `0123456789abcdef` in
+/// `libfoo-0123456789abcdef.rlib` or `foo-0123456789abcdef`.
+fn hash_of(name: &str) -> Option<&str> {
+    let stem = name.split('.').next().unwrap();
+    let hash = &stem[stem.rfind('-')? + 1..];
+    if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Some(hash)
+    } else {
+        None
+    }
+}
+
+/// The size of the file at `path`, or of the files in the directory at
+/// `path`, not following symbolic links.
+fn dir_size(path: &Path) -> u64 {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return 0,
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| dir_size(&e.path()))
+            .sum(),
+        Err(_) => 0,
+    }
+}
+
+fn remove(path: &Path) -> CargoResult<()> {
+    if path.is_dir() {
+        paths::remove_dir_all(path)
+    } else {
+        paths::remove_file(path)
+    }
+}
+
+/// Parses sizes like `500MB`, `20GB` or `4GiB`, a plain number is a number
+/// of bytes.
+fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => (&s[..i], s[i..].trim()),
+        None => (s, "B"),
+    };
+    let number = number.parse::<u64>().ok()?;
+    let unit: u64 = match unit {
+        "B" => 1,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        "TiB" => 1 << 40,
+        _ => return None,
+    };
+    number.checked_mul(unit)
+}
+
+/// Formats `bytes` like `1.5GB`.
+fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_of, human_size, parse_size};
+
+    #[test]
+    fn hashes_of_files() {
+        assert_eq!(
+            hash_of("libfoo-0123456789abcdef.rlib"),
+            Some("0123456789abcdef")
+        );
+        assert_eq!(
+            hash_of("build_script_build-0123456789abcdef.d"),
+            Some("0123456789abcdef")
+        );
+        assert_eq!(
+            hash_of("foo-bar-0123456789abcdef"),
+            Some("0123456789abcdef")
+        );
+        assert_eq!(hash_of("foo-bar"), None);
+        assert_eq!(hash_of("libfoo.rlib"), None);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("20GB"), Some(20_000_000_000));
+        assert_eq!(parse_size("4 GiB"), Some(4 << 30));
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("20 gigs"), None);
+        assert_eq!(human_size(999), "999B");
+        assert_eq!(human_size(1_500_000_000), "1.5GB");
+    }
+}
@@ -0,0 +1,173 @@
+//! Code for building the standard library from source with `-Z build-std`.
+//!
+//! The crates of the standard library are resolved in a workspace of their
+//! own, made up from the `rust-src` component of the toolchain. Their library
+//! units are then injected into the unit graph of the build as dependencies
+//! of everything compiled for the target, in place of the libraries in the
+//! sysroot (which custom targets don't have).
+
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::path::PathBuf;
+
+use super::{BuildConfig, CompileMode, Unit};
+use crate::core::profiles::{Profiles, UnitFor};
+use crate::core::resolver::Method;
+use crate::core::{Dependency, Features, Package, PackageId, PackageIdSpec, PackageSet, Resolve};
+use crate::core::{SourceId, VirtualManifest, Workspace, WorkspaceConfig, WorkspaceRootConfig};
+use crate::ops;
+use crate::sources::CRATES_IO_INDEX;
+use crate::util::{CargoResult, ToUrl};
+
+/// The standard library crates built with `-Z build-std`, everything a build
+/// needs to know about them once they're resolved.
+pub struct StandardLib<'a> {
+    pub resolve: &'a Resolve,
+    pub packages: HashMap<PackageId, &'a Package>,
+    /// The library units of the requested crates for each of the targets,
+    /// these are what the units compiled for a target get to link against.
+    pub roots: Vec<Unit<'a>>,
+}
+
+/// Parses the value of `-Z build-std`, a comma separated list of crates
+/// which defaults to `std`. The crates a requested crate builds upon are
+/// added to the list, so that they can be used by name as well.
+pub fn parse_unstable_flag(value: Option<&str>) -> Vec<String> {
+    let value = value.unwrap_or("std");
+    let mut crates = value
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect::<BTreeSet<_>>();
+    if crates.contains("std") {
+        crates.insert("alloc");
+    }
+    if crates.contains("alloc") {
+        crates.insert("core");
+    }
+    crates.into_iter().map(|s| s.to_string()).collect()
+}
+
+/// Resolves the standard library crates in `crates` from the sources in the
+/// `rust-src` component of the toolchain.
+pub fn resolve_std<'cfg>(
+    ws: &Workspace<'cfg>,
+    crates: &[String],
+) -> CargoResult<(PackageSet<'cfg>, Resolve)> {
+    let config = ws.config();
+    let src_path = detect_sysroot_src_path(ws)?;
+
+    // The dependencies of the standard library from crates.io depend on
+    // `rustc-std-workspace-*` placeholder crates, which stand in for the
+    // standard library crates themselves and are patched here.
+    let mut patches = Vec::new();
+    for name in &[
+        "rustc-std-workspace-core",
+        "rustc-std-workspace-alloc",
+        "rustc-std-workspace-std",
+    ] {
+        let path = src_path.join("src/tools").join(name);
+        if path.exists() {
+            let source_id = SourceId::for_path(&path)?;
+            patches.push(Dependency::parse_no_deprecated(name, None, source_id)?);
+        }
+    }
+    let mut patch = HashMap::new();
+    patch.insert(CRATES_IO_INDEX.to_url()?, patches);
+
+    let members = crates
+        .iter()
+        .map(|krate| format!("src/lib{}", krate))
+        .collect();
+    let ws_config = WorkspaceConfig::Root(WorkspaceRootConfig::new(
+        &src_path,
+        &Some(members),
+        &None,
+        &None,
+    ));
+    let profiles = Profiles::new(None, config, &Features::default(), &mut Vec::new())?;
+    let virtual_manifest = VirtualManifest::new(Vec::new(), patch, ws_config, profiles);
+    let mut std_ws = Workspace::new_virtual(src_path, virtual_manifest, config)?;
+    // Nothing may be written to the sysroot, which makes this also skip
+    // writing a lock file.
+    std_ws.set_require_optional_deps(false);
+
+    let specs = crates
+        .iter()
+        .map(|krate| PackageIdSpec::parse(krate))
+        .collect::<CargoResult<Vec<_>>>()?;
+    let method = Method::Required {
+        dev_deps: false,
+        features: &[],
+        all_features: false,
+        uses_default_features: true,
+    };
+    ops::resolve_ws_with_method(&std_ws, None, method, &specs, None)
+}
+
+/// Creates the library units of the standard library crates in `crates`,
+/// built with the active profile for each of the targets of the build.
+pub fn generate_std_roots<'a>(
+    build_config: &BuildConfig,
+    profiles: &Profiles,
+    crates: &[String],
+    packages: &HashMap<PackageId, &'a Package>,
+) -> CargoResult<Vec<Unit<'a>>> {
+    let mut roots = Vec::new();
+    for krate in crates {
+        let pkg = match packages.values().find(|pkg| pkg.name().as_str() == krate.as_str()) {
+            Some(pkg) => *pkg,
+            None => bail!("the standard library has no crate named `{}`", krate),
+        };
+        let lib = match pkg.targets().iter().find(|t| t.is_lib()) {
+            Some(lib) => lib,
+            None => bail!("the `{}` standard library crate has no library", krate),
+        };
+        let profile = profiles.get_profile(
+            pkg.package_id(),
+            false,
+            UnitFor::new_normal(),
+            CompileMode::Build,
+            build_config.profile_kind,
+        )?;
+        for kind in build_config.target_kinds() {
+            roots.push(Unit {
+                pkg,
+                target: lib,
+                profile,
+                kind,
+                mode: CompileMode::Build,
+            });
+        }
+    }
+    Ok(roots)
+}
+
+/// Checks that a build with `-Z build-std` has targets to build the standard
+/// library for, the host always comes with a standard library of its own.
+pub fn validate_targets(build_config: &BuildConfig) -> CargoResult<()> {
+    if build_config.requested_targets.is_empty() {
+        bail!("-Z build-std requires --target");
+    }
+    Ok(())
+}
+
+fn detect_sysroot_src_path(ws: &Workspace) -> CargoResult<PathBuf> {
+    if let Some(path) = env::var_os("__CARGO_TESTS_ONLY_SRC_ROOT") {
+        return Ok(PathBuf::from(path));
+    }
+    let rustc = ws.config().rustc(Some(ws))?;
+    let output = rustc.process().arg("--print=sysroot").exec_with_output()?;
+    let sysroot = String::from_utf8(output.stdout)
+        .map_err(|_| format_err!("rustc didn't return utf8 output"))?;
+    let src_path = PathBuf::from(sysroot.trim()).join("lib/rustlib/src/rust");
+    let lib_src = src_path.join("src/libstd/Cargo.toml");
+    if !lib_src.exists() {
+        bail!(
+            "{:?} does not exist, unable to build with the standard \
+             library, try:\n        rustup component add rust-src",
+            lib_src
+        );
+    }
+    Ok(src_path)
+}
@@ -0,0 +1,95 @@
+//! Checks for the native libraries packages declare in
+//! `[package.system-dependencies]`, so that a missing library is reported
+//! before anything is built rather than by the linker.
+
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+
+use super::{BuildContext, Unit};
+use crate::core::PackageId;
+use crate::util::{process, CargoResult};
+
+/// Checks the system dependencies of the packages of `units` with
+/// pkg-config, failing if any of them is missing.
+///
+/// pkg-config describes the libraries of the host, so units cross compiled
+/// for another target are only checked with `PKG_CONFIG_ALLOW_CROSS=1`, like
+/// the `pkg-config` crate does.
+pub fn check<'a, I>(bcx: &BuildContext, units: I) -> CargoResult<()>
+where
+    I: IntoIterator<Item = &'a Unit<'a>>,
+{
+    if bcx.build_config.build_plan {
+        return Ok(());
+    }
+    let allow_cross = env::var_os("PKG_CONFIG_ALLOW_CROSS").map_or(false, |v| v == "1");
+    let mut seen = HashSet::new();
+    let mut required = Vec::new();
+    for unit in units {
+        let deps = unit.pkg.manifest().system_dependencies();
+        if deps.is_empty() || (bcx.triple(unit.kind) != bcx.host_triple() && !allow_cross) {
+            continue;
+        }
+        let id = unit.pkg.package_id();
+        for dep in deps.values() {
+            if seen.insert((id, dep.constraint())) {
+                required.push((id, dep));
+            }
+        }
+    }
+    if required.is_empty() {
+        return Ok(());
+    }
+    required.sort_by_key(|&(id, dep)| (id, dep.constraint()));
+
+    let pkg_config = env::var_os("PKG_CONFIG").unwrap_or_else(|| OsString::from("pkg-config"));
+    if process(&pkg_config).arg("--version").exec_with_output().is_err() {
+        bcx.config.shell().warn(
+            "the system dependencies of packages weren't checked, as \
+             `pkg-config` isn't available",
+        )?;
+        return Ok(());
+    }
+
+    let mut missing = Vec::new();
+    for (id, dep) in required {
+        let constraint = dep.constraint();
+        let found = process(&pkg_config)
+            .arg("--exists")
+            .arg(&constraint)
+            .exec_with_output()
+            .is_ok();
+        if !found {
+            missing.push(describe_missing(&pkg_config, id, &dep.pkg_config, &constraint));
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    bail!(
+        "missing system dependencies:\n  {}\n\n\
+         Install the development files of these libraries, like the `-dev` or \
+         `-devel` packages of a distribution, or add the directories of their \
+         `.pc` files to `PKG_CONFIG_PATH`.",
+        missing.join("\n  ")
+    )
+}
+
+fn describe_missing(pkg_config: &OsString, id: PackageId, module: &str, constraint: &str) -> String {
+    let version = process(pkg_config)
+        .arg("--modversion")
+        .arg(module)
+        .exec_with_output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    match version {
+        Some(version) => format!(
+            "`{}`, required by `{}`, but version {} is installed",
+            constraint,
+            id,
+            version.trim()
+        ),
+        None => format!("`{}`, required by `{}`, was not found", constraint, id),
+    }
+}
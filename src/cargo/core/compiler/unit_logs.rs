@@ -0,0 +1,129 @@
+//! Logs of the output of each unit, with `build.log-dir`.
+//!
+//! When many jobs run at once their output is interleaved on the terminal,
+//! which may also cut long outputs off. Each unit which runs during a build
+//! also gets a log file of its own, like `target/logs/foo-foo-<hash>.log`,
+//! with the commands it ran and everything they printed, and `index.json`
+//! lists the unit each log belongs to and whether it succeeded.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde_json;
+
+use super::job_queue::Key;
+use super::{CompileMode, Kind};
+use crate::core::{PackageId, Target};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, short_hash};
+
+#[derive(Serialize)]
+struct LogEntry<'a> {
+    package_id: PackageId,
+    target: &'a Target,
+    mode: CompileMode,
+    kind: Kind,
+    log: String,
+    success: Option<bool>,
+}
+
+pub struct UnitLogs<'a> {
+    dir: PathBuf,
+    files: HashMap<Key<'a>, (File, usize)>,
+    index: Vec<LogEntry<'a>>,
+}
+
+impl<'a> UnitLogs<'a> {
+    /// Prepares the log directory `dir`, removing the logs of the previous
+    /// build.
+    pub fn new(dir: PathBuf) -> CargoResult<UnitLogs<'a>> {
+        paths::create_dir_all(&dir)?;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "log") {
+                paths::remove_file(&path)?;
+            }
+        }
+        Ok(UnitLogs {
+            dir,
+            files: HashMap::new(),
+            index: Vec::new(),
+        })
+    }
+
+    /// Records a command run for the unit of `key`.
+    pub fn command(&mut self, key: &Key<'a>, cmd: &str) -> CargoResult<()> {
+        self.line(key, &format!("Running {}", cmd))
+    }
+
+    /// Records a line the unit of `key` printed, without its colors.
+    pub fn line(&mut self, key: &Key<'a>, line: &str) -> CargoResult<()> {
+        if !self.files.contains_key(key) {
+            let name = format!(
+                "{}-{}-{}.log",
+                key.pkg.name(),
+                key.target.name(),
+                short_hash(key)
+            );
+            let path = self.dir.join(&name);
+            let file = File::create(&path)
+                .chain_err(|| format!("failed to create `{}`", path.display()))?;
+            self.files.insert(*key, (file, self.index.len()));
+            self.index.push(LogEntry {
+                package_id: key.pkg,
+                target: key.target,
+                mode: key.mode,
+                kind: key.kind,
+                log: name,
+                success: None,
+            });
+        }
+        let file = &mut self.files.get_mut(key).unwrap().0;
+        writeln!(file, "{}", strip_colors(line))?;
+        Ok(())
+    }
+
+    /// Records whether the unit of `key` succeeded, if it logged anything.
+    pub fn finish(&mut self, key: &Key<'a>, success: bool) {
+        if let Some(&(_, i)) = self.files.get(key) {
+            self.index[i].success = Some(success);
+        }
+    }
+
+    /// Writes `index.json`, listing the logs written so far.
+    pub fn write_index(&self) -> CargoResult<()> {
+        let json = serde_json::to_string_pretty(&self.index)?;
+        paths::write(&self.dir.join("index.json"), json.as_bytes())
+    }
+}
+
+/// Removes the escape sequences coloring the output of rustc.
+fn strip_colors(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("\x1B[") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start..].find(|c: char| c.is_ascii_alphabetic()) {
+            Some(end) => &rest[start + end + 1..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_colors;
+
+    #[test]
+    fn strips_colors() {
+        assert_eq!(
+            strip_colors("\x1B[0m\x1B[1m\x1B[38;5;9merror\x1B[0m: oops"),
+            "error: oops"
+        );
+        assert_eq!(strip_colors("plain"), "plain");
+    }
+}
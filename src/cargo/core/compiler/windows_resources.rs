@@ -0,0 +1,134 @@
+//! Compiling the resources declared in `[package.windows-resources]` into
+//! the binaries of a package built for Windows.
+//!
+//! A resource script including the script, icon and manifest of the package
+//! is written next to the binary and compiled with the resource compiler of
+//! the target's toolchain, `rc` for MSVC or `windres` for GNU, and the result
+//! is passed to the linker. The files of the resources are added to the
+//! dep-info of the binary, so that changing them rebuilds it.
+
+use std::path::{Path, PathBuf};
+
+use super::job_queue::JobState;
+use super::{BuildContext, CompileMode, Context, Unit};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, process, ProcessBuilder};
+
+/// The resources of a binary, ready to be compiled.
+pub struct Resources {
+    script: PathBuf,
+    contents: String,
+    cmd: ProcessBuilder,
+    /// The compiled resources, passed to the linker.
+    pub output: PathBuf,
+    /// The files of the resources, which the binary is rebuilt for.
+    pub inputs: Vec<PathBuf>,
+}
+
+impl Resources {
+    /// Writes the resource script and compiles it, for the package `name`.
+    pub fn compile(&self, state: &JobState<'_>, name: &str) -> CargoResult<()> {
+        paths::write(&self.script, self.contents.as_bytes())?;
+        state.running(&self.cmd);
+        self.cmd
+            .exec_with_output()
+            .chain_err(|| format!("failed to compile the Windows resources of `{}`", name))?;
+        Ok(())
+    }
+}
+
+/// The resources to compile into the binary of `unit`, if it's built for
+/// Windows and its package declares any.
+pub fn prepare<'a, 'cfg>(
+    cx: &mut Context<'a, 'cfg>,
+    unit: &Unit<'a>,
+) -> CargoResult<Option<Resources>> {
+    let bcx = cx.bcx;
+    let triple = bcx.triple(unit.kind);
+    if !unit.target.is_bin() || unit.mode != CompileMode::Build || !triple.contains("-windows-") {
+        return Ok(None);
+    }
+    let resources = match unit.pkg.manifest().windows_resources() {
+        Some(resources) => resources,
+        None => return Ok(None),
+    };
+    let root = unit.pkg.root();
+    let inputs = resources
+        .inputs()
+        .into_iter()
+        .map(|p| root.join(p))
+        .collect::<Vec<_>>();
+    if inputs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut contents = String::new();
+    if let Some(ref rc) = resources.rc {
+        contents.push_str(&format!("#include \"{}\"\n", escape(&root.join(rc))));
+    }
+    if let Some(ref icon) = resources.icon {
+        contents.push_str(&format!("1 ICON \"{}\"\n", escape(&root.join(icon))));
+    }
+    if let Some(ref manifest) = resources.manifest {
+        // `CREATEPROCESS_MANIFEST_RESOURCE_ID` and `RT_MANIFEST`.
+        contents.push_str(&format!("1 24 \"{}\"\n", escape(&root.join(manifest))));
+    }
+
+    let stem = format!("{}-resources", cx.files().file_stem(unit));
+    let out_dir = cx.files().out_dir(unit);
+    let script = out_dir.join(format!("{}.rc", stem));
+    let msvc = triple.ends_with("-msvc");
+    let output = out_dir.join(if msvc {
+        format!("{}.res", stem)
+    } else {
+        format!("{}.o", stem)
+    });
+
+    let mut cmd = process(resource_compiler(bcx, unit, msvc));
+    cmd.cwd(root);
+    if msvc {
+        cmd.arg("/nologo")
+            .arg("/I")
+            .arg(root)
+            .arg("/fo")
+            .arg(&output)
+            .arg(&script);
+    } else {
+        cmd.arg("--include-dir")
+            .arg(root)
+            .arg("--input")
+            .arg(&script)
+            .arg("--output-format=coff")
+            .arg("--output")
+            .arg(&output);
+    }
+    Ok(Some(Resources {
+        script,
+        contents,
+        cmd,
+        output,
+        inputs,
+    }))
+}
+
+/// The resource compiler configured for the target, or the one of its
+/// toolchain, prefixed like the other MinGW tools when cross compiling.
+fn resource_compiler(bcx: &BuildContext, unit: &Unit, msvc: bool) -> PathBuf {
+    if let Some(path) = bcx.resource_compiler(unit.kind) {
+        return path.to_path_buf();
+    }
+    let triple = bcx.triple(unit.kind);
+    if msvc {
+        PathBuf::from("rc")
+    } else if triple == bcx.host_triple() {
+        PathBuf::from("windres")
+    } else {
+        let arch = triple.split('-').next().unwrap();
+        PathBuf::from(format!("{}-w64-mingw32-windres", arch))
+    }
+}
+
+/// Quotes a path in a resource script, where backslashes are escapes.
+fn escape(path: &Path) -> String {
+    path.display().to_string().replace('\\', "\\\\")
+}
@@ -419,6 +419,14 @@ impl Dependency {
         if self.source_id() != to_replace {
             self
         } else {
+            // A locked dependency on a registry stays locked on the registry
+            // replacing it, so that it can still be a yanked version.
+            let replace_with = match self.source_id().precise() {
+                Some(precise) if replace_with.is_registry() => {
+                    replace_with.with_precise(Some(precise.to_string()))
+                }
+                _ => replace_with,
+            };
             self.set_source_id(replace_with);
             self
         }
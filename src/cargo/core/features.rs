@@ -193,6 +193,28 @@ features! {
 
         // Declarative build scripts.
         [unstable] metabuild: bool,
+
+        // Compile probes run by Cargo on behalf of build scripts.
+        [unstable] build_probes: bool,
+
+        // Custom profiles selected with `--profile`.
+        [unstable] named_profiles: bool,
+
+        // Default features enabled only for some platforms with
+        // `[target.'cfg(..)'.features]`.
+        [unstable] target_default_features: bool,
+
+        // Native libraries checked for with pkg-config, declared in
+        // `[package.system-dependencies]`.
+        [unstable] system_dependencies: bool,
+
+        // Windows resources compiled into binaries, declared in
+        // `[package.windows-resources]`.
+        [unstable] windows_resources: bool,
+
+        // Shared libraries uplifted as `libfoo.so.1.2.3` with symlinks from
+        // `libfoo.so.1` and `libfoo.so`, with `versioned` in `[lib]`.
+        [unstable] versioned_libraries: bool,
     }
 }
 
@@ -318,6 +340,20 @@ pub struct CliUnstable {
     pub package_features: bool,
     pub advanced_env: bool,
     pub config_profile: bool,
+    pub package_signing: bool,
+    pub member_features: bool,
+    pub test_metadata: bool,
+    pub target_applies_to_host: bool,
+    pub allow_dirty_vendor: bool,
+    pub symlinks: bool,
+    pub term_output: bool,
+    pub install_metadata: bool,
+    pub multitarget: bool,
+    pub content_hash: bool,
+    pub resolver_debug: bool,
+    pub partial_workspace: bool,
+    pub build_std: Option<Vec<String>>,
+    pub dependent_cfg: bool,
 }
 
 impl CliUnstable {
@@ -354,6 +390,22 @@ impl CliUnstable {
             "package-features" => self.package_features = true,
             "advanced-env" => self.advanced_env = true,
             "config-profile" => self.config_profile = true,
+            "package-signing" => self.package_signing = true,
+            "member-features" => self.member_features = true,
+            "test-metadata" => self.test_metadata = true,
+            "target-applies-to-host" => self.target_applies_to_host = true,
+            "allow-dirty-vendor" => self.allow_dirty_vendor = true,
+            "symlinks" => self.symlinks = true,
+            "term-output" => self.term_output = true,
+            "install-metadata" => self.install_metadata = true,
+            "multitarget" => self.multitarget = true,
+            "content-hash" => self.content_hash = true,
+            "resolver-debug" => self.resolver_debug = true,
+            "partial-workspace" => self.partial_workspace = true,
+            "build-std" => {
+                self.build_std = Some(crate::core::compiler::standard_lib::parse_unstable_flag(v))
+            }
+            "dependent-cfg" => self.dependent_cfg = true,
             _ => bail!("unknown `-Z` flag specified: {}", k),
         }
 
@@ -45,6 +45,101 @@ pub struct Manifest {
     im_a_teapot: Option<bool>,
     default_run: Option<String>,
     metabuild: Option<Vec<String>>,
+    probes: BTreeMap<String, Probe>,
+    system_dependencies: BTreeMap<String, SystemDependency>,
+    windows_resources: Option<WindowsResources>,
+}
+
+/// A compile probe declared in `[package.probes]`.
+///
+/// Cargo compiles probes on behalf of the package's build script and tells
+/// it through `CARGO_PROBE_<NAME>` whether each one succeeded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Probe {
+    /// The given type exists.
+    Type(String),
+    /// The given item can be imported.
+    Path(String),
+    /// The given expression compiles.
+    Expr(String),
+}
+
+impl Probe {
+    /// The source of the library crate compiled for this probe.
+    pub fn source(&self) -> String {
+        let body = match *self {
+            Probe::Type(ref ty) => format!("pub type Probe = {};", ty),
+            Probe::Path(ref path) => format!("pub use {};", path),
+            Probe::Expr(ref expr) => format!("pub fn probe() {{ let _ = {}; }}", expr),
+        };
+        format!("#![allow(warnings)]\n{}\n", body)
+    }
+}
+
+/// A native library declared in `[package.system-dependencies]`, which
+/// Cargo checks for with pkg-config before building the package.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct SystemDependency {
+    /// The pkg-config comparison and version the module has to satisfy,
+    /// like `>= 1.0.2`, `None` if any version will do.
+    pub version: Option<String>,
+    /// The name of the pkg-config module, by default the name of the entry.
+    pub pkg_config: String,
+    /// The names of the libraries the package links, for packagers.
+    pub libs: Vec<String>,
+}
+
+impl SystemDependency {
+    /// Parses a version constraint like `>= 1.0.2`, a bare version means at
+    /// least that version.
+    pub fn parse_version(version: &str) -> CargoResult<String> {
+        let version = version.trim();
+        let op_len = version
+            .find(|c: char| c.is_alphanumeric() || c.is_whitespace())
+            .unwrap_or_else(|| version.len());
+        let (op, number) = version.split_at(op_len);
+        let number = number.trim();
+        let op = if op.is_empty() { ">=" } else { op };
+        if !["=", "!=", "<", "<=", ">", ">="].contains(&op) {
+            bail!("invalid comparison `{}` in `{}`", op, version);
+        }
+        if number.is_empty() || number.contains(char::is_whitespace) {
+            bail!("expected a version like `>= 1.0.2`, found `{}`", version);
+        }
+        Ok(format!("{} {}", op, number))
+    }
+
+    /// The module and constraint as pkg-config takes them, like
+    /// `openssl >= 1.0.2`.
+    pub fn constraint(&self) -> String {
+        match self.version {
+            Some(ref version) => format!("{} {}", self.pkg_config, version),
+            None => self.pkg_config.clone(),
+        }
+    }
+}
+
+/// The resources declared in `[package.windows-resources]`, which are
+/// compiled into the binaries of the package when building for Windows. The
+/// paths are relative to the root of the package.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WindowsResources {
+    /// A resource script, for anything beyond the icon and manifest.
+    pub rc: Option<PathBuf>,
+    /// The application manifest, like `app.manifest`.
+    pub manifest: Option<PathBuf>,
+    /// The icon of the binaries, like `app.ico`.
+    pub icon: Option<PathBuf>,
+}
+
+impl WindowsResources {
+    /// The files the resources are made from.
+    pub fn inputs(&self) -> Vec<&Path> {
+        vec![&self.rc, &self.manifest, &self.icon]
+            .into_iter()
+            .filter_map(|p| p.as_ref().map(|p| p.as_path()))
+            .collect()
+    }
 }
 
 /// When parsing `Cargo.toml`, some warnings should silenced
@@ -119,6 +214,15 @@ impl LibKind {
             LibKind::Other(..) => false,
         }
     }
+
+    /// Returns whether producing this kind of library needs the object code
+    /// of its dependencies rather than just their metadata.
+    pub fn requires_upstream_objects(&self) -> bool {
+        match *self {
+            LibKind::Lib | LibKind::Rlib => false,
+            LibKind::Dylib | LibKind::ProcMacro | LibKind::Other(..) => true,
+        }
+    }
 }
 
 impl fmt::Debug for LibKind {
@@ -192,6 +296,18 @@ impl TargetKind {
             TargetKind::CustomBuild => "build-script",
         }
     }
+
+    /// Returns whether producing this target needs the object code of its
+    /// dependencies, like for anything that gets linked, rather than just
+    /// their metadata.
+    pub fn requires_upstream_objects(&self) -> bool {
+        match self {
+            TargetKind::Lib(kinds) | TargetKind::ExampleLib(kinds) => {
+                kinds.iter().any(|k| k.requires_upstream_objects())
+            }
+            _ => true,
+        }
+    }
 }
 
 /// Information about a binary, a library, an example, etc. that is part of the
@@ -213,6 +329,8 @@ pub struct Target {
     harness: bool, // whether to use the test harness (--test)
     for_host: bool,
     edition: Edition,
+    // whether shared libraries are uplifted under versioned names
+    versioned: bool,
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -347,6 +465,7 @@ compact_debug! {
                 harness
                 for_host
                 edition
+                versioned
             )]
         }
     }
@@ -373,6 +492,9 @@ impl Manifest {
         default_run: Option<String>,
         original: Rc<TomlManifest>,
         metabuild: Option<Vec<String>>,
+        probes: BTreeMap<String, Probe>,
+        system_dependencies: BTreeMap<String, SystemDependency>,
+        windows_resources: Option<WindowsResources>,
     ) -> Manifest {
         Manifest {
             summary,
@@ -395,6 +517,9 @@ impl Manifest {
             default_run,
             publish_lockfile,
             metabuild,
+            probes,
+            system_dependencies,
+            windows_resources,
         }
     }
 
@@ -422,6 +547,9 @@ impl Manifest {
     pub fn targets(&self) -> &[Target] {
         &self.targets
     }
+    pub fn targets_mut(&mut self) -> &mut [Target] {
+        &mut self.targets
+    }
     pub fn version(&self) -> &Version {
         self.package_id().version()
     }
@@ -518,6 +646,18 @@ impl Manifest {
         self.metabuild.as_ref()
     }
 
+    pub fn probes(&self) -> &BTreeMap<String, Probe> {
+        &self.probes
+    }
+
+    pub fn system_dependencies(&self) -> &BTreeMap<String, SystemDependency> {
+        &self.system_dependencies
+    }
+
+    pub fn windows_resources(&self) -> Option<&WindowsResources> {
+        self.windows_resources.as_ref()
+    }
+
     pub fn metabuild_path(&self, target_dir: Filesystem) -> PathBuf {
         let hash = short_hash(&self.package_id());
         target_dir
@@ -582,6 +722,7 @@ impl Target {
             edition,
             tested: true,
             benched: true,
+            versioned: false,
         }
     }
 
@@ -732,6 +873,9 @@ impl Target {
     pub fn edition(&self) -> Edition {
         self.edition
     }
+    pub fn versioned(&self) -> bool {
+        self.versioned
+    }
     pub fn benched(&self) -> bool {
         self.benched
     }
@@ -759,6 +903,13 @@ impl Target {
         }
     }
 
+    pub fn is_proc_macro(&self) -> bool {
+        match self.kind {
+            TargetKind::Lib(ref libs) => libs.iter().any(|l| *l == LibKind::ProcMacro),
+            _ => false,
+        }
+    }
+
     pub fn is_dylib(&self) -> bool {
         match self.kind {
             TargetKind::Lib(ref libs) => libs.iter().any(|l| *l == LibKind::Dylib),
@@ -858,6 +1009,10 @@ impl Target {
         self.edition = edition;
         self
     }
+    pub fn set_versioned(&mut self, versioned: bool) -> &mut Target {
+        self.versioned = versioned;
+        self
+    }
     pub fn set_harness(&mut self, harness: bool) -> &mut Target {
         self.harness = harness;
         self
@@ -866,6 +1021,10 @@ impl Target {
         self.doc = doc;
         self
     }
+    pub fn set_kind(&mut self, kind: TargetKind) -> &mut Target {
+        self.kind = kind;
+        self
+    }
 }
 
 impl fmt::Display for Target {
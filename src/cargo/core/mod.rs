@@ -5,16 +5,16 @@ pub use self::features::{
     enable_nightly_features,
     nightly_features_allowed
 };
-pub use self::manifest::{EitherManifest, VirtualManifest};
-pub use self::manifest::{LibKind, Manifest, Target, TargetKind};
+pub use self::manifest::{EitherManifest, VirtualManifest, WindowsResources};
+pub use self::manifest::{LibKind, Manifest, Probe, SystemDependency, Target, TargetKind};
 pub use self::package::{Package, PackageSet};
 pub use self::package_id::PackageId;
 pub use self::package_id_spec::PackageIdSpec;
 pub use self::registry::Registry;
 pub use self::resolver::Resolve;
-pub use self::shell::{Shell, Verbosity};
+pub use self::shell::{Category, Shell, Verbosity};
 pub use self::source::{GitReference, Source, SourceId, SourceMap};
-pub use self::summary::{FeatureMap, FeatureValue, Summary};
+pub use self::summary::{FeatureMap, FeatureValue, IndexFields, Summary};
 pub use self::workspace::{Members, Workspace, WorkspaceConfig, WorkspaceRootConfig};
 
 pub mod compiler;
@@ -1,6 +1,6 @@
 use std::cell::{Cell, Ref, RefCell};
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::hash;
 use std::mem;
@@ -20,7 +20,7 @@ use toml;
 
 use crate::core::interning::InternedString;
 use crate::core::source::MaybePackage;
-use crate::core::{Dependency, Manifest, PackageId, SourceId, Target};
+use crate::core::{Dependency, Manifest, PackageId, SourceId, SystemDependency, Target};
 use crate::core::{FeatureMap, SourceMap, Summary};
 use crate::ops;
 use crate::util::errors::{CargoResult, CargoResultExt, HttpNot200};
@@ -74,6 +74,8 @@ struct SerializedPackage<'a> {
     edition: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
     metabuild: Option<&'a Vec<String>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    system_dependencies: &'a BTreeMap<String, SystemDependency>,
 }
 
 impl ser::Serialize for Package {
@@ -122,6 +124,7 @@ impl ser::Serialize for Package {
             repository,
             edition: &self.manifest.edition().to_string(),
             metabuild: self.manifest.metabuild(),
+            system_dependencies: self.manifest.system_dependencies(),
         }
         .serialize(s)
     }
@@ -144,6 +147,10 @@ impl Package {
     pub fn manifest(&self) -> &Manifest {
         &self.manifest
     }
+    /// Get the manifest, to change it
+    pub fn manifest_mut(&mut self) -> &mut Manifest {
+        &mut self.manifest
+    }
     /// Get the path to the manifest
     pub fn manifest_path(&self) -> &Path {
         &self.manifest_path
@@ -268,12 +275,17 @@ pub struct PackageSet<'cfg> {
     multi: Multi,
     downloading: Cell<bool>,
     multiplexing: bool,
+    /// The `http.max-concurrent-downloads` configuration.
+    max_concurrent_downloads: Option<usize>,
 }
 
 pub struct Downloads<'a, 'cfg: 'a> {
     set: &'a PackageSet<'cfg>,
     pending: HashMap<usize, (Download<'cfg>, EasyHandle)>,
     pending_ids: HashSet<PackageId>,
+    /// Downloads waiting for one of the `pending` ones to finish, to stay
+    /// within `http.max-concurrent-downloads`.
+    queued: VecDeque<(Download<'cfg>, Easy)>,
     results: Vec<(usize, Result<(), curl::Error>)>,
     next: usize,
     progress: RefCell<Option<Progress<'cfg>>>,
@@ -351,6 +363,12 @@ impl<'cfg> PackageSet<'cfg> {
         // let's not flood crates.io with connections
         multi.set_max_host_connections(2)?;
 
+        let max_concurrent_downloads =
+            config.get::<Option<u32>>("http.max-concurrent-downloads")?;
+        if max_concurrent_downloads == Some(0) {
+            bail!("`http.max-concurrent-downloads` must be at least 1");
+        }
+
         Ok(PackageSet {
             packages: package_ids
                 .iter()
@@ -361,6 +379,7 @@ impl<'cfg> PackageSet<'cfg> {
             multi,
             downloading: Cell::new(false),
             multiplexing,
+            max_concurrent_downloads: max_concurrent_downloads.map(|n| n as usize),
         })
     }
 
@@ -377,6 +396,7 @@ impl<'cfg> PackageSet<'cfg> {
             next: 0,
             pending: HashMap::new(),
             pending_ids: HashSet::new(),
+            queued: VecDeque::new(),
             results: Vec::new(),
             progress: RefCell::new(Some(Progress::with_style(
                 "Downloading",
@@ -394,6 +414,15 @@ impl<'cfg> PackageSet<'cfg> {
         })
     }
 
+    /// Replaces the package of the set with the same id as `pkg`, like to
+    /// build it with some of its targets changed.
+    pub fn replace(&mut self, pkg: Package) {
+        let cell = LazyCell::new();
+        let id = pkg.package_id();
+        assert!(cell.fill(pkg).is_ok());
+        self.packages.insert(id, cell);
+    }
+
     pub fn get_one(&self, id: PackageId) -> CargoResult<&Package> {
         Ok(self.get_many(Some(id))?.remove(0))
     }
@@ -566,7 +595,7 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
 
     /// Returns the number of crates that are still downloading
     pub fn remaining(&self) -> usize {
-        self.pending.len()
+        self.pending.len() + self.queued.len()
     }
 
     /// Blocks the current thread waiting for a package to finish downloading.
@@ -579,7 +608,7 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
     /// This function will panic if there are no remaining downloads.
     pub fn wait(&mut self) -> CargoResult<&'a Package> {
         let (dl, data) = loop {
-            assert_eq!(self.pending.len(), self.pending_ids.len());
+            assert_eq!(self.remaining(), self.pending_ids.len());
             let (token, result) = self.wait_for_curl()?;
             debug!("{} finished with {:?}", token, result);
 
@@ -591,6 +620,11 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
             let mut handle = self.set.multi.remove(handle)?;
             self.pending_ids.remove(&dl.id);
 
+            // Now that a transfer finished, the next queued one can start.
+            if let Some((queued, queued_handle)) = self.queued.pop_front() {
+                self.enqueue(queued, queued_handle)?;
+            }
+
             // Check if this was a spurious error. If it was a spurious error
             // then we want to re-enqueue our request for another attempt and
             // then we wait for another request to finish.
@@ -695,6 +729,12 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
     }
 
     fn enqueue(&mut self, dl: Download<'cfg>, handle: Easy) -> CargoResult<()> {
+        if let Some(max) = self.set.max_concurrent_downloads {
+            if self.pending.len() >= max {
+                self.queued.push_back((dl, handle));
+                return Ok(());
+            }
+        }
         let mut handle = self.set.multi.add(handle)?;
         let now = Instant::now();
         handle.set_token(dl.token)?;
@@ -826,7 +866,7 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
                 return Ok(());
             }
         }
-        let pending = self.pending.len();
+        let pending = self.remaining();
         let mut msg = if pending == 1 {
             format!("{} crate", pending)
         } else {
@@ -1,9 +1,9 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::{cmp, fmt, hash};
 
-use crate::core::compiler::CompileMode;
+use crate::core::compiler::{CompileMode, ProfileKind};
 use crate::core::interning::InternedString;
-use crate::core::{Features, PackageId, PackageIdSpec, PackageSet, Shell};
+use crate::core::{Feature, Features, PackageId, PackageIdSpec, PackageSet, Shell};
 use crate::util::errors::CargoResultExt;
 use crate::util::lev_distance::lev_distance;
 use crate::util::toml::{ProfilePackageSpec, StringOrBool, TomlProfile, TomlProfiles, U32OrBool};
@@ -17,6 +17,8 @@ pub struct Profiles {
     test: ProfileMaker,
     bench: ProfileMaker,
     doc: ProfileMaker,
+    /// Custom profiles, along with whether they inherit from `release`.
+    custom: BTreeMap<InternedString, (ProfileMaker, bool)>,
 }
 
 impl Profiles {
@@ -33,35 +35,78 @@ impl Profiles {
         let config_profiles = config.profiles()?;
         config_profiles.validate(features, warnings)?;
 
+        let mut custom = BTreeMap::new();
+        if features.is_enabled(Feature::named_profiles()) {
+            for name in profiles.iter().flat_map(|p| p.custom.keys()) {
+                let (maker, release) =
+                    custom_profile_maker(name, profiles.unwrap(), config_profiles)?;
+                custom.insert(InternedString::new(name), (maker, release));
+            }
+        }
+
+        let toml = |name: &str| profiles.and_then(|p| p.get(name)).cloned();
         Ok(Profiles {
             dev: ProfileMaker {
                 default: Profile::default_dev(),
-                toml: profiles.and_then(|p| p.dev.clone()),
+                toml: toml("dev").into_iter().collect(),
                 config: config_profiles.dev.clone(),
             },
             release: ProfileMaker {
                 default: Profile::default_release(),
-                toml: profiles.and_then(|p| p.release.clone()),
+                toml: toml("release").into_iter().collect(),
                 config: config_profiles.release.clone(),
             },
             test: ProfileMaker {
                 default: Profile::default_test(),
-                toml: profiles.and_then(|p| p.test.clone()),
+                toml: toml("test").into_iter().collect(),
                 config: None,
             },
             bench: ProfileMaker {
                 default: Profile::default_bench(),
-                toml: profiles.and_then(|p| p.bench.clone()),
+                toml: toml("bench").into_iter().collect(),
                 config: None,
             },
             doc: ProfileMaker {
                 default: Profile::default_doc(),
-                toml: profiles.and_then(|p| p.doc.clone()),
+                toml: toml("doc").into_iter().collect(),
                 config: None,
             },
+            custom,
         })
     }
 
+    /// Checks that the profile selected on the command line exists.
+    pub fn validate_profile_kind(&self, kind: ProfileKind) -> CargoResult<()> {
+        if let ProfileKind::Custom(name) = kind {
+            if !self.custom.contains_key(&name) {
+                bail!("profile `{}` is not defined", name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `kind` is `release` or a custom profile inheriting from it.
+    pub fn is_release(&self, kind: ProfileKind) -> bool {
+        match kind {
+            ProfileKind::Dev => false,
+            ProfileKind::Release => true,
+            ProfileKind::Custom(name) => self.custom.get(&name).map_or(false, |c| c.1),
+        }
+    }
+
+    /// The maker for units built with `kind` in a mode which isn't testing
+    /// or documenting.
+    fn build_maker(&self, kind: ProfileKind) -> CargoResult<&ProfileMaker> {
+        match kind {
+            ProfileKind::Dev => Ok(&self.dev),
+            ProfileKind::Release => Ok(&self.release),
+            ProfileKind::Custom(name) => match self.custom.get(&name) {
+                Some(&(ref maker, _)) => Ok(maker),
+                None => bail!("profile `{}` is not defined", name),
+            },
+        }
+    }
+
     /// Retrieve the profile for a target.
     /// `is_member` is whether or not this package is a member of the
     /// workspace.
@@ -71,11 +116,11 @@ impl Profiles {
         is_member: bool,
         unit_for: UnitFor,
         mode: CompileMode,
-        release: bool,
-    ) -> Profile {
+        profile_kind: ProfileKind,
+    ) -> CargoResult<Profile> {
         let maker = match mode {
             CompileMode::Test | CompileMode::Bench => {
-                if release {
+                if self.is_release(profile_kind) {
                     &self.bench
                 } else {
                     &self.test
@@ -89,11 +134,7 @@ impl Profiles {
                 // `build_unit_profiles` normally ensures that it selects the
                 // ancestor's profile.  However `cargo clean -p` can hit this
                 // path.
-                if release {
-                    &self.release
-                } else {
-                    &self.dev
-                }
+                self.build_maker(profile_kind)?
             }
             CompileMode::Doc { .. } => &self.doc,
         };
@@ -103,7 +144,7 @@ impl Profiles {
         if !unit_for.is_panic_ok() || mode.is_any_test() {
             profile.panic = None;
         }
-        profile
+        Ok(profile)
     }
 
     /// The profile for *running* a `build.rs` script is only used for setting
@@ -121,12 +162,10 @@ impl Profiles {
     /// This returns a generic base profile. This is currently used for the
     /// `[Finished]` line.  It is not entirely accurate, since it doesn't
     /// select for the package that was actually built.
-    pub fn base_profile(&self, release: bool) -> Profile {
-        if release {
-            self.release.get_profile(None, true, UnitFor::new_normal())
-        } else {
-            self.dev.get_profile(None, true, UnitFor::new_normal())
-        }
+    pub fn base_profile(&self, profile_kind: ProfileKind) -> CargoResult<Profile> {
+        Ok(self
+            .build_maker(profile_kind)?
+            .get_profile(None, true, UnitFor::new_normal()))
     }
 
     /// Used to check for overrides for non-existing packages.
@@ -136,10 +175,75 @@ impl Profiles {
         self.test.validate_packages(shell, packages)?;
         self.bench.validate_packages(shell, packages)?;
         self.doc.validate_packages(shell, packages)?;
+        for &(ref maker, _) in self.custom.values() {
+            maker.validate_packages(shell, packages)?;
+        }
         Ok(())
     }
 }
 
+/// Builds the maker of the custom profile `name`, by following its chain of
+/// `inherits` up to `dev` or `release`. Also returns whether the chain ends
+/// at `release`.
+fn custom_profile_maker(
+    name: &str,
+    profiles: &TomlProfiles,
+    config_profiles: &ConfigProfiles,
+) -> CargoResult<(ProfileMaker, bool)> {
+    let mut chain = vec![name];
+    let mut tomls = Vec::new();
+    let mut current = name;
+    loop {
+        let toml = profiles.get(current);
+        if current == "dev" || current == "release" {
+            tomls.extend(toml.cloned());
+            break;
+        }
+        let toml = match toml {
+            Some(toml) => toml,
+            None => bail!(
+                "profile `{}` inherits from `{}`, but that profile is not defined",
+                chain[chain.len() - 2],
+                current
+            ),
+        };
+        tomls.push(toml.clone());
+        // Custom profiles are validated to have `inherits`.
+        let parent = toml.inherits.as_ref().unwrap().as_str();
+        match parent {
+            "test" | "bench" | "doc" => bail!(
+                "profile `{}` inherits from `{}`, custom profiles may only \
+                 inherit from `dev`, `release` or other custom profiles",
+                current,
+                parent
+            ),
+            _ => {}
+        }
+        if chain.contains(&parent) {
+            chain.push(parent);
+            bail!("profile inheritance loop detected: {}", chain.join(" -> "));
+        }
+        chain.push(parent);
+        current = parent;
+    }
+    let release = current == "release";
+    let (mut default, config) = if release {
+        (Profile::default_release(), config_profiles.release.clone())
+    } else {
+        (Profile::default_dev(), config_profiles.dev.clone())
+    };
+    default.name = InternedString::new(name).as_str();
+    tomls.reverse();
+    Ok((
+        ProfileMaker {
+            default,
+            toml: tomls,
+            config,
+        },
+        release,
+    ))
+}
+
 /// An object used for handling the profile override hierarchy.
 ///
 /// The precedence of profiles are (first one wins):
@@ -154,8 +258,10 @@ impl Profiles {
 struct ProfileMaker {
     /// The starting, hard-coded defaults for the profile.
     default: Profile,
-    /// The profile from the `Cargo.toml` manifest.
-    toml: Option<TomlProfile>,
+    /// The profiles from the `Cargo.toml` manifest, applied in order. This is
+    /// the chain of profiles a custom profile inherits from, ending with the
+    /// custom profile itself.
+    toml: Vec<TomlProfile>,
     /// Profile loaded from `.cargo/config` files.
     config: Option<TomlProfile>,
 }
@@ -168,7 +274,7 @@ impl ProfileMaker {
         unit_for: UnitFor,
     ) -> Profile {
         let mut profile = self.default;
-        if let Some(ref toml) = self.toml {
+        for toml in self.toml.iter() {
             merge_toml(pkg_id, is_member, unit_for, &mut profile, toml);
         }
         if let Some(ref toml) = self.config {
@@ -178,8 +284,14 @@ impl ProfileMaker {
     }
 
     fn validate_packages(&self, shell: &mut Shell, packages: &PackageSet) -> CargoResult<()> {
-        self.validate_packages_toml(shell, packages, &self.toml, true)?;
-        self.validate_packages_toml(shell, packages, &self.config, false)?;
+        // The profiles a custom profile inherits from are validated on their
+        // own.
+        if let Some(toml) = self.toml.last() {
+            self.validate_packages_toml(shell, packages, toml, true)?;
+        }
+        if let Some(ref toml) = self.config {
+            self.validate_packages_toml(shell, packages, toml, false)?;
+        }
         Ok(())
     }
 
@@ -187,13 +299,9 @@ impl ProfileMaker {
         &self,
         shell: &mut Shell,
         packages: &PackageSet,
-        toml: &Option<TomlProfile>,
+        toml: &TomlProfile,
         warn_unmatched: bool,
     ) -> CargoResult<()> {
-        let toml = match *toml {
-            Some(ref toml) => toml,
-            None => return Ok(()),
-        };
         let overrides = match toml.overrides {
             Some(ref overrides) => overrides,
             None => return Ok(()),
@@ -1,13 +1,17 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 
+use lazycell::LazyCell;
 use semver::VersionReq;
 use url::Url;
 
+use crate::core::dependency::Platform;
+use crate::core::resolver::Pins;
 use crate::core::PackageSet;
 use crate::core::{Dependency, PackageId, Source, SourceId, SourceMap, Summary};
 use crate::sources::config::SourceConfigMap;
 use crate::util::errors::{CargoResult, CargoResultExt};
-use crate::util::{profile, Config};
+use crate::util::{profile, Cfg, Config};
 
 /// Source of information about a group of packages.
 ///
@@ -70,6 +74,42 @@ pub struct PackageRegistry<'cfg> {
     patches: HashMap<Url, Vec<Summary>>,
     patches_locked: bool,
     patches_available: HashMap<Url, Vec<PackageId>>,
+
+    pins: Pins,
+
+    // The platforms which target-specific default features are evaluated
+    // for. If they aren't known the default features for all platforms are
+    // enabled, like all target-specific dependencies are resolved.
+    target_platforms: Option<Rc<TargetPlatforms<'cfg>>>,
+}
+
+/// The platforms, by name and `cfg`, the packages being resolved are
+/// compiled for.
+///
+/// Learning about them requires running the compiler, so this is only done
+/// once a package with target-specific default features shows up.
+pub struct TargetPlatforms<'cfg> {
+    probe: Box<Fn() -> CargoResult<Vec<(String, Vec<Cfg>)>> + 'cfg>,
+    platforms: LazyCell<Vec<(String, Vec<Cfg>)>>,
+}
+
+impl<'cfg> TargetPlatforms<'cfg> {
+    pub fn new<F>(probe: F) -> TargetPlatforms<'cfg>
+    where
+        F: Fn() -> CargoResult<Vec<(String, Vec<Cfg>)>> + 'cfg,
+    {
+        TargetPlatforms {
+            probe: Box::new(probe),
+            platforms: LazyCell::new(),
+        }
+    }
+
+    fn matches(&self, platform: &Platform) -> CargoResult<bool> {
+        let platforms = self.platforms.try_borrow_with(|| (self.probe)())?;
+        Ok(platforms
+            .iter()
+            .any(|&(ref name, ref cfg)| platform.matches(name, Some(cfg))))
+    }
 }
 
 type LockedMap = HashMap<SourceId, HashMap<String, Vec<(PackageId, Vec<PackageId>)>>>;
@@ -94,9 +134,23 @@ impl<'cfg> PackageRegistry<'cfg> {
             patches: HashMap::new(),
             patches_locked: false,
             patches_available: HashMap::new(),
+            pins: Pins::new(),
+            target_platforms: None,
         })
     }
 
+    /// Restricts the target-specific default features of the packages
+    /// queried from now on to the ones of the given platforms.
+    pub fn set_target_platforms(&mut self, platforms: TargetPlatforms<'cfg>) {
+        self.target_platforms = Some(Rc::new(platforms));
+    }
+
+    /// Enables the target-specific default features of `summary` which apply
+    /// to the platforms being compiled for.
+    pub fn target_default_features(&self, summary: Summary) -> CargoResult<Summary> {
+        target_default_features(&self.target_platforms, summary)
+    }
+
     pub fn get(self, package_ids: &[PackageId]) -> CargoResult<PackageSet<'cfg>> {
         trace!("getting packages; sources={}", self.sources.len());
         PackageSet::new(package_ids, self.sources, self.config)
@@ -175,6 +229,12 @@ impl<'cfg> PackageRegistry<'cfg> {
         sub_vec.push((id, deps));
     }
 
+    /// Holds the dependencies on the packages of `pins` back to the pinned
+    /// versions, wherever their requirements allow for them.
+    pub fn set_pins(&mut self, pins: Pins) {
+        self.pins = pins;
+    }
+
     /// Insert a `[patch]` section into this registry.
     ///
     /// This method will insert a `[patch]` section for the `url` specified,
@@ -282,7 +342,12 @@ impl<'cfg> PackageRegistry<'cfg> {
         assert!(!self.patches_locked);
         for summaries in self.patches.values_mut() {
             for summary in summaries {
-                *summary = lock(&self.locked, &self.patches_available, summary.clone());
+                *summary = lock(
+                    &self.locked,
+                    &self.patches_available,
+                    &self.pins,
+                    summary.clone(),
+                );
             }
         }
         self.patches_locked = true;
@@ -342,7 +407,7 @@ impl<'cfg> PackageRegistry<'cfg> {
     /// through.
     pub fn lock(&self, summary: Summary) -> Summary {
         assert!(self.patches_locked);
-        lock(&self.locked, &self.patches_available, summary)
+        lock(&self.locked, &self.patches_available, &self.pins, summary)
     }
 
     fn warn_bad_override(
@@ -400,10 +465,15 @@ https://doc.rust-lang.org/cargo/reference/specifying-dependencies.html#overridin
 
         Ok(())
     }
-}
 
-impl<'cfg> Registry for PackageRegistry<'cfg> {
-    fn query(&mut self, dep: &Dependency, f: &mut FnMut(Summary), fuzzy: bool) -> CargoResult<()> {
+    /// Queries the overrides, patches and sources for `dep`, passing the
+    /// summaries to `f` as they were declared.
+    fn query_without_target_features(
+        &mut self,
+        dep: &Dependency,
+        f: &mut FnMut(Summary),
+        fuzzy: bool,
+    ) -> CargoResult<()> {
         assert!(self.patches_locked);
         let (override_summary, n, to_warn) = {
             // Look for an override and get ready to query the real source.
@@ -484,6 +554,7 @@ impl<'cfg> Registry for PackageRegistry<'cfg> {
                         // already selected, then we skip this `summary`.
                         let locked = &self.locked;
                         let all_patches = &self.patches_available;
+                        let pins = &self.pins;
                         let callback = &mut |summary: Summary| {
                             for patch in patches.iter() {
                                 let patch = patch.package_id().version();
@@ -491,7 +562,7 @@ impl<'cfg> Registry for PackageRegistry<'cfg> {
                                     return;
                                 }
                             }
-                            f(lock(locked, all_patches, summary))
+                            f(lock(locked, all_patches, pins, summary))
                         };
                         return if fuzzy {
                             source.fuzzy_query(dep, callback)
@@ -534,6 +605,29 @@ impl<'cfg> Registry for PackageRegistry<'cfg> {
         f(self.lock(override_summary));
         Ok(())
     }
+}
+
+impl<'cfg> Registry for PackageRegistry<'cfg> {
+    fn query(&mut self, dep: &Dependency, f: &mut FnMut(Summary), fuzzy: bool) -> CargoResult<()> {
+        let platforms = self.target_platforms.clone();
+        let mut error = None;
+        self.query_without_target_features(
+            dep,
+            &mut |summary| match target_default_features(&platforms, summary) {
+                Ok(summary) => f(summary),
+                Err(e) => {
+                    if error.is_none() {
+                        error = Some(e);
+                    }
+                }
+            },
+            fuzzy,
+        )?;
+        match error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
 
     fn describe_source(&self, id: SourceId) -> String {
         match self.sources.get(id) {
@@ -550,7 +644,22 @@ impl<'cfg> Registry for PackageRegistry<'cfg> {
     }
 }
 
-fn lock(locked: &LockedMap, patches: &HashMap<Url, Vec<PackageId>>, summary: Summary) -> Summary {
+fn target_default_features(
+    platforms: &Option<Rc<TargetPlatforms>>,
+    summary: Summary,
+) -> CargoResult<Summary> {
+    summary.with_target_default_features(|platform| match *platforms {
+        Some(ref platforms) => platforms.matches(platform),
+        None => Ok(true),
+    })
+}
+
+fn lock(
+    locked: &LockedMap,
+    patches: &HashMap<Url, Vec<PackageId>>,
+    pins: &Pins,
+    summary: Summary,
+) -> Summary {
     let pair = locked
         .get(&summary.source_id())
         .and_then(|map| map.get(&*summary.name()))
@@ -644,4 +753,17 @@ fn lock(locked: &LockedMap, patches: &HashMap<Url, Vec<PackageId>>, summary: Sum
         trace!("\tnope, unlocked");
         dep
     })
+    .map_dependencies(|dep| pin(pins, dep))
+}
+
+/// Holds `dep` back to the version pinned by `cargo update --pin`, if its
+/// requirement allows for it.
+fn pin(pins: &Pins, mut dep: Dependency) -> Dependency {
+    if let Some(vers) = pins.get(&*dep.package_name()) {
+        if dep.source_id().is_registry() && dep.version_req().matches(vers) {
+            trace!("\tpinned to {}", vers);
+            dep.set_version_req(VersionReq::exact(vers));
+        }
+    }
+    dep
 }
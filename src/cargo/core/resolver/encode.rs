@@ -2,6 +2,7 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::str::FromStr;
 
+use semver::Version;
 use serde::de;
 use serde::ser;
 
@@ -20,6 +21,10 @@ pub struct EncodableResolve {
 
     #[serde(default, skip_serializing_if = "Patch::is_empty")]
     patch: Patch,
+
+    /// The versions held back by `cargo update --pin`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pin: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -29,6 +34,10 @@ struct Patch {
 
 pub type Metadata = BTreeMap<String, String>;
 
+/// The versions of registry packages pinned with `cargo update --pin`, by
+/// name.
+pub type Pins = BTreeMap<String, Version>;
+
 impl EncodableResolve {
     pub fn into_resolve(self, ws: &Workspace) -> CargoResult<Resolve> {
         let path_deps = build_path_deps(ws);
@@ -162,14 +171,24 @@ impl EncodableResolve {
             unused_patches.push(id);
         }
 
-        Ok(Resolve::new(
+        let mut pins = Pins::new();
+        for (name, vers) in self.pin {
+            let vers = vers
+                .parse::<Version>()
+                .chain_err(|| format!("invalid version `{}` pinned for `{}`", vers, name))?;
+            pins.insert(name, vers);
+        }
+
+        let mut resolve = Resolve::new(
             g,
             replacements,
             HashMap::new(),
             checksums,
             metadata,
             unused_patches,
-        ))
+        );
+        resolve.set_pins(pins);
+        Ok(resolve)
     }
 }
 
@@ -372,11 +391,18 @@ impl<'a, 'cfg> ser::Serialize for WorkspaceResolve<'a, 'cfg> {
                 })
                 .collect(),
         };
+        let pin = self
+            .resolve
+            .pins()
+            .iter()
+            .map(|(name, vers)| (name.clone(), vers.to_string()))
+            .collect();
         EncodableResolve {
             package: Some(encodable),
             root: None,
             metadata,
             patch,
+            pin,
         }
         .serialize(s)
     }
@@ -66,7 +66,7 @@ use self::types::{Candidate, ConflictReason, DepsFrame, GraphNode};
 use self::types::{RcVecIter, RegistryQueryer, RemainingDeps, ResolverProgress};
 
 pub use self::encode::{EncodableDependency, EncodablePackageId, EncodableResolve};
-pub use self::encode::{Metadata, WorkspaceResolve};
+pub use self::encode::{Metadata, Pins, WorkspaceResolve};
 pub use self::errors::{ActivateError, ActivateResult, ResolveError};
 pub use self::resolve::Resolve;
 pub use self::types::Method;
@@ -122,14 +122,21 @@ pub fn resolve(
         None => false,
     };
     let mut registry = RegistryQueryer::new(registry, replacements, try_to_use, minimal_versions);
-    let cx = activate_deps_loop(cx, &mut registry, summaries, config)?;
+    let mut printed = ResolverProgress::new(config)?;
+    let cx = activate_deps_loop(cx, &mut registry, summaries, config, &mut printed);
+    printed.print_stats(config)?;
+    let cx = cx?;
 
     let mut cksums = HashMap::new();
+    let mut index_fields = HashMap::new();
     for summary in cx.activations.values().flat_map(|v| v.iter()) {
         let cksum = summary.checksum().map(|s| s.to_string());
         cksums.insert(summary.package_id(), cksum);
+        if !summary.index_fields().is_empty() {
+            index_fields.insert(summary.package_id(), summary.index_fields().clone());
+        }
     }
-    let resolve = Resolve::new(
+    let mut resolve = Resolve::new(
         cx.graph(),
         cx.resolve_replacements(),
         cx.resolve_features
@@ -140,6 +147,7 @@ pub fn resolve(
         BTreeMap::new(),
         Vec::new(),
     );
+    resolve.set_index_fields(index_fields);
 
     check_cycles(&resolve, &cx.activations)?;
     check_duplicate_pkgs_in_lockfile(&resolve)?;
@@ -170,6 +178,7 @@ fn activate_deps_loop(
     registry: &mut RegistryQueryer,
     summaries: &[(Summary, Method)],
     config: Option<&Config>,
+    printed: &mut ResolverProgress,
 ) -> CargoResult<Context> {
     let mut backtrack_stack = Vec::new();
     let mut remaining_deps = RemainingDeps::new();
@@ -194,8 +203,6 @@ fn activate_deps_loop(
         }
     }
 
-    let mut printed = ResolverProgress::new();
-
     // Main resolution loop, this is the workhorse of the resolution algorithm.
     //
     // You'll note that a few stacks are maintained on the side, which might
@@ -217,6 +224,7 @@ fn activate_deps_loop(
         // If we spend a lot of time here (we shouldn't in most cases) then give
         // a bit of a visual indicator as to what we're doing.
         printed.shell_status(config)?;
+        printed.check_limits(&parent, &dep)?;
 
         trace!(
             "{}[{}]>{} {} candidates",
@@ -298,6 +306,8 @@ fn activate_deps_loop(
                     &conflicting_activations,
                 ) {
                     Some((candidate, has_another, frame)) => {
+                        printed.backtracked(conflicting_activations.keys());
+
                         // Reset all of our local variables used with the
                         // contents of `frame` to complete our backtrack.
                         cur = frame.cur;
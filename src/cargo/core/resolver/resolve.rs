@@ -6,11 +6,11 @@ use std::iter::FromIterator;
 
 use url::Url;
 
-use crate::core::{Dependency, PackageId, PackageIdSpec, Summary, Target};
+use crate::core::{Dependency, IndexFields, PackageId, PackageIdSpec, Summary, Target};
 use crate::util::errors::CargoResult;
 use crate::util::Graph;
 
-use super::encode::Metadata;
+use super::encode::{Metadata, Pins};
 
 /// Represents a fully resolved package dependency graph. Each node in the graph
 /// is a package and edges represent dependencies between packages.
@@ -30,6 +30,10 @@ pub struct Resolve {
     checksums: HashMap<PackageId, Option<String>>,
     metadata: Metadata,
     unused_patches: Vec<PackageId>,
+    pins: Pins,
+    /// The fields of the index lines of the packages from registries which
+    /// cargo doesn't know about, for the packages which have some.
+    index_fields: HashMap<PackageId, IndexFields>,
 }
 
 impl Resolve {
@@ -49,6 +53,8 @@ impl Resolve {
             checksums,
             metadata,
             unused_patches,
+            pins: Pins::new(),
+            index_fields: HashMap::new(),
             empty_features: HashSet::new(),
             reverse_replacements,
         }
@@ -157,6 +163,7 @@ unable to verify that `{0}` is the same as when the lockfile was generated
 
         // Be sure to just copy over any unknown metadata.
         self.metadata = previous.metadata.clone();
+        self.pins = previous.pins.clone();
         Ok(())
     }
 
@@ -220,6 +227,24 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         &self.metadata
     }
 
+    pub fn pins(&self) -> &Pins {
+        &self.pins
+    }
+
+    pub fn set_pins(&mut self, pins: Pins) {
+        self.pins = pins;
+    }
+
+    /// The fields of the index line of `pkg` which cargo doesn't know about,
+    /// if it's from a registry and has some.
+    pub fn index_fields(&self, pkg: PackageId) -> Option<&IndexFields> {
+        self.index_fields.get(&pkg)
+    }
+
+    pub fn set_index_fields(&mut self, index_fields: HashMap<PackageId, IndexFields>) {
+        self.index_fields = index_fields;
+    }
+
     pub fn extern_crate_name(
         &self,
         from: PackageId,
@@ -12,22 +12,48 @@ use crate::util::Config;
 use im_rc;
 
 pub struct ResolverProgress {
-    ticks: u16,
+    ticks: u64,
     start: Instant,
     time_to_print: Duration,
     printed: bool,
     deps_time: Duration,
+    backtracks: u64,
+    /// How many times each package was one of the reasons a dependency
+    /// couldn't be activated and the resolver had to backtrack.
+    conflicts: HashMap<PackageId, u64>,
+    /// Whether to print statistics about the resolution, `-Z resolver-debug`.
+    debug: bool,
+    /// The `resolver.step-limit` configuration.
+    step_limit: Option<u64>,
+    /// The `resolver.time-limit` configuration.
+    time_limit: Option<Duration>,
 }
 
 impl ResolverProgress {
-    pub fn new() -> ResolverProgress {
-        ResolverProgress {
+    pub fn new(config: Option<&Config>) -> CargoResult<ResolverProgress> {
+        let (debug, step_limit, time_limit) = match config {
+            Some(config) => {
+                let time_limit: Option<u64> = config.get("resolver.time-limit")?;
+                (
+                    config.cli_unstable().resolver_debug,
+                    config.get("resolver.step-limit")?,
+                    time_limit.map(Duration::from_secs),
+                )
+            }
+            None => (false, None, None),
+        };
+        Ok(ResolverProgress {
             ticks: 0,
             start: Instant::now(),
             time_to_print: Duration::from_millis(500),
             printed: false,
             deps_time: Duration::new(0, 0),
-        }
+            backtracks: 0,
+            conflicts: HashMap::new(),
+            debug,
+            step_limit,
+            time_limit,
+        })
     }
     pub fn shell_status(&mut self, config: Option<&Config>) -> CargoResult<()> {
         // If we spend a lot of time here (we shouldn't in most cases) then give
@@ -71,6 +97,92 @@ impl ResolverProgress {
     pub fn elapsed(&mut self, dur: Duration) {
         self.deps_time += dur;
     }
+    /// Records that the resolver backtracked because of `conflicting`.
+    pub fn backtracked<'a, I>(&mut self, conflicting: I)
+    where
+        I: IntoIterator<Item = &'a PackageId>,
+    {
+        self.backtracks += 1;
+        for id in conflicting {
+            *self.conflicts.entry(*id).or_insert(0) += 1;
+        }
+    }
+    /// Gives up on the resolution once the configured step or time limit has
+    /// been exceeded, explaining what the resolver was doing at the time.
+    pub fn check_limits(&self, parent: &Summary, dep: &Dependency) -> CargoResult<()> {
+        let steps_exceeded = self.step_limit.map_or(false, |limit| self.ticks > limit);
+        let time_exceeded = match self.time_limit {
+            Some(limit) if self.ticks % 1000 == 0 => {
+                self.start.elapsed() - self.deps_time > limit
+            }
+            _ => false,
+        };
+        if !steps_exceeded && !time_exceeded {
+            return Ok(());
+        }
+        let mut msg = format!(
+            "failed to resolve the dependency graph within the limit of {}\n\
+             gave up after {} steps and {} backtracks while resolving the \
+             dependency `{}` of `{}`",
+            if steps_exceeded {
+                format!("{} steps", self.step_limit.unwrap())
+            } else {
+                format!("{} seconds", self.time_limit.unwrap().as_secs())
+            },
+            self.ticks,
+            self.backtracks,
+            dep.package_name(),
+            parent.package_id(),
+        );
+        let conflicts = self.most_conflicting();
+        if !conflicts.is_empty() {
+            msg.push_str("\n\nthe packages which conflicted the most were:");
+            for (id, count) in conflicts {
+                msg.push_str(&format!("\n  {} ({} times)", id, count));
+            }
+        }
+        msg.push_str(
+            "\n\nthe limits can be changed with the `resolver.step-limit` and \
+             `resolver.time-limit` configuration",
+        );
+        bail!("{}", msg)
+    }
+    /// Prints the statistics of the resolution with `-Z resolver-debug`.
+    pub fn print_stats(&self, config: Option<&Config>) -> CargoResult<()> {
+        let config = match config {
+            Some(config) if self.debug => config,
+            _ => return Ok(()),
+        };
+        let elapsed = self.start.elapsed();
+        let mut shell = config.shell();
+        shell.status(
+            "Resolver",
+            format!(
+                "{} steps, {} backtracks in {}.{:03}s ({}.{:03}s querying dependencies)",
+                self.ticks,
+                self.backtracks,
+                elapsed.as_secs(),
+                elapsed.subsec_millis(),
+                self.deps_time.as_secs(),
+                self.deps_time.subsec_millis(),
+            ),
+        )?;
+        for (id, count) in self.most_conflicting() {
+            shell.status("Resolver", format!("{} conflicted {} times", id, count))?;
+        }
+        Ok(())
+    }
+    /// The packages which caused the most backtracking, most frequent first.
+    fn most_conflicting(&self) -> Vec<(PackageId, u64)> {
+        let mut conflicts = self
+            .conflicts
+            .iter()
+            .map(|(&id, &count)| (id, count))
+            .collect::<Vec<_>>();
+        conflicts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        conflicts.truncate(5);
+        conflicts
+    }
 }
 
 pub struct RegistryQueryer<'a> {
@@ -1,9 +1,12 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::io::prelude::*;
+use std::path::Path;
 
 use atty;
 use termcolor::Color::{Cyan, Green, Red, Yellow};
 use termcolor::{self, Color, ColorSpec, StandardStream, WriteColor};
+use url::Url;
 
 use crate::util::errors::CargoResult;
 
@@ -15,6 +18,29 @@ pub enum Verbosity {
     Quiet,
 }
 
+/// Kinds of messages whose verbosity can be set on their own, in `[term.categories]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Updating indexes and downloading or uploading packages
+    Network,
+    /// The progress of compilation
+    Compile,
+    /// Warnings, but not the warnings of the compiler
+    Warnings,
+}
+
+impl Category {
+    fn of_status(status: &str) -> Option<Category> {
+        match status {
+            "Updating" | "Downloading" | "Downloaded" | "Uploading" => Some(Category::Network),
+            "Compiling" | "Checking" | "Documenting" | "Fresh" | "Finished" => {
+                Some(Category::Compile)
+            }
+            _ => None,
+        }
+    }
+}
+
 /// An abstraction around a `Write`able object that remembers preferences for output verbosity and
 /// color.
 pub struct Shell {
@@ -23,6 +49,14 @@ pub struct Shell {
     err: ShellOut,
     /// How verbose messages should be
     verbosity: Verbosity,
+    /// Verbosity overriding `verbosity` for some categories of messages
+    category_verbosity: HashMap<Category, Verbosity>,
+    /// Translations of statuses and labels, keyed by their English text
+    messages: HashMap<String, String>,
+    /// Whether paths are printed as hyperlinks
+    hyperlinks: bool,
+    /// The color choice passed on to programs writing to stdout, if any
+    stdout_color_choice: Option<ColorChoice>,
 }
 
 impl fmt::Debug for Shell {
@@ -75,6 +109,10 @@ impl Shell {
                 tty: atty::is(atty::Stream::Stderr),
             },
             verbosity: Verbosity::Verbose,
+            category_verbosity: HashMap::new(),
+            messages: HashMap::new(),
+            hyperlinks: false,
+            stdout_color_choice: None,
         }
     }
 
@@ -83,6 +121,10 @@ impl Shell {
         Shell {
             err: ShellOut::Write(out),
             verbosity: Verbosity::Verbose,
+            category_verbosity: HashMap::new(),
+            messages: HashMap::new(),
+            hyperlinks: false,
+            stdout_color_choice: None,
         }
     }
 
@@ -101,6 +143,44 @@ impl Shell {
         }
     }
 
+    /// Like `print`, but for a message of `category`, and with the status
+    /// translated.
+    fn print_in(
+        &mut self,
+        category: Option<Category>,
+        status: &str,
+        message: Option<&fmt::Display>,
+        color: Color,
+        justified: bool,
+    ) -> CargoResult<()> {
+        let verbosity = category
+            .and_then(|c| self.category_verbosity.get(&c).cloned())
+            .unwrap_or(self.verbosity);
+        match verbosity {
+            Verbosity::Quiet => Ok(()),
+            _ => {
+                let status = self.message(status).to_string();
+                self.err.print(&status, message, color, justified)
+            }
+        }
+    }
+
+    /// Returns the translation of a message from the catalog set with
+    /// `set_messages`, or the message itself if there is none.
+    pub fn message<'a>(&'a self, message: &'a str) -> &'a str {
+        self.messages.get(message).map_or(message, |s| &s[..])
+    }
+
+    /// Returns `text`, displayed as a link to `path` if hyperlinks are enabled
+    pub fn hyperlink<D: fmt::Display>(&self, path: &Path, text: D) -> Hyperlink<D> {
+        let url = if self.hyperlinks {
+            Url::from_file_path(path).ok()
+        } else {
+            None
+        };
+        Hyperlink { url, text }
+    }
+
     /// Returns the width of the terminal in spaces, if any
     pub fn err_width(&self) -> Option<usize> {
         match self.err {
@@ -135,14 +215,16 @@ impl Shell {
         T: fmt::Display,
         U: fmt::Display,
     {
-        self.print(&status, Some(&message), Green, true)
+        self.status_with_color(status, message, Green)
     }
 
     pub fn status_header<T>(&mut self, status: T) -> CargoResult<()>
     where
         T: fmt::Display,
     {
-        self.print(&status, None, Cyan, true)
+        let status = status.to_string();
+        let category = Category::of_status(&status);
+        self.print_in(category, &status, None, Cyan, true)
     }
 
     /// Shortcut to right-align a status message.
@@ -156,7 +238,9 @@ impl Shell {
         T: fmt::Display,
         U: fmt::Display,
     {
-        self.print(&status, Some(&message), color, true)
+        let status = status.to_string();
+        let category = Category::of_status(&status);
+        self.print_in(category, &status, Some(&message), color, true)
     }
 
     /// Run the callback only if we are in verbose mode
@@ -183,14 +267,23 @@ impl Shell {
 
     /// Print a red 'error' message
     pub fn error<T: fmt::Display>(&mut self, message: T) -> CargoResult<()> {
-        self.print(&"error:", Some(&message), Red, false)
+        let label = format!("{}:", self.message("error"));
+        self.print(&label, Some(&message), Red, false)
     }
 
     /// Print an amber 'warning' message
     pub fn warn<T: fmt::Display>(&mut self, message: T) -> CargoResult<()> {
-        match self.verbosity {
+        let verbosity = self
+            .category_verbosity
+            .get(&Category::Warnings)
+            .cloned()
+            .unwrap_or(self.verbosity);
+        match verbosity {
             Verbosity::Quiet => Ok(()),
-            _ => self.print(&"warning:", Some(&message), Yellow, false),
+            _ => {
+                let label = format!("{}:", self.message("warning"));
+                self.err.print(&label, Some(&message), Yellow, false)
+            }
         }
     }
 
@@ -199,6 +292,31 @@ impl Shell {
         self.verbosity = verbosity;
     }
 
+    /// Override the verbosity of the shell for messages of `category`
+    pub fn set_category_verbosity(&mut self, category: Category, verbosity: Verbosity) {
+        self.category_verbosity.insert(category, verbosity);
+    }
+
+    /// Set the catalog of translated statuses and labels, keyed by their
+    /// English text
+    pub fn set_messages(&mut self, messages: HashMap<String, String>) {
+        self.messages = messages;
+    }
+
+    /// Enable or disable hyperlinks, or detect whether the terminal supports
+    /// them if `None`
+    pub fn set_hyperlinks(&mut self, hyperlinks: Option<bool>) {
+        self.hyperlinks = match hyperlinks {
+            Some(hyperlinks) => hyperlinks,
+            None => self.is_err_tty() && self.supports_color() && supports_hyperlinks(),
+        };
+    }
+
+    /// Whether paths are printed as hyperlinks
+    pub fn hyperlinks(&self) -> bool {
+        self.hyperlinks
+    }
+
     /// Get the verbosity of the shell
     pub fn verbosity(&self) -> Verbosity {
         self.verbosity
@@ -230,6 +348,17 @@ impl Shell {
         Ok(())
     }
 
+    /// Set the color choice passed on to programs writing to stdout, such as
+    /// test harnesses
+    pub fn set_stdout_color_choice(&mut self, color_choice: Option<ColorChoice>) {
+        self.stdout_color_choice = color_choice;
+    }
+
+    /// Get the color choice for stdout, if one was set
+    pub fn stdout_color_choice(&self) -> Option<ColorChoice> {
+        self.stdout_color_choice
+    }
+
     /// Get the current color choice
     ///
     /// If we are not using a color stream, this will always return Never, even if the color choice
@@ -269,6 +398,42 @@ impl Default for Shell {
     }
 }
 
+/// Text which is displayed as a link to a file in terminals supporting the
+/// `OSC 8` escape sequence
+pub struct Hyperlink<D> {
+    url: Option<Url>,
+    text: D,
+}
+
+impl<D: fmt::Display> fmt::Display for Hyperlink<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.url {
+            Some(ref url) => write!(f, "\x1B]8;;{}\x1B\\{}\x1B]8;;\x1B\\", url, self.text),
+            None => self.text.fmt(f),
+        }
+    }
+}
+
+/// Guesses whether the terminal supports hyperlinks from the environment
+/// variables set by terminals known to support them.
+fn supports_hyperlinks() -> bool {
+    use std::env;
+
+    if env::var_os("WT_SESSION").is_some() || env::var_os("KONSOLE_VERSION").is_some() {
+        return true;
+    }
+    if let Ok(program) = env::var("TERM_PROGRAM") {
+        if ["iTerm.app", "WezTerm", "vscode"].contains(&&program[..]) {
+            return true;
+        }
+    }
+    // VTE based terminals, like GNOME Terminal, support them since 0.50.
+    let vte_version = env::var("VTE_VERSION")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok());
+    vte_version.map_or(false, |v| v >= 5000)
+}
+
 impl ShellOut {
     /// Print out a message with a status. The status comes first and is bold + the given color.
     /// The status can be justified, in which case the max width that will right align is 12 chars.
@@ -319,6 +484,15 @@ impl ShellOut {
 }
 
 impl ColorChoice {
+    /// The argument for `--color` which selects this choice
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+            ColorChoice::CargoAuto => "auto",
+        }
+    }
+
     /// Convert our color choice to termcolor's version
     fn to_termcolor_color_choice(self) -> termcolor::ColorChoice {
         match self {
@@ -224,6 +224,8 @@ impl SourceId {
     pub fn display_registry(self) -> String {
         if self.is_default_registry() {
             "crates.io index".to_string()
+        } else if let Some(ref name) = self.inner.name {
+            format!("`{}` index", name)
         } else {
             format!("`{}` index", url_display(self.url()))
         }
@@ -5,7 +5,9 @@ use std::mem;
 use std::rc::Rc;
 
 use serde::{Serialize, Serializer};
+use serde_json;
 
+use crate::core::dependency::Platform;
 use crate::core::interning::InternedString;
 use crate::core::{Dependency, PackageId, SourceId};
 use semver::Version;
@@ -29,6 +31,8 @@ struct Inner {
     checksum: Option<String>,
     links: Option<InternedString>,
     namespaced_features: bool,
+    target_default_features: Vec<(Platform, Vec<InternedString>)>,
+    index_fields: IndexFields,
 }
 
 impl Summary {
@@ -67,6 +71,8 @@ impl Summary {
                 checksum: None,
                 links: links.map(|l| InternedString::new(l.as_ref())),
                 namespaced_features,
+                target_default_features: Vec::new(),
+                index_fields: IndexFields::new(),
             }),
         })
     }
@@ -98,6 +104,11 @@ impl Summary {
     pub fn namespaced_features(&self) -> bool {
         self.inner.namespaced_features
     }
+    /// The fields of the line of this version in the index of its registry
+    /// which cargo doesn't know about, like ones a private registry adds.
+    pub fn index_fields(&self) -> &IndexFields {
+        &self.inner.index_fields
+    }
 
     pub fn override_id(mut self, id: PackageId) -> Summary {
         Rc::make_mut(&mut self.inner).package_id = id;
@@ -109,6 +120,70 @@ impl Summary {
         self
     }
 
+    pub fn set_index_fields(mut self, index_fields: IndexFields) -> Summary {
+        Rc::make_mut(&mut self.inner).index_fields = index_fields;
+        self
+    }
+
+    pub fn set_target_default_features(
+        mut self,
+        target_default_features: Vec<(Platform, Vec<String>)>,
+    ) -> CargoResult<Summary> {
+        let mut ret = Vec::new();
+        for (platform, features) in target_default_features {
+            let mut values = Vec::new();
+            for feature in features {
+                let feature = InternedString::new(&feature);
+                let dep_name = match FeatureValue::new(feature, &self) {
+                    FeatureValue::Feature(_) => None,
+                    FeatureValue::Crate(dep) => Some((dep, true)),
+                    FeatureValue::CrateFeature(dep, _) => Some((dep, false)),
+                };
+                if let Some((dep_name, needs_optional)) = dep_name {
+                    let found = self.dependencies().iter().any(|dep| {
+                        dep.name_in_toml() == dep_name && (dep.is_optional() || !needs_optional)
+                    });
+                    if !found {
+                        bail!(
+                            "default feature `{}` for target `{}` is neither a \
+                             feature nor {}dependency of the package",
+                            feature,
+                            platform,
+                            if needs_optional { "an optional " } else { "a " }
+                        );
+                    }
+                }
+                values.push(feature);
+            }
+            ret.push((platform, values));
+        }
+        Rc::make_mut(&mut self.inner).target_default_features = ret;
+        Ok(self)
+    }
+
+    /// Adds the target-specific default features of the platforms `matches`
+    /// accepts to the `default` feature.
+    pub fn with_target_default_features<F>(mut self, mut matches: F) -> CargoResult<Summary>
+    where
+        F: FnMut(&Platform) -> CargoResult<bool>,
+    {
+        let mut values = Vec::new();
+        for &(ref platform, ref features) in self.inner.target_default_features.iter() {
+            if matches(platform)? {
+                values.extend(features.iter().map(|f| FeatureValue::new(*f, &self)));
+            }
+        }
+        if values.is_empty() {
+            return Ok(self);
+        }
+        Rc::make_mut(&mut self.inner)
+            .features
+            .entry(InternedString::new("default"))
+            .or_insert_with(Vec::new)
+            .extend(values);
+        Ok(self)
+    }
+
     pub fn map_dependencies<F>(mut self, f: F) -> Summary
     where
         F: FnMut(Dependency) -> Dependency,
@@ -409,3 +484,7 @@ impl Serialize for FeatureValue {
 }
 
 pub type FeatureMap = BTreeMap<InternedString, Vec<FeatureValue>>;
+
+/// The fields of a line of a registry index cargo doesn't know about, by
+/// name, kept as they are.
+pub type IndexFields = BTreeMap<String, serde_json::Value>;
@@ -5,6 +5,7 @@ use std::path::{Path, PathBuf};
 use std::slice;
 
 use glob::glob;
+use toml;
 use url::Url;
 
 use crate::core::profiles::Profiles;
@@ -76,6 +77,11 @@ pub struct Workspace<'cfg> {
     // A cache of loaded packages for particular paths which is disjoint from
     // `packages` up above, used in the `load` method down below.
     loaded_packages: RefCell<HashMap<PathBuf, Package>>,
+
+    // The names of the members to load for `-Z partial-workspace`, which
+    // leaves the other members out of `members`. `None` if the whole
+    // workspace is loaded.
+    partial: Option<Vec<String>>,
 }
 
 // Separate structure for tracking loaded packages (to avoid loading anything
@@ -131,6 +137,30 @@ impl<'cfg> Workspace<'cfg> {
     /// root and all member packages. It will then validate the workspace
     /// before returning it, so `Ok` is only returned for valid workspaces.
     pub fn new(manifest_path: &Path, config: &'cfg Config) -> CargoResult<Workspace<'cfg>> {
+        Workspace::open(manifest_path, config, None)
+    }
+
+    /// Creates a new workspace given the target manifest pointed to by
+    /// `manifest_path`, like `new`, but only loads the current package, the
+    /// members named `names` and the path dependencies they pull in, for
+    /// `-Z partial-workspace`.
+    ///
+    /// The whole workspace is loaded when one of `names` isn't the name of a
+    /// member, or when there are no names and the current manifest is
+    /// virtual.
+    pub fn new_partial(
+        manifest_path: &Path,
+        config: &'cfg Config,
+        names: &[String],
+    ) -> CargoResult<Workspace<'cfg>> {
+        Workspace::open(manifest_path, config, Some(names.to_vec()))
+    }
+
+    fn open(
+        manifest_path: &Path,
+        config: &'cfg Config,
+        partial: Option<Vec<String>>,
+    ) -> CargoResult<Workspace<'cfg>> {
         let target_dir = config.target_dir()?;
 
         let mut ws = Workspace {
@@ -147,6 +177,7 @@ impl<'cfg> Workspace<'cfg> {
             is_ephemeral: false,
             require_optional_deps: true,
             loaded_packages: RefCell::new(HashMap::new()),
+            partial,
         };
         ws.root_manifest = ws.find_root(manifest_path)?;
         ws.find_members()?;
@@ -183,6 +214,7 @@ impl<'cfg> Workspace<'cfg> {
             is_ephemeral: true,
             require_optional_deps,
             loaded_packages: RefCell::new(HashMap::new()),
+            partial: None,
         };
         {
             let key = ws.current_manifest.parent().unwrap();
@@ -199,6 +231,41 @@ impl<'cfg> Workspace<'cfg> {
         Ok(ws)
     }
 
+    /// Creates a workspace rooted at `root_path` from a virtual manifest
+    /// which only exists in memory, the members are loaded from the
+    /// filesystem as usual.
+    ///
+    /// This is used for building the standard library from the sources of
+    /// the `rust-src` component, which don't come with a workspace manifest.
+    pub fn new_virtual(
+        root_path: PathBuf,
+        manifest: VirtualManifest,
+        config: &'cfg Config,
+    ) -> CargoResult<Workspace<'cfg>> {
+        let current_manifest = root_path.join("Cargo.toml");
+        let mut ws = Workspace {
+            config,
+            current_manifest: current_manifest.clone(),
+            packages: Packages {
+                config,
+                packages: HashMap::new(),
+            },
+            root_manifest: Some(current_manifest),
+            target_dir: config.target_dir()?,
+            members: Vec::new(),
+            default_members: Vec::new(),
+            is_ephemeral: false,
+            require_optional_deps: true,
+            loaded_packages: RefCell::new(HashMap::new()),
+            partial: None,
+        };
+        ws.packages
+            .packages
+            .insert(root_path, MaybePackage::Virtual(manifest));
+        ws.find_members()?;
+        Ok(ws)
+    }
+
     /// Returns the current package of this workspace.
     ///
     /// Note that this can return an error if it the current manifest is
@@ -318,6 +385,12 @@ impl<'cfg> Workspace<'cfg> {
         self.is_ephemeral
     }
 
+    /// Returns true if only some of the members of the workspace are loaded,
+    /// with `-Z partial-workspace`.
+    pub fn is_partial(&self) -> bool {
+        self.partial.is_some()
+    }
+
     pub fn require_optional_deps(&self) -> bool {
         self.require_optional_deps
     }
@@ -330,6 +403,11 @@ impl<'cfg> Workspace<'cfg> {
         self
     }
 
+    pub fn set_target_dir(&mut self, target_dir: Filesystem) -> &mut Workspace<'cfg> {
+        self.target_dir = Some(target_dir);
+        self
+    }
+
     /// Finds the root of a workspace for the crate whose manifest is located
     /// at `manifest_path`.
     ///
@@ -442,6 +520,26 @@ impl<'cfg> Workspace<'cfg> {
             }
         }
 
+        if let Some(names) = self.partial.clone() {
+            match self.partial_members(&root_manifest_path, &members_paths, &names)? {
+                Some(selected) => {
+                    for path in selected.iter() {
+                        self.find_path_deps(path, &root_manifest_path, false)?;
+                    }
+                    self.default_members = selected
+                        .iter()
+                        .map(|path| paths::normalize_path(path))
+                        .filter(|path| self.members.contains(path))
+                        .collect();
+                    return Ok(());
+                }
+                None => {
+                    debug!("find_members - loading the whole workspace");
+                    self.partial = None;
+                }
+            }
+        }
+
         for path in members_paths {
             self.find_path_deps(&path.join("Cargo.toml"), &root_manifest_path, false)?;
         }
@@ -467,6 +565,47 @@ impl<'cfg> Workspace<'cfg> {
         self.find_path_deps(&root_manifest_path, &root_manifest_path, false)
     }
 
+    /// The manifests of the members a partial workspace loads: the current
+    /// package, and the root and the members named `names`, whose names are
+    /// read from their manifests without loading them. `None` if the whole
+    /// workspace has to be loaded.
+    fn partial_members(
+        &mut self,
+        root_manifest_path: &Path,
+        members_paths: &[PathBuf],
+        names: &[String],
+    ) -> CargoResult<Option<Vec<PathBuf>>> {
+        if names.is_empty() && self.is_virtual() {
+            return Ok(None);
+        }
+        let mut selected = Vec::new();
+        let mut found = Vec::new();
+        let current_manifest = self.current_manifest.clone();
+        for path in &[&current_manifest, root_manifest_path] {
+            if let MaybePackage::Package(ref p) = *self.packages.load(path)? {
+                let name = p.name().to_string();
+                if *path == current_manifest || names.contains(&name) {
+                    selected.push(path.to_path_buf());
+                    found.push(name);
+                }
+            }
+        }
+        for path in members_paths {
+            let manifest_path = path.join("Cargo.toml");
+            match peek_package_name(&manifest_path) {
+                Some(ref name) if !names.contains(name) => continue,
+                Some(name) => found.push(name),
+                // Loading it reports what's wrong with the manifest.
+                None => {}
+            }
+            selected.push(manifest_path);
+        }
+        if names.iter().any(|name| !found.contains(name)) {
+            return Ok(None);
+        }
+        Ok(Some(selected))
+    }
+
     fn find_path_deps(
         &mut self,
         manifest_path: &Path,
@@ -894,3 +1033,15 @@ impl WorkspaceRootConfig {
         Ok(res)
     }
 }
+
+/// The name of the package of the manifest at `manifest_path`, read without
+/// loading the package, `None` if the manifest can't be read or doesn't have
+/// one.
+fn peek_package_name(manifest_path: &Path) -> Option<String> {
+    let contents = paths::read(manifest_path).ok()?;
+    let manifest: toml::Value = toml::from_str(&contents).ok()?;
+    let package = manifest
+        .get("package")
+        .or_else(|| manifest.get("project"))?;
+    package.get("name")?.as_str().map(|s| s.to_string())
+}
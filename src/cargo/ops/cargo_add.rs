@@ -0,0 +1,303 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use serde_json;
+use toml;
+
+use crate::core::{Dependency, Source, SourceId};
+use crate::ops::{self, ManifestEdit};
+use crate::sources::SourceConfigMap;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, process, Config};
+
+/// The error codes of rustc for paths which couldn't be resolved, which is
+/// how a missing dependency shows up.
+const UNRESOLVED_CODES: &[&str] = &["E0432", "E0433", "E0463"];
+
+/// Names in unresolved paths which are never dependencies.
+const BUILTIN_NAMES: &[&str] = &[
+    "crate",
+    "self",
+    "super",
+    "std",
+    "core",
+    "alloc",
+    "proc_macro",
+    "test",
+];
+
+pub struct AddOptions<'a> {
+    pub config: &'a Config,
+    pub manifest_path: PathBuf,
+    /// The crates to add, like `serde` or `serde@1.0`.
+    pub crates: Vec<String>,
+    /// Whether to also add the crates the code of the package uses without
+    /// depending on them.
+    pub auto: bool,
+    /// The registry to look the crates up in, crates.io unless specified.
+    pub registry: Option<String>,
+    /// Whether to add the crates found with `auto` without asking first.
+    pub yes: bool,
+    /// The flags of cargo passed on to the `cargo check` run by `auto`, like
+    /// `--offline`.
+    pub check_flags: Vec<String>,
+}
+
+/// Adds dependencies on registry crates to a manifest, for `cargo add`.
+///
+/// Crates are added with the latest stable version in the registry, unless
+/// one is given, which the registry must have. With `auto` the package is
+/// checked, and the first segment of the paths rustc couldn't resolve, from
+/// `use` declarations and `extern crate` items, are looked up in the
+/// registry, trying `foo-bar` for `foo_bar`, unless the package declares a
+/// module of that name. Each crate found is proposed, and added once
+/// confirmed.
+pub fn add(opts: &AddOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    // The package is checked before the registry is opened, which may keep
+    // its index locked.
+    let missing = if opts.auto {
+        missing_crates(opts)?
+    } else {
+        BTreeSet::new()
+    };
+    let source_id = match opts.registry {
+        Some(ref registry) => SourceId::alt_registry(config, registry)?,
+        None => SourceId::crates_io(config)?,
+    };
+    let map = SourceConfigMap::new(config)?;
+    let mut source = map.load(source_id)?;
+    source.update()?;
+
+    for spec in opts.crates.iter() {
+        let (name, req) = match spec.find('@') {
+            Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+            None => (spec.as_str(), None),
+        };
+        let req = match req {
+            Some(req) => {
+                let dep = Dependency::parse_no_deprecated(name, Some(req), source_id)?;
+                if source.query_vec(&dep)?.is_empty() {
+                    bail!(
+                        "could not find `{}` in {} with version `{}`",
+                        name,
+                        source_id,
+                        req
+                    );
+                }
+                req.to_string()
+            }
+            None => match latest_version(&mut *source, source_id, name)? {
+                Some(version) => version.to_string(),
+                None => bail!("could not find `{}` in {}", name, source_id),
+            },
+        };
+        add_dependency(opts, name, &req)?;
+    }
+
+    if !opts.auto {
+        return Ok(());
+    }
+    let explicit = opts
+        .crates
+        .iter()
+        .map(|spec| spec.split('@').next().unwrap().replace('-', "_"))
+        .collect::<Vec<_>>();
+    let missing = missing
+        .into_iter()
+        .filter(|name| !explicit.contains(name))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        config.shell().status(
+            "Skipped",
+            "the package doesn't use any crate it doesn't depend on",
+        )?;
+        return Ok(());
+    }
+    for name in missing {
+        let mut candidates = vec![name.clone()];
+        if name.contains('_') {
+            candidates.push(name.replace('_', "-"));
+        }
+        let mut found = None;
+        for candidate in candidates {
+            if let Some(version) = latest_version(&mut *source, source_id, &candidate)? {
+                found = Some((candidate, version));
+                break;
+            }
+        }
+        let (krate, version) = match found {
+            Some(found) => found,
+            None => {
+                config.shell().warn(format!(
+                    "`{}` couldn't be resolved, but there's no crate of that name in {}",
+                    name, source_id
+                ))?;
+                continue;
+            }
+        };
+        let proposal = format!(
+            "`{} = \"{}\"`, add it to the manifest? [y/N]",
+            krate, version
+        );
+        if !opts.yes && !confirm(config, &proposal)? {
+            continue;
+        }
+        add_dependency(opts, &krate, &version.to_string())?;
+    }
+    Ok(())
+}
+
+fn add_dependency(opts: &AddOptions<'_>, name: &str, req: &str) -> CargoResult<()> {
+    let req = toml::Value::String(req.to_string());
+    let value = match opts.registry {
+        Some(ref registry) => format!(
+            "{{ version = {}, registry = {} }}",
+            req,
+            toml::Value::String(registry.clone())
+        ),
+        None => req.to_string(),
+    };
+    let key = format!("dependencies.{}", toml::Value::String(name.to_string()));
+    ops::edit_manifest(
+        opts.config,
+        &opts.manifest_path,
+        &ManifestEdit::Set { key, value },
+    )
+}
+
+/// The latest version of the crate `name` in `source`, if it has that crate,
+/// which is a prerelease only if the crate has no other versions.
+fn latest_version(
+    source: &mut dyn Source,
+    source_id: SourceId,
+    name: &str,
+) -> CargoResult<Option<Version>> {
+    let dep = Dependency::parse_no_deprecated(name, None, source_id)?;
+    let summaries = source.query_vec(&dep)?;
+    let versions = summaries.iter().map(|s| s.version());
+    let stable = versions.clone().filter(|v| !v.is_prerelease()).max();
+    Ok(stable.or_else(|| versions.max()).cloned())
+}
+
+/// The names rustc couldn't resolve checking the package, which aren't the
+/// names of its dependencies.
+fn missing_crates(opts: &AddOptions<'_>) -> CargoResult<BTreeSet<String>> {
+    let config = opts.config;
+    config.shell().status("Checking", "for unresolved crates")?;
+    let mut cmd = process(config.cargo_exe()?);
+    cmd.arg("check")
+        .args(&opts.check_flags)
+        .arg("--message-format=json")
+        .arg("--manifest-path")
+        .arg(&opts.manifest_path);
+    let output = cmd
+        .build_command()
+        .output()
+        .chain_err(|| format!("failed to run `{}`", cmd))?;
+
+    let mut names = BTreeSet::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let msg: serde_json::Value = match serde_json::from_str(line) {
+            Ok(msg) => msg,
+            Err(_) => continue,
+        };
+        if msg["reason"] != "compiler-message" {
+            continue;
+        }
+        let code = msg["message"]["code"]["code"].as_str().unwrap_or("");
+        if !UNRESOLVED_CODES.contains(&code) {
+            continue;
+        }
+        let text = msg["message"]["message"].as_str().unwrap_or("");
+        names.extend(unresolved_names(text));
+    }
+    if names.is_empty() && !output.status.success() {
+        bail!(
+            "`cargo check` failed without reporting unresolved crates:\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+
+    let manifest: toml::Value = toml::from_str(&paths::read(&opts.manifest_path)?)?;
+    let mut known = BTreeSet::new();
+    for table in &["dependencies", "dev-dependencies", "build-dependencies"] {
+        if let Some(table) = manifest.get(table).and_then(|t| t.as_table()) {
+            known.extend(table.keys().map(|k| k.replace('-', "_")));
+        }
+    }
+    // Paths to the modules of the package don't resolve either where they
+    // aren't in scope, as with the 2015 edition outside of its root.
+    local_modules(opts.manifest_path.parent().unwrap(), &mut known)?;
+    Ok(names.into_iter().filter(|n| !known.contains(n)).collect())
+}
+
+/// Adds the names of the modules declared with `mod` in the Rust files under
+/// `dir` to `modules`, skipping `target` and hidden directories.
+fn local_modules(dir: &Path, modules: &mut BTreeSet<String>) -> CargoResult<()> {
+    let entries = fs::read_dir(dir).chain_err(|| format!("failed to read `{}`", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                local_modules(&path, modules)?;
+            }
+            continue;
+        }
+        if !name.ends_with(".rs") {
+            continue;
+        }
+        for line in paths::read(&path)?.lines() {
+            let mut line = line.trim_start();
+            if line.starts_with("pub(") {
+                line = line[line.find(')').map_or(line.len(), |i| i + 1)..].trim_start();
+            } else if line.starts_with("pub ") {
+                line = line[4..].trim_start();
+            }
+            if line.starts_with("mod ") {
+                let module = line[4..]
+                    .trim_start()
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                    .collect::<String>();
+                if !module.is_empty() {
+                    modules.insert(module);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The first segments of the paths in backticks of a message of rustc, like
+/// `foo` in "unresolved import `foo::bar`", which may name crates.
+fn unresolved_names(message: &str) -> Vec<String> {
+    message
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .filter_map(|path| path.split("::").find(|s| !s.is_empty()))
+        .filter(|name| {
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && name.starts_with(|c: char| c.is_ascii_lowercase())
+                && !BUILTIN_NAMES.contains(name)
+        })
+        .map(|name| name.to_string())
+        .collect()
+}
+
+fn confirm(config: &Config, question: &str) -> CargoResult<bool> {
+    config.shell().status("Found", question)?;
+    let mut line = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .chain_err(|| "failed to read stdin")?;
+    let answer = line.trim().to_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use semver::Version;
+use tempfile::Builder as TempFileBuilder;
+
+use crate::core::compiler::{DefaultExecutor, Executor};
+use crate::core::{SourceId, Workspace};
+use crate::ops::{self, CompileOptions};
+use crate::sources::SourceConfigMap;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::Filesystem;
+
+/// Checks a version of a crate published to crates.io, for
+/// `cargo check --remote --package foo@1.2.3`.
+///
+/// `spec` is the name of the crate, followed by `@` and the exact version to
+/// check, or by nothing for the latest one. The crate is downloaded and
+/// checked as the root of a workspace of its own, with `options`, in a
+/// temporary target directory unless one is configured, which is kept around
+/// if the check fails.
+pub fn check_remote(spec: &str, options: &CompileOptions<'_>) -> CargoResult<()> {
+    let config = options.config;
+    let (name, vers) = match spec.find('@') {
+        Some(i) => {
+            let vers = &spec[i + 1..];
+            if vers.parse::<Version>().is_err() {
+                bail!(
+                    "invalid version `{}` in `{}`, `--remote` requires an exact \
+                     version, like `foo@1.2.3`",
+                    vers,
+                    spec
+                );
+            }
+            (&spec[..i], Some(format!("={}", vers)))
+        }
+        None => (spec, None),
+    };
+
+    let source_id = SourceId::crates_io(config)?;
+    let map = SourceConfigMap::new(config)?;
+    let (pkg, source) = ops::cargo_install::select_pkg(
+        map.load(source_id)?,
+        Some(name),
+        vers.as_ref().map(|s| s.as_str()),
+        config,
+        true,
+        &mut |_| Ok(Vec::new()),
+    )?;
+
+    let mut td_opt = None;
+    let target_dir = match config.target_dir()? {
+        Some(dir) => dir,
+        None => {
+            let td = TempFileBuilder::new()
+                .prefix("cargo-check-remote")
+                .tempdir()
+                .chain_err(|| "failed to create a temporary target directory")?;
+            let dir = Filesystem::new(td.path().to_owned());
+            td_opt = Some(td);
+            dir
+        }
+    };
+    let ws = Workspace::ephemeral(pkg, config, Some(target_dir), false)?;
+    let pkg = ws.current()?;
+
+    let exec: Arc<Executor> = Arc::new(DefaultExecutor);
+    ops::compile_ws(&ws, Some(source), options, &exec).chain_err(|| {
+        if let Some(td) = td_opt.take() {
+            // Preserve the temporary directory, so the user can inspect it.
+            // Its path is `ws.target_dir()`, which the error below prints.
+            let _ = td.into_path();
+        }
+
+        format_err!(
+            "failed to check `{}`, intermediate artifacts can be \
+             found at `{}`",
+            pkg,
+            ws.target_dir().display()
+        )
+    })?;
+    Ok(())
+}
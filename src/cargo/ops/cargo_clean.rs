@@ -2,7 +2,8 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-use crate::core::compiler::{BuildConfig, BuildContext, CompileMode, Context, Kind, Unit};
+use crate::core::compiler::Unit;
+use crate::core::compiler::{BuildConfig, BuildContext, CompileMode, Context, Kind, ProfileKind};
 use crate::core::profiles::UnitFor;
 use crate::core::Workspace;
 use crate::ops;
@@ -16,8 +17,8 @@ pub struct CleanOptions<'a> {
     pub spec: Vec<String>,
     /// The target arch triple to clean, or None for the host arch
     pub target: Option<String>,
-    /// Whether to clean the release directory
-    pub release: bool,
+    /// The profile whose directory is cleaned
+    pub profile_kind: ProfileKind,
     /// Whether to just clean the doc directory
     pub doc: bool,
 }
@@ -33,9 +34,13 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
         return rm_rf(&target_dir.into_path_unlocked(), config);
     }
 
-    // If the release option is set, we set target to release directory
-    if opts.release {
-        target_dir = target_dir.join("release");
+    let profiles = ws.profiles();
+    profiles.validate_profile_kind(opts.profile_kind)?;
+
+    // If the release option or a custom profile is set, we set target to
+    // its directory
+    if opts.profile_kind != ProfileKind::Dev {
+        target_dir = target_dir.join(opts.profile_kind.dest());
     }
 
     // If we have a spec, then we need to delete some packages, otherwise, just
@@ -49,7 +54,12 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
 
     let (packages, resolve) = ops::resolve_ws(ws)?;
 
-    let profiles = ws.profiles();
+    let targets = opts.target.iter().cloned().collect::<Vec<_>>();
+    let mut build_config = BuildConfig::new(config, Some(1), &targets, CompileMode::Build)?;
+    build_config.profile_kind = opts.profile_kind;
+    let mut kinds = build_config.target_kinds();
+    kinds.push(Kind::Host);
+
     let mut units = Vec::new();
 
     for spec in opts.spec.iter() {
@@ -59,7 +69,7 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
 
         // Generate all relevant `Unit` targets for this package
         for target in pkg.targets() {
-            for kind in [Kind::Host, Kind::Target].iter() {
+            for kind in kinds.iter() {
                 for mode in CompileMode::all_modes() {
                     for unit_for in UnitFor::all_values() {
                         let profile = if mode.is_run_custom_build() {
@@ -68,16 +78,16 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
                                 ws.is_member(pkg),
                                 *unit_for,
                                 CompileMode::Build,
-                                opts.release,
-                            ))
+                                opts.profile_kind,
+                            )?)
                         } else {
                             profiles.get_profile(
                                 pkg.package_id(),
                                 ws.is_member(pkg),
                                 *unit_for,
                                 *mode,
-                                opts.release,
-                            )
+                                opts.profile_kind,
+                            )?
                         };
                         units.push(Unit {
                             pkg,
@@ -92,8 +102,6 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
         }
     }
 
-    let mut build_config = BuildConfig::new(config, Some(1), &opts.target, CompileMode::Build)?;
-    build_config.release = opts.release;
     let bcx = BuildContext::new(
         ws,
         &resolve,
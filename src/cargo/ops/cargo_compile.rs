@@ -27,14 +27,17 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::core::compiler::{BuildConfig, BuildContext, Compilation, Context, DefaultExecutor, Executor};
-use crate::core::compiler::{CompileMode, Kind, Unit};
+use crate::core::compiler::{BloatReport, BugReport, CompileMode, InputsReport, Kind, Unit};
+use crate::core::compiler::standard_lib::{self, StandardLib};
+use crate::core::compiler::TargetInfo;
 use crate::core::profiles::{Profiles, UnitFor};
+use crate::core::registry::TargetPlatforms;
 use crate::core::resolver::{Method, Resolve};
-use crate::core::{Package, Source, Target};
+use crate::core::{LibKind, Package, PackageSet, Source, Target};
 use crate::core::{PackageId, PackageIdSpec, TargetKind, Workspace};
 use crate::ops;
 use crate::util::config::Config;
-use crate::util::{lev_distance, profile, CargoResult};
+use crate::util::{lev_distance, machine_message, profile, CargoResult, Cfg};
 
 /// Contains information about how a package should be compiled.
 #[derive(Debug)]
@@ -72,7 +75,7 @@ impl<'a> CompileOptions<'a> {
     pub fn new(config: &'a Config, mode: CompileMode) -> CargoResult<CompileOptions<'a>> {
         Ok(CompileOptions {
             config,
-            build_config: BuildConfig::new(config, None, &None, mode)?,
+            build_config: BuildConfig::new(config, None, &[], mode)?,
             features: Vec::new(),
             all_features: false,
             no_default_features: false,
@@ -216,10 +219,104 @@ pub fn compile_ws<'a>(
     options: &CompileOptions<'a>,
     exec: &Arc<Executor>,
 ) -> CargoResult<Compilation<'a>> {
+    let specs = options.spec.to_package_id_specs(ws)?;
+    if !options.config.cli_unstable().member_features || specs.len() == 1 {
+        let compilation = with_units(ws, source, options, &specs, |cx, units| {
+            let _p = profile::start("compiling");
+            cx.compile(units, options.export_dir.clone(), exec)
+        })?;
+        warn_native_export(options)?;
+        return Ok(compilation);
+    }
+
+    // With `-Z member-features` every selected package gets a feature
+    // resolution of its own, so a dependency shared with another package
+    // isn't built with features that only the other package asked for. A
+    // dependency whose features end up differing is compiled once per set
+    // of features, the metadata hash keeps the artifacts apart.
+    if !options.features.is_empty() {
+        bail!("cannot specify features for more than one package");
+    }
+    let mut source = source;
+    let mut ret: Option<Compilation<'a>> = None;
+    for spec in specs {
+        let compilation = with_units(ws, source.take(), options, &[spec], |cx, units| {
+            let _p = profile::start("compiling");
+            cx.compile(units, options.export_dir.clone(), exec)
+        })?;
+        match ret {
+            Some(ref mut ret) => ret.extend(compilation),
+            None => ret = Some(compilation),
+        }
+    }
+    warn_native_export(options)?;
+    Ok(ret.unwrap())
+}
+
+/// Warns when the artifacts copied out with `--out-dir`, likely to be
+/// distributed, were built for the CPU of this machine and may not run on
+/// other machines.
+fn warn_native_export(options: &CompileOptions) -> CargoResult<()> {
+    let native = options.build_config.target_cpu.as_ref().map(|s| &s[..]) == Some("native");
+    if native && options.export_dir.is_some() {
+        options.config.shell().warn(
+            "the artifacts copied to `--out-dir` were built with `--target-cpu native`, \
+             they use the features of the CPU of this machine and may not run on others",
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the files and environment variables that the last build of the
+/// units selected by `options` depended on, without compiling anything.
+///
+/// With `external` the inputs of the path dependencies of those units which
+/// are outside of the workspace root are returned instead.
+pub fn unit_inputs<'a>(
+    ws: &Workspace<'a>,
+    options: &CompileOptions<'a>,
+    external: bool,
+) -> CargoResult<InputsReport> {
+    let specs = options.spec.to_package_id_specs(ws)?;
+    with_units(ws, None, options, &specs, |cx, units| {
+        cx.inputs(units, external)
+    })
+}
+
+/// Returns an anonymized description of the environment and of the state
+/// the last build of the units selected by `options` left behind, for
+/// reporting a bug.
+pub fn bug_report<'a>(ws: &Workspace<'a>, options: &CompileOptions<'a>) -> CargoResult<BugReport> {
+    let specs = options.spec.to_package_id_specs(ws)?;
+    with_units(ws, None, options, &specs, |cx, units| cx.bug_report(units))
+}
+
+/// Returns the sizes of the binaries the last build of the units selected
+/// by `options` produced, attributed to the crates they're built from.
+pub fn bloat_report<'a>(
+    ws: &Workspace<'a>,
+    options: &CompileOptions<'a>,
+) -> CargoResult<BloatReport> {
+    let specs = options.spec.to_package_id_specs(ws)?;
+    with_units(ws, None, options, &specs, |cx, units| cx.bloat(units))
+}
+
+/// Resolves the workspace for the packages matching `specs` and generates
+/// the units selected by `options`, then hands them to `f` along with a
+/// fresh `Context`.
+fn with_units<'a, F, R>(
+    ws: &Workspace<'a>,
+    source: Option<Box<Source + 'a>>,
+    options: &CompileOptions<'a>,
+    specs: &[PackageIdSpec],
+    f: F,
+) -> CargoResult<R>
+where
+    F: for<'b> FnOnce(Context<'b, 'a>, &[Unit<'b>]) -> CargoResult<R>,
+{
     let CompileOptions {
         config,
         ref build_config,
-        ref spec,
         ref features,
         all_features,
         no_default_features,
@@ -227,16 +324,22 @@ pub fn compile_ws<'a>(
         ref target_rustdoc_args,
         ref target_rustc_args,
         ref local_rustdoc_args,
-        ref export_dir,
+        ..
     } = *options;
 
-    let default_arch_kind = if build_config.requested_target.is_some() {
-        Kind::Target
+    let default_arch_kinds = if !build_config.requested_targets.is_empty()
+        || !config.target_applies_to_host()?
+    {
+        build_config.target_kinds()
     } else {
-        Kind::Host
+        vec![Kind::Host]
     };
 
-    let specs = spec.to_package_id_specs(ws)?;
+    let build_std = config.cli_unstable().build_std.clone();
+    if build_std.is_some() {
+        standard_lib::validate_targets(build_config)?;
+    }
+
     let features = Method::split_features(features);
     let method = Method::Required {
         dev_deps: ws.require_optional_deps() || filter.need_dev_deps(build_config.mode),
@@ -244,13 +347,25 @@ pub fn compile_ws<'a>(
         all_features,
         uses_default_features: !no_default_features,
     };
-    let resolve = ops::resolve_ws_with_method(ws, source, method, &specs)?;
-    let (packages, resolve_with_overrides) = resolve;
+    let platforms = {
+        let requested_targets = build_config.requested_targets.clone();
+        let kinds = default_arch_kinds.clone();
+        TargetPlatforms::new(move || target_platforms(config, &requested_targets, &kinds))
+    };
+    let resolve = ops::resolve_ws_with_method(ws, source, method, specs, Some(platforms))?;
+    let (mut packages, resolve_with_overrides) = resolve;
+    let std_resolve = match build_std {
+        Some(ref crates) => Some(standard_lib::resolve_std(ws, crates)?),
+        None => None,
+    };
 
     let to_build_ids = specs
         .iter()
         .map(|s| s.query(resolve_with_overrides.iter()))
         .collect::<CargoResult<Vec<_>>>()?;
+    if !build_config.crate_types.is_empty() {
+        override_crate_types(&mut packages, &to_build_ids, &build_config.crate_types)?;
+    }
     let mut to_builds = packages.get_many(to_build_ids)?;
 
     // The ordering here affects some error messages coming out of cargo, so
@@ -287,17 +402,20 @@ pub fn compile_ws<'a>(
     }
 
     let profiles = ws.profiles();
+    profiles.validate_profile_kind(build_config.profile_kind)?;
     profiles.validate_packages(&mut config.shell(), &packages)?;
 
+    let p = profile::start("generating the units to build");
     let units = generate_targets(
         ws,
         profiles,
         &to_builds,
         filter,
-        default_arch_kind,
+        &default_arch_kinds,
         &resolve_with_overrides,
         build_config,
     )?;
+    drop(p);
 
     let mut extra_compiler_args = HashMap::new();
     if let Some(args) = extra_args {
@@ -319,22 +437,110 @@ pub fn compile_ws<'a>(
         }
     }
 
-    let ret = {
-        let _p = profile::start("compiling");
-        let bcx = BuildContext::new(
-            ws,
-            &resolve_with_overrides,
-            &packages,
-            config,
-            &build_config,
-            profiles,
-            extra_compiler_args,
-        )?;
-        let cx = Context::new(config, &bcx)?;
-        cx.compile(&units, export_dir.clone(), &exec)?
+    let mut bcx = BuildContext::new(
+        ws,
+        &resolve_with_overrides,
+        &packages,
+        config,
+        &build_config,
+        profiles,
+        extra_compiler_args,
+    )?;
+    if build_config.json_messages() && !build_config.build_plan {
+        for info in bcx.target_infos() {
+            machine_message::emit(&info);
+        }
+    }
+    if let (Some(crates), Some((std_packages, std_resolve))) = (&build_std, &std_resolve) {
+        let packages = std_packages
+            .get_many(std_packages.package_ids())?
+            .into_iter()
+            .map(|pkg| (pkg.package_id(), pkg))
+            .collect();
+        let roots = standard_lib::generate_std_roots(build_config, profiles, crates, &packages)?;
+        bcx.std = Some(StandardLib {
+            resolve: std_resolve,
+            packages,
+            roots,
+        });
+    }
+    let cx = Context::new(config, &bcx)?;
+    f(cx, &units)
+}
+
+/// Replaces the crate types of the libraries of the packages `ids` with
+/// `crate_types`, for `cargo build --crate-type`, which must be some of the
+/// ones each of them declares.
+fn override_crate_types(
+    packages: &mut PackageSet,
+    ids: &[PackageId],
+    crate_types: &[String],
+) -> CargoResult<()> {
+    // `lib` is what rustc builds by default, an `rlib`.
+    let normalize = |crate_type: &str| -> String {
+        match crate_type {
+            "lib" => "rlib".to_string(),
+            other => other.to_string(),
+        }
     };
+    let pkgs = packages
+        .get_many(ids.iter().cloned())?
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut found_lib = false;
+    for mut pkg in pkgs {
+        let declared = match pkg.targets().iter().find(|t| t.is_lib()) {
+            Some(lib) => lib.rustc_crate_types(),
+            None => continue,
+        };
+        found_lib = true;
+        let declared = declared.into_iter().map(normalize).collect::<Vec<_>>();
+        for crate_type in crate_types {
+            if !declared.contains(&normalize(crate_type)) {
+                bail!(
+                    "the library of `{}` doesn't have the crate type `{}`, \
+                     it has: {}",
+                    pkg.name(),
+                    crate_type,
+                    declared.join(", ")
+                );
+            }
+        }
+        let kinds = crate_types.iter().map(LibKind::from).collect();
+        for target in pkg.manifest_mut().targets_mut() {
+            if target.is_lib() {
+                target.set_kind(TargetKind::Lib(kinds));
+                break;
+            }
+        }
+        packages.replace(pkg);
+    }
+    if !found_lib {
+        bail!("`--crate-type` was passed, but none of the packages to build have a library");
+    }
+    Ok(())
+}
 
-    Ok(ret)
+/// The names and `cfg` of the platforms the units of the given kinds are
+/// compiled for, the target-specific default features are enabled for them.
+fn target_platforms(
+    config: &Config,
+    requested_targets: &[String],
+    kinds: &[Kind],
+) -> CargoResult<Vec<(String, Vec<Cfg>)>> {
+    let rustc = config.rustc(None)?;
+    kinds
+        .iter()
+        .map(|&kind| {
+            let info = TargetInfo::new(config, requested_targets, &rustc, kind)?;
+            let name = match kind {
+                Kind::Target(Some(triple)) => triple.to_string(),
+                Kind::Target(None) | Kind::Host => rustc.host.clone(),
+            };
+            Ok((name, info.cfg().unwrap_or(&[]).to_vec()))
+        })
+        .collect()
 }
 
 impl FilterRule {
@@ -491,12 +697,16 @@ fn generate_targets<'a>(
     profiles: &Profiles,
     packages: &[&'a Package],
     filter: &CompileFilter,
-    default_arch_kind: Kind,
+    default_arch_kinds: &[Kind],
     resolve: &Resolve,
     build_config: &BuildConfig,
 ) -> CargoResult<Vec<Unit<'a>>> {
     // Helper for creating a Unit struct.
-    let new_unit = |pkg: &'a Package, target: &'a Target, target_mode: CompileMode| {
+    let new_unit = |pkg: &'a Package,
+                    target: &'a Target,
+                    target_mode: CompileMode,
+                    default_arch_kind: Kind|
+     -> CargoResult<Unit<'a>> {
         let unit_for = if build_config.mode.is_any_test() {
             // NOTE: The UnitFor here is subtle.  If you have a profile
             // with `panic` set, the `panic` flag is cleared for
@@ -562,15 +772,15 @@ fn generate_targets<'a>(
             ws.is_member(pkg),
             unit_for,
             target_mode,
-            build_config.release,
-        );
-        Unit {
+            build_config.profile_kind,
+        )?;
+        Ok(Unit {
             pkg,
             target,
             profile,
             kind,
             mode: target_mode,
-        }
+        })
     };
 
     // Create a list of proposed targets.
@@ -720,8 +930,9 @@ fn generate_targets<'a>(
             None => Vec::new(),
         };
         if target.is_lib() || unavailable_features.is_empty() {
-            let unit = new_unit(pkg, target, mode);
-            units.insert(unit);
+            for kind in default_arch_kinds {
+                units.insert(new_unit(pkg, target, mode, *kind)?);
+            }
         } else if requires_features {
             let required_features = target.required_features().unwrap();
             let quoted_required_features: Vec<String> = required_features
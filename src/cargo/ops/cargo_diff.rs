@@ -0,0 +1,399 @@
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+
+use crate::core::{Dependency, Package, PackageId, PackageSet, Source, SourceId, SourceMap};
+use crate::sources::{PathSource, SourceConfigMap};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, Config};
+
+/// The number of edits between two files past which they're shown as
+/// entirely rewritten, which keeps huge changes from exhausting memory.
+const MAX_EDITS: usize = 4000;
+
+pub struct DiffOptions<'a> {
+    pub config: &'a Config,
+    /// The name of the crate to compare the versions of.
+    pub krate: String,
+    pub from: String,
+    pub to: String,
+    /// The registry of the crate, crates.io unless specified.
+    pub registry: Option<String>,
+    /// The number of unchanged lines shown around each change.
+    pub context: usize,
+}
+
+/// Downloads two versions of a crate from its registry, verifying their
+/// checksums like the downloads of dependencies are, and returns a unified
+/// diff of their sources, for `cargo diff`.
+pub fn diff(opts: &DiffOptions<'_>) -> CargoResult<String> {
+    let config = opts.config;
+    for vers in &[&opts.from, &opts.to] {
+        if vers.parse::<Version>().is_err() {
+            bail!(
+                "invalid version `{}`, `cargo diff` compares exact versions, like `1.2.3`",
+                vers
+            );
+        }
+    }
+    let pkgs = download_versions(
+        config,
+        opts.registry.as_ref().map(|s| s.as_str()),
+        &opts.krate,
+        &[&opts.from, &opts.to],
+    )?;
+    let (from, to) = (&pkgs[0], &pkgs[1]);
+    config.shell().status(
+        "Comparing",
+        format!("{} v{} to v{}", opts.krate, from.version(), to.version()),
+    )?;
+
+    let from_files = crate_files(from)?;
+    let to_files = crate_files(to)?;
+    let names = from_files
+        .keys()
+        .chain(to_files.keys())
+        .collect::<BTreeSet<_>>();
+    let mut out = String::new();
+    for name in names {
+        let old = match from_files.get(name) {
+            Some(path) => Some(paths::read_bytes(path)?),
+            None => None,
+        };
+        let new = match to_files.get(name) {
+            Some(path) => Some(paths::read_bytes(path)?),
+            None => None,
+        };
+        if old == new {
+            continue;
+        }
+        let old_name = match old {
+            Some(_) => format!("a/{}/{}", dir_name(from.package_id()), name),
+            None => "/dev/null".to_string(),
+        };
+        let new_name = match new {
+            Some(_) => format!("b/{}/{}", dir_name(to.package_id()), name),
+            None => "/dev/null".to_string(),
+        };
+        let old = old.unwrap_or_default();
+        let new = new.unwrap_or_default();
+        match (String::from_utf8(old), String::from_utf8(new)) {
+            (Ok(old), Ok(new)) => {
+                out.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+                out.push_str(&unified_diff(&old, &new, opts.context));
+            }
+            _ => out.push_str(&format!(
+                "Binary files {} and {} differ\n",
+                old_name, new_name
+            )),
+        }
+    }
+    Ok(out)
+}
+
+/// Downloads the exact versions `versions` of the crate `name` from
+/// `registry`, or crates.io, and returns them in the same order. Yanked
+/// versions are found too, as they're named exactly.
+pub(super) fn download_versions(
+    config: &Config,
+    registry: Option<&str>,
+    name: &str,
+    versions: &[&str],
+) -> CargoResult<Vec<Package>> {
+    let source_id = match registry {
+        Some(registry) => SourceId::alt_registry(config, registry)?,
+        None => SourceId::crates_io(config)?,
+    };
+    let map = SourceConfigMap::new(config)?;
+    let mut source = map.load(source_id)?;
+    source.update()?;
+
+    // Registries only return yanked versions for locked dependencies.
+    let locked = source_id.with_precise(Some("locked".to_string()));
+    let mut ids = Vec::new();
+    for vers in versions {
+        let req = format!("={}", vers);
+        let dep = Dependency::parse_no_deprecated(name, Some(&req), locked)?;
+        match source.query_vec(&dep)?.first() {
+            Some(pkg) => ids.push(pkg.package_id()),
+            None => bail!(
+                "could not find `{}` in {} with version `{}`",
+                name,
+                source_id,
+                vers
+            ),
+        }
+    }
+
+    let mut sources = SourceMap::new();
+    sources.insert(source);
+    let set = PackageSet::new(&ids, sources, config)?;
+    // Downloads finish in any order.
+    let pkgs = set.get_many(ids.iter().cloned())?;
+    Ok(ids
+        .iter()
+        .map(|&id| (*pkgs.iter().find(|pkg| pkg.package_id() == id).unwrap()).clone())
+        .collect())
+}
+
+fn dir_name(id: PackageId) -> String {
+    format!("{}-{}", id.name(), id.version())
+}
+
+/// The files of the package `pkg` which `cargo package` would include, by
+/// their path relative to its root.
+pub(super) fn package_files(pkg: &Package, config: &Config) -> CargoResult<BTreeMap<String, PathBuf>> {
+    let src = PathSource::new(pkg.root(), pkg.package_id().source_id(), config);
+    let mut files = BTreeMap::new();
+    for file in src.list_files(pkg)? {
+        let relative = file.strip_prefix(pkg.root())?;
+        // Left behind by the unpacking of registry packages.
+        if relative == Path::new(".cargo-ok") {
+            continue;
+        }
+        let name = relative.to_string_lossy().replace('\\', "/");
+        files.insert(name, file);
+    }
+    Ok(files)
+}
+
+/// All the files of the downloaded crate `pkg`, as they were in its tarball,
+/// by their path relative to its root.
+///
+/// Unlike `package_files`, this doesn't apply the `include` and `exclude`
+/// rules of the crate's manifest, nor skip hidden files, `target` or the
+/// directories of other packages, as the crate could ship anything there.
+pub(super) fn crate_files(pkg: &Package) -> CargoResult<BTreeMap<String, PathBuf>> {
+    fn walk(root: &Path, dir: &Path, files: &mut BTreeMap<String, PathBuf>) -> CargoResult<()> {
+        let entries =
+            fs::read_dir(dir).chain_err(|| format!("failed to read `{}`", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let relative = path.strip_prefix(root)?;
+            // Left behind by the unpacking of registry packages.
+            if relative == Path::new(".cargo-ok") {
+                continue;
+            }
+            if fs::symlink_metadata(&path)?.is_dir() {
+                walk(root, &path, files)?;
+            } else {
+                let name = relative.to_string_lossy().replace('\\', "/");
+                files.insert(name, path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = BTreeMap::new();
+    walk(pkg.root(), pkg.root(), &mut files)?;
+    Ok(files)
+}
+
+#[derive(Debug, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The hunks of the unified diff between `old` and `new`, with `context`
+/// unchanged lines around each change.
+fn unified_diff(old: &str, new: &str, context: usize) -> String {
+    let lines = diff_lines(&split_lines(old), &split_lines(new));
+
+    // The number of old and new lines before each of `lines`.
+    let mut numbers = Vec::with_capacity(lines.len() + 1);
+    let (mut old_no, mut new_no) = (0, 0);
+    for line in &lines {
+        numbers.push((old_no, new_no));
+        match *line {
+            Line::Same(_) => {
+                old_no += 1;
+                new_no += 1;
+            }
+            Line::Removed(_) => old_no += 1,
+            Line::Added(_) => new_no += 1,
+        }
+    }
+    numbers.push((old_no, new_no));
+
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let Line::Same(_) = *line {
+            continue;
+        }
+        let start = i.saturating_sub(context);
+        let end = cmp::min(i + context + 1, lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = String::new();
+    for (start, end) in hunks {
+        let old_len = numbers[end].0 - numbers[start].0;
+        let new_len = numbers[end].1 - numbers[start].1;
+        // An empty range is numbered after the line preceding it.
+        let old_start = numbers[start].0 + if old_len == 0 { 0 } else { 1 };
+        let new_start = numbers[start].1 + if new_len == 0 { 0 } else { 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_len, new_start, new_len
+        ));
+        for line in &lines[start..end] {
+            let (prefix, text) = match *line {
+                Line::Same(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            out.push(prefix);
+            out.push_str(text);
+            if !text.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// The lines of `text`, along with the newline ending each of them.
+fn split_lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('\n') {
+        lines.push(&rest[..=i]);
+        rest = &rest[i + 1..];
+    }
+    if !rest.is_empty() {
+        lines.push(rest);
+    }
+    lines
+}
+
+/// The shortest sequence of removals and additions turning the lines `a`
+/// into the lines `b`, with Myers' algorithm.
+fn diff_lines<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = a.iter().zip(b).take_while(|&(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|&(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut lines = a[..prefix]
+        .iter()
+        .map(|&line| Line::Same(line))
+        .collect::<Vec<_>>();
+    lines.extend(diff_middle(a_mid, b_mid));
+    lines.extend(a[a.len() - suffix..].iter().map(|&line| Line::Same(line)));
+    lines
+}
+
+fn diff_middle<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<Line<'a>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    // The furthest `x` reached on each diagonal `k = x - y`, and the ones
+    // reached before each number of edits `d`, around the diagonals `-d..=d`.
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace = Vec::new();
+    let mut found = false;
+    for d in 0..=cmp::min(max, MAX_EDITS) as isize {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                found = true;
+                break;
+            }
+            k += 2;
+        }
+        if found {
+            break;
+        }
+    }
+    if !found {
+        let mut lines = a.iter().map(|&line| Line::Removed(line)).collect::<Vec<_>>();
+        lines.extend(b.iter().map(|&line| Line::Added(line)));
+        return lines;
+    }
+
+    let mut lines = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            lines.push(Line::Same(a[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                lines.push(Line::Added(b[y as usize - 1]));
+            } else {
+                lines.push(Line::Removed(a[x as usize - 1]));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    lines.reverse();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    #[test]
+    fn diffs_lines() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\nk";
+        assert_eq!(
+            unified_diff(old, new, 2),
+            "\
+@@ -1,5 +1,5 @@
+ a
+ b
+-c
++C
+ d
+ e
+@@ -9,2 +9,3 @@
+ i
+ j
++k
+\\ No newline at end of file
+"
+        );
+        assert_eq!(unified_diff("", "x\n", 3), "@@ -0,0 +1,1 @@\n+x\n");
+        assert_eq!(unified_diff("x\ny\n", "y\n", 3), "@@ -1,2 +1,1 @@\n-x\n y\n");
+    }
+}
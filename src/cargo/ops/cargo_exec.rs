@@ -0,0 +1,139 @@
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::Path;
+
+use serde_json;
+use tempfile::Builder as TempFileBuilder;
+
+use crate::core::compiler::ProfileKind;
+use crate::core::{Package, Workspace};
+use crate::ops::{self, OutputMetadataOptions, Packages};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, process, ProcessBuilder, ProcessError};
+
+pub struct ExecOptions {
+    /// The tool to run, and the arguments to pass it.
+    pub tool: String,
+    pub args: Vec<String>,
+    /// The members to run the tool for, one after the other, or `None` to run
+    /// it once at the root of the workspace.
+    pub each: Option<Packages>,
+    /// The profile of the artifacts the tool may load libraries from.
+    pub profile_kind: ProfileKind,
+    /// Whether to describe the workspace members only in the metadata handed
+    /// to the tool, without resolving their dependencies.
+    pub no_deps: bool,
+}
+
+/// Runs an external tool with the environment cargo sets up for the
+/// workspace, for `cargo exec`.
+///
+/// The tool finds the workspace in `CARGO_WORKSPACE_ROOT`, `CARGO_TARGET_DIR`
+/// and `CARGO_MANIFEST_PATH`, and its packages in the file `CARGO_METADATA`
+/// names, the output of `cargo metadata --format-version 1`. Run for each
+/// member it also gets the `CARGO_MANIFEST_DIR` and `CARGO_PKG_*` variables
+/// builds get. The libraries built for the workspace, and the ones of the
+/// toolchain, are in its dynamic library search path.
+///
+/// Returns the error of the first run of the tool which failed, if any.
+pub fn exec(ws: &Workspace<'_>, opts: &ExecOptions) -> CargoResult<Option<ProcessError>> {
+    let config = ws.config();
+    let members = match opts.each {
+        Some(ref spec) => spec.get_packages(ws)?,
+        None => Vec::new(),
+    };
+
+    let metadata = ops::output_metadata(
+        ws,
+        &OutputMetadataOptions {
+            features: Vec::new(),
+            no_default_features: false,
+            all_features: false,
+            no_deps: opts.no_deps,
+            version: 1,
+            target_info: false,
+            targets: Vec::new(),
+        },
+    )?;
+    let mut metadata_file = TempFileBuilder::new()
+        .prefix("cargo-exec-metadata")
+        .suffix(".json")
+        .tempfile()
+        .chain_err(|| "failed to create a temporary file")?;
+    metadata_file.write_all(serde_json::to_string(&metadata)?.as_bytes())?;
+
+    let mut cmd = process(&opts.tool);
+    cmd.args(&opts.args)
+        .env(crate::CARGO_ENV, config.cargo_exe()?)
+        .env("CARGO_WORKSPACE_ROOT", ws.root())
+        .env("CARGO_TARGET_DIR", ws.target_dir().into_path_unlocked())
+        .env("CARGO_METADATA", metadata_file.path())
+        .env(
+            config.host_platform()?.dylib_path_envvar(),
+            dylib_path(ws, opts.profile_kind)?,
+        );
+
+    if opts.each.is_none() {
+        cmd.env("CARGO_MANIFEST_PATH", ws.root().join("Cargo.toml"))
+            .cwd(ws.root());
+        config.shell().status("Running", cmd.to_string())?;
+        return run(&cmd);
+    }
+    for pkg in members {
+        let mut cmd = cmd.clone();
+        package_env(&mut cmd, pkg);
+        config
+            .shell()
+            .status("Running", format!("{} for {}", cmd, pkg.package_id()))?;
+        if let Some(err) = run(&cmd)? {
+            return Ok(Some(err));
+        }
+    }
+    Ok(None)
+}
+
+fn run(cmd: &ProcessBuilder) -> CargoResult<Option<ProcessError>> {
+    match cmd.exec() {
+        Ok(()) => Ok(None),
+        Err(e) => Ok(Some(e.downcast::<ProcessError>()?)),
+    }
+}
+
+fn package_env(cmd: &mut ProcessBuilder, pkg: &Package) {
+    let metadata = pkg.manifest().metadata();
+    cmd.env("CARGO_MANIFEST_PATH", pkg.manifest_path())
+        .env("CARGO_MANIFEST_DIR", pkg.root())
+        .env("CARGO_PKG_NAME", &*pkg.name())
+        .env("CARGO_PKG_VERSION", &pkg.version().to_string())
+        .env(
+            "CARGO_PKG_DESCRIPTION",
+            metadata.description.as_ref().unwrap_or(&String::new()),
+        )
+        .env(
+            "CARGO_PKG_REPOSITORY",
+            metadata.repository.as_ref().unwrap_or(&String::new()),
+        )
+        .env("CARGO_PKG_AUTHORS", &pkg.authors().join(":"))
+        .cwd(pkg.root());
+}
+
+/// The dynamic library search path of the tool: the artifacts of the profile
+/// `profile_kind`, the libraries of the toolchain, then the path cargo was
+/// run with.
+fn dylib_path(ws: &Workspace<'_>, profile_kind: ProfileKind) -> CargoResult<OsString> {
+    let dest = ws
+        .target_dir()
+        .into_path_unlocked()
+        .join(profile_kind.dest());
+    let mut search_path = vec![dest.join("deps"), dest];
+
+    let rustc = ws.config().rustc(Some(ws))?;
+    let mut sysroot_cmd = rustc.process();
+    sysroot_cmd.arg("--print=sysroot");
+    let (output, _) = rustc.cached_output(&sysroot_cmd)?;
+    let host_platform = ws.config().host_platform()?;
+    search_path.push(host_platform.sysroot_libdir(Path::new(output.trim())));
+
+    search_path.extend(host_platform.dylib_path());
+    paths::join_paths(&search_path, host_platform.dylib_path_envvar())
+}
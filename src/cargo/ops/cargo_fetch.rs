@@ -1,4 +1,4 @@
-use crate::core::compiler::{BuildConfig, CompileMode, Kind, TargetInfo};
+use crate::core::compiler::{BuildConfig, CompileMode, TargetInfo};
 use crate::core::{PackageSet, Resolve, Workspace};
 use crate::ops;
 use std::collections::HashSet;
@@ -20,10 +20,21 @@ pub fn fetch<'a>(
 
     let jobs = Some(1);
     let config = ws.config();
-    let build_config = BuildConfig::new(config, jobs, &options.target, CompileMode::Build)?;
+    let targets = options.target.iter().cloned().collect::<Vec<_>>();
+    let build_config = BuildConfig::new(config, jobs, &targets, CompileMode::Build)?;
     let rustc = config.rustc(Some(ws))?;
-    let target_info =
-        TargetInfo::new(config, &build_config.requested_target, &rustc, Kind::Target)?;
+    // The targets `--target` names, more than one for a group of targets.
+    let mut targets = Vec::new();
+    if options.target.is_some() {
+        for (triple, kind) in build_config
+            .requested_targets
+            .iter()
+            .zip(build_config.target_kinds())
+        {
+            let info = TargetInfo::new(config, &build_config.requested_targets, &rustc, kind)?;
+            targets.push((triple, info));
+        }
+    }
     {
         let mut fetched_packages = HashSet::new();
         let mut deps_to_fetch = ws.members().map(|p| p.package_id()).collect::<Vec<_>>();
@@ -41,18 +52,19 @@ pub fn fetch<'a>(
                     deps.iter().any(|d| {
                         // If no target was specified then all dependencies can
                         // be fetched.
-                        let target = match options.target {
-                            Some(ref t) => t,
-                            None => return true,
-                        };
+                        if options.target.is_none() {
+                            return true;
+                        }
                         // If this dependency is only available for certain
-                        // platforms, make sure we're only fetching it for that
-                        // platform.
+                        // platforms, make sure we're only fetching it for one
+                        // of those requested.
                         let platform = match d.platform() {
                             Some(p) => p,
                             None => return true,
                         };
-                        platform.matches(target, target_info.cfg())
+                        targets
+                            .iter()
+                            .any(|&(triple, ref info)| platform.matches(triple, info.cfg()))
                     })
                 })
                 .map(|(id, _deps)| id);
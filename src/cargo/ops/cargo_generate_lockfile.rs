@@ -1,9 +1,10 @@
 use std::collections::{BTreeMap, HashSet};
 
+use semver::Version;
 use termcolor::Color::{self, Cyan, Green, Red};
 
 use crate::core::registry::PackageRegistry;
-use crate::core::resolver::Method;
+use crate::core::resolver::{Method, Pins};
 use crate::core::PackageId;
 use crate::core::{Resolve, SourceId, Workspace};
 use crate::ops;
@@ -15,11 +16,22 @@ pub struct UpdateOptions<'a> {
     pub to_update: Vec<String>,
     pub precise: Option<&'a str>,
     pub aggressive: bool,
+    /// Versions to pin, like `foo@1.4.2`.
+    pub pin: Vec<String>,
+    /// Names of the packages to unpin.
+    pub unpin: Vec<String>,
 }
 
 pub fn generate_lockfile(ws: &Workspace) -> CargoResult<()> {
+    // The versions pinned with `cargo update --pin` are kept, unless the lock
+    // file can't be read, which may be why it's generated again.
+    let pins = match ops::load_pkg_lockfile(ws) {
+        Ok(Some(previous)) => previous.pins().clone(),
+        Ok(None) | Err(_) => Pins::new(),
+    };
     let mut registry = PackageRegistry::new(ws.config())?;
-    let resolve = ops::resolve_with_previous(
+    registry.set_pins(pins.clone());
+    let mut resolve = ops::resolve_with_previous(
         &mut registry,
         ws,
         Method::Everything,
@@ -29,6 +41,7 @@ pub fn generate_lockfile(ws: &Workspace) -> CargoResult<()> {
         true,
         true,
     )?;
+    resolve.set_pins(pins);
     ops::write_pkg_lockfile(ws, &resolve)?;
     Ok(())
 }
@@ -42,18 +55,72 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions) -> CargoResult<()>
         bail!("you can't generate a lockfile for an empty workspace.")
     }
 
-    if opts.config.cli_unstable().offline {
+    if opts.config.offline() {
         bail!("you can't update in the offline mode");
     }
 
-    let previous_resolve = match ops::load_pkg_lockfile(ws)? {
+    let pinning = !opts.pin.is_empty() || !opts.unpin.is_empty();
+    if pinning && (!opts.to_update.is_empty() || opts.precise.is_some() || opts.aggressive) {
+        bail!(
+            "`--pin` and `--unpin` can't be combined with `--package`, \
+             `--precise` or `--aggressive`"
+        )
+    }
+    let mut new_pins = Vec::new();
+    for spec in opts.pin.iter() {
+        let vers = match spec.find('@') {
+            Some(i) => spec[i + 1..].parse::<Version>().ok().map(|v| (&spec[..i], v)),
+            None => None,
+        };
+        match vers {
+            Some(pin) => new_pins.push(pin),
+            None => bail!(
+                "invalid pin `{}`, `--pin` takes a package and an exact version, \
+                 like `foo@1.4.2`",
+                spec
+            ),
+        }
+    }
+
+    let mut previous_resolve = match ops::load_pkg_lockfile(ws)? {
         Some(resolve) => resolve,
-        None => return generate_lockfile(ws),
+        None if !pinning => return generate_lockfile(ws),
+        None => {
+            generate_lockfile(ws)?;
+            ops::load_pkg_lockfile(ws)?.unwrap()
+        }
     };
     let mut registry = PackageRegistry::new(opts.config)?;
     let mut to_avoid = HashSet::new();
 
-    if opts.to_update.is_empty() {
+    if pinning {
+        let mut pins = previous_resolve.pins().clone();
+        for name in opts.unpin.iter() {
+            if pins.remove(name).is_none() {
+                bail!("`{}` isn't pinned in the lock file", name);
+            }
+            opts.config.shell().status("Unpinning", name)?;
+        }
+        for &(name, ref vers) in new_pins.iter() {
+            let ids = previous_resolve
+                .iter()
+                .filter(|id| id.name().as_str() == name && id.source_id().is_registry())
+                .collect::<Vec<_>>();
+            if ids.is_empty() {
+                bail!(
+                    "`{}` isn't a dependency from a registry in the lock file, \
+                     only those can be pinned",
+                    name
+                );
+            }
+            to_avoid.extend(ids);
+            pins.insert(name.to_string(), vers.clone());
+            opts.config
+                .shell()
+                .status("Pinning", format!("{} to v{}", name, vers))?;
+        }
+        previous_resolve.set_pins(pins);
+    } else if opts.to_update.is_empty() {
         to_avoid.extend(previous_resolve.iter());
     } else {
         let mut sources = Vec::new();
@@ -93,6 +160,20 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions) -> CargoResult<()>
         true,
     )?;
 
+    for &(name, ref vers) in new_pins.iter() {
+        if !resolve
+            .iter()
+            .any(|id| id.name().as_str() == name && id.version() == vers)
+        {
+            bail!(
+                "failed to pin `{}` to v{}, as none of the dependencies on it allow for \
+                 that version",
+                name,
+                vers
+            );
+        }
+    }
+
     // Summarize what is changing for the user.
     let print_change = |status: &str, msg: String, color: Color| {
         opts.config.shell().status_with_color(status, msg, color)
@@ -0,0 +1,261 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt::Write;
+use std::str::FromStr;
+
+use serde_json;
+
+use crate::core::dependency::Kind;
+use crate::core::resolver::Resolve;
+use crate::core::{PackageId, Workspace};
+use crate::ops::{self, Packages};
+use crate::util::{CargoResult, CargoError};
+
+const VERSION: u32 = 1;
+
+pub struct GraphOptions {
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    /// The packages the graph starts from.
+    pub spec: Packages,
+    pub format: GraphFormat,
+    /// Only dependency edges of these kinds are followed.
+    pub kinds: Vec<Kind>,
+    /// How many levels of dependencies to include, unlimited if `None`.
+    pub depth: Option<usize>,
+    /// Whether to annotate nodes with their activated features and edges with
+    /// the features they enable.
+    pub show_features: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = CargoError;
+
+    fn from_str(s: &str) -> CargoResult<GraphFormat> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => bail!(
+                "invalid graph format `{}`, expected one of `dot`, `json` or `mermaid`",
+                s
+            ),
+        }
+    }
+}
+
+struct Graph {
+    roots: Vec<PackageId>,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+#[derive(Serialize)]
+struct Node {
+    id: PackageId,
+    name: String,
+    version: String,
+    features: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Edge {
+    #[serde(skip)]
+    from_index: usize,
+    #[serde(skip)]
+    to_index: usize,
+    from: PackageId,
+    to: PackageId,
+    kinds: Vec<&'static str>,
+    features: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ExportGraph<'a> {
+    roots: &'a [PackageId],
+    nodes: &'a [Node],
+    edges: &'a [Edge],
+    version: u32,
+}
+
+/// Resolves the workspace and renders the dependency graph reachable from the
+/// selected packages in the requested format.
+pub fn graph(ws: &Workspace, opts: &GraphOptions) -> CargoResult<String> {
+    let specs = opts.spec.to_package_id_specs(ws)?;
+    let (_packages, resolve) = ops::resolve_ws_precisely(
+        ws,
+        None,
+        &opts.features,
+        opts.all_features,
+        opts.no_default_features,
+        &specs,
+    )?;
+    let mut roots = specs
+        .iter()
+        .map(|spec| spec.query(resolve.iter()))
+        .collect::<CargoResult<Vec<_>>>()?;
+    roots.sort();
+    roots.dedup();
+
+    let graph = build_graph(&resolve, roots, opts);
+    let out = match opts.format {
+        GraphFormat::Dot => render_dot(&graph, opts.show_features),
+        GraphFormat::Mermaid => render_mermaid(&graph, opts.show_features),
+        GraphFormat::Json => serde_json::to_string(&ExportGraph {
+            roots: &graph.roots,
+            nodes: &graph.nodes,
+            edges: &graph.edges,
+            version: VERSION,
+        })?,
+    };
+    Ok(out)
+}
+
+fn build_graph(resolve: &Resolve, roots: Vec<PackageId>, opts: &GraphOptions) -> Graph {
+    let mut indices = HashMap::new();
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    let mut queue = VecDeque::new();
+
+    for &root in roots.iter() {
+        add_node(resolve, root, &mut indices, &mut nodes);
+        queue.push_back((root, 0));
+    }
+
+    while let Some((id, depth)) = queue.pop_front() {
+        if opts.depth.map_or(false, |max| depth >= max) {
+            continue;
+        }
+        let mut deps = resolve.deps(id).collect::<Vec<_>>();
+        deps.sort_by_key(|&(dep_id, _)| dep_id);
+        for (dep_id, deps) in deps {
+            let mut kinds = BTreeSet::new();
+            let mut features = BTreeSet::new();
+            for dep in deps.iter().filter(|d| opts.kinds.contains(&d.kind())) {
+                kinds.insert(dep.kind());
+                features.extend(dep.features().iter().map(|f| f.to_string()));
+            }
+            if kinds.is_empty() {
+                continue;
+            }
+            if !indices.contains_key(&dep_id) {
+                add_node(resolve, dep_id, &mut indices, &mut nodes);
+                queue.push_back((dep_id, depth + 1));
+            }
+            edges.push(Edge {
+                from_index: indices[&id],
+                to_index: indices[&dep_id],
+                from: id,
+                to: dep_id,
+                kinds: kinds.into_iter().map(kind_name).collect(),
+                features: features.into_iter().collect(),
+            });
+        }
+    }
+
+    Graph {
+        roots,
+        nodes,
+        edges,
+    }
+}
+
+fn add_node(
+    resolve: &Resolve,
+    id: PackageId,
+    indices: &mut HashMap<PackageId, usize>,
+    nodes: &mut Vec<Node>,
+) {
+    indices.insert(id, nodes.len());
+    nodes.push(Node {
+        id,
+        name: id.name().to_string(),
+        version: id.version().to_string(),
+        features: resolve
+            .features_sorted(id)
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect(),
+    });
+}
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Normal => "normal",
+        Kind::Development => "dev",
+        Kind::Build => "build",
+    }
+}
+
+fn node_label(node: &Node, show_features: bool) -> String {
+    let mut label = format!("{} v{}", node.name, node.version);
+    if show_features && !node.features.is_empty() {
+        write!(label, "\\n[{}]", node.features.join(", ")).unwrap();
+    }
+    label
+}
+
+/// The label of an edge, or `None` for a plain normal dependency.
+fn edge_label(edge: &Edge, show_features: bool) -> Option<String> {
+    let mut parts = Vec::new();
+    if edge.kinds != ["normal"] {
+        parts.push(edge.kinds.join(", "));
+    }
+    if show_features && !edge.features.is_empty() {
+        parts.push(format!("[{}]", edge.features.join(", ")));
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+fn render_dot(graph: &Graph, show_features: bool) -> String {
+    let mut out = String::from("digraph {\n");
+    for (i, node) in graph.nodes.iter().enumerate() {
+        writeln!(out, "    n{} [label=\"{}\"];", i, node_label(node, show_features)).unwrap();
+    }
+    for edge in graph.edges.iter() {
+        match edge_label(edge, show_features) {
+            Some(label) => writeln!(
+                out,
+                "    n{} -> n{} [label=\"{}\"];",
+                edge.from_index, edge.to_index, label
+            ),
+            None => writeln!(out, "    n{} -> n{};", edge.from_index, edge.to_index),
+        }
+        .unwrap();
+    }
+    out.push('}');
+    out
+}
+
+fn render_mermaid(graph: &Graph, show_features: bool) -> String {
+    let mut out = String::from("graph TD\n");
+    for (i, node) in graph.nodes.iter().enumerate() {
+        // Mermaid breaks lines with `<br>` rather than `\n`.
+        let label = node_label(node, show_features).replace("\\n", "<br>");
+        writeln!(out, "    n{}[\"{}\"]", i, label).unwrap();
+    }
+    for edge in graph.edges.iter() {
+        match edge_label(edge, show_features) {
+            Some(label) => writeln!(
+                out,
+                "    n{} -- \"{}\" --> n{}",
+                edge.from_index, label, edge.to_index
+            ),
+            None => writeln!(out, "    n{} --> n{}", edge.from_index, edge.to_index),
+        }
+        .unwrap();
+    }
+    out.pop();
+    out
+}
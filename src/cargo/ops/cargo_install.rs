@@ -10,7 +10,7 @@ use semver::{Version, VersionReq};
 use tempfile::Builder as TempFileBuilder;
 use toml;
 
-use crate::core::compiler::{DefaultExecutor, Executor};
+use crate::core::compiler::{Compilation, DefaultExecutor, Executor};
 use crate::core::package::PackageSet;
 use crate::core::source::SourceMap;
 use crate::core::{Dependency, Edition, Package, PackageIdSpec, Source, SourceId};
@@ -36,6 +36,9 @@ struct Empty {}
 #[derive(Deserialize, Serialize)]
 struct CrateListingV1 {
     v1: BTreeMap<PackageId, BTreeSet<String>>,
+    /// Files other than binaries installed for `[package.metadata.install]`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    files: BTreeMap<PackageId, BTreeSet<PathBuf>>,
 }
 
 struct Transaction {
@@ -64,6 +67,7 @@ pub fn install(
     vers: Option<&str>,
     opts: &ops::CompileOptions,
     force: bool,
+    ignore_local_config: bool,
 ) -> CargoResult<()> {
     let root = resolve_root(root, opts.config)?;
     let map = SourceConfigMap::new(opts.config)?;
@@ -78,6 +82,7 @@ pub fn install(
             vers,
             opts,
             force,
+            ignore_local_config,
             true,
         )?;
         (true, false)
@@ -97,6 +102,7 @@ pub fn install(
                 vers,
                 opts,
                 force,
+                ignore_local_config,
                 first,
             ) {
                 Ok(()) => succeeded.push(krate),
@@ -159,6 +165,7 @@ fn install_one(
     vers: Option<&str>,
     opts: &ops::CompileOptions,
     force: bool,
+    ignore_local_config: bool,
     is_first_install: bool,
 ) -> CargoResult<()> {
     let config = opts.config;
@@ -202,6 +209,12 @@ fn install_one(
         )?
     };
 
+    let _layer = if source_id.is_path() || ignore_local_config {
+        None
+    } else {
+        bundled_config(&pkg, config)?
+    };
+
     let mut td_opt = None;
     let mut needs_cleanup = false;
     let overidden_target_dir = if source_id.is_path() {
@@ -293,6 +306,13 @@ fn install_one(
     let mut list = read_crate_list(&metadata)?;
     let dst = metadata.parent().join("bin");
     let duplicates = check_overwrites(&dst, pkg, &opts.filter, &list, force)?;
+    let files = if config.cli_unstable().install_metadata {
+        let files = metadata_files(pkg, &compile, metadata.parent(), config)?;
+        check_file_overwrites(&files, &list, force)?;
+        files
+    } else {
+        Vec::new()
+    };
 
     fs::create_dir_all(&dst)?;
 
@@ -324,7 +344,9 @@ fn install_one(
     for bin in to_install.iter() {
         let src = staging_dir.path().join(bin);
         let dst = dst.join(bin);
-        config.shell().status("Installing", dst.display())?;
+        let mut shell = config.shell();
+        let message = shell.hyperlink(&dst, dst.display());
+        shell.status("Installing", message)?;
         fs::rename(&src, &dst).chain_err(|| {
             format_err!("failed to move `{}` to `{}`", src.display(), dst.display())
         })?;
@@ -339,7 +361,9 @@ fn install_one(
             for &bin in to_replace.iter() {
                 let src = staging_dir.path().join(bin);
                 let dst = dst.join(bin);
-                config.shell().status("Replacing", dst.display())?;
+                let mut shell = config.shell();
+                let message = shell.hyperlink(&dst, dst.display());
+                shell.status("Replacing", message)?;
                 fs::rename(&src, &dst).chain_err(|| {
                     format_err!("failed to move `{}` to `{}`", src.display(), dst.display())
                 })?;
@@ -350,6 +374,21 @@ fn install_one(
         try_install()
     };
 
+    // Install the other files once all binaries are in place.
+    let result = result.and_then(|()| {
+        for &(ref src, ref dst) in files.iter() {
+            fs::create_dir_all(dst.parent().unwrap())?;
+            let mut shell = config.shell();
+            let message = shell.hyperlink(dst, dst.display());
+            shell.status("Installing", message)?;
+            fs::copy(src, dst).chain_err(|| {
+                format_err!("failed to copy `{}` to `{}`", src.display(), dst.display())
+            })?;
+            installed.bins.push(dst.clone());
+        }
+        Ok(())
+    });
+
     // Update records of replaced binaries.
     for &bin in replaced_names.iter() {
         if let Some(&Some(ref p)) = duplicates.get(bin) {
@@ -374,8 +413,10 @@ fn install_one(
         .iter()
         .filter_map(|(&p, set)| if set.is_empty() { Some(p) } else { None })
         .collect::<Vec<_>>();
+    let mut stale_files = Vec::new();
     for p in pkgs.iter() {
         list.v1.remove(p);
+        stale_files.extend(list.files.remove(p).unwrap_or_default());
     }
 
     // If installation was successful record newly installed binaries.
@@ -384,6 +425,29 @@ fn install_one(
             .entry(pkg.package_id())
             .or_insert_with(BTreeSet::new)
             .extend(to_install.iter().map(|s| s.to_string()));
+
+        // Files installed by a previous version which this one doesn't
+        // install anymore are removed.
+        let new_files = files
+            .iter()
+            .map(|&(_, ref dst)| dst.clone())
+            .collect::<BTreeSet<_>>();
+        stale_files.extend(list.files.remove(&pkg.package_id()).unwrap_or_default());
+        stale_files.retain(|file| !new_files.contains(file) && file.exists());
+        // With `--force` this package takes over files of other packages.
+        let pkgs = list.files.keys().cloned().collect::<Vec<_>>();
+        for p in pkgs {
+            let set = list.files.remove(&p).unwrap();
+            let set = set.difference(&new_files).cloned().collect::<BTreeSet<_>>();
+            if !set.is_empty() {
+                list.files.insert(p, set);
+            }
+        }
+        if !new_files.is_empty() {
+            list.files.insert(pkg.package_id(), new_files);
+        }
+    } else {
+        stale_files.clear();
     }
 
     let write_result = write_crate_list(&metadata, list);
@@ -396,6 +460,10 @@ fn install_one(
 
     // Reaching here means all actions have succeeded. Clean up.
     installed.success();
+    for file in stale_files {
+        config.shell().status("Removing", file.display())?;
+        paths::remove_file(&file)?;
+    }
     if needs_cleanup {
         // Don't bother grabbing a lock as we're going to blow it all away
         // anyway.
@@ -406,6 +474,63 @@ fn install_one(
     Ok(())
 }
 
+/// Clears the configuration layered for the build of a crate once it's done.
+struct ConfigLayer<'a>(&'a Config);
+
+impl<'a> Drop for ConfigLayer<'a> {
+    fn drop(&mut self) {
+        self.0.clear_layer();
+    }
+}
+
+/// Layers the configuration file bundled with the crate `pkg`, fetched from
+/// a registry or a git repository, over the configuration of cargo for its
+/// build, like it's layered over the configuration of the home directory of
+/// cargo when the crate is built in its own directory, and warns about the
+/// keys it sets.
+fn bundled_config<'a>(pkg: &Package, config: &'a Config) -> CargoResult<Option<ConfigLayer<'a>>> {
+    let path = pkg.root().join(".cargo").join("config");
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let contents = paths::read(&path)?;
+    let mut keys = Vec::new();
+    if let Ok(toml) = contents.parse::<toml::Value>() {
+        config_keys("", &toml, &mut keys);
+    }
+    if keys.is_empty() {
+        return Ok(None);
+    }
+    config.set_layer(&path)?;
+    config.shell().warn(format!(
+        "`{}` bundles the configuration file `.cargo/config`, which applies \
+         to its build and sets {}\n\
+         pass `--ignore-local-config` to ignore it",
+        pkg.package_id(),
+        keys.iter()
+            .map(|k| format!("`{}`", k))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))?;
+    Ok(Some(ConfigLayer(config)))
+}
+
+fn config_keys(prefix: &str, value: &toml::Value, keys: &mut Vec<String>) {
+    match value.as_table() {
+        Some(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                config_keys(&key, value, keys);
+            }
+        }
+        None => keys.push(prefix.to_string()),
+    }
+}
+
 fn path_source<'a>(source_id: SourceId, config: &'a Config) -> CargoResult<PathSource<'a>> {
     let path = source_id
         .url()
@@ -414,7 +539,7 @@ fn path_source<'a>(source_id: SourceId, config: &'a Config) -> CargoResult<PathS
     Ok(PathSource::new(&path, source_id, config))
 }
 
-fn select_pkg<'a, T>(
+pub(super) fn select_pkg<'a, T>(
     mut source: T,
     name: Option<&str>,
     vers: Option<&str>,
@@ -600,6 +725,165 @@ fn check_overwrites(
     Err(format_err!("{}", msg))
 }
 
+/// Collects the files `[package.metadata.install]` asks to install along with
+/// the binaries, as pairs of the file to copy and its destination.
+///
+/// Paths are relative to the package root, or to the build script's
+/// `OUT_DIR` if they start with `$OUT_DIR/`, for files generated when
+/// building.
+fn metadata_files(
+    pkg: &Package,
+    compile: &Compilation,
+    root: &Path,
+    config: &Config,
+) -> CargoResult<Vec<(PathBuf, PathBuf)>> {
+    let metadata = pkg.manifest().custom_metadata();
+    let table = match metadata.and_then(|m| m.get("install")) {
+        Some(table) => table,
+        None => return Ok(Vec::new()),
+    };
+    let source = |path: &str| -> CargoResult<PathBuf> {
+        let src = if path.starts_with("$OUT_DIR/") {
+            match compile.out_dirs.get(&pkg.package_id()) {
+                Some(out_dir) => out_dir.join(&path["$OUT_DIR/".len()..]),
+                None => bail!(
+                    "`{}` in `package.metadata.install` refers to `$OUT_DIR`, \
+                     but `{}` has no build script",
+                    path,
+                    pkg.name()
+                ),
+            }
+        } else {
+            pkg.root().join(path)
+        };
+        if !src.is_file() {
+            bail!(
+                "`{}` in `package.metadata.install` does not exist, \
+                 expected a file at `{}`",
+                path,
+                src.display()
+            )
+        }
+        Ok(src)
+    };
+
+    let mut files = Vec::new();
+    if let Some(man) = table.get("man") {
+        let pages = match man.as_array() {
+            Some(pages) => pages,
+            None => bail!("`package.metadata.install.man` must be a list of paths"),
+        };
+        let man_dir = install_dir(config, "install.man-dir", root, "share/man")?;
+        for page in pages {
+            let page = match page.as_str() {
+                Some(page) => page,
+                None => bail!("`package.metadata.install.man` must be a list of paths"),
+            };
+            let src = source(page)?;
+            // Pages go into the directory of their section, which is the
+            // extension of their file name, like `man1` for `foo.1`.
+            let section = src
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(|e| e.chars().next())
+                .filter(|c| c.is_ascii_digit());
+            let section = match section {
+                Some(section) => section,
+                None => bail!(
+                    "the man page `{}` must have its section as the \
+                     extension of its file name, like `{}.1`",
+                    page,
+                    pkg.name()
+                ),
+            };
+            let dst = man_dir
+                .join(format!("man{}", section))
+                .join(src.file_name().unwrap());
+            files.push((src, dst));
+        }
+    }
+    if let Some(completions) = table.get("completions") {
+        let completions = match completions.as_table() {
+            Some(completions) => completions,
+            None => bail!(
+                "`package.metadata.install.completions` must be a table \
+                 of paths by shell"
+            ),
+        };
+        for (shell, path) in completions {
+            let (key, default) = match &shell[..] {
+                "bash" => (
+                    "install.bash-completion-dir",
+                    "share/bash-completion/completions",
+                ),
+                "zsh" => ("install.zsh-completion-dir", "share/zsh/site-functions"),
+                "fish" => (
+                    "install.fish-completion-dir",
+                    "share/fish/vendor_completions.d",
+                ),
+                _ => bail!(
+                    "unsupported shell `{}` in `package.metadata.install.completions`, \
+                     expected `bash`, `zsh` or `fish`",
+                    shell
+                ),
+            };
+            let path = match path.as_str() {
+                Some(path) => path,
+                None => bail!(
+                    "`package.metadata.install.completions.{}` must be a path",
+                    shell
+                ),
+            };
+            let src = source(path)?;
+            let dst = install_dir(config, key, root, default)?.join(src.file_name().unwrap());
+            files.push((src, dst));
+        }
+    }
+    Ok(files)
+}
+
+/// The directory configured with `key`, relative to the configuration it's
+/// defined in, or `default` in the installation root.
+fn install_dir(config: &Config, key: &str, root: &Path, default: &str) -> CargoResult<PathBuf> {
+    Ok(match config.get_string(key)? {
+        Some(dir) => dir.definition.root(config).join(dir.val),
+        None => root.join(default),
+    })
+}
+
+fn check_file_overwrites(
+    files: &[(PathBuf, PathBuf)],
+    prev: &CrateListingV1,
+    force: bool,
+) -> CargoResult<()> {
+    if force {
+        return Ok(());
+    }
+    let mut msg = String::new();
+    for &(_, ref dst) in files {
+        if !dst.exists() {
+            continue;
+        }
+        let owner = prev.files.iter().find(|&(_, set)| set.contains(dst));
+        match owner {
+            Some((p, _)) => msg.push_str(&format!(
+                "`{}` already exists in destination as part of `{}`\n",
+                dst.display(),
+                p
+            )),
+            None => msg.push_str(&format!(
+                "`{}` already exists in destination\n",
+                dst.display()
+            )),
+        }
+    }
+    if msg.is_empty() {
+        return Ok(());
+    }
+    msg.push_str("Add --force to overwrite");
+    Err(format_err!("{}", msg))
+}
+
 fn find_duplicates(
     dst: &Path,
     pkg: &Package,
@@ -663,6 +947,7 @@ fn read_crate_list(file: &FileLock) -> CargoResult<CrateListingV1> {
             CrateListing::V1(v1) => Ok(v1),
             CrateListing::Empty(_) => Ok(CrateListingV1 {
                 v1: BTreeMap::new(),
+                files: BTreeMap::new(),
             }),
         }
     })()
@@ -843,6 +1128,11 @@ fn uninstall_pkgid(
             installed.remove();
         }
     }
+    // The other files of a package go away along with its last binary.
+    if !metadata.v1.contains_key(&pkgid) {
+        let files = metadata.files.remove(&pkgid).unwrap_or_default();
+        to_remove.extend(files.into_iter().filter(|f| f.exists()));
+    }
     write_crate_list(&crate_metadata, metadata)?;
     for bin in to_remove {
         config.shell().status("Removing", bin.display())?;
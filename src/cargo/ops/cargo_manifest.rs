@@ -0,0 +1,694 @@
+use std::ops::Range;
+use std::path::Path;
+
+use toml;
+
+use crate::core::manifest::EitherManifest;
+use crate::core::SourceId;
+use crate::util::errors::{internal, CargoResult, CargoResultExt};
+use crate::util::{paths, toml as cargo_toml, Config};
+
+/// An edit of a manifest made by `cargo manifest`.
+pub enum ManifestEdit {
+    /// Sets the key, like `package.version`, to a value written in TOML.
+    Set { key: String, value: String },
+    /// Removes the key, or the table of that name.
+    Unset { key: String },
+    /// Adds the feature `name` enabling `enables`, or adds those to the
+    /// features it enables if it exists already.
+    AddFeature { name: String, enables: Vec<String> },
+}
+
+/// Edits the manifest at `path` for `cargo manifest`.
+///
+/// The text of the manifest is edited in place, so that the rest of the file
+/// keeps its formatting and comments. The edited manifest is read back the
+/// way cargo reads manifests before it's written, so an edit which would make
+/// the manifest invalid, or which sets a key cargo doesn't know, leaves it
+/// untouched.
+pub fn edit_manifest(config: &Config, path: &Path, edit: &ManifestEdit) -> CargoResult<()> {
+    let contents = paths::read(path)?;
+    let (key, expected, updated) = match *edit {
+        ManifestEdit::Set { ref key, ref value } => {
+            let key = parse_key(key)?;
+            let value = parse_value(value)?;
+            let updated = Document::parse(&contents)?.set(&key, &value)?;
+            (key, Some(value), updated)
+        }
+        ManifestEdit::Unset { ref key } => {
+            let key = parse_key(key)?;
+            let updated = Document::parse(&contents)?.unset(&key)?;
+            (key, None, updated)
+        }
+        ManifestEdit::AddFeature {
+            ref name,
+            ref enables,
+        } => {
+            let key = vec!["features".to_string(), name.clone()];
+            let mut features = match lookup(&parse_document(&contents)?, &key) {
+                Some(toml::Value::Array(features)) => features,
+                Some(v) => bail!(
+                    "feature `{}` is {} in the manifest, not an array",
+                    name,
+                    v.type_str()
+                ),
+                None => Vec::new(),
+            };
+            for feature in enables {
+                let feature = toml::Value::String(feature.clone());
+                if !features.contains(&feature) {
+                    features.push(feature);
+                }
+            }
+            let value = toml::Value::Array(features);
+            let updated = Document::parse(&contents)?.set(&key, &value)?;
+            (key, Some(value), updated)
+        }
+    };
+
+    // Guard against the edit of the text having done something else than
+    // what was asked.
+    let edited = parse_document(&updated)
+        .chain_err(|| internal(format!("failed to edit `{}` in place", key_path_repr(&key))))?;
+    if lookup(&edited, &key) != expected {
+        return Err(internal(format!(
+            "failed to edit `{}` in place",
+            key_path_repr(&key)
+        )));
+    }
+    validate(config, path, &contents, &updated).chain_err(|| {
+        format!(
+            "the edit would make the manifest at `{}` invalid, it wasn't changed",
+            path.display()
+        )
+    })?;
+
+    paths::write(path, updated.as_bytes())?;
+    let verb = if expected.is_some() {
+        "Updated"
+    } else {
+        "Removed"
+    };
+    config.shell().status(
+        verb,
+        format!("`{}` in {}", key_path_repr(&key), path.display()),
+    )?;
+    Ok(())
+}
+
+/// Reads the edited manifest `after`, failing if it's invalid or if it has
+/// keys cargo doesn't know which the original manifest `before` didn't have.
+fn validate(config: &Config, path: &Path, before: &str, after: &str) -> CargoResult<()> {
+    let source_id = SourceId::for_path(path.parent().unwrap())?;
+    let unused = |contents: &str| -> CargoResult<Vec<String>> {
+        let (manifest, _) = cargo_toml::read_manifest_contents(contents, path, source_id, config)?;
+        let warnings = match manifest {
+            EitherManifest::Real(ref m) => m.warnings().warnings(),
+            EitherManifest::Virtual(ref m) => m.warnings().warnings(),
+        };
+        Ok(warnings
+            .iter()
+            .filter(|w| w.message.starts_with("unused manifest key: "))
+            .map(|w| w.message["unused manifest key: ".len()..].to_string())
+            .collect())
+    };
+    let known = unused(before).unwrap_or_default();
+    for key in unused(after)? {
+        if !known.contains(&key) {
+            bail!("`{}` isn't a manifest key cargo knows about", key);
+        }
+    }
+    Ok(())
+}
+
+fn parse_document(contents: &str) -> CargoResult<toml::Value> {
+    Ok(toml::from_str(contents)?)
+}
+
+/// Parses a value written in TOML, like `"1.2.0"` or `["a", "b"]`.
+fn parse_value(value: &str) -> CargoResult<toml::Value> {
+    let doc: toml::value::Table = toml::from_str(&format!("value = {}", value))
+        .map_err(|_| format_err!("`{}` isn't a TOML value", value))?;
+    Ok(doc["value"].clone())
+}
+
+fn lookup(doc: &toml::Value, key: &[String]) -> Option<toml::Value> {
+    let mut value = doc;
+    for part in key {
+        value = value.as_table()?.get(part)?;
+    }
+    Some(value.clone())
+}
+
+/// Parses a key like `dependencies.serde."default-features"`.
+fn parse_key(key: &str) -> CargoResult<Vec<String>> {
+    let mut parts = vec![String::new()];
+    let mut quote = None;
+    let mut chars = key.trim().chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some('"') if c == '\\' => parts.last_mut().unwrap().extend(chars.next()),
+            Some(_) => parts.last_mut().unwrap().push(c),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '.' => parts.push(String::new()),
+            None if c.is_whitespace() => {}
+            None => parts.last_mut().unwrap().push(c),
+        }
+    }
+    if quote.is_some() || parts.iter().any(|p| p.is_empty()) {
+        bail!("invalid manifest key `{}`", key.trim());
+    }
+    Ok(parts)
+}
+
+/// Writes `key` the way it's written in a TOML document, quoted if needed.
+fn key_repr(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if bare {
+        key.to_string()
+    } else {
+        toml::Value::String(key.to_string()).to_string()
+    }
+}
+
+fn key_path_repr(key: &[String]) -> String {
+    key.iter()
+        .map(|k| key_repr(k))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Writes `value` on a single line, with tables written inline.
+fn inline(value: &toml::Value) -> String {
+    match *value {
+        toml::Value::Table(ref table) if table.is_empty() => "{}".to_string(),
+        toml::Value::Table(ref table) => {
+            let pairs = table
+                .iter()
+                .map(|(k, v)| format!("{} = {}", key_repr(k), inline(v)))
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", pairs.join(", "))
+        }
+        toml::Value::Array(ref array) => {
+            let values = array.iter().map(inline).collect::<Vec<_>>();
+            format!("[{}]", values.join(", "))
+        }
+        ref v => v.to_string(),
+    }
+}
+
+/// A key-value pair of a manifest, on the lines `start..=end`.
+struct Entry {
+    key: Vec<String>,
+    start: usize,
+    end: usize,
+}
+
+/// A table of a manifest, including the root one before the first header.
+struct Table {
+    key: Vec<String>,
+    header: Option<usize>,
+    /// The last line of the last key-value pair of the table, if any.
+    last: Option<usize>,
+    /// The line of the next header, or the number of lines.
+    end: usize,
+    /// Whether the table is an element of an array of tables, like `[[bin]]`.
+    array: bool,
+}
+
+/// The lines of a manifest, along with where its tables and key-value pairs
+/// are, so that they can be edited without touching the rest of the text.
+struct Document<'a> {
+    original: &'a str,
+    lines: Vec<String>,
+    entries: Vec<Entry>,
+    tables: Vec<Table>,
+}
+
+impl<'a> Document<'a> {
+    fn parse(contents: &'a str) -> CargoResult<Document<'a>> {
+        let lines = contents.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        let mut entries = Vec::new();
+        let mut tables = vec![Table {
+            key: Vec::new(),
+            header: None,
+            last: None,
+            end: lines.len(),
+            array: false,
+        }];
+        let mut i = 0;
+        while i < lines.len() {
+            let (code, _) = split_comment(&lines[i]);
+            let code = code.trim();
+            if code.is_empty() {
+                i += 1;
+                continue;
+            }
+            if code.starts_with('[') {
+                let array = code.starts_with("[[");
+                let name = code
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_string();
+                tables.last_mut().unwrap().end = i;
+                tables.push(Table {
+                    key: parse_key(&name)?,
+                    header: Some(i),
+                    last: None,
+                    end: lines.len(),
+                    array,
+                });
+                i += 1;
+                continue;
+            }
+
+            let eq = match find_unquoted(code, '=') {
+                Some(eq) => eq,
+                None => bail!("failed to parse line {} of the manifest", i + 1),
+            };
+            let mut key = parse_key(&code[..eq])?;
+            let start = i;
+            let mut scanner = Scanner::default();
+            scanner.scan(&lines[i][find_unquoted(&lines[i], '=').unwrap() + 1..]);
+            while scanner.open() && i + 1 < lines.len() {
+                i += 1;
+                scanner.scan(&lines[i]);
+            }
+            let table = tables.last_mut().unwrap();
+            table.last = Some(i);
+            if !table.array {
+                let mut full = table.key.clone();
+                full.append(&mut key);
+                entries.push(Entry {
+                    key: full,
+                    start,
+                    end: i,
+                });
+            }
+            i += 1;
+        }
+        Ok(Document {
+            original: contents,
+            lines,
+            entries,
+            tables,
+        })
+    }
+
+    fn set(mut self, key: &[String], value: &toml::Value) -> CargoResult<String> {
+        if let Some(entry) = self.entries.iter().find(|e| e.key == key) {
+            let (start, end) = (entry.start, entry.end);
+            self.replace_value(start, end, &inline(value));
+            return Ok(self.finish());
+        }
+        if let Some(entry) = self.entries.iter().find(|e| key.starts_with(&e.key)) {
+            let (start, end) = (entry.start, entry.end);
+            let table = self.inline_table(entry)?;
+            let edited = edit_inline_table(&table, &key[entry.key.len()..], Some(value), key)?;
+            self.replace_value(start, end, &edited.unwrap());
+            return Ok(self.finish());
+        }
+
+        // Add the key to the closest table it's in, or a new table for it.
+        let table = self
+            .tables
+            .iter()
+            .filter(|t| !t.array && t.key.len() < key.len() && key.starts_with(&t.key))
+            .max_by_key(|t| t.key.len())
+            .unwrap();
+        let line = format!("{} = {}", key_repr(&key[key.len() - 1]), inline(value));
+        if table.key.len() == key.len() - 1 {
+            let at = match (table.last, table.header) {
+                (Some(last), _) => last + 1,
+                (None, Some(header)) => header + 1,
+                (None, None) => 0,
+            };
+            let indent = match table.last.or(table.header) {
+                Some(line) => indentation(&self.lines[line]).to_string(),
+                None => String::new(),
+            };
+            self.lines.insert(at, format!("{}{}", indent, line));
+        } else {
+            let at = match table.header {
+                Some(_) => table.last.or(table.header).unwrap() + 1,
+                None => self.lines.len(),
+            };
+            let mut new = vec![format!("[{}]", key_path_repr(&key[..key.len() - 1])), line];
+            if at > 0 && !self.lines[at - 1].trim().is_empty() {
+                new.insert(0, String::new());
+            }
+            if at < self.lines.len() && !self.lines[at].trim().is_empty() {
+                new.push(String::new());
+            }
+            for (i, line) in new.into_iter().enumerate() {
+                self.lines.insert(at + i, line);
+            }
+        }
+        Ok(self.finish())
+    }
+
+    fn unset(mut self, key: &[String]) -> CargoResult<String> {
+        if let Some(entry) = self.entries.iter().find(|e| e.key == key) {
+            self.lines.drain(entry.start..=entry.end);
+            return Ok(self.finish());
+        }
+        let tables = self
+            .tables
+            .iter()
+            .filter(|t| t.header.is_some() && t.key.starts_with(key))
+            .map(|t| (t.header.unwrap(), t.last.unwrap_or(t.header.unwrap())))
+            .collect::<Vec<_>>();
+        if !tables.is_empty() {
+            for &(header, last) in tables.iter().rev() {
+                let blank_before = header > 0 && self.lines[header - 1].trim().is_empty();
+                let start = if blank_before { header - 1 } else { header };
+                self.lines.drain(start..=last);
+            }
+            return Ok(self.finish());
+        }
+        if let Some(entry) = self.entries.iter().find(|e| key.starts_with(&e.key)) {
+            let (start, end) = (entry.start, entry.end);
+            let table = self.inline_table(entry)?;
+            if let Some(edited) = edit_inline_table(&table, &key[entry.key.len()..], None, key)? {
+                self.replace_value(start, end, &edited);
+                return Ok(self.finish());
+            }
+        }
+        bail!("`{}` isn't set in the manifest", key_path_repr(key))
+    }
+
+    /// The text of the value of `entry`, which is a prefix of the key being
+    /// edited, so it has to be an inline table. The comment following a value
+    /// on a single line isn't part of it.
+    fn inline_table(&self, entry: &Entry) -> CargoResult<String> {
+        let text = if entry.start == entry.end {
+            split_comment(&self.lines[entry.start]).0.to_string()
+        } else {
+            self.lines[entry.start..=entry.end].join("\n")
+        };
+        let text = text[find_unquoted(&text, '=').unwrap() + 1..].trim();
+        if !text.starts_with('{') {
+            let stripped = self.lines[entry.start..=entry.end]
+                .iter()
+                .map(|l| split_comment(l).0)
+                .collect::<Vec<_>>()
+                .join("\n");
+            let value = parse_value(&stripped[find_unquoted(&stripped, '=').unwrap() + 1..])?;
+            bail!(
+                "`{}` is {} in the manifest, not a table",
+                key_path_repr(&entry.key),
+                value.type_str()
+            );
+        }
+        Ok(text.to_string())
+    }
+
+    /// Replaces the value of the key-value pair on the lines `start..=end`,
+    /// keeping the key and the comment following a value on a single line.
+    fn replace_value(&mut self, start: usize, end: usize, value: &str) {
+        let first = &self.lines[start];
+        let eq = find_unquoted(first, '=').unwrap();
+        let value_start = eq + 1 + (first[eq + 1..].len() - first[eq + 1..].trim_start().len());
+        let mut line = format!("{}{}", &first[..value_start], value);
+        if start == end {
+            let (code, comment) = split_comment(first);
+            if !comment.is_empty() {
+                line.push_str(&code[code.trim_end().len()..]);
+                line.push_str(comment);
+            }
+        }
+        let lines = line.split('\n').map(|l| l.to_string()).collect::<Vec<_>>();
+        self.lines.splice(start..=end, lines);
+    }
+
+    fn finish(self) -> String {
+        let newline = if self.original.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let mut ret = self.lines.join(newline);
+        if self.original.ends_with('\n') || self.original.is_empty() {
+            ret.push_str(newline);
+        }
+        ret
+    }
+}
+
+/// A key-value pair of an inline table, as the positions of its text in the
+/// table.
+struct InlinePair {
+    key: Vec<String>,
+    start: usize,
+    value: Range<usize>,
+}
+
+/// Splits the inline table `text` into its key-value pairs, returning them
+/// along with the position of the closing brace of the table.
+fn inline_pairs(text: &str) -> CargoResult<(Vec<InlinePair>, usize)> {
+    let mut pairs = Vec::new();
+    let mut i = 1;
+    loop {
+        i = text.len() - text[i..].trim_start().len();
+        if i >= text.len() || text[i..].starts_with('}') {
+            return Ok((pairs, i));
+        }
+        let eq = match find_unquoted(&text[i..], '=') {
+            Some(eq) => i + eq,
+            None => bail!("failed to parse the inline table `{}`", text),
+        };
+        let key = parse_key(&text[i..eq])?;
+        let value_start = text.len() - text[eq + 1..].trim_start().len();
+        let end = inline_value_end(text, value_start);
+        pairs.push(InlinePair {
+            key,
+            start: i,
+            value: value_start..value_start + text[value_start..end].trim_end().len(),
+        });
+        i = end;
+        if text[i..].starts_with(',') {
+            i += 1;
+        }
+    }
+}
+
+/// The position of the `,` or `}` following the value starting at `start` in
+/// an inline table, skipping strings, comments and nested arrays and tables.
+fn inline_value_end(text: &str, start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < text.len() {
+        let rest = &text[i..];
+        if rest.starts_with("\"\"\"") || rest.starts_with("'''") {
+            let delim = &rest[..3];
+            i += 3 + rest[3..].find(delim).map_or(rest.len() - 3, |j| j + 3);
+            continue;
+        }
+        let c = rest.chars().next().unwrap();
+        match c {
+            '"' | '\'' => {
+                let mut escaped = false;
+                let mut close = rest.len();
+                for (j, ch) in rest.char_indices().skip(1) {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '"' && ch == '\\' {
+                        escaped = true;
+                    } else if ch == c {
+                        close = j + 1;
+                        break;
+                    }
+                }
+                i += close;
+                continue;
+            }
+            '#' => {
+                i += rest.find('\n').unwrap_or(rest.len());
+                continue;
+            }
+            '[' | '{' => depth += 1,
+            ']' | '}' if depth == 0 => return i,
+            ']' | '}' => depth -= 1,
+            ',' if depth == 0 => return i,
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    i
+}
+
+/// Edits the text of the inline table `text`, setting `key` in it to `value`,
+/// or removing it if `value` is `None`, without touching the other pairs.
+/// Returns `None` if the key to remove isn't set. `full` is the key being
+/// edited in the manifest.
+fn edit_inline_table(
+    text: &str,
+    key: &[String],
+    value: Option<&toml::Value>,
+    full: &[String],
+) -> CargoResult<Option<String>> {
+    let (pairs, close) = inline_pairs(text)?;
+    for (i, pair) in pairs.iter().enumerate() {
+        if pair.key == key {
+            let edited = match value {
+                Some(value) => format!(
+                    "{}{}{}",
+                    &text[..pair.value.start],
+                    inline(value),
+                    &text[pair.value.end..]
+                ),
+                None if i + 1 < pairs.len() => format!(
+                    "{}{}",
+                    &text[..pair.start],
+                    &text[pairs[i + 1].start..]
+                ),
+                None if i > 0 => format!(
+                    "{}{}",
+                    &text[..pairs[i - 1].value.end],
+                    &text[pair.value.end..]
+                ),
+                None => format!("{{}}{}", &text[close + 1..]),
+            };
+            return Ok(Some(edited));
+        }
+        if key.starts_with(&pair.key) {
+            let inner = &text[pair.value.clone()];
+            if !inner.starts_with('{') {
+                if value.is_none() {
+                    return Ok(None);
+                }
+                bail!("`{}` isn't a table", key_path_repr(full));
+            }
+            let edited = edit_inline_table(inner, &key[pair.key.len()..], value, full)?;
+            return Ok(edited.map(|edited| {
+                format!(
+                    "{}{}{}",
+                    &text[..pair.value.start],
+                    edited,
+                    &text[pair.value.end..]
+                )
+            }));
+        }
+    }
+
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let mut nested = value.clone();
+    for part in key[1..].iter().rev() {
+        let mut table = toml::value::Table::new();
+        table.insert(part.clone(), nested);
+        nested = toml::Value::Table(table);
+    }
+    let pair = format!("{} = {}", key_repr(&key[0]), inline(&nested));
+    Ok(Some(match pairs.last() {
+        Some(last) => format!(
+            "{}, {}{}",
+            &text[..last.value.end],
+            pair,
+            &text[last.value.end..]
+        ),
+        None => format!("{{ {} }}{}", pair, &text[close + 1..]),
+    }))
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Splits a line into its code and its comment, if any.
+fn split_comment(line: &str) -> (&str, &str) {
+    match find_unquoted(line, '#') {
+        Some(i) => (&line[..i], &line[i..]),
+        None => (line, ""),
+    }
+}
+
+/// The position of the first `needle` of `line` outside of strings.
+fn find_unquoted(line: &str, needle: char) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == needle => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Follows the brackets and multi-line strings of a value, to find the line
+/// it ends on.
+#[derive(Default)]
+struct Scanner {
+    depth: i32,
+    multi_line: Option<&'static str>,
+}
+
+impl Scanner {
+    fn scan(&mut self, line: &str) {
+        let mut rest = line;
+        loop {
+            if let Some(delim) = self.multi_line {
+                match rest.find(delim) {
+                    Some(i) => {
+                        rest = &rest[i + delim.len()..];
+                        self.multi_line = None;
+                    }
+                    None => return,
+                }
+            }
+            if rest.starts_with("\"\"\"") || rest.starts_with("'''") {
+                self.multi_line = Some(if rest.starts_with('"') {
+                    "\"\"\""
+                } else {
+                    "'''"
+                });
+                rest = &rest[3..];
+                continue;
+            }
+            let c = match rest.chars().next() {
+                Some(c) => c,
+                None => return,
+            };
+            match c {
+                '#' => return,
+                '"' | '\'' => {
+                    // Skip the string, up to its closing quote.
+                    let mut escaped = false;
+                    let mut close = rest.len();
+                    for (i, ch) in rest.char_indices().skip(1) {
+                        if escaped {
+                            escaped = false;
+                        } else if c == '"' && ch == '\\' {
+                            escaped = true;
+                        } else if ch == c {
+                            close = i + 1;
+                            break;
+                        }
+                    }
+                    rest = &rest[close..];
+                    continue;
+                }
+                '[' | '{' => self.depth += 1,
+                ']' | '}' => self.depth -= 1,
+                _ => {}
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    fn open(&self) -> bool {
+        self.depth > 0 || self.multi_line.is_some()
+    }
+}
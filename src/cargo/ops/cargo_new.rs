@@ -7,7 +7,7 @@ use std::path::{Path, PathBuf};
 use git2::Config as GitConfig;
 use git2::Repository as GitRepository;
 
-use crate::core::{compiler, Workspace};
+use crate::core::{compiler, Edition, Workspace};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::{existing_vcs_repo, internal, FossilRepo, GitRepo, HgRepo, PijulRepo};
 use crate::util::{paths, Config};
@@ -56,10 +56,69 @@ impl fmt::Display for NewProjectKind {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SourceKind {
+    Lib,
+    Bin,
+    Example,
+    Test,
+    Bench,
+}
+
+impl SourceKind {
+    fn description(self) -> &'static str {
+        match self {
+            SourceKind::Lib => "library",
+            SourceKind::Bin => "binary",
+            SourceKind::Example => "example",
+            SourceKind::Test => "test",
+            SourceKind::Bench => "benchmark",
+        }
+    }
+
+    /// The description of the kind along with its article, like `an example`.
+    fn description_with_article(self) -> String {
+        match self {
+            SourceKind::Example => format!("an {}", self.description()),
+            _ => format!("a {}", self.description()),
+        }
+    }
+
+    /// The name of the manifest section.
+    fn section(self) -> &'static str {
+        match self {
+            SourceKind::Lib => "lib",
+            SourceKind::Bin => "bin",
+            SourceKind::Example => "example",
+            SourceKind::Test => "test",
+            SourceKind::Bench => "bench",
+        }
+    }
+
+    /// The `auto*` key disabling the inference of targets of the kind.
+    fn auto_key(self) -> &'static str {
+        match self {
+            SourceKind::Lib => unreachable!("libraries are always inferred"),
+            SourceKind::Bin => "autobins",
+            SourceKind::Example => "autoexamples",
+            SourceKind::Test => "autotests",
+            SourceKind::Bench => "autobenches",
+        }
+    }
+}
+
 struct SourceFileInformation {
     relative_path: String,
     target_name: String,
-    bin: bool,
+    kind: SourceKind,
+    /// Whether Cargo would find this target without a section in the manifest.
+    inferred: bool,
+}
+
+impl SourceFileInformation {
+    fn is_bin(&self) -> bool {
+        self.kind == SourceKind::Bin
+    }
 }
 
 struct MkOptions<'a> {
@@ -67,6 +126,9 @@ struct MkOptions<'a> {
     path: &'a Path,
     name: &'a str,
     source_files: Vec<SourceFileInformation>,
+    /// Kinds of targets which are all listed explicitly, as inferring them
+    /// would pick the wrong files.
+    explicit_kinds: Vec<SourceKind>,
     bin: bool,
     edition: Option<&'a str>,
     registry: Option<&'a str>,
@@ -81,13 +143,19 @@ impl NewOptions {
         name: Option<String>,
         edition: Option<String>,
         registry: Option<String>,
+        config: &Config,
     ) -> CargoResult<NewOptions> {
+        // Only the defaults are read here, any other invalid configuration is
+        // reported when the package is created.
+        let (default_kind, default_edition) = config_defaults(config)?;
         let kind = match (bin, lib) {
             (true, true) => bail!("can't specify both lib and binary outputs"),
             (false, true) => NewProjectKind::Lib,
+            (true, false) => NewProjectKind::Bin,
             // default to bin
-            (_, false) => NewProjectKind::Bin,
+            (false, false) => default_kind.unwrap_or(NewProjectKind::Bin),
         };
+        let edition = edition.or(default_edition);
 
         let opts = NewOptions {
             version_control,
@@ -105,6 +173,10 @@ struct CargoNewConfig {
     name: Option<String>,
     email: Option<String>,
     version_control: Option<VersionControl>,
+    license: Option<String>,
+    /// Files to copy into new packages, as pairs of the path relative to the
+    /// package root and the file to copy.
+    files: Vec<(String, PathBuf)>,
 }
 
 fn get_name<'a>(path: &'a Path, opts: &'a NewOptions) -> CargoResult<&'a str> {
@@ -182,6 +254,7 @@ fn detect_source_paths_and_types(
     package_path: &Path,
     package_name: &str,
     detected_files: &mut Vec<SourceFileInformation>,
+    skipped_files: &mut Vec<SourceFileInformation>,
 ) -> CargoResult<()> {
     let path = package_path;
     let name = package_name;
@@ -237,14 +310,16 @@ fn detect_source_paths_and_types(
 
         let sfi = match i.handling {
             H::Bin => SourceFileInformation {
+                inferred: pp == "src/main.rs",
                 relative_path: pp,
                 target_name: package_name.to_string(),
-                bin: true,
+                kind: SourceKind::Bin,
             },
             H::Lib => SourceFileInformation {
+                inferred: pp == "src/lib.rs",
                 relative_path: pp,
                 target_name: package_name.to_string(),
-                bin: false,
+                kind: SourceKind::Lib,
             },
             H::Detect => {
                 let content = paths::read(&path.join(pp.clone()))?;
@@ -252,20 +327,35 @@ fn detect_source_paths_and_types(
                 SourceFileInformation {
                     relative_path: pp,
                     target_name: package_name.to_string(),
-                    bin: isbin,
+                    kind: if isbin {
+                        SourceKind::Bin
+                    } else {
+                        SourceKind::Lib
+                    },
+                    inferred: false,
                 }
             }
         };
         detected_files.push(sfi);
     }
 
+    let dirs = [
+        ("src/bin", SourceKind::Bin),
+        ("examples", SourceKind::Example),
+        ("tests", SourceKind::Test),
+        ("benches", SourceKind::Bench),
+    ];
+    for &(dir, kind) in dirs.iter() {
+        detect_target_directory(path, dir, kind, detected_files, skipped_files)?;
+    }
+
     // Check for duplicate lib attempt
 
     let mut previous_lib_relpath: Option<&str> = None;
     let mut duplicates_checker: BTreeMap<&str, &SourceFileInformation> = BTreeMap::new();
 
     for i in detected_files {
-        if i.bin {
+        if i.kind == SourceKind::Bin {
             if let Some(x) = BTreeMap::get::<str>(&duplicates_checker, i.target_name.as_ref()) {
                 bail!(
                     "\
@@ -278,7 +368,7 @@ cannot automatically generate Cargo.toml as the main target would be ambiguous",
                 );
             }
             duplicates_checker.insert(i.target_name.as_ref(), i);
-        } else {
+        } else if i.kind == SourceKind::Lib {
             if let Some(plp) = previous_lib_relpath {
                 bail!(
                     "cannot have a package with \
@@ -295,22 +385,104 @@ cannot automatically generate Cargo.toml as the main target would be ambiguous",
     Ok(())
 }
 
+/// Finds the targets Cargo would infer from `dir`, the same way the manifest
+/// parser does.
+///
+/// Binaries and examples without a `main` function are most likely modules
+/// shared between them rather than targets of their own, so they are put in
+/// `skipped_files` instead.
+fn detect_target_directory(
+    package_path: &Path,
+    dir: &str,
+    kind: SourceKind,
+    detected_files: &mut Vec<SourceFileInformation>,
+    skipped_files: &mut Vec<SourceFileInformation>,
+) -> CargoResult<()> {
+    let entries = match fs::read_dir(package_path.join(dir)) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+    let mut found = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let file_name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        if file_name.starts_with('.') {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_file() && file_name.ends_with(".rs") {
+            let name = file_name[..file_name.len() - 3].to_string();
+            found.push((name, format!("{}/{}", dir, file_name)));
+        } else if file_type.is_dir() && entry.path().join("main.rs").is_file() {
+            let relative_path = format!("{}/{}/main.rs", dir, file_name);
+            found.push((file_name, relative_path));
+        }
+    }
+    found.sort();
+
+    for (target_name, relative_path) in found {
+        let needs_main = kind == SourceKind::Bin || kind == SourceKind::Example;
+        let sfi = SourceFileInformation {
+            relative_path,
+            target_name,
+            kind,
+            inferred: true,
+        };
+        if needs_main && !paths::read(&package_path.join(&sfi.relative_path))?.contains("fn main") {
+            skipped_files.push(sfi);
+        } else {
+            detected_files.push(sfi);
+        }
+    }
+    Ok(())
+}
+
 fn plan_new_source_file(bin: bool, package_name: String) -> SourceFileInformation {
     if bin {
         SourceFileInformation {
             relative_path: "src/main.rs".to_string(),
             target_name: package_name,
-            bin: true,
+            kind: SourceKind::Bin,
+            inferred: true,
         }
     } else {
         SourceFileInformation {
             relative_path: "src/lib.rs".to_string(),
             target_name: package_name,
-            bin: false,
+            kind: SourceKind::Lib,
+            inferred: true,
         }
     }
 }
 
+/// Returns the kinds of targets which have to be listed explicitly in the
+/// manifest.
+///
+/// That's the case if inference would pick up files which aren't targets,
+/// or if some target of the kind needs a section anyway, as with the 2015
+/// edition that disables the inference of all other targets of the kind.
+/// Only binaries and examples need a `main` function, and only binaries are
+/// found outside of their usual directories, so tests and benchmarks can
+/// always be inferred.
+fn explicit_kinds(
+    detected_files: &[SourceFileInformation],
+    skipped_files: &[SourceFileInformation],
+) -> Vec<SourceKind> {
+    let kinds = [SourceKind::Bin, SourceKind::Example];
+    kinds
+        .iter()
+        .cloned()
+        .filter(|&kind| {
+            let targets = detected_files.iter().filter(|i| i.kind == kind);
+            skipped_files.iter().any(|i| i.kind == kind)
+                || (targets.clone().any(|i| !i.inferred) && targets.count() > 1)
+        })
+        .collect()
+}
+
 pub fn new(opts: &NewOptions, config: &Config) -> CargoResult<()> {
     let path = &opts.path;
     if fs::metadata(path).is_ok() {
@@ -329,6 +501,7 @@ pub fn new(opts: &NewOptions, config: &Config) -> CargoResult<()> {
         path,
         name,
         source_files: vec![plan_new_source_file(opts.kind.is_bin(), name.to_string())],
+        explicit_kinds: Vec::new(),
         bin: opts.kind.is_bin(),
         edition: opts.edition.as_ref().map(|s| &**s),
         registry: opts.registry.as_ref().map(|s| &**s),
@@ -355,10 +528,17 @@ pub fn init(opts: &NewOptions, config: &Config) -> CargoResult<()> {
     check_name(name, opts)?;
 
     let mut src_paths_types = vec![];
+    let mut skipped_paths = vec![];
 
-    detect_source_paths_and_types(path, name, &mut src_paths_types)?;
+    detect_source_paths_and_types(path, name, &mut src_paths_types, &mut skipped_paths)?;
+    let detected = src_paths_types.len();
+    let explicit_kinds = explicit_kinds(&src_paths_types, &skipped_paths);
 
-    if src_paths_types.is_empty() {
+    if !src_paths_types
+        .iter()
+        .any(|x| x.kind == SourceKind::Lib || x.kind == SourceKind::Bin)
+    {
+        // Examples, tests and benchmarks alone don't make a package.
         src_paths_types.push(plan_new_source_file(opts.kind.is_bin(), name.to_string()));
     } else {
         // --bin option may be ignored if lib.rs or src/lib.rs present
@@ -406,8 +586,9 @@ pub fn init(opts: &NewOptions, config: &Config) -> CargoResult<()> {
         version_control,
         path,
         name,
-        bin: src_paths_types.iter().any(|x| x.bin),
+        bin: src_paths_types.iter().any(|x| x.is_bin()),
         source_files: src_paths_types,
+        explicit_kinds,
         edition: opts.edition.as_ref().map(|s| &**s),
         registry: opts.registry.as_ref().map(|s| &**s),
     };
@@ -419,6 +600,38 @@ pub fn init(opts: &NewOptions, config: &Config) -> CargoResult<()> {
             path.display()
         )
     })?;
+
+    let mut shell = config.shell();
+    for i in &mkopts.source_files[..detected] {
+        shell.status(
+            "Detected",
+            format!(
+                "{} target `{}` at `{}`",
+                i.kind.description(),
+                i.target_name,
+                i.relative_path
+            ),
+        )?;
+    }
+    for i in &skipped_paths {
+        shell.status(
+            "Skipped",
+            format!(
+                "`{}` as it has no `main` function, it is not {} target",
+                i.relative_path,
+                i.kind.description_with_article()
+            ),
+        )?;
+    }
+    for kind in &mkopts.explicit_kinds {
+        shell.status(
+            "Listed",
+            format!(
+                "all {} targets explicitly, as they can't be inferred",
+                kind.description()
+            ),
+        )?;
+    }
     Ok(())
 }
 
@@ -507,23 +720,29 @@ fn mk(config: &Config, opts: &MkOptions) -> CargoResult<()> {
     };
 
     let mut cargotoml_path_specifier = String::new();
+    let mut autodiscover_specifier = String::new();
+
+    for kind in &opts.explicit_kinds {
+        autodiscover_specifier.push_str(&format!("{} = false\n", kind.auto_key()));
+    }
 
     // Calculate what [lib] and [[bin]]s do we need to append to Cargo.toml
 
     for i in &opts.source_files {
-        if i.bin {
-            if i.relative_path != "src/main.rs" {
+        if i.kind != SourceKind::Lib {
+            if !i.inferred || opts.explicit_kinds.contains(&i.kind) {
                 cargotoml_path_specifier.push_str(&format!(
                     r#"
-[[bin]]
+[[{}]]
 name = "{}"
 path = {}
 "#,
+                    i.kind.section(),
                     i.target_name,
                     toml::Value::String(i.relative_path.clone())
                 ));
             }
-        } else if i.relative_path != "src/lib.rs" {
+        } else if !i.inferred {
             cargotoml_path_specifier.push_str(&format!(
                 r#"
 [lib]
@@ -546,7 +765,7 @@ name = "{}"
 version = "0.1.0"
 authors = [{}]
 edition = {}
-{}
+{}{}{}
 [dependencies]
 {}"#,
             name,
@@ -555,6 +774,10 @@ edition = {}
                 Some(edition) => toml::Value::String(edition.to_string()),
                 None => toml::Value::String("2018".to_string()),
             },
+            match cfg.license {
+                Some(license) => format!("license = {}\n", toml::Value::String(license)),
+                None => "".to_string(),
+            },
             match opts.registry {
                 Some(registry) => format!(
                     "publish = {}\n",
@@ -562,6 +785,7 @@ edition = {}
                 ),
                 None => "".to_string(),
             },
+            autodiscover_specifier,
             cargotoml_path_specifier
         )
         .as_bytes(),
@@ -578,7 +802,7 @@ edition = {}
             fs::create_dir_all(src_dir)?;
         }
 
-        let default_file_content: &[u8] = if i.bin {
+        let default_file_content: &[u8] = if i.is_bin() {
             b"\
 fn main() {
     println!(\"Hello, world!\");
@@ -604,6 +828,26 @@ mod tests {
         }
     }
 
+    // Copy the files from the `cargo-new.files` configuration, without
+    // clobbering any which exist already.
+
+    for (dest, src) in &cfg.files {
+        let dest = path.join(dest);
+        if dest.exists() {
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(src, &dest).chain_err(|| {
+            format!(
+                "failed to copy `{}` to `{}`, as configured by `cargo-new.files`",
+                src.display(),
+                dest.display()
+            )
+        })?;
+    }
+
     if let Err(e) = Workspace::new(&path.join("Cargo.toml"), config) {
         let msg = format!(
             "compiling this new crate may not work due to invalid \
@@ -687,6 +931,7 @@ fn global_config(config: &Config) -> CargoResult<CargoNewConfig> {
         Some(("git", _)) => Some(VersionControl::Git),
         Some(("hg", _)) => Some(VersionControl::Hg),
         Some(("pijul", _)) => Some(VersionControl::Pijul),
+        Some(("fossil", _)) => Some(VersionControl::Fossil),
         Some(("none", _)) => Some(VersionControl::NoVcs),
         Some((s, p)) => {
             return Err(internal(format!(
@@ -698,9 +943,62 @@ fn global_config(config: &Config) -> CargoResult<CargoNewConfig> {
         }
         None => None,
     };
+    let license = config.get_string("cargo-new.license")?.map(|s| s.val);
+
+    let mut files = Vec::new();
+    if let Some(table) = config.get_table("cargo-new.files")? {
+        for (dest, value) in table.val {
+            let key = format!("cargo-new.files.{}", dest);
+            let (s, path) = value.string(&key)?;
+            if Path::new(&dest).is_absolute() {
+                bail!(
+                    "invalid configuration for key `{}`, the destination \
+                     must be a relative path (found in {})",
+                    key,
+                    path.display()
+                )
+            }
+            let mut path = path.to_path_buf();
+            path.pop();
+            path.pop();
+            path.push(s);
+            files.push((dest, path));
+        }
+    }
+    files.sort();
+
     Ok(CargoNewConfig {
         name,
         email,
         version_control: vcs,
+        license,
+        files,
     })
 }
+
+/// The `cargo-new.kind` and `cargo-new.edition` defaults.
+fn config_defaults(config: &Config) -> CargoResult<(Option<NewProjectKind>, Option<String>)> {
+    let kind = config.get_string("cargo-new.kind")?;
+    let kind = match kind.as_ref().map(|p| (&p.val[..], &p.definition)) {
+        Some(("bin", _)) => Some(NewProjectKind::Bin),
+        Some(("lib", _)) => Some(NewProjectKind::Lib),
+        Some((s, p)) => bail!(
+            "invalid configuration for key `cargo-new.kind`, \
+             expected `bin` or `lib`, found `{}` (found in {})",
+            s,
+            p
+        ),
+        None => None,
+    };
+
+    let edition = config.get_string("cargo-new.edition")?;
+    if let Some(ref edition) = edition {
+        edition.val.parse::<Edition>().chain_err(|| {
+            format!(
+                "invalid configuration for key `cargo-new.edition` (found in {})",
+                edition.definition
+            )
+        })?;
+    }
+    Ok((kind, edition.map(|s| s.val)))
+}
@@ -1,11 +1,13 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use serde::ser;
 
+use crate::core::compiler::{BuildConfig, CompileMode, Kind, TargetConfig, TargetInfo};
 use crate::core::resolver::Resolve;
-use crate::core::{Package, PackageId, Workspace};
+use crate::core::{IndexFields, Package, PackageId, Workspace};
 use crate::ops::{self, Packages};
-use crate::util::CargoResult;
+use crate::util::{machine_message, CargoResult};
 
 const VERSION: u32 = 1;
 
@@ -15,6 +17,11 @@ pub struct OutputMetadataOptions {
     pub all_features: bool,
     pub no_deps: bool,
     pub version: u32,
+    /// Whether to tell about the platforms of the host and the targets, and
+    /// their cfg values and tools.
+    pub target_info: bool,
+    /// The targets to tell about, the host if there are none.
+    pub targets: Vec<String>,
 }
 
 /// Loads the manifest, resolves the dependencies of the package to the concrete
@@ -28,11 +35,52 @@ pub fn output_metadata(ws: &Workspace, opt: &OutputMetadataOptions) -> CargoResu
             VERSION
         );
     }
-    if opt.no_deps {
-        metadata_no_deps(ws, opt)
+    let mut info = if opt.no_deps {
+        metadata_no_deps(ws, opt)?
     } else {
-        metadata_full(ws, opt)
+        metadata_full(ws, opt)?
+    };
+    if opt.target_info {
+        info.target_info = Some(target_info(ws, &opt.targets)?);
     }
+    Ok(info)
+}
+
+/// The platforms of the host, which build scripts and procedural macros are
+/// compiled for, and of the targets `targets`, with their cfg values.
+fn target_info(
+    ws: &Workspace,
+    targets: &[String],
+) -> CargoResult<Vec<machine_message::TargetInfo>> {
+    let config = ws.config();
+    let rustc = config.rustc(Some(ws))?;
+    let build_config = BuildConfig::new(config, None, targets, CompileMode::Build)?;
+    let mut kinds = vec![Kind::Host];
+    kinds.extend(build_config.target_kinds());
+    let mut infos = Vec::new();
+    for kind in kinds {
+        let info = TargetInfo::new(config, &build_config.requested_targets, &rustc, kind)?;
+        let triple = match kind {
+            Kind::Target(Some(triple)) => triple.as_str(),
+            _ => &rustc.host,
+        };
+        let target_config = TargetConfig::new(config, triple)?;
+        let linker = target_config
+            .linker
+            .unwrap_or_else(|| PathBuf::from(&info.default_linker));
+        let ar = target_config
+            .ar
+            .unwrap_or_else(|| PathBuf::from(&info.default_ar));
+        infos.push(machine_message::TargetInfo::new(
+            kind,
+            triple,
+            info.cfg().unwrap_or(&[]),
+            &linker,
+            &ar,
+            info.llvm_tools_dir.as_ref().map(|p| p.as_ref()),
+        ));
+    }
+    Ok(infos)
 }
 
 fn metadata_no_deps(ws: &Workspace, _opt: &OutputMetadataOptions) -> CargoResult<ExportInfo> {
@@ -43,6 +91,7 @@ fn metadata_no_deps(ws: &Workspace, _opt: &OutputMetadataOptions) -> CargoResult
         target_directory: ws.target_dir().display().to_string(),
         version: VERSION,
         workspace_root: ws.root().display().to_string(),
+        target_info: None,
     })
 }
 
@@ -71,6 +120,7 @@ fn metadata_full(ws: &Workspace, opt: &OutputMetadataOptions) -> CargoResult<Exp
         target_directory: ws.target_dir().display().to_string(),
         version: VERSION,
         workspace_root: ws.root().display().to_string(),
+        target_info: None,
     })
 }
 
@@ -82,6 +132,8 @@ pub struct ExportInfo {
     target_directory: String,
     version: u32,
     workspace_root: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_info: Option<Vec<machine_message::TargetInfo>>,
 }
 
 /// Newtype wrapper to provide a custom `Serialize` implementation.
@@ -113,6 +165,8 @@ where
         dependencies: Vec<PackageId>,
         deps: Vec<Dep>,
         features: Vec<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        index_fields: Option<&'a IndexFields>,
     }
 
     s.collect_seq(resolve.iter().map(|id| {
@@ -131,6 +185,7 @@ where
                 })
                 .collect(),
             features: resolve.features_sorted(id),
+            index_fields: resolve.index_fields(id),
         }
     }))
 }
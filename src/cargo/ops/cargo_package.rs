@@ -109,10 +109,79 @@ pub fn package(ws: &Workspace, opts: &PackageOpts) -> CargoResult<Option<FileLoc
         let dst_path = dst.parent().join(&filename);
         fs::rename(&src_path, &dst_path)
             .chain_err(|| "failed to move temporary tarball into final location")?;
+        sign(pkg, &dst_path, opts)?;
     }
     Ok(Some(dst))
 }
 
+/// Returns the path of the detached signature written next to `pkg`'s
+/// tarball when package signing is enabled.
+pub fn signature_path(ws: &Workspace, pkg: &Package) -> PathBuf {
+    ws.target_dir()
+        .join("package")
+        .into_path_unlocked()
+        .join(format!("{}-{}.crate.sig", pkg.name(), pkg.version()))
+}
+
+/// Looks up the signing command configured for the registry being packaged
+/// for, either `registries.<name>.sign-command` or `registry.sign-command`.
+fn sign_command(
+    config: &Config,
+    registry: &Option<String>,
+) -> CargoResult<Option<(PathBuf, Vec<String>)>> {
+    if !config.cli_unstable().package_signing {
+        return Ok(None);
+    }
+    let key = match *registry {
+        Some(ref registry) => format!("registries.{}.sign-command", registry),
+        None => "registry.sign-command".to_string(),
+    };
+    Ok(config.get_path_and_args(&key)?.map(|v| v.val))
+}
+
+// Run the configured signing command with the path of the finished tarball
+// as its last argument, and store whatever it prints to stdout as the
+// detached signature. Any signature left over from a previous run is removed
+// first so it can never be mistaken for one of the new tarball.
+fn sign(pkg: &Package, tarball: &Path, opts: &PackageOpts) -> CargoResult<()> {
+    let config = opts.config;
+    let sig_path = tarball.with_file_name(format!(
+        "{}.sig",
+        tarball.file_name().unwrap().to_string_lossy()
+    ));
+    if sig_path.exists() {
+        paths::remove_file(&sig_path)?;
+    }
+    let (program, args) = match sign_command(config, &opts.registry)? {
+        Some(cmd) => cmd,
+        None => return Ok(()),
+    };
+    config
+        .shell()
+        .status("Signing", pkg.package_id().to_string())?;
+    let output = util::process(&program)
+        .args(&args)
+        .arg(tarball)
+        .cwd(pkg.root())
+        .exec_with_output()
+        .chain_err(|| format!("failed to sign package `{}`", pkg.package_id()))?;
+    let signature = match String::from_utf8(output.stdout) {
+        Ok(ref s) if s.trim().is_empty() => bail!(
+            "signing command `{}` did not produce a signature for `{}`",
+            program.display(),
+            pkg.package_id()
+        ),
+        Ok(s) => s,
+        Err(_) => bail!(
+            "signing command `{}` printed a binary signature for `{}`, \
+             registries require a textual (e.g. ASCII-armored) signature",
+            program.display(),
+            pkg.package_id()
+        ),
+    };
+    paths::write(&sig_path, signature.as_bytes())
+}
+
 fn include_lockfile(pkg: &Package) -> bool {
     pkg.manifest().publish_lockfile() && pkg.targets().iter().any(|t| t.is_example() || t.is_bin())
 }
@@ -221,23 +290,63 @@ fn check_repo_state(
         allow_dirty: bool,
     ) -> CargoResult<Option<String>> {
         let workdir = repo.workdir().unwrap();
-        let dirty = src_files
-            .iter()
-            .filter(|file| {
-                let relative = file.strip_prefix(workdir).unwrap();
-                if let Ok(status) = repo.status_file(relative) {
-                    status != git2::Status::CURRENT
-                } else {
-                    false
-                }
-            })
-            .map(|path| {
-                path.strip_prefix(p.root())
-                    .unwrap_or(path)
-                    .display()
-                    .to_string()
-            })
-            .collect::<Vec<_>>();
+        let display = |path: &Path| {
+            path.strip_prefix(p.root())
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+
+        // Files in submodules have to be checked against the submodule's own
+        // repository, the superproject only knows which commit it's at.
+        let mut submodules = Vec::new();
+        let mut dirty = Vec::new();
+        for submodule in repo.submodules()? {
+            let sub_repo = match submodule.open() {
+                Ok(sub_repo) => sub_repo,
+                Err(..) => continue,
+            };
+            let path = workdir.join(submodule.path());
+            if path.starts_with(p.root()) && submodule.head_id() != submodule.workdir_id() {
+                dirty.push(format!(
+                    "{} (submodule checked out at a different commit)",
+                    display(&path)
+                ));
+            }
+            submodules.push(sub_repo);
+        }
+
+        let mut sparse = Vec::new();
+        for file in src_files {
+            let repo = submodules
+                .iter()
+                .find(|r| r.workdir().map_or(false, |w| file.starts_with(w)))
+                .unwrap_or(repo);
+            let relative = file.strip_prefix(repo.workdir().unwrap()).unwrap();
+            let status = match repo.status_file(relative) {
+                Ok(status) => status,
+                Err(..) => continue,
+            };
+            if status == git2::Status::CURRENT {
+                continue;
+            }
+            // Files left out of a sparse checkout are still in the index,
+            // but look like they've been deleted.
+            if status == git2::Status::WT_DELETED && is_skip_worktree(repo, relative)? {
+                sparse.push(display(file));
+            } else {
+                dirty.push(display(file));
+            }
+        }
+        if !sparse.is_empty() {
+            bail!(
+                "{} files of the package are not checked out because of a git \
+                 sparse checkout:\n\n{}\n\n\
+                 they have to be checked out for the package to be complete",
+                sparse.len(),
+                sparse.join("\n")
+            )
+        }
         if dirty.is_empty() {
             let rev_obj = repo.revparse_single("HEAD")?;
             Ok(Some(rev_obj.id().to_string()))
@@ -254,6 +363,14 @@ fn check_repo_state(
             Ok(None)
         }
     }
+
+    fn is_skip_worktree(repo: &git2::Repository, relative: &Path) -> CargoResult<bool> {
+        use libgit2_sys::GIT_IDXENTRY_SKIP_WORKTREE;
+        let index = repo.index()?;
+        Ok(index.get_path(relative, 0).map_or(false, |entry| {
+            u32::from(entry.flags_extended) & GIT_IDXENTRY_SKIP_WORKTREE as u32 != 0
+        }))
+    }
 }
 
 // Check for and `bail!` if a source file matches ROOT/VCS_INFO_FILE, since
@@ -447,7 +564,12 @@ fn run_verify(ws: &Workspace, tar: &FileLock, opts: &PackageOpts) -> CargoResult
         None,
         &ops::CompileOptions {
             config,
-            build_config: BuildConfig::new(config, opts.jobs, &opts.target, CompileMode::Build)?,
+            build_config: BuildConfig::new(
+                config,
+                opts.jobs,
+                &opts.target.iter().cloned().collect::<Vec<_>>(),
+                CompileMode::Build,
+            )?,
             features: Vec::new(),
             no_default_features: false,
             all_features: false,
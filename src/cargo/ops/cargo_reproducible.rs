@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str;
+
+use tempfile::Builder as TempFileBuilder;
+
+use crate::core::Workspace;
+use crate::ops::{self, CompileOptions};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, Filesystem};
+
+/// The path the target directories of both builds are remapped to.
+const REMAPPED_TARGET_DIR: &str = "/cargo-target";
+
+/// Builds with `options` twice, for `cargo build --assert-reproducible`, and
+/// fails if the artifacts of the two builds differ.
+///
+/// The first build is a full rebuild in the target directory of `ws`, the
+/// second one in a temporary target directory, with the paths of both target
+/// directories remapped to the same one in the artifacts. The libraries,
+/// binaries and build scripts built are then compared byte for byte, and
+/// for each one which differs the first difference is reported, with the
+/// member of the archive and the ELF section it's in when there are any. The
+/// temporary target directory is kept if they differ.
+pub fn assert_reproducible(
+    ws: &mut Workspace<'_>,
+    options: &mut CompileOptions<'_>,
+) -> CargoResult<()> {
+    let config = options.config;
+    let target_dir = ws.target_dir();
+    let td = TempFileBuilder::new()
+        .prefix("cargo-reproducible")
+        .tempdir()
+        .chain_err(|| "failed to create a temporary target directory")?;
+
+    options.build_config.force_rebuild = true;
+    let remap_args = options.build_config.extra_rustc_args.len();
+    let target_path = target_dir.clone().into_path_unlocked();
+    for dir in &[&*target_path, td.path()] {
+        options.build_config.extra_rustc_args.truncate(remap_args);
+        options.build_config.extra_rustc_args.push(format!(
+            "--remap-path-prefix={}={}",
+            dir.display(),
+            REMAPPED_TARGET_DIR
+        ));
+        if *dir == td.path() {
+            config.shell().status(
+                "Rebuilding",
+                format!("in `{}` to compare the artifacts", dir.display()),
+            )?;
+            ws.set_target_dir(Filesystem::new(dir.to_path_buf()));
+        }
+        ops::compile(ws, options)?;
+    }
+    ws.set_target_dir(target_dir);
+
+    let mut artifacts = Vec::new();
+    list_artifacts(td.path(), Path::new(""), &mut artifacts)?;
+    artifacts.sort();
+    let mut differences = Vec::new();
+    for artifact in artifacts.iter() {
+        let first = paths::read_bytes(&target_path.join(artifact))?;
+        let second = paths::read_bytes(&td.path().join(artifact))?;
+        if let Some(difference) = first_difference(&first, &second) {
+            differences.push(format!("  {}: {}", artifact.display(), difference));
+        }
+    }
+    if differences.is_empty() {
+        config.shell().status(
+            "Reproducible",
+            format!(
+                "the {} artifacts of both builds are identical",
+                artifacts.len()
+            ),
+        )?;
+        return Ok(());
+    }
+    let td = td.into_path();
+    bail!(
+        "the build isn't reproducible, {} of its {} artifacts differ between \
+         `{}` and `{}`:\n{}",
+        differences.len(),
+        artifacts.len(),
+        target_path.display(),
+        td.display(),
+        differences.join("\n")
+    )
+}
+
+/// The artifacts in the target directory `root`, relative to it: the files in
+/// `deps` and `examples` directories, except dep-info files, and the build
+/// scripts in `build`. Their outputs and the files uplifted from `deps`
+/// aren't compared.
+fn list_artifacts(root: &Path, dir: &Path, artifacts: &mut Vec<PathBuf>) -> CargoResult<()> {
+    let path = root.join(dir);
+    let entries =
+        fs::read_dir(&path).chain_err(|| format!("failed to read `{}`", path.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let rel = dir.join(&*name);
+        if entry.file_type()?.is_dir() {
+            if !name.starts_with('.') && name != "incremental" && name != "out" {
+                list_artifacts(root, &rel, artifacts)?;
+            }
+            continue;
+        }
+        let parent = dir.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        let grandparent = dir
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|s| s.to_str())
+            .unwrap_or("");
+        let is_artifact = parent == "deps"
+            || parent == "examples"
+            || (grandparent == "build" && name.starts_with("build-script-"));
+        if is_artifact && !name.ends_with(".d") {
+            artifacts.push(rel);
+        }
+    }
+    Ok(())
+}
+
+/// Describes the first difference between the artifacts `a` and `b`, if
+/// they differ.
+fn first_difference(a: &[u8], b: &[u8]) -> Option<String> {
+    let offset = match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(offset) => offset,
+        None if a.len() == b.len() => return None,
+        None => a.len().min(b.len()),
+    };
+    let mut description = format!("differs at byte {}", offset);
+    if a.len() != b.len() {
+        description.push_str(&format!(" (of {} and {} bytes)", a.len(), b.len()));
+    }
+    let (data, offset) = match ar_member(a, offset) {
+        Some((name, start, member)) => {
+            if offset < start {
+                description.push_str(&format!(", in the header of member `{}`", name));
+                return Some(description);
+            }
+            description.push_str(&format!(", in member `{}`", name));
+            (member, offset - start)
+        }
+        None => (a, offset),
+    };
+    if let Some(section) = elf_section(data, offset) {
+        description.push_str(&format!(", in {}", section));
+    }
+    Some(description)
+}
+
+/// The name, the start of the data and the data of the member of the `ar`
+/// archive `data`, like an rlib or a static library, whose header or data
+/// contains `offset`.
+fn ar_member(data: &[u8], offset: usize) -> Option<(String, usize, &[u8])> {
+    if !data.starts_with(b"!<arch>\n") {
+        return None;
+    }
+    let mut long_names: &[u8] = &[];
+    let mut pos = 8;
+    while pos + 60 <= data.len() {
+        let header = &data[pos..pos + 60];
+        let size = str::from_utf8(&header[48..58])
+            .ok()?
+            .trim()
+            .parse::<usize>()
+            .ok()?;
+        let start = pos + 60;
+        let end = start.checked_add(size)?.min(data.len());
+        let raw = str::from_utf8(&header[..16]).ok()?.trim_end();
+        let (name, data_start) = if raw == "/" || raw == "/SYM64/" || raw == "//" {
+            // The GNU symbol table, or table of the names longer than 15
+            // bytes.
+            if raw == "//" {
+                long_names = &data[start..end];
+            }
+            (raw.to_string(), start)
+        } else if raw.starts_with("#1/") {
+            // A BSD name of this length following the header.
+            let len = raw[3..].parse::<usize>().ok()?;
+            let name = data.get(start..start + len)?;
+            let name = String::from_utf8_lossy(name);
+            (name.trim_end_matches('\0').to_string(), start + len)
+        } else if raw.len() > 1
+            && raw.starts_with('/')
+            && raw[1..].bytes().all(|b| b.is_ascii_digit())
+        {
+            // A GNU name at this offset in the table of long names.
+            let name = long_names.get(raw[1..].parse::<usize>().ok()?..)?;
+            let name = name.split(|&b| b == b'\n').next()?;
+            let name = String::from_utf8_lossy(name);
+            (name.trim_end_matches('/').to_string(), start)
+        } else {
+            (raw.trim_end_matches('/').to_string(), start)
+        };
+        if offset >= pos && offset < end {
+            return Some((name, data_start, &data[data_start..end]));
+        }
+        // Members are aligned to 2 bytes.
+        pos = end + (end & 1);
+    }
+    None
+}
+
+/// Describes the section of the ELF object `data` containing `offset`.
+fn elf_section(data: &[u8], offset: usize) -> Option<String> {
+    if !data.starts_with(b"\x7fELF") || data.len() < 0x40 {
+        return None;
+    }
+    let is_64 = data[4] == 2;
+    let is_le = data[5] == 1;
+    let read = |at: u64, len: u64| -> Option<u64> {
+        let bytes = data.get(at as usize..(at + len) as usize)?;
+        let mut value = 0;
+        for i in 0..bytes.len() {
+            let byte = if is_le {
+                bytes[bytes.len() - 1 - i]
+            } else {
+                bytes[i]
+            };
+            value = (value << 8) | u64::from(byte);
+        }
+        Some(value)
+    };
+    let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+        (
+            read(0x28, 8)?,
+            read(0x3a, 2)?,
+            read(0x3c, 2)?,
+            read(0x3e, 2)?,
+        )
+    } else {
+        (
+            read(0x20, 4)?,
+            read(0x2e, 2)?,
+            read(0x30, 2)?,
+            read(0x32, 2)?,
+        )
+    };
+    // The name, type, offset and size of a section.
+    let section = |i: u64| -> Option<(u64, u64, u64, u64)> {
+        let at = shoff + i * shentsize;
+        if is_64 {
+            Some((
+                read(at, 4)?,
+                read(at + 4, 4)?,
+                read(at + 0x18, 8)?,
+                read(at + 0x20, 8)?,
+            ))
+        } else {
+            Some((
+                read(at, 4)?,
+                read(at + 4, 4)?,
+                read(at + 0x10, 4)?,
+                read(at + 0x14, 4)?,
+            ))
+        }
+    };
+    let offset = offset as u64;
+    if offset < if is_64 { 0x40 } else { 0x34 } {
+        return Some("the ELF header".to_string());
+    }
+    if offset >= shoff && offset < shoff + shnum * shentsize {
+        return Some("the section headers".to_string());
+    }
+    let (_, _, strtab, _) = section(shstrndx)?;
+    for i in 0..shnum {
+        let (name, kind, start, size) = section(i)?;
+        // Sections of type `SHT_NOBITS`, like `.bss`, take no room in the
+        // file.
+        if kind == 8 || offset < start || offset >= start + size {
+            continue;
+        }
+        let name = data.get((strtab + name) as usize..)?;
+        let name = name.split(|&b| b == 0).next()?;
+        return Some(format!("section `{}`", String::from_utf8_lossy(name)));
+    }
+    None
+}
@@ -1,9 +1,11 @@
 use std::iter;
 use std::path::Path;
 
-use crate::ops;
-use crate::util::{self, CargoResult, ProcessError};
-use crate::core::{TargetKind, Workspace, nightly_features_allowed};
+use failure::Error;
+
+use crate::ops::{self, CompileFilter, FilterRule};
+use crate::util::{self, lev_distance, paths, CargoResult, ProcessError};
+use crate::core::{Package, Target, TargetKind, Workspace, nightly_features_allowed};
 
 pub fn run(
     ws: &Workspace,
@@ -16,7 +18,8 @@ pub fn run(
     // packages to be run is determined by the `ops::compile` call below.
     let packages = options.spec.get_packages(ws)?;
     let bins: Vec<_> = packages
-        .into_iter()
+        .iter()
+        .cloned()
         .flat_map(|pkg| {
             iter::repeat(pkg).zip(pkg.manifest().targets().iter().filter(|target| {
                 !target.is_lib() && !target.is_custom_build() && if !options.filter.is_specific() {
@@ -30,8 +33,17 @@ pub fn run(
     if bins.is_empty() {
         if !options.filter.is_specific() {
             bail!("a bin target must be available for `cargo run`")
-        } else {
-            // this will be verified in cargo_compile
+        } else if let CompileFilter::Only {
+            ref bins,
+            ref examples,
+            ..
+        } = options.filter
+        {
+            // Names which don't exist at all would be reported by
+            // cargo_compile, but here the targets which do exist are listed
+            // along with the error.
+            check_target_names(&packages, bins, "bin", Target::is_bin)?;
+            check_target_names(&packages, examples, "example", Target::is_example)?;
         }
     }
 
@@ -86,7 +98,7 @@ pub fn run(
         None => exe.to_path_buf(),
     };
     let pkg = bins[0].0;
-    let mut process = compile.target_process(exe, pkg)?;
+    let mut process = compile.target_process(exe, compile.target_kind, pkg)?;
     process.args(args).cwd(config.cwd());
 
     config.shell().status("Running", process.to_string())?;
@@ -101,3 +113,104 @@ pub fn run(
         }
     }
 }
+
+fn check_target_names(
+    packages: &[&Package],
+    rule: &FilterRule,
+    flag: &str,
+    is_expected_kind: fn(&Target) -> bool,
+) -> CargoResult<()> {
+    let names = match *rule {
+        FilterRule::Just(ref names) => names,
+        FilterRule::All => return Ok(()),
+    };
+    for name in names {
+        let exists = packages
+            .iter()
+            .flat_map(|pkg| pkg.targets())
+            .any(|t| t.name() == name && is_expected_kind(t));
+        if !exists {
+            return Err(missing_target_error(packages, flag, Some(name)));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the error for `--bin` or `--example` given either without a name
+/// or with the name of a target which doesn't exist, listing the targets
+/// `cargo run` can run instead.
+pub fn missing_target_error(packages: &[&Package], flag: &str, name: Option<&str>) -> Error {
+    let (desc, is_expected_kind): (_, fn(&Target) -> bool) = match flag {
+        "bin" => ("binaries", Target::is_bin),
+        _ => ("examples", Target::is_bin_example),
+    };
+    let mut targets = packages
+        .iter()
+        .flat_map(|pkg| pkg.targets())
+        .filter(|t| is_expected_kind(t))
+        .collect::<Vec<_>>();
+    targets.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let mut msg = match name {
+        Some(name) => {
+            let mut msg = format!("no {} target named `{}`", flag, name);
+            let suggestion = targets
+                .iter()
+                .map(|t| (lev_distance(name, t.name()), t))
+                .filter(|&(d, _)| d < 4)
+                .min_by_key(|t| t.0);
+            if let Some((_, t)) = suggestion {
+                msg.push_str(&format!("\n\nDid you mean `{}`?", t.name()));
+            }
+            msg
+        }
+        None => format!("`--{}` requires the name of the target to run", flag),
+    };
+    if targets.is_empty() {
+        msg.push_str(&format!("\n\nThere are no {} available.", desc));
+        return format_err!("{}", msg);
+    }
+
+    msg.push_str(&format!("\n\nAvailable {}:", desc));
+    let width = targets.iter().map(|t| t.name().len()).max().unwrap_or(0);
+    for target in targets {
+        let mut notes = Vec::new();
+        if let Some(doc) = leading_comment(target.src_path().path()) {
+            notes.push(doc);
+        }
+        if let Some(features) = target.required_features() {
+            let features = features
+                .iter()
+                .map(|f| format!("`{}`", f))
+                .collect::<Vec<_>>();
+            notes.push(format!("(required features: {})", features.join(", ")));
+        }
+        let name = format!("{:<1$}", target.name(), width);
+        let line = format!("\n    {}    {}", name, notes.join(" "));
+        msg.push_str(line.trim_end());
+    }
+    format_err!("{}", msg)
+}
+
+/// The first line of the comment a source file starts with, if any, which
+/// usually describes what a binary or example does.
+fn leading_comment(path: &Path) -> Option<String> {
+    let contents = paths::read(path).ok()?;
+    let line = contents
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with("#!"))?;
+    let doc = if line.starts_with("//!") {
+        &line[3..]
+    } else if line.starts_with("//") && !line.starts_with("///") {
+        &line[2..]
+    } else {
+        return None;
+    };
+    let doc = doc.trim();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc.to_string())
+    }
+}
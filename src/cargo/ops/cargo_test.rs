@@ -1,15 +1,28 @@
+use std::collections::BTreeMap;
 use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::core::compiler::{Compilation, Doctest};
-use crate::core::Workspace;
+use tempfile::{Builder as TempFileBuilder, TempDir};
+
+use crate::core::compiler::{BuildEnv, Compilation, CompileMode, Doctest, Kind};
+use crate::core::{Package, Workspace};
 use crate::ops;
-use crate::util::errors::CargoResult;
-use crate::util::{self, CargoTestError, ProcessError, Test};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::machine_message::{self, TestExecutable};
+use crate::util::{
+    self, internal, paths, CargoTestError, Config, ProcessBuilder, ProcessError, Test,
+};
 
 pub struct TestOptions<'a> {
     pub compile_opts: ops::CompileOptions<'a>,
     pub no_run: bool,
     pub no_fail_fast: bool,
+    /// Print how to run each test executable instead of running them.
+    pub run_manifest: bool,
+    /// Run the test executables in an order shuffled with this seed.
+    pub shuffle: Option<u64>,
 }
 
 pub fn run_tests(
@@ -20,16 +33,20 @@ pub fn run_tests(
     let compilation = compile_tests(ws, options)?;
 
     if options.no_run {
+        if options.run_manifest {
+            print_run_manifest(options, &compilation)?;
+        }
         return Ok(None);
     }
-    let (test, mut errors) = run_unit_tests(options, test_args, &compilation)?;
+    let isolation = Isolation::new(ws)?;
+    let (test, mut errors) = run_unit_tests(options, test_args, &compilation, &isolation)?;
 
     // If we have an error and want to fail fast, return
     if !errors.is_empty() && !options.no_fail_fast {
         return Ok(Some(CargoTestError::new(test, errors)));
     }
 
-    let (doctest, docerrors) = run_doc_tests(options, test_args, &compilation)?;
+    let (doctest, docerrors) = run_doc_tests(options, test_args, &compilation, &isolation)?;
     let test = if docerrors.is_empty() { test } else { doctest };
     errors.extend(docerrors);
     if errors.is_empty() {
@@ -49,9 +66,13 @@ pub fn run_benches(
     let compilation = compile_tests(ws, options)?;
 
     if options.no_run {
+        if options.run_manifest {
+            print_run_manifest(options, &compilation)?;
+        }
         return Ok(None);
     }
-    let (test, errors) = run_unit_tests(options, &args, &compilation)?;
+    let isolation = Isolation::new(ws)?;
+    let (test, errors) = run_unit_tests(options, &args, &compilation, &isolation)?;
     match errors.len() {
         0 => Ok(None),
         _ => Ok(Some(CargoTestError::new(test, errors))),
@@ -69,32 +90,216 @@ fn compile_tests<'a>(
     Ok(compilation)
 }
 
+/// Print the executable, working directory and environment needed to run
+/// each of the test executables that were built, so that an external harness
+/// can run them on another device or under an emulator.
+///
+/// This is printed as text on stdout, or as `test-executable` messages if
+/// JSON messages were requested.
+fn print_run_manifest(options: &TestOptions, compilation: &Compilation) -> CargoResult<()> {
+    let json_messages = options.compile_opts.build_config.json_messages();
+    let mode = options.compile_opts.build_config.mode;
+    for &(ref pkg, ref kind, ref test, ref exe, platform) in &compilation.tests {
+        let target = pkg
+            .targets()
+            .iter()
+            .find(|t| t.name() == test && t.kind() == kind)
+            .ok_or_else(|| internal(format!("couldn't find target `{}` of {}", test, pkg)))?;
+        let mut cmd = test_process(options.compile_opts.config, compilation, pkg, exe, platform)?;
+        if mode == CompileMode::Bench {
+            cmd.arg("--bench");
+        }
+        let env = cmd
+            .get_envs()
+            .iter()
+            .filter_map(|(k, v)| {
+                v.as_ref()
+                    .map(|v| (k.clone(), v.to_string_lossy().into_owned()))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        if json_messages {
+            machine_message::emit(&TestExecutable {
+                package_id: pkg.package_id(),
+                target,
+                mode,
+                executable: exe,
+                program: cmd.get_program().to_string_lossy().into_owned(),
+                args: cmd
+                    .get_args()
+                    .iter()
+                    .map(|a| a.to_string_lossy().into_owned())
+                    .collect(),
+                cwd: cmd.get_cwd(),
+                env,
+            });
+            continue;
+        }
+
+        println!("{} {} `{}`", pkg, target.kind().description(), test);
+        println!("  executable: {}", exe.display());
+        if cmd.get_program() != exe.as_os_str() || !cmd.get_args().is_empty() {
+            println!("  command: {}", cmd);
+        }
+        if let Some(cwd) = cmd.get_cwd() {
+            println!("  cwd: {}", cwd.display());
+        }
+        for (k, v) in env.iter() {
+            println!("  env: {}={}", k, v);
+        }
+    }
+    Ok(())
+}
+
+/// Prepares the process running the test executable `exe` of `pkg`.
+///
+/// With `-Z test-metadata` the package may set the working directory,
+/// relative to its root, and extra environment variables for its test
+/// executables in `[package.metadata.test]`:
+///
+/// ```toml
+/// [package.metadata.test]
+/// cwd = "tests/fixtures"
+/// env = { FIXTURES = "1" }
+/// ```
+fn test_process(
+    config: &Config,
+    compilation: &Compilation,
+    pkg: &Package,
+    exe: &Path,
+    kind: Kind,
+) -> CargoResult<ProcessBuilder> {
+    let mut cmd = compilation.target_process(exe, kind, pkg)?;
+    if !config.cli_unstable().test_metadata {
+        return Ok(cmd);
+    }
+    let test = match pkg.manifest().custom_metadata().and_then(|m| m.get("test")) {
+        Some(test) => test,
+        None => return Ok(cmd),
+    };
+    let invalid = |key: &str, expected: &str| {
+        format_err!(
+            "`package.metadata.{}` must be {} in `{}`",
+            key,
+            expected,
+            pkg.manifest_path().display()
+        )
+    };
+    let test = test.as_table().ok_or_else(|| invalid("test", "a table"))?;
+    if let Some(cwd) = test.get("cwd") {
+        let cwd = cwd
+            .as_str()
+            .ok_or_else(|| invalid("test.cwd", "a string"))?;
+        cmd.cwd(pkg.root().join(cwd));
+    }
+    if let Some(env) = test.get("env") {
+        let env = env
+            .as_table()
+            .ok_or_else(|| invalid("test.env", "a table"))?;
+        for (key, value) in env {
+            let value = value
+                .as_str()
+                .ok_or_else(|| invalid(&format!("test.env.{}", key), "a string"))?;
+            cmd.env(key, value);
+        }
+    }
+    Ok(cmd)
+}
+
+/// What keeps the runs of test executables, and of the doctests of each
+/// target, from interfering with each other: every run gets a fresh
+/// temporary directory in `CARGO_TEST_TMPDIR`, removed once it's done, and
+/// the environment configured with `test.env-scrub` and `test.env-allowlist`.
+struct Isolation {
+    /// The directory the temporary directories are made in, `target/tmp`.
+    tmp_root: PathBuf,
+    env: BuildEnv,
+}
+
+impl Isolation {
+    fn new(ws: &Workspace) -> CargoResult<Isolation> {
+        Ok(Isolation {
+            tmp_root: ws.target_dir().into_path_unlocked().join("tmp"),
+            env: BuildEnv::for_tests(ws.config())?,
+        })
+    }
+
+    /// Isolates the run `cmd` of the tests `name`, once it has every
+    /// environment variable cargo sets for it. The temporary directory is
+    /// removed when the returned value is dropped.
+    fn apply(&self, cmd: &mut ProcessBuilder, name: &str) -> CargoResult<TempDir> {
+        paths::create_dir_all(&self.tmp_root)?;
+        let dir = TempFileBuilder::new()
+            .prefix(&format!("{}-", name))
+            .tempdir_in(&self.tmp_root)
+            .chain_err(|| {
+                format!(
+                    "failed to create a temporary directory in `{}`",
+                    self.tmp_root.display()
+                )
+            })?;
+        cmd.env("CARGO_TEST_TMPDIR", dir.path());
+        self.env.apply(cmd);
+        Ok(dir)
+    }
+}
+
 /// Run the unit and integration tests of a package.
 fn run_unit_tests(
     options: &TestOptions,
     test_args: &[String],
     compilation: &Compilation,
+    isolation: &Isolation,
 ) -> CargoResult<(Test, Vec<ProcessError>)> {
     let config = options.compile_opts.config;
     let cwd = options.compile_opts.config.cwd();
 
     let mut errors = Vec::new();
 
-    for &(ref pkg, ref kind, ref test, ref exe) in &compilation.tests {
+    let mut tests = compilation.tests.iter().collect::<Vec<_>>();
+    if let Some(seed) = options.shuffle {
+        config
+            .shell()
+            .status("Shuffling", format!("test binaries with seed {}", seed))?;
+        shuffle(&mut tests, seed);
+    }
+
+    for &(ref pkg, ref kind, ref test, ref exe, platform) in tests {
         let to_display = match util::without_prefix(exe, cwd) {
             Some(path) => path,
             None => &**exe,
         };
-        let mut cmd = compilation.target_process(exe, pkg)?;
+        let mut cmd = test_process(config, compilation, pkg, exe, platform)?;
+        let harness = pkg
+            .targets()
+            .iter()
+            .any(|t| t.name() == test && t.kind() == kind && t.harness());
+        let color_arg = test_args
+            .iter()
+            .any(|arg| arg == "--color" || arg.starts_with("--color="));
+        if let Some(color) = config.shell().stdout_color_choice() {
+            // The test harness writes to stdout, which may want a different
+            // color choice than Cargo's own output.
+            if harness && !color_arg {
+                cmd.arg("--color").arg(color.as_arg());
+            }
+        }
         cmd.args(test_args);
-        config
-            .shell()
-            .concise(|shell| shell.status("Running", to_display.display().to_string()))?;
+        if let Some(seed) = options.shuffle {
+            // Custom harnesses can use this to shuffle the tests themselves.
+            cmd.env("CARGO_TEST_SHUFFLE_SEED", seed.to_string());
+        }
+        config.shell().concise(|shell| {
+            let message = shell.hyperlink(exe, to_display.display());
+            shell.status("Running", message)
+        })?;
+        let tmp = isolation.apply(&mut cmd, &format!("{}-{}", pkg.name(), test))?;
         config
             .shell()
             .verbose(|shell| shell.status("Running", cmd.to_string()))?;
 
         let result = cmd.exec();
+        drop(tmp);
 
         match result {
             Err(e) => {
@@ -126,10 +331,36 @@ fn run_unit_tests(
     }
 }
 
+/// Returns a seed for `--shuffle` when none was given on the command line.
+pub fn random_shuffle_seed() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_secs() ^ u64::from(now.subsec_nanos()) ^ (u64::from(process::id()) << 32)
+}
+
+/// Fisher-Yates shuffle driven by splitmix64, so that a seed produces the
+/// same order on every platform and Cargo version.
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
 fn run_doc_tests(
     options: &TestOptions,
     test_args: &[String],
     compilation: &Compilation,
+    isolation: &Isolation,
 ) -> CargoResult<(Test, Vec<ProcessError>)> {
     let mut errors = Vec::new();
     let config = options.compile_opts.config;
@@ -144,6 +375,7 @@ fn run_doc_tests(
             package,
             target,
             deps,
+            sandbox_dir,
         } = doctest_info;
         config.shell().status("Doc-tests", target.name())?;
         let mut p = compilation.rustdoc_process(package, target)?;
@@ -189,10 +421,18 @@ fn run_doc_tests(
             p.args(flags);
         }
 
+        let tmp = isolation.apply(&mut p, &format!("{}-doc-{}", package.name(), target.name()))?;
+        if let Some(dir) = sandbox_dir {
+            let build_config = &options.compile_opts.build_config;
+            let sandbox = build_config.proc_macro_sandbox.as_ref().unwrap();
+            sandbox.restrict_doctests(config, &build_config.build_env, dir, &mut p)?;
+        }
         config
             .shell()
             .verbose(|shell| shell.status("Running", p.to_string()))?;
-        if let Err(e) = p.exec() {
+        let result = p.exec();
+        drop(tmp);
+        if let Err(e) = result {
             let e = e.downcast::<ProcessError>()?;
             errors.push(e);
             if !options.no_fail_fast {
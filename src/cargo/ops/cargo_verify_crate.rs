@@ -0,0 +1,195 @@
+use std::path::Path;
+
+use git2;
+use semver::Version;
+use serde_json;
+use tempfile::Builder as TempFileBuilder;
+
+use crate::core::source::GitReference;
+use crate::core::{Package, SourceId};
+use crate::ops;
+use crate::sources::git::{self, GitRemote, GitRevision};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, Config, ToUrl};
+
+use super::cargo_diff::{crate_files, download_versions, package_files};
+
+/// Files `cargo package` writes into a package, which aren't expected in its
+/// repository: the normalized manifest, the original one being compared
+/// instead, the commit it was packaged from, and the lock file of its
+/// workspace.
+const GENERATED_FILES: &[&str] = &["Cargo.toml", ".cargo_vcs_info.json", "Cargo.lock"];
+
+pub struct VerifyCrateOptions<'a> {
+    pub config: &'a Config,
+    /// The crate to verify, like `foo@1.2.3`.
+    pub spec: String,
+    /// The registry of the crate, crates.io unless specified.
+    pub registry: Option<String>,
+    /// Whether to compare the crate with the source in its repository.
+    pub against_repo: bool,
+}
+
+/// Downloads a published version of a crate, verifying its checksum, for
+/// `cargo verify-crate`.
+///
+/// With `--against-repo` the repository declared by the crate is cloned, and
+/// the files `cargo package` would include from the package at the tag of
+/// the version, or at the commit the crate records it was packaged from if
+/// there's no such tag, are compared with all the files of the crate, whatever
+/// its manifest includes or excludes. It's an error for a file of the crate
+/// to differ or to be missing in the repository.
+pub fn verify_crate(opts: &VerifyCrateOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    let (name, vers) = match opts.spec.find('@') {
+        Some(i) => (&opts.spec[..i], &opts.spec[i + 1..]),
+        None => bail!(
+            "`{}` doesn't have a version, `cargo verify-crate` requires an exact \
+             version, like `foo@1.2.3`",
+            opts.spec
+        ),
+    };
+    if vers.parse::<Version>().is_err() {
+        bail!(
+            "invalid version `{}` in `{}`, `cargo verify-crate` requires an exact \
+             version, like `foo@1.2.3`",
+            vers,
+            opts.spec
+        );
+    }
+    let pkg = download_versions(
+        config,
+        opts.registry.as_ref().map(|s| s.as_str()),
+        name,
+        &[vers],
+    )?
+    .remove(0);
+    if !opts.against_repo {
+        config
+            .shell()
+            .status("Verified", format!("{} (checksum)", pkg.package_id()))?;
+        return Ok(());
+    }
+
+    let repository = match pkg.manifest().metadata().repository {
+        Some(ref repository) => repository.clone(),
+        None => bail!(
+            "`{}` doesn't declare a `repository` to verify it against",
+            pkg.package_id()
+        ),
+    };
+    let url = repository.to_url()?;
+    let td = TempFileBuilder::new()
+        .prefix("cargo-verify-crate")
+        .tempdir()
+        .chain_err(|| "failed to create a temporary directory")?;
+    config
+        .shell()
+        .status("Fetching", format!("`{}`", repository))?;
+    let db_path = td.path().join("db");
+    let mut repo = git2::Repository::init_bare(&db_path)?;
+    git::fetch(&mut repo, &url, "refs/heads/*:refs/heads/*", config)
+        .chain_err(|| format!("failed to fetch `{}`", repository))?;
+    git::fetch(&mut repo, &url, "refs/tags/*:refs/tags/*", config)
+        .chain_err(|| format!("failed to fetch the tags of `{}`", repository))?;
+
+    let remote = GitRemote::new(&url);
+    let (reference, rev) = revision(&remote, &db_path, &pkg, name, vers)?;
+    let checkout_path = td.path().join("checkout");
+    remote
+        .db_at(&db_path)?
+        .copy_to(rev, &checkout_path, config)?;
+
+    let source_id = SourceId::for_path(&checkout_path)?;
+    let upstream = ops::read_packages(&checkout_path, source_id, config)?
+        .into_iter()
+        .find(|p| p.name().as_str() == name)
+        .ok_or_else(|| {
+            format_err!(
+                "`{}` at {} doesn't have a package named `{}`",
+                repository,
+                reference,
+                name
+            )
+        })?;
+
+    let published = crate_files(&pkg)?;
+    let upstream_files = package_files(&upstream, config)?;
+    let mut problems = Vec::new();
+    for (file, path) in published.iter() {
+        if GENERATED_FILES.contains(&file.as_str()) {
+            continue;
+        }
+        let upstream_name = if file == "Cargo.toml.orig" {
+            "Cargo.toml"
+        } else {
+            file.as_str()
+        };
+        match upstream_files.get(upstream_name) {
+            Some(upstream_path) => {
+                if paths::read_bytes(path)? != paths::read_bytes(upstream_path)? {
+                    problems.push(format!("differs: {}", file));
+                }
+            }
+            None => problems.push(format!("only in the package: {}", file)),
+        }
+    }
+    if !problems.is_empty() {
+        bail!(
+            "`{}` doesn't match `{}` at {}:\n  {}",
+            pkg.package_id(),
+            repository,
+            reference,
+            problems.join("\n  ")
+        );
+    }
+    config.shell().status(
+        "Verified",
+        format!("{} against `{}` at {}", pkg.package_id(), repository, reference),
+    )?;
+    Ok(())
+}
+
+/// The revision of the repository `pkg` was published from: the tag of its
+/// version, or the commit recorded by `cargo package`, along with a
+/// description of it.
+fn revision(
+    remote: &GitRemote,
+    db_path: &Path,
+    pkg: &Package,
+    name: &str,
+    vers: &str,
+) -> CargoResult<(String, GitRevision)> {
+    let tags = vec![
+        format!("v{}", vers),
+        vers.to_string(),
+        format!("{}-v{}", name, vers),
+        format!("{}-{}", name, vers),
+    ];
+    for tag in tags.iter() {
+        if let Ok(rev) = remote.rev_for(db_path, &GitReference::Tag(tag.clone())) {
+            return Ok((format!("tag `{}`", tag), rev));
+        }
+    }
+
+    let vcs_info = pkg.root().join(".cargo_vcs_info.json");
+    if vcs_info.exists() {
+        let json: serde_json::Value = serde_json::from_str(&paths::read(&vcs_info)?)?;
+        if let Some(sha1) = json["git"]["sha1"].as_str() {
+            let rev = remote
+                .rev_for(db_path, &GitReference::Rev(sha1.to_string()))
+                .chain_err(|| format!("failed to find commit `{}`", sha1))?;
+            return Ok((format!("commit `{}`", sha1), rev));
+        }
+    }
+    bail!(
+        "none of the tags {} are in `{}`, and `{}` doesn't record the commit \
+         it was packaged from",
+        tags.iter()
+            .map(|t| format!("`{}`", t))
+            .collect::<Vec<_>>()
+            .join(", "),
+        remote.url(),
+        pkg.package_id()
+    )
+}
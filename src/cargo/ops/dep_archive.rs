@@ -0,0 +1,204 @@
+//! Archives of the sources of the dependencies of a workspace, for building
+//! without the registries and git repositories they come from, with
+//! `cargo fetch --to-archive` and `cargo build --from-archive`.
+//!
+//! An archive is a directory source, like vendored sources are, laid out as
+//!
+//! ```text
+//! .cargo/config
+//! sources/<name>-<version>/.cargo-checksum.json
+//! sources/<name>-<version>/Cargo.toml
+//! sources/<name>-<version>/...
+//! ```
+//!
+//! where `.cargo/config` replaces every registry and git repository the lock
+//! file refers to with the `sources` directory. Building from an archive
+//! unpacks it in the target directory and loads this configuration over the
+//! one found in the usual places.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use serde_json;
+use tar::{Archive, Builder, EntryType, Header};
+use toml;
+
+use crate::core::source::GitReference;
+use crate::core::{PackageSet, Resolve, SourceId, Workspace};
+use crate::sources::PathSource;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, short_hash, Config, Sha256};
+
+/// The name of the directory source of an archive in its configuration.
+const SOURCE_NAME: &str = "archive";
+
+/// Writes the sources of every package of `resolve` that doesn't come from
+/// a path to the archive `dst`, all of which must be in `packages`.
+pub fn write_archive(
+    ws: &Workspace<'_>,
+    resolve: &Resolve,
+    packages: &PackageSet<'_>,
+    dst: &Path,
+) -> CargoResult<()> {
+    let config = ws.config();
+    let ids = resolve
+        .iter()
+        .filter(|id| !id.source_id().is_path())
+        .collect::<Vec<_>>();
+    let pkgs = packages.get_many(ids)?;
+
+    let file =
+        File::create(dst).chain_err(|| format!("failed to create archive `{}`", dst.display()))?;
+    let mut ar = Builder::new(file);
+    let mut sources = Vec::new();
+    let mut archived = HashMap::new();
+    for pkg in pkgs {
+        let id = pkg.package_id();
+        let dir = format!("{}-{}", id.name(), id.version());
+        if let Some(other) = archived.insert(dir.clone(), id.source_id()) {
+            if other != id.source_id() {
+                bail!(
+                    "`{} v{}` comes from both `{}` and `{}`, \
+                     which can't be told apart in an archive",
+                    id.name(),
+                    id.version(),
+                    other,
+                    id.source_id()
+                );
+            }
+            continue;
+        }
+        if !sources.contains(&id.source_id()) {
+            sources.push(id.source_id());
+        }
+        config
+            .shell()
+            .verbose(|shell| shell.status("Archiving", id))?;
+
+        let src = PathSource::new(pkg.root(), id.source_id(), config);
+        let mut checksums = BTreeMap::new();
+        for file in src.list_files(pkg)? {
+            let relative = file.strip_prefix(pkg.root())?;
+            // Left behind by the unpacking of registry packages.
+            if relative == Path::new(".cargo-ok") {
+                continue;
+            }
+            let contents = paths::read_bytes(&file)?;
+            let mut sha = Sha256::new();
+            sha.update(&contents);
+            let relative = relative.to_str().ok_or_else(|| {
+                format_err!("non-utf8 path in package `{}`: `{}`", id, file.display())
+            })?;
+            checksums.insert(relative.replace('\\', "/"), hex::encode(sha.finish()));
+            append(&mut ar, &format!("sources/{}/{}", dir, relative), &contents)?;
+        }
+        let checksum = resolve.checksums().get(&id).cloned().unwrap_or(None);
+        let json = json!({ "package": checksum, "files": checksums });
+        append(
+            &mut ar,
+            &format!("sources/{}/.cargo-checksum.json", dir),
+            serde_json::to_string(&json)?.as_bytes(),
+        )?;
+    }
+    append(
+        &mut ar,
+        ".cargo/config",
+        source_config(&sources)?.as_bytes(),
+    )?;
+    ar.finish()
+        .chain_err(|| format!("failed to write archive `{}`", dst.display()))?;
+    Ok(())
+}
+
+/// The configuration replacing each of `sources` with the directory source
+/// of the archive.
+fn source_config(sources: &[SourceId]) -> CargoResult<String> {
+    let mut table = toml::value::Table::new();
+    let mut archive = toml::value::Table::new();
+    archive.insert("directory".to_string(), "sources".into());
+    table.insert(SOURCE_NAME.to_string(), archive.into());
+    for (i, id) in sources.iter().enumerate() {
+        let mut source = toml::value::Table::new();
+        source.insert("replace-with".to_string(), SOURCE_NAME.into());
+        let name = if id.is_default_registry() {
+            "crates-io".to_string()
+        } else if id.is_git() {
+            source.insert("git".to_string(), id.url().to_string().into());
+            match id.git_reference() {
+                Some(GitReference::Branch(b)) => {
+                    source.insert("branch".to_string(), b.as_str().into())
+                }
+                Some(GitReference::Tag(t)) => source.insert("tag".to_string(), t.as_str().into()),
+                Some(GitReference::Rev(r)) => source.insert("rev".to_string(), r.as_str().into()),
+                None => None,
+            };
+            format!("{}-git-{}", SOURCE_NAME, i)
+        } else {
+            source.insert("registry".to_string(), id.url().to_string().into());
+            format!("{}-registry-{}", SOURCE_NAME, i)
+        };
+        table.insert(name, source.into());
+    }
+    let mut root = toml::value::Table::new();
+    root.insert("source".to_string(), table.into());
+    Ok(toml::to_string(&root)?)
+}
+
+fn append(ar: &mut Builder<File>, path: &str, contents: &[u8]) -> CargoResult<()> {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::file());
+    header.set_mode(0o644);
+    header.set_size(contents.len() as u64);
+    ar.append_data(&mut header, path, contents)
+        .chain_err(|| format!("failed to add `{}` to the archive", path))?;
+    Ok(())
+}
+
+/// Unpacks the archive `src` into the target directory of `ws`, unless it's
+/// unpacked there already, and returns the directory it's in.
+pub fn unpack_archive(ws: &Workspace<'_>, src: &Path) -> CargoResult<PathBuf> {
+    let stem = src
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(SOURCE_NAME);
+    let dir = ws
+        .target_dir()
+        .into_path_unlocked()
+        .join("from-archive")
+        .join(format!("{}-{}", stem, short_hash(&src)));
+
+    // Keeps an archive from being unpacked by every build.
+    let mtime =
+        paths::mtime(src).chain_err(|| format!("failed to read archive `{}`", src.display()))?;
+    let stamp = dir.join(".archive-stamp");
+    let expected = format!("{}.{}\n", mtime.seconds(), mtime.nanoseconds());
+    if paths::read(&stamp).ok().as_ref() == Some(&expected) {
+        return Ok(dir);
+    }
+
+    if dir.exists() {
+        paths::remove_dir_all(&dir)?;
+    }
+    paths::create_dir_all(&dir)?;
+    let file =
+        File::open(src).chain_err(|| format!("failed to open archive `{}`", src.display()))?;
+    Archive::new(file)
+        .unpack(&dir)
+        .chain_err(|| format!("failed to unpack archive `{}`", src.display()))?;
+    if !dir.join(".cargo/config").is_file() {
+        bail!(
+            "`{}` isn't an archive of dependencies, \
+             like the ones written by `cargo fetch --to-archive`",
+            src.display()
+        );
+    }
+    paths::write(&stamp, expected.as_bytes())?;
+    Ok(dir)
+}
+
+/// Replaces the sources of the packages with the ones of the archive
+/// unpacked in `dir`, for the rest of the command.
+pub fn use_archive(config: &mut Config, dir: &Path) -> CargoResult<()> {
+    config.load_overrides(&dir.join(".cargo/config"))
+}
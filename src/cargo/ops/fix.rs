@@ -2,6 +2,7 @@ use std::collections::{BTreeSet, HashMap, HashSet};
 use std::env;
 use std::ffi::OsString;
 use std::fs;
+use std::mem;
 use std::path::{Path, PathBuf};
 use std::process::{self, Command, ExitStatus};
 use std::str;
@@ -12,10 +13,10 @@ use rustfix::diagnostics::Diagnostic;
 use rustfix::{self, CodeFix};
 use serde_json;
 
-use crate::core::Workspace;
+use crate::core::{Edition, Workspace};
 use crate::ops::{self, CompileOptions};
 use crate::util::diagnostic_server::{Message, RustfixDiagnosticServer};
-use crate::util::errors::CargoResult;
+use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::paths;
 use crate::util::{existing_vcs_repo, LockServer, LockServerClient};
 
@@ -28,6 +29,9 @@ const IDIOMS_ENV: &str = "__CARGO_FIX_IDIOMS";
 
 pub struct FixOptions<'a> {
     pub edition: bool,
+    /// Run the full edition migration: fix every feature set, then switch
+    /// the manifests to the next edition and check that they still build.
+    pub migrate: bool,
     pub prepare_for: Option<&'a str>,
     pub idioms: bool,
     pub compile_opts: CompileOptions<'a>,
@@ -40,6 +44,16 @@ pub struct FixOptions<'a> {
 pub fn fix(ws: &Workspace, opts: &mut FixOptions) -> CargoResult<()> {
     check_version_control(opts)?;
 
+    let migrating = if opts.migrate {
+        let migrating = packages_to_migrate(ws, opts)?;
+        if migrating.is_empty() {
+            return Ok(());
+        }
+        migrating
+    } else {
+        Vec::new()
+    };
+
     // Spin up our lock server which our subprocesses will use to synchronize
     // fixes.
     let lock_server = LockServer::new()?;
@@ -85,9 +99,162 @@ pub fn fix(ws: &Workspace, opts: &mut FixOptions) -> CargoResult<()> {
         .borrow_mut() = Some(RustfixDiagnosticServer::new()?);
 
     ops::compile(ws, &opts.compile_opts)?;
+
+    if opts.migrate {
+        migrate(ws, opts, &migrating)?;
+    }
     Ok(())
 }
 
+/// Returns the manifests of the selected packages that are not on the latest
+/// edition yet, along with the edition each one should be moved to.
+fn packages_to_migrate(ws: &Workspace, opts: &FixOptions) -> CargoResult<Vec<(PathBuf, Edition)>> {
+    let config = opts.compile_opts.config;
+    let specs = opts.compile_opts.spec.to_package_id_specs(ws)?;
+    let mut ret = Vec::new();
+    for pkg in ws.members() {
+        if !specs.iter().any(|spec| spec.matches(pkg.package_id())) {
+            continue;
+        }
+        match pkg.manifest().edition() {
+            Edition::Edition2015 => {
+                ret.push((pkg.manifest_path().to_path_buf(), Edition::Edition2018));
+            }
+            edition @ Edition::Edition2018 => {
+                config.shell().warn(format!(
+                    "`{}` is already on the latest edition ({})",
+                    pkg.name(),
+                    edition
+                ))?;
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Finishes an edition migration after the initial round of fixes.
+///
+/// Code behind optional features is only seen by the compiler when those
+/// features are enabled, so unless the user already asked for them all we run
+/// a second round of fixes with `--all-features`. Afterwards the `edition` key
+/// of every migrated package is bumped and the workspace is checked once more,
+/// this time without any fixing, to make sure it builds on the new edition.
+fn migrate(
+    ws: &Workspace,
+    opts: &mut FixOptions,
+    packages: &[(PathBuf, Edition)],
+) -> CargoResult<()> {
+    let config = opts.compile_opts.config;
+    if !opts.compile_opts.all_features {
+        let features = mem::replace(&mut opts.compile_opts.features, Vec::new());
+        let no_default_features = opts.compile_opts.no_default_features;
+        opts.compile_opts.all_features = true;
+        opts.compile_opts.no_default_features = false;
+        *opts
+            .compile_opts
+            .build_config
+            .rustfix_diagnostic_server
+            .borrow_mut() = Some(RustfixDiagnosticServer::new()?);
+        ops::compile(ws, &opts.compile_opts)?;
+        opts.compile_opts.features = features;
+        opts.compile_opts.all_features = false;
+        opts.compile_opts.no_default_features = no_default_features;
+    }
+
+    for &(ref path, edition) in packages {
+        let pkg = ws.members().find(|p| p.manifest_path() == &**path).unwrap();
+        config.shell().status(
+            "Migrating",
+            format!("{} to edition {}", pkg.name(), edition),
+        )?;
+        let contents = paths::read(path)?;
+        let updated = set_manifest_edition(&contents, &edition.to_string())
+            .chain_err(|| format!("failed to update the edition in `{}`", path.display()))?;
+        paths::write(path, updated.as_bytes())?;
+    }
+
+    // Verify the migrated workspace with a plain `cargo check` of the same
+    // targets, reloading it since the manifests just changed.
+    let build_config = &mut opts.compile_opts.build_config;
+    build_config.cargo_as_rustc_wrapper = false;
+    build_config.extra_rustc_env.clear();
+    *build_config.rustfix_diagnostic_server.borrow_mut() = None;
+    let manifest_path = match ws.current_opt() {
+        Some(pkg) => pkg.manifest_path().to_path_buf(),
+        None => ws.root().join("Cargo.toml"),
+    };
+    let ws = Workspace::new(&manifest_path, config)?;
+    ops::compile(&ws, &opts.compile_opts)
+        .chain_err(|| "the migrated code failed to compile on the new edition")?;
+    Ok(())
+}
+
+/// Sets `edition = "<edition>"` in the `[package]` (or `[project]`) table of
+/// a manifest, editing the text in place so the rest of the file keeps its
+/// formatting and comments.
+fn set_manifest_edition(contents: &str, edition: &str) -> CargoResult<String> {
+    let new_line = format!("edition = \"{}\"", edition);
+    let mut lines = contents.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+    let mut in_package = false;
+    let mut header = None;
+    let mut insert_at = None;
+    for (i, line) in lines.iter_mut().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if in_package {
+                break;
+            }
+            in_package = trimmed == "[package]" || trimmed == "[project]";
+            if in_package {
+                header = Some(i);
+                insert_at = Some(i + 1);
+            }
+            continue;
+        }
+        if !in_package {
+            continue;
+        }
+        let key = trimmed.split('=').next().unwrap_or("").trim();
+        if key == "edition" {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            *line = format!("{}{}", indent, new_line);
+            return Ok(join_lines(&lines, contents));
+        }
+        if key == "version" || key == "name" {
+            insert_at = Some(i + 1);
+        }
+    }
+    let at = match insert_at {
+        Some(at) => at,
+        None => bail!("no `[package]` section found"),
+    };
+    // Match the indentation of the key we're inserting after.
+    let indent = {
+        let prev = &lines[at - 1];
+        let reference = if Some(at - 1) == header {
+            lines.get(at).unwrap_or(prev)
+        } else {
+            prev
+        };
+        reference[..reference.len() - reference.trim_start().len()].to_string()
+    };
+    lines.insert(at, format!("{}{}", indent, new_line));
+    Ok(join_lines(&lines, contents))
+}
+
+fn join_lines(lines: &[String], original: &str) -> String {
+    let newline = if original.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut ret = lines.join(newline);
+    if original.ends_with('\n') {
+        ret.push_str(newline);
+    }
+    ret
+}
+
 fn check_version_control(opts: &FixOptions) -> CargoResult<()> {
     if opts.allow_no_vcs {
         return Ok(());
@@ -1,12 +1,14 @@
+use std::collections::HashSet;
 use std::io::prelude::*;
 
+use tempfile::Builder as TempFileBuilder;
 use toml;
 
 use crate::core::resolver::WorkspaceResolve;
 use crate::core::{resolver, Resolve, Workspace};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::toml as cargo_toml;
-use crate::util::Filesystem;
+use crate::util::{paths, Config, Filesystem};
 
 pub fn load_pkg_lockfile(ws: &Workspace) -> CargoResult<Option<Resolve>> {
     if !ws.root().join("Cargo.lock").exists() {
@@ -30,6 +32,12 @@ pub fn load_pkg_lockfile(ws: &Workspace) -> CargoResult<Option<Resolve>> {
 }
 
 pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()> {
+    // A partial workspace doesn't know the packages of the members it left
+    // out, which would be dropped from the lock file.
+    if ws.is_partial() {
+        return check_partial_lockfile(ws, resolve);
+    }
+
     // Load the original lockfile if it exists.
     let ws_root = Filesystem::new(ws.root().to_path_buf());
     let orig = ws_root.open_ro("Cargo.lock", ws.config(), "Cargo.lock file");
@@ -70,11 +78,21 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()>
         }
     }
 
+    if let Some(pin) = toml.get("pin") {
+        out.push_str("[pin]\n");
+        out.push_str(&pin.to_string());
+        out.push_str("\n");
+    }
+
     if let Some(meta) = toml.get("metadata") {
         out.push_str("[metadata]\n");
         out.push_str(&meta.to_string());
     }
 
+    if ws.config().ephemeral() {
+        return write_ephemeral_lockfile(ws.config(), &out);
+    }
+
     // If the lockfile contents haven't changed so don't rewrite it. This is
     // helpful on read-only filesystems.
     if let Ok(orig) = orig {
@@ -84,7 +102,7 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()>
     }
 
     if !ws.config().lock_update_allowed() {
-        if ws.config().cli_unstable().offline {
+        if ws.config().offline() {
             bail!("can't update in the offline mode");
         }
 
@@ -113,6 +131,46 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()>
     Ok(())
 }
 
+/// Checks that the lock file has the packages of `resolve`, the resolve of a
+/// partial workspace, with the same dependencies, as it can't be updated.
+fn check_partial_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()> {
+    let up_to_date = match load_pkg_lockfile(ws)? {
+        Some(prev) => resolve.iter().all(|id| {
+            prev.contains(&id)
+                && resolve.deps_not_replaced(id).collect::<HashSet<_>>()
+                    == prev.deps_not_replaced(id).collect::<HashSet<_>>()
+        }),
+        None => false,
+    };
+    if !up_to_date {
+        bail!(
+            "the lock file {} needs to be updated, which `-Z partial-workspace` \
+             doesn't do as it only loads some of the workspace's members\n\
+             run the command without it once to update the lock file",
+            ws.root().join("Cargo.lock").display()
+        );
+    }
+    Ok(())
+}
+
+/// Writes the lock file to a temporary directory, the same one for the whole
+/// command, which is left behind for the user to look at.
+fn write_ephemeral_lockfile(config: &Config, contents: &str) -> CargoResult<()> {
+    let path = match config.ephemeral_lockfile() {
+        Some(path) => path,
+        None => {
+            let dir = TempFileBuilder::new()
+                .prefix("cargo-lock-")
+                .tempdir()
+                .chain_err(|| "failed to create a directory for the ephemeral lock file")?;
+            let path = dir.into_path().join("Cargo.lock");
+            config.set_ephemeral_lockfile(path.clone());
+            path
+        }
+    };
+    paths::write(&path, contents.as_bytes())
+}
+
 fn are_equal_lockfiles(mut orig: String, current: &str, ws: &Workspace) -> bool {
     if has_crlf_line_endings(&orig) {
         orig = orig.replace("\r\n", "\n");
@@ -1,20 +1,30 @@
+pub use self::cargo_check_remote::check_remote;
+pub use self::cargo_add::{add, AddOptions};
 pub use self::cargo_clean::{clean, CleanOptions};
-pub use self::cargo_compile::{compile, compile_with_exec, compile_ws, CompileOptions};
+pub use self::cargo_diff::{diff, DiffOptions};
+pub use self::cargo_compile::{
+    bloat_report, bug_report, compile, compile_with_exec, compile_ws, unit_inputs,
+    CompileOptions,
+};
 pub use self::cargo_compile::{CompileFilter, FilterRule, Packages};
 pub use self::cargo_read_manifest::{read_package, read_packages};
-pub use self::cargo_run::run;
+pub use self::cargo_reproducible::assert_reproducible;
+pub use self::cargo_run::{missing_target_error, run};
 pub use self::cargo_install::{install, install_list, uninstall};
 pub use self::cargo_new::{init, new, NewOptions, VersionControl};
 pub use self::cargo_doc::{doc, DocOptions};
+pub use self::cargo_exec::{exec, ExecOptions};
 pub use self::cargo_generate_lockfile::generate_lockfile;
 pub use self::cargo_generate_lockfile::update_lockfile;
 pub use self::cargo_generate_lockfile::UpdateOptions;
+pub use self::cargo_graph::{graph, GraphFormat, GraphOptions};
+pub use self::cargo_manifest::{edit_manifest, ManifestEdit};
 pub use self::lockfile::{load_pkg_lockfile, write_pkg_lockfile};
-pub use self::cargo_test::{run_benches, run_tests, TestOptions};
-pub use self::cargo_package::{package, PackageOpts};
+pub use self::cargo_test::{random_shuffle_seed, run_benches, run_tests, TestOptions};
+pub use self::cargo_package::{package, signature_path, PackageOpts};
 pub use self::registry::{publish, registry_configuration, RegistryConfig};
-pub use self::registry::{http_handle, needs_custom_http_transport, registry_login, search};
-pub use self::registry::{modify_owners, yank, OwnersOptions, PublishOpts};
+pub use self::registry::{http_handle, info, needs_custom_http_transport, registry_login, search};
+pub use self::registry::{complete, modify_owners, yank, Completion, OwnersOptions, PublishOpts};
 pub use self::registry::{configure_http_handle, http_handle_and_timeout};
 pub use self::registry::HttpTimeout;
 pub use self::cargo_fetch::{fetch, FetchOptions};
@@ -22,22 +32,35 @@ pub use self::cargo_pkgid::pkgid;
 pub use self::resolve::{add_overrides, get_resolved_packages, resolve_with_previous, resolve_ws,
                         resolve_ws_precisely, resolve_ws_with_method};
 pub use self::cargo_output_metadata::{output_metadata, ExportInfo, OutputMetadataOptions};
+pub use self::cargo_verify_crate::{verify_crate, VerifyCrateOptions};
 pub use self::fix::{fix, FixOptions, fix_maybe_exec_rustc};
+pub use self::dep_archive::{unpack_archive, use_archive, write_archive};
+pub use self::vendor::verify_vendored_sources;
 
+mod cargo_add;
+mod cargo_check_remote;
 mod cargo_clean;
+mod cargo_diff;
 mod cargo_compile;
 mod cargo_doc;
+mod cargo_exec;
 mod cargo_fetch;
 mod cargo_generate_lockfile;
+mod cargo_graph;
 mod cargo_install;
+mod cargo_manifest;
 mod cargo_new;
 mod cargo_output_metadata;
 mod cargo_package;
 mod cargo_pkgid;
 mod cargo_read_manifest;
+mod cargo_reproducible;
 mod cargo_run;
 mod cargo_test;
+mod cargo_verify_crate;
+mod dep_archive;
 mod lockfile;
 mod registry;
 mod resolve;
 mod fix;
+mod vendor;
@@ -1,13 +1,18 @@
 use std::collections::BTreeMap;
-use std::fs::{self, File};
+use std::fs;
+use std::io::{Seek, SeekFrom};
 use std::iter::repeat;
+use std::path::PathBuf;
 use std::str;
 use std::time::Duration;
 use std::{cmp, env};
 
 use curl::easy::{Easy, InfoType, SslOpt};
+use flate2::read::GzDecoder;
 use git2;
 use log::Level;
+use serde_json;
+use tar::Archive;
 use crate::registry::{NewCrate, NewCrateDependency, Registry};
 
 use url::percent_encoding::{percent_encode, QUERY_ENCODE_SET};
@@ -15,14 +20,14 @@ use url::percent_encoding::{percent_encode, QUERY_ENCODE_SET};
 use crate::core::dependency::Kind;
 use crate::core::manifest::ManifestMetadata;
 use crate::core::source::Source;
-use crate::core::{Package, SourceId, Workspace};
+use crate::core::{Dependency, Package, SourceId, Summary, Workspace};
 use crate::ops;
 use crate::sources::{RegistrySource, SourceConfigMap};
 use crate::util::config::{self, Config};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::important_paths::find_root_manifest_for_wd;
 use crate::util::paths;
-use crate::util::ToUrl;
+use crate::util::{FileLock, ToUrl};
 use crate::version;
 
 pub struct RegistryConfig {
@@ -39,6 +44,9 @@ pub struct PublishOpts<'cfg> {
     pub jobs: Option<u32>,
     pub target: Option<String>,
     pub dry_run: bool,
+    /// Print the metadata sent to the registry and the files of the tarball
+    /// before uploading it.
+    pub show_payload: bool,
     pub registry: Option<String>,
 }
 
@@ -87,18 +95,20 @@ pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
     )?
     .unwrap();
 
+    // `ops::package` leaves a detached signature next to the tarball if
+    // signing is configured, which is forwarded to the registry as is.
+    let sig_path = ops::signature_path(ws, pkg);
+    let signature = if sig_path.exists() {
+        Some(paths::read(&sig_path)?)
+    } else {
+        None
+    };
+
     // Upload said tarball to the specified destination
     opts.config
         .shell()
         .status("Uploading", pkg.package_id().to_string())?;
-    transmit(
-        opts.config,
-        pkg,
-        tarball.file(),
-        &mut registry,
-        reg_id,
-        opts.dry_run,
-    )?;
+    transmit(opts, pkg, &tarball, signature, &mut registry, reg_id)?;
 
     Ok(())
 }
@@ -145,13 +155,14 @@ fn verify_dependencies(pkg: &Package, registry_src: SourceId) -> CargoResult<()>
 }
 
 fn transmit(
-    config: &Config,
+    opts: &PublishOpts<'_>,
     pkg: &Package,
-    tarball: &File,
+    tarball: &FileLock,
+    signature: Option<String>,
     registry: &mut Registry,
     registry_id: SourceId,
-    dry_run: bool,
 ) -> CargoResult<()> {
+    let config = opts.config;
     let deps = pkg
         .dependencies()
         .iter()
@@ -211,12 +222,6 @@ fn transmit(
         }
     }
 
-    // Do not upload if performing a dry run
-    if dry_run {
-        config.shell().warn("aborting upload due to dry run")?;
-        return Ok(());
-    }
-
     let summary = pkg.summary();
     let string_features = summary
         .features()
@@ -229,28 +234,38 @@ fn transmit(
         })
         .collect::<BTreeMap<String, Vec<String>>>();
 
-    let publish = registry.publish(
-        &NewCrate {
-            name: pkg.name().to_string(),
-            vers: pkg.version().to_string(),
-            deps,
-            features: string_features,
-            authors: authors.clone(),
-            description: description.clone(),
-            homepage: homepage.clone(),
-            documentation: documentation.clone(),
-            keywords: keywords.clone(),
-            categories: categories.clone(),
-            readme: readme_content,
-            readme_file: readme.clone(),
-            repository: repository.clone(),
-            license: license.clone(),
-            license_file: license_file.clone(),
-            badges: badges.clone(),
-            links: links.clone(),
-        },
-        tarball,
-    );
+    let krate = NewCrate {
+        name: pkg.name().to_string(),
+        vers: pkg.version().to_string(),
+        deps,
+        features: string_features,
+        authors: authors.clone(),
+        description: description.clone(),
+        homepage: homepage.clone(),
+        documentation: documentation.clone(),
+        keywords: keywords.clone(),
+        categories: categories.clone(),
+        readme: readme_content,
+        readme_file: readme.clone(),
+        repository: repository.clone(),
+        license: license.clone(),
+        license_file: license_file.clone(),
+        badges: badges.clone(),
+        links: links.clone(),
+        signature,
+    };
+
+    if opts.show_payload {
+        show_payload(&krate, tarball)?;
+    }
+
+    // Do not upload if performing a dry run, which `--show-payload` implies
+    if opts.dry_run || opts.show_payload {
+        config.shell().warn("aborting upload due to dry run")?;
+        return Ok(());
+    }
+
+    let publish = registry.publish(&krate, tarball.file());
 
     match publish {
         Ok(warnings) => {
@@ -291,6 +306,42 @@ fn transmit(
     }
 }
 
+#[derive(Serialize)]
+struct Payload<'a> {
+    metadata: &'a NewCrate,
+    files: Vec<PayloadFile>,
+}
+
+#[derive(Serialize)]
+struct PayloadFile {
+    path: PathBuf,
+    size: u64,
+}
+
+/// Prints the metadata of `krate` as it's sent to the registry, along with
+/// the files of the tarball uploaded with it.
+fn show_payload(krate: &NewCrate, tarball: &FileLock) -> CargoResult<()> {
+    // The tarball may have been renamed since it was locked, so it's read
+    // through the open file rather than its path.
+    let mut file = tarball.file();
+    file.seek(SeekFrom::Start(0))?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        files.push(PayloadFile {
+            path: entry.path()?.into_owned(),
+            size: entry.header().size()?,
+        });
+    }
+    let payload = Payload {
+        metadata: krate,
+        files,
+    };
+    println!("{}", serde_json::to_string(&payload)?);
+    Ok(())
+}
+
 pub fn registry_configuration(
     config: &Config,
     registry: Option<String>,
@@ -722,3 +773,260 @@ pub fn search(
 
     Ok(())
 }
+
+/// Prints the metadata of the crate `spec`, like `serde` or `serde@1.0`, for
+/// `cargo info`: its versions, features and dependencies from the index,
+/// its description, license and declared minimum Rust version from the API
+/// of the registry when it has one, and the versions of the crate the
+/// lockfile of the current workspace uses.
+pub fn info(
+    spec: &str,
+    config: &Config,
+    index: Option<String>,
+    reg: Option<String>,
+) -> CargoResult<()> {
+    let (name, _) = parse_crate_spec(spec);
+    let sid = get_source_id(config, index, reg)?;
+
+    let mut regsrc = RegistrySource::remote(sid, config);
+    regsrc
+        .update()
+        .chain_err(|| format!("failed to update {}", sid))?;
+    let (summaries, i) = query_crate_spec(&mut regsrc, spec)?;
+    let summary = &summaries[i];
+
+    // The index has no description or license, which only the API serves.
+    let api = regsrc.config()?.and_then(|cfg| cfg.api);
+    let api_info = match api {
+        Some(api) => {
+            let mut registry = Registry::new_handle(api, None, http_handle(config)?);
+            match registry.crate_info(name) {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    config.shell().warn(format!(
+                        "failed to get the metadata of `{}` from the registry's API, \
+                         showing the index's only: {}",
+                        name, e
+                    ))?;
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let latest = summaries[latest_index(&summaries)].version();
+    if summary.version() == latest {
+        println!("{} v{}", name, summary.version());
+    } else {
+        println!("{} v{} (latest: v{})", name, summary.version(), latest);
+    }
+    if let Some((ref krate, ref versions)) = api_info {
+        if let Some(ref description) = krate.description {
+            println!("{}", description.trim());
+        }
+        let version = versions
+            .iter()
+            .find(|v| v.num == summary.version().to_string());
+        if let Some(version) = version {
+            if let Some(ref license) = version.license {
+                println!("license: {}", license);
+            }
+            if let Some(ref rust_version) = version.rust_version {
+                println!("rust-version: {}", rust_version);
+            }
+        }
+        for (key, value) in &[
+            ("homepage", &krate.homepage),
+            ("repository", &krate.repository),
+            ("documentation", &krate.documentation),
+        ] {
+            if let Some(value) = value {
+                println!("{}: {}", key, value);
+            }
+        }
+    }
+
+    let shown = 10;
+    let mut versions = summaries
+        .iter()
+        .take(shown)
+        .map(|s| s.version().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if summaries.len() > shown {
+        versions.push_str(&format!(" and {} older", summaries.len() - shown));
+    }
+    println!("versions: {}", versions);
+
+    // The locked versions are only shown if there is a lock file which can be
+    // read, `cargo info` doesn't need a package, even less a valid one.
+    let resolve = find_root_manifest_for_wd(config.cwd()).and_then(|root| {
+        let ws = Workspace::new(&root, config)?;
+        ops::load_pkg_lockfile(&ws)
+    });
+    if let Ok(Some(resolve)) = resolve {
+        let locked = resolve
+            .iter()
+            .filter(|id| id.name().as_str() == name && id.source_id().is_registry())
+            .map(|id| id.version().to_string())
+            .collect::<Vec<_>>();
+        if !locked.is_empty() {
+            println!("locked: {}", locked.join(", "));
+        }
+    }
+
+    if !summary.features().is_empty() {
+        println!("features:");
+        for (feature, values) in summary.features() {
+            let values = values
+                .iter()
+                .map(|v| format!("{:?}", v.to_string(summary)))
+                .collect::<Vec<_>>();
+            println!("  {} = [{}]", feature, values.join(", "));
+        }
+    }
+
+    for &(kind, title) in &[
+        (Kind::Normal, "dependencies"),
+        (Kind::Build, "build-dependencies"),
+        (Kind::Development, "dev-dependencies"),
+    ] {
+        let mut deps = summary
+            .dependencies()
+            .iter()
+            .filter(|d| d.kind() == kind)
+            .collect::<Vec<_>>();
+        if deps.is_empty() {
+            continue;
+        }
+        deps.sort_by_key(|d| d.name_in_toml());
+        println!("{}:", title);
+        for dep in deps {
+            let mut notes = Vec::new();
+            if dep.is_optional() {
+                notes.push("optional".to_string());
+            }
+            if let Some(platform) = dep.platform() {
+                notes.push(format!("target: {}", platform));
+            }
+            if notes.is_empty() {
+                println!("  {} = \"{}\"", dep.name_in_toml(), dep.version_req());
+            } else {
+                println!(
+                    "  {} = \"{}\" ({})",
+                    dep.name_in_toml(),
+                    dep.version_req(),
+                    notes.join(", ")
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// What `cargo complete-*` completes, for editors and shell completions.
+pub enum Completion<'a> {
+    /// The names of the crates starting with a prefix.
+    CrateNames(&'a str),
+    /// The versions of a crate, newest first.
+    Versions(&'a str),
+    /// The features of a crate, and its optional dependencies, at its latest
+    /// version or the latest one matching the requirement given with it.
+    Features(&'a str),
+}
+
+/// Completes `completion` from the local copy of the index of a registry,
+/// which isn't updated, so that it's fast and works offline.
+pub fn complete(
+    config: &Config,
+    completion: &Completion<'_>,
+    index: Option<String>,
+    reg: Option<String>,
+) -> CargoResult<Vec<String>> {
+    let sid = get_source_id(config, index, reg)?;
+    let mut regsrc = RegistrySource::remote(sid, config);
+    let not_fetched = || {
+        format!(
+            "failed to read the local copy of the index of {}, \
+             which is downloaded by commands like `cargo fetch`",
+            sid
+        )
+    };
+    // Reading the index doesn't fail when it's missing, but its
+    // configuration does.
+    regsrc.config().chain_err(not_fetched)?;
+    match *completion {
+        Completion::CrateNames(prefix) => regsrc.crate_names(prefix),
+        Completion::Versions(name) => {
+            let dep = Dependency::parse_no_deprecated(name, None, sid)?;
+            let mut summaries = regsrc.query_vec(&dep)?;
+            summaries.sort_by(|a, b| b.version().cmp(a.version()));
+            Ok(summaries.iter().map(|s| s.version().to_string()).collect())
+        }
+        Completion::Features(spec) => {
+            let (summaries, i) = query_crate_spec(&mut regsrc, spec)?;
+            let summary = &summaries[i];
+            let mut features = summary
+                .features()
+                .keys()
+                .map(|f| f.to_string())
+                .chain(
+                    summary
+                        .dependencies()
+                        .iter()
+                        .filter(|d| d.is_optional())
+                        .map(|d| d.name_in_toml().to_string()),
+                )
+                .collect::<Vec<_>>();
+            features.sort();
+            features.dedup();
+            Ok(features)
+        }
+    }
+}
+
+/// The name of the crate `spec`, like `serde` or `serde@1.0`, and its
+/// version requirement.
+fn parse_crate_spec(spec: &str) -> (&str, Option<&str>) {
+    match spec.find('@') {
+        Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+        None => (spec, None),
+    }
+}
+
+/// The versions of the crate `spec` in `regsrc`, newest first, and the index
+/// of its latest one matching the requirement given with it, or of its latest
+/// stable version if none is given.
+fn query_crate_spec(
+    regsrc: &mut RegistrySource<'_>,
+    spec: &str,
+) -> CargoResult<(Vec<Summary>, usize)> {
+    let (name, req) = parse_crate_spec(spec);
+    let sid = regsrc.source_id();
+    let dep = Dependency::parse_no_deprecated(name, None, sid)?;
+    let mut summaries = regsrc.query_vec(&dep)?;
+    summaries.sort_by(|a, b| b.version().cmp(a.version()));
+    let i = match req {
+        Some(req) => {
+            let req = Dependency::parse_no_deprecated(name, Some(req), sid)?;
+            summaries.iter().position(|s| req.matches(s))
+        }
+        None if summaries.is_empty() => None,
+        None => Some(latest_index(&summaries)),
+    };
+    match i {
+        Some(i) => Ok((summaries, i)),
+        None => bail!("could not find `{}` in {}", spec, sid),
+    }
+}
+
+/// The index of the newest stable version in `summaries`, sorted newest
+/// first, or of the newest version if they are all pre-releases.
+fn latest_index(summaries: &[Summary]) -> usize {
+    summaries
+        .iter()
+        .position(|s| s.version().pre.is_empty())
+        .unwrap_or(0)
+}
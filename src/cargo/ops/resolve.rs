@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::core::registry::PackageRegistry;
+use crate::core::registry::{PackageRegistry, TargetPlatforms};
 use crate::core::resolver::{self, Method, Resolve};
 use crate::core::{PackageId, PackageIdSpec, PackageSet, Source, SourceId, Workspace};
 use crate::ops;
@@ -41,14 +41,18 @@ pub fn resolve_ws_precisely<'a>(
             uses_default_features: !no_default_features,
         }
     };
-    resolve_ws_with_method(ws, source, method, specs)
+    resolve_ws_with_method(ws, source, method, specs, None)
 }
 
+/// Like `resolve_ws_precisely`, if `target_platforms` is given the
+/// target-specific default features are only enabled for these platforms
+/// rather than for all of them.
 pub fn resolve_ws_with_method<'a>(
     ws: &Workspace<'a>,
     source: Option<Box<Source + 'a>>,
     method: Method,
     specs: &[PackageIdSpec],
+    target_platforms: Option<TargetPlatforms<'a>>,
 ) -> CargoResult<(PackageSet<'a>, Resolve)> {
     let mut registry = PackageRegistry::new(ws.config())?;
     if let Some(source) = source {
@@ -82,9 +86,13 @@ pub fn resolve_ws_with_method<'a>(
 
         Some(resolve)
     } else {
-        ops::load_pkg_lockfile(ws)?
+        load_previous(ws)?
     };
 
+    if let Some(platforms) = target_platforms {
+        registry.set_target_platforms(platforms);
+    }
+
     let resolved_with_overrides = ops::resolve_with_previous(
         &mut registry,
         ws,
@@ -101,12 +109,20 @@ pub fn resolve_ws_with_method<'a>(
     Ok((packages, resolved_with_overrides))
 }
 
+/// The resolve of the lock file to start from, none with `--unlocked`.
+fn load_previous(ws: &Workspace<'_>) -> CargoResult<Option<Resolve>> {
+    if ws.config().unlocked() {
+        return Ok(None);
+    }
+    ops::load_pkg_lockfile(ws)
+}
+
 fn resolve_with_registry<'cfg>(
     ws: &Workspace<'cfg>,
     registry: &mut PackageRegistry<'cfg>,
     warn: bool,
 ) -> CargoResult<Resolve> {
-    let prev = ops::load_pkg_lockfile(ws)?;
+    let prev = load_previous(ws)?;
     let resolve = resolve_with_previous(
         registry,
         ws,
@@ -174,6 +190,7 @@ pub fn resolve_with_previous<'cfg>(
     let mut try_to_use = HashSet::new();
     if let Some(r) = previous {
         trace!("previous: {:?}", r);
+        registry.set_pins(r.pins().clone());
         register_previous_locks(ws, registry, r, &keep);
 
         // Everything in the previous lock file we want to keep is prioritized
@@ -249,6 +266,7 @@ pub fn resolve_with_previous<'cfg>(
         }
         for member in members {
             let summary = registry.lock(member.summary().clone());
+            let summary = registry.target_default_features(summary)?;
             summaries.push((summary, method))
         }
     } else {
@@ -299,6 +317,7 @@ pub fn resolve_with_previous<'cfg>(
             };
 
             let summary = registry.lock(member.summary().clone());
+            let summary = registry.target_default_features(summary)?;
             summaries.push((summary, method_to_resolve));
         }
     };
@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::core::{Source, SourceId, Workspace};
+use crate::ops;
+use crate::sources::DirectorySource;
+use crate::util::errors::CargoResult;
+
+/// Re-validates a directory of vendored sources against the checksums of the
+/// workspace's lock file, for `cargo vendor --verify`.
+///
+/// Vendoring itself is done by the `cargo-vendor` plugin, which `cargo
+/// vendor` hands over to without `--verify`. Each vendored package must be
+/// unmodified, and it must have the checksum the lock file lists for it.
+/// Every package from a registry in the lock file must also be vendored. All
+/// problems found are reported in the error, not just the first one.
+pub fn verify_vendored_sources(ws: &Workspace, dir: &Path) -> CargoResult<()> {
+    let config = ws.config();
+    let resolve = match ops::load_pkg_lockfile(ws)? {
+        Some(resolve) => resolve,
+        None => bail!(
+            "a lock file is required to verify vendored sources, \
+             but `{}` doesn't exist",
+            ws.root().join("Cargo.lock").display()
+        ),
+    };
+
+    let id = SourceId::for_directory(dir)?;
+    let mut src = DirectorySource::new(dir, id, config);
+    src.update()?;
+
+    let mut problems = Vec::new();
+    let mut vendored = HashSet::new();
+    for (pkg, changes) in src.changed_files()? {
+        let pkg_id = pkg.package_id();
+        vendored.insert((pkg_id.name(), pkg_id.version().clone()));
+        problems.extend(changes);
+
+        let vendored_cksum = match pkg.summary().checksum() {
+            Some(cksum) => cksum,
+            None => continue,
+        };
+        let locked = resolve
+            .checksums()
+            .iter()
+            .find(|&(id, _)| id.name() == pkg_id.name() && id.version() == pkg_id.version());
+        if let Some((_, Some(locked_cksum))) = locked {
+            if locked_cksum != vendored_cksum {
+                problems.push(format!(
+                    "the checksum of `{} v{}` doesn't match the lock file:\n\
+                     expected: {}\n\
+                     actual:   {}",
+                    pkg_id.name(),
+                    pkg_id.version(),
+                    locked_cksum,
+                    vendored_cksum
+                ));
+            }
+        }
+    }
+
+    let mut missing = resolve
+        .iter()
+        .filter(|id| id.source_id().is_registry())
+        .filter(|id| !vendored.contains(&(id.name(), id.version().clone())))
+        .collect::<Vec<_>>();
+    missing.sort();
+    for id in missing {
+        problems.push(format!(
+            "`{} v{}` is in the lock file but hasn't been vendored",
+            id.name(),
+            id.version()
+        ));
+    }
+
+    if !problems.is_empty() {
+        bail!(
+            "vendored sources in `{}` don't match `{}`:\n\n{}",
+            dir.display(),
+            ws.root().join("Cargo.lock").display(),
+            problems.join("\n\n")
+        );
+    }
+    Ok(())
+}
@@ -37,6 +37,65 @@ impl<'cfg> DirectorySource<'cfg> {
             packages: HashMap::new(),
         }
     }
+
+    /// Checks each package of this source against its
+    /// `.cargo-checksum.json`.
+    ///
+    /// The source must have been updated. Returns every package along with a
+    /// description of each of its files which changed.
+    pub fn changed_files(&self) -> CargoResult<Vec<(&Package, Vec<String>)>> {
+        let mut ret = Vec::new();
+        for &(ref pkg, ref cksum) in self.packages.values() {
+            ret.push((pkg, changed_files(pkg, cksum)?));
+        }
+        ret.sort_by_key(|p| p.0.package_id());
+        Ok(ret)
+    }
+}
+
+/// Describes each file of `pkg` which doesn't match the checksum listed for
+/// it, sorted by path.
+fn changed_files(pkg: &Package, cksum: &Checksum) -> CargoResult<Vec<String>> {
+    let mut files = cksum.files.iter().collect::<Vec<_>>();
+    files.sort();
+
+    let mut buf = [0; 16 * 1024];
+    let mut ret = Vec::new();
+    for (file, cksum) in files {
+        let mut h = Sha256::new();
+        let file = pkg.root().join(file);
+        if !file.exists() {
+            ret.push(format!(
+                "the listed file `{}` has been removed",
+                file.display()
+            ));
+            continue;
+        }
+
+        (|| -> CargoResult<()> {
+            let mut f = File::open(&file)?;
+            loop {
+                match f.read(&mut buf)? {
+                    0 => return Ok(()),
+                    n => h.update(&buf[..n]),
+                }
+            }
+        })()
+        .chain_err(|| format!("failed to calculate checksum of: {}", file.display()))?;
+
+        let actual = hex::encode(h.finish());
+        if &*actual != cksum {
+            ret.push(format!(
+                "the listed checksum of `{}` has changed:\n\
+                 expected: {}\n\
+                 actual:   {}",
+                file.display(),
+                cksum,
+                actual
+            ));
+        }
+    }
+    Ok(ret)
 }
 
 impl<'cfg> Debug for DirectorySource<'cfg> {
@@ -174,43 +233,32 @@ impl<'cfg> Source for DirectorySource<'cfg> {
             None => bail!("failed to find entry for `{}` in directory source", id),
         };
 
-        let mut buf = [0; 16 * 1024];
-        for (file, cksum) in cksum.files.iter() {
-            let mut h = Sha256::new();
-            let file = pkg.root().join(file);
-
-            (|| -> CargoResult<()> {
-                let mut f = File::open(&file)?;
-                loop {
-                    match f.read(&mut buf)? {
-                        0 => return Ok(()),
-                        n => h.update(&buf[..n]),
-                    }
-                }
-            })()
-            .chain_err(|| format!("failed to calculate checksum of: {}", file.display()))?;
-
-            let actual = hex::encode(h.finish());
-            if &*actual != cksum {
-                bail!(
-                    "\
-                     the listed checksum of `{}` has changed:\n\
-                     expected: {}\n\
-                     actual:   {}\n\
-                     \n\
-                     directory sources are not intended to be edited, if \
-                     modifications are required then it is recommended \
-                     that [replace] is used with a forked copy of the \
-                     source\
-                     ",
-                    file.display(),
-                    cksum,
-                    actual
-                );
-            }
+        let changes = changed_files(pkg, cksum)?;
+        if changes.is_empty() {
+            return Ok(());
         }
-
-        Ok(())
+        let changes = changes.join("\n");
+        if self.config.cli_unstable().allow_dirty_vendor {
+            self.config.shell().warn(format!(
+                "building `{} v{}` with modified sources\n{}",
+                id.name(),
+                id.version(),
+                changes
+            ))?;
+            return Ok(());
+        }
+        bail!(
+            "\
+             {}\n\
+             \n\
+             directory sources are not intended to be edited, if \
+             modifications are required then it is recommended \
+             that [replace] is used with a forked copy of the \
+             source, or pass `-Z allow-dirty-vendor` to build with \
+             the modified sources anyway\
+             ",
+            changes
+        )
     }
 
     fn describe(&self) -> String {
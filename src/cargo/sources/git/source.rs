@@ -153,16 +153,19 @@ impl<'cfg> Source for GitSource<'cfg> {
     }
 
     fn update(&mut self) -> CargoResult<()> {
-        let lock =
-            self.config
-                .git_path()
-                .open_rw(".cargo-lock-git", self.config, "the git checkouts")?;
+        let git_path = self.config.git_path();
+        let _lock = self.config.home_lock_dir(&git_path).open_rw(
+            ".cargo-lock-git",
+            self.config,
+            "the git checkouts",
+        )?;
 
-        let db_path = lock.parent().join("db").join(&self.ident);
+        let git_path = git_path.into_path_unlocked();
+        let db_path = git_path.join("db").join(&self.ident);
 
-        if self.config.cli_unstable().offline && !db_path.exists() {
+        if self.config.offline() && !db_path.exists() {
             bail!(
-                "can't checkout from '{}': you are in the offline mode (-Z offline)",
+                "can't checkout from '{}': you are in the offline mode (--offline)",
                 self.remote.url()
             );
         }
@@ -174,7 +177,7 @@ impl<'cfg> Source for GitSource<'cfg> {
         let actual_rev = self.remote.rev_for(&db_path, &self.reference);
         let should_update = actual_rev.is_err() || self.source_id.precise().is_none();
 
-        let (db, actual_rev) = if should_update && !self.config.cli_unstable().offline {
+        let (db, actual_rev) = if should_update && !self.config.offline() {
             self.config.shell().status(
                 "Updating",
                 format!("git repository `{}`", self.remote.url()),
@@ -193,8 +196,7 @@ impl<'cfg> Source for GitSource<'cfg> {
         // https://github.com/servo/servo/pull/14397
         let short_id = db.to_short_id(&actual_rev).unwrap();
 
-        let checkout_path = lock
-            .parent()
+        let checkout_path = git_path
             .join("checkouts")
             .join(&self.ident)
             .join(short_id.as_str());
@@ -266,10 +266,15 @@ impl<'cfg> PathSource<'cfg> {
         //
         // We walk this package's path upwards and look for a sibling
         // Cargo.toml and .git folder. If we find one then we assume that we're
-        // part of that repository.
+        // part of that repository. The root of a repository is checked even
+        // without a Cargo.toml, as packages are often nested deeper inside of
+        // submodules.
         let mut cur = root;
         loop {
-            if cur.join("Cargo.toml").is_file() {
+            // In submodules and worktrees `.git` is a file pointing to the
+            // actual git directory.
+            let is_repo_root = cur.join(".git").exists();
+            if cur.join("Cargo.toml").is_file() || is_repo_root {
                 // If we find a git repository next to this Cargo.toml, we still
                 // check to see if we are indeed part of the index. If not, then
                 // this is likely an unrelated git repo, so keep going.
@@ -285,7 +290,7 @@ impl<'cfg> PathSource<'cfg> {
                 }
             }
             // don't cross submodule boundaries
-            if cur.join(".git").is_dir() {
+            if is_repo_root {
                 break;
             }
             match cur.parent() {
@@ -495,6 +500,23 @@ impl<'cfg> PathSource<'cfg> {
         Ok((max, max_path))
     }
 
+    /// Hashes the paths and contents of all files in the package, used as
+    /// its fingerprint with `-Z content-hash`.
+    pub fn content_hash(&self, pkg: &Package) -> CargoResult<u64> {
+        if !self.updated {
+            return Err(internal("BUG: source was not updated"));
+        }
+
+        let mut files = Vec::new();
+        for file in self.list_files(pkg)? {
+            // Like for mtimes, files which can't be read are ignored.
+            let contents = paths::read_bytes(&file).unwrap_or_default();
+            let relative = file.strip_prefix(pkg.root()).unwrap_or(&file);
+            files.push((relative.to_path_buf(), util::hash_u64(&contents)));
+        }
+        Ok(util::hash_u64(&files))
+    }
+
     pub fn path(&self) -> &Path {
         &self.path
     }
@@ -559,6 +581,10 @@ impl<'cfg> Source for PathSource<'cfg> {
     }
 
     fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
+        if self.config.cli_unstable().content_hash {
+            let hash = self.content_hash(pkg)?;
+            return Ok(format!("content hash {}", util::to_hex(hash)));
+        }
         let (max, max_path) = self.last_modified_file(pkg)?;
         Ok(format!("{} ({})", max, max_path.display()))
     }
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::path::Path;
 use std::str;
@@ -9,7 +10,7 @@ use crate::core::dependency::Dependency;
 use crate::core::{PackageId, SourceId, Summary};
 use crate::sources::registry::RegistryData;
 use crate::sources::registry::{RegistryPackage, INDEX_LOCK};
-use crate::util::{internal, CargoResult, Config, Filesystem};
+use crate::util::{internal, profile, CargoResult, Config, Filesystem};
 
 /// Crates.io treats hyphen and underscores as interchangeable
 /// but, the index and old cargo do not. So the index must store uncanonicalized version
@@ -97,10 +98,46 @@ fn overflow_hyphen() {
     )
 }
 
+/// A version of a package listed in the index.
+///
+/// Only the name, version, checksum and yanked status are read up front, the
+/// rest of the line, most importantly the dependencies, is only parsed into a
+/// `Summary` once a query asks for this version. This way the resolver only
+/// pays for the versions it considers, and the names of the dependencies of
+/// all other versions are never looked at: the names whose index files are
+/// read are exactly the ones reachable from the versions the resolver
+/// considers, as it queries them.
+struct IndexSummary {
+    version: Version,
+    yanked: bool,
+    summary: MaybeSummary,
+}
+
+enum MaybeSummary {
+    Unparsed(String),
+    Parsed(Summary),
+    /// The line couldn't be parsed, the version is ignored.
+    Invalid,
+}
+
+/// The fields of an index line which are read when the index file of a
+/// package is loaded.
+#[derive(Deserialize)]
+struct IndexVersion<'a> {
+    #[serde(borrow)]
+    name: Cow<'a, str>,
+    vers: Version,
+    cksum: String,
+    yanked: Option<bool>,
+}
+
 pub struct RegistryIndex<'cfg> {
     source_id: SourceId,
     path: Filesystem,
-    cache: HashMap<&'static str, Vec<(Summary, bool)>>,
+    /// The versions listed for each name which has been queried. Names which
+    /// aren't in the index are cached as well, so each name is only looked up
+    /// once.
+    cache: HashMap<&'static str, Vec<IndexSummary>>,
     hashes: HashMap<&'static str, HashMap<Version, String>>, // (name, vers) => cksum
     config: &'cfg Config,
     locked: bool,
@@ -131,7 +168,7 @@ impl<'cfg> RegistryIndex<'cfg> {
             return Ok(s.clone());
         }
         // Ok, we're missing the key, so parse the index file to load it.
-        self.summaries(name, load)?;
+        self.load(name, load)?;
         self.hashes
             .get(name)
             .and_then(|v| v.get(version))
@@ -139,44 +176,62 @@ impl<'cfg> RegistryIndex<'cfg> {
             .map(|s| s.clone())
     }
 
-    /// Parse the on-disk metadata for the package provided
-    ///
-    /// Returns a list of pairs of (summary, yanked) for the package name
-    /// specified.
-    pub fn summaries(
+    /// Return the summary listed in the index for a specified PackageId.
+    pub fn summary(
+        &mut self,
+        pkg: PackageId,
+        load: &mut RegistryData,
+    ) -> CargoResult<Option<Summary>> {
+        let source_id = self.source_id;
+        let summaries = self.load(pkg.name().as_str(), load)?;
+        Ok(summaries
+            .iter_mut()
+            .filter(|s| s.version == *pkg.version())
+            .filter_map(|s| s.parse(source_id))
+            .find(|s| s.package_id() == pkg))
+    }
+
+    /// Load the versions listed in the index file for the package `name`, if
+    /// that hasn't happened already.
+    fn load(
         &mut self,
         name: &'static str,
         load: &mut RegistryData,
-    ) -> CargoResult<&Vec<(Summary, bool)>> {
-        if self.cache.contains_key(name) {
-            return Ok(&self.cache[name]);
+    ) -> CargoResult<&mut Vec<IndexSummary>> {
+        if !self.cache.contains_key(name) {
+            let summaries = self.load_summaries(name, load)?;
+            self.cache.insert(name, summaries);
         }
-        let summaries = self.load_summaries(name, load)?;
-        self.cache.insert(name, summaries);
-        Ok(&self.cache[name])
+        Ok(self.cache.get_mut(name).unwrap())
     }
 
     fn load_summaries(
         &mut self,
         name: &str,
         load: &mut RegistryData,
-    ) -> CargoResult<Vec<(Summary, bool)>> {
+    ) -> CargoResult<Vec<IndexSummary>> {
+        let _p = profile::start(format!("parsing the index of `{}`", name));
         // Prepare the `RegistryData` which will lazily initialize internal data
         // structures. Note that this is also importantly needed to initialize
         // to avoid deadlocks where we acquire a lock below but the `load`
         // function inside *also* wants to acquire a lock. See an instance of
         // this on #5551.
         load.prepare()?;
-        let (root, _lock) = if self.locked {
-            let lock = self
-                .path
-                .open_ro(Path::new(INDEX_LOCK), self.config, "the registry index");
+        let root = self.path.clone().into_path_unlocked();
+        let _lock = if self.locked {
+            let lock = self.config.open_home_lock_ro(
+                &self.path,
+                Path::new(INDEX_LOCK),
+                "the registry index",
+            );
             match lock {
-                Ok(lock) => (lock.path().parent().unwrap().to_path_buf(), Some(lock)),
-                Err(_) => return Ok(Vec::new()),
+                Ok(lock) => Some(lock),
+                // Without a lock file there's no index yet.
+                Err(_) if !root.exists() => return Ok(Vec::new()),
+                Err(e) => return Err(e),
             }
         } else {
-            (self.path.clone().into_path_unlocked(), None)
+            None
         };
 
         let fs_name = name
@@ -201,26 +256,57 @@ impl<'cfg> RegistryIndex<'cfg> {
                 ret.reserve(contents.lines().count());
                 let lines = contents.lines().map(|s| s.trim()).filter(|l| !l.is_empty());
 
-                let online = !self.config.cli_unstable().offline;
+                let online = !self.config.offline();
+                let source_id = self.source_id;
+                let hashes = &mut self.hashes;
+                let canonical = canonical_name(name);
                 // Attempt forwards-compatibility on the index by ignoring
                 // everything that we ourselves don't understand, that should
                 // allow future cargo implementations to break the
                 // interpretation of each line here and older cargo will simply
                 // ignore the new lines.
                 ret.extend(lines.filter_map(|line| {
-                    let (summary, locked) = match self.parse_registry_package(line) {
-                        Ok(p) => p,
+                    let IndexVersion {
+                        name: pkg_name,
+                        vers,
+                        cksum,
+                        yanked,
+                    } = match serde_json::from_str(line) {
+                        Ok(v) => v,
                         Err(e) => {
                             info!("failed to parse `{}` registry package: {}", name, e);
                             trace!("line: {}", line);
                             return None;
                         }
                     };
-                    if online || load.is_crate_downloaded(summary.package_id()) {
-                        Some((summary, locked))
-                    } else {
-                        None
+                    // The file is shared by the names which only differ in
+                    // case and in `-` and `_`, any other line doesn't belong
+                    // to it and is skipped before being parsed further.
+                    if canonical_name(&pkg_name) != canonical {
+                        info!("skipping `{}` in the index file of `{}`", pkg_name, name);
+                        trace!("line: {}", line);
+                        return None;
+                    }
+                    let pkgid = match PackageId::new(&pkg_name, &vers, source_id) {
+                        Ok(pkgid) => pkgid,
+                        Err(e) => {
+                            info!("failed to parse `{}` registry package: {}", name, e);
+                            trace!("line: {}", line);
+                            return None;
+                        }
+                    };
+                    if !online && !load.is_crate_downloaded(pkgid) {
+                        return None;
                     }
+                    hashes
+                        .entry(pkgid.name().as_str())
+                        .or_insert_with(HashMap::new)
+                        .insert(vers.clone(), cksum);
+                    Some(IndexSummary {
+                        version: vers,
+                        yanked: yanked.unwrap_or(false),
+                        summary: MaybeSummary::Unparsed(line.to_string()),
+                    })
                 }));
 
                 Ok(())
@@ -240,48 +326,42 @@ impl<'cfg> RegistryIndex<'cfg> {
         Ok(ret)
     }
 
-    /// Parse a line from the registry's index file into a Summary for a
-    /// package.
-    ///
-    /// The returned boolean is whether or not the summary has been yanked.
-    fn parse_registry_package(&mut self, line: &str) -> CargoResult<(Summary, bool)> {
-        let RegistryPackage {
-            name,
-            vers,
-            cksum,
-            deps,
-            features,
-            yanked,
-            links,
-        } = serde_json::from_str(line)?;
-        let pkgid = PackageId::new(&name, &vers, self.source_id)?;
-        let name = pkgid.name();
-        let deps = deps
-            .into_iter()
-            .map(|dep| dep.into_dep(self.source_id))
-            .collect::<CargoResult<Vec<_>>>()?;
-        let summary = Summary::new(pkgid, deps, &features, links, false)?;
-        let summary = summary.set_checksum(cksum.clone());
-        self.hashes
-            .entry(name.as_str())
-            .or_insert_with(HashMap::new)
-            .insert(vers, cksum);
-        Ok((summary, yanked.unwrap_or(false)))
+    pub fn query_inner(
+        &mut self,
+        dep: &Dependency,
+        load: &mut RegistryData,
+        f: &mut FnMut(Summary),
+    ) -> CargoResult<()> {
+        self.query_summaries(dep, load, false, f)
     }
 
-    pub fn query_inner(
+    /// Like `query_inner`, but also returns the versions which don't match
+    /// the version requirement of `dep`.
+    pub fn fuzzy_query_inner(
         &mut self,
         dep: &Dependency,
         load: &mut RegistryData,
         f: &mut FnMut(Summary),
+    ) -> CargoResult<()> {
+        self.query_summaries(dep, load, true, f)
+    }
+
+    fn query_summaries(
+        &mut self,
+        dep: &Dependency,
+        load: &mut RegistryData,
+        fuzzy: bool,
+        f: &mut FnMut(Summary),
     ) -> CargoResult<()> {
         let source_id = self.source_id;
         let name = dep.package_name().as_str();
-        let summaries = self.summaries(name, load)?;
+        let summaries = self.load(name, load)?;
+        // Only the versions which can match are parsed, see `IndexSummary`.
         let summaries = summaries
-            .iter()
-            .filter(|&&(_, yanked)| dep.source_id().precise().is_some() || !yanked)
-            .map(|s| s.0.clone());
+            .iter_mut()
+            .filter(|s| dep.source_id().precise().is_some() || !s.yanked)
+            .filter(|s| fuzzy || dep.version_req().matches(&s.version))
+            .filter_map(|s| s.parse(source_id));
 
         // Handle `cargo update --precise` here. If specified, our own source
         // will have a precise version listed of the form
@@ -309,3 +389,102 @@ impl<'cfg> RegistryIndex<'cfg> {
         Ok(())
     }
 }
+
+impl IndexSummary {
+    /// Returns the summary of this version, parsing it on first use.
+    fn parse(&mut self, source_id: SourceId) -> Option<Summary> {
+        if let MaybeSummary::Unparsed(ref line) = self.summary {
+            self.summary = match parse_registry_package(line, source_id) {
+                Ok(summary) => MaybeSummary::Parsed(summary),
+                Err(e) => {
+                    info!("failed to parse `{}` registry package: {}", self.version, e);
+                    trace!("line: {}", line);
+                    MaybeSummary::Invalid
+                }
+            };
+        }
+        match self.summary {
+            MaybeSummary::Parsed(ref summary) => Some(summary.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// The name under which `name` and the names which only differ from it in
+/// case and in `-` and `_` share an index file.
+fn canonical_name(name: &str) -> String {
+    name.chars()
+        .flat_map(|c| c.to_lowercase())
+        .map(|c| if c == '_' { '-' } else { c })
+        .collect()
+}
+
+/// Parse a line from the registry's index file into a Summary for a package.
+fn parse_registry_package(line: &str, source_id: SourceId) -> CargoResult<Summary> {
+    let RegistryPackage {
+        name,
+        vers,
+        cksum,
+        deps,
+        features,
+        links,
+        index_fields,
+    } = serde_json::from_str(line)?;
+    let pkgid = PackageId::new(&name, &vers, source_id)?;
+    let deps = deps
+        .into_iter()
+        .map(|dep| dep.into_dep(source_id))
+        .collect::<CargoResult<Vec<_>>>()?;
+    let summary = Summary::new(pkgid, deps, &features, links, false)?;
+    Ok(summary.set_checksum(cksum).set_index_fields(index_fields))
+}
+
+#[test]
+fn skips_malformed_and_unrelated_lines() {
+    use std::fs;
+
+    use tempfile::Builder as TempFileBuilder;
+
+    use crate::sources::registry::local::LocalRegistry;
+
+    let root = TempFileBuilder::new()
+        .prefix("cargo-index")
+        .tempdir()
+        .unwrap();
+    let line = |name: &str, vers: &str, deps: &str| {
+        format!(
+            r#"{{"name":"{}","vers":"{}","deps":[{}],"cksum":"00","features":{{}}}}"#,
+            name, vers, deps
+        )
+    };
+    let bad_dep = r#"{"name":"baz","req":"not a req","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"}"#;
+    let lines = [
+        line("foo", "0.1.0", ""),
+        // Not even JSON.
+        "not json".to_string(),
+        line("foo", "not a version", ""),
+        // A package which has nothing to do in the index file of `foo`.
+        line("bar", "0.2.0", ""),
+        // Only invalid once its dependencies are parsed.
+        line("foo", "0.3.0", bad_dep),
+        line("foo", "0.4.0", ""),
+    ];
+    let dir = root.path().join("index/3/f");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("foo"), lines.join("\n")).unwrap();
+
+    let config = Config::default().unwrap();
+    let source_id = SourceId::for_local_registry(root.path()).unwrap();
+    let mut load = LocalRegistry::new(root.path(), &config, "test");
+    let path = Filesystem::new(root.path().join("index"));
+    let mut index = RegistryIndex::new(source_id, &path, &config, false);
+    let dep = Dependency::parse_no_deprecated("foo", None, source_id).unwrap();
+    let mut versions = Vec::new();
+    index
+        .query_inner(&dep, &mut load, &mut |s| {
+            versions.push(s.version().to_string())
+        })
+        .unwrap();
+    versions.sort();
+    assert_eq!(versions, ["0.1.0", "0.4.0"]);
+}
@@ -1,9 +1,11 @@
+use std::fs;
 use std::io::prelude::*;
 use std::io::SeekFrom;
 use std::path::Path;
 
 use crate::core::PackageId;
 use hex;
+use crate::sources::registry::{index_dir_may_contain, index_file_matches};
 use crate::sources::registry::{MaybeLock, RegistryConfig, RegistryData};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::paths;
@@ -51,6 +53,37 @@ impl<'cfg> RegistryData for LocalRegistry<'cfg> {
         Ok(None)
     }
 
+    fn crate_names(&self, prefix: &str) -> CargoResult<Vec<String>> {
+        fn walk(
+            path: &Path,
+            dir: &[&str],
+            prefix: &str,
+            names: &mut Vec<String>,
+        ) -> CargoResult<()> {
+            let entries =
+                fs::read_dir(path).chain_err(|| format!("failed to read `{}`", path.display()))?;
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type()?.is_dir() {
+                    let mut sub = dir.to_vec();
+                    sub.push(&name);
+                    if index_dir_may_contain(&sub, prefix) {
+                        walk(&entry.path(), &sub, prefix, names)?;
+                    }
+                } else if index_file_matches(dir, &name, prefix) {
+                    names.push(name);
+                }
+            }
+            Ok(())
+        }
+
+        let mut names = Vec::new();
+        let root = self.index_path.clone().into_path_unlocked();
+        walk(&root, &[], prefix, &mut names)?;
+        Ok(names)
+    }
+
     fn update_index(&mut self) -> CargoResult<()> {
         // Nothing to update, we just use what's on disk. Verify it actually
         // exists though. We don't use any locks as we're just checking whether
@@ -160,18 +160,20 @@
 
 use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 
 use flate2::read::GzDecoder;
 use semver::Version;
+use serde::de;
 #[cfg(test)]
 use serde_json;
 use tar::Archive;
 
 use crate::core::dependency::{Dependency, Kind};
 use crate::core::source::MaybePackage;
-use crate::core::{Package, PackageId, Source, SourceId, Summary};
+use crate::core::{IndexFields, Package, PackageId, Source, SourceId, Summary};
 use crate::sources::PathSource;
 use crate::util::errors::CargoResultExt;
 use crate::util::hex;
@@ -214,15 +216,76 @@ pub struct RegistryConfig {
     pub api: Option<String>,
 }
 
-#[derive(Deserialize)]
 pub struct RegistryPackage<'a> {
     name: Cow<'a, str>,
     vers: Version,
     deps: Vec<RegistryDependency<'a>>,
     features: BTreeMap<Cow<'a, str>, Vec<Cow<'a, str>>>,
     cksum: String,
-    yanked: Option<bool>,
     links: Option<Cow<'a, str>>,
+    /// The fields cargo doesn't know about, kept for the summary.
+    index_fields: IndexFields,
+}
+
+// Not derived: `#[serde(flatten)]` would buffer every line before parsing
+// it, while only the unknown fields need to be kept as JSON values.
+impl<'de> de::Deserialize<'de> for RegistryPackage<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct RegistryPackageVisitor;
+
+        impl<'de> de::Visitor<'de> for RegistryPackageVisitor {
+            type Value = RegistryPackage<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a registry package")
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::MapAccess<'de>,
+            {
+                let mut name = None;
+                let mut vers = None;
+                let mut deps = None;
+                let mut features = None;
+                let mut cksum = None;
+                let mut links = None;
+                let mut index_fields = IndexFields::new();
+                while let Some(field) = map.next_key()? {
+                    match field {
+                        Field::Name => name = Some(map.next_value()?),
+                        Field::Vers => vers = Some(map.next_value()?),
+                        Field::Deps => deps = Some(map.next_value()?),
+                        Field::Features => features = Some(map.next_value()?),
+                        Field::Cksum => cksum = Some(map.next_value()?),
+                        Field::Links => links = map.next_value()?,
+                        // Read along with the version, see `IndexVersion`.
+                        Field::Yanked => {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                        Field::Other(key) => {
+                            let value = map.next_value()?;
+                            index_fields.insert(key, value);
+                        }
+                    }
+                }
+                Ok(RegistryPackage {
+                    name: name.ok_or_else(|| de::Error::missing_field("name"))?,
+                    vers: vers.ok_or_else(|| de::Error::missing_field("vers"))?,
+                    deps: deps.ok_or_else(|| de::Error::missing_field("deps"))?,
+                    features: features.ok_or_else(|| de::Error::missing_field("features"))?,
+                    cksum: cksum.ok_or_else(|| de::Error::missing_field("cksum"))?,
+                    links,
+                    index_fields,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(RegistryPackageVisitor)
+    }
 }
 
 #[test]
@@ -258,6 +321,21 @@ fn escaped_cher_in_json() {
     .unwrap();
 }
 
+#[test]
+fn unknown_fields_in_json() {
+    let pkg: RegistryPackage = serde_json::from_str(
+        r#"{"name":"a","vers":"0.0.1","deps":[],"cksum":"bae3","features":{},"yanked":false,
+            "rust_version":"1.31","yank_reason":null,"hints":{"artifacts":["bin"]},"v":2}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        pkg.index_fields.keys().collect::<Vec<_>>(),
+        ["hints", "rust_version", "v", "yank_reason"]
+    );
+    assert_eq!(pkg.index_fields["rust_version"], "1.31");
+    assert_eq!(pkg.index_fields["hints"]["artifacts"][0], "bin");
+}
+
 #[derive(Deserialize)]
 #[serde(field_identifier, rename_all = "lowercase")]
 enum Field {
@@ -268,6 +346,9 @@ enum Field {
     Cksum,
     Yanked,
     Links,
+    /// Any other field, like the `v` and `features2` of newer index formats,
+    /// which cargo doesn't interpret.
+    Other(String),
 }
 
 #[derive(Deserialize)]
@@ -347,6 +428,9 @@ pub trait RegistryData {
         data: &mut FnMut(&[u8]) -> CargoResult<()>,
     ) -> CargoResult<()>;
     fn config(&mut self) -> CargoResult<Option<RegistryConfig>>;
+    /// The names of the crates in the index starting with `prefix`, which is
+    /// lowercase.
+    fn crate_names(&self, prefix: &str) -> CargoResult<Vec<String>>;
     fn update_index(&mut self) -> CargoResult<()>;
     fn download(&mut self, pkg: PackageId, checksum: &str) -> CargoResult<MaybeLock>;
     fn finish_download(
@@ -370,6 +454,39 @@ mod index;
 mod local;
 mod remote;
 
+/// Whether the directory of an index with the path `dir` may contain crates
+/// whose names start with `prefix`. Names of one, two and three characters
+/// are in `1`, `2` and `3/{first}`, longer ones in `{first two}/{next two}`.
+fn index_dir_may_contain(dir: &[&str], prefix: &str) -> bool {
+    let covers = |part: &str, start: usize| {
+        part.chars()
+            .zip(prefix.chars().skip(start))
+            .all(|(a, b)| a == b)
+    };
+    let short = |len: usize| prefix.chars().count() <= len;
+    match dir.len() {
+        0 => true,
+        1 => match dir[0] {
+            "1" => short(1),
+            "2" => short(2),
+            "3" => short(3),
+            part => part.len() == 2 && covers(part, 0),
+        },
+        2 if dir[0] == "3" => short(3) && covers(dir[1], 0),
+        2 => covers(dir[0], 0) && covers(dir[1], 2),
+        _ => false,
+    }
+}
+
+/// Whether the file `name` in the directory `dir` of an index describes a
+/// crate whose name starts with `prefix`.
+fn index_file_matches(dir: &[&str], name: &str, prefix: &str) -> bool {
+    !dir.is_empty()
+        && !name.starts_with('.')
+        && name.starts_with(prefix)
+        && index_dir_may_contain(dir, prefix)
+}
+
 fn short_name(id: SourceId) -> String {
     let hash = hex::short_hash(&id);
     let ident = id.url().host_str().unwrap_or("").to_string();
@@ -414,6 +531,14 @@ impl<'cfg> RegistrySource<'cfg> {
         self.ops.config()
     }
 
+    /// The names of the crates in the index starting with `prefix`, read from
+    /// the local copy of the index, which isn't updated.
+    pub fn crate_names(&mut self, prefix: &str) -> CargoResult<Vec<String>> {
+        let mut names = self.ops.crate_names(&prefix.to_lowercase())?;
+        names.sort();
+        Ok(names)
+    }
+
     /// Unpacks a downloaded package into a location where it's ready to be
     /// compiled.
     ///
@@ -490,16 +615,12 @@ impl<'cfg> RegistrySource<'cfg> {
         // differ due to historical Cargo bugs. To paper over these we trash the
         // *summary* loaded from the Cargo.toml we just downloaded with the one
         // we loaded from the index.
-        let summaries = self
+        let summary = self
             .index
-            .summaries(package.name().as_str(), &mut *self.ops)?;
-        let summary = summaries
-            .iter()
-            .map(|s| &s.0)
-            .find(|s| s.package_id() == package)
+            .summary(package, &mut *self.ops)?
             .expect("summary not found");
         let mut manifest = pkg.manifest().clone();
-        manifest.set_summary(summary.clone());
+        manifest.set_summary(summary);
         Ok(Package::new(manifest, pkg.manifest_path()))
     }
 }
@@ -535,7 +656,7 @@ impl<'cfg> Source for RegistrySource<'cfg> {
     }
 
     fn fuzzy_query(&mut self, dep: &Dependency, f: &mut FnMut(Summary)) -> CargoResult<()> {
-        self.index.query_inner(dep, &mut *self.ops, f)
+        self.index.fuzzy_query_inner(dep, &mut *self.ops, f)
     }
 
     fn supports_checksums(&self) -> bool {
@@ -13,7 +13,7 @@ use serde_json;
 
 use crate::core::{PackageId, SourceId};
 use crate::sources::git;
-use crate::sources::registry::MaybeLock;
+use crate::sources::registry::{index_dir_may_contain, index_file_matches, MaybeLock};
 use crate::sources::registry::{
     RegistryConfig, RegistryData, CRATE_TEMPLATE, INDEX_LOCK, VERSION_TEMPLATE,
 };
@@ -56,7 +56,7 @@ impl<'cfg> RemoteRegistry<'cfg> {
 
             // Ok, now we need to lock and try the whole thing over again.
             trace!("acquiring registry index lock");
-            let lock = self.index_path.open_rw(
+            let lock = self.config.home_lock_dir(&self.index_path).open_rw(
                 Path::new(INDEX_LOCK),
                 self.config,
                 "the registry index",
@@ -129,6 +129,18 @@ impl<'cfg> RemoteRegistry<'cfg> {
     fn filename(&self, pkg: PackageId) -> String {
         format!("{}-{}.crate", pkg.name(), pkg.version())
     }
+
+    /// The directories downloaded crates are looked for in: the cache of the
+    /// home, then the one of `CARGO_HOME_OVERLAY`, where crates are
+    /// downloaded to when it's set.
+    fn cache_paths(&self) -> Vec<Filesystem> {
+        let overlay = self.config.home_lock_dir(&self.cache_path);
+        if *self.cache_path.clone().into_path_unlocked() == overlay {
+            vec![overlay]
+        } else {
+            vec![self.cache_path.clone(), overlay]
+        }
+    }
 }
 
 impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
@@ -164,9 +176,11 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
     fn config(&mut self) -> CargoResult<Option<RegistryConfig>> {
         debug!("loading config");
         self.prepare()?;
-        let _lock =
-            self.index_path
-                .open_ro(Path::new(INDEX_LOCK), self.config, "the registry index")?;
+        let _lock = self.config.open_home_lock_ro(
+            &self.index_path,
+            Path::new(INDEX_LOCK),
+            "the registry index",
+        )?;
         let mut config = None;
         self.load(Path::new(""), Path::new("config.json"), &mut |json| {
             config = Some(serde_json::from_slice(json)?);
@@ -176,8 +190,50 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         Ok(config)
     }
 
+    fn crate_names(&self, prefix: &str) -> CargoResult<Vec<String>> {
+        fn walk(
+            repo: &git2::Repository,
+            tree: &git2::Tree,
+            dir: &[&str],
+            prefix: &str,
+            names: &mut Vec<String>,
+        ) -> CargoResult<()> {
+            for entry in tree.iter() {
+                let name = match entry.name() {
+                    Some(name) => name,
+                    None => continue,
+                };
+                match entry.kind() {
+                    Some(git2::ObjectType::Tree) => {
+                        let mut sub = dir.to_vec();
+                        sub.push(name);
+                        if index_dir_may_contain(&sub, prefix) {
+                            let tree = repo.find_tree(entry.id())?;
+                            walk(repo, &tree, &sub, prefix, names)?;
+                        }
+                    }
+                    Some(git2::ObjectType::Blob) if index_file_matches(dir, name, prefix) => {
+                        names.push(name.to_string());
+                    }
+                    _ => {}
+                }
+            }
+            Ok(())
+        }
+
+        self.prepare()?;
+        let _lock = self.config.open_home_lock_ro(
+            &self.index_path,
+            Path::new(INDEX_LOCK),
+            "the registry index",
+        )?;
+        let mut names = Vec::new();
+        walk(self.repo()?, &*self.tree()?, &[], prefix, &mut names)?;
+        Ok(names)
+    }
+
     fn update_index(&mut self) -> CargoResult<()> {
-        if self.config.cli_unstable().offline {
+        if self.config.offline() {
             return Ok(());
         }
         if self.config.cli_unstable().no_index_update {
@@ -197,9 +253,11 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         self.prepare()?;
         self.head.set(None);
         *self.tree.borrow_mut() = None;
-        let _lock =
-            self.index_path
-                .open_rw(Path::new(INDEX_LOCK), self.config, "the registry index")?;
+        let _lock = self.config.home_lock_dir(&self.index_path).open_rw(
+            Path::new(INDEX_LOCK),
+            self.config,
+            "the registry index",
+        )?;
         self.config
             .shell()
             .status("Updating", self.source_id.display_registry())?;
@@ -222,10 +280,12 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         //
         // If this fails then we fall through to the exclusive path where we may
         // have to redownload the file.
-        if let Ok(dst) = self.cache_path.open_ro(&filename, self.config, &filename) {
-            let meta = dst.file().metadata()?;
-            if meta.len() > 0 {
-                return Ok(MaybeLock::Ready(dst));
+        for cache_path in self.cache_paths() {
+            if let Ok(dst) = cache_path.open_ro(&filename, self.config, &filename) {
+                let meta = dst.file().metadata()?;
+                if meta.len() > 0 {
+                    return Ok(MaybeLock::Ready(dst));
+                }
             }
         }
 
@@ -258,7 +318,10 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         }
 
         let filename = self.filename(pkg);
-        let mut dst = self.cache_path.open_rw(&filename, self.config, &filename)?;
+        let mut dst = self
+            .config
+            .home_lock_dir(&self.cache_path)
+            .open_rw(&filename, self.config, &filename)?;
         let meta = dst.file().metadata()?;
         if meta.len() > 0 {
             return Ok(dst);
@@ -273,12 +336,12 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
         let path = Path::new(&filename);
 
-        if let Ok(dst) = self.cache_path.open_ro(path, self.config, &filename) {
-            if let Ok(meta) = dst.file().metadata() {
-                return meta.len() > 0;
+        self.cache_paths().iter().any(|cache_path| {
+            match cache_path.open_ro(path, self.config, &filename) {
+                Ok(dst) => dst.file().metadata().map(|m| m.len() > 0).unwrap_or(false),
+                Err(_) => false,
             }
-        }
-        false
+        })
     }
 }
 
@@ -3,8 +3,8 @@ use std::fs;
 
 use clap::{self, SubCommand};
 use crate::CargoResult;
-use crate::core::Workspace;
-use crate::core::compiler::{BuildConfig, MessageFormat};
+use crate::core::{PackageIdSpec, Workspace};
+use crate::core::compiler::{BuildConfig, MessageFormat, WarningHandling};
 use crate::ops::{CompileFilter, CompileOptions, NewOptions, Packages, VersionControl};
 use crate::sources::CRATES_IO_REGISTRY;
 use crate::util::paths;
@@ -12,7 +12,7 @@ use crate::util::important_paths::find_root_manifest_for_wd;
 
 pub use clap::{AppSettings, Arg, ArgMatches};
 pub use crate::{CliError, CliResult, Config};
-pub use crate::core::compiler::CompileMode;
+pub use crate::core::compiler::{CompileMode, ProfileKind};
 
 pub type App = clap::App<'static, 'static>;
 
@@ -89,8 +89,8 @@ pub trait AppExt: Sized {
     }
 
     fn arg_targets_bin_example(self, bin: &'static str, example: &'static str) -> Self {
-        self._arg(multi_opt("bin", "NAME", bin))
-            ._arg(multi_opt("example", "NAME", example))
+        self._arg(optional_multi_opt("bin", "NAME", bin))
+            ._arg(optional_multi_opt("example", "NAME", example))
     }
 
     fn arg_features(self) -> Self {
@@ -107,6 +107,16 @@ pub trait AppExt: Sized {
         self._arg(opt("release", release))
     }
 
+    fn arg_profile(self, profile: &'static str) -> Self {
+        // `check` and `rustc` use `--profile` to select a compile mode instead.
+        self._arg(
+            Arg::with_name("custom-profile")
+                .long("profile")
+                .value_name("PROFILE-NAME")
+                .help(profile),
+        )
+    }
+
     fn arg_doc(self, doc: &'static str) -> Self {
         self._arg(opt("doc", doc))
     }
@@ -115,6 +125,10 @@ pub trait AppExt: Sized {
         self._arg(opt("target", target).value_name("TRIPLE"))
     }
 
+    fn arg_target_triples(self, target: &'static str) -> Self {
+        self._arg(multi_opt("target", "TRIPLE", target))
+    }
+
     fn arg_target_dir(self) -> Self {
         self._arg(opt("target-dir", "Directory for all generated artifacts").value_name("DIRECTORY"))
     }
@@ -137,6 +151,49 @@ pub trait AppExt: Sized {
         self._arg(opt("build-plan", "Output the build plan in JSON"))
     }
 
+    fn arg_target_cpu(self) -> Self {
+        self._arg(
+            opt(
+                "target-cpu",
+                "Generate code for this CPU, `native` for the CPU of this machine",
+            )
+            .value_name("CPU"),
+        )
+    }
+
+    fn arg_emit(self) -> Self {
+        self._arg(multi_opt(
+            "emit",
+            "KINDS",
+            "Also write asm, llvm-ir, llvm-bc or mir files for the selected \
+             packages, collected in target/<profile>/emit",
+        ))
+    }
+
+    fn arg_lipo(self) -> Self {
+        self._arg(opt(
+            "lipo",
+            "Combine the artifacts built for each Apple target into universal ones",
+        ))
+    }
+
+    fn arg_dependency(self) -> Self {
+        self._arg(
+            opt(
+                "dependency",
+                "Only build this dependency of the selected packages, as it's built for them",
+            )
+            .value_name("SPEC"),
+        )
+    }
+
+    fn arg_deny_warnings(self) -> Self {
+        self._arg(opt(
+            "deny-warnings",
+            "Fail the build if compiling workspace members emits warnings",
+        ))
+    }
+
     fn arg_new_opts(self) -> Self {
         self._arg(
             opt(
@@ -199,6 +256,15 @@ pub fn multi_opt(
         .number_of_values(1)
 }
 
+/// Like `multi_opt`, but the value may be left out.
+pub fn optional_multi_opt(
+    name: &'static str,
+    value_name: &'static str,
+    help: &'static str,
+) -> Arg<'static, 'static> {
+    multi_opt(name, value_name, help).min_values(0)
+}
+
 pub fn subcommand(name: &'static str) -> App {
     SubCommand::with_name(name).settings(&[
         AppSettings::UnifiedHelpMessage,
@@ -237,14 +303,23 @@ pub trait ArgMatchesExt {
                     self._value_of("manifest-path").unwrap()
                 )
             }
-            return Ok(path);
+            return config.apply_symlink_policy(path);
         }
         find_root_manifest_for_wd(config.cwd())
     }
 
     fn workspace<'a>(&self, config: &'a Config) -> CargoResult<Workspace<'a>> {
         let root = self.root_manifest(config)?;
-        let mut ws = Workspace::new(&root, config)?;
+        let mut ws = if config.cli_unstable().partial_workspace && !self._is_present("all") {
+            let names = self
+                ._values_of("package")
+                .iter()
+                .map(|spec| Ok(PackageIdSpec::parse(spec)?.name().to_string()))
+                .collect::<CargoResult<Vec<_>>>()?;
+            Workspace::new_partial(&root, config, &names)?
+        } else {
+            Workspace::new(&root, config)?
+        };
         if config.cli_unstable().avoid_dev_deps {
             ws.set_require_optional_deps(false);
         }
@@ -259,6 +334,26 @@ pub trait ArgMatchesExt {
         self._value_of("target").map(|s| s.to_string())
     }
 
+    fn targets(&self) -> Vec<String> {
+        self._values_of("target")
+    }
+
+    fn profile_kind(&self, config: &Config) -> CargoResult<ProfileKind> {
+        let release = self._is_present("release");
+        let name = match self._value_of("custom-profile") {
+            Some(name) => name,
+            None if release => return Ok(ProfileKind::Release),
+            None => return Ok(ProfileKind::Dev),
+        };
+        if !config.cli_unstable().unstable_options {
+            bail!("`--profile` is unstable, pass `-Z unstable-options` to enable it")
+        }
+        if release {
+            bail!("conflicting usage of --profile and --release")
+        }
+        ProfileKind::from_name(name)
+    }
+
     fn compile_options<'a>(
         &self,
         config: &'a Config,
@@ -285,10 +380,51 @@ pub trait ArgMatchesExt {
             }
         };
 
-        let mut build_config = BuildConfig::new(config, self.jobs()?, &self.target(), mode)?;
+        let targets = self.targets();
+        if targets.len() > 1 && !config.cli_unstable().multitarget {
+            Err(format_err!(
+                "multiple `--target` flags are unstable, pass `-Z multitarget` to enable them"
+            ))?;
+        }
+        let mut build_config = BuildConfig::new(config, self.jobs()?, &targets, mode)?;
         build_config.message_format = message_format;
-        build_config.release = self._is_present("release");
+        build_config.profile_kind = self.profile_kind(config)?;
         build_config.build_plan = self._is_present("build-plan");
+        if self._is_present("deny-warnings") {
+            build_config.warnings = WarningHandling::Deny;
+        }
+        build_config.target_cpu = self._value_of("target-cpu").map(|s| s.to_string());
+        for kinds in self._values_of("emit") {
+            for kind in kinds.split(',') {
+                let kind = kind.trim().parse()?;
+                if !build_config.emit.contains(&kind) {
+                    build_config.emit.push(kind);
+                }
+            }
+        }
+        build_config.dependency = match self._value_of("dependency") {
+            Some(spec) => Some(PackageIdSpec::parse(spec)?),
+            None => None,
+        };
+        build_config.lipo = self._is_present("lipo");
+        if build_config.lipo {
+            if build_config.requested_targets.len() < 2 {
+                Err(format_err!(
+                    "`--lipo` requires at least two Apple targets to combine, like \
+                     `--target x86_64-apple-darwin --target aarch64-apple-darwin`"
+                ))?;
+            }
+            if let Some(target) = build_config
+                .requested_targets
+                .iter()
+                .find(|t| !t.contains("-apple-"))
+            {
+                Err(format_err!(
+                    "`--lipo` can only combine Apple targets, which `{}` isn't",
+                    target
+                ))?;
+            }
+        }
         if build_config.build_plan && !config.cli_unstable().unstable_options {
             Err(format_err!(
                 "`--build-plan` flag is unstable, pass `-Z unstable-options` to enable it"
@@ -349,6 +485,7 @@ pub trait ArgMatchesExt {
             self._value_of("name").map(|s| s.to_string()),
             self._value_of("edition").map(|s| s.to_string()),
             self.registry(config)?,
+            config,
         )
     }
 
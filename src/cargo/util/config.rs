@@ -5,7 +5,7 @@ use std::collections::hash_map::HashMap;
 use std::collections::HashSet;
 use std::env;
 use std::fmt;
-use std::fs::{self, File};
+use std::fs::{self, File, OpenOptions};
 use std::io::prelude::*;
 use std::io::SeekFrom;
 use std::mem;
@@ -23,15 +23,15 @@ use serde::{de, de::IntoDeserializer};
 use toml;
 
 use crate::core::profiles::ConfigProfiles;
-use crate::core::shell::Verbosity;
+use crate::core::shell::{Category, ColorChoice, Verbosity};
 use crate::core::{CliUnstable, Shell, SourceId, Workspace};
 use crate::ops;
 use url::Url;
 use crate::util::errors::{internal, CargoResult, CargoResultExt};
 use crate::util::paths;
 use crate::util::toml as cargo_toml;
-use crate::util::Filesystem;
-use crate::util::Rustc;
+use crate::util::{Cfg, CfgExpr, FileLock, Filesystem, HostPlatform};
+use crate::util::{profile, Rustc};
 use crate::util::ToUrl;
 
 use self::ConfigValue as CV;
@@ -44,10 +44,16 @@ use self::ConfigValue as CV;
 pub struct Config {
     /// The location of the user's 'home' directory. OS-dependent.
     home_path: Filesystem,
+    /// Where the files Cargo has to write to a read-only home go, from
+    /// `CARGO_HOME_OVERLAY`.
+    home_overlay: Option<Filesystem>,
     /// Information about how to write messages to the shell
     shell: RefCell<Shell>,
     /// A collection of configuration options
     values: LazyCell<HashMap<String, ConfigValue>>,
+    /// Configuration layered over `values` for part of a command, like the
+    /// configuration file bundled with a crate `cargo install` builds.
+    layer: RefCell<Option<HashMap<String, ConfigValue>>>,
     /// The current working directory of cargo
     cwd: PathBuf,
     /// The location of the cargo executable (path to current process)
@@ -60,6 +66,16 @@ pub struct Config {
     frozen: bool,
     /// `locked` is set if we should not update lock files
     locked: bool,
+    /// `offline` is set if we should never access the network, but use
+    /// whatever is available locally
+    offline: bool,
+    /// `unlocked` is set if we should resolve afresh, ignoring lock files
+    unlocked: bool,
+    /// `ephemeral` is set if we should write lock files to a temporary
+    /// location instead of the workspace
+    ephemeral: bool,
+    /// The lock file written instead of `Cargo.lock` with `--ephemeral`
+    ephemeral_lockfile: RefCell<Option<PathBuf>>,
     /// A global static IPC control mechanism (used for managing parallel builds)
     jobserver: Option<jobserver::Client>,
     /// Cli flags of the form "-Z something"
@@ -78,6 +94,8 @@ pub struct Config {
     env: HashMap<String, String>,
     /// Profiles loaded from config.
     profiles: LazyCell<ConfigProfiles>,
+    /// How the host loads dynamic libraries.
+    host_platform: LazyCell<HostPlatform>,
 }
 
 impl Config {
@@ -108,16 +126,26 @@ impl Config {
             _ => true,
         };
 
+        let home_overlay = env
+            .get("CARGO_HOME_OVERLAY")
+            .map(|overlay| Filesystem::new(cwd.join(overlay)));
+
         Config {
             home_path: Filesystem::new(homedir),
+            home_overlay,
             shell: RefCell::new(shell),
             cwd,
             values: LazyCell::new(),
+            layer: RefCell::new(None),
             cargo_exe: LazyCell::new(),
             rustdoc: LazyCell::new(),
             extra_verbose: false,
             frozen: false,
             locked: false,
+            offline: false,
+            unlocked: false,
+            ephemeral: false,
+            ephemeral_lockfile: RefCell::new(None),
             jobserver: unsafe {
                 if GLOBAL_JOBSERVER.is_null() {
                     None
@@ -133,6 +161,7 @@ impl Config {
             target_dir: None,
             env,
             profiles: LazyCell::new(),
+            host_platform: LazyCell::new(),
         }
     }
 
@@ -154,6 +183,48 @@ impl Config {
         &self.home_path
     }
 
+    /// The directory to take exclusive locks on `dir`, a directory of the
+    /// Cargo home, in. This is `dir` itself, unless `CARGO_HOME_OVERLAY` is
+    /// set for a read-only home, in which case it's the same directory of
+    /// the overlay.
+    pub fn home_lock_dir(&self, dir: &Filesystem) -> Filesystem {
+        let overlay = match self.home_overlay {
+            Some(ref overlay) => overlay,
+            None => return dir.clone(),
+        };
+        let dir = dir.clone().into_path_unlocked();
+        let home = self.home_path.clone().into_path_unlocked();
+        match dir.strip_prefix(&home) {
+            Ok(relative) => overlay.join(relative),
+            Err(_) => Filesystem::new(dir),
+        }
+    }
+
+    /// Takes a shared lock on the file `path` of `dir`, a directory of the
+    /// Cargo home, in `home_lock_dir`, so that it excludes the exclusive
+    /// locks taken there. The lock file is created in the overlay if it isn't
+    /// there yet, but it's an error for it to be missing from the home.
+    pub fn open_home_lock_ro(
+        &self,
+        dir: &Filesystem,
+        path: &Path,
+        msg: &str,
+    ) -> CargoResult<FileLock> {
+        let lock_dir = self.home_lock_dir(dir);
+        if self.home_overlay.is_some() {
+            let file = lock_dir.join(path).into_path_unlocked();
+            if !file.exists() {
+                paths::create_dir_all(file.parent().unwrap())?;
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .open(&file)
+                    .chain_err(|| format!("failed to create `{}`", file.display()))?;
+            }
+        }
+        lock_dir.open_ro(path, self, msg)
+    }
+
     /// The cargo git directory (`<cargo_home>/git`)
     pub fn git_path(&self) -> Filesystem {
         self.home_path.join("git")
@@ -289,9 +360,62 @@ impl Config {
         }
     }
 
+    /// Loads the configuration file at `path` over the configuration found
+    /// in the usual places, with its values taking precedence.
+    pub fn load_overrides(&mut self, path: &Path) -> CargoResult<()> {
+        let mut cfg = self.load_file(path)?;
+        let values = self.values()?.clone();
+        cfg.merge(CV::Table(values, PathBuf::from(".")))
+            .chain_err(|| format!("failed to merge configuration at `{}`", path.display()))?;
+        match cfg {
+            CV::Table(map, _) => {
+                self.values.replace(map);
+                Ok(())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Layers the configuration file at `path` over the configuration, with
+    /// its values taking precedence, until `clear_layer` is called.
+    pub fn set_layer(&self, path: &Path) -> CargoResult<()> {
+        let cfg = self.load_file(path)?;
+        match cfg {
+            CV::Table(map, _) => {
+                *self.layer.borrow_mut() = Some(map);
+                Ok(())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn clear_layer(&self) {
+        *self.layer.borrow_mut() = None;
+    }
+
+    fn load_file(&self, path: &Path) -> CargoResult<ConfigValue> {
+        let contents = paths::read(path)?;
+        let toml = cargo_toml::parse(&contents, path, self).chain_err(|| {
+            format!("could not parse TOML configuration in `{}`", path.display())
+        })?;
+        let mut cfg = CV::from_toml(path, toml).chain_err(|| {
+            format!(
+                "failed to load TOML configuration from `{}`",
+                path.display()
+            )
+        })?;
+        apply_host_cfgs(&mut cfg)?;
+        Ok(cfg)
+    }
+
     pub fn reload_rooted_at_cargo_home(&mut self) -> CargoResult<()> {
         let home = self.home_path.clone().into_path_unlocked();
-        let values = self.load_values_from(&home)?;
+        self.reload_rooted_at(&home)
+    }
+
+    /// Reloads the configuration as if cargo was run in `path`.
+    pub fn reload_rooted_at(&mut self, path: &Path) -> CargoResult<()> {
+        let values = self.load_values_from(path)?;
         self.values.replace(values);
         Ok(())
     }
@@ -301,50 +425,35 @@ impl Config {
     }
 
     pub fn target_dir(&self) -> CargoResult<Option<Filesystem>> {
-        if let Some(ref dir) = self.target_dir {
-            Ok(Some(dir.clone()))
+        let dir = if let Some(ref dir) = self.target_dir {
+            dir.clone().into_path_unlocked()
         } else if let Some(dir) = env::var_os("CARGO_TARGET_DIR") {
-            Ok(Some(Filesystem::new(self.cwd.join(dir))))
+            self.cwd.join(dir)
         } else if let Some(val) = self.get_path("build.target-dir")? {
-            let val = self.cwd.join(val.val);
-            Ok(Some(Filesystem::new(val)))
+            self.cwd.join(val.val)
         } else {
-            Ok(None)
-        }
+            return Ok(None);
+        };
+        Ok(Some(Filesystem::new(self.apply_symlink_policy(dir)?)))
     }
 
     fn get_cv(&self, key: &str) -> CargoResult<Option<ConfigValue>> {
-        let vals = self.values()?;
-        let mut parts = key.split('.').enumerate();
-        let mut val = match vals.get(parts.next().unwrap().1) {
-            Some(val) => val,
-            None => return Ok(None),
+        let val = lookup_cv(self.values()?, key)?;
+        let layer = self.layer.borrow();
+        let layered = match *layer {
+            Some(ref layer) => lookup_cv(layer, key)?,
+            None => None,
         };
-        for (i, part) in parts {
-            match *val {
-                CV::Table(ref map, _) => {
-                    val = match map.get(part) {
-                        Some(val) => val,
-                        None => return Ok(None),
-                    }
-                }
-                CV::Integer(_, ref path)
-                | CV::String(_, ref path)
-                | CV::List(_, ref path)
-                | CV::Boolean(_, ref path) => {
-                    let idx = key.split('.').take(i).fold(0, |n, s| n + s.len()) + i - 1;
-                    let key_so_far = &key[..idx];
-                    bail!(
-                        "expected table for configuration key `{}`, \
-                         but found {} in {}",
-                        key_so_far,
-                        val.desc(),
-                        path.display()
-                    )
+        match (layered, val) {
+            (Some(layered), Some(val)) => {
+                let mut merged = layered.clone();
+                match merged.merge(val) {
+                    Ok(()) => Ok(Some(merged)),
+                    Err(_) => Ok(Some(layered)),
                 }
             }
+            (layered, val) => Ok(layered.or(val)),
         }
-        Ok(Some(val.clone()))
     }
 
     // Helper primarily for testing.
@@ -551,15 +660,20 @@ impl Config {
         color: &Option<String>,
         frozen: bool,
         locked: bool,
+        offline: bool,
+        unlocked: bool,
+        ephemeral: bool,
         target_dir: &Option<PathBuf>,
         unstable_flags: &[String],
     ) -> CargoResult<()> {
         let extra_verbose = verbose >= 2;
         let verbose = if verbose == 0 { None } else { Some(true) };
+        let cli_color = color.is_some();
 
         // Ignore errors in the configuration files.
         let cfg_verbose = self.get_bool("term.verbose").unwrap_or(None).map(|v| v.val);
         let cfg_color = self.get_string("term.color").unwrap_or(None).map(|v| v.val);
+        let cfg_offline = self.get_bool("net.offline").unwrap_or(None).map(|v| v.val);
 
         let color = color.as_ref().or_else(|| cfg_color.as_ref());
 
@@ -585,6 +699,13 @@ impl Config {
             | (None, None, None) => Verbosity::Normal,
         };
 
+        if unlocked && (frozen || locked) {
+            bail!("cannot pass `--unlocked` along with `--frozen` or `--locked`");
+        }
+        if ephemeral && !unlocked {
+            bail!("`--ephemeral` can only be passed along with `--unlocked`");
+        }
+
         let cli_target_dir = match target_dir.as_ref() {
             Some(dir) => Some(Filesystem::new(dir.clone())),
             None => None,
@@ -595,12 +716,155 @@ impl Config {
         self.extra_verbose = extra_verbose;
         self.frozen = frozen;
         self.locked = locked;
+        self.unlocked = unlocked;
+        self.ephemeral = ephemeral;
         self.target_dir = cli_target_dir;
         self.cli_flags.parse(unstable_flags)?;
+        self.offline = offline || cfg_offline.unwrap_or(false) || self.cli_flags.offline;
+
+        match self.symlink_policy()? {
+            Some(SymlinkPolicy::Follow) => {
+                self.cwd = paths::resolve_symlinks(&self.cwd)?;
+            }
+            Some(SymlinkPolicy::Preserve) => {
+                // The current directory of the process never contains
+                // symlinks, but the shell tracks the path it was entered
+                // through in `PWD`.
+                if let Some(pwd) = env::var_os("PWD").map(PathBuf::from) {
+                    if pwd.is_absolute() && same_file::is_same_file(&pwd, &self.cwd)? {
+                        self.cwd = pwd;
+                    }
+                }
+            }
+            None => {}
+        }
+
+        if self.cli_unstable().term_output {
+            self.configure_term_output(cli_color)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the verbosity of `[term.categories]`, the translations of
+    /// `term.messages`, the per-stream colors and hyperlinks to the shell, with
+    /// `-Z term-output`. `--color` takes precedence over `term.stderr-color`.
+    fn configure_term_output(&mut self, cli_color: bool) -> CargoResult<()> {
+        if let Some(categories) = self.get_table("term.categories")? {
+            for (name, value) in categories.val.iter() {
+                let key = format!("term.categories.{}", name);
+                let (level, path) = value.string(&key)?;
+                let category = match &name[..] {
+                    "network" => Category::Network,
+                    "compile" => Category::Compile,
+                    "warnings" => Category::Warnings,
+                    _ => bail!(
+                        "unknown output category `{}`, expected `network`, \
+                         `compile` or `warnings` (found in {})",
+                        name,
+                        path.display()
+                    ),
+                };
+                let verbosity = match level {
+                    "quiet" => Verbosity::Quiet,
+                    "normal" => Verbosity::Normal,
+                    _ => bail!(
+                        "`{}` must be `quiet` or `normal`, found `{}` (found in {})",
+                        key,
+                        level,
+                        path.display()
+                    ),
+                };
+                self.shell().set_category_verbosity(category, verbosity);
+            }
+        }
+
+        if let Some(messages) = self.get_string("term.messages")? {
+            let path = messages.definition.root(self).join(&messages.val);
+            let contents = paths::read(&path)?;
+            let toml = cargo_toml::parse(&contents, &path, self)?;
+            let table = match toml {
+                toml::Value::Table(table) => table,
+                _ => unreachable!(),
+            };
+            let mut catalog = HashMap::new();
+            for (message, translation) in table {
+                match translation {
+                    toml::Value::String(translation) => {
+                        catalog.insert(message, translation);
+                    }
+                    _ => bail!(
+                        "the translation of `{}` in `{}` must be a string",
+                        message,
+                        path.display()
+                    ),
+                }
+            }
+            self.shell().set_messages(catalog);
+        }
+
+        if !cli_color {
+            if let Some(color) = self.get_color_choice("term.stderr-color")? {
+                self.shell().set_color_choice(Some(color.as_arg()))?;
+            }
+        }
+        let stdout_color = self.get_color_choice("term.stdout-color")?;
+        self.shell().set_stdout_color_choice(stdout_color);
 
+        let hyperlinks = self.get_bool("term.hyperlinks")?.map(|v| v.val);
+        self.shell().set_hyperlinks(hyperlinks);
         Ok(())
     }
 
+    fn get_color_choice(&self, key: &str) -> CargoResult<Option<ColorChoice>> {
+        let color = match self.get_string(key)? {
+            Some(color) => color,
+            None => return Ok(None),
+        };
+        match &color.val[..] {
+            "always" => Ok(Some(ColorChoice::Always)),
+            "never" => Ok(Some(ColorChoice::Never)),
+            "auto" => Ok(Some(ColorChoice::CargoAuto)),
+            _ => bail!(
+                "`{}` must be `auto`, `always` or `never`, found `{}` (found in {})",
+                key,
+                color.val,
+                color.definition
+            ),
+        }
+    }
+
+    /// The policy for symlinks in the paths of packages and of the target
+    /// directory, set with `build.symlinks` and `-Z symlinks`.
+    ///
+    /// `None` means the paths are used as they were found, which keeps the
+    /// symlinks in `--manifest-path` and `CARGO_TARGET_DIR` but not in the
+    /// current directory.
+    pub fn symlink_policy(&self) -> CargoResult<Option<SymlinkPolicy>> {
+        if !self.cli_unstable().symlinks {
+            return Ok(None);
+        }
+        match self.get_string("build.symlinks")? {
+            Some(ref v) if v.val == "follow" => Ok(Some(SymlinkPolicy::Follow)),
+            Some(ref v) if v.val == "preserve" => Ok(Some(SymlinkPolicy::Preserve)),
+            Some(v) => bail!(
+                "`build.symlinks` must be `follow` or `preserve`, found `{}` in {}",
+                v.val,
+                v.definition
+            ),
+            None => Ok(None),
+        }
+    }
+
+    /// Applies the symlink policy to the path of a package or of the target
+    /// directory.
+    pub fn apply_symlink_policy(&self, path: PathBuf) -> CargoResult<PathBuf> {
+        match self.symlink_policy()? {
+            Some(SymlinkPolicy::Follow) => paths::resolve_symlinks(&path),
+            Some(SymlinkPolicy::Preserve) | None => Ok(path),
+        }
+    }
+
     pub fn cli_unstable(&self) -> &CliUnstable {
         &self.cli_flags
     }
@@ -609,8 +873,42 @@ impl Config {
         self.extra_verbose
     }
 
+    /// Whether units built for the host, such as build scripts and proc
+    /// macros, get the same `RUSTFLAGS`, `target.*` and `build.rustflags`
+    /// settings as the target when no `--target` is passed.
+    ///
+    /// This is the default. Setting `target-applies-to-host = false` with
+    /// `-Z target-applies-to-host` always builds host units separately and
+    /// configures them with the `[host]` table instead.
+    pub fn target_applies_to_host(&self) -> CargoResult<bool> {
+        if !self.cli_unstable().target_applies_to_host {
+            return Ok(true);
+        }
+        Ok(self
+            .get_bool("target-applies-to-host")?
+            .map(|v| v.val)
+            .unwrap_or(true))
+    }
+
+    /// How the platform cargo runs on loads dynamic libraries, the
+    /// procedural macros rustc loads and the ones of the programs cargo runs.
+    pub fn host_platform(&self) -> CargoResult<&HostPlatform> {
+        self.host_platform
+            .try_borrow_with(|| HostPlatform::new(self))
+    }
+
     pub fn network_allowed(&self) -> bool {
-        !self.frozen() && !self.cli_unstable().offline
+        !self.frozen() && !self.offline()
+    }
+
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Forbids (or allows again) network access for the rest of this
+    /// invocation, like `--offline` does.
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
     }
 
     pub fn frozen(&self) -> bool {
@@ -621,12 +919,33 @@ impl Config {
         !self.frozen && !self.locked
     }
 
+    /// Whether to resolve dependencies afresh, as if there was no lock file.
+    pub fn unlocked(&self) -> bool {
+        self.unlocked
+    }
+
+    /// Whether to write lock files to a temporary location instead of
+    /// `Cargo.lock`.
+    pub fn ephemeral(&self) -> bool {
+        self.ephemeral
+    }
+
+    /// The lock file written with `--ephemeral`, if any was.
+    pub fn ephemeral_lockfile(&self) -> Option<PathBuf> {
+        self.ephemeral_lockfile.borrow().clone()
+    }
+
+    pub fn set_ephemeral_lockfile(&self, path: PathBuf) {
+        *self.ephemeral_lockfile.borrow_mut() = Some(path);
+    }
+
     /// Loads configuration from the filesystem
     pub fn load_values(&self) -> CargoResult<HashMap<String, ConfigValue>> {
         self.load_values_from(&self.cwd)
     }
 
     fn load_values_from(&self, path: &Path) -> CargoResult<HashMap<String, ConfigValue>> {
+        let _p = profile::start("loading the configuration");
         let mut cfg = CV::Table(HashMap::new(), PathBuf::from("."));
         let home = self.home_path.clone().into_path_unlocked();
 
@@ -638,12 +957,13 @@ impl Config {
             let toml = cargo_toml::parse(&contents, path, self).chain_err(|| {
                 format!("could not parse TOML configuration in `{}`", path.display())
             })?;
-            let value = CV::from_toml(path, toml).chain_err(|| {
+            let mut value = CV::from_toml(path, toml).chain_err(|| {
                 format!(
                     "failed to load TOML configuration from `{}`",
                     path.display()
                 )
             })?;
+            apply_host_cfgs(&mut value)?;
             cfg.merge(value)
                 .chain_err(|| format!("failed to merge configuration at `{}`", path.display()))?;
             Ok(())
@@ -1550,10 +1870,170 @@ impl fmt::Display for Definition {
     }
 }
 
+/// How symlinks in the paths of packages and of the target directory are
+/// treated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Resolve all symlinks, so that paths refer to physical locations.
+    Follow,
+    /// Keep symlinks, including the ones in the current directory the shell
+    /// reports in `PWD`.
+    Preserve,
+}
+
 pub fn homedir(cwd: &Path) -> Option<PathBuf> {
     ::home::cargo_home_with_cwd(cwd).ok()
 }
 
+/// The value of `key` in the configuration `vals`.
+fn lookup_cv(vals: &HashMap<String, ConfigValue>, key: &str) -> CargoResult<Option<ConfigValue>> {
+    let mut parts = key.split('.').enumerate();
+    let mut val = match vals.get(parts.next().unwrap().1) {
+        Some(val) => val,
+        None => return Ok(None),
+    };
+    for (i, part) in parts {
+        match *val {
+            CV::Table(ref map, _) => {
+                val = match map.get(part) {
+                    Some(val) => val,
+                    None => return Ok(None),
+                }
+            }
+            CV::Integer(_, ref path)
+            | CV::String(_, ref path)
+            | CV::List(_, ref path)
+            | CV::Boolean(_, ref path) => {
+                let idx = key.split('.').take(i).fold(0, |n, s| n + s.len()) + i - 1;
+                let key_so_far = &key[..idx];
+                bail!(
+                    "expected table for configuration key `{}`, \
+                     but found {} in {}",
+                    key_so_far,
+                    val.desc(),
+                    path.display()
+                )
+            }
+        }
+    }
+    Ok(Some(val.clone()))
+}
+
+/// Merges the tables `[host.'cfg(...)']` whose `cfg` matches the platform
+/// cargo runs on into the root of `cfg`, the configuration of a single file,
+/// where their values take precedence over the other values of the file, and
+/// removes them all from the `host` table. Doing so before the file is merged
+/// with the others keeps its precedence among them.
+fn apply_host_cfgs(cfg: &mut ConfigValue) -> CargoResult<()> {
+    let mut blocks = match *cfg {
+        CV::Table(ref mut map, _) => {
+            let blocks = match map.get_mut("host") {
+                Some(CV::Table(ref mut host, _)) => {
+                    let keys = host
+                        .keys()
+                        .filter(|k| k.starts_with("cfg("))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    keys.into_iter()
+                        .map(|k| {
+                            let block = host.remove(&k).unwrap();
+                            (k, block)
+                        })
+                        .collect::<Vec<_>>()
+                }
+                _ => return Ok(()),
+            };
+            if map["host"].table("host")?.0.is_empty() {
+                map.remove("host");
+            }
+            blocks
+        }
+        _ => unreachable!(),
+    };
+    blocks.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let host_cfg = host_cfg();
+    for (key, block) in blocks {
+        let expr = if key.ends_with(')') {
+            CfgExpr::from_str(&key[4..key.len() - 1]).ok()
+        } else {
+            None
+        };
+        let expr = match expr {
+            Some(expr) => expr,
+            None => bail!(
+                "invalid `cfg` expression in `host.'{}'` in {}",
+                key,
+                block.definition_path().display()
+            ),
+        };
+        if !expr.matches(&host_cfg) {
+            continue;
+        }
+        match block {
+            CV::Table(..) => {}
+            _ => bail!(
+                "expected a table for `host.'{}'`, but found {} in {}",
+                key,
+                block.desc(),
+                block.definition_path().display()
+            ),
+        }
+        let mut merged = block;
+        let root = mem::replace(cfg, CV::Table(HashMap::new(), PathBuf::from(".")));
+        merged
+            .merge(root)
+            .chain_err(|| format!("failed to merge `host.'{}'` into the configuration", key))?;
+        *cfg = match merged {
+            CV::Table(map, _) => CV::Table(map, PathBuf::from(".")),
+            _ => unreachable!(),
+        };
+    }
+    Ok(())
+}
+
+/// The `cfg` values of the platform cargo runs on, which `[host.'cfg(...)']`
+/// tables are matched against. Cargo runs on the platform it was built for,
+/// so they're known without asking rustc.
+fn host_cfg() -> Vec<Cfg> {
+    let key = |k: &str, v: &str| Cfg::KeyPair(k.to_string(), v.to_string());
+    let mut cfg = vec![
+        Cfg::Name(env::consts::FAMILY.to_string()),
+        key("target_family", env::consts::FAMILY),
+        key("target_os", env::consts::OS),
+        key("target_arch", env::consts::ARCH),
+        key(
+            "target_endian",
+            if cfg!(target_endian = "big") {
+                "big"
+            } else {
+                "little"
+            },
+        ),
+        key(
+            "target_pointer_width",
+            if cfg!(target_pointer_width = "64") {
+                "64"
+            } else if cfg!(target_pointer_width = "32") {
+                "32"
+            } else {
+                "16"
+            },
+        ),
+    ];
+    let target_env = if cfg!(target_env = "msvc") {
+        "msvc"
+    } else if cfg!(target_env = "gnu") {
+        "gnu"
+    } else if cfg!(target_env = "musl") {
+        "musl"
+    } else {
+        ""
+    };
+    cfg.push(key("target_env", target_env));
+    cfg
+}
+
 fn walk_tree<F>(pwd: &Path, home: &Path, mut walk: F) -> CargoResult<()>
 where
     F: FnMut(&Path) -> CargoResult<()>,
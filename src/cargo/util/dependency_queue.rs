@@ -3,6 +3,10 @@
 //!
 //! This structure is used to store the dependency graph and dynamically update
 //! it to figure out when a dependency should be built.
+//!
+//! Dependency edges are labeled with what of the dependency is needed, which
+//! allows a package to start building once a part of a dependency is ready
+//! (e.g. its metadata) instead of waiting for all of it.
 
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::{HashMap, HashSet};
@@ -11,20 +15,21 @@ use std::hash::Hash;
 pub use self::Freshness::{Dirty, Fresh};
 
 #[derive(Debug)]
-pub struct DependencyQueue<K: Eq + Hash, V> {
+pub struct DependencyQueue<K: Eq + Hash, E: Eq + Hash, V> {
     /// A list of all known keys to build.
     ///
-    /// The value of the hash map is list of dependencies which still need to be
-    /// built before the package can be built. Note that the set is dynamically
-    /// updated as more dependencies are built.
-    dep_map: HashMap<K, (HashSet<K>, V)>,
+    /// The value of the hash map is list of dependencies, along with the edge
+    /// they're waited on for, which still need to be built before the package
+    /// can be built. Note that the set is dynamically updated as more
+    /// dependencies are built.
+    dep_map: HashMap<K, (HashSet<(K, E)>, V)>,
 
     /// A reverse mapping of a package to all packages that depend on that
-    /// package.
+    /// package, by the edge they depend on.
     ///
-    /// This map is statically known and does not get updated throughout the
-    /// lifecycle of the DependencyQueue.
-    reverse_dep_map: HashMap<K, HashSet<K>>,
+    /// This map is statically known, except that edges are removed once
+    /// they've been finished with `finish_edge`.
+    reverse_dep_map: HashMap<K, HashMap<E, HashSet<K>>>,
 
     /// A set of dirty packages.
     ///
@@ -58,15 +63,15 @@ impl Freshness {
     }
 }
 
-impl<K: Hash + Eq + Clone, V> Default for DependencyQueue<K, V> {
-    fn default() -> DependencyQueue<K, V> {
+impl<K: Hash + Eq + Clone, E: Hash + Eq + Clone, V> Default for DependencyQueue<K, E, V> {
+    fn default() -> DependencyQueue<K, E, V> {
         DependencyQueue::new()
     }
 }
 
-impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
+impl<K: Hash + Eq + Clone, E: Hash + Eq + Clone, V> DependencyQueue<K, E, V> {
     /// Creates a new dependency queue with 0 packages.
-    pub fn new() -> DependencyQueue<K, V> {
+    pub fn new() -> DependencyQueue<K, E, V> {
         DependencyQueue {
             dep_map: HashMap::new(),
             reverse_dep_map: HashMap::new(),
@@ -79,8 +84,15 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
     /// Adds a new package to this dependency queue.
     ///
     /// It is assumed that any dependencies of this package will eventually also
-    /// be added to the dependency queue.
-    pub fn queue(&mut self, fresh: Freshness, key: &K, value: V, dependencies: &[K]) -> &mut V {
+    /// be added to the dependency queue. Each dependency is paired with the
+    /// edge this package waits on.
+    pub fn queue(
+        &mut self,
+        fresh: Freshness,
+        key: &K,
+        value: V,
+        dependencies: &[(K, E)],
+    ) -> &mut V {
         let slot = match self.dep_map.entry(key.clone()) {
             Occupied(v) => return &mut v.into_mut().1,
             Vacant(v) => v,
@@ -91,10 +103,12 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
         }
 
         let mut my_dependencies = HashSet::new();
-        for dep in dependencies {
-            my_dependencies.insert(dep.clone());
+        for &(ref dep, ref edge) in dependencies {
+            my_dependencies.insert((dep.clone(), edge.clone()));
             let rev = self.reverse_dep_map
                 .entry(dep.clone())
+                .or_insert_with(HashMap::new)
+                .entry(edge.clone())
                 .or_insert_with(HashSet::new);
             rev.insert(key.clone());
         }
@@ -108,9 +122,9 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
             depth(key, &self.reverse_dep_map, &mut self.depth);
         }
 
-        fn depth<K: Hash + Eq + Clone>(
+        fn depth<K: Hash + Eq + Clone, E: Hash + Eq + Clone>(
             key: &K,
-            map: &HashMap<K, HashSet<K>>,
+            map: &HashMap<K, HashMap<E, HashSet<K>>>,
             results: &mut HashMap<K, usize>,
         ) -> usize {
             const IN_PROGRESS: usize = !0;
@@ -125,6 +139,7 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
             let depth = 1
                 + map.get(&key)
                     .into_iter()
+                    .flat_map(|it| it.values())
                     .flat_map(|it| it)
                     .map(|dep| depth(dep, map, results))
                     .max()
@@ -186,15 +201,39 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
     /// possibly allowing the next invocation of `dequeue` to return a package.
     pub fn finish(&mut self, key: &K, fresh: Freshness) {
         assert!(self.pending.remove(key));
-        let reverse_deps = match self.reverse_dep_map.get(key) {
-            Some(deps) => deps,
+        let edges = match self.reverse_dep_map.remove(key) {
+            Some(edges) => edges,
             None => return,
         };
+        for (edge, reverse_deps) in edges {
+            self.unblock(key, &edge, &reverse_deps, fresh);
+        }
+    }
+
+    /// Indicate that the part `edge` of a package which is still building is
+    /// ready.
+    ///
+    /// Packages which only wait for this part of `key` may be returned by the
+    /// next invocation of `dequeue`, the others keep waiting for `finish`.
+    pub fn finish_edge(&mut self, key: &K, edge: &E, fresh: Freshness) {
+        assert!(self.pending.contains(key));
+        let reverse_deps = match self.reverse_dep_map.get_mut(key) {
+            Some(edges) => match edges.remove(edge) {
+                Some(deps) => deps,
+                None => return,
+            },
+            None => return,
+        };
+        self.unblock(key, edge, &reverse_deps, fresh);
+    }
+
+    fn unblock(&mut self, key: &K, edge: &E, reverse_deps: &HashSet<K>, fresh: Freshness) {
+        let dep_edge = (key.clone(), edge.clone());
         for dep in reverse_deps.iter() {
             if fresh == Dirty {
                 self.dirty.insert(dep.clone());
             }
-            assert!(self.dep_map.get_mut(dep).unwrap().0.remove(key));
+            assert!(self.dep_map.get_mut(dep).unwrap().0.remove(&dep_edge));
         }
     }
 }
@@ -208,10 +247,10 @@ mod test {
         let mut q = DependencyQueue::new();
 
         q.queue(Freshness::Fresh, &1, (), &[]);
-        q.queue(Freshness::Fresh, &2, (), &[1]);
+        q.queue(Freshness::Fresh, &2, (), &[(1, ())]);
         q.queue(Freshness::Fresh, &3, (), &[]);
-        q.queue(Freshness::Fresh, &4, (), &[2, 3]);
-        q.queue(Freshness::Fresh, &5, (), &[4, 3]);
+        q.queue(Freshness::Fresh, &4, (), &[(2, ()), (3, ())]);
+        q.queue(Freshness::Fresh, &5, (), &[(4, ()), (3, ())]);
         q.queue_finished();
 
         assert_eq!(q.dequeue(), Some((Freshness::Fresh, 1, ())));
@@ -228,4 +267,25 @@ mod test {
         q.finish(&4, Freshness::Fresh);
         assert_eq!(q.dequeue(), Some((Freshness::Fresh, 5, ())));
     }
+
+    #[test]
+    fn finish_edge() {
+        let mut q = DependencyQueue::new();
+
+        q.queue(Freshness::Fresh, &1, (), &[]);
+        q.queue(Freshness::Fresh, &2, (), &[(1, "meta")]);
+        q.queue(Freshness::Fresh, &3, (), &[(1, "all")]);
+        q.queue_finished();
+
+        assert_eq!(q.dequeue(), Some((Freshness::Fresh, 1, ())));
+        assert_eq!(q.dequeue(), None);
+        q.finish_edge(&1, &"meta", Freshness::Dirty);
+        assert_eq!(q.dequeue(), Some((Freshness::Dirty, 2, ())));
+        assert_eq!(q.dequeue(), None);
+        q.finish(&1, Freshness::Dirty);
+        assert_eq!(q.dequeue(), Some((Freshness::Dirty, 3, ())));
+        q.finish(&2, Freshness::Fresh);
+        q.finish(&3, Freshness::Fresh);
+        assert!(q.is_empty());
+    }
 }
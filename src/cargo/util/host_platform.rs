@@ -0,0 +1,93 @@
+//! How the platform cargo runs on loads dynamic libraries: the procedural
+//! macros and compiler plugins rustc loads, and the libraries the build
+//! scripts, tests and binaries cargo runs link to.
+//!
+//! The defaults cover Windows, macOS, Haiku, AIX and the other Unixes,
+//! including musl hosts, which load libraries like glibc ones do. Platforms
+//! and custom toolchains which differ set `build.dylib-path-env`, the
+//! variable their dynamic loader searches, and `build.sysroot-libdir`, the
+//! directory of the sysroot with the dynamic libraries of the host, usually
+//! in the `[host.'cfg(...)'.build]` table matching them.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::util::errors::CargoResult;
+use crate::util::{paths, Config};
+
+#[derive(Debug)]
+pub struct HostPlatform {
+    /// The environment variable the dynamic loader searches for libraries,
+    /// like `LD_LIBRARY_PATH`.
+    dylib_path_envvar: String,
+    /// The directory with the dynamic libraries of the host, relative to
+    /// the sysroot, like `lib`.
+    sysroot_libdir: PathBuf,
+}
+
+impl HostPlatform {
+    pub fn new(config: &Config) -> CargoResult<HostPlatform> {
+        let dylib_path_envvar = match config.get_string("build.dylib-path-env")? {
+            Some(var) => {
+                if var.val.is_empty() || var.val.contains('=') {
+                    bail!(
+                        "`build.dylib-path-env` in {} isn't the name of an environment \
+                         variable: `{}`",
+                        var.definition,
+                        var.val
+                    );
+                }
+                var.val
+            }
+            None => paths::dylib_path_envvar().to_string(),
+        };
+        let sysroot_libdir = match config.get_string("build.sysroot-libdir")? {
+            Some(dir) => {
+                let path = PathBuf::from(&dir.val);
+                if path.has_root() {
+                    bail!(
+                        "`build.sysroot-libdir` in {} must be relative to the sysroot: `{}`",
+                        dir.definition,
+                        dir.val
+                    );
+                }
+                path
+            }
+            None => PathBuf::from(default_sysroot_libdir()),
+        };
+        Ok(HostPlatform {
+            dylib_path_envvar,
+            sysroot_libdir,
+        })
+    }
+
+    /// The environment variable the dynamic loader searches for libraries.
+    pub fn dylib_path_envvar(&self) -> &str {
+        &self.dylib_path_envvar
+    }
+
+    /// The directories the dynamic loader searched when cargo was run.
+    pub fn dylib_path(&self) -> Vec<PathBuf> {
+        match env::var_os(&self.dylib_path_envvar) {
+            Some(var) => env::split_paths(&var).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The directory with the dynamic libraries of the host in `sysroot`,
+    /// like the ones of the standard library which procedural macros link
+    /// to.
+    pub fn sysroot_libdir(&self, sysroot: &Path) -> PathBuf {
+        sysroot.join(&self.sysroot_libdir)
+    }
+}
+
+/// The directory of the sysroot with the dynamic libraries of the host:
+/// Windows looks for them next to the executables.
+fn default_sysroot_libdir() -> &'static str {
+    if cfg!(windows) {
+        "bin"
+    } else {
+        "lib"
+    }
+}
@@ -1,9 +1,12 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 use serde::ser;
 use serde_json::{self, value::RawValue};
 
+use crate::core::compiler::{CompileMode, Kind};
 use crate::core::{PackageId, Target};
+use crate::util::Cfg;
 
 pub trait Message: ser::Serialize {
     fn reason(&self) -> &str;
@@ -38,6 +41,8 @@ pub struct Artifact<'a> {
     pub filenames: Vec<PathBuf>,
     pub executable: Option<PathBuf>,
     pub fresh: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_cpu: Option<ArtifactTargetCpu>,
 }
 
 impl<'a> Message for Artifact<'a> {
@@ -58,6 +63,14 @@ pub struct ArtifactProfile {
     pub test: bool,
 }
 
+/// The CPU an artifact was built for with `--target-cpu`, and the target
+/// features that enabled.
+#[derive(Serialize)]
+pub struct ArtifactTargetCpu {
+    pub name: String,
+    pub features: Vec<String>,
+}
+
 #[derive(Serialize)]
 pub struct BuildScript<'a> {
     pub package_id: PackageId,
@@ -72,3 +85,63 @@ impl<'a> Message for BuildScript<'a> {
         "build-script-executed"
     }
 }
+
+#[derive(Serialize)]
+pub struct TestExecutable<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    pub mode: CompileMode,
+    pub executable: &'a Path,
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<&'a Path>,
+    pub env: BTreeMap<String, String>,
+}
+
+impl<'a> Message for TestExecutable<'a> {
+    fn reason(&self) -> &str {
+        "test-executable"
+    }
+}
+
+/// The platform the units of a kind are compiled for, its cfg values and
+/// tools: the host for build scripts and procedural macros, and a target for
+/// the rest, which differ when cross compiling.
+#[derive(Serialize)]
+pub struct TargetInfo {
+    pub kind: &'static str,
+    pub triple: String,
+    pub cfg: Vec<String>,
+    pub linker: PathBuf,
+    pub ar: PathBuf,
+    pub llvm_tools_dir: Option<PathBuf>,
+}
+
+impl TargetInfo {
+    pub fn new(
+        kind: Kind,
+        triple: &str,
+        cfg: &[Cfg],
+        linker: &Path,
+        ar: &Path,
+        llvm_tools_dir: Option<&Path>,
+    ) -> TargetInfo {
+        TargetInfo {
+            kind: match kind {
+                Kind::Host => "host",
+                Kind::Target(_) => "target",
+            },
+            triple: triple.to_string(),
+            cfg: cfg.iter().map(|cfg| cfg.to_string()).collect(),
+            linker: linker.to_path_buf(),
+            ar: ar.to_path_buf(),
+            llvm_tools_dir: llvm_tools_dir.map(|p| p.to_path_buf()),
+        }
+    }
+}
+
+impl Message for TargetInfo {
+    fn reason(&self) -> &str {
+        "target-info"
+    }
+}
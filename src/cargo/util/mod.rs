@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 pub use self::cfg::{Cfg, CfgExpr};
-pub use self::config::{homedir, Config, ConfigValue};
+pub use self::config::{homedir, Config, ConfigValue, SymlinkPolicy};
 pub use self::dependency_queue::{DependencyQueue, Dirty, Fresh, Freshness};
 pub use self::errors::{CargoError, CargoResult, CargoResultExt, CliResult, Test};
 pub use self::errors::{CargoTestError, CliError, ProcessError};
@@ -9,10 +9,11 @@ pub use self::errors::{internal, process_error};
 pub use self::flock::{FileLock, Filesystem};
 pub use self::graph::Graph;
 pub use self::hex::{short_hash, to_hex, hash_u64};
+pub use self::host_platform::HostPlatform;
 pub use self::lev_distance::lev_distance;
 pub use self::paths::{dylib_path, join_paths, bytes2path, path2bytes};
 pub use self::paths::{dylib_path_envvar, normalize_path, without_prefix};
-pub use self::process_builder::{process, ProcessBuilder};
+pub use self::process_builder::{process, ProcessBuilder, ResourceLimits};
 pub use self::rustc::Rustc;
 pub use self::sha256::Sha256;
 pub use self::to_semver::ToSemver;
@@ -45,6 +46,7 @@ mod rustc;
 mod sha256;
 mod vcs;
 mod flock;
+mod host_platform;
 mod read2;
 mod progress;
 mod lockserver;
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::env;
 use std::ffi::{OsStr, OsString};
 use std::fs::{self, File, OpenOptions};
@@ -27,11 +28,18 @@ pub fn join_paths<T: AsRef<OsStr>>(paths: &[T], env: &str) -> CargoResult<OsStri
     Err(err.context(more_explain).into())
 }
 
+/// The environment variable the dynamic loader of the platform cargo was
+/// built for searches, unless `build.dylib-path-env` says otherwise (see
+/// `Config::host_platform`).
 pub fn dylib_path_envvar() -> &'static str {
     if cfg!(windows) {
         "PATH"
     } else if cfg!(target_os = "macos") {
         "DYLD_LIBRARY_PATH"
+    } else if cfg!(target_os = "haiku") {
+        "LIBRARY_PATH"
+    } else if cfg!(target_os = "aix") {
+        "LIBPATH"
     } else {
         "LD_LIBRARY_PATH"
     }
@@ -44,6 +52,30 @@ pub fn dylib_path() -> Vec<PathBuf> {
     }
 }
 
+/// Resolves all symlinks in `path`, like `fs::canonicalize`, but also works
+/// for paths which don't exist yet by resolving their longest existing
+/// ancestor.
+pub fn resolve_symlinks(path: &Path) -> CargoResult<PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if existing.exists() {
+            let mut ret = existing
+                .canonicalize()
+                .chain_err(|| format!("failed to resolve symlinks in `{}`", path.display()))?;
+            ret.extend(rest.iter().rev());
+            return Ok(ret);
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return Ok(path.to_path_buf()),
+        }
+    }
+}
+
 pub fn normalize_path(path: &Path) -> PathBuf {
     let mut components = path.components().peekable();
     let mut ret = if let Some(c @ Component::Prefix(..)) = components.peek().cloned() {
@@ -246,8 +278,76 @@ impl<'a> Iterator for PathAncestors<'a> {
     }
 }
 
+/// The longest path, including the terminating NUL, that the Windows file
+/// system APIs accept without the `\\?\` prefix.
+const MAX_PATH: usize = 260;
+
+/// Returns `path` in a form which can be passed to the file system APIs even
+/// if it's longer than `MAX_PATH`.
+///
+/// On Windows this prefixes long absolute paths with `\\?\`, which lifts the
+/// limit. Such paths are passed to the file system unparsed, so they are
+/// normalized first. Everywhere else `path` is returned as is.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    // Directories are limited to 12 characters less than files, leaving
+    // room for an 8.3 file name in them.
+    let len = path.as_os_str().len();
+    if !path.is_absolute() || len < MAX_PATH - 12 {
+        return Cow::Borrowed(path);
+    }
+    let path = normalize_path(path).to_string_lossy().replace('/', "\\");
+    if path.starts_with(r"\\?\") {
+        Cow::Owned(PathBuf::from(path))
+    } else if path.starts_with(r"\\") {
+        Cow::Owned(PathBuf::from(format!(r"\\?\UNC\{}", &path[2..])))
+    } else {
+        Cow::Owned(PathBuf::from(format!(r"\\?\{}", path)))
+    }
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// Converts the result of a file system operation on `path` into a
+/// `CargoResult`, explaining how to fix it if it failed because `path` is
+/// longer than Windows supports.
+pub fn explain_long_path<T>(result: io::Result<T>, path: &Path) -> CargoResult<T> {
+    result.map_err(|e| {
+        // ERROR_PATH_NOT_FOUND and ERROR_FILENAME_EXCED_RANGE
+        let too_long = cfg!(windows)
+            && path.as_os_str().len() >= MAX_PATH - 12
+            && (e.raw_os_error() == Some(3) || e.raw_os_error() == Some(206));
+        let err = CargoError::from(e);
+        if !too_long {
+            return err;
+        }
+        err.context(format!(
+            "the path `{}` is {} characters long, which is more than Windows \
+             supports by default\n\
+             move the project, or the target directory with `CARGO_TARGET_DIR`, \
+             to a shorter path, or enable long paths in Windows",
+            path.display(),
+            path.as_os_str().len()
+        ))
+        .into()
+    })
+}
+
+pub fn create_dir_all<P: AsRef<Path>>(p: P) -> CargoResult<()> {
+    _create_dir_all(p.as_ref())
+}
+
+fn _create_dir_all(p: &Path) -> CargoResult<()> {
+    explain_long_path(fs::create_dir_all(long_path(p)), p)
+        .chain_err(|| format!("failed to create directory `{}`", p.display()))?;
+    Ok(())
+}
+
 pub fn remove_dir_all<P: AsRef<Path>>(p: P) -> CargoResult<()> {
-    _remove_dir_all(p.as_ref())
+    _remove_dir_all(&long_path(p.as_ref()))
 }
 
 fn _remove_dir_all(p: &Path) -> CargoResult<()> {
@@ -274,7 +374,8 @@ pub fn remove_dir<P: AsRef<Path>>(p: P) -> CargoResult<()> {
 }
 
 fn _remove_dir(p: &Path) -> CargoResult<()> {
-    fs::remove_dir(p).chain_err(|| format!("failed to remove directory `{}`", p.display()))?;
+    fs::remove_dir(long_path(p))
+        .chain_err(|| format!("failed to remove directory `{}`", p.display()))?;
     Ok(())
 }
 
@@ -283,6 +384,7 @@ pub fn remove_file<P: AsRef<Path>>(p: P) -> CargoResult<()> {
 }
 
 fn _remove_file(p: &Path) -> CargoResult<()> {
+    let p = &*long_path(p);
     let mut err = match fs::remove_file(p) {
         Ok(()) => return Ok(()),
         Err(e) => e,
@@ -27,6 +27,21 @@ pub struct ProcessBuilder {
     ///
     /// [jobserver_docs]: https://docs.rs/jobserver/0.1.6/jobserver/
     jobserver: Option<Client>,
+    /// Whether the environment of cargo is left out, so that the process
+    /// only sees the variables set explicitly.
+    env_clear: bool,
+    /// The limits on the resources the process may use.
+    limits: ResourceLimits,
+}
+
+/// Limits on the resources a process may use, enforced by the operating
+/// system. Only supported on Unix.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// Seconds of CPU time.
+    pub cpu_time: Option<u64>,
+    /// Bytes of address space.
+    pub memory: Option<u64>,
 }
 
 impl fmt::Display for ProcessBuilder {
@@ -89,6 +104,19 @@ impl ProcessBuilder {
         self
     }
 
+    /// (chainable) Leave the environment of cargo out, so that the process
+    /// only sees the variables set with `env`.
+    pub fn env_clear(&mut self) -> &mut ProcessBuilder {
+        self.env_clear = true;
+        self
+    }
+
+    /// (chainable) Limit the resources the process may use.
+    pub fn resource_limits(&mut self, limits: ResourceLimits) -> &mut ProcessBuilder {
+        self.limits = limits;
+        self
+    }
+
     /// Get the executable name.
     pub fn get_program(&self) -> &OsString {
         &self.program
@@ -107,10 +135,11 @@ impl ProcessBuilder {
     /// Get an environment variable as the process will see it (will inherit from environment
     /// unless explicitally unset).
     pub fn get_env(&self, var: &str) -> Option<OsString> {
+        let env_clear = self.env_clear;
         self.env
             .get(var)
             .cloned()
-            .or_else(|| Some(env::var_os(var)))
+            .or_else(|| if env_clear { None } else { Some(env::var_os(var)) })
             .and_then(|s| s)
     }
 
@@ -302,6 +331,9 @@ impl ProcessBuilder {
         for arg in &self.args {
             command.arg(arg);
         }
+        if self.env_clear {
+            command.env_clear();
+        }
         for (k, v) in &self.env {
             match *v {
                 Some(ref v) => {
@@ -315,6 +347,9 @@ impl ProcessBuilder {
         if let Some(ref c) = self.jobserver {
             c.configure(&mut command);
         }
+        if self.limits != ResourceLimits::default() {
+            imp::set_resource_limits(&mut command, self.limits.clone());
+        }
         command
     }
 }
@@ -327,15 +362,48 @@ pub fn process<T: AsRef<OsStr>>(cmd: T) -> ProcessBuilder {
         cwd: None,
         env: HashMap::new(),
         jobserver: None,
+        env_clear: false,
+        limits: ResourceLimits::default(),
     }
 }
 
 #[cfg(unix)]
 mod imp {
     use crate::CargoResult;
+    use std::io;
     use std::os::unix::process::CommandExt;
+    use std::process::Command;
     use crate::util::{process_error, ProcessBuilder};
 
+    use super::ResourceLimits;
+
+    pub fn set_resource_limits(command: &mut Command, limits: ResourceLimits) {
+        fn rlimit(value: u64) -> libc::rlimit {
+            libc::rlimit {
+                rlim_cur: value as libc::rlim_t,
+                rlim_max: value as libc::rlim_t,
+            }
+        }
+
+        // Only `setrlimit` is called between the fork and the exec, which is
+        // safe to do there.
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(secs) = limits.cpu_time {
+                    if libc::setrlimit(libc::RLIMIT_CPU, &rlimit(secs)) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                if let Some(bytes) = limits.memory {
+                    if libc::setrlimit(libc::RLIMIT_AS, &rlimit(bytes)) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+
     pub fn exec_replace(process_builder: &ProcessBuilder) -> CargoResult<()> {
         let mut command = process_builder.build_command();
         let error = command.exec();
@@ -358,6 +426,9 @@ mod imp {
     use self::winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
     use self::winapi::um::consoleapi::SetConsoleCtrlHandler;
 
+    // Resource limits are rejected before a process could get any.
+    pub fn set_resource_limits(_: &mut ::std::process::Command, _: super::ResourceLimits) {}
+
     unsafe extern "system" fn ctrlc_handler(_: DWORD) -> BOOL {
         // Do nothing. Let the child process handle it.
         TRUE
@@ -1,18 +1,63 @@
 use std::env;
 use std::fmt;
 use std::mem;
+use std::path::Path;
+use std::process;
 use std::time;
 use std::iter::repeat;
 use std::cell::RefCell;
 use std::io::{stdout, StdoutLock, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde_json;
+
+use crate::util::errors::CargoResult;
+use crate::util::paths;
 
 thread_local!(static PROFILE_STACK: RefCell<Vec<time::Instant>> = RefCell::new(Vec::new()));
 thread_local!(static MESSAGES: RefCell<Vec<Message>> = RefCell::new(Vec::new()));
 
 type Message = (usize, u64, String);
 
+/// Whether `cargo --self-profile` is recording a trace.
+static SELF_PROFILING: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref TRACE: Mutex<Trace> = Mutex::new(Trace {
+        start: None,
+        events: Vec::new(),
+    });
+}
+
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+thread_local!(static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::SeqCst));
+
+/// The trace `cargo --self-profile` records.
+struct Trace {
+    start: Option<time::Instant>,
+    events: Vec<TraceEvent>,
+}
+
+/// A complete event of the Trace Event Format Chrome's `about:tracing` and
+/// other viewers read.
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    /// When it started, in microseconds since the trace started.
+    ts: u64,
+    /// How long it took, in microseconds.
+    dur: u64,
+    pid: u32,
+    tid: usize,
+}
+
 pub struct Profiler {
     desc: String,
+    /// When it started, if `cargo --self-profile` is recording a trace.
+    traced: Option<time::Instant>,
 }
 
 fn enabled_level() -> Option<usize> {
@@ -20,21 +65,69 @@ fn enabled_level() -> Option<usize> {
 }
 
 pub fn start<T: fmt::Display>(desc: T) -> Profiler {
-    if enabled_level().is_none() {
+    let traced = if SELF_PROFILING.load(Ordering::SeqCst) {
+        Some(time::Instant::now())
+    } else {
+        None
+    };
+    if enabled_level().is_none() && traced.is_none() {
         return Profiler {
             desc: String::new(),
+            traced,
         };
     }
 
-    PROFILE_STACK.with(|stack| stack.borrow_mut().push(time::Instant::now()));
+    if enabled_level().is_some() {
+        PROFILE_STACK.with(|stack| stack.borrow_mut().push(time::Instant::now()));
+    }
 
     Profiler {
         desc: desc.to_string(),
+        traced,
     }
 }
 
+/// Starts recording what the profilers started from now on do in a trace,
+/// for `cargo --self-profile`.
+pub fn start_self_profile() {
+    TRACE.lock().unwrap().start = Some(time::Instant::now());
+    SELF_PROFILING.store(true, Ordering::SeqCst);
+}
+
+/// Stops recording the trace of `cargo --self-profile` and writes it to
+/// `path`, in the JSON format of Chrome's `about:tracing`.
+pub fn finish_self_profile(path: &Path) -> CargoResult<()> {
+    SELF_PROFILING.store(false, Ordering::SeqCst);
+    let events = mem::replace(&mut TRACE.lock().unwrap().events, Vec::new());
+    let trace = json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+    });
+    paths::write(path, serde_json::to_string(&trace)?.as_bytes())
+}
+
+fn micros(duration: time::Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
 impl Drop for Profiler {
     fn drop(&mut self) {
+        if let Some(start) = self.traced {
+            let mut trace = TRACE.lock().unwrap();
+            if let Some(trace_start) = trace.start {
+                let event = TraceEvent {
+                    name: self.desc.clone(),
+                    cat: "cargo",
+                    ph: "X",
+                    ts: micros(start.duration_since(trace_start)),
+                    dur: micros(start.elapsed()),
+                    pid: process::id(),
+                    tid: THREAD.with(|thread| *thread),
+                };
+                trace.events.push(event);
+            }
+        }
+
         let enabled = match enabled_level() {
             Some(i) => i,
             None => return,
@@ -94,7 +94,9 @@ impl Rustc {
 /// What is less known is that even `rustc --version --verbose` takes about a
 /// hundred milliseconds! Because we need compiler version info even for no-op
 /// builds, we cache it here, based on compiler's mtime and rustup's current
-/// toolchain.
+/// toolchain. If those can't tell compilers apart the output of
+/// `rustc --version --verbose` is used instead, which still saves running the
+/// probes for the target information.
 ///
 /// https://github.com/rust-lang/cargo/issues/5315
 /// https://github.com/rust-lang/rust/issues/49761
@@ -114,7 +116,14 @@ struct CacheData {
 
 impl Cache {
     fn load(rustc: &Path, rustup_rustc: &Path, cache_location: Option<PathBuf>) -> Cache {
-        match (cache_location, rustc_fingerprint(rustc, rustup_rustc)) {
+        let fingerprint = match rustc_fingerprint(rustc, rustup_rustc) {
+            Err(ref e) if cache_location.is_some() => {
+                info!("fingerprinting rustc by its version: {}", e);
+                version_fingerprint(rustc)
+            }
+            fingerprint => fingerprint,
+        };
+        match (cache_location, fingerprint) {
             (Some(cache_location), Ok(rustc_fingerprint)) => {
                 let empty = CacheData {
                     rustc_fingerprint,
@@ -267,8 +276,21 @@ fn rustc_fingerprint(path: &Path, rustup_rustc: &Path) -> CargoResult<u64> {
     Ok(hasher.finish())
 }
 
+/// Fingerprints the compiler by the output of `rustc -vV`, for when it can't
+/// be done by its mtime. This is the case for the rustup proxy when it isn't
+/// run by rustup itself, as it then picks the toolchain on its own.
+fn version_fingerprint(path: &Path) -> CargoResult<u64> {
+    let output = util::process(path).arg("-vV").exec_with_output()?;
+    let mut hasher = SipHasher::new_with_keys(0, 0);
+    path.hash(&mut hasher);
+    output.stdout.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
 fn process_fingerprint(cmd: &ProcessBuilder) -> u64 {
     let mut hasher = SipHasher::new_with_keys(0, 0);
+    // The program differs from the compiler if a wrapper is used.
+    cmd.get_program().hash(&mut hasher);
     cmd.get_args().hash(&mut hasher);
     let mut env = cmd.get_envs().iter().collect::<Vec<_>>();
     env.sort_unstable();
@@ -15,8 +15,8 @@ use url::Url;
 use crate::core::dependency::{Kind, Platform};
 use crate::core::manifest::{LibKind, ManifestMetadata, TargetSourcePath, Warnings};
 use crate::core::profiles::Profiles;
-use crate::core::{Dependency, Manifest, PackageId, Summary, Target};
-use crate::core::{Edition, EitherManifest, Feature, Features, VirtualManifest};
+use crate::core::{Dependency, Manifest, PackageId, Probe, Summary, SystemDependency, Target};
+use crate::core::{Edition, EitherManifest, Feature, Features, VirtualManifest, WindowsResources};
 use crate::core::{GitReference, PackageIdSpec, SourceId, WorkspaceConfig, WorkspaceRootConfig};
 use crate::sources::{CRATES_IO_INDEX, CRATES_IO_REGISTRY};
 use crate::util::errors::{CargoError, CargoResult, CargoResultExt, ManifestError};
@@ -43,6 +43,19 @@ pub fn read_manifest(
         .map_err(|err| ManifestError::new(err, path.into()))
 }
 
+/// Reads the manifest `contents` as if they were the ones of the file at
+/// `path`.
+pub fn read_manifest_contents(
+    contents: &str,
+    path: &Path,
+    source_id: SourceId,
+    config: &Config,
+) -> CargoResult<(EitherManifest, Vec<PathBuf>)> {
+    let manifest = do_read_manifest(contents, path, source_id, config)
+        .chain_err(|| format!("failed to parse manifest at `{}`", path.display()))?;
+    Ok(manifest)
+}
+
 fn do_read_manifest(
     contents: &str,
     manifest_file: &Path,
@@ -242,16 +255,76 @@ pub struct TomlManifest {
     badges: Option<BTreeMap<String, BTreeMap<String, String>>>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[derive(Serialize, Clone, Debug, Default)]
 pub struct TomlProfiles {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub test: Option<TomlProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub doc: Option<TomlProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub bench: Option<TomlProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub dev: Option<TomlProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub release: Option<TomlProfile>,
+    /// Custom profiles like `[profile.release-lto]`, keyed by their name.
+    #[serde(flatten)]
+    pub custom: BTreeMap<String, TomlProfile>,
+}
+
+impl<'de> de::Deserialize<'de> for TomlProfiles {
+    fn deserialize<D>(d: D) -> Result<TomlProfiles, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = TomlProfiles;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a table of profiles")
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<TomlProfiles, V::Error>
+            where
+                V: de::MapAccess<'de>,
+            {
+                let mut profiles = TomlProfiles::default();
+                while let Some(name) = map.next_key::<String>()? {
+                    let profile = Some(map.next_value::<TomlProfile>()?);
+                    match name.as_str() {
+                        "test" => profiles.test = profile,
+                        "doc" => profiles.doc = profile,
+                        "bench" => profiles.bench = profile,
+                        "dev" => profiles.dev = profile,
+                        "release" => profiles.release = profile,
+                        _ => {
+                            profiles.custom.insert(name, profile.unwrap());
+                        }
+                    }
+                }
+                Ok(profiles)
+            }
+        }
+
+        d.deserialize_map(Visitor)
+    }
 }
 
 impl TomlProfiles {
+    /// Returns the profile named `name`, custom or not.
+    pub fn get(&self, name: &str) -> Option<&TomlProfile> {
+        match name {
+            "test" => self.test.as_ref(),
+            "doc" => self.doc.as_ref(),
+            "bench" => self.bench.as_ref(),
+            "dev" => self.dev.as_ref(),
+            "release" => self.release.as_ref(),
+            _ => self.custom.get(name),
+        }
+    }
+
     pub fn validate(&self, features: &Features, warnings: &mut Vec<String>) -> CargoResult<()> {
         if let Some(ref test) = self.test {
             test.validate("test", features, warnings)?;
@@ -268,6 +341,17 @@ impl TomlProfiles {
         if let Some(ref release) = self.release {
             release.validate("release", features, warnings)?;
         }
+        for (name, profile) in self.custom.iter() {
+            if !features.is_enabled(Feature::named_profiles()) {
+                // Without the feature these are just unknown keys.
+                warnings.push(format!("unused manifest key: profile.{}", name));
+                if name == "debug" {
+                    warnings.push("use `[profile.dev]` to configure debug builds".to_string());
+                }
+                continue;
+            }
+            profile.validate(name, features, warnings)?;
+        }
         Ok(())
     }
 }
@@ -389,6 +473,7 @@ pub struct TomlProfile {
     pub incremental: Option<bool>,
     pub overrides: Option<BTreeMap<ProfilePackageSpec, TomlProfile>>,
     pub build_override: Option<Box<TomlProfile>>,
+    pub inherits: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
@@ -443,8 +528,13 @@ impl TomlProfile {
             }
         }
 
+        let is_custom = match name {
+            "dev" | "release" | "test" | "bench" | "doc" => false,
+            _ => true,
+        };
         match name {
             "dev" | "release" => {}
+            _ if is_custom => {}
             _ => {
                 if self.overrides.is_some() || self.build_override.is_some() {
                     bail!(
@@ -455,6 +545,22 @@ impl TomlProfile {
                 }
             }
         }
+        if is_custom {
+            validate_custom_profile_name(name)?;
+            if self.inherits.is_none() {
+                bail!(
+                    "profile `{}` is missing an `inherits` directive \
+                     (`inherits` is required for all profiles except \
+                     `dev`, `release`, `test`, `bench` and `doc`)",
+                    name
+                );
+            }
+        } else if self.inherits.is_some() {
+            bail!(
+                "`inherits` may only be specified for custom profiles, not `{}`",
+                name
+            );
+        }
 
         match name {
             "doc" => {
@@ -474,6 +580,9 @@ impl TomlProfile {
         if self.overrides.is_some() || self.build_override.is_some() {
             bail!("Profile overrides cannot be nested.");
         }
+        if self.inherits.is_some() {
+            bail!("`inherits` may not be specified in a profile override.")
+        }
         if self.panic.is_some() {
             bail!("`panic` may not be specified in a profile override.")
         }
@@ -487,6 +596,29 @@ impl TomlProfile {
     }
 }
 
+/// Custom profiles name the directory their artifacts go to, and may not
+/// shadow the directory of the `dev` profile.
+fn validate_custom_profile_name(name: &str) -> CargoResult<()> {
+    if let Some(c) = name
+        .chars()
+        .find(|&c| !c.is_alphanumeric() && c != '_' && c != '-')
+    {
+        bail!(
+            "invalid character `{}` in profile name `{}`, profile names may \
+             only contain alphanumeric characters, `-` or `_`",
+            c,
+            name
+        );
+    }
+    if name == "debug" {
+        bail!(
+            "profile name `debug` is reserved\n\
+             use `[profile.dev]` to configure debug builds"
+        );
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Serialize, Eq, PartialEq)]
 pub struct StringOrVec(Vec<String>);
 
@@ -648,9 +780,97 @@ pub struct TomlProject {
     #[serde(rename = "license-file")]
     license_file: Option<String>,
     repository: Option<String>,
+    probes: Option<BTreeMap<String, TomlProbe>>,
+    #[serde(rename = "system-dependencies")]
+    system_dependencies: Option<BTreeMap<String, TomlSystemDependency>>,
+    #[serde(rename = "windows-resources")]
+    windows_resources: Option<TomlWindowsResources>,
     metadata: Option<toml::Value>,
 }
 
+/// An entry of `[package.probes]`, naming exactly one thing to check for.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TomlProbe {
+    #[serde(rename = "type")]
+    ty: Option<String>,
+    path: Option<String>,
+    expr: Option<String>,
+}
+
+impl TomlProbe {
+    fn to_probe(&self, name: &str) -> CargoResult<Probe> {
+        let valid = name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if name.is_empty() || !valid {
+            bail!(
+                "invalid probe name `{}`, probe names may only contain \
+                 alphanumeric characters, `-` or `_`",
+                name
+            );
+        }
+        match (&self.ty, &self.path, &self.expr) {
+            (Some(ty), None, None) => Ok(Probe::Type(ty.clone())),
+            (None, Some(path), None) => Ok(Probe::Path(path.clone())),
+            (None, None, Some(expr)) => Ok(Probe::Expr(expr.clone())),
+            _ => bail!(
+                "probe `{}` must specify exactly one of `type`, `path` or `expr`",
+                name
+            ),
+        }
+    }
+}
+
+/// An entry of `[package.system-dependencies]`, either just the version
+/// constraint of the pkg-config module or a table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum TomlSystemDependency {
+    Simple(String),
+    Detailed(DetailedTomlSystemDependency),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct DetailedTomlSystemDependency {
+    version: Option<String>,
+    pkg_config: Option<String>,
+    libs: Option<Vec<String>>,
+}
+
+impl TomlSystemDependency {
+    fn to_system_dependency(&self, name: &str) -> CargoResult<SystemDependency> {
+        let detailed = match *self {
+            TomlSystemDependency::Simple(ref version) => DetailedTomlSystemDependency {
+                version: Some(version.clone()),
+                ..Default::default()
+            },
+            TomlSystemDependency::Detailed(ref detailed) => detailed.clone(),
+        };
+        let version = match detailed.version {
+            Some(ref version) if version.trim() != "*" => {
+                Some(SystemDependency::parse_version(version).chain_err(|| {
+                    format!("failed to parse the version of system dependency `{}`", name)
+                })?)
+            }
+            _ => None,
+        };
+        Ok(SystemDependency {
+            version,
+            pkg_config: detailed.pkg_config.unwrap_or_else(|| name.to_string()),
+            libs: detailed.libs.unwrap_or_default(),
+        })
+    }
+}
+
+/// The `[package.windows-resources]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TomlWindowsResources {
+    rc: Option<String>,
+    manifest: Option<String>,
+    icon: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TomlWorkspace {
     members: Option<Vec<String>>,
@@ -733,6 +953,7 @@ impl TomlManifest {
                                         .or_else(|| v.build_dependencies2.as_ref()),
                                 )?,
                                 build_dependencies2: None,
+                                features: v.features.clone(),
                             },
                         ))
                     })
@@ -840,6 +1061,34 @@ impl TomlManifest {
             features.require(Feature::metabuild())?;
         }
 
+        let mut probes = BTreeMap::new();
+        if let Some(ref toml_probes) = project.probes {
+            features.require(Feature::build_probes())?;
+            for (name, probe) in toml_probes {
+                probes.insert(name.clone(), probe.to_probe(name)?);
+            }
+        }
+
+        let mut system_dependencies = BTreeMap::new();
+        if let Some(ref toml_deps) = project.system_dependencies {
+            features.require(Feature::system_dependencies())?;
+            for (name, dep) in toml_deps {
+                system_dependencies.insert(name.clone(), dep.to_system_dependency(name)?);
+            }
+        }
+
+        let windows_resources = match project.windows_resources {
+            Some(ref resources) => {
+                features.require(Feature::windows_resources())?;
+                Some(WindowsResources {
+                    rc: resources.rc.as_ref().map(PathBuf::from),
+                    manifest: resources.manifest.as_ref().map(PathBuf::from),
+                    icon: resources.icon.as_ref().map(PathBuf::from),
+                })
+            }
+            None => None,
+        };
+
         // If we have no lib at all, use the inferred lib if available
         // If we have a lib with a path, we're done
         // If we have a lib with no path, use the inferred lib or_else package name
@@ -969,6 +1218,30 @@ impl TomlManifest {
             project.links.as_ref().map(|x| x.as_str()),
             project.namespaced_features.unwrap_or(false),
         )?;
+
+        let mut target_default_features = Vec::new();
+        for (name, platform) in me.target.iter().flat_map(|t| t) {
+            let platform_features = match platform.features {
+                Some(ref features) => features,
+                None => continue,
+            };
+            features.require(Feature::target_default_features())?;
+            for key in platform_features.keys() {
+                if key != "default" {
+                    bail!(
+                        "only default features can be specified for a target, \
+                         found `{}` in `target.{}.features`",
+                        key,
+                        name
+                    );
+                }
+            }
+            if let Some(default) = platform_features.get("default") {
+                target_default_features.push((name.parse()?, default.clone()));
+            }
+        }
+        let summary = summary.set_target_default_features(target_default_features)?;
+
         let metadata = ManifestMetadata {
             description: project.description.clone(),
             homepage: project.homepage.clone(),
@@ -1049,6 +1322,9 @@ impl TomlManifest {
             project.default_run.clone(),
             Rc::clone(me),
             project.metabuild.clone().map(|sov| sov.0),
+            probes,
+            system_dependencies,
+            windows_resources,
         );
         if project.license_file.is_some() && project.license.is_some() {
             manifest.warnings_mut().add_warning(
@@ -1385,6 +1661,7 @@ impl DetailedTomlDependency {
                 if cx.source_id.is_path() {
                     let path = cx.root.join(path);
                     let path = util::normalize_path(&path);
+                    let path = cx.config.apply_symlink_policy(path)?;
                     SourceId::for_path(&path)?
                 } else {
                     cx.source_id
@@ -1453,6 +1730,7 @@ struct TomlTarget {
     #[serde(rename = "required-features")]
     required_features: Option<Vec<String>>,
     edition: Option<String>,
+    versioned: Option<bool>,
 }
 
 #[derive(Clone)]
@@ -1488,6 +1766,7 @@ struct TomlPlatform {
     dev_dependencies: Option<BTreeMap<String, TomlDependency>>,
     #[serde(rename = "dev_dependencies")]
     dev_dependencies2: Option<BTreeMap<String, TomlDependency>>,
+    features: Option<BTreeMap<String, Vec<String>>>,
 }
 
 impl TomlTarget {
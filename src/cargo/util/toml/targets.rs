@@ -776,6 +776,13 @@ fn configure(features: &Features, toml: &TomlTarget, target: &mut Target) -> Car
                 .chain_err(|| "failed to parse the `edition` key")?,
         );
     }
+    if let Some(versioned) = toml.versioned {
+        features.require(Feature::versioned_libraries())?;
+        if !target.is_lib() {
+            bail!("the `versioned` key is only supported for the library target");
+        }
+        target.set_versioned(versioned);
+    }
     Ok(())
 }
 
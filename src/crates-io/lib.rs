@@ -38,6 +38,26 @@ pub struct Crate {
     pub max_version: String,
 }
 
+#[derive(Deserialize)]
+pub struct CrateInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub documentation: Option<String>,
+    pub repository: Option<String>,
+    pub max_version: String,
+}
+
+#[derive(Deserialize)]
+pub struct VersionInfo {
+    pub num: String,
+    pub license: Option<String>,
+    #[serde(default)]
+    pub rust_version: Option<String>,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
 #[derive(Serialize)]
 pub struct NewCrate {
     pub name: String,
@@ -57,6 +77,8 @@ pub struct NewCrate {
     pub repository: Option<String>,
     pub badges: BTreeMap<String, BTreeMap<String, String>>,
     #[serde(default)] pub links: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -123,6 +145,12 @@ struct Crates {
     crates: Vec<Crate>,
     meta: TotalCrates,
 }
+#[derive(Deserialize)]
+struct CrateResponse {
+    #[serde(rename = "crate")]
+    krate: CrateInfo,
+    versions: Vec<VersionInfo>,
+}
 impl Registry {
     pub fn new(host: String, token: Option<String>) -> Registry {
         Registry::new_handle(host, token, Easy::new())
@@ -250,6 +278,12 @@ impl Registry {
         Ok((crates.crates, crates.meta.total))
     }
 
+    pub fn crate_info(&mut self, krate: &str) -> Result<(CrateInfo, Vec<VersionInfo>)> {
+        let body = self.req(&format!("/crates/{}", krate), None, Auth::Unauthorized)?;
+        let info = serde_json::from_str::<CrateResponse>(&body)?;
+        Ok((info.krate, info.versions))
+    }
+
     pub fn yank(&mut self, krate: &str, version: &str) -> Result<()> {
         let body = self.delete(&format!("/crates/{}/{}/yank", krate, version), None)?;
         assert!(serde_json::from_str::<R>(&body)?.ok);
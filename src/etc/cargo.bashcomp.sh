@@ -32,37 +32,48 @@ _cargo()
 	local opt_jobs='-j --jobs'
 	local opt_force='-f --force'
 	local opt_test='--test --bench'
-	local opt_lock='--frozen --locked'
+	local opt_lock='--frozen --locked --unlocked --ephemeral'
 
-	local opt___nocmd="$opt_common -V --version --list"
-	local opt__bench="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --target --lib --bin --example --no-run"
-	local opt__build="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --target --lib --bin --example --release"
-	local opt__check="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --target --lib --bin --example --release"
+	local opt___nocmd="$opt_common -V --version --list --self-profile"
+	local opt__add="$opt_common $opt_mani $opt_lock --auto --yes --registry"
+	local opt__bench="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --deny-warnings --target --target-cpu --lib --bin --example --no-run"
+	local opt__build="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --assert-reproducible --message-format --deny-warnings --dependency --dist --emit --lipo --crate-type --from-archive --repeat-failed --show-failed-command --target --target-cpu --lib --bin --example --release"
+	local opt__check="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --deny-warnings --dependency --remote --target --target-cpu --lib --bin --example --release"
 	local opt__clean="$opt_common $opt_pkg $opt_mani $opt_lock --target --release"
+	local opt__complete_crate_name="$opt_common $opt_lock --index --registry"
+	local opt__complete_features="${opt__complete_crate_name}"
+	local opt__complete_versions="${opt__complete_crate_name}"
+	local opt__diff="$opt_common $opt_lock --registry --context --output"
 	local opt__doc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs --message-format --bin --lib --target --open --no-deps --release"
-	local opt__fetch="$opt_common $opt_mani $opt_lock"
-	local opt__generate_lockfile="${opt__fetch}"
+	local opt__exec="$opt_common $opt_pkg $opt_mani $opt_lock --each --all --exclude --no-deps --release"
+	local opt__fetch="$opt_common $opt_mani $opt_lock --target --to-archive"
+	local opt__generate_lockfile="$opt_common $opt_mani $opt_lock"
+	local opt__graph="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --all --exclude --format --edges --depth --show-features"
 	local opt__git_checkout="$opt_common $opt_lock --reference --url"
 	local opt__help="$opt_help"
+	local opt__info="$opt_common $opt_lock --index --registry"
 	local opt__init="$opt_common $opt_lock --bin --lib --name --vcs"
-	local opt__install="$opt_common $opt_feat $opt_jobs $opt_lock $opt_force --bin --branch --debug --example --git --list --path --rev --root --tag --vers"
+	local opt__install="$opt_common $opt_feat $opt_jobs $opt_lock $opt_force --bin --branch --debug --example --git --ignore-local-config --list --path --rev --root --tag --vers"
 	local opt__locate_project="$opt_mani -h --help"
 	local opt__login="$opt_common $opt_lock --host"
-	local opt__metadata="$opt_common $opt_feat $opt_mani $opt_lock --format-version --no-deps"
+	local opt__manifest="$opt_common $opt_mani $opt_lock --toml --enables"
+	local opt__metadata="$opt_common $opt_feat $opt_mani $opt_lock --format-version --no-deps --no-deps-resolve --target-info --target"
 	local opt__new="$opt_common $opt_lock --vcs --bin --lib --name"
 	local opt__owner="$opt_common $opt_lock -a --add -r --remove -l --list --index --token"
 	local opt__package="$opt_common $opt_mani $opt_lock $opt_jobs --allow-dirty -l --list --no-verify --no-metadata"
-	local opt__pkgid="${opt__fetch} $opt_pkg"
-	local opt__publish="$opt_common $opt_mani $opt_lock $opt_jobs --allow-dirty --dry-run --host --token --no-verify"
+	local opt__pkgid="${opt__generate_lockfile} $opt_pkg"
+	local opt__publish="$opt_common $opt_mani $opt_lock $opt_jobs --allow-dirty --dry-run --show-payload --host --token --no-verify"
 	local opt__read_manifest="$opt_help $opt_verbose $opt_mani $opt_color --no-deps"
-	local opt__run="$opt_common $opt_feat $opt_mani $opt_lock $opt_jobs --message-format --target --bin --example --release"
-	local opt__rustc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --profile --target --lib --bin --example --release"
+	local opt__run="$opt_common $opt_feat $opt_mani $opt_lock $opt_jobs --message-format --deny-warnings --target --target-cpu --bin --example --release"
+	local opt__rustc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --deny-warnings --profile --target --target-cpu --lib --bin --example --release"
 	local opt__rustdoc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --target --lib --bin --example --release --open"
 	local opt__search="$opt_common $opt_lock --host --limit"
-	local opt__test="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --all --doc --target --lib --bin --example --no-run --release --no-fail-fast"
+	local opt__test="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --deny-warnings --all --doc --target --target-cpu --lib --bin --example --no-run --release --no-fail-fast"
 	local opt__uninstall="$opt_common $opt_lock --bin --root"
-	local opt__update="$opt_common $opt_pkg $opt_mani $opt_lock --aggressive --precise"
-	local opt__verify_project="${opt__fetch}"
+	local opt__update="$opt_common $opt_pkg $opt_mani $opt_lock --aggressive --precise --pin --unpin"
+	local opt__vendor="$opt_common $opt_mani $opt_lock --verify"
+	local opt__verify_crate="$opt_common $opt_lock --registry --against-repo"
+	local opt__verify_project="${opt__generate_lockfile}"
 	local opt__version="$opt_help $opt_verbose $opt_color"
 	local opt__yank="$opt_common $opt_lock --vers --undo --index --token"
 
@@ -108,8 +119,12 @@ _cargo()
 				COMPREPLY=( $( compgen -W "$__cargo_commands" -- "$cur" ) )
 				;;
 			*)
-				local opt_var=opt__${cmd//-/_}
-				COMPREPLY=( $( compgen -W "${!opt_var}" -- "$cur" ) )
+				if [[ -n "$cur" && "$cur" != -* && ( "$cmd" == add || "$cmd" == info || "$cmd" == install ) ]]; then
+					COMPREPLY=( $( cargo complete-crate-name "$cur" 2>/dev/null ) )
+				else
+					local opt_var=opt__${cmd//-/_}
+					COMPREPLY=( $( compgen -W "${!opt_var}" -- "$cur" ) )
+				fi
 				;;
 		esac
 	fi
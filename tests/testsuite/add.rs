@@ -0,0 +1,185 @@
+use crate::support::project;
+use crate::support::registry::Package;
+
+#[test]
+fn add_crates() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.2.0").publish();
+    Package::new("baz-qux", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar baz-qux@1.0")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATED] `dependencies.bar` in [CWD]/Cargo.toml
+[UPDATED] `dependencies.baz-qux` in [CWD]/Cargo.toml
+",
+        )
+        .run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.2.0"
+            baz-qux = "1.0"
+            "#
+    );
+
+    p.cargo("add quux")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] could not find `quux` in registry `[..]`
+",
+        )
+        .run();
+
+    p.cargo("add bar@9.0")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] could not find `bar` in registry `[..]` with version `9.0`
+",
+        )
+        .run();
+}
+
+#[test]
+fn add_prefers_stable_versions() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.2.0-alpha.1").publish();
+    Package::new("baz", "1.0.0-rc.1").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar baz").run();
+    assert!(p.read_file("Cargo.toml").contains(
+        r#"
+            bar = "0.1.0"
+            baz = "1.0.0-rc.1"
+"#
+    ));
+}
+
+#[test]
+fn add_auto_skips_local_modules() {
+    Package::new("helpers", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "
+            pub mod helpers {
+                pub fn f() {}
+            }
+
+            mod inner {
+                pub fn g() {
+                    helpers::f();
+                }
+            }
+            ",
+        )
+        .build();
+
+    p.cargo("add --auto --yes")
+        .with_stderr_does_not_contain("[UPDATED] [..]")
+        .run();
+}
+
+#[test]
+fn add_auto() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn f() {}")
+        .publish();
+    Package::new("baz-qux", "1.0.0")
+        .file("src/lib.rs", "pub fn g() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            edition = "2018"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "
+            use bar::f;
+            mod local;
+
+            pub fn h() {
+                f();
+                baz_qux::g();
+                local::i();
+                missing::j();
+            }
+            ",
+        )
+        .file("src/local.rs", "pub fn i() {}")
+        .build();
+
+    p.cargo("add --auto --yes")
+        .with_stderr_contains("[UPDATED] `dependencies.bar` in [CWD]/Cargo.toml")
+        .with_stderr_contains("[UPDATED] `dependencies.baz-qux` in [CWD]/Cargo.toml")
+        .with_stderr_contains(
+            "[WARNING] `missing` couldn't be resolved, but there's no crate of that name in [..]",
+        )
+        .run();
+    assert!(p.read_file("Cargo.toml").contains(
+        r#"
+[dependencies]
+bar = "0.1.0"
+baz-qux = "1.0.0""#
+    ));
+
+    p.cargo("add --auto --yes")
+        .with_stderr_does_not_contain("[UPDATED] [..]")
+        .with_stderr_contains("[WARNING] `missing` couldn't be resolved, [..]")
+        .run();
+}
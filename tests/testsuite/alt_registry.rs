@@ -54,17 +54,16 @@ fn depend_on_alt_registry() {
 
     p.cargo("build")
         .masquerade_as_nightly_cargo()
-        .with_stderr(&format!(
+        .with_stderr(
             "\
-[UPDATING] `{reg}` index
+[UPDATING] `alternative` index
 [DOWNLOADING] crates ...
 [DOWNLOADED] bar v0.0.1 (registry `[ROOT][..]`)
 [COMPILING] bar v0.0.1 (registry `[ROOT][..]`)
 [COMPILING] foo v0.0.1 ([CWD])
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]s
 ",
-            reg = registry::alt_registry_path().to_str().unwrap()
-        )).run();
+        ).run();
 
     p.cargo("clean").masquerade_as_nightly_cargo().run();
 
@@ -108,9 +107,9 @@ fn depend_on_alt_registry_depends_on_same_registry_no_index() {
 
     p.cargo("build")
         .masquerade_as_nightly_cargo()
-        .with_stderr(&format!(
+        .with_stderr(
             "\
-[UPDATING] `{reg}` index
+[UPDATING] `alternative` index
 [DOWNLOADING] crates ...
 [DOWNLOADED] [..] v0.0.1 (registry `[ROOT][..]`)
 [DOWNLOADED] [..] v0.0.1 (registry `[ROOT][..]`)
@@ -119,8 +118,7 @@ fn depend_on_alt_registry_depends_on_same_registry_no_index() {
 [COMPILING] foo v0.0.1 ([CWD])
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]s
 ",
-            reg = registry::alt_registry_path().to_str().unwrap()
-        )).run();
+        ).run();
 }
 
 #[test]
@@ -151,9 +149,9 @@ fn depend_on_alt_registry_depends_on_same_registry() {
 
     p.cargo("build")
         .masquerade_as_nightly_cargo()
-        .with_stderr(&format!(
+        .with_stderr(
             "\
-[UPDATING] `{reg}` index
+[UPDATING] `alternative` index
 [DOWNLOADING] crates ...
 [DOWNLOADED] [..] v0.0.1 (registry `[ROOT][..]`)
 [DOWNLOADED] [..] v0.0.1 (registry `[ROOT][..]`)
@@ -162,8 +160,7 @@ fn depend_on_alt_registry_depends_on_same_registry() {
 [COMPILING] foo v0.0.1 ([CWD])
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]s
 ",
-            reg = registry::alt_registry_path().to_str().unwrap()
-        )).run();
+        ).run();
 }
 
 #[test]
@@ -196,7 +193,7 @@ fn depend_on_alt_registry_depends_on_crates_io() {
         .masquerade_as_nightly_cargo()
         .with_stderr(&format!(
             "\
-[UPDATING] `{alt_reg}` index
+[UPDATING] `alternative` index
 [UPDATING] `{reg}` index
 [DOWNLOADING] crates ...
 [DOWNLOADED] [..] v0.0.1 (registry `[ROOT][..]`)
@@ -206,7 +203,6 @@ fn depend_on_alt_registry_depends_on_crates_io() {
 [COMPILING] foo v0.0.1 ([CWD])
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]s
 ",
-            alt_reg = registry::alt_registry_path().to_str().unwrap(),
             reg = registry::registry_path().to_str().unwrap()
         )).run();
 }
@@ -361,10 +357,8 @@ fn alt_registry_and_crates_io_deps() {
 
     p.cargo("build")
         .masquerade_as_nightly_cargo()
-        .with_stderr_contains(format!(
-            "[UPDATING] `{}` index",
-            registry::alt_registry_path().to_str().unwrap()
-        )).with_stderr_contains(&format!(
+        .with_stderr_contains("[UPDATING] `alternative` index")
+        .with_stderr_contains(&format!(
             "[UPDATING] `{}` index",
             registry::registry_path().to_str().unwrap()))
         .with_stderr_contains("[DOWNLOADED] crates_io_dep v0.0.1 (registry `[ROOT][..]`)")
@@ -403,7 +403,12 @@ fn bad_crate_type() {
     p.cargo("build -v")
         .with_status(101)
         .with_stderr_contains(
-            "error: failed to run `rustc` to learn about crate-type bad_type information",
+            "\
+[ERROR] failed to learn about the files of crate-type bad_type of `foo v0.0.0 ([CWD])`
+
+Caused by:
+  rustc [..] doesn't support crate-type bad_type: unknown crate type: `bad_type`[..]
+",
         ).run();
 }
 
@@ -1199,3 +1204,24 @@ Caused by:
 ",
         ).run();
 }
+
+#[test]
+fn bad_sysroot_libdir() {
+    let p = project()
+        .file(
+            ".cargo/config",
+            r#"
+            [build]
+            sysroot-libdir = "/lib"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("metadata --no-deps --target-info --format-version 1")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `build.sysroot-libdir` in [..]config must be relative to the sysroot: `/lib`
+",
+        ).run();
+}
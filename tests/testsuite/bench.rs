@@ -1,6 +1,6 @@
 use crate::support::is_nightly;
 use crate::support::paths::CargoPathExt;
-use crate::support::{basic_bin_manifest, basic_lib_manifest, basic_manifest, project};
+use crate::support::{basic_bin_manifest, basic_lib_manifest, basic_manifest, project, rustc_host};
 
 #[test]
 fn cargo_bench_simple() {
@@ -1509,6 +1509,26 @@ fn json_artifact_includes_executable_for_benchmark() {
 
     p.cargo("bench --no-run --message-format=json")
         .with_json(r#"
+            {
+                "reason": "target-info",
+                "kind": "host",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
+            {
+                "reason": "target-info",
+                "kind": "target",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
             {
                 "executable": "[..]/foo/target/release/benchmark-[..][EXE]",
                 "features": [],
@@ -1528,3 +1548,78 @@ fn json_artifact_includes_executable_for_benchmark() {
         "#)
         .run();
 }
+
+#[test]
+fn bench_no_run_run_manifest() {
+    let target = rustc_host();
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                [target.{}]
+                runner = "nonexistent-runner -r"
+            "#,
+                target
+            ),
+        ).build();
+
+    p.cargo("bench --no-run --run-manifest -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("foo v0.0.1 ([CWD]) lib `foo`")
+        .with_stdout_contains("  executable: [CWD]/target/release/deps/foo-[..][EXE]")
+        .with_stdout_contains(
+            "  command: `nonexistent-runner -r [CWD]/target/release/deps/foo-[..][EXE] --bench`",
+        ).run();
+
+    p.cargo("bench --no-run --run-manifest -Zunstable-options --message-format=json")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+            {
+                "reason": "target-info",
+                "kind": "host",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
+            {
+                "reason": "target-info",
+                "kind": "target",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
+            {
+                "reason": "compiler-artifact",
+                "package_id": "foo 0.0.1 ([..])",
+                "target": "{...}",
+                "profile": "{...}",
+                "features": [],
+                "filenames": "{...}",
+                "executable": "[..]/foo-[..][EXE]",
+                "fresh": true
+            }
+
+            {
+                "reason": "test-executable",
+                "package_id": "foo 0.0.1 ([..])",
+                "target": "{...}",
+                "mode": "bench",
+                "executable": "[..]/target/release/deps/foo-[..][EXE]",
+                "program": "nonexistent-runner",
+                "args": ["-r", "[..]/target/release/deps/foo-[..][EXE]", "--bench"],
+                "cwd": "[..]/foo",
+                "env": "{...}"
+            }
+        "#,
+        ).run();
+}
@@ -1,6 +1,7 @@
 use std::env;
 use std::fs::{self, File};
 use std::io::prelude::*;
+use std::path::Path;
 
 use cargo::util::paths::dylib_path_envvar;
 use crate::support::paths::{root, CargoPathExt};
@@ -579,6 +580,202 @@ fn cargo_compile_with_warnings_in_a_dep_package() {
     p.process(&p.bin("foo")).with_stdout("test passed\n").run();
 }
 
+#[test]
+fn cargo_compile_deny_warnings() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#,
+        ).file("src/lib.rs", "fn dead() {}")
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file("bar/src/lib.rs", "fn dead() {}")
+        .build();
+
+    p.cargo("build --deny-warnings")
+        .with_status(101)
+        .with_stderr_contains("[..]function is never used: `dead`[..]")
+        .with_stderr_contains(
+            "\
+[ERROR] build failed because warnings are denied:
+  `foo` emitted 1 warning",
+        ).with_stderr_does_not_contain("[..]`bar` emitted[..]")
+        .run();
+
+    // The unit isn't fresh, so the warnings are reported again.
+    p.cargo("build --deny-warnings")
+        .with_status(101)
+        .with_stderr_contains("[..]function is never used: `dead`[..]")
+        .run();
+
+    p.change_file("src/lib.rs", "");
+    p.cargo("build --deny-warnings").run();
+}
+
+#[test]
+fn cargo_compile_allow_warnings_config() {
+    let p = project()
+        .file("src/lib.rs", "fn dead() {}")
+        .file(
+            ".cargo/config",
+            r#"
+            [build]
+            warnings = "allow"
+        "#,
+        ).build();
+
+    p.cargo("build")
+        .with_stderr_does_not_contain("[..]function is never used[..]")
+        .run();
+}
+
+#[test]
+fn cargo_compile_bad_warnings_config() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+            [build]
+            warnings = "forbid"
+        "#,
+        ).build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] invalid configuration for key `build.warnings`, expected `warn`, \
+`allow` or `deny`, found `forbid` (found in [..]config)
+",
+        ).run();
+}
+
+#[test]
+fn cargo_compile_emit() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#,
+        ).file("src/lib.rs", "pub fn foo() {}")
+        .file("src/main.rs", "fn main() { foo::foo(); bar::bar(); }")
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .build();
+
+    p.cargo("build --emit asm,llvm-ir --message-format json")
+        .with_stdout_contains(
+            r#"{"reason":"compiler-artifact",[..]"filenames":[[..]"[..]emit/lib/foo.s","[..]emit/lib/foo.ll"],[..]}"#,
+        ).run();
+
+    assert!(p.root().join("target/debug/emit/lib/foo.s").is_file());
+    assert!(p.root().join("target/debug/emit/lib/foo.ll").is_file());
+    assert!(p.root().join("target/debug/emit/bin/foo.s").is_file());
+    assert!(p.root().join("target/debug/emit/bin/foo.ll").is_file());
+    assert!(!p.root().join("target/debug/emit/lib/bar.s").exists());
+
+    // The files are written again when they're gone.
+    p.root().join("target/debug/emit").rm_rf();
+    p.cargo("build --emit asm").run();
+    assert!(p.root().join("target/debug/emit/lib/foo.s").is_file());
+}
+
+#[test]
+fn cargo_compile_bad_emit() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build --emit asm,obj")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] invalid emit kind `obj`, expected one of `asm`, `llvm-ir`, `llvm-bc` or `mir`
+",
+        ).run();
+}
+
+#[test]
+fn cargo_compile_target_cpu() {
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .file("build.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -v --target-cpu native --message-format json")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name foo src/main.rs [..]-C target-cpu=native[..]`")
+        .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name build_script_build [..]target-cpu[..]`")
+        .with_stdout_contains(
+            r#"{"reason":"compiler-artifact",[..]"target":{"kind":["bin"][..]"target_cpu":{"name":"native","features":[..]}}"#,
+        ).run();
+
+    p.cargo("build --target-cpu native -Z unstable-options --out-dir out")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] the artifacts copied to `--out-dir` were built with `--target-cpu native`, \
+             they use the features of the CPU of this machine and may not run on others",
+        ).run();
+}
+
+#[test]
+fn cargo_compile_artifact_manifest() {
+    let host = rustc_host();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [lib]
+            crate-type = ["cdylib"]
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                [build]
+                artifact-manifest = true
+
+                [target.{}]
+                min-sdk = "21"
+                abi = "x86_64"
+            "#,
+                host
+            ),
+        ).build();
+
+    p.cargo("build").run();
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&p.read_file("target/debug/artifacts.json")).unwrap();
+    assert_eq!(manifest["version"], 1);
+    assert_eq!(manifest["profile"], "debug");
+    let target = &manifest["targets"][0];
+    assert_eq!(target["triple"], host.as_str());
+    assert_eq!(target["min_sdk"], "21");
+    assert_eq!(target["abi"], "x86_64");
+    let artifact = &target["artifacts"][0];
+    assert_eq!(artifact["target"]["crate_types"][0], "cdylib");
+    let filename = artifact["filenames"][0].as_str().unwrap();
+    assert!(filename.starts_with(p.target_debug_dir().to_str().unwrap()));
+}
+
 #[test]
 fn cargo_compile_with_nested_deps_inferred() {
     let p = project()
@@ -921,9 +1118,7 @@ fn cargo_compile_path_with_offline() {
         .file("bar/src/lib.rs", "")
         .build();
 
-    p.cargo("build -Zoffline")
-        .masquerade_as_nightly_cargo()
-        .run();
+    p.cargo("build --offline").run();
 }
 
 #[test]
@@ -966,8 +1161,7 @@ fn cargo_compile_with_downloaded_dependency_with_offline() {
         ).file("src/lib.rs", "")
         .build();
 
-    p2.cargo("build -Zoffline")
-        .masquerade_as_nightly_cargo()
+    p2.cargo("build --offline")
         .with_stderr(
             "\
 [COMPILING] present_dep v1.2.3
@@ -993,19 +1187,26 @@ fn cargo_compile_offline_not_try_update() {
         ).file("src/lib.rs", "")
         .build();
 
-    p.cargo("build -Zoffline")
-        .masquerade_as_nightly_cargo()
+    p.cargo("build --offline")
         .with_status(101)
         .with_stderr(
             "\
 error: no matching package named `not_cached_dep` found
 location searched: registry `[..]`
 required by package `bar v0.1.0 ([..])`
-As a reminder, you're using offline mode (-Z offline) \
+As a reminder, you're using offline mode (--offline) \
 which can sometimes cause surprising resolution failures, \
 if this error is too confusing you may with to retry \
 without the offline flag.",
         ).run();
+
+    // Offline mode can also be enabled in the configuration.
+    p.change_file(".cargo/config", "[net]\noffline = true");
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("error: no matching package named `not_cached_dep` found")
+        .with_stderr_contains("As a reminder, you're using offline mode (--offline) [..]")
+        .run();
 }
 
 #[test]
@@ -1063,8 +1264,7 @@ fn main(){
 }",
         ).build();
 
-    p2.cargo("run -Zoffline")
-        .masquerade_as_nightly_cargo()
+    p2.cargo("run --offline")
         .with_stderr(
             "\
 [COMPILING] present_dep v1.2.3
@@ -1204,8 +1404,7 @@ fn compile_offline_while_transitive_dep_not_cached() {
 
     drop(File::create(baz_path).ok().unwrap().write_all(&content));
 
-    p.cargo("build -Zoffline")
-        .masquerade_as_nightly_cargo()
+    p.cargo("build --offline")
         .with_status(101)
         .with_stderr(
             "\
@@ -1213,7 +1412,7 @@ error: no matching package named `baz` found
 location searched: registry `[..]`
 required by package `bar v0.1.0`
     ... which is depended on by `foo v0.0.1 ([CWD])`
-As a reminder, you're using offline mode (-Z offline) \
+As a reminder, you're using offline mode (--offline) \
 which can sometimes cause surprising resolution failures, \
 if this error is too confusing you may with to retry \
 without the offline flag.",
@@ -3066,6 +3265,26 @@ fn compiler_json_error_format() {
     p.cargo("build -v --message-format=json --jobs=1")
         .with_json(
             r#"
+    {
+        "reason": "target-info",
+        "kind": "host",
+        "triple": "[..]",
+        "cfg": "{...}",
+        "linker": "[..]",
+        "ar": "[..]",
+        "llvm_tools_dir": "{...}"
+    }
+
+    {
+        "reason": "target-info",
+        "kind": "target",
+        "triple": "[..]",
+        "cfg": "{...}",
+        "linker": "[..]",
+        "ar": "[..]",
+        "llvm_tools_dir": "{...}"
+    }
+
     {
         "reason":"compiler-artifact",
         "package_id":"foo 0.5.0 ([..])",
@@ -3177,6 +3396,26 @@ fn compiler_json_error_format() {
     p.cargo("build -v --message-format=json --jobs=1")
         .with_json(
             r#"
+    {
+        "reason": "target-info",
+        "kind": "host",
+        "triple": "[..]",
+        "cfg": "{...}",
+        "linker": "[..]",
+        "ar": "[..]",
+        "llvm_tools_dir": "{...}"
+    }
+
+    {
+        "reason": "target-info",
+        "kind": "target",
+        "triple": "[..]",
+        "cfg": "{...}",
+        "linker": "[..]",
+        "ar": "[..]",
+        "llvm_tools_dir": "{...}"
+    }
+
     {
         "reason":"compiler-artifact",
         "package_id":"foo 0.5.0 ([..])",
@@ -3285,6 +3524,26 @@ fn message_format_json_forward_stderr() {
     p.cargo("rustc --release --bin foo --message-format JSON")
         .with_json(
             r#"
+    {
+        "reason": "target-info",
+        "kind": "host",
+        "triple": "[..]",
+        "cfg": "{...}",
+        "linker": "[..]",
+        "ar": "[..]",
+        "llvm_tools_dir": "{...}"
+    }
+
+    {
+        "reason": "target-info",
+        "kind": "target",
+        "triple": "[..]",
+        "cfg": "{...}",
+        "linker": "[..]",
+        "ar": "[..]",
+        "llvm_tools_dir": "{...}"
+    }
+
     {
         "reason":"compiler-message",
         "package_id":"foo 0.5.0 ([..])",
@@ -3732,6 +3991,128 @@ fn rustc_wrapper() {
         .run();
 }
 
+#[cfg(unix)]
+#[test]
+fn rustc_crash_writes_ice_report() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", &main_file(r#""i am foo""#, &[]))
+        // Crashes like rustc does, with a backtrace if asked for one, which
+        // is when it's run again, in the out dir given then.
+        .file(
+            "ice",
+            r#"#!/bin/sh
+            case "$*" in *"--crate-name foo "*) ;; *) exec "$@" ;; esac
+            echo "error: internal compiler error: boom" >&2
+            echo "thread 'rustc' panicked at 'boom'" >&2
+            if [ -n "$RUST_BACKTRACE" ]; then
+                echo "stack backtrace:" >&2
+                while [ $# -gt 0 ]; do
+                    if [ "$1" = "--out-dir" ]; then touch "$2/rerun"; fi
+                    shift
+                done
+            fi
+            exit 101
+        "#,
+        ).build();
+    let ice = p.root().join("ice");
+    let mut perms = fs::metadata(&ice).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&ice, perms).unwrap();
+
+    p.cargo("build")
+        .env("RUSTC_WRAPPER", &ice)
+        .env_remove("RUST_BACKTRACE")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] Could not compile `foo`.
+
+Caused by:
+  rustc crashed compiling `foo(bin)`, which is a bug in the compiler
+the command, its output and its backtrace are in `[CWD]/target/ice-reports/foo-foo-[..]`, \
+please attach them to an issue at https://github.com/rust-lang/rust/issues
+",
+        ).run();
+
+    let reports = p.root().join("target/ice-reports");
+    let report = fs::read_dir(&reports).unwrap().next().unwrap().unwrap().path();
+    let mut stderr = String::new();
+    File::open(report.join("stderr.txt"))
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    assert!(stderr.contains("stack backtrace:"));
+    let mut command = String::new();
+    File::open(report.join("command.sh"))
+        .unwrap()
+        .read_to_string(&mut command)
+        .unwrap();
+    assert!(command.contains("--crate-name foo"));
+    let mut summary = String::new();
+    File::open(report.join("report.txt"))
+        .unwrap()
+        .read_to_string(&mut summary)
+        .unwrap();
+    assert!(summary.contains("rerun with RUST_BACKTRACE=1: crashed again"));
+    assert!(summary.contains("input snapshot: not available"));
+    assert!(report.join("out/rerun").is_file());
+    assert!(!p.root().join("target/debug/deps/rerun").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn rustc_crash_with_backtrace_is_not_rerun() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "ice",
+            r#"#!/bin/sh
+            case "$*" in *"--crate-name foo "*) ;; *) exec "$@" ;; esac
+            echo "thread 'rustc' panicked at 'boom'" >&2
+            echo "stack backtrace:" >&2
+            echo "ran" >> "$(dirname "$0")/runs"
+            exit 101
+        "#,
+        ).build();
+    let ice = p.root().join("ice");
+    let mut perms = fs::metadata(&ice).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&ice, perms).unwrap();
+
+    p.cargo("build")
+        .env("RUSTC_WRAPPER", &ice)
+        .with_status(101)
+        .with_stderr_contains("[..]rustc crashed compiling `foo(bin)`[..]")
+        .run();
+
+    let mut runs = String::new();
+    File::open(p.root().join("runs"))
+        .unwrap()
+        .read_to_string(&mut runs)
+        .unwrap();
+    assert_eq!(runs, "ran\n");
+    let reports = p.root().join("target/ice-reports");
+    let report = fs::read_dir(&reports).unwrap().next().unwrap().unwrap().path();
+    let mut stderr = String::new();
+    File::open(report.join("stderr.txt"))
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    assert!(stderr.contains("stack backtrace:"));
+    let mut summary = String::new();
+    File::open(report.join("report.txt"))
+        .unwrap()
+        .read_to_string(&mut summary)
+        .unwrap();
+    assert!(summary.contains("rerun with RUST_BACKTRACE=1: not needed"));
+}
+
 #[test]
 fn cdylib_not_lifted() {
     let p = project()
@@ -3799,62 +4180,172 @@ fn cdylib_final_outputs() {
 }
 
 #[test]
-fn deterministic_cfg_flags() {
-    // This bug is non-deterministic
-
+fn staticlib_and_cdylib_compiled_once() {
     let p = project()
         .file(
             "Cargo.toml",
             r#"
             [project]
             name = "foo"
-            version = "0.1.0"
             authors = []
-            build = "build.rs"
+            version = "0.1.0"
 
-            [features]
-            default = ["f_a", "f_b", "f_c", "f_d"]
-            f_a = []
-            f_b = []
-            f_c = []
-            f_d = []
+            [lib]
+            crate-type = ["staticlib", "cdylib"]
+
+            [profile.release]
+            lto = true
         "#,
-        ).file(
-            "build.rs",
-            r#"
-                fn main() {
-                    println!("cargo:rustc-cfg=cfg_a");
-                    println!("cargo:rustc-cfg=cfg_b");
-                    println!("cargo:rustc-cfg=cfg_c");
-                    println!("cargo:rustc-cfg=cfg_d");
-                    println!("cargo:rustc-cfg=cfg_e");
-                }
-            "#,
-        ).file("src/main.rs", "fn main() {}")
+        ).file("src/lib.rs", "")
         .build();
 
-    p.cargo("build -v")
+    p.cargo("build -v --release")
         .with_stderr(
             "\
-[COMPILING] foo v0.1.0 [..]
-[RUNNING] [..]
-[RUNNING] [..]
-[RUNNING] `rustc --crate-name foo [..] \
---cfg[..]default[..]--cfg[..]f_a[..]--cfg[..]f_b[..]\
---cfg[..]f_c[..]--cfg[..]f_d[..] \
---cfg cfg_a --cfg cfg_b --cfg cfg_c --cfg cfg_d --cfg cfg_e`
-[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]",
+[COMPILING] foo v0.1.0 ([CWD])
+[RUNNING] `rustc --crate-name foo src/lib.rs [..]--crate-type staticlib --crate-type cdylib [..]-C lto [..]`
+[FINISHED] release [optimized] target(s) in [..]
+",
         ).run();
 }
 
 #[test]
-fn explicit_bins_without_paths() {
+fn cdylib_versioned_outputs() {
     let p = project()
         .file(
             "Cargo.toml",
             r#"
-            [package]
-            name = "foo"
+            cargo-features = ["versioned-libraries"]
+
+            [project]
+            name = "foo"
+            authors = []
+            version = "1.2.3"
+
+            [lib]
+            crate-type = ["cdylib"]
+            versioned = true
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    if !cfg!(unix) || cfg!(target_os = "macos") {
+        p.cargo("build").masquerade_as_nightly_cargo().run();
+        return;
+    }
+
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc [..]-C link-arg=-Wl,-soname,libfoo.so.1[..]")
+        .run();
+
+    let dir = p.root().join("target/debug");
+    assert!(dir.join("libfoo.so.1.2.3").is_file());
+    assert_eq!(
+        fs::read_link(dir.join("libfoo.so.1")).unwrap(),
+        Path::new("libfoo.so.1.2.3")
+    );
+    assert_eq!(
+        fs::read_link(dir.join("libfoo.so")).unwrap(),
+        Path::new("libfoo.so.1")
+    );
+    assert_eq!(
+        fs::read_link(dir.join("deps/libfoo.so.1")).unwrap(),
+        Path::new("libfoo.so")
+    );
+}
+
+#[test]
+fn versioned_requires_lib() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["versioned-libraries"]
+
+            [project]
+            name = "foo"
+            authors = []
+            version = "1.2.3"
+
+            [[bin]]
+            name = "foo"
+            path = "src/main.rs"
+            versioned = true
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  the `versioned` key is only supported for the library target
+",
+        ).run();
+}
+
+#[test]
+fn deterministic_cfg_flags() {
+    // This bug is non-deterministic
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            build = "build.rs"
+
+            [features]
+            default = ["f_a", "f_b", "f_c", "f_d"]
+            f_a = []
+            f_b = []
+            f_c = []
+            f_d = []
+        "#,
+        ).file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rustc-cfg=cfg_a");
+                    println!("cargo:rustc-cfg=cfg_b");
+                    println!("cargo:rustc-cfg=cfg_c");
+                    println!("cargo:rustc-cfg=cfg_d");
+                    println!("cargo:rustc-cfg=cfg_e");
+                }
+            "#,
+        ).file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -v")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 [..]
+[RUNNING] [..]
+[RUNNING] [..]
+[RUNNING] `rustc --crate-name foo [..] \
+--cfg[..]default[..]--cfg[..]f_a[..]--cfg[..]f_b[..]\
+--cfg[..]f_c[..]--cfg[..]f_d[..] \
+--cfg cfg_a --cfg cfg_b --cfg cfg_c --cfg cfg_d --cfg cfg_e`
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]",
+        ).run();
+}
+
+#[test]
+fn explicit_bins_without_paths() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
             version = "0.1.0"
             authors = []
 
@@ -4499,3 +4990,532 @@ Caused by:
         .with_status(101)
         .run();
 }
+
+#[cfg(unix)]
+#[test]
+fn symlinks_follow() {
+    let p = project()
+        .at("real")
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+    t!(std::os::unix::fs::symlink(p.root(), root().join("link")));
+    t!(fs::create_dir(root().join(".cargo")));
+    t!(fs::write(
+        root().join(".cargo/config"),
+        "[build]\nsymlinks = \"follow\"\n"
+    ));
+
+    p.cargo("build -Zsymlinks --manifest-path ../link/Cargo.toml")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([..]/real)
+[FINISHED] [..]
+",
+        ).run();
+    p.cargo("build -Zsymlinks")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    // Without a policy the path is kept as it was given.
+    p.cargo("build -v --manifest-path ../link/Cargo.toml")
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([..]/link)
+[FINISHED] [..]
+",
+        ).run();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_preserve() {
+    let p = project()
+        .at("real")
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+    let link = root().join("link");
+    t!(std::os::unix::fs::symlink(p.root(), &link));
+    t!(fs::create_dir(root().join(".cargo")));
+    t!(fs::write(
+        root().join(".cargo/config"),
+        "[build]\nsymlinks = \"preserve\"\n"
+    ));
+
+    p.cargo("build -Zsymlinks")
+        .masquerade_as_nightly_cargo()
+        .cwd(&link)
+        .env("PWD", &link)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([..]/link)
+[FINISHED] [..]
+",
+        ).run();
+    p.cargo("build -Zsymlinks --manifest-path ../link/Cargo.toml")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    t!(fs::write(
+        root().join(".cargo/config"),
+        "[build]\nsymlinks = \"resolve\"\n"
+    ));
+    p.cargo("build -Zsymlinks")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `build.symlinks` must be `follow` or `preserve`, found `resolve` in [..]config",
+        ).run();
+}
+
+#[test]
+fn warn_slow_units() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            default = ["fast"]
+            fast = []
+            "#,
+        ).file("src/lib.rs", "")
+        .file(".cargo/config", "[build]\nwarn-slow-units = \"0s\"\n")
+        .build();
+
+    p.cargo("build")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[WARNING] `foo` took [..]s, exceeding the build time budget of 0.00s (features: default, fast)
+",
+        ).run();
+
+    // Fresh units aren't reported.
+    p.cargo("build").with_stderr("[FINISHED] [..]").run();
+
+    p.change_file(".cargo/config", "[build]\nwarn-slow-units = \"1h\"\n");
+    p.cargo("build --release")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        ).run();
+}
+
+#[test]
+fn warn_slow_units_invalid() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file(".cargo/config", "[build]\nwarn-slow-units = \"soon\"\n")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] invalid configuration for key `build.warn-slow-units`, expected a duration \
+like `60s` or `2m`, found `soon` (found in [..]config)
+",
+        ).run();
+}
+
+fn pipelined_project() -> ProjectBuilder {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            "#,
+        ).file("src/lib.rs", "pub fn foo() { bar::bar() }")
+        .file("src/main.rs", "fn main() { foo::foo() }")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.0.1"))
+        .file("bar/src/lib.rs", "pub fn bar() { let x = 1; }")
+        .file(".cargo/config", "[build]\npipelining = true\n")
+}
+
+#[test]
+fn pipelining_works() {
+    let p = pipelined_project().build();
+
+    p.cargo("build -v")
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name bar bar/src/lib.rs [..]--error-format json \
+             --json=artifacts --crate-type lib --emit=dep-info,metadata,link [..]",
+        ).with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo src/lib.rs [..]\
+             --emit=dep-info,metadata,link [..]--extern bar=[..]/libbar-[..].rmeta`",
+        ).with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo src/main.rs [..]\
+             --extern bar=[..]/libbar-[..].rlib --extern foo=[..]/libfoo-[..].rlib`",
+        ).with_stderr_contains("[..]unused variable: `x`")
+        .with_stderr_does_not_contain("[..]\"artifact\"[..]")
+        .run();
+
+    p.cargo("build").with_stderr("[FINISHED] [..]").run();
+}
+
+#[test]
+fn pipelining_json_messages() {
+    let p = pipelined_project().build();
+
+    p.cargo("build --message-format=json")
+        .with_stdout_contains("[..]\"reason\":\"compiler-message\"[..]unused variable[..]")
+        .with_stdout_does_not_contain("[..]\"artifact\":[..]\"emit\"[..]")
+        .run();
+}
+
+#[test]
+fn pipelining_links_transitive_deps() {
+    // `bar` only waits for the metadata of `baz`, so the binary, which only
+    // depends on `bar` directly, has to wait for `baz` to be finished itself
+    // to link against it. Many functions make the codegen of `baz` take long
+    // enough for linking to fail without that.
+    let baz = (0..500)
+        .map(|i| format!("pub fn baz{}() -> usize {{ {} }}\n", i, i))
+        .collect::<String>();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            "#,
+        ).file("src/main.rs", "fn main() { assert_eq!(bar::bar(), 499); }")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = { path = "../baz" }
+            "#,
+        ).file("bar/src/lib.rs", "pub fn bar() -> usize { baz::baz499() }")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.0.1"))
+        .file("baz/src/lib.rs", &baz)
+        .file(".cargo/config", "[build]\npipelining = true\n")
+        .build();
+
+    p.cargo("build -v")
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name bar bar/src/lib.rs [..]--extern baz=[..]/libbaz-[..].rmeta`",
+        ).with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo src/main.rs [..]--extern bar=[..]/libbar-[..].rlib`",
+        ).run();
+    p.process(&p.bin("foo")).run();
+}
+
+#[test]
+fn log_dir_writes_unit_logs() {
+    let p = project()
+        .file(
+            ".cargo/config",
+            r#"
+            [build]
+            log-dir = "logs"
+        "#,
+        ).file("src/lib.rs", "fn unused() {}")
+        .file("build.rs", r#"fn main() { println!("hello from build.rs"); }"#)
+        .build();
+
+    p.cargo("build").run();
+
+    let logs = p.root().join("target/logs");
+    let index = fs::read_to_string(logs.join("index.json")).unwrap();
+    let index: serde_json::Value = serde_json::from_str(&index).unwrap();
+    let entries = index.as_array().unwrap();
+    assert_eq!(entries.len(), 3);
+    for entry in entries {
+        assert_eq!(entry["success"], true);
+        assert!(logs.join(entry["log"].as_str().unwrap()).is_file());
+    }
+    let lib = entries
+        .iter()
+        .find(|e| e["target"]["kind"][0] == "lib")
+        .unwrap();
+    let log = fs::read_to_string(logs.join(lib["log"].as_str().unwrap())).unwrap();
+    assert!(log.starts_with("Running `rustc --crate-name foo"));
+    assert!(log.contains("warning: "));
+    let script = entries
+        .iter()
+        .find(|e| e["mode"] == "run-custom-build")
+        .unwrap();
+    let log = fs::read_to_string(logs.join(script["log"].as_str().unwrap())).unwrap();
+    assert!(log.contains("hello from build.rs"));
+}
+
+#[test]
+fn repeat_failed_builds_the_failed_units() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["a", "b"]
+        "#,
+        ).file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "invalid rust code")
+        .build();
+
+    p.cargo("build --all -j1").with_status(101).run();
+
+    p.cargo("build --show-failed-command")
+        .with_stderr("[..]Failed b (b v0.1.0 ([CWD]/b))")
+        .with_stdout_contains("cd [..] && [..]rustc --crate-name b [..]")
+        .run();
+
+    p.change_file("b/src/lib.rs", "");
+    p.cargo("build --repeat-failed")
+        .with_stderr(
+            "\
+[COMPILING] b v0.1.0 ([CWD]/b)
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+
+    p.cargo("build --repeat-failed")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] no units failed in the previous build, there's nothing to repeat",
+        ).run();
+}
+
+#[test]
+fn show_failed_command_without_failed_build() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build --show-failed-command")
+        .with_status(101)
+        .with_stderr("[ERROR] no units failed in the previous build")
+        .run();
+}
+
+#[test]
+fn crate_type_flag_skips_other_crate_types() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [lib]
+            crate-type = ["rlib", "cdylib"]
+        "#,
+        ).file("src/lib.rs", "pub fn foo() {}")
+        .build();
+
+    p.cargo("build -v --crate-type rlib")
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo src/lib.rs --color never --crate-type rlib [..]",
+        )
+        .with_stderr_does_not_contain("[..]--crate-type cdylib[..]")
+        .run();
+    assert!(p.target_debug_dir().join("libfoo.rlib").is_file());
+    assert!(!p
+        .target_debug_dir()
+        .join(format!("{}foo{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX))
+        .exists());
+
+    p.cargo("build --crate-type staticlib")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the library of `foo` doesn't have the crate type `staticlib`, \
+             it has: rlib, cdylib",
+        ).run();
+}
+
+#[test]
+fn keep_artifacts_prunes_unused() {
+    let p = project()
+        .file(
+            ".cargo/config",
+            r#"
+            [build.keep-artifacts]
+            profiles = ["dev"]
+            max-age = "30d"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build").run();
+    let fingerprints = p.target_debug_dir().join(".fingerprint");
+    let last_use = fs::read_dir(&fingerprints)
+        .unwrap()
+        .map(|e| e.unwrap().path().join("last-use"))
+        .find(|path| path.to_str().unwrap().contains("foo-"))
+        .unwrap();
+    assert!(last_use.is_file());
+
+    // The artifacts of units other builds left behind: one they last used
+    // long ago, and one some other build still uses, though it was built
+    // long ago.
+    let deps = p.target_debug_dir().join("deps");
+    let old = filetime::FileTime::from_unix_time(0, 0);
+    for name in &["old-0123456789abcdef", "used-0123456789abcdee"] {
+        let fingerprint = fingerprints.join(name);
+        fs::create_dir_all(&fingerprint).unwrap();
+        File::create(fingerprint.join("last-use")).unwrap();
+        File::create(deps.join(format!("lib{}.rlib", name))).unwrap();
+        File::create(deps.join(format!("{}.d", name))).unwrap();
+        for path in &[
+            fingerprint.clone(),
+            deps.join(format!("lib{}.rlib", name)),
+            deps.join(format!("{}.d", name)),
+        ] {
+            filetime::set_file_times(path, old, old).unwrap();
+        }
+    }
+    filetime::set_file_times(fingerprints.join("old-0123456789abcdef/last-use"), old, old)
+        .unwrap();
+    // And of one a build without a policy left recently.
+    File::create(deps.join("libnew-fedcba9876543210.rlib")).unwrap();
+
+    p.change_file("src/lib.rs", "pub fn foo() {}");
+    p.cargo("build")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[PRUNED] the artifacts of 1 units not used recently from `[..]debug`, freeing [..]B
+",
+        ).run();
+    assert!(!fingerprints.join("old-0123456789abcdef").exists());
+    assert!(!deps.join("libold-0123456789abcdef.rlib").exists());
+    assert!(!deps.join("old-0123456789abcdef.d").exists());
+    assert!(fingerprints.join("used-0123456789abcdee").exists());
+    assert!(deps.join("libused-0123456789abcdee.rlib").exists());
+    assert!(deps.join("libnew-fedcba9876543210.rlib").exists());
+    assert!(p.target_debug_dir().join("libfoo.rlib").is_file());
+
+    // Release builds aren't in `profiles`.
+    p.cargo("build --release")
+        .with_stderr_does_not_contain("[PRUNED][..]")
+        .run();
+}
+
+#[test]
+fn keep_artifacts_bad_config() {
+    let p = project()
+        .file(
+            ".cargo/config",
+            r#"
+            [build.keep-artifacts]
+            max-size = "20 gigs"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] invalid configuration for key `build.keep-artifacts.max-size`, \
+expected a size like `500MB` or `20GB`, found `20 gigs`
+",
+        ).run();
+
+    p.change_file(".cargo/config", "[build.keep-artifacts]\nprofiles = [\"release\"]");
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr("[ERROR] `build.keep-artifacts` needs a `max-age` or a `max-size`")
+        .run();
+}
+
+#[test]
+fn dist_layout() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            license-file = "LICENSE-CUSTOM"
+
+            [lib]
+            crate-type = ["rlib", "cdylib"]
+        "#,
+        ).file("src/lib.rs", "pub fn foo() {}")
+        .file("src/main.rs", "fn main() {}")
+        .file("README.md", "foo")
+        .file("LICENSE-CUSTOM", "license")
+        .file("COPYING", "copying")
+        .build();
+
+    p.cargo("build --dist dist").run();
+
+    let dir = p
+        .root()
+        .join("dist")
+        .join(format!("foo-0.1.0-{}", rustc_host()));
+    assert!(dir
+        .join("bin")
+        .join(format!("foo{}", env::consts::EXE_SUFFIX))
+        .is_file());
+    assert!(dir
+        .join("lib")
+        .join(format!("{}foo{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX))
+        .is_file());
+    assert!(!dir.join("lib/libfoo.rlib").exists());
+    for doc in &["README.md", "LICENSE-CUSTOM", "COPYING"] {
+        assert!(dir.join("doc").join(doc).is_file());
+    }
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&p.read_file("dist/dist.json")).unwrap();
+    assert_eq!(manifest["version"], 1);
+    assert_eq!(manifest["profile"], "debug");
+    let packages = manifest["packages"].as_array().unwrap();
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[0]["triple"], rustc_host());
+    assert_eq!(
+        packages[0]["dir"],
+        format!("foo-0.1.0-{}", rustc_host()).as_str()
+    );
+    let files = packages[0]["files"].as_array().unwrap();
+    assert!(files
+        .iter()
+        .any(|f| f["kind"] == "bin" && f["target"] == "foo"));
+    assert!(files
+        .iter()
+        .any(|f| f["kind"] == "doc" && f["path"] == "doc/README.md"));
+    assert!(files
+        .iter()
+        .all(|f| !f["path"].as_str().unwrap().ends_with(".rlib")));
+
+    // Files which are gone from the build are gone from the layout too.
+    fs::remove_file(p.root().join("COPYING")).unwrap();
+    p.cargo("build --dist dist").run();
+    assert!(!dir.join("doc/COPYING").exists());
+}
@@ -111,6 +111,8 @@ fn custom_build_env_vars() {
                 assert_eq!(rustdoc, "rustdoc");
 
                 assert!(env::var("RUSTC_LINKER").is_err());
+                let _linker = env::var("TARGET_LINKER").unwrap();
+                let _ar = env::var("TARGET_AR").unwrap();
             }}
         "#,
         p.root()
@@ -148,6 +150,7 @@ fn custom_build_env_var_rustc_linker() {
 
             fn main() {
                 assert!(env::var("RUSTC_LINKER").unwrap().ends_with("/path/to/linker"));
+                assert!(env::var("TARGET_LINKER").unwrap().ends_with("/path/to/linker"));
             }
             "#,
         ).file("src/lib.rs", "")
@@ -3446,3 +3449,424 @@ fn optional_build_dep_and_required_normal_dep() {
 [RUNNING] `[..]foo[EXE]`",
         ).run();
 }
+
+#[test]
+fn probes_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [package.probes]
+            has_i128 = { type = "i128" }
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  feature `build-probes` is required
+
+consider adding `cargo-features = [\"build-probes\"]` to the manifest
+",
+        ).run();
+}
+
+#[test]
+fn probes_passed_to_build_script() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["build-probes"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [package.probes]
+            has_i128 = { type = "i128" }
+            has-nothing = { type = "ThisTypeDoesNotExist" }
+            has_atomic_usize = { path = "std::sync::atomic::AtomicUsize" }
+            has_wrapping_add = { expr = "1u8.wrapping_add(1)" }
+        "#,
+        ).file(
+            "src/lib.rs",
+            r#"
+            #[cfg(not(has_i128))]
+            compile_error!("i128 should have been found");
+        "#,
+        ).file(
+            "build.rs",
+            r#"
+            use std::env;
+
+            fn main() {
+                assert_eq!(env::var("CARGO_PROBE_HAS_I128").unwrap(), "1");
+                assert_eq!(env::var("CARGO_PROBE_HAS_NOTHING").unwrap(), "0");
+                assert_eq!(env::var("CARGO_PROBE_HAS_ATOMIC_USIZE").unwrap(), "1");
+                assert_eq!(env::var("CARGO_PROBE_HAS_WRAPPING_ADD").unwrap(), "1");
+                if env::var("CARGO_PROBE_HAS_I128").unwrap() == "1" {
+                    println!("cargo:rustc-cfg=has_i128");
+                }
+            }
+        "#,
+        ).build();
+
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]probe_[..]")
+        .run();
+
+    // The results are remembered in the rustc info cache, so probing again
+    // doesn't compile anything.
+    p.root().join("target/debug/build").rm_rf();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .env("RUST_LOG", "cargo::util::rustc=info")
+        .with_stderr_does_not_contain("[..]rustc info cache miss[..]")
+        .run();
+}
+
+#[test]
+fn probe_needs_one_kind() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["build-probes"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [package.probes]
+            bad = { type = "i128", expr = "1" }
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  probe `bad` must specify exactly one of `type`, `path` or `expr`
+",
+        ).run();
+}
+
+#[test]
+fn dependent_cfg() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.a]
+            path = "a"
+        "#,
+        ).file(
+            "src/lib.rs",
+            r#"
+            #[cfg(dep_native_foo_has_x)]
+            pub fn foo() {}
+            #[cfg(a_private)]
+            pub fn foo() { compile_error!("private cfg leaked") }
+
+            /// ```
+            /// foo::foo();
+            /// ```
+            #[cfg(dep_native_foo_has_x)]
+            pub fn doctested() {}
+        "#,
+        ).file(
+            "a/Cargo.toml",
+            r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+            links = "native-foo"
+            build = "build.rs"
+        "#,
+        ).file("a/src/lib.rs", "")
+        .file(
+            "a/build.rs",
+            r#"
+            fn main() {
+                println!("cargo:rustc-cfg=a_private");
+                println!("cargo:dependent-cfg=has_x");
+            }
+        "#,
+        ).build();
+
+    p.cargo("test -v -Z dependent-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo src/lib.rs [..]--cfg dep_native_foo_has_x[..]`",
+        ).with_stderr_contains(
+            "[RUNNING] `rustdoc --test [..]--cfg dep_native_foo_has_x[..]`",
+        ).run();
+}
+
+#[test]
+fn dependent_cfg_requires_links() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:dependent-cfg=has_x");
+            }
+        "#,
+        ).build();
+
+    p.cargo("build -Z dependent-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+warning: `cargo:dependent-cfg` is ignored because the package has no `links` key
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+}
+
+#[test]
+fn dependent_cfg_requires_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.a]
+            path = "a"
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            "a/Cargo.toml",
+            r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+            links = "native-foo"
+            build = "build.rs"
+        "#,
+        ).file("a/src/lib.rs", "")
+        .file(
+            "a/build.rs",
+            r#"
+            fn main() {
+                println!("cargo:dependent-cfg=has_x");
+            }
+        "#,
+        ).build();
+
+    p.cargo("build -v")
+        .with_stderr_contains(
+            "warning: `cargo:dependent-cfg` is ignored because it requires `-Z dependent-cfg`",
+        ).with_stderr_contains("[RUNNING] `rustc --crate-name foo src/lib.rs [..]`")
+        .with_stderr_does_not_contain("[..]--cfg dep_native_foo_has_x[..]")
+        .run();
+}
+
+#[test]
+fn dependent_cfg_must_be_identifier() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            links = "native-foo"
+            build = "build.rs"
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:dependent-cfg=x=\"y\"");
+            }
+        "#,
+        ).build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "error: Variable dependent-cfg must be a plain identifier in \
+             build script of `foo v0.5.0 ([CWD])`: `x=\"y\"`",
+        ).run();
+}
+
+#[test]
+fn target_info_for_build_script() {
+    let target = rustc_host();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            use std::env;
+            use std::path::Path;
+
+            fn main() {
+                let libdir = env::var("TARGET_SYSROOT_LIBDIR").unwrap();
+                assert!(Path::new(&libdir).is_dir());
+                assert!(libdir.contains(&env::var("TARGET").unwrap()));
+                assert_eq!(env::var("TARGET_RUNNER").unwrap(), "nonexistent-runner -r");
+            }
+        "#,
+        ).file(
+            ".cargo/config",
+            &format!(
+                r#"
+            [target.{}]
+            runner = "nonexistent-runner -r"
+        "#,
+                target
+            ),
+        ).build();
+
+    p.cargo("build").run();
+}
+
+#[test]
+fn env_allowlist_restricts_build_script_env() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.5.0"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            use std::env;
+
+            fn main() {
+                assert!(env::var("SECRET").is_err());
+                assert!(env::var("LEAKED").is_err());
+                assert!(env::var("ALLOWED").is_ok());
+                assert!(env::var("OUT_DIR").is_ok());
+            }
+        "#,
+        ).file(
+            ".cargo/config",
+            r#"
+            [build]
+            env-allowlist = ["ALLOWED", "LEAKED", "PATH"]
+            env-scrub = ["LEAKED"]
+        "#,
+        ).build();
+
+    p.cargo("build")
+        .env("SECRET", "1")
+        .env("LEAKED", "1")
+        .env("ALLOWED", "1")
+        .run();
+    p.cargo("build")
+        .env("SECRET", "2")
+        .env("ALLOWED", "1")
+        .with_stderr("[FINISHED] [..]")
+        .run();
+    p.cargo("build -v")
+        .env("ALLOWED", "2")
+        .with_stderr_contains("[COMPILING] foo v0.5.0 ([CWD])")
+        .run();
+}
+
+#[test]
+fn audit_build_scripts_reruns_build_script() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.5.0"))
+        .file("src/lib.rs", "")
+        .file("build.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build").run();
+    // The script runs again to write its audit log.
+    p.cargo("build -v")
+        .env("CARGO_BUILD_AUDIT_BUILD_SCRIPTS", "true")
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .run();
+    p.cargo("build -v")
+        .env("CARGO_BUILD_AUDIT_BUILD_SCRIPTS", "true")
+        .with_stderr_does_not_contain("[RUNNING] `[..]/build-script-build`")
+        .run();
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .run();
+}
+
+#[test]
+fn build_script_cache_dir() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.5.0"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            use std::env;
+            use std::fs;
+            use std::path::Path;
+
+            fn main() {
+                let cache = env::var("CARGO_CACHE_DIR").unwrap();
+                assert!(!cache.contains(&env::var("OUT_DIR").unwrap()));
+                let runs = Path::new(&cache).join("runs");
+                let n = fs::read_to_string(&runs)
+                    .map(|s| s.parse::<u32>().unwrap())
+                    .unwrap_or(0) + 1;
+                fs::write(&runs, n.to_string()).unwrap();
+                println!("cargo:warning=run {}", n);
+            }
+        "#,
+        ).build();
+
+    p.cargo("build")
+        .with_stderr_contains("warning: run 1")
+        .run();
+    p.cargo("clean -p foo").run();
+    p.cargo("build")
+        .with_stderr_contains("warning: run 2")
+        .run();
+    p.cargo("build --release")
+        .with_stderr_contains("warning: run 3")
+        .run();
+    p.cargo("clean").run();
+    p.cargo("build")
+        .with_stderr_contains("warning: run 1")
+        .run();
+}
@@ -0,0 +1,52 @@
+use crate::support::{basic_lib_manifest, is_nightly, paths, project, rustc_host};
+
+#[test]
+fn build_std_requires_target() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build -Z build-std=core")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] -Z build-std requires --target")
+        .run();
+}
+
+#[test]
+fn build_std_from_mock_rust_src() {
+    if !is_nightly() {
+        // -Z force-unstable-if-unmarked is only accepted by a nightly rustc
+        return;
+    }
+
+    // A `rust-src` tree with nothing but a `core` standard library crate,
+    // which the crate only gets if it's injected in place of the sysroot's.
+    project()
+        .at("rust-src")
+        .file("src/libcore/Cargo.toml", &basic_lib_manifest("core"))
+        .file("src/libcore/src/lib.rs", "pub fn mock_core() {}")
+        .build();
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+            #![feature(rustc_private)]
+            extern crate core;
+
+            pub fn foo() { core::mock_core() }
+        "#,
+        ).build();
+
+    p.cargo("build -v -Z build-std=core --target")
+        .arg(rustc_host())
+        .env("__CARGO_TESTS_ONLY_SRC_ROOT", paths::root().join("rust-src"))
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name core [..]rust-src/src/libcore/src/lib.rs [..]\
+             -Z force-unstable-if-unmarked [..]",
+        ).with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo src/lib.rs [..]-Z unstable-options [..]\
+             --extern noprelude:core=[..]/libcore-[..].rlib[..]`",
+        ).with_stderr_does_not_contain(
+            "[RUNNING] `rustc --crate-name core [..]--extern noprelude:[..]",
+        ).run();
+}
@@ -315,3 +315,34 @@ fn z_flags_help() {
             "    -Z unstable-options -- Allow the usage of unstable options such as --registry",
         ).run();
 }
+
+#[test]
+fn self_profile() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("--self-profile metadata --no-deps --format-version 1")
+        .with_stderr("[PROFILED] cargo, the trace is in `[CWD]/cargo-self-profile-[..].json`")
+        .run();
+
+    let traces = fs::read_dir(p.root())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            name.starts_with("cargo-self-profile-")
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(traces.len(), 1);
+    let mut trace = String::new();
+    File::open(&traces[0])
+        .unwrap()
+        .read_to_string(&mut trace)
+        .unwrap();
+    let trace: serde_json::Value = serde_json::from_str(&trace).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    for name in &["loading the configuration", "cargo metadata"] {
+        let event = events.iter().find(|e| e["name"] == *name).unwrap();
+        assert_eq!(event["ph"], "X");
+        assert!(event["ts"].is_u64() && event["dur"].is_u64());
+    }
+}
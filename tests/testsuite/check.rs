@@ -4,7 +4,7 @@ use glob::glob;
 use crate::support::install::exe;
 use crate::support::paths::CargoPathExt;
 use crate::support::registry::Package;
-use crate::support::{basic_manifest, project};
+use crate::support::{basic_manifest, cargo_process, project};
 
 #[test]
 fn check_success() {
@@ -716,3 +716,106 @@ fn error_from_deep_recursion() -> Result<(), fmt::Error> {
 
     Ok(())
 }
+
+#[test]
+fn check_remote_version() {
+    Package::new("foo", "0.1.0")
+        .file("src/lib.rs", "pub fn foo() {}")
+        .publish();
+    Package::new("foo", "0.2.0")
+        .file("src/lib.rs", "pub fn foo() -> u32 { \"\" }")
+        .publish();
+
+    cargo_process("check --remote -p foo@0.1.0")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] foo v0.1.0 (registry [..])
+[CHECKING] foo v0.1.0
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+
+    cargo_process("check --remote -p foo")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to check `foo v0.2.0`, intermediate artifacts can be found at `[..]`")
+        .run();
+}
+
+#[test]
+fn check_remote_requires_one_exact_version() {
+    cargo_process("check --remote")
+        .with_status(101)
+        .with_stderr("[ERROR] `--remote` requires exactly one `--package`, like `--package foo@1.2.3`")
+        .run();
+
+    cargo_process("check --remote -p foo@1.2")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid version `1.2` in `foo@1.2`, `--remote` requires an exact \
+             version, like `foo@1.2.3`",
+        ).run();
+}
+
+#[test]
+fn check_dependency_with_workspace_features() {
+    Package::new("dep", "0.1.0")
+        .feature("a", &[])
+        .feature("b", &[])
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(not(all(feature = "a", feature = "b")))]
+            compile_error!("features of the workspace missing");
+        "#,
+        ).publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["foo", "bar"]
+        "#,
+        ).file(
+            "foo/Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            dep = { version = "0.1", features = ["a"] }
+        "#,
+        ).file("foo/src/lib.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+
+            [dependencies]
+            dep = { version = "0.1", features = ["b"] }
+        "#,
+        ).file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("check --dependency dep")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] dep v0.1.0 (registry [..])
+[CHECKING] dep v0.1.0
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+
+    p.cargo("check --dependency nope")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `nope` isn't a dependency of the packages being built, \
+             see `cargo graph` for their dependencies",
+        ).run();
+}
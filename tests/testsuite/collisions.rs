@@ -103,3 +103,106 @@ This may become a hard error in the future, see https://github.com/rust-lang/car
 ")
         .run();
 }
+
+#[test]
+fn collision_case_insensitive() {
+    // Binaries are uplifted without a metadata hash, so names which only
+    // differ in case end up as the same file on macOS and Windows.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["a", "b"]
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+            [package]
+            name = "a"
+            version = "1.0.0"
+
+            [[bin]]
+            name = "Foo"
+            path = "src/main.rs"
+            "#,
+        )
+        .file("a/src/main.rs", "fn main() {}")
+        .file(
+            "b/Cargo.toml",
+            r#"
+            [package]
+            name = "b"
+            version = "1.0.0"
+
+            [[bin]]
+            name = "foo"
+            path = "src/main.rs"
+            "#,
+        )
+        .file("b/src/main.rs", "fn main() {}")
+        .build();
+
+    // The build fails on the filesystems which would overwrite one of the
+    // files, the others get a warning.
+    if p.root().join("A").exists() {
+        p.cargo("build")
+            .with_status(101)
+            .with_stderr("\
+[ERROR] output filename collision on a case-insensitive filesystem.
+The bin target `foo` in package `b v1.0.0 ([..]/foo/b)` has the same output filename as the bin target `Foo` in package `a v1.0.0 ([..]/foo/a)`, ignoring case.
+Colliding filenames are: [..]/foo/target/debug/Foo[EXE] and [..]/foo/target/debug/foo[EXE]
+Consider changing their names to differ in more than case.
+")
+            .run();
+    } else {
+        p.cargo("build")
+            .with_stderr_contains("\
+[WARNING] output filenames would collide on a case-insensitive filesystem.
+The bin target `foo` in package `b v1.0.0 ([..]/foo/b)` has the same output filename as the bin target `Foo` in package `a v1.0.0 ([..]/foo/a)`, ignoring case.
+Colliding filenames are: [..]/foo/target/debug/Foo[EXE] and [..]/foo/target/debug/foo[EXE]
+Consider changing their names to differ in more than case.
+")
+            .run();
+    }
+}
+
+#[test]
+fn no_case_collision_of_dependencies() {
+    // The dependency isn't uplifted, and its file in `deps` has a metadata
+    // hash, so nothing collides even on a case-insensitive filesystem.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "a"
+            version = "1.0.0"
+
+            [lib]
+            name = "Foo"
+
+            [dependencies]
+            b = { path = "b" }
+            "#,
+        )
+        .file("src/lib.rs", "#![allow(non_snake_case)] extern crate foo;")
+        .file(
+            "b/Cargo.toml",
+            r#"
+            [package]
+            name = "b"
+            version = "1.0.0"
+
+            [lib]
+            name = "foo"
+            "#,
+        )
+        .file("b/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_stderr_does_not_contain("[..]collision[..]")
+        .run();
+}
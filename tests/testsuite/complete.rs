@@ -0,0 +1,59 @@
+use crate::support::cargo_process;
+use crate::support::registry::{Dependency, Package};
+
+fn publish() {
+    Package::new("f", "0.1.0").publish();
+    Package::new("fa", "1.0.0").publish();
+    Package::new("foo", "0.1.0").publish();
+    Package::new("foo", "0.2.0")
+        .add_dep(Dependency::new("bar", "1.0").optional(true))
+        .feature("default", &["std"])
+        .feature("std", &[])
+        .publish();
+    Package::new("foo-bar", "1.0.0").publish();
+    Package::new("other", "1.0.0").publish();
+}
+
+#[test]
+fn complete_from_local_index() {
+    publish();
+
+    cargo_process("complete-versions foo")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] failed to read the local copy of the index of registry `[..]`, \
+             which is downloaded by commands like `cargo fetch`",
+        )
+        .run();
+
+    // Fetches the index.
+    cargo_process("info foo").run();
+
+    cargo_process("complete-crate-name fo")
+        .with_stdout("foo\nfoo-bar\n")
+        .with_stderr("")
+        .run();
+    cargo_process("complete-crate-name F")
+        .with_stdout("f\nfa\nfoo\nfoo-bar\n")
+        .run();
+    cargo_process("complete-crate-name foo-")
+        .with_stdout("foo-bar\n")
+        .run();
+    cargo_process("complete-crate-name x").with_stdout("").run();
+
+    cargo_process("complete-versions foo")
+        .with_stdout("0.2.0\n0.1.0\n")
+        .with_stderr("")
+        .run();
+
+    cargo_process("complete-features foo")
+        .with_stdout("bar\ndefault\nstd\n")
+        .run();
+    cargo_process("complete-features foo@0.1")
+        .with_stdout("")
+        .run();
+    cargo_process("complete-features nope")
+        .with_status(101)
+        .with_stderr_contains("[..]could not find `nope` in registry `[..]`")
+        .run();
+}
@@ -515,3 +515,44 @@ fn no_deadlock_with_git_dependencies() {
         execs().run_output(&result);
     }
 }
+
+#[test]
+fn debug_release_share_build_script_cache() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            use std::env;
+            use std::fs;
+            use std::path::Path;
+            use std::thread;
+            use std::time::Duration;
+
+            fn main() {
+                let cache = env::var("CARGO_CACHE_DIR").unwrap();
+                let busy = Path::new(&cache).join("busy");
+                assert!(!busy.exists(), "the cache is used by another run");
+                fs::write(&busy, "").unwrap();
+                thread::sleep(Duration::from_millis(500));
+                fs::remove_file(&busy).unwrap();
+            }
+        "#,
+        ).build();
+
+    let mut a = p.cargo("build").build_command();
+    let mut b = p.cargo("build --release").build_command();
+
+    a.stdout(Stdio::piped()).stderr(Stdio::piped());
+    b.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let a = a.spawn().unwrap();
+    let b = b.spawn().unwrap();
+    let a = thread::spawn(move || a.wait_with_output().unwrap());
+    let b = b.wait_with_output().unwrap();
+    let a = a.join().unwrap();
+
+    execs().run_output(&a);
+    execs().run_output(&b);
+}
@@ -58,6 +58,9 @@ fn new_config(env: &[(&str, &str)]) -> Config {
             &None,
             false,
             false,
+            false,
+            false,
+            false,
             &None,
             &["advanced-env".into()],
         ).unwrap();
@@ -185,6 +188,7 @@ codegen-units = 9
         incremental: None,
         overrides: None,
         build_override: None,
+        inherits: None,
     };
     overrides.insert(key, o_profile);
     let key = toml::ProfilePackageSpec::Spec(::cargo::core::PackageIdSpec::parse("env").unwrap());
@@ -200,6 +204,7 @@ codegen-units = 9
         incremental: None,
         overrides: None,
         build_override: None,
+        inherits: None,
     };
     overrides.insert(key, o_profile);
 
@@ -227,8 +232,10 @@ codegen-units = 9
                 overflow_checks: None,
                 incremental: None,
                 overrides: None,
-                build_override: None
-            }))
+                build_override: None,
+                inherits: None,
+            })),
+            inherits: None,
         }
     );
 }
@@ -688,3 +695,89 @@ i64max = 9223372036854775807
          invalid value: integer `123456789`, expected i8",
     );
 }
+
+#[test]
+fn config_host_cfg() {
+    write_config(
+        "\
+[build]
+jobs = 1
+rustflags = [\"--cfg\", \"everywhere\"]
+
+[host.'cfg(unix)'.build]
+jobs = 2
+rustflags = [\"--cfg\", \"unix\"]
+
+[host.'cfg(windows)'.build]
+jobs = 3
+rustflags = [\"--cfg\", \"windows\"]
+
+[host.'cfg(not(any(unix, windows)))'.build]
+jobs = 4
+
+[host.'cfg(any())'.target.foo]
+linker = \"never\"
+",
+    );
+
+    let config = new_config(&[]);
+    let (jobs, flag) = if cfg!(unix) {
+        (2, "unix")
+    } else if cfg!(windows) {
+        (3, "windows")
+    } else {
+        (4, "everywhere")
+    };
+    assert_eq!(config.get::<u32>("build.jobs").unwrap(), jobs);
+    let rustflags = config.get::<Vec<String>>("build.rustflags").unwrap();
+    assert!(rustflags.contains(&flag.to_string()));
+    assert!(rustflags.contains(&"everywhere".to_string()));
+    assert_eq!(config.get::<Option<String>>("target.foo.linker").unwrap(), None);
+    assert!(config
+        .get_table("host")
+        .unwrap()
+        .map_or(true, |t| t.val.is_empty()));
+}
+
+#[test]
+fn config_host_cfg_keeps_file_precedence() {
+    write_config(
+        "\
+[build]
+jobs = 1
+",
+    );
+    fs::write(
+        paths::home().join("config"),
+        "\
+[host.'cfg(all())'.build]
+jobs = 2
+target-dir = \"home-target\"
+",
+    )
+    .unwrap();
+
+    let config = new_config(&[]);
+    assert_eq!(config.get::<u32>("build.jobs").unwrap(), 1);
+    assert_eq!(
+        config.get::<String>("build.target-dir").unwrap(),
+        "home-target"
+    );
+}
+
+#[test]
+fn config_host_cfg_errors() {
+    write_config(
+        "\
+[host.'cfg(unix']
+jobs = 1
+",
+    );
+
+    let config = new_config(&[]);
+    assert_error(
+        config.values().unwrap_err(),
+        "could not load Cargo configuration\n\
+         invalid `cfg` expression in `host.'cfg(unix'` in [..].cargo/config",
+    );
+}
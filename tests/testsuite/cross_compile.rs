@@ -1,3 +1,5 @@
+use std::fs;
+
 use crate::support::{basic_bin_manifest, basic_manifest, cross_compile, project};
 use crate::support::{is_nightly, rustc_host};
 
@@ -1161,3 +1163,258 @@ fn cross_test_dylib() {
         )).with_stdout_contains_n("test foo ... ok", 2)
         .run();
 }
+
+#[test]
+fn multiple_targets() {
+    if cross_compile::disabled() {
+        return;
+    }
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            build = "build.rs"
+        "#,
+        ).file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:rustc-env=TARGET={}", std::env::var("TARGET").unwrap());
+            }
+        "#,
+        ).file(
+            "src/main.rs",
+            r#"
+            fn main() {
+                assert_eq!(std::env::consts::ARCH, env!("TARGET").split('-').next().unwrap());
+            }
+        "#,
+        ).build();
+
+    let host = rustc_host();
+    let target = cross_compile::alternate();
+    p.cargo("build -v -Z multitarget --target")
+        .arg(&host)
+        .arg("--target")
+        .arg(&target)
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(&format!(
+            "[RUNNING] `rustc --crate-name foo [..]--target {} [..]",
+            host
+        )).with_stderr_contains(&format!(
+            "[RUNNING] `rustc --crate-name foo [..]--target {} [..]",
+            target
+        )).run();
+
+    // The build script is only compiled once, for the host.
+    let scripts = fs::read_dir(p.root().join("target/debug/build")).unwrap();
+    assert_eq!(scripts.count(), 1);
+    assert!(p.target_bin(&host, "foo").is_file());
+    assert!(p.target_bin(&target, "foo").is_file());
+    p.process(&p.target_bin(&host, "foo")).run();
+    p.process(&p.target_bin(&target, "foo")).run();
+}
+
+#[test]
+fn multiple_targets_require_flag() {
+    let p = project().file("src/main.rs", "fn main() {}").build();
+
+    p.cargo("check --target a --target b")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] multiple `--target` flags are unstable, \
+             pass `-Z multitarget` to enable them",
+        ).run();
+
+    p.cargo("test --target a --target b")
+        .with_status(1)
+        .with_stderr_contains(
+            "error: The argument '--target <TRIPLE>' was provided more than once, \
+             but cannot be used multiple times[..]",
+        ).run();
+}
+
+#[test]
+fn lipo_requires_apple_targets() {
+    let p = project().file("src/main.rs", "fn main() {}").build();
+
+    p.cargo("build -Z multitarget --lipo --target x86_64-apple-darwin")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `--lipo` requires at least two Apple targets to combine, like \
+             `--target x86_64-apple-darwin --target aarch64-apple-darwin`",
+        ).run();
+
+    p.cargo(
+        "build -Z multitarget --lipo --target x86_64-apple-darwin \
+         --target x86_64-unknown-linux-gnu",
+    ).masquerade_as_nightly_cargo()
+    .with_status(101)
+    .with_stderr(
+        "[ERROR] `--lipo` can only combine Apple targets, \
+         which `x86_64-unknown-linux-gnu` isn't",
+    ).run();
+}
+
+#[test]
+fn target_group() {
+    if cross_compile::disabled() {
+        return;
+    }
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                [target-groups]
+                native = ["{}"]
+                all = ["@native", "{}"]
+            "#,
+                rustc_host(),
+                cross_compile::alternate()
+            ),
+        ).build();
+
+    p.cargo("build -v --target @native")
+        .with_stderr_contains(&format!(
+            "[RUNNING] `rustc --crate-name foo [..]--target {} [..]",
+            rustc_host()
+        )).run();
+    assert!(p.target_bin(&rustc_host(), "foo").is_file());
+
+    p.cargo("build -Z multitarget --target @all")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(p.target_bin(&cross_compile::alternate(), "foo").is_file());
+}
+
+#[test]
+fn target_group_errors() {
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            ".cargo/config",
+            r#"
+            [target-groups]
+            mobile = ["aarch64-apple-ios", "aarch64-linux-android"]
+            empty = []
+            a = ["@b"]
+            b = ["@a"]
+        "#,
+        ).build();
+
+    p.cargo("check --target @mobile")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `@mobile` builds for several targets, which is unstable, \
+             pass `-Z multitarget` to enable it",
+        ).run();
+
+    p.cargo("check --target @desktop")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] no target group named `@desktop`, the groups are: \
+             `@a`, `@b`, `@empty`, `@mobile`",
+        ).run();
+
+    p.cargo("check --target @empty")
+        .with_status(101)
+        .with_stderr("[ERROR] target group `@empty` is empty")
+        .run();
+
+    p.cargo("check --target @a")
+        .with_status(101)
+        .with_stderr("[ERROR] target group `@a` includes itself: @a -> @b -> @a")
+        .run();
+}
+
+#[test]
+fn missing_target_std() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("sysroot/lib/rustlib/.keep", "")
+        .build();
+    let target = rustc_host();
+    let rustflags = format!("--sysroot {}", p.root().join("sysroot").display());
+
+    p.cargo("build -v --target")
+        .arg(&target)
+        .env("RUSTFLAGS", &rustflags)
+        .with_status(101)
+        .with_stderr_contains("[ERROR] Could not compile `foo`.")
+        .with_stderr_contains(&format!(
+            "  the standard library for the `{}` target isn't installed in the \
+             sysroot `[..]sysroot`, install it to `[..]` \
+             or select a toolchain which ships it with the `build.rustc` configuration",
+            target
+        )).run();
+
+    // A toolchain installed with rustup.
+    p.change_file("sysroot/lib/rustlib/multirust-channel-manifest.toml", "");
+    p.cargo("build --target")
+        .arg(&target)
+        .env("RUSTFLAGS", &rustflags)
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "  the `{}` target isn't installed, install it with \
+             `rustup target add {0}`",
+            target
+        )).run();
+}
+
+#[test]
+fn multiple_targets_runner_of_host() {
+    if cross_compile::disabled() {
+        return;
+    }
+
+    let host = rustc_host();
+    let target = cross_compile::alternate();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            build = "build.rs"
+        "#,
+        ).file(
+            "build.rs",
+            r#"
+            fn main() {
+                assert_eq!(std::env::var("TARGET_RUNNER").unwrap(), "host-runner -r");
+            }
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+            [target.{}]
+            runner = "host-runner -r"
+
+            [target.{}]
+            runner = "target-runner"
+        "#,
+                host, target
+            ),
+        ).build();
+
+    // The script runs on the host, so it gets the runner of the host, even
+    // though the host isn't the first of the targets.
+    p.cargo("build -Z multitarget --target")
+        .arg(&target)
+        .arg("--target")
+        .arg(&host)
+        .masquerade_as_nightly_cargo()
+        .run();
+}
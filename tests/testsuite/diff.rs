@@ -0,0 +1,106 @@
+use std::fs;
+
+use crate::support::paths;
+use crate::support::registry::Package;
+use crate::support::cargo_process;
+
+fn publish() {
+    Package::new("foo", "0.1.0")
+        .file("src/lib.rs", "pub fn foo() {}\n")
+        .file("src/old.rs", "")
+        .publish();
+    Package::new("foo", "0.2.0")
+        .file("src/lib.rs", "pub fn foo() {}\n\npub fn bar() {}\n")
+        .publish();
+}
+
+#[test]
+fn simple() {
+    publish();
+
+    cargo_process("diff foo 0.1.0 0.2.0")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] foo v0.[..] (registry [..])
+[DOWNLOADED] foo v0.[..] (registry [..])
+[COMPARING] foo v0.1.0 to v0.2.0
+",
+        )
+        .with_stdout_contains(
+            "\
+--- a/foo-0.1.0/src/lib.rs
++++ b/foo-0.2.0/src/lib.rs
+@@ -1,1 +1,3 @@
+ pub fn foo() {}
++
++pub fn bar() {}
+",
+        ).with_stdout_contains(
+            "\
+--- a/foo-0.1.0/src/old.rs
++++ /dev/null
+",
+        ).run();
+}
+
+#[test]
+fn output_to_file() {
+    publish();
+
+    cargo_process("diff foo 0.1.0 0.2.0 --context 0 --output foo.diff")
+        .with_stdout("")
+        .run();
+    let diff = fs::read_to_string(paths::root().join("foo.diff")).unwrap();
+    assert!(diff.contains("@@ -1,0 +2,2 @@\n+\n+pub fn bar() {}\n"));
+}
+
+#[test]
+fn yanked_version_with_excluded_files() {
+    publish();
+    Package::new("foo", "0.2.1")
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.2.1"
+            authors = []
+            exclude = ["src/generated.rs"]
+        "#,
+        ).file("src/lib.rs", "pub fn foo() {}\n\npub fn bar() {}\n")
+        .file("src/generated.rs", "pub fn hidden() {}\n")
+        .yanked(true)
+        .publish();
+
+    cargo_process("diff foo 0.2.0 0.2.1")
+        .with_stdout_contains(
+            "\
+--- /dev/null
++++ b/foo-0.2.1/src/generated.rs
+@@ -0,0 +1,1 @@
++pub fn hidden() {}
+",
+        ).run();
+}
+
+#[test]
+fn missing_version() {
+    publish();
+
+    cargo_process("diff foo 0.1.0 0.3.0")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] could not find `foo` in registry `[..]` with version `0.3.0`",
+        ).run();
+}
+
+#[test]
+fn requires_exact_versions() {
+    cargo_process("diff foo 0.1 0.2.0")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid version `0.1`, `cargo diff` compares exact versions, like `1.2.3`",
+        ).run();
+}
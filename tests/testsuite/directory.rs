@@ -1,5 +1,9 @@
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::Config;
 use serde_json;
 use std::collections::HashMap;
+use std::env;
 use std::fs::{self, File};
 use std::io::prelude::*;
 use std::str;
@@ -439,11 +443,171 @@ actual:   [..]
 
 directory sources are not intended to be edited, if modifications are \
 required then it is recommended that [replace] is used with a forked copy of \
-the source
+the source, or pass `-Z allow-dirty-vendor` to build with the modified \
+sources anyway
 ",
         ).run();
 }
 
+#[test]
+fn bad_file_checksums_lists_all_files() {
+    setup();
+
+    VendorPackage::new("bar")
+        .file("Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("src/lib.rs", "mod a;")
+        .file("src/a.rs", "")
+        .file("src/b.rs", "")
+        .build();
+
+    t!(fs::write(paths::root().join("index/bar/src/a.rs"), "pub fn a() {}"));
+    t!(fs::remove_file(paths::root().join("index/bar/src/b.rs")));
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+error: the listed checksum of `[..]a.rs` has changed:
+expected: [..]
+actual:   [..]
+the listed file `[..]b.rs` has been removed
+
+directory sources are not intended to be edited, if modifications are \
+required then it is recommended that [replace] is used with a forked copy of \
+the source, or pass `-Z allow-dirty-vendor` to build with the modified \
+sources anyway
+",
+        ).run();
+
+    p.cargo("build -Zallow-dirty-vendor")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] building `bar v0.1.0` with modified sources
+the listed checksum of `[..]a.rs` has changed:
+expected: [..]
+actual:   [..]
+the listed file `[..]b.rs` has been removed
+[COMPILING] bar v0.1.0
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        ).run();
+}
+
+#[test]
+fn verify_vendored_sources() {
+    setup();
+
+    VendorPackage::new("bar")
+        .file("Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    let config = Config::default().unwrap();
+    let ws = Workspace::new(&p.root().join("Cargo.toml"), &config).unwrap();
+    let vendor = paths::root().join("index");
+    ops::verify_vendored_sources(&ws, &vendor).unwrap();
+
+    t!(fs::write(vendor.join("bar/src/lib.rs"), "pub fn bar() {}"));
+    let err = ops::verify_vendored_sources(&ws, &vendor)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("the listed checksum of `") && err.contains("lib.rs` has changed"),
+        "{}",
+        err
+    );
+
+    t!(fs::remove_dir_all(vendor.join("bar")));
+    let err = ops::verify_vendored_sources(&ws, &vendor)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("`bar v0.1.0` is in the lock file but hasn't been vendored"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn vendor_verify() {
+    setup();
+
+    VendorPackage::new("bar")
+        .file("Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("vendor --verify ../index")
+        .with_stderr("[VERIFIED] vendored sources in `[CWD]/../index`")
+        .run();
+    p.cargo("vendor ../index --verify")
+        .with_stderr("[VERIFIED] vendored sources in `[CWD]/../index`")
+        .run();
+
+    t!(fs::write(
+        paths::root().join("index/bar/src/lib.rs"),
+        "pub fn bar() {}"
+    ));
+    p.cargo("vendor --verify ../index")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] vendored sources in `[CWD]/../index` don't match `[CWD]/Cargo.lock`:",
+        )
+        .with_stderr_contains("[..]the listed checksum of `[..]lib.rs` has changed[..]")
+        .run();
+}
+
 #[test]
 fn only_dot_files_ok() {
     setup();
@@ -725,3 +889,36 @@ perhaps a crate was updated and forgotten to be re-vendored?
         .with_status(101)
         .run();
 }
+
+#[test]
+fn vendor_forwards_to_plugin() {
+    let plugin = project()
+        .at("cargo-vendor")
+        .file("Cargo.toml", &basic_manifest("cargo-vendor", "0.0.1"))
+        .file(
+            "src/main.rs",
+            r#"
+            fn main() {
+                let args: Vec<_> = ::std::env::args().skip(1).collect();
+                println!("{:?}", args);
+            }
+        "#,
+        ).build();
+    plugin.cargo("build").run();
+    let mut path = env::split_paths(&env::var_os("PATH").unwrap_or_default()).collect::<Vec<_>>();
+    path.push(plugin.target_debug_dir());
+    let path = env::join_paths(path.iter()).unwrap();
+
+    let p = project().file("src/lib.rs", "").build();
+    // Cargo's own flags are parsed by cargo when they come first.
+    p.cargo("vendor -v --frozen --color never dir --versioned-dirs")
+        .env("PATH", &path)
+        .with_stdout(
+            r#"["vendor", "-v", "--color", "never", "--frozen", "dir", "--versioned-dirs"]"#,
+        ).run();
+    p.cargo("vendor --help")
+        .env("PATH", &path)
+        .with_stdout_contains("[..]--verify[..]")
+        .with_stdout_contains(r#"["vendor", "--help"]"#)
+        .run();
+}
@@ -1303,6 +1303,26 @@ fn doc_message_format() {
         .with_status(101)
         .with_json(
             r#"
+            {
+                "reason": "target-info",
+                "kind": "host",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
+            {
+                "reason": "target-info",
+                "kind": "target",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
             {
                 "message": {
                     "children": "{...}",
@@ -1336,3 +1356,82 @@ error: Could not document `foo`.
 ",
         ).run();
 }
+
+#[test]
+fn doc_fresh_and_removes_stale_items() {
+    let p = project()
+        .file("src/lib.rs", "pub fn a() {} pub mod m { pub fn b() {} }")
+        .build();
+
+    p.cargo("doc")
+        .with_stderr(
+            "\
+[DOCUMENTING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+    assert!(p.root().join("target/doc/foo/m/index.html").is_file());
+
+    p.cargo("doc")
+        .with_stderr("[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]")
+        .run();
+
+    p.change_file("src/lib.rs", "pub fn a() {}");
+    p.cargo("doc")
+        .with_stderr(
+            "\
+[DOCUMENTING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+    assert!(p.root().join("target/doc/foo/fn.a.html").is_file());
+    assert!(!p.root().join("target/doc/foo/m").exists());
+}
+
+#[test]
+fn doc_rebuilds_for_other_rustdoc() {
+    let p = project().file("src/lib.rs", "pub fn a() {}").build();
+
+    // Forwards to the real rustdoc, but claims to be another version.
+    let other_rustdoc = {
+        let p = project()
+            .at("other-rustdoc")
+            .file("Cargo.toml", &basic_manifest("other-rustdoc", "0.1.0"))
+            .file(
+                "src/main.rs",
+                r#"
+            use std::env;
+            use std::process::Command;
+
+            fn main() {
+                let args = env::args_os().skip(1).collect::<Vec<_>>();
+                if args.len() == 1 && args[0] == "-vV" {
+                    println!("rustdoc 0.0.0-other");
+                    return;
+                }
+                let status = Command::new("rustdoc").args(&args).status().unwrap();
+                std::process::exit(status.code().unwrap());
+            }
+        "#,
+            ).build();
+        p.cargo("build").run();
+        p.bin("other-rustdoc")
+    };
+
+    p.cargo("doc").run();
+    p.cargo("doc")
+        .with_stderr("[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]")
+        .run();
+    p.cargo("doc")
+        .env("RUSTDOC", &other_rustdoc)
+        .with_stderr(
+            "\
+[DOCUMENTING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+    p.cargo("doc")
+        .env("RUSTDOC", &other_rustdoc)
+        .with_stderr("[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]")
+        .run();
+}
@@ -0,0 +1,96 @@
+use crate::support::{basic_manifest, project, Project};
+
+fn workspace() -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["a", "b"]
+        "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.2.0"))
+        .file("b/src/lib.rs", "")
+        .build()
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_once_at_the_root() {
+    let p = workspace();
+    p.cargo("exec --no-deps sh")
+        .arg("-c")
+        .arg("echo $CARGO_MANIFEST_PATH; echo $CARGO_TARGET_DIR; pwd")
+        .with_stdout(
+            "\
+[..]/foo/Cargo.toml
+[..]/foo/target
+[..]/foo
+",
+        )
+        .with_stderr("[RUNNING] `sh -c [..]`")
+        .run();
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_for_each_package() {
+    let p = workspace();
+    p.cargo("exec --each --all --exclude b --no-deps sh")
+        .arg("-c")
+        .arg("echo $CARGO_PKG_NAME $CARGO_PKG_VERSION; pwd")
+        .with_stdout(
+            "\
+a 0.1.0
+[..]/foo/a
+",
+        )
+        .with_stderr("[RUNNING] `sh -c [..]` for a v0.1.0 ([CWD]/a)")
+        .run();
+
+    p.cargo("exec --each --all --no-deps sh")
+        .arg("-c")
+        .arg("echo $CARGO_PKG_NAME")
+        .with_stdout("a\nb\n")
+        .run();
+}
+
+#[cfg(unix)]
+#[test]
+fn hands_off_metadata() {
+    let p = workspace();
+    p.cargo("exec --no-deps sh")
+        .arg("-c")
+        .arg("cat $CARGO_METADATA")
+        .with_stdout_contains(r#"[..]"workspace_members":["a 0.1.0 [..]","b 0.2.0 [..]"][..]"#)
+        .run();
+}
+
+#[cfg(unix)]
+#[test]
+fn stops_at_the_first_failure() {
+    let p = workspace();
+    p.cargo("exec --each --all --no-deps sh")
+        .arg("-c")
+        .arg("echo $CARGO_PKG_NAME; exit 3")
+        .with_status(3)
+        .with_stdout("a\n")
+        .with_stderr(
+            "\
+[RUNNING] `sh -c [..]` for a v0.1.0 ([CWD]/a)
+[ERROR] process didn't exit successfully: `sh -c [..]` (exit [..]: 3)
+",
+        )
+        .run();
+}
+
+#[test]
+fn packages_require_each() {
+    let p = workspace();
+    p.cargo("exec -p a rustfmt")
+        .with_status(101)
+        .with_stderr("[ERROR] `-p`, `--all` and `--exclude` require `--each`")
+        .run();
+}
@@ -3,7 +3,7 @@ use std::io::prelude::*;
 
 use crate::support::paths::CargoPathExt;
 use crate::support::registry::Package;
-use crate::support::{basic_manifest, project};
+use crate::support::{basic_manifest, project, rustc_host};
 
 #[test]
 fn invalid1() {
@@ -1763,3 +1763,289 @@ fn warn_if_default_features() {
             "#.trim(),
         ).run();
 }
+
+#[test]
+fn member_features_are_not_unified() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["tool", "firmware"]
+        "#,
+        ).file(
+            "tool/Cargo.toml",
+            r#"
+            [package]
+            name = "tool"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            common = { path = "../common", features = ["std"] }
+        "#,
+        ).file("tool/src/main.rs", "fn main() { assert!(common::STD); }")
+        .file(
+            "firmware/Cargo.toml",
+            r#"
+            [package]
+            name = "firmware"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            common = { path = "../common" }
+        "#,
+        ).file("firmware/src/main.rs", "fn main() { assert!(!common::STD); }")
+        .file(
+            "common/Cargo.toml",
+            r#"
+            [package]
+            name = "common"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            std = []
+        "#,
+        ).file(
+            "common/src/lib.rs",
+            r#"pub const STD: bool = cfg!(feature = "std");"#,
+        ).build();
+
+    p.cargo("build --all -Z member-features")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[COMPILING] common v0.0.1 ([CWD]/common)
+[COMPILING] common v0.0.1 ([CWD]/common)
+[COMPILING] firmware v0.0.1 ([CWD]/firmware)
+[COMPILING] tool v0.0.1 ([CWD]/tool)
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+    p.process(&p.bin("firmware")).run();
+    p.process(&p.bin("tool")).run();
+
+    p.cargo("build --all").run();
+    p.process(&p.bin("firmware")).with_status(101).run();
+
+    p.cargo("build --all -Z member-features --features std")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] cannot specify features for more than one package")
+        .run();
+}
+
+#[test]
+fn target_default_features_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            a = []
+
+            [target.'cfg(unix)'.features]
+            default = ["a"]
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  feature `target-default-features` is required
+
+consider adding `cargo-features = [\"target-default-features\"]` to the manifest
+",
+        ).run();
+}
+
+#[test]
+fn target_default_features() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                cargo-features = ["target-default-features"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [features]
+                default = ["always"]
+                always = []
+                matching = []
+                by-name = []
+                never = []
+
+                [target.'cfg(all())'.features]
+                default = ["matching"]
+
+                [target.{}.features]
+                default = ["by-name"]
+
+                [target.'cfg(any())'.features]
+                default = ["never"]
+            "#,
+                rustc_host()
+            ),
+        ).file(
+            "src/main.rs",
+            r#"
+            fn main() {
+                assert!(cfg!(feature = "always"));
+                assert!(cfg!(feature = "matching"));
+                assert!(cfg!(feature = "by-name"));
+                assert!(!cfg!(feature = "never"));
+            }
+        "#,
+        ).build();
+
+    p.cargo("run").masquerade_as_nightly_cargo().run();
+    p.cargo("run --target")
+        .arg(rustc_host())
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("build -v --no-default-features")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]feature=[..]")
+        .run();
+}
+
+#[test]
+fn target_default_features_of_dependency() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#,
+        ).file("src/lib.rs", "extern crate bar;")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            cargo-features = ["target-default-features"]
+
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = { path = "../baz", optional = true }
+            unused = { path = "../unused", optional = true }
+
+            [target.'cfg(all())'.features]
+            default = ["baz"]
+
+            [target.'cfg(any())'.features]
+            default = ["unused"]
+        "#,
+        ).file("bar/src/lib.rs", "extern crate baz;")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.0.1"))
+        .file("baz/src/lib.rs", "")
+        .file("unused/Cargo.toml", &basic_manifest("unused", "0.0.1"))
+        .file("unused/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] baz v0.0.1 ([CWD]/baz)
+[COMPILING] bar v0.0.1 ([CWD]/bar)
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+
+    // The lock file covers the dependencies of all platforms.
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"unused\""));
+}
+
+#[test]
+fn invalid_target_default_features() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["target-default-features"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [target.'cfg(unix)'.features]
+            default = ["missing"]
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  default feature `missing` for target `cfg(unix)` is neither a feature nor an \
+optional dependency of the package
+",
+        ).run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        cargo-features = ["target-default-features"]
+
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [features]
+        a = []
+
+        [target.'cfg(unix)'.features]
+        a = []
+    "#,
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  only default features can be specified for a target, found `a` in \
+`target.cfg(unix).features`
+",
+        ).run();
+}
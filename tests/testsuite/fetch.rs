@@ -110,3 +110,121 @@ fn fetch_platform_specific_dependencies() {
         .with_stderr_does_not_contain("[DOWNLOADED] d1 v1.2.3 [..]")
         .run();
 }
+
+#[test]
+fn fetch_for_target_group() {
+    Package::new("d1", "1.2.3").publish();
+    Package::new("d2", "0.1.2").publish();
+
+    let host = rustc_host();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [target.{host}.dependencies]
+            d1 = "1.2.3"
+
+            [target.not-a-target.dependencies]
+            d2 = "0.1.2"
+        "#,
+                host = host
+            ),
+        ).file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            &format!("[target-groups]\nnative = [\"{}\"]", host),
+        ).build();
+
+    p.cargo("fetch --target @native")
+        .with_stderr_contains("[DOWNLOADED] d1 v1.2.3 [..]")
+        .with_stderr_does_not_contain("[DOWNLOADED] d2 v0.1.2 [..]")
+        .run();
+
+    if cross_compile::disabled() {
+        return;
+    }
+    let target = cross_compile::alternate();
+    p.change_file(
+        ".cargo/config",
+        &format!("[target-groups]\nall = [\"{}\", \"{}\"]", host, target),
+    );
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [target.{host}.dependencies]
+            d1 = "1.2.3"
+
+            [target.{target}.dependencies]
+            d2 = "0.1.2"
+        "#,
+            host = host,
+            target = target
+        ),
+    );
+    p.cargo("fetch --target @all")
+        .with_stderr_contains("[DOWNLOADED] d2 v0.1.2 [..]")
+        .run();
+}
+
+#[test]
+fn to_archive_then_build_from_it() {
+    Package::new("bar", "0.1.0").file("src/lib.rs", "").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "extern crate bar;")
+        .build();
+
+    p.cargo("fetch --to-archive deps.tar").run();
+    assert!(p.root().join("deps.tar").is_file());
+
+    p.cargo("build --from-archive deps.tar")
+        .with_stderr(
+            "\
+[COMPILING] bar v0.1.0
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+    assert!(p.root().join("target/from-archive").is_dir());
+}
+
+#[test]
+fn from_archive_requires_an_archive() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("deps.tar", "")
+        .build();
+
+    p.cargo("build --from-archive deps.tar")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `[CWD]/deps.tar` isn't an archive of dependencies, \
+             like the ones written by `cargo fetch --to-archive`",
+        )
+        .run();
+}
@@ -1187,3 +1187,82 @@ fn fix_to_broken_code() {
 
     assert_eq!(p.read_file("bar/src/lib.rs"), "pub fn foo() { let x = 3; drop(x); }");
 }
+
+#[test]
+fn migrate_edition() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [features]
+                bar = []
+            "#,
+        ).file(
+            "src/lib.rs",
+            r#"
+                #![allow(unused)]
+
+                mod foo {
+                    pub const FOO: &str = "fooo";
+                }
+
+                #[cfg(feature = "bar")]
+                mod bar {
+                    use ::foo::FOO;
+                }
+
+                fn main() {
+                    let x = ::foo::FOO;
+                }
+            "#,
+        ).build();
+
+    p.cargo("fix --edition --migrate -Z unstable-options --allow-no-vcs")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.1.0 ([..])
+[FIXING] src/lib.rs (1 fix)
+[FINISHED] [..]
+[CHECKING] foo v0.1.0 ([..])
+[FIXING] src/lib.rs (1 fix)
+[FINISHED] [..]
+[MIGRATING] foo to edition 2018
+[CHECKING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        ).run();
+
+    let lib = p.read_file("src/lib.rs");
+    assert!(lib.contains("use crate::foo::FOO;"));
+    assert!(lib.contains("let x = crate::foo::FOO;"));
+    assert!(p.read_file("Cargo.toml").contains(
+        r#"
+                version = "0.1.0"
+                edition = "2018"
+                authors = []
+"#
+    ));
+
+    // Running the migration again only reports that there is nothing to do.
+    p.cargo("fix --edition --migrate -Z unstable-options --allow-no-vcs")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[WARNING] `foo` is already on the latest edition (2018)")
+        .run();
+}
+
+#[test]
+fn migrate_requires_unstable_options() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("fix --edition --migrate --allow-no-vcs")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `--migrate` flag is unstable, pass `-Z unstable-options` to enable it",
+        ).run();
+}
@@ -1177,3 +1177,190 @@ fn reuse_panic_pm() {
         )
         .run();
 }
+
+#[test]
+fn rebuild_external_path_dep() {
+    let ext = project()
+        .at("ext")
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "ext"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:rerun-if-changed=data.txt");
+            }
+        "#,
+        ).file("data.txt", "")
+        .build();
+    let p = project()
+        .at("ws")
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            ext = { path = "../ext" }
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build").run();
+    p.cargo("build")
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    sleep_ms(1000);
+    File::create(&ext.root().join("src/lib.rs"))
+        .unwrap()
+        .write_all(b"pub fn ext() {}")
+        .unwrap();
+    p.cargo("build")
+        .with_stderr(
+            "\
+[COMPILING] ext v0.0.1 ([..]/ext)
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        ).run();
+
+    sleep_ms(1000);
+    File::create(&ext.root().join("data.txt"))
+        .unwrap()
+        .write_all(b"changed")
+        .unwrap();
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .with_stderr_contains("[COMPILING] foo v0.0.1 ([CWD])")
+        .run();
+    p.cargo("build")
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
+
+#[test]
+fn content_hash_no_rebuild_if_sources_move_forward_in_time() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = { path = "a" }
+        "#,
+        ).file("src/lib.rs", "")
+        .file("a/Cargo.toml", &basic_manifest("a", "0.0.1"))
+        .file("a/src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.root().move_into_the_future();
+
+    p.cargo("build -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("")
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    p.change_file("a/src/lib.rs", "pub fn a() {}");
+    p.root().move_into_the_past();
+
+    p.cargo("build -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] a v0.0.1 ([..])
+[COMPILING] foo v0.0.1 ([..])
+[FINISHED] [..]
+",
+        ).run();
+    p.cargo("build -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
+
+#[test]
+fn content_hash_build_script() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = { path = "a" }
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:rerun-if-changed=data.txt");
+            }
+        "#,
+        ).file("data.txt", "")
+        .file(
+            "a/Cargo.toml",
+            r#"
+            [package]
+            name = "a"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#,
+        ).file("a/src/lib.rs", "")
+        .file("a/build.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.root().move_into_the_future();
+
+    p.cargo("build -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    p.change_file("data.txt", "changed");
+    p.cargo("build -v -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `[..]/build/foo-[..]/build-script-build`")
+        .with_stderr_does_not_contain("[RUNNING] `[..]/build/a-[..]/build-script-build`")
+        .run();
+
+    p.change_file("a/build.rs", "fn main() { println!(\"changed\"); }");
+    p.cargo("build -v -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `[..]/build/a-[..]/build-script-build`")
+        .with_stderr_does_not_contain("[RUNNING] `[..]/build/foo-[..]/build-script-build`")
+        .run();
+    p.cargo("build -Z content-hash")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
@@ -229,3 +229,108 @@ fn duplicate_entries_in_lockfile() {
              lockfile unambiguously",
         ).run();
 }
+
+fn backtracking_project() -> ProjectBuilder {
+    Package::new("b", "1.0.0").publish();
+    Package::new("b", "2.0.0").publish();
+    Package::new("a", "1.0.0").dep("b", "1.0").publish();
+    Package::new("a", "2.0.0").dep("b", "2.0").publish();
+
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "*"
+            b = "1.0"
+        "#,
+        ).file("src/lib.rs", "")
+}
+
+#[test]
+fn resolver_debug() {
+    let p = backtracking_project().build();
+
+    p.cargo("generate-lockfile -Z resolver-debug")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[RESOLVER] [..] steps, 1 backtracks in [..]s ([..]s querying dependencies)
+[RESOLVER] b v1.0.0 conflicted 1 times
+",
+        ).run();
+
+    assert!(p.read_lockfile().contains("name = \"a\"\nversion = \"1.0.0\""));
+}
+
+#[test]
+fn resolver_step_limit() {
+    let p = backtracking_project()
+        .file(".cargo/config", "[resolver]\nstep-limit = 2")
+        .build();
+
+    p.cargo("generate-lockfile")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+error: failed to resolve the dependency graph within the limit of 2 steps
+gave up after 3 steps and 1 backtracks while resolving the dependency `[..]` of `[..]`
+
+the packages which conflicted the most were:
+  b v1.0.0 (1 times)
+
+the limits can be changed with the `resolver.step-limit` and `resolver.time-limit` configuration
+",
+        ).run();
+}
+
+#[test]
+fn unlocked_ephemeral_leaves_lockfile_alone() {
+    Package::new("bar", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            authors = []
+            version = "0.0.1"
+
+            [dependencies]
+            bar = "0.1"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    let lock = p.read_lockfile();
+
+    Package::new("bar", "0.1.1").publish();
+    p.cargo("build --unlocked --ephemeral")
+        .with_stderr_contains("[UPDATING] `[..]` index")
+        .with_stderr_contains("[COMPILING] bar v0.1.1")
+        .with_stderr_contains("[EPHEMERAL] lock file written to [..]Cargo.lock")
+        .run();
+    assert_eq!(lock, p.read_lockfile());
+}
+
+#[test]
+fn ephemeral_requires_unlocked() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build --ephemeral")
+        .with_status(101)
+        .with_stderr("[ERROR] `--ephemeral` can only be passed along with `--unlocked`")
+        .run();
+    p.cargo("build --unlocked --locked")
+        .with_status(101)
+        .with_stderr("[ERROR] cannot pass `--unlocked` along with `--frozen` or `--locked`")
+        .run();
+}
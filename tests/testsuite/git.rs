@@ -90,7 +90,7 @@ fn cargo_compile_forbird_git_httpsrepo_offline() {
         ).file("src/main.rs", "")
         .build();
 
-    p.cargo("build -Zoffline").masquerade_as_nightly_cargo().with_status(101).
+    p.cargo("build --offline").with_status(101).
                     with_stderr("\
 error: failed to load source for a dependency on `dep1`
 
@@ -98,7 +98,7 @@ Caused by:
   Unable to update https://github.com/some_user/dep1.git
 
 Caused by:
-  can't checkout from 'https://github.com/some_user/dep1.git': you are in the offline mode (-Z offline)").run();
+  can't checkout from 'https://github.com/some_user/dep1.git': you are in the offline mode (--offline)").run();
 }
 
 #[test]
@@ -189,8 +189,7 @@ fn cargo_compile_offline_with_cached_git_dep() {
 
     let git_root = git_project.root();
 
-    p.cargo("build -Zoffline")
-        .masquerade_as_nightly_cargo()
+    p.cargo("build --offline")
         .with_stderr(format!(
             "\
 [COMPILING] dep1 v0.5.0 ({}#[..])
@@ -223,7 +222,7 @@ fn cargo_compile_offline_with_cached_git_dep() {
             ).as_bytes(),
         ).unwrap();
 
-    p.cargo("build -Zoffline").masquerade_as_nightly_cargo().run();
+    p.cargo("build --offline").run();
     p.process(&p.bin("foo"))
         .with_stdout("hello from cached git repo rev1\n")
         .run();
@@ -2684,3 +2683,51 @@ fn templatedir_doesnt_cause_problems() {
 
     p.cargo("build").run();
 }
+
+#[test]
+fn home_overlay_takes_the_locks() {
+    let git_project = git::new("dep1", |project| {
+        project
+            .file("Cargo.toml", &basic_lib_manifest("dep1"))
+            .file("src/dep1.rs", "pub fn hello() {}")
+    }).unwrap();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.dep1]
+            git = '{}'
+        "#,
+                git_project.url()
+            ),
+        ).file("src/lib.rs", "extern crate dep1;")
+        .build();
+
+    // Populate the home, as a base image would.
+    p.cargo("build").run();
+    let home = paths::home().join(".cargo");
+    let lock = home.join("git/.cargo-lock-git");
+    assert!(lock.is_file());
+    fs::remove_file(&lock).unwrap();
+
+    let overlay = paths::root().join("overlay");
+    p.cargo("clean").run();
+    p.cargo("build")
+        .env("CARGO_HOME_OVERLAY", &overlay)
+        .with_stderr(
+            "\
+[COMPILING] dep1 v0.5.0 ([..])
+[COMPILING] foo v0.5.0 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+    assert!(overlay.join("git/.cargo-lock-git").is_file());
+    assert!(!lock.exists());
+}
@@ -0,0 +1,121 @@
+use crate::support::{basic_lib_manifest, project, Project};
+
+fn graph_project() -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar", features = ["extra"] }
+
+            [dev-dependencies]
+            baz = { path = "baz" }
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+
+            [features]
+            extra = []
+
+            [dependencies]
+            baz = { path = "../baz" }
+        "#,
+        ).file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_lib_manifest("baz"))
+        .file("baz/src/lib.rs", "")
+        .build()
+}
+
+#[test]
+fn graph_dot() {
+    let p = graph_project();
+
+    p.cargo("graph")
+        .with_stdout(
+            "\
+digraph {
+    n0 [label=\"foo v0.1.0\"];
+    n1 [label=\"bar v0.5.0\"];
+    n2 [label=\"baz v0.5.0\"];
+    n0 -> n1;
+    n0 -> n2 [label=\"dev\"];
+    n1 -> n2;
+}
+",
+        ).run();
+}
+
+#[test]
+fn graph_mermaid_with_features() {
+    let p = graph_project();
+
+    p.cargo("graph --format mermaid --show-features")
+        .with_stdout(
+            "\
+graph TD
+    n0[\"foo v0.1.0\"]
+    n1[\"bar v0.5.0<br>[extra]\"]
+    n2[\"baz v0.5.0\"]
+    n0 -- \"[extra]\" --> n1
+    n0 -- \"dev\" --> n2
+    n1 --> n2
+",
+        ).run();
+}
+
+#[test]
+fn graph_json() {
+    let p = graph_project();
+
+    p.cargo("graph --format json --edges normal --depth 1")
+        .with_json(
+            r#"
+    {
+        "roots": ["foo 0.1.0 (path+file:[..]foo)"],
+        "nodes": [
+            {
+                "id": "foo 0.1.0 (path+file:[..]foo)",
+                "name": "foo",
+                "version": "0.1.0",
+                "features": []
+            },
+            {
+                "id": "bar 0.5.0 (path+file:[..]bar)",
+                "name": "bar",
+                "version": "0.5.0",
+                "features": ["extra"]
+            }
+        ],
+        "edges": [
+            {
+                "from": "foo 0.1.0 (path+file:[..]foo)",
+                "to": "bar 0.5.0 (path+file:[..]bar)",
+                "kinds": ["normal"],
+                "features": ["extra"]
+            }
+        ],
+        "version": 1
+    }"#,
+        ).run();
+}
+
+#[test]
+fn graph_bad_format() {
+    let p = graph_project();
+
+    p.cargo("graph --format svg")
+        .with_status(1)
+        .with_stderr_contains("error: 'svg' isn't a valid value for '--format <FORMAT>'")
+        .run();
+}
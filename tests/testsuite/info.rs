@@ -0,0 +1,139 @@
+use std::fs;
+
+use crate::support::registry::{dl_path, Dependency, Package};
+use crate::support::{basic_manifest, cargo_process, project};
+
+fn publish_bar() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.2.0")
+        .dep("baz", "1.0")
+        .add_dep(Dependency::new("qux", "0.3").optional(true))
+        .build_dep("cc", "1.0")
+        .target_dep("winapi", "0.3", "cfg(windows)")
+        .feature("default", &["std"])
+        .feature("std", &[])
+        .publish();
+}
+
+#[test]
+fn info_from_index_and_api() {
+    publish_bar();
+    fs::write(
+        dl_path().join("api/v1/crates/bar"),
+        r#"{
+            "crate": {
+                "name": "bar",
+                "description": "A bar\n",
+                "homepage": null,
+                "documentation": "https://docs.rs/bar",
+                "repository": "https://github.com/bar/bar",
+                "max_version": "0.2.0"
+            },
+            "versions": [
+                {"num": "0.2.0", "license": "MIT", "rust_version": "1.31", "yanked": false},
+                {"num": "0.1.0", "license": "MIT", "yanked": false}
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    cargo_process("info bar")
+        .with_stdout(
+            "\
+bar v0.2.0
+A bar
+license: MIT
+rust-version: 1.31
+repository: https://github.com/bar/bar
+documentation: https://docs.rs/bar
+versions: 0.2.0, 0.1.0
+features:
+  default = [\"std\"]
+  std = []
+dependencies:
+  baz = \"^1.0\"
+  qux = \"^0.3\" (optional)
+  winapi = \"^0.3\" (target: cfg(windows))
+build-dependencies:
+  cc = \"^1.0\"
+",
+        )
+        .run();
+}
+
+#[test]
+fn info_without_api() {
+    publish_bar();
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file(
+            "Cargo.lock",
+            r#"
+[[package]]
+name = "bar"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foo"
+version = "0.0.1"
+dependencies = [
+ "bar 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+]
+"#,
+        )
+        .build();
+
+    p.cargo("info bar@0.1")
+        .with_stderr_contains(
+            "[WARNING] failed to get the metadata of `bar` from the registry's API, \
+             showing the index's only: [..]",
+        )
+        .with_stdout(
+            "\
+bar v0.1.0 (latest: v0.2.0)
+versions: 0.2.0, 0.1.0
+locked: 0.1.0
+",
+        )
+        .run();
+
+    p.cargo("info nope")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] could not find `nope` in registry `[..]`")
+        .run();
+}
+
+#[test]
+fn info_prefers_stable_versions() {
+    publish_bar();
+    Package::new("bar", "0.3.0-beta.1").publish();
+
+    cargo_process("info bar")
+        .with_stdout_contains("bar v0.2.0")
+        .with_stdout_contains("versions: 0.3.0-beta.1, 0.2.0, 0.1.0")
+        .run();
+
+    cargo_process("info bar@0.3.0-beta.1")
+        .with_stdout_contains("bar v0.3.0-beta.1 (latest: v0.2.0)")
+        .run();
+}
+
+#[test]
+fn info_in_invalid_package() {
+    publish_bar();
+    let p = project()
+        .file("Cargo.toml", "[package]\nname = ")
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("info bar@0.1")
+        .with_stdout(
+            "\
+bar v0.1.0 (latest: v0.2.0)
+versions: 0.2.0, 0.1.0
+",
+        )
+        .run();
+}
@@ -188,6 +188,148 @@ cannot automatically generate Cargo.toml as the main target would be ambiguous
     assert!(!paths::root().join("foo/Cargo.toml").is_file());
 }
 
+#[test]
+fn detects_existing_targets() {
+    let path = paths::root().join("foo");
+    fs::create_dir_all(&path.join("src/bin")).unwrap();
+    fs::create_dir_all(&path.join("examples")).unwrap();
+    fs::create_dir_all(&path.join("tests")).unwrap();
+    File::create(&path.join("src/lib.rs")).unwrap();
+    File::create(&path.join("src/bin/tool.rs"))
+        .unwrap()
+        .write_all(b"fn main() {}")
+        .unwrap();
+    File::create(&path.join("examples/demo.rs"))
+        .unwrap()
+        .write_all(b"fn main() {}")
+        .unwrap();
+    File::create(&path.join("tests/it.rs")).unwrap();
+
+    cargo_process("init --vcs none")
+        .env("USER", "foo")
+        .cwd(&path)
+        .with_stderr(
+            "\
+[DETECTED] library target `foo` at `src/lib.rs`
+[DETECTED] binary target `tool` at `src/bin/tool.rs`
+[DETECTED] example target `demo` at `examples/demo.rs`
+[DETECTED] test target `it` at `tests/it.rs`
+[CREATED] binary (application) package
+",
+        ).run();
+
+    let mut contents = String::new();
+    File::open(&path.join("Cargo.toml"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert!(!contents.contains("[lib]"));
+    assert!(!contents.contains("[["));
+    assert!(!path.join("src/main.rs").is_file());
+
+    cargo_process("build --all-targets").cwd(&path).run();
+}
+
+#[test]
+fn explicit_targets_when_inference_is_ambiguous() {
+    let path = paths::root().join("foo");
+    fs::create_dir_all(&path.join("src/bin")).unwrap();
+    File::create(&path.join("main.rs"))
+        .unwrap()
+        .write_all(b"fn main() {}")
+        .unwrap();
+    File::create(&path.join("src/bin/tool.rs"))
+        .unwrap()
+        .write_all(b"fn main() {}")
+        .unwrap();
+    File::create(&path.join("src/bin/common.rs"))
+        .unwrap()
+        .write_all(b"pub fn helper() {}")
+        .unwrap();
+
+    cargo_process("init --vcs none")
+        .env("USER", "foo")
+        .cwd(&path)
+        .with_stderr(
+            "\
+[DETECTED] binary target `foo` at `main.rs`
+[DETECTED] binary target `tool` at `src/bin/tool.rs`
+[SKIPPED] `src/bin/common.rs` as it has no `main` function, it is not a binary target
+[LISTED] all binary targets explicitly, as they can't be inferred
+[CREATED] binary (application) package
+",
+        ).run();
+
+    let mut contents = String::new();
+    File::open(&path.join("Cargo.toml"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert!(contents.contains("autobins = false"));
+    assert!(contents.contains(
+        r#"
+[[bin]]
+name = "foo"
+path = "main.rs"
+
+[[bin]]
+name = "tool"
+path = "src/bin/tool.rs"
+"#
+    ));
+
+    cargo_process("build").cwd(&path).run();
+    assert!(path
+        .join(&format!("target/debug/tool{}", env::consts::EXE_SUFFIX))
+        .is_file());
+    assert!(!path
+        .join(&format!("target/debug/common{}", env::consts::EXE_SUFFIX))
+        .is_file());
+}
+
+#[test]
+fn explicit_examples_when_one_has_no_main() {
+    let path = paths::root().join("foo");
+    fs::create_dir_all(&path.join("src")).unwrap();
+    fs::create_dir_all(&path.join("examples")).unwrap();
+    fs::create_dir_all(&path.join("benches")).unwrap();
+    File::create(&path.join("src/lib.rs")).unwrap();
+    File::create(&path.join("examples/demo.rs"))
+        .unwrap()
+        .write_all(b"fn main() {}")
+        .unwrap();
+    File::create(&path.join("examples/shared.rs"))
+        .unwrap()
+        .write_all(b"pub fn helper() {}")
+        .unwrap();
+    File::create(&path.join("benches/speed.rs")).unwrap();
+
+    cargo_process("init --vcs none")
+        .env("USER", "foo")
+        .cwd(&path)
+        .with_stderr_contains(
+            "[SKIPPED] `examples/shared.rs` as it has no `main` function, \
+             it is not an example target",
+        ).with_stderr_contains(
+            "[LISTED] all example targets explicitly, as they can't be inferred",
+        ).run();
+
+    let mut contents = String::new();
+    File::open(&path.join("Cargo.toml"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert!(contents.contains("autoexamples = false"));
+    assert!(!contents.contains("autobenches"));
+    assert!(contents.contains(
+        r#"
+[[example]]
+name = "demo"
+path = "examples/demo.rs"
+"#
+    ));
+}
+
 fn lib_already_exists(rellocation: &str) {
     let path = paths::root().join("foo");
     fs::create_dir_all(&path.join("src")).unwrap();
@@ -1292,3 +1292,219 @@ fn install_global_cargo_config() {
         .with_stderr_contains("[..]--target nonexistent[..]")
         .run();
 }
+
+#[test]
+fn install_bundled_cargo_config() {
+    Package::new("bar", "0.0.1")
+        .file(
+            ".cargo/config",
+            r#"
+                [build]
+                rustflags = ["--cfg", "bundled"]
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+                #[cfg(not(bundled))]
+                compile_error!("not bundled");
+                fn main() {}
+            "#,
+        )
+        .publish();
+
+    cargo_process("install bar")
+        .with_stderr_contains(
+            "\
+[WARNING] `bar v0.0.1` bundles the configuration file `.cargo/config`, which \
+applies to its build and sets `build.rustflags`
+pass `--ignore-local-config` to ignore it",
+        )
+        .run();
+    assert_has_installed_exe(cargo_home(), "bar");
+
+    cargo_process("install bar --force --ignore-local-config")
+        .with_status(101)
+        .with_stderr_contains("[..]not bundled[..]")
+        .with_stderr_does_not_contain("[..]bundles the configuration file[..]")
+        .run();
+}
+
+#[test]
+fn install_path_uses_local_cargo_config() {
+    let p = project()
+        .file(
+            ".cargo/config",
+            r#"
+                [build]
+                rustflags = ["--cfg", "local"]
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+                #[cfg(not(local))]
+                compile_error!("not local");
+                fn main() {}
+            "#,
+        )
+        .build();
+
+    cargo_process("install --path").arg(p.root()).run();
+    assert_has_installed_exe(cargo_home(), "foo");
+
+    cargo_process("install --force --ignore-local-config --path")
+        .arg(p.root())
+        .with_status(101)
+        .with_stderr_contains("[..]not local[..]")
+        .run();
+}
+
+#[test]
+fn install_metadata_files() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [package.metadata.install]
+            man = ["doc/foo.1"]
+
+            [package.metadata.install.completions]
+            bash = "$OUT_DIR/foo.bash"
+            zsh = "$OUT_DIR/_foo"
+            "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file(
+            "build.rs",
+            r#"
+            use std::env;
+            use std::fs;
+            use std::path::Path;
+
+            fn main() {
+                let out_dir = env::var("OUT_DIR").unwrap();
+                fs::write(Path::new(&out_dir).join("foo.bash"), "complete -F _foo foo").unwrap();
+                fs::write(Path::new(&out_dir).join("_foo"), "compdef _foo foo").unwrap();
+            }
+            "#,
+        ).file("doc/foo.1", ".TH FOO 1")
+        .build();
+
+    // The configuration of the package is ignored by `cargo install`.
+    fs::create_dir_all(cargo_home()).unwrap();
+    let config = cargo_home().join("config");
+    let mut toml = fs::read_to_string(&config).unwrap_or(String::new());
+    toml.push_str(
+        r#"
+        [install]
+        zsh-completion-dir = "zsh"
+    "#,
+    );
+    fs::write(&config, toml).unwrap();
+
+    p.cargo("install --path . -Z install-metadata")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[INSTALLING] [..]/home/.cargo/share/man/man1/foo.1")
+        .with_stderr_contains(
+            "[INSTALLING] [..]/home/.cargo/share/bash-completion/completions/foo.bash",
+        ).with_stderr_contains("[INSTALLING] [..]/home/zsh/_foo")
+        .run();
+    assert_has_installed_exe(cargo_home(), "foo");
+    let man = cargo_home().join("share/man/man1/foo.1");
+    assert_eq!(fs::read_to_string(&man).unwrap(), ".TH FOO 1");
+    let bash = cargo_home().join("share/bash-completion/completions/foo.bash");
+    assert_eq!(fs::read_to_string(&bash).unwrap(), "complete -F _foo foo");
+    let zsh = paths::home().join("zsh/_foo");
+    assert!(zsh.is_file());
+
+    p.cargo("uninstall foo")
+        .with_stderr_unordered(
+            "\
+[REMOVING] [..]/home/.cargo/bin/foo[EXE]
+[REMOVING] [..]/home/.cargo/share/man/man1/foo.1
+[REMOVING] [..]/home/.cargo/share/bash-completion/completions/foo.bash
+[REMOVING] [..]/home/zsh/_foo
+",
+        ).run();
+    assert_has_not_installed_exe(cargo_home(), "foo");
+    assert!(!man.exists());
+    assert!(!bash.exists());
+    assert!(!zsh.exists());
+}
+
+#[test]
+fn install_metadata_files_requires_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [package.metadata.install]
+            man = ["doc/foo.1"]
+            "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file("doc/foo.1", ".TH FOO 1")
+        .build();
+
+    cargo_process("install --path").arg(p.root()).run();
+    assert_has_installed_exe(cargo_home(), "foo");
+    assert!(!cargo_home().join("share/man/man1/foo.1").exists());
+}
+
+#[test]
+fn install_metadata_files_errors() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [package.metadata.install]
+            man = ["doc/foo.man"]
+            "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file("doc/foo.man", "")
+        .build();
+
+    p.cargo("install --path . -Z install-metadata")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] the man page `doc/foo.man` must have its section as the extension \
+             of its file name, like `foo.1`",
+        ).run();
+    assert_has_not_installed_exe(cargo_home(), "foo");
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [package.metadata.install]
+        man = ["$OUT_DIR/foo.1"]
+        "#,
+    );
+    p.cargo("install --path . -Z install-metadata")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] `$OUT_DIR/foo.1` in `package.metadata.install` refers to `$OUT_DIR`, \
+             but `foo` has no build script",
+        ).run();
+    assert_has_not_installed_exe(cargo_home(), "foo");
+}
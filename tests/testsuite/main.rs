@@ -27,6 +27,7 @@ extern crate winapi;
 #[macro_use]
 mod support;
 
+mod add;
 mod alt_registry;
 mod bad_config;
 mod bad_manifest_path;
@@ -37,6 +38,7 @@ mod build_lib;
 mod build_plan;
 mod build_script;
 mod build_script_env;
+mod build_std;
 mod cargo_alias_config;
 mod cargo_command;
 mod cargo_features;
@@ -44,6 +46,7 @@ mod cfg;
 mod check;
 mod clean;
 mod collisions;
+mod complete;
 mod concurrent;
 mod config;
 mod corrupt_git;
@@ -52,24 +55,30 @@ mod cross_publish;
 mod custom_target;
 mod death;
 mod dep_info;
+mod diff;
 mod directory;
 mod doc;
 mod edition;
+mod exec;
 mod features;
 mod fetch;
 mod fix;
 mod freshness;
 mod generate_lockfile;
+mod graph;
 mod git;
+mod info;
 mod init;
 mod install;
 mod jobserver;
 mod local_registry;
 mod lockfile_compat;
 mod login;
+mod manifest;
 mod member_errors;
 mod metabuild;
 mod metadata;
+mod named_profiles;
 mod net_config;
 mod new;
 mod out_dir;
@@ -86,6 +95,8 @@ mod profiles;
 mod publish;
 mod read_manifest;
 mod registry;
+mod report;
+mod reproducible;
 mod rename_deps;
 mod required_features;
 mod resolve;
@@ -98,12 +109,16 @@ mod rustflags;
 mod search;
 mod shell_quoting;
 mod small_fd_limits;
+mod system_deps;
+mod term_output;
 mod test;
 mod tool_paths;
 mod update;
+mod verify_crate;
 mod verify_project;
 mod version;
 mod warn_on_failure;
+mod windows_resources;
 mod workspaces;
 
 #[test]
@@ -0,0 +1,228 @@
+use crate::support::project;
+
+const MANIFEST: &str = r#"# The foo package.
+[package]
+name    = "foo"
+version = "0.1.0"  # bumped by the release script
+authors = []
+
+[dependencies]
+bar = { path = "bar", optional = true }
+baz = "1.0"
+
+[features]
+default = [
+    "bar",
+]
+"#;
+
+#[test]
+fn set_keeps_formatting() {
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("manifest set package.version 1.2.0")
+        .with_stderr("[UPDATED] `package.version` in [CWD]/Cargo.toml")
+        .run();
+    p.cargo("manifest set dependencies.bar.default-features false --toml")
+        .run();
+    p.cargo("manifest set package.metadata.release.tag-prefix v")
+        .run();
+    p.cargo("manifest set features.default [] --toml").run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"# The foo package.
+[package]
+name    = "foo"
+version = "1.2.0"  # bumped by the release script
+authors = []
+
+[package.metadata.release]
+tag-prefix = "v"
+
+[dependencies]
+bar = { path = "bar", optional = true, default-features = false }
+baz = "1.0"
+
+[features]
+default = []
+"#
+    );
+}
+
+#[test]
+fn add_feature() {
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("manifest add-feature extra --enables bar --enables baz/std")
+        .with_stderr("[UPDATED] `features.extra` in [CWD]/Cargo.toml")
+        .run();
+    p.cargo("manifest add-feature extra --enables bar --enables qux")
+        .with_status(101)
+        .with_stderr_contains(
+            "[..]Feature `extra` includes `qux` which is neither a dependency nor another feature",
+        )
+        .run();
+    p.cargo("manifest add-feature default --enables baz/std")
+        .run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"# The foo package.
+[package]
+name    = "foo"
+version = "0.1.0"  # bumped by the release script
+authors = []
+
+[dependencies]
+bar = { path = "bar", optional = true }
+baz = "1.0"
+
+[features]
+default = ["bar", "baz/std"]
+extra = ["bar", "baz/std"]
+"#
+    );
+}
+
+#[test]
+fn unset() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            description = "foo"
+
+            [dependencies]
+            bar = { path = "bar", optional = true }
+
+            [dependencies.baz]
+            version = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("manifest unset package.description")
+        .with_stderr("[REMOVED] `package.description` in [CWD]/Cargo.toml")
+        .run();
+    p.cargo("manifest unset dependencies.bar.optional").run();
+    p.cargo("manifest unset dependencies.baz").run();
+    p.cargo("manifest unset package.license")
+        .with_status(101)
+        .with_stderr("[ERROR] `package.license` isn't set in the manifest")
+        .run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            "#
+    );
+}
+
+#[test]
+fn inline_tables_keep_their_order() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            zed = { version = "1.0", path = "zed", features = ["b", "a"] }  # pinned
+            yak = { path = "yak", version = "1.0" }
+
+            [target.'cfg(unix)'.dependencies]
+            bar = { version = "1.0" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("manifest set dependencies.zed.version 1.1").run();
+    p.cargo("manifest set dependencies.zed.optional true --toml").run();
+    p.cargo("manifest unset dependencies.yak.path").run();
+    p.cargo("manifest set dependencies.yak.path yak").run();
+    p.cargo("manifest unset dependencies.zed.path").run();
+    p.cargo("manifest unset")
+        .arg("target.'cfg(unix)'.dependencies.bar.version")
+        .run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            zed = { version = "1.1", features = ["b", "a"], optional = true }  # pinned
+            yak = { version = "1.0", path = "yak" }
+
+            [target.'cfg(unix)'.dependencies]
+            bar = {}
+            "#
+    );
+}
+
+#[test]
+fn invalid_edits() {
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("manifest set package.version one")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the edit would make the manifest at `[CWD]/Cargo.toml` invalid, it wasn't changed
+
+Caused by:
+  failed to parse manifest at `[CWD]/Cargo.toml`
+
+Caused by:
+  [..] for key `package.version`
+",
+        )
+        .run();
+    p.cargo("manifest set package.verison 1.2.0")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the edit would make the manifest at `[CWD]/Cargo.toml` invalid, it wasn't changed
+
+Caused by:
+  `package.verison` isn't a manifest key cargo knows about
+",
+        )
+        .run();
+    p.cargo("manifest set dependencies.baz.features")
+        .arg(r#"["std"]"#)
+        .arg("--toml")
+        .with_status(101)
+        .with_stderr("[ERROR] `dependencies.baz` is string in the manifest, not a table")
+        .run();
+    p.cargo("manifest set package.publish [false --toml")
+        .with_status(101)
+        .with_stderr("[ERROR] `[false` isn't a TOML value")
+        .run();
+    assert_eq!(p.read_file("Cargo.toml"), MANIFEST);
+}
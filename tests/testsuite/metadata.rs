@@ -783,6 +783,90 @@ fn workspace_metadata_no_deps() {
         ).run();
 }
 
+#[test]
+fn metadata_no_deps_resolve() {
+    // `bar` is never published, so anything trying to resolve it would fail.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("metadata --no-deps-resolve --format-version 1")
+        .with_json(
+            r#"
+    {
+        "packages": [
+            {
+                "authors": [],
+                "categories": [],
+                "name": "foo",
+                "readme": null,
+                "repository": null,
+                "version": "0.5.0",
+                "id": "foo[..]",
+                "keywords": [],
+                "source": null,
+                "dependencies": [
+                    {
+                        "features": [],
+                        "kind": null,
+                        "name": "bar",
+                        "optional": false,
+                        "req": "^1.0",
+                        "source": "registry+[..]",
+                        "target": null,
+                        "uses_default_features": true,
+                        "rename": null
+                    }
+                ],
+                "license": null,
+                "license_file": null,
+                "description": null,
+                "edition": "2015",
+                "targets": [
+                    {
+                        "kind": [ "lib" ],
+                        "crate_types": [ "lib" ],
+                        "edition": "2015",
+                        "name": "foo",
+                        "src_path": "[..]src/lib.rs"
+                    }
+                ],
+                "features": {},
+                "manifest_path": "[..]Cargo.toml",
+                "metadata": null
+            }
+        ],
+        "workspace_members": ["foo 0.5.0 (path+file:[..]foo)"],
+        "resolve": null,
+        "target_directory": "[..]foo/target",
+        "version": 1,
+        "workspace_root": "[..]/foo"
+    }"#,
+        ).run();
+}
+
+#[test]
+fn metadata_no_deps_resolve_rejects_features() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("metadata --no-deps-resolve --all-features --format-version 1")
+        .with_status(1)
+        .with_stderr_contains(
+            "error: The argument '--all-features' cannot be used with '--no-deps-resolve'",
+        ).run();
+}
+
 #[test]
 fn cargo_metadata_with_invalid_manifest() {
     let p = project().file("Cargo.toml", "").build();
@@ -907,6 +991,45 @@ fn cargo_metadata_no_deps_cwd() {
         .run();
 }
 
+#[test]
+fn cargo_metadata_target_info() {
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+
+    p.cargo("metadata --no-deps --target-info")
+        .with_json(
+            r#"
+{
+    "packages": "{...}",
+    "workspace_members": [ "foo 0.5.0 (path+file:[..]foo)" ],
+    "resolve": null,
+    "target_directory": "[..]foo/target",
+    "target_info": [
+        {
+            "kind": "host",
+            "triple": "[..]",
+            "cfg": "{...}",
+            "linker": "[..]",
+            "ar": "[..]",
+            "llvm_tools_dir": "{...}"
+        },
+        {
+            "kind": "target",
+            "triple": "[..]",
+            "cfg": "{...}",
+            "linker": "[..]",
+            "ar": "[..]",
+            "llvm_tools_dir": "{...}"
+        }
+    ],
+    "version": 1,
+    "workspace_root": "[..]/foo"
+}"#,
+        ).run();
+}
+
 #[test]
 fn cargo_metadata_bad_version() {
     let p = project()
@@ -1460,3 +1583,59 @@ fn rename_dependency() {
 }"#,
         ).run();
 }
+
+#[test]
+fn index_fields_of_registry_packages() {
+    Package::new("bar", "0.1.0")
+        .index_field("rust_version", json!("1.31"))
+        .index_field("hints", json!({ "artifacts": ["bin"] }))
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("metadata -q --format-version 1")
+        .with_json(
+            r#"
+    {
+        "packages": "{...}",
+        "workspace_members": "{...}",
+        "resolve": {
+            "nodes": [
+                {
+                    "dependencies": ["bar 0.1.0 (registry+[..])"],
+                    "deps": [{ "name": "bar", "pkg": "bar 0.1.0 (registry+[..])" }],
+                    "features": [],
+                    "id": "foo 0.0.1 (path+file:[..]foo)"
+                },
+                {
+                    "dependencies": [],
+                    "deps": [],
+                    "features": [],
+                    "id": "bar 0.1.0 (registry+[..])",
+                    "index_fields": {
+                        "hints": { "artifacts": ["bin"] },
+                        "rust_version": "1.31"
+                    }
+                }
+            ],
+            "root": "foo 0.0.1 (path+file:[..]foo)"
+        },
+        "target_directory": "[..]foo/target",
+        "version": 1,
+        "workspace_root": "[..]/foo"
+    }"#,
+        ).run();
+}
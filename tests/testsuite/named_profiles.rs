@@ -0,0 +1,268 @@
+use crate::support::{basic_lib_manifest, basic_manifest, project};
+
+#[test]
+fn named_profiles_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.release-lto]
+            inherits = "release"
+            lto = true
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .with_stderr(
+            "\
+[WARNING] unused manifest key: profile.release-lto
+[COMPILING] foo [..]
+[FINISHED] dev [..]
+",
+        ).run();
+
+    p.cargo("build --profile release-lto")
+        .with_status(101)
+        .with_stderr("[ERROR] `--profile` is unstable, pass `-Z unstable-options` to enable it")
+        .run();
+
+    p.cargo("build --profile release-lto -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[WARNING] unused manifest key: profile.release-lto
+[ERROR] profile `release-lto` is not defined
+",
+        ).run();
+}
+
+#[test]
+fn inherits_release() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["named-profiles"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.release]
+            codegen-units = 2
+
+            [profile.release-lto]
+            inherits = "release"
+            lto = true
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                assert_eq!(std::env::var("PROFILE").unwrap(), "release");
+            }
+        "#,
+        ).build();
+
+    p.cargo("build --profile release-lto -Z unstable-options -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "\
+[RUNNING] `rustc --crate-name foo src/main.rs --color never --crate-type bin \
+--emit=dep-info,link -C opt-level=3 -C lto -C codegen-units=2 [..]\
+--out-dir [CWD]/target/release-lto/deps [..]",
+        ).with_stderr_contains("[FINISHED] release-lto [optimized] target(s) in [..]")
+        .run();
+    assert!(p.root().join("target/release-lto/foo").is_file());
+    assert!(!p.root().join("target/release").exists());
+
+    p.cargo("run --profile release-lto -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] release-lto [optimized] target(s) in [..]
+[RUNNING] `target/release-lto/foo[EXE]`
+",
+        ).run();
+
+    p.cargo("clean --profile release-lto -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(!p.root().join("target/release-lto").exists());
+}
+
+#[test]
+fn inherits_chain_with_overrides() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["named-profiles", "profile-overrides"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [profile.dev]
+            debug = false
+
+            [profile.fast-deps]
+            inherits = "dev"
+
+            [profile.fast-deps.overrides.bar]
+            opt-level = 3
+
+            [profile.fast-deps-checked]
+            inherits = "fast-deps"
+            overflow-checks = false
+        "#,
+        ).file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build --profile fast-deps-checked -Z unstable-options -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar [..]
+[RUNNING] `rustc --crate-name bar bar/src/lib.rs --color never --crate-type lib \
+--emit=dep-info,link -C opt-level=3 -C debug-assertions=on -C overflow-checks=off [..]\
+--out-dir [CWD]/target/fast-deps-checked/deps [..]`
+[COMPILING] foo [..]
+[RUNNING] `rustc --crate-name foo src/lib.rs --color never --crate-type lib \
+--emit=dep-info,link -C overflow-checks=off [..]`
+[FINISHED] fast-deps-checked [unoptimized] target(s) in [..]
+",
+        ).run();
+}
+
+#[test]
+fn invalid_named_profiles() {
+    let cases = [
+        (
+            "[profile.fast]\nopt-level = 1",
+            "profile `fast` is missing an `inherits` directive (`inherits` is required \
+             for all profiles except `dev`, `release`, `test`, `bench` and `doc`)",
+        ),
+        (
+            "[profile.fast]\ninherits = \"test\"",
+            "profile `fast` inherits from `test`, custom profiles may only inherit \
+             from `dev`, `release` or other custom profiles",
+        ),
+        (
+            "[profile.fast]\ninherits = \"faster\"",
+            "profile `fast` inherits from `faster`, but that profile is not defined",
+        ),
+        (
+            "[profile.a]\ninherits = \"b\"\n[profile.b]\ninherits = \"a\"",
+            "profile inheritance loop detected: a -> b -> a",
+        ),
+        (
+            "[profile.release]\ninherits = \"dev\"",
+            "`inherits` may only be specified for custom profiles, not `release`",
+        ),
+        (
+            "[profile.\"my.profile\"]\ninherits = \"dev\"",
+            "invalid character `.` in profile name `my.profile`, profile names may \
+             only contain alphanumeric characters, `-` or `_`",
+        ),
+    ];
+    for &(profiles, error) in cases.iter() {
+        let p = project()
+            .file(
+                "Cargo.toml",
+                &format!(
+                    r#"
+                    cargo-features = ["named-profiles"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.0.1"
+                    authors = []
+
+                    {}
+                "#,
+                    profiles
+                ),
+            ).file("src/lib.rs", "")
+            .build();
+
+        p.cargo("build")
+            .masquerade_as_nightly_cargo()
+            .with_status(101)
+            .with_stderr(&format!(
+                "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  {}
+",
+                error
+            )).run();
+    }
+}
+
+#[test]
+fn select_profile_errors() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build --profile test -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] profile `test` can't be selected with `--profile`, only `dev`, \
+             `release` and custom profiles can",
+        ).run();
+
+    p.cargo("build --profile release --release -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] conflicting usage of --profile and --release")
+        .run();
+
+    p.cargo("build --profile release -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo [..]
+[FINISHED] release [optimized] target(s) in [..]
+",
+        ).run();
+}
+
+#[test]
+fn clean_undefined_profile() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("clean --profile nope -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] profile `nope` is not defined")
+        .run();
+
+    p.cargo("clean -p foo --profile nope -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] profile `nope` is not defined")
+        .run();
+}
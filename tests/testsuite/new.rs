@@ -354,6 +354,98 @@ fn strip_angle_bracket_author_email() {
     assert!(contents.contains(r#"authors = ["bar <baz>"]"#));
 }
 
+#[test]
+fn new_package_defaults_from_config() {
+    let root = paths::root();
+    fs::create_dir_all(&root.join(".cargo")).unwrap();
+    fs::create_dir_all(&root.join("templates/ci")).unwrap();
+    File::create(&root.join(".cargo/config"))
+        .unwrap()
+        .write_all(
+            br#"
+        [cargo-new]
+        name = "foo"
+        email = "bar"
+        vcs = "none"
+        kind = "lib"
+        edition = "2015"
+        license = "MIT OR Apache-2.0"
+
+        [cargo-new.files]
+        "rustfmt.toml" = "templates/rustfmt.toml"
+        "ci/build.sh" = "templates/ci/build.sh"
+    "#,
+        ).unwrap();
+    File::create(&root.join("templates/rustfmt.toml"))
+        .unwrap()
+        .write_all(b"max_width = 80\n")
+        .unwrap();
+    File::create(&root.join("templates/ci/build.sh"))
+        .unwrap()
+        .write_all(b"cargo test\n")
+        .unwrap();
+
+    cargo_process("new foo")
+        .env("USER", "foo")
+        .with_stderr("[CREATED] library `foo` package")
+        .run();
+
+    let mut contents = String::new();
+    File::open(&root.join("foo/Cargo.toml"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert!(contents.contains("edition = \"2015\""));
+    assert!(contents.contains("license = \"MIT OR Apache-2.0\""));
+    assert!(root.join("foo/src/lib.rs").is_file());
+    assert!(!root.join("foo/.gitignore").exists());
+
+    let mut rustfmt = String::new();
+    File::open(&root.join("foo/rustfmt.toml"))
+        .unwrap()
+        .read_to_string(&mut rustfmt)
+        .unwrap();
+    assert_eq!(rustfmt, "max_width = 80\n");
+    assert!(root.join("foo/ci/build.sh").is_file());
+
+    // Flags passed on the command line take precedence.
+    cargo_process("new bar --bin --edition 2018")
+        .env("USER", "foo")
+        .with_stderr("[CREATED] binary (application) `bar` package")
+        .run();
+    let mut contents = String::new();
+    File::open(&root.join("bar/Cargo.toml"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert!(contents.contains("edition = \"2018\""));
+    assert!(root.join("bar/src/main.rs").is_file());
+}
+
+#[test]
+fn new_package_invalid_kind_in_config() {
+    let root = paths::root();
+    fs::create_dir_all(&root.join(".cargo")).unwrap();
+    File::create(&root.join(".cargo/config"))
+        .unwrap()
+        .write_all(
+            br#"
+        [cargo-new]
+        kind = "dylib"
+    "#,
+        ).unwrap();
+
+    cargo_process("new foo")
+        .env("USER", "foo")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] invalid configuration for key `cargo-new.kind`, expected `bin` or `lib`, \
+found `dylib` (found in [..]/.cargo/config)
+",
+        ).run();
+}
+
 #[test]
 fn git_prefers_command_line() {
     let root = paths::root();
@@ -120,6 +120,25 @@ fn rlib_with_debug() {
     );
 }
 
+#[test]
+fn hyphenated_package_name() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo-bar", "0.0.1"))
+        .file("src/lib.rs", "pub fn foo() {}")
+        .file("src/main.rs", "fn main() { foo_bar::foo() }")
+        .build();
+
+    p.cargo("build -Z unstable-options --out-dir out")
+        .masquerade_as_nightly_cargo()
+        .run();
+    check_dir_contents(
+        &p.root().join("out"),
+        &["foo-bar", "libfoo_bar.rlib"],
+        &["foo-bar", "foo-bar.dSYM", "libfoo_bar.rlib"],
+        &["foo-bar.exe", "foo-bar.pdb", "libfoo_bar.rlib"],
+    );
+}
+
 #[test]
 fn include_only_the_binary_from_the_current_package() {
     let p = project()
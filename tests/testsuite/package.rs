@@ -2,12 +2,13 @@ use std;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use flate2::read::GzDecoder;
 use git2;
 use crate::support::registry::Package;
 use crate::support::{basic_manifest, git, is_nightly, path2url, paths, project, registry};
-use crate::support::{cargo_process, sleep_ms};
+use crate::support::{cargo_process, git_process, sleep_ms, Project};
 use tar::Archive;
 
 #[test]
@@ -1234,3 +1235,165 @@ To proceed despite this, pass the `--no-verify` flag.",
 
     p.cargo("package --no-verify").run();
 }
+
+fn git_submodule_project() -> (Project, PathBuf) {
+    let project = git::new("foo", |project| {
+        project
+            .file(
+                "Cargo.toml",
+                r#"
+                    [project]
+                    name = "foo"
+                    version = "0.0.1"
+                    authors = ["foo@example.com"]
+                    license = "MIT"
+                    description = "foo"
+                    repository = "foo"
+                "#,
+            ).file("src/lib.rs", "pub fn foo() {}")
+    }).unwrap();
+    let library = git::new("bar", |library| {
+        library.no_manifest().file("Makefile", "all:")
+    }).unwrap();
+
+    let repository = git2::Repository::open(&project.root()).unwrap();
+    let url = path2url(library.root()).to_string();
+    git::add_submodule(&repository, &url, Path::new("bar"));
+    git::commit(&repository);
+    let bar = project.root().join("bar");
+    (project, bar)
+}
+
+#[test]
+fn dirty_file_in_git_submodule() {
+    let (project, bar) = git_submodule_project();
+    File::create(bar.join("Makefile"))
+        .unwrap()
+        .write_all(b"all: foo")
+        .unwrap();
+
+    project.cargo("package --no-verify")
+        .with_status(101)
+        .with_stderr(
+            "\
+error: 1 files in the working directory contain changes that were not yet \
+committed into git:
+
+bar/Makefile
+
+to proceed despite this, pass the `--allow-dirty` flag
+",
+        ).run();
+}
+
+#[test]
+fn git_submodule_at_different_commit() {
+    let (project, bar) = git_submodule_project();
+    let repository = git2::Repository::open(&bar).unwrap();
+    let mut config = repository.config().unwrap();
+    config.set_str("user.name", "name").unwrap();
+    config.set_str("user.email", "email").unwrap();
+    File::create(bar.join("Makefile"))
+        .unwrap()
+        .write_all(b"all: foo")
+        .unwrap();
+    git::add(&repository);
+    git::commit(&repository);
+
+    project.cargo("package --no-verify")
+        .with_status(101)
+        .with_stderr(
+            "\
+error: 1 files in the working directory contain changes that were not yet \
+committed into git:
+
+bar (submodule checked out at a different commit)
+
+to proceed despite this, pass the `--allow-dirty` flag
+",
+        ).run();
+}
+
+#[test]
+fn package_nested_in_git_submodule() {
+    if Command::new("git").arg("--version").output().is_err() {
+        return;
+    }
+    // The package isn't at the root of the submodule, whose `.git` is a file
+    // pointing into the superproject's git directory.
+    let library = git::repo(&paths::root().join("bar"))
+        .file(".gitignore", "*.log\n")
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("foo/src/lib.rs", "")
+        .build();
+    let _ = git::repo(&paths::root().join("sup"))
+        .file("README", "")
+        .build();
+    let url = path2url(library.root()).to_string();
+    git_process(&format!("-c protocol.file.allow=always submodule add -q {} bar", url))
+        .cwd(paths::root().join("sup"))
+        .exec_with_output()
+        .unwrap();
+    let foo = paths::root().join("sup/bar/foo");
+    assert!(paths::root().join("sup/bar/.git").is_file());
+    File::create(foo.join("build.log")).unwrap();
+
+    cargo_process("package -l")
+        .cwd(&foo)
+        .with_stdout(
+            "\
+.cargo_vcs_info.json
+Cargo.toml
+src/lib.rs
+",
+        ).run();
+}
+
+#[test]
+fn package_in_git_sparse_checkout() {
+    if Command::new("git").arg("--version").output().is_err() {
+        return;
+    }
+    let p = git::repo(&paths::root().join("foo"))
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            license = "MIT"
+            description = "foo"
+            documentation = "foo"
+            homepage = "foo"
+            repository = "foo"
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file("data.txt", "")
+        .build();
+    git_process("config core.sparseCheckout true")
+        .cwd(p.root())
+        .exec_with_output()
+        .unwrap();
+    File::create(p.root().join(".git/info/sparse-checkout"))
+        .unwrap()
+        .write_all(b"/*\n!/data.txt\n")
+        .unwrap();
+    git_process("read-tree -mu HEAD")
+        .cwd(p.root())
+        .exec_with_output()
+        .unwrap();
+    assert!(!p.root().join("data.txt").exists());
+
+    cargo_process("package")
+        .cwd(p.root())
+        .with_status(101)
+        .with_stderr(
+            "\
+error: 1 files of the package are not checked out because of a git sparse checkout:
+
+data.txt
+
+they have to be checked out for the package to be complete
+",
+        ).run();
+}
@@ -421,3 +421,166 @@ Caused by:
         .with_status(101)
         .run();
 }
+
+#[test]
+fn sandbox_scrubs_environment() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            spy = { path = "spy" }
+        "#,
+        ).file(
+            ".cargo/config",
+            r#"
+            [build.proc-macro-sandbox]
+            env = ["SPY_ALLOWED"]
+        "#,
+        ).file(
+            "src/main.rs",
+            r#"
+            #[macro_use]
+            extern crate spy;
+
+            #[derive(Spy)]
+            struct X;
+
+            fn main() {
+                println!("{} {} {}", SECRET, ALLOWED, CWD);
+            }
+        "#,
+        ).file(
+            "spy/Cargo.toml",
+            r#"
+            [package]
+            name = "spy"
+            version = "0.0.1"
+            authors = []
+
+            [lib]
+            proc-macro = true
+        "#,
+        ).file(
+            "spy/src/lib.rs",
+            r#"
+            extern crate proc_macro;
+            use proc_macro::TokenStream;
+            use std::env;
+
+            #[proc_macro_derive(Spy)]
+            pub fn spy(_input: TokenStream) -> TokenStream {
+                let var = |name| env::var(name).unwrap_or("-".to_string());
+                let cwd = env::current_dir().unwrap();
+                format!(
+                    "const SECRET: &str = {:?}; const ALLOWED: &str = {:?}; \
+                     const CWD: &str = {:?};",
+                    var("SPY_SECRET"),
+                    var("SPY_ALLOWED"),
+                    cwd.file_name().unwrap().to_str().unwrap(),
+                ).parse().unwrap()
+            }
+        "#,
+        ).build();
+
+    p.cargo("run")
+        .env("SPY_SECRET", "hunter2")
+        .env("SPY_ALLOWED", "ok")
+        .with_stdout("- ok proc-macro-sandbox")
+        .run();
+    assert!(p.root().join("target/proc-macro-sandbox").is_dir());
+}
+
+#[test]
+fn sandbox_covers_reexported_macros_and_doctests() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            edition = "2018"
+
+            [dependencies]
+            facade = { path = "facade" }
+        "#,
+        ).file(".cargo/config", "[build.proc-macro-sandbox]")
+        .file(
+            "src/lib.rs",
+            r#"
+            //! ```
+            //! #[derive(foo::Spy)]
+            //! struct X;
+            //! assert_eq!(SECRET, "-");
+            //! assert_eq!(CWD, "proc-macro-sandbox");
+            //! ```
+
+            pub use facade::Spy;
+
+            #[derive(Spy)]
+            pub struct X;
+
+            pub fn leaked() -> &'static str { SECRET }
+        "#,
+        ).file(
+            "tests/t.rs",
+            r#"
+            #[test]
+            fn nothing_leaked() { assert_eq!(foo::leaked(), "-"); }
+        "#,
+        ).file(
+            "facade/Cargo.toml",
+            r#"
+            [package]
+            name = "facade"
+            version = "0.0.1"
+            authors = []
+            edition = "2018"
+
+            [dependencies]
+            spy = { path = "../spy" }
+        "#,
+        ).file("facade/src/lib.rs", "pub use spy::Spy;")
+        .file(
+            "spy/Cargo.toml",
+            r#"
+            [package]
+            name = "spy"
+            version = "0.0.1"
+            authors = []
+
+            [lib]
+            proc-macro = true
+        "#,
+        ).file(
+            "spy/src/lib.rs",
+            r#"
+            extern crate proc_macro;
+            use proc_macro::TokenStream;
+            use std::env;
+
+            #[proc_macro_derive(Spy)]
+            pub fn spy(_input: TokenStream) -> TokenStream {
+                let secret = env::var("SPY_SECRET").unwrap_or("-".to_string());
+                let cwd = env::current_dir().unwrap();
+                format!(
+                    "const SECRET: &str = {:?}; const CWD: &str = {:?};",
+                    secret,
+                    cwd.file_name().unwrap().to_str().unwrap(),
+                ).parse().unwrap()
+            }
+        "#,
+        ).build();
+
+    p.cargo("test")
+        .env("SPY_SECRET", "hunter2")
+        .with_stderr_contains("[DOCTEST] foo")
+        .run();
+}
@@ -617,6 +617,94 @@ See [..]
     assert!(!publish::upload_path().join("api/v1/crates/new").exists());
 }
 
+#[test]
+fn dry_run_show_payload() {
+    publish::setup();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            links = "foo"
+
+            [features]
+            extra = []
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file("build.rs", "fn main() {}")
+        .build();
+
+    p.cargo("publish --dry-run --no-verify --show-payload --index")
+        .arg(publish::registry().to_string())
+        .with_json(
+            r#"
+            {
+              "metadata": {
+                "name": "foo",
+                "vers": "0.0.1",
+                "deps": [],
+                "features": { "extra": [] },
+                "authors": [],
+                "description": "foo",
+                "documentation": null,
+                "homepage": null,
+                "readme": null,
+                "readme_file": null,
+                "keywords": [],
+                "categories": [],
+                "license": "MIT",
+                "license_file": null,
+                "repository": null,
+                "badges": {},
+                "links": "foo"
+              },
+              "files": [
+                { "path": "foo-0.0.1/Cargo.toml.orig", "size": "{...}" },
+                { "path": "foo-0.0.1/Cargo.toml", "size": "{...}" },
+                { "path": "foo-0.0.1/build.rs", "size": 12 },
+                { "path": "foo-0.0.1/src/main.rs", "size": 12 }
+              ]
+            }
+        "#,
+        ).with_stderr_contains("[WARNING] aborting upload due to dry run")
+        .run();
+
+    assert!(!publish::upload_path().join("api/v1/crates/new").exists());
+}
+
+#[test]
+fn show_payload_implies_dry_run() {
+    publish::setup();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("publish --no-verify --show-payload --index")
+        .arg(publish::registry().to_string())
+        .with_stdout_contains("[..]\"name\":\"foo\"[..]")
+        .with_stderr_contains("[WARNING] aborting upload due to dry run")
+        .run();
+
+    assert!(!publish::upload_path().join("api/v1/crates/new").exists());
+}
+
 #[test]
 fn block_publish_feature_not_enabled() {
     publish::setup();
@@ -786,3 +874,120 @@ fn block_publish_no_registry() {
 ",
         ).run();
 }
+
+#[test]
+fn signed_publish() {
+    publish::setup();
+
+    // A stand-in for a real signing tool: it "signs" the tarball by printing
+    // its file name and size.
+    let signer = project()
+        .at("signer")
+        .file("Cargo.toml", &basic_manifest("signer", "0.0.1"))
+        .file(
+            "src/main.rs",
+            r#"
+            use std::env;
+            use std::fs;
+            use std::path::Path;
+
+            fn main() {
+                let path = env::args().last().unwrap();
+                let len = fs::metadata(&path).unwrap().len();
+                let name = Path::new(&path).file_name().unwrap().to_str().unwrap();
+                print!("SIGNED {} {}", name, len);
+            }
+        "#,
+        ).build();
+    signer.cargo("build").run();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                [registry]
+                sign-command = '{}'
+            "#,
+                signer.bin("signer").display()
+            ),
+        ).build();
+
+    p.cargo("publish --no-verify -Z package-signing --index")
+        .arg(publish::registry().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] manifest has no documentation, [..]
+See [..]
+[PACKAGING] foo v0.0.1 ([CWD])
+[SIGNING] foo v0.0.1 ([CWD])
+[UPLOADING] foo v0.0.1 ([CWD])
+",
+        ).run();
+
+    let tarball = p.root().join("target/package/foo-0.0.1.crate");
+    let len = fs::metadata(&tarball).unwrap().len();
+    let sig = format!("SIGNED foo-0.0.1.crate {}", len);
+    assert_eq!(p.read_file("target/package/foo-0.0.1.crate.sig"), sig);
+
+    let mut f = File::open(&publish::upload_path().join("api/v1/crates/new")).unwrap();
+    let mut sz = [0; 4];
+    assert_eq!(f.read(&mut sz).unwrap(), 4);
+    let sz = (u32::from(sz[0]) << 0)
+        | (u32::from(sz[1]) << 8)
+        | (u32::from(sz[2]) << 16)
+        | (u32::from(sz[3]) << 24);
+    let mut json = vec![0; sz as usize];
+    f.read_exact(&mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["signature"], serde_json::Value::String(sig));
+
+    // Without the flag the signing command is not run, and the stale
+    // signature is removed.
+    p.cargo("package --no-verify").run();
+    assert!(!p.root().join("target/package/foo-0.0.1.crate.sig").exists());
+}
+
+#[test]
+fn signing_command_fails() {
+    publish::setup();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file(
+            ".cargo/config",
+            r#"
+            [registry]
+            sign-command = "does-not-exist"
+        "#,
+        ).build();
+
+    p.cargo("package --no-verify -Z package-signing")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to sign package `foo v0.0.1 ([CWD])`")
+        .run();
+}
@@ -1,6 +1,6 @@
 use std::fs::{self, File};
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use cargo::util::paths::remove_dir_all;
 use crate::support::cargo_process;
@@ -706,8 +706,7 @@ fn update_offline() {
         "#,
         ).file("src/main.rs", "fn main() {}")
         .build();
-    p.cargo("update -Zoffline")
-        .masquerade_as_nightly_cargo()
+    p.cargo("update --offline")
         .with_status(101)
         .with_stderr("error: you can't update in the offline mode[..]")
         .run();
@@ -1782,3 +1781,106 @@ fn rename_deps_and_features() {
     p.cargo("build --features bar/foo01").run();
     p.cargo("build --features bar/another").run();
 }
+
+#[test]
+fn max_concurrent_downloads() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            a = "0.1.0"
+            b = "0.1.0"
+            c = "0.1.0"
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", "[http]\nmax-concurrent-downloads = 1\n")
+        .build();
+
+    Package::new("a", "0.1.0").publish();
+    Package::new("b", "0.1.0").publish();
+    Package::new("c", "0.1.0").publish();
+
+    p.cargo("fetch")
+        .with_stderr_unordered(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] a v0.1.0 (registry [..])
+[DOWNLOADED] b v0.1.0 (registry [..])
+[DOWNLOADED] c v0.1.0 (registry [..])
+",
+        ).run();
+
+    p.change_file(".cargo/config", "[http]\nmax-concurrent-downloads = 0\n");
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr("[ERROR] `http.max-concurrent-downloads` must be at least 1")
+        .run();
+}
+
+#[test]
+fn read_only_home_with_overlay() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#,
+        ).file("src/main.rs", "extern crate bar; fn main() {}")
+        .build();
+
+    // Populate the home, as a base image would, but without the lock files
+    // readers need, then make it read-only.
+    p.cargo("build").run();
+    let home = paths::home().join(".cargo");
+    let index = fs::read_dir(home.join("registry/index"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let lock = index.join(".cargo-index-lock");
+    fs::remove_file(&lock).unwrap();
+    fn set_readonly(path: &Path, readonly: bool) {
+        if path.is_dir() {
+            for entry in fs::read_dir(path).unwrap() {
+                set_readonly(&entry.unwrap().path(), readonly);
+            }
+        }
+        let mut perms = fs::symlink_metadata(path).unwrap().permissions();
+        perms.set_readonly(readonly);
+        fs::set_permissions(path, perms).unwrap();
+    }
+    set_readonly(&home.join("registry"), true);
+
+    let overlay = paths::root().join("overlay");
+    p.cargo("clean").run();
+    p.cargo("build")
+        .env("CARGO_HOME_OVERLAY", &overlay)
+        .with_stderr(
+            "\
+[COMPILING] bar v0.1.0
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+    set_readonly(&home.join("registry"), false);
+
+    assert!(!lock.exists());
+    let overlay_index = overlay.join(index.strip_prefix(&home).unwrap());
+    assert!(overlay_index.join(".cargo-index-lock").is_file());
+}
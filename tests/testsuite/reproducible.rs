@@ -0,0 +1,60 @@
+use crate::support::{basic_lib_manifest, project};
+
+#[test]
+fn reproducible_build() {
+    let p = project()
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", "pub fn foo() -> &'static str { file!() }")
+        .build();
+
+    p.cargo("build --assert-reproducible")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.5.0 ([CWD])
+[FINISHED] [..]
+[REBUILDING] in `[..]` to compare the artifacts
+[COMPILING] foo v0.5.0 ([CWD])
+[FINISHED] [..]
+[REPRODUCIBLE] the [..] artifacts of both builds are identical
+",
+        )
+        .run();
+}
+
+#[test]
+fn unreproducible_build() {
+    let p = project()
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file(
+            "build.rs",
+            r#"
+            use std::env;
+            use std::fs;
+            use std::path::Path;
+            use std::time::SystemTime;
+
+            fn main() {
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap();
+                let out = Path::new(&env::var("OUT_DIR").unwrap()).join("now.rs");
+                fs::write(out, format!("pub const NOW: u128 = {};", now.as_nanos())).unwrap();
+            }
+        "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"include!(concat!(env!("OUT_DIR"), "/now.rs"));"#,
+        )
+        .build();
+
+    p.cargo("build --assert-reproducible")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] the build isn't reproducible, 1 of its [..] artifacts differ between `[..]` and `[..]`:
+  debug/deps/libfoo-[..].rlib: differs at byte [..], in member `[..]`[..]
+",
+        )
+        .run();
+}
@@ -74,6 +74,9 @@ proptest! {
                 &None,
                 false,
                 false,
+                false,
+                false,
+                false,
                 &None,
                 &["minimal-versions".to_string()],
             )
@@ -367,6 +370,9 @@ fn test_resolving_minimum_version_with_transitive_deps() {
             &None,
             false,
             false,
+            false,
+            false,
+            false,
             &None,
             &["minimal-versions".to_string()],
         )
@@ -254,8 +254,15 @@ fn bogus_default_run() {
     p.cargo("run")
         .masquerade_as_nightly_cargo()
         .with_status(101)
-        .with_stderr("error: no bin target named `b`\n\nDid you mean [..]?")
-        .run();
+        .with_stderr(
+            "\
+error: no bin target named `b`
+
+Did you mean [..]?
+
+Available binaries:
+    a",
+        ).run();
 }
 
 #[test]
@@ -441,6 +448,9 @@ automatically infer them to be a target, such as in subfolders.
 For more information on this warning you can consult
 https://github.com/rust-lang/cargo/issues/5330
 error: no example target named `a`
+
+Available examples:
+    do_magic    (required features: `magic`)
 ",
         ).run();
 }
@@ -471,8 +481,14 @@ fn run_example_autodiscover_2015_with_autoexamples_disabled() {
     let p = autodiscover_examples_project("2015", Some(false));
     p.cargo("run --example a")
         .with_status(101)
-        .with_stderr("error: no example target named `a`\n")
-        .run();
+        .with_stderr(
+            "\
+error: no example target named `a`
+
+Available examples:
+    do_magic    (required features: `magic`)
+",
+        ).run();
 }
 
 #[test]
@@ -544,8 +560,13 @@ fn run_with_filename() {
 
     p.cargo("run --bin bin.rs")
         .with_status(101)
-        .with_stderr("[ERROR] no bin target named `bin.rs`")
-        .run();
+        .with_stderr(
+            "\
+[ERROR] no bin target named `bin.rs`
+
+Available binaries:
+    a",
+        ).run();
 
     p.cargo("run --bin a.rs")
         .with_status(101)
@@ -553,13 +574,21 @@ fn run_with_filename() {
             "\
 [ERROR] no bin target named `a.rs`
 
-Did you mean `a`?",
+Did you mean `a`?
+
+Available binaries:
+    a",
         ).run();
 
     p.cargo("run --example example.rs")
         .with_status(101)
-        .with_stderr("[ERROR] no example target named `example.rs`")
-        .run();
+        .with_stderr(
+            "\
+[ERROR] no example target named `example.rs`
+
+Available examples:
+    a",
+        ).run();
 
     p.cargo("run --example a.rs")
         .with_status(101)
@@ -567,7 +596,80 @@ Did you mean `a`?",
             "\
 [ERROR] no example target named `a.rs`
 
-Did you mean `a`?",
+Did you mean `a`?
+
+Available examples:
+    a",
+        ).run();
+}
+
+#[test]
+fn list_targets_without_name() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            edition = "2018"
+
+            [features]
+            net = []
+
+            [[example]]
+            name = "client"
+            required-features = ["net"]
+        "#,
+        ).file("src/lib.rs", "")
+        .file(
+            "examples/client.rs",
+            "//! Fetches a page over HTTP.\n\nfn main() {}",
+        ).file(
+            "examples/hello.rs",
+            "#![allow(unused)]\n\n// Prints a greeting.\nfn main() {}",
+        ).file("examples/plain.rs", "fn main() {}")
+        .build();
+
+    p.cargo("run --example")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `--example` requires the name of the target to run
+
+Available examples:
+    client    Fetches a page over HTTP. (required features: `net`)
+    hello     Prints a greeting.
+    plain",
+        ).run();
+
+    p.cargo("run --bin")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `--bin` requires the name of the target to run
+
+There are no binaries available.",
+        ).run();
+}
+
+#[test]
+fn list_targets_for_unknown_name() {
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/tool.rs", "/// Not a doc comment of the file.\nfn main() {}")
+        .build();
+
+    p.cargo("run --bin something")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] no bin target named `something`
+
+Available binaries:
+    foo
+    tool",
         ).run();
 }
 
@@ -931,6 +1033,29 @@ fn library_paths_sorted_alphabetically() {
     p.cargo("run").run();
 }
 
+#[test]
+fn run_with_custom_dylib_path_env() {
+    let p = project()
+        .file(
+            ".cargo/config",
+            r#"
+            [build]
+            dylib-path-env = "CUSTOM_LIBRARY_PATH"
+        "#,
+        ).file(
+            "src/main.rs",
+            r#"
+            fn main() {
+                let search_path = std::env::var_os("CUSTOM_LIBRARY_PATH").unwrap();
+                let paths = std::env::split_paths(&search_path).collect::<Vec<_>>();
+                assert!(paths.iter().any(|p| p.ends_with("target/debug/deps")));
+            }
+        "#,
+        ).build();
+
+    p.cargo("run").run();
+}
+
 #[test]
 fn fail_no_extra_verbose() {
     let p = project()
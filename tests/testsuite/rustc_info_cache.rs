@@ -1,4 +1,6 @@
 use std::env;
+use std::fs;
+use crate::support::install::cargo_home;
 use crate::support::paths::CargoPathExt;
 use crate::support::{basic_manifest, project};
 
@@ -99,3 +101,66 @@ fn rustc_info_cache() {
         .with_stderr_does_not_contain(update)
         .run();
 }
+
+#[test]
+fn rustc_info_cache_with_rustup_proxy() {
+    let p = project()
+        .file("src/main.rs", r#"fn main() { println!("hello"); }"#)
+        .build();
+
+    // A compiler in `$CARGO_HOME/bin` looks like a rustup proxy, which can't
+    // be fingerprinted by its mtime without rustup's environment variables.
+    // It must still find rustup's home to run the compiler though.
+    let proxy = {
+        let p = project()
+            .at("compiler")
+            .file("Cargo.toml", &basic_manifest("compiler", "0.1.0"))
+            .file(
+                "src/main.rs",
+                r#"
+            use std::process::Command;
+            use std::env;
+
+            fn main() {
+                let mut cmd = Command::new("rustc");
+                for arg in env::args_os().skip(1) {
+                    cmd.arg(arg);
+                }
+                std::process::exit(cmd.status().unwrap().code().unwrap());
+            }
+        "#,
+            ).build();
+        p.cargo("build").run();
+
+        let dst = cargo_home()
+            .join("bin/rustc")
+            .with_extension(env::consts::EXE_EXTENSION);
+        fs::create_dir_all(dst.parent().unwrap()).unwrap();
+        fs::copy(
+            p.root()
+                .join("target/debug/compiler")
+                .with_extension(env::consts::EXE_EXTENSION),
+            &dst,
+        ).unwrap();
+        dst
+    };
+
+    p.cargo("build")
+        .env("RUST_LOG", "cargo::util::rustc=info")
+        .env("RUSTC", proxy.display().to_string())
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .with_stderr_contains("[..]fingerprinting rustc by its version[..]")
+        .with_stderr_contains("[..]rustc info cache miss[..]")
+        .with_stderr_contains("[..]updated rustc info cache[..]")
+        .run();
+
+    p.cargo("build")
+        .env("RUST_LOG", "cargo::util::rustc=info")
+        .env("RUSTC", proxy.display().to_string())
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .with_stderr_contains("[..]reusing existing rustc info cache[..]")
+        .with_stderr_contains("[..]rustc info cache hit[..]")
+        .with_stderr_does_not_contain("[..]rustc info cache miss[..]")
+        .with_stderr_does_not_contain("[..]updated rustc info cache[..]")
+        .run();
+}
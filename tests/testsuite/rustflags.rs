@@ -1175,3 +1175,146 @@ fn two_matching_in_config() {
     p1.cargo("run").run();
     p1.cargo("build").with_stderr("[FINISHED] [..]").run();
 }
+
+#[test]
+fn target_applies_to_host_false() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            build = "build.rs"
+        "#,
+        ).file(
+            ".cargo/config",
+            r#"
+            target-applies-to-host = false
+
+            [build]
+            rustflags = ["--cfg", "foo"]
+
+            [host]
+            rustflags = ["--cfg", "bar"]
+        "#,
+        ).file(
+            "build.rs",
+            r#"
+            #[cfg(foo)]
+            compile_error!("target rustflags applied to build script");
+            #[cfg(not(bar))]
+            compile_error!("host rustflags not applied to build script");
+            fn main() {}
+        "#,
+        ).file(
+            "src/lib.rs",
+            r#"
+            #[cfg(not(foo))]
+            compile_error!("target rustflags not applied");
+            #[cfg(bar)]
+            compile_error!("host rustflags applied to target");
+        "#,
+        ).build();
+
+    // Without the flag the setting and the `[host]` table are ignored.
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("[..]host rustflags not applied to build script[..]")
+        .run();
+
+    p.cargo("build -Ztarget-applies-to-host")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.cargo("build -Ztarget-applies-to-host --target")
+        .arg(rustc_host())
+        .masquerade_as_nightly_cargo()
+        .run();
+}
+
+#[test]
+fn target_applies_to_host_false_host_linker() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file(
+            ".cargo/config",
+            r#"
+            target-applies-to-host = false
+
+            [host]
+            linker = "nonexistent-linker"
+        "#,
+        ).file("build.rs", "fn main() {}")
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -v -Ztarget-applies-to-host")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name build_script_build build.rs [..]-C linker=nonexistent-linker [..]`",
+        ).run();
+}
+
+#[test]
+fn rustflags_conflicting_with_probe() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build")
+        .env("RUSTFLAGS", "--cfg foo --crate-type lib")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the rustflags can't contain `--crate-type lib`: cargo passes the crate types of \
+each target itself, set them with `crate-type` in the manifest
+",
+        ).run();
+
+    // They're refused when they aren't used to probe as well.
+    p.change_file(".cargo/config", "[build]\nprobe-rustflags = false");
+    p.cargo("build")
+        .env("RUSTFLAGS", "--codegen extra-filename=-x")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the rustflags can't contain `--codegen extra-filename=-x`: cargo sets it itself to \
+tell the artifacts of each crate apart
+",
+        ).run();
+}
+
+#[test]
+fn rustflags_failing_probe_are_named() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build")
+        .env("RUSTFLAGS", "--cfg foo -C bogus=1")
+        .with_status(101)
+        .with_stderr_contains(
+            "  rustc [..] fails with `-C bogus=1` of the rustflags, which cargo also passes to \
+             it to learn about the target unless `build.probe-rustflags` is false",
+        ).run();
+}
+
+#[test]
+fn probe_rustflags_false() {
+    let p = project()
+        .file(
+            ".cargo/config",
+            r#"
+            [build]
+            probe-rustflags = false
+        "#,
+        ).file(
+            "build.rs",
+            r#"
+            fn main() {
+                assert!(std::env::var("CARGO_CFG_FOO").is_err());
+            }
+        "#,
+        ).file("src/lib.rs", "#[cfg(not(foo))] compile_error!(\"foo\");")
+        .build();
+
+    p.cargo("build").env("RUSTFLAGS", "--cfg foo").run();
+}
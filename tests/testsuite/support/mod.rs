@@ -1400,20 +1400,39 @@ fn substitute_macros(input: &str) -> String {
         ("[DOCUMENTING]", " Documenting"),
         ("[FRESH]", "       Fresh"),
         ("[UPDATING]", "    Updating"),
+        ("[PINNING]", "     Pinning"),
+        ("[UNPINNING]", "   Unpinning"),
         ("[ADDING]", "      Adding"),
         ("[REMOVING]", "    Removing"),
         ("[DOCTEST]", "   Doc-tests"),
         ("[PACKAGING]", "   Packaging"),
         ("[DOWNLOADING]", " Downloading"),
         ("[DOWNLOADED]", "  Downloaded"),
+        ("[COMPARING]", "   Comparing"),
         ("[UPLOADING]", "   Uploading"),
+        ("[SIGNING]", "     Signing"),
         ("[VERIFYING]", "   Verifying"),
+        ("[VERIFIED]", "    Verified"),
+        ("[FETCHING]", "    Fetching"),
         ("[ARCHIVING]", "   Archiving"),
         ("[INSTALLING]", "  Installing"),
         ("[REPLACING]", "   Replacing"),
         ("[UNPACKING]", "   Unpacking"),
         ("[SUMMARY]", "     Summary"),
         ("[FIXING]", "      Fixing"),
+        ("[MIGRATING]", "   Migrating"),
+        ("[SHUFFLING]", "   Shuffling"),
+        ("[DETECTED]", "    Detected"),
+        ("[SKIPPED]", "     Skipped"),
+        ("[LISTED]", "      Listed"),
+        ("[RESOLVER]", "    Resolver"),
+        ("[EPHEMERAL]", "   Ephemeral"),
+        ("[UPDATED]", "     Updated"),
+        ("[REMOVED]", "     Removed"),
+        ("[PRUNED]", "      Pruned"),
+        ("[PROFILED]", "    Profiled"),
+        ("[REBUILDING]", "  Rebuilding"),
+        ("[REPRODUCIBLE]", "Reproducible"),
         ("[EXE]", if cfg!(windows) { ".exe" } else { "" }),
     ];
     let mut result = input.to_owned();
@@ -119,6 +119,7 @@ pub struct Package {
     extra_files: Vec<(String, String)>,
     yanked: bool,
     features: HashMap<String, Vec<String>>,
+    index_fields: Vec<(String, serde_json::Value)>,
     local: bool,
     alternative: bool,
 }
@@ -203,6 +204,7 @@ impl Package {
             extra_files: Vec::new(),
             yanked: false,
             features: HashMap::new(),
+            index_fields: Vec::new(),
             local: false,
             alternative: false,
         }
@@ -316,6 +318,13 @@ impl Package {
         self
     }
 
+    /// Add a field cargo doesn't know about to the line of the package in
+    /// the index.
+    pub fn index_field(&mut self, name: &str, value: serde_json::Value) -> &mut Package {
+        self.index_fields.push((name.to_string(), value));
+        self
+    }
+
     /// Create the package and place it in the registry.
     ///
     /// This does not actually use Cargo's publishing system, but instead
@@ -347,14 +356,18 @@ impl Package {
             t!(t!(File::open(&self.archive_dst())).read_to_end(&mut c));
             cksum(&c)
         };
-        let line = json!({
+        let mut line = json!({
             "name": self.name,
             "vers": self.vers,
             "deps": deps,
             "cksum": cksum,
             "features": self.features,
             "yanked": self.yanked,
-        }).to_string();
+        });
+        for &(ref name, ref value) in self.index_fields.iter() {
+            line[name] = value.clone();
+        }
+        let line = line.to_string();
 
         let file = match self.name.len() {
             1 => format!("1/{}", self.name),
@@ -0,0 +1,179 @@
+use crate::support::project;
+
+#[test]
+fn system_dependencies_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [package.system-dependencies]
+            openssl = ">= 1.0.2"
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  feature `system-dependencies` is required
+
+consider adding `cargo-features = [\"system-dependencies\"]` to the manifest
+",
+        ).run();
+}
+
+#[test]
+fn system_dependency_bad_version() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["system-dependencies"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [package.system-dependencies]
+            zlib = { version = "~> 1.2", libs = ["z"] }
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  failed to parse the version of system dependency `zlib`
+
+Caused by:
+  invalid comparison `~>` in `~> 1.2`
+",
+        ).run();
+}
+
+#[test]
+fn system_dependencies_in_manifest_output() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["system-dependencies"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [package.system-dependencies]
+            openssl = "1.0.2"
+            zlib = { pkg-config = "libz", version = "*", libs = ["z"] }
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("read-manifest")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+    {
+        "authors": [],
+        "categories": [],
+        "name": "foo",
+        "readme": null,
+        "repository": null,
+        "version": "0.0.1",
+        "id": "foo[..]",
+        "keywords": [],
+        "license": null,
+        "license_file": null,
+        "description": null,
+        "edition": "2015",
+        "source": null,
+        "dependencies": [],
+        "targets": "{...}",
+        "features": {},
+        "manifest_path": "[..]Cargo.toml",
+        "metadata": null,
+        "system_dependencies": {
+            "openssl": {
+                "version": ">= 1.0.2",
+                "pkg_config": "openssl",
+                "libs": []
+            },
+            "zlib": {
+                "version": null,
+                "pkg_config": "libz",
+                "libs": ["z"]
+            }
+        }
+    }"#,
+        ).run();
+}
+
+#[cfg(unix)]
+#[test]
+fn missing_system_dependency() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["system-dependencies"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [package.system-dependencies]
+            openssl = ">= 1.1"
+            zlib = "*"
+        "#,
+        ).file("src/lib.rs", "")
+        // Knows zlib, and version 1.0.2 of openssl.
+        .file(
+            "pkg-config",
+            r#"#!/bin/sh
+            case "$1 $2" in
+                "--version ") echo 0.29 ;;
+                "--exists zlib") exit 0 ;;
+                "--modversion openssl") echo 1.0.2 ;;
+                *) exit 1 ;;
+            esac
+        "#,
+        ).build();
+    let pkg_config = p.root().join("pkg-config");
+    let mut perms = fs::metadata(&pkg_config).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&pkg_config, perms).unwrap();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .env("PKG_CONFIG", &pkg_config)
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] missing system dependencies:
+  `openssl >= 1.1`, required by `foo v0.0.1 ([..])`, but version 1.0.2 is installed
+
+Install the development files of these libraries, like the `-dev` or `-devel` \
+packages of a distribution, or add the directories of their `.pc` files to \
+`PKG_CONFIG_PATH`.
+",
+        ).run();
+}
@@ -0,0 +1,272 @@
+use crate::support::registry::Package;
+use crate::support::{basic_manifest, project};
+
+#[test]
+fn quiet_categories() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            unused = true
+
+            [dependencies]
+            bar = "0.1.0"
+            "#,
+        ).file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+            [term.categories]
+            network = "quiet"
+            warnings = "quiet"
+            "#,
+        ).build();
+
+    p.cargo("build -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.1.0
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+
+    // Without the flag the configuration is ignored.
+    p.cargo("build")
+        .with_stderr_contains("[WARNING] unused manifest key: package.unused")
+        .run();
+}
+
+#[test]
+fn category_overrides_quiet_flag() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+            [term.categories]
+            compile = "normal"
+            "#,
+        ).build();
+
+    p.cargo("build -q -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+}
+
+#[test]
+fn translated_messages() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+            [term]
+            messages = "messages.toml"
+            "#,
+        ).file(
+            "messages.toml",
+            r#"
+            Compiling = "Kompiliere"
+            Finished = "Fertig"
+            error = "Fehler"
+            "#,
+        ).build();
+
+    p.cargo("build -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "  Kompiliere foo v0.0.1 ([CWD])\n      \
+             Fertig dev [unoptimized + debuginfo] target(s) in [..]\n",
+        ).run();
+
+    p.cargo("build -p bar -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("Fehler: package id specification `bar` matched no packages")
+        .run();
+}
+
+#[test]
+fn unknown_category() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+            [term.categories]
+            linking = "quiet"
+            "#,
+        ).build();
+
+    p.cargo("build -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] unknown output category `linking`, expected `network`, `compile` or \
+`warnings` (found in [..]/foo/.cargo/config)
+",
+        ).run();
+}
+
+#[test]
+fn invalid_level() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+            [term.categories]
+            network = "verbose"
+            "#,
+        ).build();
+
+    p.cargo("build -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `term.categories.network` must be `quiet` or `normal`, found `verbose` \
+(found in [..]/foo/.cargo/config)
+",
+        ).run();
+}
+
+#[test]
+fn hyperlinks() {
+    let p = project()
+        .file("src/lib.rs", "pub fn f() -> u8 { \"\" }")
+        .file(
+            ".cargo/config",
+            r#"
+            [term]
+            hyperlinks = true
+            "#,
+        ).build();
+
+    p.cargo("build -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[COMPILING] \x1B]8;;file://[..]/foo\x1B\\foo v0.0.1 ([CWD])\x1B]8;;\x1B\\",
+        ).with_stderr_contains(
+            " --> \x1B]8;;file://[..]/foo/src/lib.rs\x1B\\src/lib.rs:1:20\x1B]8;;\x1B\\",
+        ).run();
+
+    // Without the flag paths are printed as usual.
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("[COMPILING] foo v0.0.1 ([CWD])")
+        .with_stderr_contains(" --> src/lib.rs:1:20")
+        .run();
+}
+
+#[test]
+fn stdout_color_is_passed_to_test_harness() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("tests/t.rs", "")
+        .file("tests/custom.rs", "fn main() {}")
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [[test]]
+            name = "t"
+
+            [[test]]
+            name = "custom"
+            harness = false
+            "#,
+        ).file(
+            ".cargo/config",
+            r#"
+            [term]
+            stdout-color = "never"
+            "#,
+        ).build();
+
+    p.cargo("test -v --lib --test t --test custom -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `[..]/deps/foo-[..] --color never`")
+        .with_stderr_contains("[RUNNING] `[..]/deps/t-[..] --color never`")
+        .with_stderr_does_not_contain("[RUNNING] `[..]/deps/custom-[..] --color never`")
+        .run();
+
+    // An explicit argument to the harness takes precedence.
+    p.cargo("test -v --lib -Z term-output -- --color always")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `[..]/deps/foo-[..] --color always`")
+        .run();
+}
+
+#[test]
+fn stderr_color() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+            [term]
+            color = "never"
+            stderr-color = "always"
+            "#,
+        ).build();
+
+    p.cargo("build -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("\x1B[[..]Compiling\x1B[..] foo v0.0.1 ([CWD])")
+        .run();
+
+    // `--color` takes precedence over the configuration.
+    p.cargo("clean").run();
+    p.cargo("build --color never -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        ).run();
+}
+
+#[test]
+fn invalid_stream_color() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+            [term]
+            stdout-color = "sometimes"
+            "#,
+        ).build();
+
+    p.cargo("build -Z term-output")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `term.stdout-color` must be `auto`, `always` or `never`, found `sometimes` \
+(found in [..]/foo/.cargo/config)
+",
+        ).run();
+}
@@ -1761,12 +1761,20 @@ fn bad_example() {
 
     p.cargo("run --example foo")
         .with_status(101)
-        .with_stderr("[ERROR] no example target named `foo`")
-        .run();
+        .with_stderr(
+            "\
+[ERROR] no example target named `foo`
+
+There are no examples available.",
+        ).run();
     p.cargo("run --bin foo")
         .with_status(101)
-        .with_stderr("[ERROR] no bin target named `foo`")
-        .run();
+        .with_stderr(
+            "\
+[ERROR] no bin target named `foo`
+
+There are no binaries available.",
+        ).run();
 }
 
 #[test]
@@ -2995,6 +3003,26 @@ fn json_artifact_includes_test_flag() {
     p.cargo("test --lib -v --message-format=json")
         .with_json(
             r#"
+    {
+        "reason": "target-info",
+        "kind": "host",
+        "triple": "[..]",
+        "cfg": "{...}",
+        "linker": "[..]",
+        "ar": "[..]",
+        "llvm_tools_dir": "{...}"
+    }
+
+    {
+        "reason": "target-info",
+        "kind": "target",
+        "triple": "[..]",
+        "cfg": "{...}",
+        "linker": "[..]",
+        "ar": "[..]",
+        "llvm_tools_dir": "{...}"
+    }
+
     {
         "reason":"compiler-artifact",
         "profile": {
@@ -3030,6 +3058,26 @@ fn json_artifact_includes_executable_for_library_tests() {
 
     p.cargo("test --lib -v --no-run --message-format=json")
         .with_json(r#"
+            {
+                "reason": "target-info",
+                "kind": "host",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
+            {
+                "reason": "target-info",
+                "kind": "target",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
             {
                 "executable": "[..]/foo/target/debug/foo-[..][EXE]",
                 "features": [],
@@ -3058,6 +3106,26 @@ fn json_artifact_includes_executable_for_integration_tests() {
 
     p.cargo("test -v --no-run --message-format=json --test integration_test")
         .with_json(r#"
+            {
+                "reason": "target-info",
+                "kind": "host",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
+            {
+                "reason": "target-info",
+                "kind": "target",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
             {
                 "executable": "[..]/foo/target/debug/integration_test-[..][EXE]",
                 "features": [],
@@ -3258,3 +3326,249 @@ fn test_dep_with_dev() {
         )
         .run();
 }
+
+#[test]
+fn no_run_run_manifest() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test --no-run --run-manifest")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `--run-manifest` flag is unstable, pass `-Z unstable-options` to enable it",
+        ).run();
+
+    p.cargo("test --no-run --run-manifest -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("foo v0.0.1 ([CWD]) lib `foo`")
+        .with_stdout_contains("  executable: [CWD]/target/debug/deps/foo-[..][EXE]")
+        .with_stdout_contains("  cwd: [CWD]")
+        .with_stdout_contains("  env: CARGO_MANIFEST_DIR=[CWD]")
+        .with_stdout_contains("  env: CARGO_PKG_NAME=foo")
+        .run();
+
+    p.cargo("test --no-run --run-manifest -Zunstable-options --message-format=json")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+            {
+                "reason": "target-info",
+                "kind": "host",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
+            {
+                "reason": "target-info",
+                "kind": "target",
+                "triple": "[..]",
+                "cfg": "{...}",
+                "linker": "[..]",
+                "ar": "[..]",
+                "llvm_tools_dir": "{...}"
+            }
+
+            {
+                "reason": "compiler-artifact",
+                "package_id": "foo 0.0.1 ([..])",
+                "target": "{...}",
+                "profile": "{...}",
+                "features": [],
+                "filenames": "{...}",
+                "executable": null,
+                "fresh": true
+            }
+
+            {
+                "reason": "compiler-artifact",
+                "package_id": "foo 0.0.1 ([..])",
+                "target": "{...}",
+                "profile": "{...}",
+                "features": [],
+                "filenames": "{...}",
+                "executable": "[..]/foo-[..]",
+                "fresh": true
+            }
+
+            {
+                "reason": "test-executable",
+                "package_id": "foo 0.0.1 ([..])",
+                "target": {
+                    "kind": ["lib"],
+                    "crate_types": ["lib"],
+                    "edition": "2015",
+                    "name": "foo",
+                    "src_path": "[..]/foo/src/lib.rs"
+                },
+                "mode": "test",
+                "executable": "[..]/foo-[..]",
+                "program": "[..]/foo-[..]",
+                "args": [],
+                "cwd": "[..]/foo",
+                "env": "{...}"
+            }
+        "#,
+        ).run();
+}
+
+#[test]
+fn test_metadata_cwd_and_env() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [package.metadata.test]
+            cwd = "tests/fixtures"
+            env = { FIXTURE_NAME = "data.txt" }
+        "#,
+        ).file(
+            "tests/t.rs",
+            r#"
+            #[test]
+            fn fixture() {
+                let name = std::env::var("FIXTURE_NAME").unwrap();
+                assert_eq!(std::fs::read_to_string(name).unwrap(), "hello");
+            }
+        "#,
+        ).file("tests/fixtures/data.txt", "hello")
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test --test t")
+        .with_status(101)
+        .with_stdout_contains("test fixture ... FAILED")
+        .run();
+
+    p.cargo("test --test t -Ztest-metadata")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("test fixture ... ok")
+        .run();
+}
+
+#[test]
+fn test_metadata_invalid() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [package.metadata.test]
+            cwd = 1
+        "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test -Ztest-metadata")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] `package.metadata.test.cwd` must be a string in `[CWD]/Cargo.toml`",
+        ).run();
+}
+
+#[test]
+fn shuffle_test_binaries() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", "")
+        .file("tests/b.rs", "")
+        .file("tests/c.rs", "")
+        .file(
+            "tests/seed.rs",
+            r#"
+            #[test]
+            fn seed() {
+                assert_eq!(std::env::var("CARGO_TEST_SHUFFLE_SEED").unwrap(), "42");
+            }
+        "#,
+        ).build();
+
+    p.cargo("test --shuffle")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `--shuffle` flag is unstable, pass `-Z unstable-options` to enable it",
+        ).run();
+
+    p.cargo("test --shuffle=42 -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[SHUFFLING] test binaries with seed 42
+[RUNNING] target/debug/deps/a-[..][EXE]
+[RUNNING] target/debug/deps/b-[..][EXE]
+[RUNNING] target/debug/deps/foo-[..][EXE]
+[RUNNING] target/debug/deps/seed-[..][EXE]
+[RUNNING] target/debug/deps/c-[..][EXE]
+[DOCTEST] foo",
+        ).run();
+
+    p.cargo("test --shuffle -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[SHUFFLING] test binaries with seed [..]")
+        .run();
+}
+
+#[test]
+fn test_tmpdir_and_env_scrub() {
+    let p = project()
+        .file(
+            ".cargo/config",
+            r#"
+            [test]
+            env-scrub = ["TEST_SECRET"]
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+            /// ```
+            /// let dir = std::env::var("CARGO_TEST_TMPDIR").unwrap();
+            /// assert!(dir.contains("foo-doc-foo-"));
+            /// ```
+            pub fn f() {}
+
+            #[test]
+            fn tmpdir() {
+                let dir = std::env::var("CARGO_TEST_TMPDIR").unwrap();
+                assert!(dir.contains("foo-foo-"));
+                assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+                std::fs::write(std::path::Path::new(&dir).join("file"), "").unwrap();
+                assert!(std::env::var("TEST_SECRET").is_err());
+            }
+            "#,
+        )
+        .file(
+            "tests/other.rs",
+            r#"
+            #[test]
+            fn tmpdir() {
+                let dir = std::env::var("CARGO_TEST_TMPDIR").unwrap();
+                assert!(dir.contains("foo-other-"));
+                assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+                std::fs::write(std::path::Path::new(&dir).join("file"), "").unwrap();
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("test").env("TEST_SECRET", "hunter2").run();
+    assert_eq!(p.root().join("target/tmp").read_dir().unwrap().count(), 0);
+}
@@ -395,3 +395,106 @@ fn preserve_top_comment() {
 
     assert!(lockfile == lockfile2);
 }
+
+#[test]
+fn pin_holds_back_transitive_update() {
+    Package::new("log", "0.1.0").publish();
+    Package::new("log", "0.1.1").publish();
+    Package::new("serde", "0.1.0").dep("log", "0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                serde = "0.1"
+            "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("update --pin log@0.1.0")
+        .with_stderr(
+            "\
+[PINNING] log to v0.1.0
+[UPDATING] log v0.1.1 -> v0.1.0
+",
+        ).run();
+    let lockfile = p.read_file("Cargo.lock");
+    assert!(lockfile.contains("[pin]\nlog = \"0.1.0\"\n"));
+
+    Package::new("log", "0.1.2").publish();
+    p.cargo("update")
+        .with_stderr("[UPDATING] `[..]` index")
+        .run();
+
+    p.cargo("update --unpin log")
+        .with_stderr(
+            "\
+[UNPINNING] log
+",
+        ).run();
+    assert!(!p.read_file("Cargo.lock").contains("[pin]"));
+
+    p.cargo("update")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] log v0.1.0 -> v0.1.2
+",
+        ).run();
+}
+
+#[test]
+fn generate_lockfile_keeps_pins() {
+    Package::new("log", "0.1.0").publish();
+    Package::new("log", "0.1.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                log = "0.1"
+            "#,
+        ).file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    p.cargo("update --pin log@0.1.0").run();
+
+    p.cargo("generate-lockfile").run();
+    let lockfile = p.read_file("Cargo.lock");
+    assert!(lockfile.contains("[pin]\nlog = \"0.1.0\"\n"));
+    assert!(lockfile.contains("name = \"log\"\nversion = \"0.1.0\""));
+}
+
+#[test]
+fn pin_requires_exact_version() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("update --pin log@0.1")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid pin `log@0.1`, `--pin` takes a package and an exact version, \
+             like `foo@1.4.2`",
+        ).run();
+
+    p.cargo("update --pin log@0.1.0 -p log")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `--pin` and `--unpin` can't be combined with `--package`, \
+             `--precise` or `--aggressive`",
+        ).run();
+}
@@ -0,0 +1,123 @@
+use git2;
+
+use crate::support::git;
+use crate::support::registry::Package;
+use crate::support::{cargo_process, path2url, paths};
+
+fn manifest(vers: &str) -> String {
+    format!(
+        r#"
+        [package]
+        name = "foo"
+        version = "{}"
+        authors = []
+        repository = "{}"
+    "#,
+        vers,
+        path2url(paths::root().join("upstream"))
+    )
+}
+
+fn upstream(tag: &str) {
+    let repo = git::repo(&paths::root().join("upstream"))
+        .file("Cargo.toml", &manifest("0.1.0"))
+        .file("src/lib.rs", "pub fn foo() {}\n")
+        .build();
+    let repo = git2::Repository::open(repo.root()).unwrap();
+    git::tag(&repo, tag);
+}
+
+#[test]
+fn matches_repository() {
+    upstream("v0.1.0");
+    Package::new("foo", "0.1.0")
+        .file("Cargo.toml", &manifest("0.1.0"))
+        .file("Cargo.toml.orig", &manifest("0.1.0"))
+        .file("src/lib.rs", "pub fn foo() {}\n")
+        .publish();
+
+    cargo_process("verify-crate foo@0.1.0 --against-repo")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] foo v0.1.0 (registry [..])
+[FETCHING] `file://[..]/upstream`
+[VERIFIED] foo v0.1.0 against `file://[..]/upstream` at tag `v0.1.0`
+",
+        ).run();
+}
+
+#[test]
+fn reports_tampered_files() {
+    upstream("foo-0.1.1");
+    Package::new("foo", "0.1.1")
+        .file("Cargo.toml", &manifest("0.1.1"))
+        .file("Cargo.toml.orig", &manifest("0.1.0"))
+        .file("src/lib.rs", "pub fn foo() { steal_secrets() }\n")
+        .file("src/extra.rs", "")
+        .publish();
+
+    cargo_process("verify-crate foo@0.1.1 --against-repo")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] `foo v0.1.1` doesn't match `file://[..]/upstream` at tag `foo-0.1.1`:
+  only in the package: src/extra.rs
+  differs: src/lib.rs
+",
+        ).run();
+}
+
+#[test]
+fn reports_files_the_manifest_hides() {
+    upstream("v0.1.2");
+    let hiding = format!("{}exclude = [\"src/evil.rs\"]\n", manifest("0.1.2"));
+    Package::new("foo", "0.1.2")
+        .file("Cargo.toml", &hiding)
+        .file("Cargo.toml.orig", &manifest("0.1.0"))
+        .file("src/lib.rs", "pub fn foo() {}\n")
+        .file("src/evil.rs", "")
+        .file(".cargo/config", "")
+        .publish();
+
+    cargo_process("verify-crate foo@0.1.2 --against-repo")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] `foo v0.1.2` doesn't match `file://[..]/upstream` at tag `v0.1.2`:
+  only in the package: .cargo/config
+  only in the package: src/evil.rs
+",
+        ).run();
+}
+
+#[test]
+fn checksum_only() {
+    Package::new("foo", "0.1.0").publish();
+
+    cargo_process("verify-crate foo@0.1.0")
+        .with_stderr_contains("[VERIFIED] foo v0.1.0 (checksum)")
+        .run();
+}
+
+#[test]
+fn requires_repository() {
+    Package::new("foo", "0.1.0").publish();
+
+    cargo_process("verify-crate foo@0.1.0 --against-repo")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] `foo v0.1.0` doesn't declare a `repository` to verify it against",
+        ).run();
+}
+
+#[test]
+fn requires_exact_version() {
+    cargo_process("verify-crate foo")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `foo` doesn't have a version, `cargo verify-crate` requires an exact \
+             version, like `foo@1.2.3`",
+        ).run();
+}
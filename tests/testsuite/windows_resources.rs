@@ -0,0 +1,73 @@
+use crate::support::{project, rustc_host};
+
+#[test]
+fn windows_resources_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [package.windows-resources]
+            icon = "app.ico"
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+error: failed to parse manifest at `[..]`
+
+Caused by:
+  feature `windows-resources` is required
+
+consider adding `cargo-features = [\"windows-resources\"]` to the manifest
+",
+        ).run();
+}
+
+#[test]
+fn windows_resources_compiled() {
+    if !cfg!(windows) {
+        return;
+    }
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["windows-resources"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [package.windows-resources]
+            manifest = "app.manifest"
+        "#,
+        ).file("src/main.rs", "fn main() {}")
+        .file(
+            "app.manifest",
+            r#"<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0"/>"#,
+        ).file(
+            ".cargo/config",
+            &format!(
+                r#"
+                [target.{}]
+                resource-compiler = "fake-rc"
+            "#,
+                rustc_host()
+            ),
+        ).build();
+
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[RUNNING] `fake-rc [..]foo-resources.rc[..]`")
+        .with_stderr_contains("[..]failed to compile the Windows resources of `foo`")
+        .run();
+}
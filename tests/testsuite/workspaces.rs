@@ -1998,3 +1998,96 @@ fn ws_warn_path() {
         .with_stderr_contains("[WARNING] [..]/foo/a/Cargo.toml: the cargo feature `edition`[..]")
         .run();
 }
+
+#[test]
+fn partial_workspace_only_loads_selected_members() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["a", "b", "c"]
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+
+            [dependencies]
+            b = { path = "../b" }
+            "#,
+        )
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .file("c/Cargo.toml", &basic_manifest("c", "0.1.0"))
+        .file("c/src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    // `c` can't be loaded anymore, but isn't needed to check `a`.
+    p.change_file("c/Cargo.toml", &basic_manifest("c", "not-a-version"));
+
+    p.cargo("check -p a -Z partial-workspace")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] b v0.1.0 ([CWD]/b)
+[CHECKING] a v0.1.0 ([CWD]/a)
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+    p.cargo("check -p a")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to parse manifest at `[CWD]/c/Cargo.toml`")
+        .run();
+}
+
+#[test]
+fn partial_workspace_does_not_update_lockfile() {
+    Package::new("dep", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    let lockfile = p.read_lockfile();
+    p.change_file(
+        "a/Cargo.toml",
+        r#"
+        [package]
+        name = "a"
+        version = "0.1.0"
+
+        [dependencies]
+        dep = "0.1"
+        "#,
+    );
+
+    p.cargo("check -p a -Z partial-workspace")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] the lock file [CWD]/Cargo.lock needs to be updated, which \
+`-Z partial-workspace` doesn't do as it only loads some of the workspace's members
+run the command without it once to update the lock file
+",
+        )
+        .run();
+    assert_eq!(p.read_lockfile(), lockfile);
+}